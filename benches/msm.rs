@@ -0,0 +1,87 @@
+use ark_bls12_381::{Bls12_381, Fr as BlsFr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::ProjectiveCurve;
+use ark_ff::PrimeField;
+use ark_groth16::{create_proof, Groth16};
+use ark_snark::SNARK;
+use ark_std::UniformRand;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use gadgets::msm::{create_proof_with_backend, CpuMsm, MsmBackend};
+use gadgets::synthetic::SyntheticCircuit;
+
+// MSMs on their own, in G1 and G2, and whole Groth16 proofs of synthetic circuits of 2^12
+// and 2^14 constraints through `msm::create_proof_with_backend` next to ark-groth16's
+// `create_proof`. With `--features msm-hook` the proofs also run through `HookMsm` with a
+// hook that does the MSM on the CPU, which prices the byte copies at the hook boundary
+// that a device has to win back.
+
+fn bench_msm(c: &mut Criterion) {
+    let rng = &mut ark_std::test_rng();
+    let mut group = c.benchmark_group("msm");
+    group.sample_size(10);
+    for &log_size in [10, 12, 14].iter() {
+        let size = 1 << log_size;
+        let scalars: Vec<_> = (0..size).map(|_| BlsFr::rand(rng).into_repr()).collect();
+        let g1: Vec<G1Projective> = (0..size).map(|_| G1Projective::rand(rng)).collect();
+        let g1: Vec<G1Affine> = G1Projective::batch_normalization_into_affine(&g1);
+        let g2: Vec<G2Projective> = (0..size).map(|_| G2Projective::rand(rng)).collect();
+        let g2: Vec<G2Affine> = G2Projective::batch_normalization_into_affine(&g2);
+        group.bench_with_input(BenchmarkId::new("g1", size), &size, |b, _| {
+            b.iter(|| CpuMsm.msm(&g1, &scalars))
+        });
+        group.bench_with_input(BenchmarkId::new("g2", size), &size, |b, _| {
+            b.iter(|| CpuMsm.msm(&g2, &scalars))
+        });
+    }
+    group.finish();
+}
+
+#[cfg(feature = "msm-hook")]
+fn cpu_hook(group: &str, count: usize, bases: &[u8], scalars: &[u8]) -> Option<Vec<u8>> {
+    use ark_ff::BigInteger256;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+    if group != std::any::type_name::<G1Affine>() {
+        return None;
+    }
+    let bases: Vec<G1Affine> = bases
+        .chunks(bases.len() / count)
+        .map(|b| G1Affine::deserialize_unchecked(b).unwrap())
+        .collect();
+    let scalars: Vec<BigInteger256> = scalars
+        .chunks(32)
+        .map(|s| BigInteger256::deserialize(s).unwrap())
+        .collect();
+    let mut sum = Vec::new();
+    let result = CpuMsm.msm(&bases, &scalars).into_affine();
+    result.serialize_uncompressed(&mut sum).unwrap();
+    Some(sum)
+}
+
+fn bench_prove(c: &mut Criterion) {
+    let rng = &mut ark_std::test_rng();
+    let mut group = c.benchmark_group("groth16_prove_msm");
+    group.sample_size(10);
+    for &log_size in [12, 14].iter() {
+        let circuit = SyntheticCircuit::of_log_size(log_size);
+        let (pk, _) = Groth16::<Bls12_381>::circuit_specific_setup(circuit, rng).unwrap();
+        let (r, s) = (BlsFr::rand(rng), BlsFr::rand(rng));
+        let size = 1 << log_size;
+        group.bench_with_input(BenchmarkId::new("ark_groth16", size), &size, |b, _| {
+            b.iter(|| create_proof(circuit, &pk, r, s).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("cpu", size), &size, |b, _| {
+            b.iter(|| create_proof_with_backend(circuit, &pk, r, s, &CpuMsm).unwrap())
+        });
+        #[cfg(feature = "msm-hook")]
+        {
+            let backend = gadgets::msm::HookMsm::new(cpu_hook);
+            group.bench_with_input(BenchmarkId::new("hook", size), &size, |b, _| {
+                b.iter(|| create_proof_with_backend(circuit, &pk, r, s, &backend).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_msm, bench_prove);
+criterion_main!(benches);
@@ -0,0 +1,65 @@
+use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+use ark_groth16::{Groth16, ProvingKey};
+use ark_snark::SNARK;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use gadgets::multiply_demo::MultiplyDemoCircuit;
+use gadgets::padding::Padded;
+use gadgets::persist::{deserialize_with_mode, serialize_with_mode, SerializationMode, Validation};
+
+// loading a Groth16 proving key: compressed keys are small but every point needs a square
+// root and a subgroup check, uncompressed keys only need the subgroup check, and
+// unchecked loading trusts the bytes completely
+fn bench_proving_key_load(c: &mut Criterion) {
+    let rng = &mut ark_std::test_rng();
+    let circuit = Padded::new(
+        MultiplyDemoCircuit::<BlsFr> { a: None, b: None },
+        1 << 10,
+        1 << 10,
+    );
+    let (pk, _) = Groth16::<Bls12_381>::circuit_specific_setup(circuit, rng).unwrap();
+
+    let mut group = c.benchmark_group("groth16_pk_load");
+    group.sample_size(10);
+    for &(mode, validation) in [
+        (SerializationMode::Compressed, Validation::Checked),
+        (SerializationMode::Uncompressed, Validation::Checked),
+        (SerializationMode::Uncompressed, Validation::Unchecked),
+    ]
+    .iter()
+    {
+        let mut bytes = Vec::new();
+        serialize_with_mode(&pk, mode, &mut bytes).unwrap();
+        let id = BenchmarkId::new(
+            format!("{:?}/{:?}", mode, validation),
+            format!("{}B", bytes.len()),
+        );
+        group.bench_function(id, |b| {
+            b.iter(|| {
+                let _: ProvingKey<Bls12_381> =
+                    deserialize_with_mode(&bytes[..], mode, validation).unwrap();
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("groth16_pk_store");
+    group.sample_size(10);
+    for &mode in [
+        SerializationMode::Compressed,
+        SerializationMode::Uncompressed,
+    ]
+    .iter()
+    {
+        group.bench_function(format!("{:?}", mode), |b| {
+            b.iter(|| {
+                let mut bytes = Vec::new();
+                serialize_with_mode(&pk, mode, &mut bytes).unwrap();
+                bytes
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_proving_key_load);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+use ark_groth16::Groth16;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use gadgets::prover_context::ProverContext;
+use gadgets::synthetic::SyntheticCircuit;
+use std::thread;
+
+// PROOFS Groth16 proofs of a 2^12 constraint synthetic circuit from one shared
+// `ProverContext`, on 1, 2 and 4 threads. ark-groth16 is built without its `parallel`
+// feature, a proof runs on one core, so the time per batch drops with the threads as
+// far as there are cores, and stays flat on one core: sharing the key costs nothing.
+//   cargo bench --bench prover_context
+
+const PROOFS: usize = 4;
+const THREADS: [usize; 3] = [1, 2, 4];
+
+fn bench_prover_context(c: &mut Criterion) {
+    let circuit = SyntheticCircuit::of_log_size(12);
+    let context = ProverContext::<BlsFr, Groth16<Bls12_381>>::setup(circuit).unwrap();
+
+    let mut group = c.benchmark_group(format!("prover_context/{} proofs", PROOFS));
+    group.sample_size(10);
+    for &threads in THREADS.iter() {
+        group.bench_function(BenchmarkId::new("threads", threads), |b| {
+            b.iter(|| {
+                thread::scope(|scope| {
+                    for t in 0..threads {
+                        let context = context.clone();
+                        scope.spawn(move || {
+                            // the proofs dealt out round robin
+                            for seed in (t..PROOFS).step_by(threads) {
+                                let circuit = circuit.with_seed(seed as u64);
+                                context.prove(circuit).unwrap();
+                            }
+                        });
+                    }
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_prover_context);
+criterion_main!(benches);
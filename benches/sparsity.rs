@@ -0,0 +1,56 @@
+use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+use ark_std::UniformRand;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use gadgets::marlin_demo::{Formulation, PrefixProductCircuit};
+use gadgets::marlin_srs::{MarlinInst, MarlinSrsSize, UniversalSetup};
+
+// the prefix product circuit of `marlin_demo` written sparse (a variable and a constraint
+// per prefix sum) and dense (the sums as linear combinations), indexed and proved with
+// Marlin against one SRS sized for both. The sizes are printed first: the dense system
+// has half the constraints and about k / 6 times the non-zero entries.
+//   cargo bench --bench sparsity
+
+fn bench_sparsity(c: &mut Criterion) {
+    let rng = &mut ark_std::test_rng();
+    let mut group = c.benchmark_group("marlin_formulation");
+    group.sample_size(10);
+    for &k in [32, 128].iter() {
+        let xs: Vec<BlsFr> = (0..k).map(|_| BlsFr::rand(rng)).collect();
+        let formulations = [Formulation::Sparse, Formulation::Dense];
+        let sizes: Vec<MarlinSrsSize> = formulations
+            .iter()
+            .map(|&f| MarlinSrsSize::of(PrefixProductCircuit::<BlsFr>::blank(k, f)).unwrap())
+            .collect();
+        let setup = UniversalSetup::<Bls12_381>::new(sizes[0].max(sizes[1]), rng).unwrap();
+        for (&formulation, size) in formulations.iter().zip(&sizes) {
+            println!("k = {}, {:?}: {:?}", k, formulation, size);
+            let name = format!("{:?}", formulation).to_lowercase();
+            let blank = || PrefixProductCircuit::<BlsFr>::blank(k, formulation);
+            group.bench_with_input(
+                BenchmarkId::new(format!("index/{}", name), k),
+                &k,
+                |b, _| b.iter(|| setup.index_circuit(blank()).unwrap()),
+            );
+            let (index_pk, _) = setup.index_circuit(blank()).unwrap();
+            let circuit = PrefixProductCircuit::new(xs.clone(), formulation);
+            group.bench_with_input(
+                BenchmarkId::new(format!("prove/{}", name), k),
+                &k,
+                |b, _| {
+                    b.iter(|| {
+                        MarlinInst::<Bls12_381>::prove(
+                            &index_pk,
+                            circuit.clone(),
+                            &mut ark_std::test_rng(),
+                        )
+                        .unwrap()
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_sparsity);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+use ark_bls12_381::Fr as BlsFr;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_std::UniformRand;
+use criterion::{criterion_group, criterion_main, Criterion};
+use gadgets::matmul_demo::MatMulCircuit;
+use gadgets::synthetic::SyntheticCircuit;
+
+// witness generation at 2^18 constraints: synthesis of the circuit as it is, computing
+// each value in its closure, against `precompute` followed by synthesis, and
+// `precompute` alone, the part that runs on all cores. The constraint system itself is
+// filled on one thread either way. Run it with and without `--features parallel` to see
+// what the cores buy:
+//   cargo bench --bench witness
+//   cargo bench --bench witness --features parallel
+
+fn synthesize<C: ConstraintSynthesizer<BlsFr>>(circuit: C) {
+    let cs = ConstraintSystem::new_ref();
+    circuit.generate_constraints(cs).unwrap();
+}
+
+fn bench_witness(c: &mut Criterion) {
+    let rng = &mut ark_std::test_rng();
+    let mut group = c.benchmark_group("witness_2^18");
+    group.sample_size(10);
+
+    let synthetic = SyntheticCircuit::of_log_size(18);
+    group.bench_function("synthetic/inline", |b| b.iter(|| synthesize(synthetic)));
+    group.bench_function("synthetic/precomputed", |b| {
+        b.iter(|| synthesize(synthetic.precompute::<BlsFr>()))
+    });
+    group.bench_function("synthetic/precompute", |b| {
+        b.iter(|| synthetic.precompute::<BlsFr>())
+    });
+
+    // 64^3 + 64^2 constraints
+    let n = 64;
+    let a: Vec<BlsFr> = (0..n * n).map(|_| BlsFr::rand(rng)).collect();
+    let b: Vec<BlsFr> = (0..n * n).map(|_| BlsFr::rand(rng)).collect();
+    let matmul = MatMulCircuit::new(n, a, b);
+    group.bench_function("matmul/inline", |b| b.iter(|| synthesize(matmul.clone())));
+    group.bench_function("matmul/precomputed", |b| {
+        b.iter(|| synthesize(matmul.clone().precompute()))
+    });
+    group.bench_function("matmul/precompute", |b| {
+        b.iter(|| matmul.clone().precompute())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_witness);
+criterion_main!(benches);
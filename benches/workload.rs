@@ -0,0 +1,103 @@
+use ark_bls12_381::{Fr as BlsFr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::ProjectiveCurve;
+use ark_ff::PrimeField;
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_std::UniformRand;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use gadgets::airdrop_demo::AirdropCircuit;
+use gadgets::boolean_demo::AdderCircuit;
+use gadgets::cubic_demo::CubicDemoCircuit;
+use gadgets::matmul_demo::MatMulCircuit;
+use gadgets::msm::{CpuMsm, MsmBackend};
+use gadgets::multiply_demo::MultiplyDemoCircuit;
+use gadgets::poseidon_params::poseidon_parameters;
+use gadgets::synthetic::SyntheticCircuit;
+use gadgets::workload::Groth16Workload;
+use std::collections::BTreeSet;
+
+// the FFTs and MSMs of a Groth16 proof of each demo circuit, at the sizes
+// `workload::Groth16Workload` reads off its matrices, one at a time. Each size is timed
+// once however many circuits share it; the workloads are printed first to add them up:
+// 7 FFTs of the domain, the G1 MSMs and the G2 MSM of a circuit make its proving time
+// less synthesis.
+//   cargo bench --features gadgets --bench workload
+
+fn workloads() -> Vec<(&'static str, Groth16Workload)> {
+    let params = poseidon_parameters::<BlsFr>();
+    vec![
+        (
+            "multiply",
+            Groth16Workload::of_circuit(MultiplyDemoCircuit::<BlsFr>::blank()).unwrap(),
+        ),
+        (
+            "cubic",
+            Groth16Workload::of_circuit(CubicDemoCircuit::<BlsFr>::blank()).unwrap(),
+        ),
+        (
+            "adder",
+            Groth16Workload::of_circuit(AdderCircuit::<BlsFr>::blank()).unwrap(),
+        ),
+        (
+            "airdrop_16",
+            Groth16Workload::of_circuit(AirdropCircuit::blank(params, 16)).unwrap(),
+        ),
+        (
+            "matmul_16",
+            Groth16Workload::of_circuit(MatMulCircuit::<BlsFr>::blank(16)).unwrap(),
+        ),
+        (
+            "synthetic_2^14",
+            Groth16Workload::of_circuit::<BlsFr, _>(SyntheticCircuit::of_log_size(14)).unwrap(),
+        ),
+    ]
+}
+
+fn bench_workload(c: &mut Criterion) {
+    let rng = &mut ark_std::test_rng();
+    let workloads = workloads();
+    let mut ffts = BTreeSet::new();
+    let mut g1 = BTreeSet::new();
+    let mut g2 = BTreeSet::new();
+    for (name, workload) in &workloads {
+        println!("{}: {}", name, workload);
+        ffts.insert(workload.domain_size);
+        g1.extend(workload.g1_msms().iter().copied());
+        g2.insert(workload.variable_msm());
+    }
+
+    let mut group = c.benchmark_group("workload");
+    group.sample_size(10);
+    for &size in &ffts {
+        let domain = GeneralEvaluationDomain::<BlsFr>::new(size).unwrap();
+        let values: Vec<BlsFr> = (0..size).map(|_| BlsFr::rand(rng)).collect();
+        group.bench_with_input(BenchmarkId::new("fft", size), &size, |b, _| {
+            b.iter(|| {
+                let mut values = values.clone();
+                domain.ifft_in_place(&mut values);
+                values
+            })
+        });
+    }
+
+    let largest = g1.iter().chain(&g2).copied().max().unwrap_or(0);
+    let scalars: Vec<_> = (0..largest).map(|_| BlsFr::rand(rng).into_repr()).collect();
+    let bases: Vec<G1Projective> = (0..largest).map(|_| G1Projective::rand(rng)).collect();
+    let bases: Vec<G1Affine> = G1Projective::batch_normalization_into_affine(&bases);
+    for &size in &g1 {
+        group.bench_with_input(BenchmarkId::new("msm_g1", size), &size, |b, _| {
+            b.iter(|| CpuMsm.msm(&bases[..size], &scalars[..size]))
+        });
+    }
+    let largest = g2.iter().copied().max().unwrap_or(0);
+    let bases: Vec<G2Projective> = (0..largest).map(|_| G2Projective::rand(rng)).collect();
+    let bases: Vec<G2Affine> = G2Projective::batch_normalization_into_affine(&bases);
+    for &size in &g2 {
+        group.bench_with_input(BenchmarkId::new("msm_g2", size), &size, |b, _| {
+            b.iter(|| CpuMsm.msm(&bases[..size], &scalars[..size]))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_workload);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+use ark_bls12_381::Bls12_381;
+use ark_crypto_primitives::prf::blake2s::constraints::evaluate_blake2s;
+use ark_ff::PrimeField;
+use ark_groth16::{Groth16, ProvingKey};
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_snark::SNARK;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use gadgets::persist::{
+    read_artifact, write_artifact, ArtifactKind, SerializationMode, Validation,
+};
+use std::io::Read;
+
+// zstd on the proving key of a bit-level hash: one Blake2s compression of a 64 byte
+// witness, ~21000 constraints of xors, ands and 32 bit additions, the same kind of
+// circuit as SHA-256. The sizes are in the ids of the compression benchmarks, next to the
+// plain envelope's.
+
+const LEVELS: [i32; 3] = [1, 3, 19];
+
+#[derive(Clone, Copy)]
+struct HashCircuit;
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for HashCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let input = Vec::<UInt8<F>>::new_witness(cs, || Ok(vec![0u8; 64]))?;
+        let bits: Vec<_> = input
+            .iter()
+            .flat_map(|byte| byte.to_bits_le().unwrap())
+            .collect();
+        evaluate_blake2s(&bits)?;
+        Ok(())
+    }
+}
+
+fn bench_zstd(c: &mut Criterion) {
+    let rng = &mut ark_std::test_rng();
+    let (pk, _) = Groth16::<Bls12_381>::circuit_specific_setup(HashCircuit, rng).unwrap();
+
+    for &mode in [
+        SerializationMode::Compressed,
+        SerializationMode::Uncompressed,
+    ]
+    .iter()
+    {
+        let mut plain = Vec::new();
+        write_artifact(&pk, ArtifactKind::ProvingKey, mode, &mut plain).unwrap();
+
+        let mut group = c.benchmark_group(format!("groth16_pk_zstd/{:?}", mode));
+        group.sample_size(10);
+        for &level in LEVELS.iter() {
+            let packed = zstd::encode_all(&plain[..], level).unwrap();
+            let id = format!("level {}/{}B of {}B", level, packed.len(), plain.len());
+            group.bench_function(BenchmarkId::new("compress", id), |b| {
+                b.iter(|| zstd::encode_all(&plain[..], level).unwrap())
+            });
+        }
+        group.finish();
+    }
+
+    // what decompression adds to the fastest load, trusted uncompressed keys. Checked
+    // loads spend seconds on point checks and would hide it
+    let mut plain = Vec::new();
+    let mode = SerializationMode::Uncompressed;
+    write_artifact(&pk, ArtifactKind::ProvingKey, mode, &mut plain).unwrap();
+    let load = |reader: &mut dyn Read| {
+        let _: ProvingKey<Bls12_381> =
+            read_artifact(reader, ArtifactKind::ProvingKey, Validation::Unchecked).unwrap();
+    };
+    let mut group = c.benchmark_group("groth16_pk_zstd/load_unchecked");
+    group.sample_size(10);
+    group.bench_function("plain", |b| b.iter(|| load(&mut &plain[..])));
+    for &level in LEVELS.iter() {
+        let packed = zstd::encode_all(&plain[..], level).unwrap();
+        group.bench_function(format!("level {}", level), |b| {
+            b.iter(|| load(&mut zstd::Decoder::new(&packed[..]).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_zstd);
+criterion_main!(benches);
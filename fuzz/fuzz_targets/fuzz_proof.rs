@@ -0,0 +1,10 @@
+#![no_main]
+use ark_bls12_381::Bls12_381;
+use ark_groth16::Proof;
+use gadgets::persist::ArtifactKind;
+use gadgets_fuzz::deserialize_all_modes;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    deserialize_all_modes::<Proof<Bls12_381>>(data, ArtifactKind::Proof);
+});
@@ -0,0 +1,48 @@
+use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+use ark_groth16::Groth16;
+use ark_serialize::CanonicalSerialize;
+use ark_snark::SNARK;
+use gadgets::multiply_demo::MultiplyDemoCircuit;
+use gadgets::persist::{self, ArtifactKind, SerializationMode};
+use std::fs;
+use std::path::Path;
+
+// writes valid multiply circuit artifacts, raw and enveloped in both encodings, to
+// corpus/<target>/ so the fuzzer starts from well formed inputs:
+//   cargo run --bin seed_corpus
+fn write_seeds<T: CanonicalSerialize>(target: &str, artifact: &T, kind: ArtifactKind) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus").join(target);
+    fs::create_dir_all(&dir).unwrap();
+    for &(mode, name) in [
+        (SerializationMode::Compressed, "compressed"),
+        (SerializationMode::Uncompressed, "uncompressed"),
+    ]
+    .iter()
+    {
+        let mut raw = Vec::new();
+        persist::serialize_with_mode(artifact, mode, &mut raw).unwrap();
+        fs::write(dir.join(format!("raw_{}", name)), raw).unwrap();
+
+        let mut enveloped = Vec::new();
+        persist::write_artifact(artifact, kind, mode, &mut enveloped).unwrap();
+        fs::write(dir.join(format!("envelope_{}", name)), enveloped).unwrap();
+    }
+}
+
+fn main() {
+    let rng = &mut ark_std::test_rng();
+    let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+        MultiplyDemoCircuit::<BlsFr> { a: None, b: None },
+        rng,
+    )
+    .unwrap();
+    let circuit = MultiplyDemoCircuit {
+        a: Some(BlsFr::from(3u8)),
+        b: Some(BlsFr::from(5u8)),
+    };
+    let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+
+    write_seeds("fuzz_proof", &proof, ArtifactKind::Proof);
+    write_seeds("fuzz_verifying_key", &vk, ArtifactKind::VerifyingKey);
+    write_seeds("fuzz_proving_key", &pk, ArtifactKind::ProvingKey);
+}
@@ -0,0 +1,16 @@
+use ark_serialize::CanonicalDeserialize;
+use gadgets::persist::{self, ArtifactKind, SerializationMode, Validation};
+
+// every way the crate turns bytes into an artifact: raw in all three supported
+// mode/validation pairs (plus the rejected compressed + unchecked pair) and wrapped in
+// the persist envelope. Errors are expected, panics are bugs.
+pub fn deserialize_all_modes<T: CanonicalDeserialize>(data: &[u8], kind: ArtifactKind) {
+    for &mode in [SerializationMode::Compressed, SerializationMode::Uncompressed].iter() {
+        for &validation in [Validation::Checked, Validation::Unchecked].iter() {
+            let _ = persist::deserialize_with_mode::<T, _>(data, mode, validation);
+        }
+    }
+    for &validation in [Validation::Checked, Validation::Unchecked].iter() {
+        let _ = persist::read_artifact::<T, _>(data, kind, validation);
+    }
+}
@@ -0,0 +1,281 @@
+use ark_bls12_381::Fr;
+use ark_ff::Zero;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::poseidon::PoseidonParameters;
+
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+use crate::merkle_gadget::{MerklePath, MerklePathVar};
+use crate::private_index_demo::select_index;
+use crate::state::TreeState;
+
+// The root history of mixers and bridges: deposits are leaves of a Merkle tree
+// (`merkle_gadget`) and every deposit changes the root, so a proof against the root of
+// the moment would be stale by the time it is submitted if anyone deposits in between.
+// The contract keeps the last `ROOT_HISTORY` roots in a ring and accepts a proof against
+// any of them. Here the whole ring is the public input and the circuit selects the root
+// with a private position (`private_index_demo`), so the proof doesn't even tell which
+// epoch the prover's path is from. A root that fell out of the ring, or was never in
+// it, gets no proof.
+//
+// The leaves are commitments H(secret), the prover shows it knows the secret of one.
+
+pub const ROOT_HISTORY: usize = 4;
+
+pub fn commitment(params: &PoseidonParameters<Fr>, secret: Fr) -> Fr {
+    hash_inputs(params, &[secret])
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RootHistory {
+    roots: Vec<Fr>,
+    // the slot of the next root, the oldest one
+    next: usize,
+}
+
+impl RootHistory {
+    // every slot holds the first root until it is overwritten
+    pub fn new(root: Fr) -> Self {
+        Self {
+            roots: vec![root; ROOT_HISTORY],
+            next: 0,
+        }
+    }
+
+    pub fn push(&mut self, root: Fr) {
+        self.roots[self.next] = root;
+        self.next = (self.next + 1) % ROOT_HISTORY;
+    }
+
+    pub fn contains(&self, root: &Fr) -> bool {
+        self.roots.contains(root)
+    }
+
+    // the ring as it is stored, the public inputs of a proof against it
+    pub fn roots(&self) -> &[Fr] {
+        &self.roots
+    }
+}
+
+// the tree of deposits and its recent roots
+pub struct Accumulator {
+    tree: TreeState<Fr>,
+    history: RootHistory,
+}
+
+impl Accumulator {
+    pub fn new(params: PoseidonParameters<Fr>, height: usize) -> Self {
+        let tree = TreeState::new(params, height);
+        let history = RootHistory::new(tree.root());
+        Self { tree, history }
+    }
+
+    // the index of the new leaf, None if the tree is full
+    pub fn insert(&mut self, leaf: Fr) -> Option<usize> {
+        let index = self.tree.insert(leaf).ok()?;
+        self.history.push(self.tree.root());
+        Some(index)
+    }
+
+    pub fn height(&self) -> usize {
+        self.tree.height()
+    }
+
+    pub fn root(&self) -> Fr {
+        self.tree.root()
+    }
+
+    // the path of a leaf in the current tree, it stays valid for the next
+    // ROOT_HISTORY - 1 deposits
+    pub fn path(&self, index: usize) -> Option<MerklePath<Fr>> {
+        self.tree.path(index)
+    }
+
+    pub fn history(&self) -> &RootHistory {
+        &self.history
+    }
+}
+
+#[derive(Clone)]
+pub struct HistoryCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub height: usize,
+    pub roots: Vec<Fr>,
+    pub secret: Option<Fr>,
+    pub path: Option<MerklePath<Fr>>,
+    // the slot of the root of the path
+    pub slot: Option<usize>,
+}
+
+impl HistoryCircuit {
+    // None if the root of the path is not in the history
+    pub fn new(
+        params: PoseidonParameters<Fr>,
+        history: &RootHistory,
+        secret: Fr,
+        path: MerklePath<Fr>,
+    ) -> Option<Self> {
+        let root = path.root(&params, commitment(&params, secret));
+        let slot = history.roots().iter().position(|r| *r == root)?;
+        Some(Self {
+            params,
+            height: path.siblings.len(),
+            roots: history.roots().to_vec(),
+            secret: Some(secret),
+            path: Some(path),
+            slot: Some(slot),
+        })
+    }
+
+    // the ring is the public input
+    pub fn blank(params: PoseidonParameters<Fr>, height: usize) -> Self {
+        Self {
+            params,
+            height,
+            roots: vec![Fr::zero(); ROOT_HISTORY],
+            secret: None,
+            path: None,
+            slot: None,
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        self.roots.clone()
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for HistoryCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let roots = Vec::<FpVar<Fr>>::new_input(ns!(cs, "roots"), || Ok(self.roots.as_slice()))?;
+        let secret = FpVar::new_witness(ns!(cs, "secret"), || {
+            self.secret.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let slot = FpVar::new_witness(ns!(cs, "slot"), || {
+            self.slot
+                .map(|s| Fr::from(s as u64))
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let leaf = hash_inputs_var(cs.clone(), &self.params, &[secret])?;
+        let path = MerklePathVar::new_witness(cs.clone(), self.height, self.path.as_ref())?;
+        let root = path.root(cs.clone(), &self.params, &leaf)?;
+        select_index(cs, &roots, &slot)?.enforce_equal(&root)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    fn is_satisfied(circuit: HistoryCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_root_history() {
+        let mut history = RootHistory::new(Fr::from(0u8));
+        for i in 1..=6u8 {
+            history.push(Fr::from(i));
+        }
+        // the last four
+        for i in 0..=6u8 {
+            assert_eq!(history.contains(&Fr::from(i)), i >= 3, "{}", i);
+        }
+    }
+
+    #[test]
+    fn test_proof_against_recent_roots() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let mut accumulator = Accumulator::new(params.clone(), 5);
+        let secret = Fr::rand(rng);
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            HistoryCircuit::blank(params.clone(), 5),
+            rng,
+        )
+        .unwrap();
+
+        // the prover's deposit, the path of that epoch
+        accumulator.insert(Fr::rand(rng)).unwrap();
+        let index = accumulator.insert(commitment(&params, secret)).unwrap();
+        let path = accumulator.path(index).unwrap();
+        let epoch_root = accumulator.root();
+
+        // still accepted after other deposits, while the root is in the ring
+        for _ in 0..ROOT_HISTORY - 1 {
+            accumulator.insert(Fr::rand(rng)).unwrap();
+            assert_ne!(accumulator.root(), epoch_root);
+            assert!(accumulator.history().contains(&epoch_root));
+            let circuit =
+                HistoryCircuit::new(params.clone(), accumulator.history(), secret, path.clone())
+                    .unwrap();
+            let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+            let inputs = accumulator.history().roots();
+            assert!(Groth16::<Bls12_381>::verify(&vk, inputs, &proof).unwrap());
+        }
+
+        // a proof made against the ring of now is rejected once the ring moves on
+        let circuit =
+            HistoryCircuit::new(params.clone(), accumulator.history(), secret, path.clone())
+                .unwrap();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        accumulator.insert(Fr::rand(rng)).unwrap();
+        let inputs = accumulator.history().roots();
+        assert!(!Groth16::<Bls12_381>::verify(&vk, inputs, &proof).unwrap());
+    }
+
+    #[test]
+    fn test_stale_and_unknown_roots() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let mut accumulator = Accumulator::new(params.clone(), 5);
+        let secret = Fr::rand(rng);
+        let index = accumulator.insert(commitment(&params, secret)).unwrap();
+        let stale_path = accumulator.path(index).unwrap();
+        let circuit = HistoryCircuit::new(
+            params.clone(),
+            accumulator.history(),
+            secret,
+            stale_path.clone(),
+        )
+        .unwrap();
+        for _ in 0..ROOT_HISTORY {
+            accumulator.insert(Fr::rand(rng)).unwrap();
+        }
+
+        // the root of the path fell out of the ring, the current path still works
+        let history = accumulator.history();
+        assert!(HistoryCircuit::new(params.clone(), history, secret, stale_path).is_none());
+        let mut stale = circuit.clone();
+        stale.roots = history.roots().to_vec();
+        assert!(!is_satisfied(stale));
+        let path = accumulator.path(index).unwrap();
+        assert!(is_satisfied(
+            HistoryCircuit::new(params.clone(), history, secret, path.clone()).unwrap()
+        ));
+
+        // a tree of its own with the commitment, and a secret that was never deposited
+        let mut other = Accumulator::new(params.clone(), 5);
+        let other_index = other.insert(commitment(&params, secret)).unwrap();
+        let other_path = other.path(other_index).unwrap();
+        assert!(HistoryCircuit::new(params.clone(), history, secret, other_path).is_none());
+        for slot in 0..ROOT_HISTORY {
+            let mut unknown =
+                HistoryCircuit::new(params.clone(), history, secret, path.clone()).unwrap();
+            unknown.secret = Some(secret + Fr::from(1u8));
+            unknown.slot = Some(slot);
+            assert!(!is_satisfied(unknown));
+        }
+    }
+}
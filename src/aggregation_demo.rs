@@ -0,0 +1,221 @@
+use ark_crypto_primitives::snark::{constraints::SNARKGadget, BooleanInputVar};
+use ark_ff::Zero;
+use ark_groth16::{
+    constraints::{ProofVar, VerifyingKeyVar},
+    Proof, VerifyingKey,
+};
+use ark_mnt4_298::MNT4_298;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+
+use crate::cycles::{self, InnerVerifierGadget, Mnt4Mnt6, TwoChain};
+use crate::ivc_demo::placeholder_proof;
+
+// Batch settlement: a verifier that pays per proof (a contract, a light client) checks
+// one outer proof that verified several inner ones, of different circuits. Here the
+// inner proofs are Groth16 proofs on MNT4-298 of any circuits, e.g. `cubic_demo` and
+// `multiply_demo`, and the outer circuit over the MNT4 base field, the scalar field of
+// MNT6-298, verifies all of them. The inner verifying keys are constants of the outer
+// circuit, so its keys are for one list of inner circuits, unlike `ivc_demo` where the
+// keys are witnesses bound by digests.
+//
+// The public inputs of the outer proof are those of the inner proofs, in order. They
+// are MNT4 scalars and become MNT6 scalars with the same bits, the MNT4 scalar field
+// being the smaller one. An inner verification is some 34000 constraints, mostly the
+// pairing. The curves are the `cycles::Mnt4Mnt6` chain.
+
+pub type InnerFr = cycles::InnerFr<Mnt4Mnt6>;
+pub type OuterFr = cycles::OuterFr<Mnt4Mnt6>;
+type PairingVar = <Mnt4Mnt6 as TwoChain>::InnerPairingVar;
+
+#[derive(Clone)]
+pub struct InnerStatement {
+    pub vk: VerifyingKey<MNT4_298>,
+    pub inputs: Vec<InnerFr>,
+    pub proof: Proof<MNT4_298>,
+}
+
+impl InnerStatement {
+    pub fn new(vk: VerifyingKey<MNT4_298>, inputs: Vec<InnerFr>, proof: Proof<MNT4_298>) -> Self {
+        Self { vk, inputs, proof }
+    }
+
+    // the circuit of a key, for the setup
+    pub fn blank(vk: VerifyingKey<MNT4_298>) -> Self {
+        Self {
+            inputs: vec![InnerFr::zero(); vk.gamma_abc_g1.len() - 1],
+            vk,
+            proof: placeholder_proof(),
+        }
+    }
+}
+
+// the same number in the outer field
+pub fn to_outer(value: InnerFr) -> OuterFr {
+    Mnt4Mnt6::to_outer(value).expect("the inner scalar field is the smaller")
+}
+
+#[derive(Clone)]
+pub struct AggregateCircuit {
+    pub statements: Vec<InnerStatement>,
+}
+
+impl AggregateCircuit {
+    pub fn new(statements: Vec<InnerStatement>) -> Self {
+        Self { statements }
+    }
+
+    pub fn blank(vks: &[VerifyingKey<MNT4_298>]) -> Self {
+        Self {
+            statements: vks.iter().cloned().map(InnerStatement::blank).collect(),
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<OuterFr> {
+        self.statements
+            .iter()
+            .flat_map(|s| s.inputs.iter().map(|&x| to_outer(x)))
+            .collect()
+    }
+}
+
+impl ConstraintSynthesizer<OuterFr> for AggregateCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<OuterFr>) -> Result<(), SynthesisError> {
+        let inputs = self.public_inputs();
+        let inputs = Vec::<FpVar<OuterFr>>::new_input(ns!(cs, "inputs"), || Ok(inputs.as_slice()))?;
+        let mut inputs = inputs.iter();
+        for statement in self.statements.iter() {
+            let vk = VerifyingKeyVar::<MNT4_298, PairingVar>::new_constant(
+                ns!(cs, "vk"),
+                &statement.vk,
+            )?;
+            let proof = ProofVar::<MNT4_298, PairingVar>::new_witness(ns!(cs, "proof"), || {
+                Ok(&statement.proof)
+            })?;
+            // the bits of an input are those of the inner scalar
+            let bits = inputs
+                .by_ref()
+                .take(statement.inputs.len())
+                .map(|x| x.to_bits_le())
+                .collect::<Result<Vec<_>, _>>()?;
+            InnerVerifierGadget::<Mnt4Mnt6>::verify(&vk, &BooleanInputVar::new(bits), &proof)?
+                .enforce_equal(&Boolean::TRUE)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::{CubicDemoCircuit, CubicWitness};
+    use crate::multiply_demo::{MultiplyDemoCircuit, MultiplyWitness};
+    use ark_ff::PrimeField;
+    use ark_groth16::{Groth16, ProvingKey};
+    use ark_mnt6_298::MNT6_298;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+    use ark_std::{
+        rand::{CryptoRng, RngCore},
+        UniformRand,
+    };
+
+    fn is_satisfied(circuit: AggregateCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    type Keys = (ProvingKey<MNT4_298>, VerifyingKey<MNT4_298>);
+
+    // the keys of both circuits and a statement of each
+    fn inner<R: RngCore + CryptoRng>(rng: &mut R) -> (Keys, Keys, Vec<InnerStatement>) {
+        let cubic =
+            Groth16::<MNT4_298>::circuit_specific_setup(CubicDemoCircuit::blank(), rng).unwrap();
+        let multiply =
+            Groth16::<MNT4_298>::circuit_specific_setup(MultiplyDemoCircuit::blank(), rng).unwrap();
+
+        let witness = CubicWitness {
+            x: InnerFr::from(3u8),
+        };
+        let proof = Groth16::<MNT4_298>::prove(&cubic.0, CubicDemoCircuit::from(witness), rng);
+        let cubic_statement = InnerStatement::new(
+            cubic.1.clone(),
+            witness.instance().public_inputs(),
+            proof.unwrap(),
+        );
+        let witness = MultiplyWitness {
+            a: InnerFr::from(7u8),
+            b: InnerFr::from(11u8),
+        };
+        let proof =
+            Groth16::<MNT4_298>::prove(&multiply.0, MultiplyDemoCircuit::from(witness), rng);
+        let multiply_statement = InnerStatement::new(
+            multiply.1.clone(),
+            witness.instance().public_inputs(),
+            proof.unwrap(),
+        );
+        (cubic, multiply, vec![cubic_statement, multiply_statement])
+    }
+
+    #[test]
+    fn test_aggregate_constraints() {
+        let rng = &mut ark_std::test_rng();
+        let (cubic, multiply, statements) = inner(rng);
+        let circuit = AggregateCircuit::new(statements);
+        assert_eq!(
+            circuit.public_inputs(),
+            vec![OuterFr::from(35u8), OuterFr::from(77u8)]
+        );
+        assert!(is_satisfied(circuit.clone()));
+
+        // another output, the proofs the other way round, a proof of another statement
+        let mut wrong = circuit.clone();
+        wrong.statements[1].inputs[0] = InnerFr::from(78u8);
+        assert!(!is_satisfied(wrong));
+        let mut wrong = circuit.clone();
+        wrong.statements[0].vk = multiply.1.clone();
+        wrong.statements[1].vk = cubic.1.clone();
+        assert!(!is_satisfied(wrong));
+        let mut wrong = circuit;
+        let other = CubicWitness {
+            x: InnerFr::from(4u8),
+        };
+        wrong.statements[0].proof =
+            Groth16::<MNT4_298>::prove(&cubic.0, CubicDemoCircuit::from(other), rng).unwrap();
+        assert!(!is_satisfied(wrong));
+
+        // every inner scalar is an outer one, the largest too
+        assert_eq!(
+            to_outer(-InnerFr::from(1u8)).into_repr(),
+            (-InnerFr::from(1u8)).into_repr()
+        );
+        for _ in 0..100 {
+            let x = InnerFr::rand(rng);
+            assert_eq!(to_outer(x).into_repr(), x.into_repr());
+        }
+    }
+
+    // cargo test --release aggregate -- --ignored, the outer proof on MNT6
+    #[test]
+    #[ignore]
+    fn test_aggregate_proof() {
+        let rng = &mut ark_std::test_rng();
+        let (cubic, multiply, statements) = inner(rng);
+        let (pk, vk) = Groth16::<MNT6_298>::circuit_specific_setup(
+            AggregateCircuit::blank(&[cubic.1, multiply.1]),
+            rng,
+        )
+        .unwrap();
+        let circuit = AggregateCircuit::new(statements);
+        let inputs = circuit.public_inputs();
+        let proof = Groth16::<MNT6_298>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<MNT6_298>::verify(&vk, &inputs, &proof).unwrap());
+        let mut other = inputs;
+        other[0] += OuterFr::from(1u8);
+        assert!(!Groth16::<MNT6_298>::verify(&vk, &other, &proof).unwrap());
+    }
+}
@@ -0,0 +1,323 @@
+use ark_bls12_381::Fr;
+use ark_ed_on_bls12_381::{EdwardsAffine, Fr as JubjubScalar};
+use ark_ff::Zero;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_snark::SNARK;
+use ark_sponge::poseidon::PoseidonParameters;
+use std::fmt;
+
+use crate::encoding::{field_from_bytes, Endian};
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+use crate::merkle_gadget::{MerklePath, MerklePathVar, MerkleTree};
+use crate::nullifier_set::{NullifierError, NullifierSet};
+use crate::schnorr_gadget::{generator_mul_var, secret_key_to_field, secret_key_var};
+
+// An airdrop: the eligible addresses, Jubjub public keys, are the leaves of a Merkle
+// tree (`merkle_gadget`) whose root is published with the airdrop. A claim proves that
+// the claimant holds the secret key of one of them without saying which, and carries
+//   - the recipient, e.g. an Ethereum address, a public input the proof is bound to, so
+//     whoever relays the claim can't redirect the tokens (Groth16 ties every public
+//     input to the proof even if no other constraint uses it),
+//   - the nullifier H(sk, airdrop id), the same for every claim of an address, so the
+//     `Airdrop` verifier pays each address once, but unlinkable to the address.
+// The secret key is range checked (`secret_key_var`) so it has a single nullifier.
+
+pub fn eligibility_leaf(params: &PoseidonParameters<Fr>, address: &EdwardsAffine) -> Fr {
+    hash_inputs(params, &[address.x, address.y])
+}
+
+// None if there are more than 2^height addresses
+pub fn eligibility_tree(
+    params: &PoseidonParameters<Fr>,
+    height: usize,
+    addresses: &[EdwardsAffine],
+) -> Option<MerkleTree<Fr>> {
+    let leaves: Vec<Fr> = addresses
+        .iter()
+        .map(|address| eligibility_leaf(params, address))
+        .collect();
+    MerkleTree::new(params.clone(), height, &leaves)
+}
+
+pub fn nullifier(params: &PoseidonParameters<Fr>, sk: JubjubScalar, airdrop_id: Fr) -> Fr {
+    hash_inputs(params, &[secret_key_to_field(sk), airdrop_id])
+}
+
+// a 20 byte address as a public input
+pub fn recipient_input(address: &[u8; 20]) -> Fr {
+    field_from_bytes(address, Endian::Big).expect("160 bits are below the modulus")
+}
+
+#[derive(Clone)]
+pub struct AirdropCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub height: usize,
+    pub root: Fr,
+    pub airdrop_id: Fr,
+    pub recipient: Fr,
+    pub secret_key: Option<JubjubScalar>,
+    pub path: Option<MerklePath<Fr>>,
+}
+
+impl AirdropCircuit {
+    // a claim of the address at `index` of `tree`, None if there is no such leaf
+    pub fn new(
+        params: PoseidonParameters<Fr>,
+        tree: &MerkleTree<Fr>,
+        index: usize,
+        airdrop_id: Fr,
+        recipient: Fr,
+        secret_key: JubjubScalar,
+    ) -> Option<Self> {
+        Some(Self {
+            params,
+            height: tree.height(),
+            root: tree.root(),
+            airdrop_id,
+            recipient,
+            secret_key: Some(secret_key),
+            path: Some(tree.path(index)?),
+        })
+    }
+
+    // only the height is fixed by the setup
+    pub fn blank(params: PoseidonParameters<Fr>, height: usize) -> Self {
+        Self {
+            params,
+            height,
+            root: Fr::zero(),
+            airdrop_id: Fr::zero(),
+            recipient: Fr::zero(),
+            secret_key: None,
+            path: None,
+        }
+    }
+
+    pub fn nullifier(&self) -> Option<Fr> {
+        self.secret_key
+            .map(|sk| nullifier(&self.params, sk, self.airdrop_id))
+    }
+
+    // the root, the airdrop, the recipient and the nullifier
+    pub fn public_inputs(&self) -> Option<Vec<Fr>> {
+        Some(vec![
+            self.root,
+            self.airdrop_id,
+            self.recipient,
+            self.nullifier()?,
+        ])
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for AirdropCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let expected_nullifier = self.nullifier();
+        let root = FpVar::new_input(ns!(cs, "root"), || Ok(self.root))?;
+        let airdrop_id = FpVar::new_input(ns!(cs, "airdrop_id"), || Ok(self.airdrop_id))?;
+        let _ = FpVar::new_input(ns!(cs, "recipient"), || Ok(self.recipient))?;
+        let nullifier = FpVar::new_input(ns!(cs, "nullifier"), || {
+            expected_nullifier.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let secret_key = secret_key_var(cs.clone(), self.secret_key)?;
+        let address = generator_mul_var(&secret_key)?;
+        let leaf = hash_inputs_var(cs.clone(), &self.params, &[address.x, address.y])?;
+        let path = MerklePathVar::new_witness(cs.clone(), self.height, self.path.as_ref())?;
+        path.root(cs.clone(), &self.params, &leaf)?
+            .enforce_equal(&root)?;
+
+        let secret_key = Boolean::le_bits_to_fp_var(&secret_key)?;
+        hash_inputs_var(cs, &self.params, &[secret_key, airdrop_id])?.enforce_equal(&nullifier)
+    }
+}
+
+pub struct Claim<S: SNARK<Fr>> {
+    pub recipient: Fr,
+    pub nullifier: Fr,
+    pub proof: S::Proof,
+}
+
+// a derive would ask for `S: Clone`
+impl<S: SNARK<Fr>> Clone for Claim<S> {
+    fn clone(&self) -> Self {
+        Self {
+            recipient: self.recipient,
+            nullifier: self.nullifier,
+            proof: self.proof.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ClaimError<E> {
+    AlreadyClaimed,
+    InvalidProof,
+    Snark(E),
+    // the claim verified but could not be recorded, the recipient must not be paid
+    Nullifiers(NullifierError),
+}
+
+impl<E: fmt::Display> fmt::Display for ClaimError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClaimError::AlreadyClaimed => write!(f, "the address has already claimed"),
+            ClaimError::InvalidProof => write!(f, "the proof does not verify"),
+            ClaimError::Snark(e) => write!(f, "verification error: {}", e),
+            ClaimError::Nullifiers(e) => write!(f, "cannot record the claim: {}", e),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ClaimError<E> {}
+
+// the verifier side of an airdrop, e.g. a contract: accepts each nullifier once
+pub struct Airdrop<S: SNARK<Fr>> {
+    pub id: Fr,
+    pub root: Fr,
+    vk: S::ProcessedVerifyingKey,
+    claimed: NullifierSet<Fr>,
+}
+
+impl<S: SNARK<Fr>> Airdrop<S> {
+    pub fn new(id: Fr, root: Fr, vk: &S::VerifyingKey) -> Result<Self, S::Error> {
+        Self::with_nullifiers(id, root, vk, NullifierSet::new())
+    }
+
+    // claims recorded in `claimed`, e.g. a `NullifierSet::open` file that outlives the
+    // process
+    pub fn with_nullifiers(
+        id: Fr,
+        root: Fr,
+        vk: &S::VerifyingKey,
+        claimed: NullifierSet<Fr>,
+    ) -> Result<Self, S::Error> {
+        Ok(Self {
+            id,
+            root,
+            vk: S::process_vk(vk)?,
+            claimed,
+        })
+    }
+
+    pub fn is_claimed(&self, nullifier: &Fr) -> bool {
+        self.claimed.contains(nullifier)
+    }
+
+    // records the claim if its proof verifies, the caller then pays the recipient
+    pub fn claim(&mut self, claim: &Claim<S>) -> Result<(), ClaimError<S::Error>> {
+        if self.is_claimed(&claim.nullifier) {
+            return Err(ClaimError::AlreadyClaimed);
+        }
+        let inputs = [self.root, self.id, claim.recipient, claim.nullifier];
+        match S::verify_with_processed_vk(&self.vk, &inputs, &claim.proof) {
+            Ok(true) => {
+                self.claimed
+                    .insert(claim.nullifier)
+                    .map_err(ClaimError::Nullifiers)?;
+                Ok(())
+            }
+            Ok(false) => Err(ClaimError::InvalidProof),
+            Err(e) => Err(ClaimError::Snark(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use crate::schnorr_gadget::keygen;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    type S = Groth16<Bls12_381>;
+
+    fn is_satisfied(circuit: AirdropCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_airdrop() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let keys: Vec<_> = (0..3).map(|_| keygen(rng)).collect();
+        let addresses: Vec<_> = keys.iter().map(|(_, pk)| *pk).collect();
+        let tree = eligibility_tree(&params, 3, &addresses).unwrap();
+        let airdrop_id = Fr::from(7u8);
+
+        let (pk, vk) =
+            S::circuit_specific_setup(AirdropCircuit::blank(params.clone(), 3), rng).unwrap();
+        let mut airdrop = Airdrop::<S>::new(airdrop_id, tree.root(), &vk).unwrap();
+        let claim = |index: usize, recipient: Fr, rng: &mut _| {
+            let circuit = AirdropCircuit::new(
+                params.clone(),
+                &tree,
+                index,
+                airdrop_id,
+                recipient,
+                keys[index].0,
+            )
+            .unwrap();
+            let nullifier = circuit.nullifier().unwrap();
+            Claim::<S> {
+                recipient,
+                nullifier,
+                proof: S::prove(&pk, circuit, rng).unwrap(),
+            }
+        };
+
+        let recipient = recipient_input(&[0x11; 20]);
+        let first = claim(1, recipient, rng);
+        // relayed to another recipient
+        let mut redirected = first.clone();
+        redirected.recipient = recipient_input(&[0x22; 20]);
+        assert!(matches!(
+            airdrop.claim(&redirected),
+            Err(ClaimError::InvalidProof)
+        ));
+        // with a fresh nullifier
+        let mut renamed = first.clone();
+        renamed.nullifier += Fr::from(1u8);
+        assert!(matches!(
+            airdrop.claim(&renamed),
+            Err(ClaimError::InvalidProof)
+        ));
+        airdrop.claim(&first).unwrap();
+        assert!(airdrop.is_claimed(&first.nullifier));
+
+        // the same address again, to another recipient with a fresh proof
+        let again = claim(1, recipient_input(&[0x33; 20]), rng);
+        assert_eq!(again.nullifier, first.nullifier);
+        assert!(matches!(
+            airdrop.claim(&again),
+            Err(ClaimError::AlreadyClaimed)
+        ));
+        airdrop.claim(&claim(2, recipient, rng)).unwrap();
+
+        // the nullifiers of another airdrop are unrelated
+        let next = Airdrop::<S>::new(airdrop_id + Fr::from(1u8), tree.root(), &vk).unwrap();
+        assert!(!next.is_claimed(&first.nullifier));
+        assert_ne!(nullifier(&params, keys[1].0, next.id), first.nullifier);
+    }
+
+    #[test]
+    fn test_not_eligible() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (sk, address) = keygen(rng);
+        let (outsider, _) = keygen(rng);
+        let tree = eligibility_tree(&params, 2, &[address]).unwrap();
+        let circuit = |sk| {
+            AirdropCircuit::new(params.clone(), &tree, 0, Fr::from(1u8), Fr::zero(), sk).unwrap()
+        };
+        assert!(is_satisfied(circuit(sk)));
+        assert!(!is_satisfied(circuit(outsider)));
+    }
+}
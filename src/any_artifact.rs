@@ -0,0 +1,312 @@
+use ark_bls12_381::Bls12_381;
+use ark_bn254::Bn254;
+use ark_ec::PairingEngine;
+use ark_groth16::{Proof, VerifyingKey};
+use ark_std::any::Any;
+use ark_std::io::{Read, Write};
+
+use crate::config::{Backend, Curve};
+use crate::marlin_srs::{MarlinProof, MarlinVerifyingKey};
+use crate::persist::{self, ArtifactKind, PersistError, SerializationMode, Validation};
+use crate::registry::{Groth16Backend, MarlinBackend, Scheme, TypedBackend};
+use crate::witness_json::FieldValue;
+use crate::Error;
+
+// verifying keys and proofs of any `registry` scheme as one type each, for code that
+// learns the scheme of an artifact only when it reads it:
+//
+//   let vk = AnyVerifyingKey::read(vk_file, Validation::Checked)?;
+//   let proof = AnyProof::read(proof_file, Validation::Checked)?;
+//   assert!(vk.verify(&public_inputs, &proof)?);
+//
+// `write` puts a scheme byte in front of the `persist` envelope so `read` knows which
+// variant follows, `read_for` reads a bare envelope (as `registry` and `persist::save`
+// write them) of a scheme the caller names. `downcast_ref` and `downcast` get the typed
+// artifact back, `From` wraps one.
+
+// the Marlin types are not `Debug`, and a Marlin proof is not `Clone` either
+#[derive(Clone)]
+pub enum AnyVerifyingKey {
+    Groth16Bls12_381(VerifyingKey<Bls12_381>),
+    Groth16Bn254(VerifyingKey<Bn254>),
+    MarlinBls12_381(MarlinVerifyingKey<Bls12_381>),
+    MarlinBn254(MarlinVerifyingKey<Bn254>),
+}
+
+pub enum AnyProof {
+    Groth16Bls12_381(Proof<Bls12_381>),
+    Groth16Bn254(Proof<Bn254>),
+    MarlinBls12_381(MarlinProof<Bls12_381>),
+    MarlinBn254(MarlinProof<Bn254>),
+}
+
+// the byte in front of the envelope, fixed per scheme so files stay readable when
+// `registry::SUPPORTED` grows
+fn scheme_to_byte(scheme: Scheme) -> u8 {
+    match (scheme.backend, scheme.curve) {
+        (Backend::Groth16, Curve::Bls12_381) => 0,
+        (Backend::Groth16, Curve::Bn254) => 1,
+        (Backend::Marlin, Curve::Bls12_381) => 2,
+        (Backend::Marlin, Curve::Bn254) => 3,
+    }
+}
+
+fn scheme_from_byte(byte: u8) -> Result<Scheme, PersistError> {
+    match byte {
+        0 => Ok(Scheme::new(Backend::Groth16, Curve::Bls12_381)),
+        1 => Ok(Scheme::new(Backend::Groth16, Curve::Bn254)),
+        2 => Ok(Scheme::new(Backend::Marlin, Curve::Bls12_381)),
+        3 => Ok(Scheme::new(Backend::Marlin, Curve::Bn254)),
+        _ => Err(PersistError::InvalidHeader),
+    }
+}
+
+fn read_scheme<R: Read>(reader: &mut R) -> Result<Scheme, PersistError> {
+    let mut byte = [0u8; 1];
+    reader.read_exact(&mut byte)?;
+    scheme_from_byte(byte[0])
+}
+
+// the same methods for both, only the artifact kind in the envelope differs
+macro_rules! any_artifact {
+    ($ty:ident, $kind:expr) => {
+        impl $ty {
+            pub fn scheme(&self) -> Scheme {
+                match self {
+                    $ty::Groth16Bls12_381(_) => Scheme::new(Backend::Groth16, Curve::Bls12_381),
+                    $ty::Groth16Bn254(_) => Scheme::new(Backend::Groth16, Curve::Bn254),
+                    $ty::MarlinBls12_381(_) => Scheme::new(Backend::Marlin, Curve::Bls12_381),
+                    $ty::MarlinBn254(_) => Scheme::new(Backend::Marlin, Curve::Bn254),
+                }
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                match self {
+                    $ty::Groth16Bls12_381(inner) => inner,
+                    $ty::Groth16Bn254(inner) => inner,
+                    $ty::MarlinBls12_381(inner) => inner,
+                    $ty::MarlinBn254(inner) => inner,
+                }
+            }
+
+            // the typed artifact if it is a `T`
+            pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+                self.as_any().downcast_ref()
+            }
+
+            // the typed artifact, or `self` back if it is not a `T`
+            pub fn downcast<T: Any>(self) -> Result<T, Box<Self>> {
+                if self.downcast_ref::<T>().is_none() {
+                    return Err(Box::new(self));
+                }
+                let boxed: Box<dyn Any> = match self {
+                    $ty::Groth16Bls12_381(inner) => Box::new(inner),
+                    $ty::Groth16Bn254(inner) => Box::new(inner),
+                    $ty::MarlinBls12_381(inner) => Box::new(inner),
+                    $ty::MarlinBn254(inner) => Box::new(inner),
+                };
+                Ok(*boxed.downcast().expect("checked by downcast_ref"))
+            }
+
+            // scheme byte | `persist` envelope
+            pub fn write<W: Write>(
+                &self,
+                mode: SerializationMode,
+                mut writer: W,
+            ) -> Result<(), PersistError> {
+                writer.write_all(&[scheme_to_byte(self.scheme())])?;
+                match self {
+                    $ty::Groth16Bls12_381(inner) => {
+                        persist::write_artifact(inner, $kind, mode, writer)
+                    }
+                    $ty::Groth16Bn254(inner) => persist::write_artifact(inner, $kind, mode, writer),
+                    $ty::MarlinBls12_381(inner) => {
+                        persist::write_artifact(inner, $kind, mode, writer)
+                    }
+                    $ty::MarlinBn254(inner) => persist::write_artifact(inner, $kind, mode, writer),
+                }
+            }
+
+            // what `write` wrote, the variant taken from the scheme byte
+            pub fn read<R: Read>(
+                mut reader: R,
+                validation: Validation,
+            ) -> Result<Self, PersistError> {
+                let scheme = read_scheme(&mut reader)?;
+                Self::read_for(scheme, reader, validation)
+            }
+
+            // a bare `persist` envelope holding an artifact of `scheme`
+            pub fn read_for<R: Read>(
+                scheme: Scheme,
+                reader: R,
+                validation: Validation,
+            ) -> Result<Self, PersistError> {
+                Ok(match (scheme.backend, scheme.curve) {
+                    (Backend::Groth16, Curve::Bls12_381) => {
+                        $ty::Groth16Bls12_381(persist::read_artifact(reader, $kind, validation)?)
+                    }
+                    (Backend::Groth16, Curve::Bn254) => {
+                        $ty::Groth16Bn254(persist::read_artifact(reader, $kind, validation)?)
+                    }
+                    (Backend::Marlin, Curve::Bls12_381) => {
+                        $ty::MarlinBls12_381(persist::read_artifact(reader, $kind, validation)?)
+                    }
+                    (Backend::Marlin, Curve::Bn254) => {
+                        $ty::MarlinBn254(persist::read_artifact(reader, $kind, validation)?)
+                    }
+                })
+            }
+        }
+    };
+}
+
+any_artifact!(AnyVerifyingKey, ArtifactKind::VerifyingKey);
+any_artifact!(AnyProof, ArtifactKind::Proof);
+
+macro_rules! impl_from {
+    ($ty:ident, $($variant:ident($inner:ty)),+) => {
+        $(impl From<$inner> for $ty {
+            fn from(inner: $inner) -> Self {
+                $ty::$variant(inner)
+            }
+        })+
+    };
+}
+
+impl_from!(
+    AnyVerifyingKey,
+    Groth16Bls12_381(VerifyingKey<Bls12_381>),
+    Groth16Bn254(VerifyingKey<Bn254>),
+    MarlinBls12_381(MarlinVerifyingKey<Bls12_381>),
+    MarlinBn254(MarlinVerifyingKey<Bn254>)
+);
+impl_from!(
+    AnyProof,
+    Groth16Bls12_381(Proof<Bls12_381>),
+    Groth16Bn254(Proof<Bn254>),
+    MarlinBls12_381(MarlinProof<Bls12_381>),
+    MarlinBn254(MarlinProof<Bn254>)
+);
+
+fn verify_typed<E: PairingEngine, B: TypedBackend<E>>(
+    vk: &B::VerifyingKey,
+    public_inputs: &[FieldValue],
+    proof: &B::Proof,
+) -> Result<bool, Error> {
+    let inputs = public_inputs
+        .iter()
+        .enumerate()
+        .map(|(i, value)| value.parse(&format!("public[{}]", i)))
+        .collect::<Result<Vec<E::Fr>, _>>()?;
+    B::verify(vk, &inputs, proof)
+}
+
+impl AnyVerifyingKey {
+    // fails (rather than returning false) for a proof of another scheme
+    pub fn verify(&self, public_inputs: &[FieldValue], proof: &AnyProof) -> Result<bool, Error> {
+        match (self, proof) {
+            (AnyVerifyingKey::Groth16Bls12_381(vk), AnyProof::Groth16Bls12_381(proof)) => {
+                verify_typed::<Bls12_381, Groth16Backend>(vk, public_inputs, proof)
+            }
+            (AnyVerifyingKey::Groth16Bn254(vk), AnyProof::Groth16Bn254(proof)) => {
+                verify_typed::<Bn254, Groth16Backend>(vk, public_inputs, proof)
+            }
+            (AnyVerifyingKey::MarlinBls12_381(vk), AnyProof::MarlinBls12_381(proof)) => {
+                verify_typed::<Bls12_381, MarlinBackend>(vk, public_inputs, proof)
+            }
+            (AnyVerifyingKey::MarlinBn254(vk), AnyProof::MarlinBn254(proof)) => {
+                verify_typed::<Bn254, MarlinBackend>(vk, public_inputs, proof)
+            }
+            _ => Err(format!(
+                "{} proof for a {} verifying key",
+                proof.scheme(),
+                self.scheme()
+            )
+            .into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::CircuitKind;
+    use crate::registry;
+    use crate::rng::RngConfig;
+
+    #[test]
+    fn test_verify_every_scheme() {
+        let rng_config = RngConfig::seeded(11);
+        for scheme in registry::all() {
+            let (pk, vk) = scheme.setup(CircuitKind::Cubic, &rng_config).unwrap();
+            let (proof, public) = scheme
+                .prove(CircuitKind::Cubic, &pk, r#"{"x": "3"}"#, &rng_config)
+                .unwrap();
+
+            let vk =
+                AnyVerifyingKey::read_for(scheme.scheme(), &vk[..], Validation::Checked).unwrap();
+            let proof =
+                AnyProof::read_for(scheme.scheme(), &proof[..], Validation::Checked).unwrap();
+            assert_eq!(vk.scheme(), scheme.scheme());
+            assert_eq!(proof.scheme(), scheme.scheme());
+            assert!(vk.verify(&public, &proof).unwrap());
+            let wrong = [FieldValue::from_field(ark_bn254::Fr::from(36u64))];
+            assert!(matches!(vk.verify(&wrong, &proof), Ok(false)));
+
+            // the scheme byte brings the variant back
+            let mut bytes = Vec::new();
+            proof
+                .write(SerializationMode::Compressed, &mut bytes)
+                .unwrap();
+            let read = AnyProof::read(&bytes[..], Validation::Checked).unwrap();
+            assert_eq!(read.scheme(), scheme.scheme());
+            let mut bytes = Vec::new();
+            vk.write(SerializationMode::Uncompressed, &mut bytes)
+                .unwrap();
+            let read = AnyVerifyingKey::read(&bytes[..], Validation::Checked).unwrap();
+            assert!(read.verify(&public, &proof).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_downcast_and_mismatch() {
+        let rng_config = RngConfig::seeded(11);
+        let groth16 = registry::lookup("groth16-bn254".parse().unwrap());
+        let marlin = registry::lookup("marlin-bn254".parse().unwrap());
+        let (pk, vk) = groth16.setup(CircuitKind::Cubic, &rng_config).unwrap();
+        let (proof, public) = groth16
+            .prove(CircuitKind::Cubic, &pk, r#"{"x": "3"}"#, &rng_config)
+            .unwrap();
+        let vk = AnyVerifyingKey::read_for(groth16.scheme(), &vk[..], Validation::Checked).unwrap();
+        let proof = AnyProof::read_for(groth16.scheme(), &proof[..], Validation::Checked).unwrap();
+
+        assert!(vk.downcast_ref::<VerifyingKey<Bn254>>().is_some());
+        assert!(vk.downcast_ref::<VerifyingKey<Bls12_381>>().is_none());
+        let back = proof.downcast::<MarlinProof<Bn254>>().err().unwrap();
+        assert_eq!(back.scheme(), groth16.scheme());
+        let typed = (*back).downcast::<Proof<Bn254>>().ok().unwrap();
+        let proof = AnyProof::from(typed);
+        assert_eq!(proof.scheme(), groth16.scheme());
+
+        // a Marlin proof against a Groth16 key is an error, not an invalid proof
+        let (pk, _) = marlin.setup(CircuitKind::Cubic, &rng_config).unwrap();
+        let (marlin_proof, _) = marlin
+            .prove(CircuitKind::Cubic, &pk, r#"{"x": "3"}"#, &rng_config)
+            .unwrap();
+        let marlin_proof =
+            AnyProof::read_for(marlin.scheme(), &marlin_proof[..], Validation::Checked).unwrap();
+        assert!(vk.verify(&public, &marlin_proof).is_err());
+
+        // an unknown scheme byte
+        let mut bytes = Vec::new();
+        proof
+            .write(SerializationMode::Compressed, &mut bytes)
+            .unwrap();
+        bytes[0] = 0xff;
+        assert!(matches!(
+            AnyProof::read(&bytes[..], Validation::Checked),
+            Err(PersistError::InvalidHeader)
+        ));
+    }
+}
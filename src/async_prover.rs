@@ -0,0 +1,133 @@
+use ark_ff::PrimeField;
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_snark::SNARK;
+use std::fmt;
+use std::sync::Arc;
+use tokio::task::JoinError;
+use tokio_util::sync::CancellationToken;
+
+use crate::prover;
+use crate::rng::RngConfig;
+
+// proving is CPU bound and can take seconds to minutes for real circuits, running it
+// directly inside an async handler would stall every other task on that runtime
+// worker. `prove_async` moves the work onto tokio's blocking thread pool.
+
+#[derive(Debug)]
+pub enum AsyncProveError<E> {
+    Cancelled,
+    Prover(E),
+    // the blocking task panicked
+    Join(JoinError),
+}
+
+impl<E: fmt::Display> fmt::Display for AsyncProveError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsyncProveError::Cancelled => write!(f, "proving was cancelled"),
+            AsyncProveError::Prover(e) => write!(f, "prover error: {}", e),
+            AsyncProveError::Join(e) => write!(f, "proving task failed: {}", e),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for AsyncProveError<E> {}
+
+// The proof is returned unless `cancel` fires first. The SNARK provers have no
+// cancellation points, so a cancelled proof keeps its blocking thread busy until it
+// finishes; only its result is discarded. Cancelling before the task is scheduled
+// skips the work entirely.
+pub async fn prove_async<F, S, C>(
+    pk: Arc<S::ProvingKey>,
+    circuit: C,
+    rng_config: RngConfig,
+    cancel: CancellationToken,
+) -> Result<S::Proof, AsyncProveError<S::Error>>
+where
+    F: PrimeField,
+    S: SNARK<F>,
+    S::ProvingKey: Send + Sync + 'static,
+    S::Proof: Send + 'static,
+    S::Error: Send + 'static,
+    C: ConstraintSynthesizer<F> + Send + 'static,
+{
+    if cancel.is_cancelled() {
+        return Err(AsyncProveError::Cancelled);
+    }
+
+    let task_cancel = cancel.clone();
+    let handle = tokio::task::spawn_blocking(move || {
+        if task_cancel.is_cancelled() {
+            return None;
+        }
+        Some(prover::prove::<F, S, C>(&pk, circuit, &rng_config))
+    });
+
+    tokio::select! {
+        _ = cancel.cancelled() => Err(AsyncProveError::Cancelled),
+        result = handle => match result {
+            Ok(Some(proof)) => proof.map_err(AsyncProveError::Prover),
+            Ok(None) => Err(AsyncProveError::Cancelled),
+            Err(e) => Err(AsyncProveError::Join(e)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+    use ark_groth16::Groth16;
+
+    type G16 = Groth16<Bls12_381>;
+
+    #[tokio::test]
+    async fn test_prove_async() {
+        let config = RngConfig::seeded(0);
+        let (pk, vk) =
+            prover::setup::<_, G16, _>(MultiplyDemoCircuit::<BlsFr> { a: None, b: None }, &config)
+                .unwrap();
+        let pk = Arc::new(pk);
+
+        let circuit = MultiplyDemoCircuit {
+            a: Some(BlsFr::from(6u8)),
+            b: Some(BlsFr::from(7u8)),
+        };
+        let proof = prove_async::<_, G16, _>(pk, circuit, config, CancellationToken::new())
+            .await
+            .unwrap();
+        assert!(prover::verify::<_, G16>(&vk, &[BlsFr::from(42u8)], &proof).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_prove_async_cancelled() {
+        let config = RngConfig::seeded(0);
+        let (pk, _) =
+            prover::setup::<_, G16, _>(MultiplyDemoCircuit::<BlsFr> { a: None, b: None }, &config)
+                .unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let circuit = MultiplyDemoCircuit {
+            a: Some(BlsFr::from(6u8)),
+            b: Some(BlsFr::from(7u8)),
+        };
+        let result = prove_async::<_, G16, _>(Arc::new(pk), circuit, config, cancel).await;
+        assert!(matches!(result, Err(AsyncProveError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_prover_error_is_reported() {
+        let config = RngConfig::seeded(0);
+        let (pk, _) =
+            prover::setup::<_, G16, _>(MultiplyDemoCircuit::<BlsFr> { a: None, b: None }, &config)
+                .unwrap();
+
+        // missing witness
+        let circuit = MultiplyDemoCircuit::<BlsFr> { a: None, b: None };
+        let result =
+            prove_async::<_, G16, _>(Arc::new(pk), circuit, config, CancellationToken::new()).await;
+        assert!(matches!(result, Err(AsyncProveError::Prover(_))));
+    }
+}
@@ -0,0 +1,348 @@
+use ark_ec::PairingEngine;
+use ark_ff::PrimeField;
+use ark_groth16::{Groth16, ProvingKey};
+use ark_snark::SNARK;
+use num_bigint::BigUint;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::multiply_demo::{MultiplyDemoCircuit, MultiplyWitness};
+use crate::persist::{self, ArtifactKind, PersistError, SerializationMode};
+use crate::rng::{RngConfig, PROVE_STREAM};
+use crate::witness_json::parse_field_element;
+
+// batch proving for the multiply circuit: every input row holds one witness (a, b),
+// each row is proven independently and a manifest records the public input c = a * b
+// and the proof file of every row, or the reason the row failed
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MultiplyRow {
+    pub a: String,
+    pub b: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    Csv,
+    Jsonl,
+}
+
+impl InputFormat {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "csv" => Some(InputFormat::Csv),
+            "jsonl" | "ndjson" => Some(InputFormat::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    pub index: usize,
+    pub ok: bool,
+    // decimal encoding of the public input
+    pub public_input: Option<String>,
+    // proof file name, relative to the manifest
+    pub proof: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Manifest {
+    pub circuit: String,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug)]
+pub enum BatchError {
+    Io(io::Error),
+    Csv(csv::Error),
+    Json(serde_json::Error),
+    Persist(PersistError),
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::Io(e) => write!(f, "i/o error: {}", e),
+            BatchError::Csv(e) => write!(f, "csv error: {}", e),
+            BatchError::Json(e) => write!(f, "json error: {}", e),
+            BatchError::Persist(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+impl From<io::Error> for BatchError {
+    fn from(e: io::Error) -> Self {
+        BatchError::Io(e)
+    }
+}
+
+impl From<csv::Error> for BatchError {
+    fn from(e: csv::Error) -> Self {
+        BatchError::Csv(e)
+    }
+}
+
+impl From<serde_json::Error> for BatchError {
+    fn from(e: serde_json::Error) -> Self {
+        BatchError::Json(e)
+    }
+}
+
+impl From<PersistError> for BatchError {
+    fn from(e: PersistError) -> Self {
+        BatchError::Persist(e)
+    }
+}
+
+// csv files need an `a,b` header line, jsonl files hold one {"a": "..", "b": ".."} per line
+pub fn read_rows<R: Read>(reader: R, format: InputFormat) -> Result<Vec<MultiplyRow>, BatchError> {
+    match format {
+        InputFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_reader(reader);
+            let mut rows = Vec::new();
+            for row in reader.deserialize() {
+                rows.push(row?);
+            }
+            Ok(rows)
+        }
+        InputFormat::Jsonl => {
+            let mut rows = Vec::new();
+            for line in BufReader::new(reader).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                rows.push(serde_json::from_str(&line)?);
+            }
+            Ok(rows)
+        }
+    }
+}
+
+pub fn read_rows_from_file<P: AsRef<Path>>(path: P) -> Result<Vec<MultiplyRow>, BatchError> {
+    let format = InputFormat::from_path(&path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "input must have a .csv or .jsonl extension",
+        )
+    })?;
+    read_rows(File::open(path)?, format)
+}
+
+// decimal only, and like a witness file at most the modulus minus one: ark-ff's own
+// parser would reduce p + 35 to 35
+pub fn parse_field<F: PrimeField>(name: &str, value: &str) -> Result<F, String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() || !trimmed.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!(
+            "`{}` is not a decimal field element: {:?}",
+            name, value
+        ));
+    }
+    parse_field_element(name, trimmed).map_err(|e| e.to_string())
+}
+
+pub fn format_field<F: PrimeField>(value: F) -> String {
+    let value: BigUint = value.into();
+    value.to_string()
+}
+
+pub fn proof_file_name(index: usize) -> String {
+    format!("proof_{:05}.bin", index)
+}
+
+fn prove_row<E: PairingEngine>(
+    pk: &ProvingKey<E>,
+    index: usize,
+    row: &MultiplyRow,
+    out_dir: &Path,
+    rng_config: &RngConfig,
+) -> Result<(String, String), String> {
+    let witness = MultiplyWitness::<E::Fr> {
+        a: parse_field("a", &row.a)?,
+        b: parse_field("b", &row.b)?,
+    };
+    let circuit = MultiplyDemoCircuit::from(witness);
+
+    // one rng stream per row so that seeded runs never reuse blinding factors
+    let mut rng = rng_config.build_stream(PROVE_STREAM + index as u64);
+    let proof = Groth16::<E>::prove(pk, circuit, &mut rng).map_err(|e| e.to_string())?;
+
+    let file_name = proof_file_name(index);
+    persist::save(
+        out_dir.join(&file_name),
+        &proof,
+        ArtifactKind::Proof,
+        SerializationMode::Compressed,
+    )
+    .map_err(|e| e.to_string())?;
+    Ok((format_field(witness.instance().c), file_name))
+}
+
+// proves every row, a failing row is recorded in the manifest and does not stop the batch
+pub fn prove_batch<E: PairingEngine>(
+    pk: &ProvingKey<E>,
+    rows: &[MultiplyRow],
+    out_dir: &Path,
+    rng_config: &RngConfig,
+    parallel: bool,
+) -> Result<Manifest, BatchError> {
+    fs::create_dir_all(out_dir)?;
+
+    let prove = |(index, row): (usize, &MultiplyRow)| {
+        let result = prove_row(pk, index, row, out_dir, rng_config);
+        match result {
+            Ok((public_input, proof)) => ManifestEntry {
+                index,
+                ok: true,
+                public_input: Some(public_input),
+                proof: Some(proof),
+                error: None,
+            },
+            Err(error) => ManifestEntry {
+                index,
+                ok: false,
+                public_input: None,
+                proof: None,
+                error: Some(error),
+            },
+        }
+    };
+    let entries: Vec<ManifestEntry> = if parallel {
+        rows.par_iter().enumerate().map(prove).collect()
+    } else {
+        rows.iter().enumerate().map(prove).collect()
+    };
+
+    let succeeded = entries.iter().filter(|e| e.ok).count();
+    let manifest = Manifest {
+        circuit: "multiply".to_string(),
+        total: entries.len(),
+        succeeded,
+        failed: entries.len() - succeeded,
+        entries,
+    };
+    fs::write(
+        out_dir.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::persist::Validation;
+    use crate::prover;
+    use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+    use ark_ff::Field;
+    use ark_groth16::Proof;
+
+    const CSV: &str = "a,b\n3,5\n 7 , 11\nnot-a-number,2\n";
+    const JSONL: &str = "{\"a\": \"3\", \"b\": \"5\"}\n\n{\"a\": \"7\", \"b\": \"11\"}\n";
+
+    #[test]
+    fn test_read_rows() {
+        let rows = read_rows(CSV.as_bytes(), InputFormat::Csv).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[1],
+            MultiplyRow {
+                a: "7".to_string(),
+                b: "11".to_string()
+            }
+        );
+
+        let rows = read_rows(JSONL.as_bytes(), InputFormat::Jsonl).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].a, "3");
+
+        assert_eq!(InputFormat::from_path("x.csv"), Some(InputFormat::Csv));
+        assert_eq!(InputFormat::from_path("x.jsonl"), Some(InputFormat::Jsonl));
+        assert_eq!(InputFormat::from_path("x.txt"), None);
+    }
+
+    #[test]
+    fn test_field_parsing() {
+        assert_eq!(parse_field::<BlsFr>("a", " 42 "), Ok(BlsFr::from(42u8)));
+        assert!(parse_field::<BlsFr>("a", "0x2a").is_err());
+        assert!(parse_field::<BlsFr>("a", "-1").is_err());
+
+        // p + 35 is not read as 35, p - 1 is the largest element
+        let modulus: BigUint = BlsFr::characteristic()
+            .iter()
+            .rev()
+            .fold(BigUint::from(0u8), |acc, limb| (acc << 64) + *limb);
+        let over = (&modulus + 35u8).to_string();
+        assert!(parse_field::<BlsFr>("a", &over).is_err());
+        let last = (&modulus - 1u8).to_string();
+        assert_eq!(parse_field::<BlsFr>("a", &last), Ok(-BlsFr::from(1u8)));
+        assert_eq!(format_field(BlsFr::from(1234u32)), "1234");
+    }
+
+    fn run_batch(parallel: bool) {
+        let config = RngConfig::seeded(3);
+        let (pk, vk) = prover::setup::<_, Groth16<Bls12_381>, _>(
+            MultiplyDemoCircuit::<BlsFr> { a: None, b: None },
+            &config,
+        )
+        .unwrap();
+
+        let rows = read_rows(CSV.as_bytes(), InputFormat::Csv).unwrap();
+        let out_dir = std::env::temp_dir().join(format!(
+            "gadgets_batch_test_{}_{}",
+            parallel,
+            std::process::id()
+        ));
+        let manifest = prove_batch(&pk, &rows, &out_dir, &config, parallel).unwrap();
+
+        assert_eq!(manifest.total, 3);
+        assert_eq!(manifest.succeeded, 2);
+        assert_eq!(manifest.failed, 1);
+        assert!(manifest.entries[2].error.as_ref().unwrap().contains("`a`"));
+
+        // the manifest on disk matches and every listed proof verifies
+        let stored: Manifest =
+            serde_json::from_slice(&fs::read(out_dir.join(MANIFEST_FILE)).unwrap()).unwrap();
+        assert_eq!(stored, manifest);
+        for entry in manifest.entries.iter().filter(|e| e.ok) {
+            let proof: Proof<Bls12_381> = persist::load(
+                out_dir.join(entry.proof.as_ref().unwrap()),
+                ArtifactKind::Proof,
+                Validation::Checked,
+            )
+            .unwrap();
+            let c: BlsFr = parse_field("c", entry.public_input.as_ref().unwrap()).unwrap();
+            assert!(Groth16::<Bls12_381>::verify(&vk, &[c], &proof).unwrap());
+        }
+        assert_eq!(manifest.entries[1].public_input.as_deref(), Some("77"));
+
+        fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn test_prove_batch_sequential() {
+        run_batch(false);
+    }
+
+    #[test]
+    fn test_prove_batch_parallel() {
+        run_batch(true);
+    }
+}
@@ -0,0 +1,435 @@
+use ark_bls12_381::Fr;
+use ark_ff::Zero;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::poseidon::PoseidonParameters;
+
+use crate::conditional_demo::enforce_if;
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+use crate::private_index_demo::one_hot;
+
+// Battleship: each player commits to their board, H(ships, salt) with a ship given by
+// the cell of its bow, y * 10 + x, and whether it lies horizontally (towards larger x)
+// or vertically (towards larger y). `BoardCircuit` proves with the commitment that the
+// board is valid, the ships of `FLEET` within the board and not overlapping; afterwards
+// `ShotCircuit` answers each shot of the opponent, a public cell, with a hit or a miss
+// that the board in the commitment has to agree with.
+//
+// The bow is a one-hot selector over the cells (`private_index_demo`), the cells a ship
+// covers are then sums of shifted selector bits, no comparisons needed: the occupancy of
+// a cell is the sum over the ships, at most one on a valid board. A ship that would
+// stick out of the board is a bow in a forbidden column (row) for its orientation.
+
+pub const BOARD_SIZE: u8 = 10;
+pub const NUM_CELLS: usize = 100;
+// the lengths of the ships
+pub const FLEET: [u8; 5] = [5, 4, 3, 3, 2];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ship {
+    pub x: u8,
+    pub y: u8,
+    pub horizontal: bool,
+}
+
+impl Ship {
+    pub fn cells(&self, len: u8) -> impl Iterator<Item = (u8, u8)> {
+        let ship = *self;
+        (0..len).map(move |i| match ship.horizontal {
+            true => (ship.x + i, ship.y),
+            false => (ship.x, ship.y + i),
+        })
+    }
+
+    fn bow(&self) -> u8 {
+        self.y * BOARD_SIZE + self.x
+    }
+}
+
+// the ships in the order of `FLEET`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Board {
+    pub ships: [Ship; 5],
+}
+
+impl Board {
+    // None if a ship sticks out of the board or two overlap
+    pub fn new(ships: [Ship; 5]) -> Option<Self> {
+        let mut occupied = [false; NUM_CELLS];
+        for (ship, &len) in ships.iter().zip(FLEET.iter()) {
+            for (x, y) in ship.cells(len) {
+                if x >= BOARD_SIZE || y >= BOARD_SIZE {
+                    return None;
+                }
+                let cell = &mut occupied[(y * BOARD_SIZE + x) as usize];
+                if *cell {
+                    return None;
+                }
+                *cell = true;
+            }
+        }
+        Some(Self { ships })
+    }
+
+    pub fn is_hit(&self, x: u8, y: u8) -> bool {
+        self.ships
+            .iter()
+            .zip(FLEET.iter())
+            .any(|(ship, &len)| ship.cells(len).any(|cell| cell == (x, y)))
+    }
+
+    fn commitment_inputs(&self, salt: Fr) -> Vec<Fr> {
+        let mut inputs: Vec<Fr> = self
+            .ships
+            .iter()
+            .flat_map(|ship| vec![Fr::from(ship.bow()), Fr::from(ship.horizontal)])
+            .collect();
+        inputs.push(salt);
+        inputs
+    }
+
+    pub fn commitment(&self, params: &PoseidonParameters<Fr>, salt: Fr) -> Fr {
+        hash_inputs(params, &self.commitment_inputs(salt))
+    }
+}
+
+struct ShipVar {
+    bow: FpVar<Fr>,
+    horizontal: Boolean<Fr>,
+    selector: Vec<Boolean<Fr>>,
+    len: u8,
+}
+
+// the private board and salt
+struct FleetVar {
+    ships: Vec<ShipVar>,
+    salt: FpVar<Fr>,
+}
+
+impl FleetVar {
+    fn new_witness(
+        cs: ConstraintSystemRef<Fr>,
+        board: Option<&Board>,
+        salt: Option<Fr>,
+    ) -> Result<Self, SynthesisError> {
+        let mut ships = Vec::with_capacity(FLEET.len());
+        for (i, &len) in FLEET.iter().enumerate() {
+            let ship = board.map(|b| b.ships[i]);
+            let bow = FpVar::new_witness(ns!(cs, "bow"), || {
+                ship.map(|s| Fr::from(s.bow()))
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let horizontal = Boolean::new_witness(ns!(cs, "horizontal"), || {
+                ship.map(|s| s.horizontal)
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let selector = one_hot(cs.clone(), &bow, NUM_CELLS)?;
+            ships.push(ShipVar {
+                bow,
+                horizontal,
+                selector,
+                len,
+            });
+        }
+        let salt = FpVar::new_witness(ns!(cs, "salt"), || {
+            salt.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        Ok(Self { ships, salt })
+    }
+
+    fn commitment(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        params: &PoseidonParameters<Fr>,
+    ) -> Result<FpVar<Fr>, SynthesisError> {
+        let mut inputs: Vec<FpVar<Fr>> = self
+            .ships
+            .iter()
+            .flat_map(|ship| vec![ship.bow.clone(), FpVar::from(ship.horizontal.clone())])
+            .collect();
+        inputs.push(self.salt.clone());
+        hash_inputs_var(cs, params, &inputs)
+    }
+
+    // no bow in a column (row) the ship would stick out of
+    fn enforce_within_board(&self) -> Result<(), SynthesisError> {
+        let zero = FpVar::zero();
+        for ship in self.ships.iter() {
+            let last = BOARD_SIZE - ship.len;
+            let mut beyond_column = FpVar::zero();
+            let mut beyond_row = FpVar::zero();
+            for (cell, bit) in ship.selector.iter().enumerate() {
+                let (x, y) = (cell as u8 % BOARD_SIZE, cell as u8 / BOARD_SIZE);
+                if x > last {
+                    beyond_column += FpVar::from(bit.clone());
+                }
+                if y > last {
+                    beyond_row += FpVar::from(bit.clone());
+                }
+            }
+            enforce_if(&ship.horizontal, &beyond_column, &zero)?;
+            enforce_if(&ship.horizontal.not(), &beyond_row, &zero)?;
+        }
+        Ok(())
+    }
+
+    // the number of ships on each cell, only meaningful within the board
+    fn occupancy(&self) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        let mut occupancy = vec![FpVar::zero(); NUM_CELLS];
+        for ship in self.ships.iter() {
+            let bit = |cell: usize| FpVar::from(ship.selector[cell].clone());
+            for (cell, count) in occupancy.iter_mut().enumerate() {
+                let mut horizontal = FpVar::zero();
+                let mut vertical = FpVar::zero();
+                for i in 0..ship.len as usize {
+                    if i <= cell {
+                        horizontal += bit(cell - i);
+                    }
+                    if i * BOARD_SIZE as usize <= cell {
+                        vertical += bit(cell - i * BOARD_SIZE as usize);
+                    }
+                }
+                *count += ship.horizontal.select(&horizontal, &vertical)?;
+            }
+        }
+        Ok(occupancy)
+    }
+}
+
+// the committed board is valid
+#[derive(Clone)]
+pub struct BoardCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub board: Option<Board>,
+    pub salt: Option<Fr>,
+}
+
+impl BoardCircuit {
+    pub fn new(params: PoseidonParameters<Fr>, board: Board, salt: Fr) -> Self {
+        Self {
+            params,
+            board: Some(board),
+            salt: Some(salt),
+        }
+    }
+
+    pub fn blank(params: PoseidonParameters<Fr>) -> Self {
+        Self {
+            params,
+            board: None,
+            salt: None,
+        }
+    }
+
+    pub fn public_inputs(&self) -> Option<Vec<Fr>> {
+        Some(vec![self.board?.commitment(&self.params, self.salt?)])
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for BoardCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let public = self.public_inputs();
+        let commitment = FpVar::new_input(ns!(cs, "commitment"), || {
+            public
+                .map(|inputs| inputs[0])
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let fleet = FleetVar::new_witness(cs.clone(), self.board.as_ref(), self.salt)?;
+        fleet
+            .commitment(cs, &self.params)?
+            .enforce_equal(&commitment)?;
+
+        fleet.enforce_within_board()?;
+        for count in fleet.occupancy()? {
+            count.mul_equals(&(&count - Fr::from(1u8)), &FpVar::zero())?;
+        }
+        Ok(())
+    }
+}
+
+// the answer to a shot at a public cell agrees with the committed board
+#[derive(Clone)]
+pub struct ShotCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub commitment: Fr,
+    pub x: u8,
+    pub y: u8,
+    pub hit: bool,
+    pub board: Option<Board>,
+    pub salt: Option<Fr>,
+}
+
+impl ShotCircuit {
+    // the honest answer
+    pub fn new(params: PoseidonParameters<Fr>, board: Board, salt: Fr, x: u8, y: u8) -> Self {
+        Self {
+            commitment: board.commitment(&params, salt),
+            params,
+            x,
+            y,
+            hit: board.is_hit(x, y),
+            board: Some(board),
+            salt: Some(salt),
+        }
+    }
+
+    pub fn blank(params: PoseidonParameters<Fr>) -> Self {
+        Self {
+            params,
+            commitment: Fr::zero(),
+            x: 0,
+            y: 0,
+            hit: false,
+            board: None,
+            salt: None,
+        }
+    }
+
+    // the commitment, the cell and the answer
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        vec![
+            self.commitment,
+            Fr::from(self.y * BOARD_SIZE + self.x),
+            Fr::from(self.hit),
+        ]
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for ShotCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let commitment = FpVar::new_input(ns!(cs, "commitment"), || Ok(self.commitment))?;
+        let cell = FpVar::new_input(ns!(cs, "cell"), || {
+            Ok(Fr::from(self.y * BOARD_SIZE + self.x))
+        })?;
+        let hit = Boolean::new_input(ns!(cs, "hit"), || Ok(self.hit))?;
+        let fleet = FleetVar::new_witness(cs.clone(), self.board.as_ref(), self.salt)?;
+        fleet
+            .commitment(cs.clone(), &self.params)?
+            .enforce_equal(&commitment)?;
+
+        // the board is valid, the occupancy of the cell is 0 or 1
+        let occupancy = fleet.occupancy()?;
+        let selector = one_hot(cs, &cell, NUM_CELLS)?;
+        let mut ships = FpVar::zero();
+        for (bit, count) in selector.iter().zip(&occupancy) {
+            ships += FpVar::from(bit.clone()) * count;
+        }
+        ships.enforce_equal(&hit.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    fn is_satisfied<C: ConstraintSynthesizer<Fr>>(circuit: C) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    fn ship(x: u8, y: u8, horizontal: bool) -> Ship {
+        Ship { x, y, horizontal }
+    }
+
+    fn ships() -> [Ship; 5] {
+        [
+            ship(0, 0, true),
+            ship(9, 2, false),
+            ship(2, 4, true),
+            ship(5, 7, false),
+            ship(8, 9, true),
+        ]
+    }
+
+    #[test]
+    fn test_board_validity() {
+        let params = poseidon_parameters();
+        let salt = Fr::from(99u8);
+        let board = Board::new(ships()).unwrap();
+        assert!(is_satisfied(BoardCircuit::new(params.clone(), board, salt)));
+
+        let invalid = [
+            // out of the board to the right, at the bottom
+            (0, ship(6, 0, true)),
+            (1, ship(9, 7, false)),
+            // over the carrier, crossing the cruiser
+            (4, ship(3, 0, false)),
+            (4, ship(3, 3, false)),
+        ];
+        for &(i, moved) in invalid.iter() {
+            let mut ships = ships();
+            ships[i] = moved;
+            assert_eq!(Board::new(ships), None);
+            let circuit = BoardCircuit::new(params.clone(), Board { ships }, salt);
+            assert!(!is_satisfied(circuit), "{:?}", moved);
+        }
+    }
+
+    #[test]
+    fn test_shots() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let board = Board::new(ships()).unwrap();
+        let salt = Fr::rand(rng);
+
+        let (board_pk, board_vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(BoardCircuit::blank(params.clone()), rng)
+                .unwrap();
+        let circuit = BoardCircuit::new(params.clone(), board, salt);
+        let commitment = circuit.public_inputs().unwrap();
+        let proof = Groth16::<Bls12_381>::prove(&board_pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&board_vk, &commitment, &proof).unwrap());
+
+        let (shot_pk, shot_vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(ShotCircuit::blank(params.clone()), rng)
+                .unwrap();
+        for &(x, y, hit) in [
+            (4, 0, true),
+            (5, 0, false),
+            (9, 5, true),
+            (9, 6, false),
+            (9, 9, true),
+        ]
+        .iter()
+        {
+            let circuit = ShotCircuit::new(params.clone(), board, salt, x, y);
+            assert_eq!(circuit.hit, hit);
+            let inputs = circuit.public_inputs();
+            assert_eq!(inputs[0], commitment[0]);
+            let proof = Groth16::<Bls12_381>::prove(&shot_pk, circuit, rng).unwrap();
+            assert!(Groth16::<Bls12_381>::verify(&shot_vk, &inputs, &proof).unwrap());
+            let mut lie = inputs.clone();
+            lie[2] = Fr::from(!hit);
+            assert!(!Groth16::<Bls12_381>::verify(&shot_vk, &lie, &proof).unwrap());
+        }
+
+        // every cell, and the lies
+        for y in 0..BOARD_SIZE {
+            for x in 0..BOARD_SIZE {
+                let mut circuit = ShotCircuit::new(params.clone(), board, salt, x, y);
+                assert!(is_satisfied(circuit.clone()));
+                circuit.hit = !circuit.hit;
+                assert!(!is_satisfied(circuit));
+            }
+        }
+
+        // answering from another board
+        let mut moved = ships();
+        moved[0] = ship(0, 1, true);
+        let mut circuit = ShotCircuit::new(params.clone(), board, salt, 0, 1);
+        circuit.board = Board::new(moved);
+        assert!(!circuit.hit);
+        circuit.hit = true;
+        assert!(!is_satisfied(circuit));
+    }
+}
@@ -0,0 +1,773 @@
+use ark_ec::PairingEngine;
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use clap::{Parser, Subcommand};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use gadgets::batch::{self, parse_field};
+use gadgets::config::{Backend, CircuitKind, Config, Curve};
+use gadgets::cubic_demo::{CubicDemoCircuit, CubicWitness};
+use gadgets::fingerprint::{circuit_fingerprint, load_for_circuit, Fingerprint, FingerprintError};
+use gadgets::formula::Formula;
+use gadgets::key_cache::{CacheId, KeyCache};
+use gadgets::lc_cost::analyze_circuit;
+use gadgets::marlin_srs::{MarlinSrsSize, UniversalSetup};
+use gadgets::matrices::circuit_matrices_table;
+use gadgets::multiply_demo::{MultiplyDemoCircuit, MultiplyWitness};
+use gadgets::persist::{self, ArtifactKind, SerializationMode, Validation};
+use gadgets::registry::{self, MarlinBackend, Scheme, SchemeVisitor, TypedBackend};
+use gadgets::rng::{RngConfig, SETUP_STREAM};
+#[cfg(feature = "insecure")]
+use gadgets::test_vectors;
+use gadgets::text::{from_text, to_text, TextFormat};
+use gadgets::visualize::{circuit_to_dot, DotOptions};
+use gadgets::witness_json::{
+    from_json, parse_field_element, CubicWitnessJson, FormulaWitnessJson, MultiplyWitnessJson,
+};
+
+// command line front end for the demo circuits. Curve, backend, circuit and key paths
+// come from the `--config` TOML file (see `gadgets::config`), flags override it.
+#[derive(Parser)]
+#[command(
+    name = "gadgets-cli",
+    about = "Setup, proving and verification for the demo circuits"
+)]
+struct Cli {
+    /// TOML file with the curve, backend, circuit and key paths (see `gadgets::config`)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// overrides the curve of the config
+    #[arg(long, global = true)]
+    curve: Option<Curve>,
+    /// overrides the backend of the config
+    #[arg(long, global = true)]
+    backend: Option<Backend>,
+    /// curve and backend at once, e.g. "marlin-bn254" (see `gadgets::registry`)
+    #[arg(long, global = true, conflicts_with_all = ["curve", "backend"])]
+    scheme: Option<Scheme>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// circuit specific setup, writes the proving and verifying key
+    Setup {
+        /// one of the demo circuits, defaults to the circuit of the config
+        #[arg(long)]
+        circuit: Option<CircuitKind>,
+        /// a circuit from an equation instead, e.g. "x^3 + x + 5 == out" (see `gadgets::formula`)
+        #[arg(long, conflicts_with = "circuit")]
+        formula: Option<String>,
+        /// directory for pk.bin and vk.bin, defaults to the paths of the config
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+        /// take the keys from this key cache, set up only if it has none for the circuit
+        /// (see `gadgets::key_cache`); `--seed` then only applies to a new setup
+        #[arg(long)]
+        cache_dir: Option<PathBuf>,
+        /// deterministic setup, for tests only
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// prove a single witness given as name=value pairs or a JSON file, prints the public inputs
+    Prove {
+        /// one of the demo circuits, defaults to the circuit of the config
+        #[arg(long)]
+        circuit: Option<CircuitKind>,
+        /// the equation the keys were set up for, values of its variables go in `--witness`
+        #[arg(long, conflicts_with = "circuit")]
+        formula: Option<String>,
+        /// proving key, defaults to the path of the config
+        #[arg(long)]
+        pk: Option<PathBuf>,
+        /// the proving key from this key cache instead, set up on first use
+        #[arg(long, conflicts_with = "pk")]
+        cache_dir: Option<PathBuf>,
+        /// a witness value, repeated for each variable
+        #[arg(long = "witness", value_name = "NAME=VALUE")]
+        witness: Vec<String>,
+        /// the witness as JSON instead, e.g. {"a": "3", "b": "5"} (see `gadgets::witness_json`)
+        #[arg(long, conflicts_with = "witness")]
+        witness_file: Option<PathBuf>,
+        /// proof file
+        #[arg(long)]
+        out: PathBuf,
+        /// deterministic randomness, for tests only
+        #[arg(long)]
+        seed: Option<u64>,
+        /// print each phase of the proof to stderr as it completes (see `gadgets::progress`)
+        #[arg(long)]
+        progress: bool,
+    },
+    /// check a proof against a verifying key
+    Verify {
+        /// the circuit the proof is checked against, refused if the verifying key records
+        /// another one or none. Without it the recorded circuit is printed to stderr and
+        /// not checked
+        #[arg(long)]
+        circuit: Option<CircuitKind>,
+        /// the equation the keys were set up for
+        #[arg(long, conflicts_with = "circuit")]
+        formula: Option<String>,
+        /// accept a key saved before circuits were recorded for --circuit/--formula
+        #[arg(long)]
+        allow_unrecorded_circuit: bool,
+        /// verifying key, defaults to the path of the config
+        #[arg(long)]
+        vk: Option<PathBuf>,
+        /// proof file written by `prove`
+        #[arg(long)]
+        proof: PathBuf,
+        /// decimal public inputs in allocation order
+        #[arg(long = "public")]
+        public: Vec<String>,
+        /// print the points of the proof, their subgroup checks, the linear combination of
+        /// the inputs and the pairing check before the verdict (see `gadgets::explain`)
+        #[arg(long)]
+        explain: bool,
+    },
+    /// write the constraint system of a circuit as a Graphviz DOT graph
+    Visualize {
+        /// one of the demo circuits, defaults to the circuit of the config
+        #[arg(long)]
+        circuit: Option<CircuitKind>,
+        /// larger circuits are sampled down to this many constraints
+        #[arg(long, default_value_t = 64)]
+        max_constraints: usize,
+        /// DOT file
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// print the A, B, C matrices of a circuit
+    Matrices {
+        /// one of the demo circuits, defaults to the circuit of the config
+        #[arg(long)]
+        circuit: Option<CircuitKind>,
+    },
+    /// print the terms of every linear combination and the constraints that could be folded
+    LcCost {
+        /// one of the demo circuits, defaults to the circuit of the config
+        #[arg(long)]
+        circuit: Option<CircuitKind>,
+    },
+    /// regenerate the known-answer vectors (Groth16, BLS12-381) checked by the test suite
+    #[cfg(feature = "insecure")]
+    GenVectors {
+        /// directory for the vectors
+        #[arg(long, default_value = "tests/data")]
+        out_dir: PathBuf,
+    },
+    /// print a key or proof file as hex or base64, the compressed bytes without the envelope
+    Encode {
+        /// pk, vk or proof
+        #[arg(long)]
+        kind: TextArtifact,
+        /// key or proof file
+        #[arg(long)]
+        input: PathBuf,
+        /// hex or base64
+        #[arg(long, default_value_t = TextFormat::Base64)]
+        format: TextFormat,
+    },
+    /// the reverse of `encode`, the text from a file into a key or proof file
+    Decode {
+        /// pk, vk or proof
+        #[arg(long)]
+        kind: TextArtifact,
+        /// text file written by `encode`
+        #[arg(long)]
+        input: PathBuf,
+        /// hex or base64
+        #[arg(long, default_value_t = TextFormat::Base64)]
+        format: TextFormat,
+        /// key or proof file
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// prove every (a, b) row of a .csv or .jsonl file for the multiply circuit
+    ProveBatch {
+        /// proving key, defaults to the path of the config
+        #[arg(long)]
+        pk: Option<PathBuf>,
+        /// .csv (header a,b) or .jsonl file of witnesses
+        #[arg(long)]
+        input: PathBuf,
+        /// directory for the proofs and their manifest
+        #[arg(long)]
+        out_dir: PathBuf,
+        /// prove the rows on all cores
+        #[arg(long)]
+        parallel: bool,
+        /// deterministic randomness, for tests only
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// list the supported curve/backend pairs; with --check set up, prove and verify the
+    /// cubic circuit with each of them
+    Schemes {
+        /// set up, prove and verify with each pair instead of only listing them
+        #[arg(long)]
+        check: bool,
+        /// deterministic randomness, for tests only
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+}
+
+// the artifacts `encode` and `decode` convert
+#[derive(Clone, Copy)]
+enum TextArtifact {
+    ProvingKey,
+    VerifyingKey,
+    Proof,
+}
+
+impl FromStr for TextArtifact {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pk" => Ok(TextArtifact::ProvingKey),
+            "vk" => Ok(TextArtifact::VerifyingKey),
+            "proof" => Ok(TextArtifact::Proof),
+            _ => Err(format!(
+                "unknown artifact {:?}, expected one of: pk, vk, proof",
+                s
+            )),
+        }
+    }
+}
+
+fn encode_file<T: CanonicalSerialize + CanonicalDeserialize>(
+    input: &Path,
+    kind: ArtifactKind,
+    format: TextFormat,
+) -> Result<String, Box<dyn Error>> {
+    let value: T = persist::load(input, kind, Validation::Checked)?;
+    Ok(to_text(&value, format))
+}
+
+fn decode_file<T: CanonicalSerialize + CanonicalDeserialize>(
+    input: &Path,
+    kind: ArtifactKind,
+    format: TextFormat,
+    out: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let value: T = from_text(&fs::read_to_string(input)?, format)?;
+    Ok(persist::save(
+        out,
+        &value,
+        kind,
+        SerializationMode::Compressed,
+    )?)
+}
+
+// seeded parameters are development parameters, a build without `insecure` refuses them
+#[cfg(feature = "insecure")]
+fn rng_config(seed: Option<u64>) -> Result<RngConfig, Box<dyn Error>> {
+    Ok(seed.map_or_else(RngConfig::default, RngConfig::seeded))
+}
+
+#[cfg(not(feature = "insecure"))]
+fn rng_config(seed: Option<u64>) -> Result<RngConfig, Box<dyn Error>> {
+    match seed {
+        None => Ok(RngConfig::default()),
+        Some(_) => Err("--seed needs a build with the `insecure` feature".into()),
+    }
+}
+
+fn parse_witness<E: PairingEngine>(
+    pairs: &[String],
+) -> Result<HashMap<String, E::Fr>, Box<dyn Error>> {
+    let mut witness = HashMap::new();
+    for pair in pairs {
+        let (name, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("witness must be NAME=VALUE, got {:?}", pair))?;
+        witness.insert(name.to_string(), parse_field_element(name, value)?);
+    }
+    Ok(witness)
+}
+
+fn witness_value<E: PairingEngine>(
+    witness: &HashMap<String, E::Fr>,
+    name: &str,
+) -> Result<E::Fr, String> {
+    witness
+        .get(name)
+        .copied()
+        .ok_or_else(|| format!("missing witness `{}`", name))
+}
+
+// the SRS is shared by all demo circuits, so it is sized for the largest of them
+fn marlin_srs_size<E: PairingEngine>() -> Result<MarlinSrsSize, Box<dyn Error>> {
+    let multiply = registry::blank::<E, MarlinBackend>(CircuitKind::Multiply);
+    let cubic = registry::blank::<E, MarlinBackend>(CircuitKind::Cubic);
+    Ok(MarlinSrsSize::of(multiply)?.max(MarlinSrsSize::of(cubic)?))
+}
+
+// reuses the SRS at `srs.universal` when it exists, otherwise generates a new one and
+// stores it there so further circuits can be indexed against the same parameters
+fn marlin_universal_setup<E: PairingEngine>(
+    config: &Config,
+    rng_config: &RngConfig,
+) -> Result<UniversalSetup<E>, Box<dyn Error>> {
+    if let Some(path) = config.srs.universal.as_ref().filter(|p| p.exists()) {
+        return Ok(UniversalSetup::load(path, Validation::Checked)?);
+    }
+    let mut rng = rng_config.build_stream(SETUP_STREAM);
+    let setup = UniversalSetup::new(marlin_srs_size::<E>()?, &mut rng)?;
+    if let Some(path) = &config.srs.universal {
+        setup.save(path, SerializationMode::Compressed)?;
+    }
+    Ok(setup)
+}
+
+fn prove<E: PairingEngine, B: TypedBackend<E>, C: ConstraintSynthesizer<E::Fr>>(
+    pk: &B::ProvingKey,
+    circuit: C,
+    rng_config: &RngConfig,
+    progress: bool,
+) -> Result<B::Proof, Box<dyn Error>> {
+    match progress {
+        true => B::prove_with_progress(pk, circuit, rng_config, &mut |p| eprintln!("{}", p)),
+        false => B::prove(pk, circuit, rng_config),
+    }
+}
+
+// the keys of the circuit `--formula` or `--circuit` names, through the key cache in
+// `cache_dir` if there is one
+fn setup_circuit<E: PairingEngine, B: TypedBackend<E>>(
+    formula: Option<&str>,
+    circuit: CircuitKind,
+    cache_dir: Option<&Path>,
+    config: &Config,
+    rng_config: &RngConfig,
+) -> Result<(B::ProvingKey, B::VerifyingKey), Box<dyn Error>> {
+    match (formula, circuit) {
+        (Some(formula), _) => {
+            let formula = Formula::<E::Fr>::parse(formula)?;
+            // a name per equation, so that several formulas can be cached side by side
+            let name = format!(
+                "formula-{}",
+                &circuit_fingerprint(formula.blank())?.to_string()[..16]
+            );
+            let blank = || B::pad(formula.blank());
+            setup_cached::<E, B, _>(&name, blank, cache_dir, config, rng_config)
+        }
+        (None, circuit) => {
+            let blank = || registry::blank::<E, B>(circuit);
+            setup_cached::<E, B, _>(&circuit.to_string(), blank, cache_dir, config, rng_config)
+        }
+    }
+}
+
+// the fingerprint of the blank `setup_circuit` sets up, padding included
+fn blank_fingerprint<E: PairingEngine, B: TypedBackend<E>>(
+    formula: Option<&str>,
+    circuit: CircuitKind,
+) -> Result<Fingerprint, Box<dyn Error>> {
+    Ok(match formula {
+        Some(formula) => circuit_fingerprint(B::pad(Formula::<E::Fr>::parse(formula)?.blank()))?,
+        None => circuit_fingerprint(registry::blank::<E, B>(circuit))?,
+    })
+}
+
+// `fingerprint::load_for_circuit` with the blank `setup_circuit` made the key from,
+// `None` without a circuit to check against
+fn load_vk_for_circuit<E: PairingEngine, B: TypedBackend<E>>(
+    path: &Path,
+    formula: Option<&Formula<E::Fr>>,
+    circuit: Option<CircuitKind>,
+) -> Result<Option<B::VerifyingKey>, FingerprintError> {
+    let kind = ArtifactKind::VerifyingKey;
+    let vk = match (formula, circuit) {
+        (Some(formula), _) => {
+            load_for_circuit(path, kind, Validation::Checked, B::pad(formula.blank()))?
+        }
+        (None, Some(circuit)) => load_for_circuit(
+            path,
+            kind,
+            Validation::Checked,
+            registry::blank::<E, B>(circuit),
+        )?,
+        (None, None) => return Ok(None),
+    };
+    Ok(Some(vk))
+}
+
+fn setup_cached<E: PairingEngine, B: TypedBackend<E>, C: ConstraintSynthesizer<E::Fr>>(
+    name: &str,
+    blank: impl Fn() -> C,
+    cache_dir: Option<&Path>,
+    config: &Config,
+    rng_config: &RngConfig,
+) -> Result<(B::ProvingKey, B::VerifyingKey), Box<dyn Error>> {
+    let setup = |circuit| {
+        B::setup_with_srs(
+            circuit,
+            || marlin_universal_setup::<E>(config, rng_config),
+            rng_config,
+        )
+    };
+    match cache_dir {
+        None => setup(blank()),
+        Some(dir) => {
+            let id = CacheId::new(name, config.curve, config.backend)?;
+            Ok(KeyCache::new(dir)?.get_or_setup(&id, blank, setup)?)
+        }
+    }
+}
+
+// goes through the registry's erased entries, the same for every curve and backend
+fn schemes(check: bool, seed: Option<u64>) -> Result<(), Box<dyn Error>> {
+    let rng_config = rng_config(seed)?;
+    for scheme in registry::all() {
+        if !check {
+            println!("{}", scheme.scheme());
+            continue;
+        }
+        let (pk, vk) = scheme.setup(CircuitKind::Cubic, &rng_config)?;
+        let (proof, public) =
+            scheme.prove(CircuitKind::Cubic, &pk, r#"{"x": "3"}"#, &rng_config)?;
+        if !scheme.verify(&vk, &public, &proof)? {
+            return Err(format!("{}: proof is invalid", scheme.scheme()).into());
+        }
+        println!("{} ok", scheme.scheme());
+    }
+    Ok(())
+}
+
+fn run<E: PairingEngine, B: TypedBackend<E>>(
+    command: Command,
+    config: &Config,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Setup {
+            circuit,
+            formula,
+            out_dir,
+            cache_dir,
+            seed,
+        } => {
+            let circuit = circuit.unwrap_or(config.circuit);
+            let (pk, vk) = setup_circuit::<E, B>(
+                formula.as_deref(),
+                circuit,
+                cache_dir.as_deref(),
+                config,
+                &rng_config(seed)?,
+            )?;
+            let (pk_path, vk_path) = match out_dir {
+                Some(dir) => {
+                    fs::create_dir_all(&dir)?;
+                    (dir.join("pk.bin"), dir.join("vk.bin"))
+                }
+                None => (
+                    config.srs.proving_key.clone(),
+                    config.srs.verifying_key.clone(),
+                ),
+            };
+            let mode = SerializationMode::Compressed;
+            persist::save(pk_path, &pk, ArtifactKind::ProvingKey, mode)?;
+            // the circuit goes into the envelope for `verify` to check
+            let fingerprint = blank_fingerprint::<E, B>(formula.as_deref(), circuit)?;
+            persist::save_for_circuit(
+                vk_path,
+                &vk,
+                ArtifactKind::VerifyingKey,
+                mode,
+                fingerprint.0,
+            )?;
+        }
+        Command::Prove {
+            circuit,
+            formula,
+            pk,
+            cache_dir,
+            witness,
+            witness_file,
+            out,
+            seed,
+            progress,
+        } => {
+            let circuit = circuit.unwrap_or(config.circuit);
+            let rng_config = rng_config(seed)?;
+            let pk: B::ProvingKey = match cache_dir {
+                Some(dir) => {
+                    setup_circuit::<E, B>(
+                        formula.as_deref(),
+                        circuit,
+                        Some(&dir),
+                        config,
+                        &rng_config,
+                    )?
+                    .0
+                }
+                None => persist::load(
+                    pk.as_ref().unwrap_or(&config.srs.proving_key),
+                    ArtifactKind::ProvingKey,
+                    Validation::Checked,
+                )?,
+            };
+            let json = witness_file.map(fs::read_to_string).transpose()?;
+            let witness = parse_witness::<E>(&witness)?;
+            let (proof, public) = match (formula, circuit) {
+                (Some(formula), _) => {
+                    let values = match &json {
+                        Some(json) => from_json::<_, FormulaWitnessJson>(json)?,
+                        None => witness.into_iter().collect(),
+                    };
+                    let circuit = Formula::<E::Fr>::parse(&formula)?.assign(&values)?;
+                    let public = circuit.public_inputs().unwrap_or_default();
+                    let circuit = B::pad(circuit);
+                    (
+                        prove::<E, B, _>(&pk, circuit, &rng_config, progress)?,
+                        public,
+                    )
+                }
+                (None, CircuitKind::Multiply) => {
+                    let witness = match &json {
+                        Some(json) => from_json::<_, MultiplyWitnessJson>(json)?,
+                        None => MultiplyWitness {
+                            a: witness_value::<E>(&witness, "a")?,
+                            b: witness_value::<E>(&witness, "b")?,
+                        },
+                    };
+                    let circuit = B::pad(MultiplyDemoCircuit::from(witness));
+                    let public = witness.instance().public_inputs();
+                    (
+                        prove::<E, B, _>(&pk, circuit, &rng_config, progress)?,
+                        public,
+                    )
+                }
+                (None, CircuitKind::Cubic) => {
+                    let witness = match &json {
+                        Some(json) => from_json::<_, CubicWitnessJson>(json)?,
+                        None => CubicWitness {
+                            x: witness_value::<E>(&witness, "x")?,
+                        },
+                    };
+                    let circuit = B::pad(CubicDemoCircuit::from(witness));
+                    let public = witness.instance().public_inputs();
+                    (
+                        prove::<E, B, _>(&pk, circuit, &rng_config, progress)?,
+                        public,
+                    )
+                }
+            };
+            persist::save(
+                &out,
+                &proof,
+                ArtifactKind::Proof,
+                SerializationMode::Compressed,
+            )?;
+            for input in public {
+                println!("{}", batch::format_field(input));
+            }
+        }
+        Command::Verify {
+            circuit,
+            formula,
+            vk,
+            proof,
+            public,
+            explain,
+            allow_unrecorded_circuit,
+        } => {
+            let vk_path = vk.as_ref().unwrap_or(&config.srs.verifying_key);
+            let formula = formula
+                .as_deref()
+                .map(Formula::<E::Fr>::parse)
+                .transpose()?;
+            let vk: B::VerifyingKey =
+                match load_vk_for_circuit::<E, B>(vk_path, formula.as_ref(), circuit) {
+                    Ok(Some(vk)) => vk,
+                    Ok(None) => {
+                        let (vk, recorded) = persist::load_with_circuit(
+                            vk_path,
+                            ArtifactKind::VerifyingKey,
+                            Validation::Checked,
+                        )?;
+                        match recorded {
+                            Some(recorded) => eprintln!("circuit {}", Fingerprint(recorded)),
+                            None => eprintln!("circuit not recorded"),
+                        }
+                        vk
+                    }
+                    Err(FingerprintError::Missing) if allow_unrecorded_circuit => {
+                        persist::load(vk_path, ArtifactKind::VerifyingKey, Validation::Checked)?
+                    }
+                    Err(e) => return Err(format!("{}: {}", vk_path.display(), e).into()),
+                };
+            let proof: B::Proof = persist::load(proof, ArtifactKind::Proof, Validation::Checked)?;
+            let inputs = public
+                .iter()
+                .map(|v| parse_field("public", v))
+                .collect::<Result<Vec<E::Fr>, _>>()?;
+            if explain {
+                print!("{}", B::explain(&vk, &inputs, &proof)?);
+            }
+            if !B::verify(&vk, &inputs, &proof)? {
+                return Err("proof is invalid".into());
+            }
+            println!("proof is valid");
+        }
+        Command::Visualize {
+            circuit,
+            max_constraints,
+            out,
+        } => {
+            let options = DotOptions {
+                max_constraints,
+                ..DotOptions::default()
+            };
+            let dot = match circuit.unwrap_or(config.circuit) {
+                CircuitKind::Multiply => {
+                    circuit_to_dot(MultiplyDemoCircuit::<E::Fr>::blank(), &options)?
+                }
+                CircuitKind::Cubic => circuit_to_dot(CubicDemoCircuit::<E::Fr>::blank(), &options)?,
+            };
+            fs::write(out, dot)?;
+        }
+        Command::Matrices { circuit } => {
+            let table = match circuit.unwrap_or(config.circuit) {
+                CircuitKind::Multiply => {
+                    circuit_matrices_table(MultiplyDemoCircuit::<E::Fr>::blank())?
+                }
+                CircuitKind::Cubic => circuit_matrices_table(CubicDemoCircuit::<E::Fr>::blank())?,
+            };
+            print!("{}", table);
+        }
+        Command::LcCost { circuit } => {
+            let report = match circuit.unwrap_or(config.circuit) {
+                CircuitKind::Multiply => analyze_circuit(MultiplyDemoCircuit::<E::Fr>::blank())?,
+                CircuitKind::Cubic => analyze_circuit(CubicDemoCircuit::<E::Fr>::blank())?,
+            };
+            print!("{}", report);
+        }
+        #[cfg(feature = "insecure")]
+        Command::GenVectors { out_dir } => {
+            test_vectors::write_vectors(&out_dir)?;
+            test_vectors::check_vectors(&out_dir)?;
+            println!("test vectors written to {}", out_dir.display());
+        }
+        Command::Encode {
+            kind,
+            input,
+            format,
+        } => {
+            let text = match kind {
+                TextArtifact::ProvingKey => {
+                    encode_file::<B::ProvingKey>(&input, ArtifactKind::ProvingKey, format)?
+                }
+                TextArtifact::VerifyingKey => {
+                    encode_file::<B::VerifyingKey>(&input, ArtifactKind::VerifyingKey, format)?
+                }
+                TextArtifact::Proof => {
+                    encode_file::<B::Proof>(&input, ArtifactKind::Proof, format)?
+                }
+            };
+            println!("{}", text);
+        }
+        Command::Decode {
+            kind,
+            input,
+            format,
+            out,
+        } => match kind {
+            TextArtifact::ProvingKey => {
+                decode_file::<B::ProvingKey>(&input, ArtifactKind::ProvingKey, format, &out)?
+            }
+            TextArtifact::VerifyingKey => {
+                decode_file::<B::VerifyingKey>(&input, ArtifactKind::VerifyingKey, format, &out)?
+            }
+            TextArtifact::Proof => {
+                decode_file::<B::Proof>(&input, ArtifactKind::Proof, format, &out)?
+            }
+        },
+        Command::ProveBatch {
+            pk,
+            input,
+            out_dir,
+            parallel,
+            seed,
+        } => {
+            let pk: B::ProvingKey = persist::load(
+                pk.as_ref().unwrap_or(&config.srs.proving_key),
+                ArtifactKind::ProvingKey,
+                Validation::Checked,
+            )?;
+            let rows = batch::read_rows_from_file(&input)?;
+            let manifest = B::prove_batch(&pk, &rows, &out_dir, &rng_config(seed)?, parallel)?;
+            println!(
+                "proved {}/{} rows, manifest written to {}",
+                manifest.succeeded,
+                manifest.total,
+                out_dir.join(batch::MANIFEST_FILE).display()
+            );
+            for entry in manifest.entries.iter().filter(|e| !e.ok) {
+                eprintln!(
+                    "row {}: {}",
+                    entry.index,
+                    entry.error.as_deref().unwrap_or_default()
+                );
+            }
+        }
+        Command::Schemes { check, seed } => schemes(check, seed)?,
+    }
+    Ok(())
+}
+
+// `run` with the curve and backend of the configured scheme
+struct Run<'a> {
+    command: Command,
+    config: &'a Config,
+}
+
+impl SchemeVisitor for Run<'_> {
+    type Output = Result<(), Box<dyn Error>>;
+
+    fn visit<E: PairingEngine, B: TypedBackend<E>>(self) -> Self::Output {
+        run::<E, B>(self.command, self.config)
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let result = cli
+        .config
+        .as_ref()
+        .map_or_else(|| Ok(Config::default()), Config::load)
+        .map_err(Into::into)
+        .and_then(|mut config| {
+            config.curve = cli.curve.unwrap_or(config.curve);
+            config.backend = cli.backend.unwrap_or(config.backend);
+            if let Some(scheme) = cli.scheme {
+                config.curve = scheme.curve;
+                config.backend = scheme.backend;
+            }
+            if let Some(threads) = config.threads {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build_global()?;
+            }
+            let scheme = Scheme::new(config.backend, config.curve);
+            registry::dispatch(
+                scheme,
+                Run {
+                    command: cli.command,
+                    config: &config,
+                },
+            )
+        });
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,194 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_std::marker::PhantomData;
+
+// Integer arithmetic built from gates on single bits, the way hash functions like SHA-256
+// are modeled: a number is a vector of `Boolean`s, least significant bit first, and
+// addition is a chain of full adders. Every xor, and, or of two allocated bits costs one
+// constraint where a whole field addition costs none, which is why circuits stay in the
+// field where they can. `UInt32::addmany` does the same addition with field
+// arithmetic on the packed bits, the tests check both agree.
+
+// the sum bit and the carry of a + b + carry_in
+pub fn full_adder<F: PrimeField>(
+    a: &Boolean<F>,
+    b: &Boolean<F>,
+    carry_in: &Boolean<F>,
+) -> Result<(Boolean<F>, Boolean<F>), SynthesisError> {
+    let a_xor_b = a.xor(b)?;
+    let sum = a_xor_b.xor(carry_in)?;
+    let carry = a.and(b)?.or(&a_xor_b.and(carry_in)?)?;
+    Ok((sum, carry))
+}
+
+// a + b + carry_in for two numbers of the same width, the sum bits and the carry out
+fn add_with_carry<F: PrimeField>(
+    a: &[Boolean<F>],
+    b: &[Boolean<F>],
+    carry_in: Boolean<F>,
+) -> Result<(Vec<Boolean<F>>, Boolean<F>), SynthesisError> {
+    assert_eq!(a.len(), b.len(), "the operands have different widths");
+    let mut carry = carry_in;
+    let mut sum = Vec::with_capacity(a.len());
+    for (a, b) in a.iter().zip(b) {
+        let (bit, next) = full_adder(a, b, &carry)?;
+        sum.push(bit);
+        carry = next;
+    }
+    Ok((sum, carry))
+}
+
+// a + b, one bit wider than the operands
+pub fn ripple_carry_add<F: PrimeField>(
+    a: &[Boolean<F>],
+    b: &[Boolean<F>],
+) -> Result<Vec<Boolean<F>>, SynthesisError> {
+    let (mut sum, carry) = add_with_carry(a, b, Boolean::FALSE)?;
+    sum.push(carry);
+    Ok(sum)
+}
+
+// a < b. a + !b + 1 is a - b plus 2^n, it carries out exactly when a >= b.
+pub fn less_than<F: PrimeField>(
+    a: &[Boolean<F>],
+    b: &[Boolean<F>],
+) -> Result<Boolean<F>, SynthesisError> {
+    let not_b: Vec<_> = b.iter().map(Boolean::not).collect();
+    let (_, carry) = add_with_carry(a, &not_b, Boolean::TRUE)?;
+    Ok(carry.not())
+}
+
+// the `num_bits` low bits of `value`, unsatisfiable if it does not fit
+pub fn to_bits<F: PrimeField>(
+    value: &FpVar<F>,
+    num_bits: usize,
+) -> Result<Vec<Boolean<F>>, SynthesisError> {
+    let mut bits = value.to_bits_le()?;
+    let high = bits.split_off(num_bits);
+    Boolean::kary_or(&high)?.enforce_equal(&Boolean::FALSE)?;
+    Ok(bits)
+}
+
+// knows a and b of 32 bits with a + b == c, for a public c of 33 bits
+#[derive(Clone, Copy)]
+pub struct AdderCircuit<F: PrimeField> {
+    pub a: Option<u32>,
+    pub b: Option<u32>,
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField> AdderCircuit<F> {
+    pub fn new(a: u32, b: u32) -> Self {
+        Self {
+            a: Some(a),
+            b: Some(b),
+            _field: PhantomData,
+        }
+    }
+
+    // no assignment, for setup and for counting constraints
+    pub fn blank() -> Self {
+        Self {
+            a: None,
+            b: None,
+            _field: PhantomData,
+        }
+    }
+
+    pub fn public_inputs(&self) -> Option<Vec<F>> {
+        Some(vec![F::from(self.a? as u64 + self.b? as u64)])
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for AdderCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let c = self.public_inputs().map(|inputs| inputs[0]);
+        let a = UInt32::new_witness(ns!(cs, "a"), || {
+            self.a.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let b = UInt32::new_witness(ns!(cs, "b"), || {
+            self.b.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let c = FpVar::new_input(ns!(cs, "c"), || c.ok_or(SynthesisError::AssignmentMissing))?;
+        let sum = ripple_carry_add(&a.to_bits_le(), &b.to_bits_le())?;
+        Boolean::le_bits_to_fp_var(&sum)?.enforce_equal(&c)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+    use ark_std::rand::Rng;
+
+    #[test]
+    fn test_full_adder() {
+        for bits in 0..8u8 {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let [a, b, c] = [bits & 1 == 1, bits & 2 == 2, bits & 4 == 4];
+            let var = |v| Boolean::new_witness(cs.clone(), || Ok(v)).unwrap();
+            let (sum, carry) = full_adder(&var(a), &var(b), &var(c)).unwrap();
+            let total = a as u8 + b as u8 + c as u8;
+            assert_eq!(sum.value().unwrap(), total & 1 == 1);
+            assert_eq!(carry.value().unwrap(), total >= 2);
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_against_uint32() {
+        let rng = &mut ark_std::test_rng();
+        for _ in 0..20 {
+            let (a, b): (u32, u32) = (rng.gen(), rng.gen());
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let a_var = UInt32::new_witness(cs.clone(), || Ok(a)).unwrap();
+            let b_var = UInt32::new_witness(cs.clone(), || Ok(b)).unwrap();
+
+            let sum = ripple_carry_add(&a_var.to_bits_le(), &b_var.to_bits_le()).unwrap();
+            let wrapping = UInt32::addmany(&[a_var.clone(), b_var.clone()]).unwrap();
+            assert_eq!(
+                UInt32::from_bits_le(&sum[..32]).value().unwrap(),
+                wrapping.value().unwrap()
+            );
+            assert_eq!(sum[32].value().unwrap(), a.checked_add(b).is_none());
+
+            let less = less_than(&a_var.to_bits_le(), &b_var.to_bits_le()).unwrap();
+            assert_eq!(less.value().unwrap(), a < b);
+            let equal = less_than(&a_var.to_bits_le(), &a_var.to_bits_le()).unwrap();
+            assert!(!equal.value().unwrap());
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_to_bits() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let value = FpVar::new_witness(cs.clone(), || Ok(Fr::from(255u16))).unwrap();
+        to_bits(&value, 8).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        let value = FpVar::new_witness(cs.clone(), || Ok(Fr::from(256u16))).unwrap();
+        to_bits(&value, 8).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_adder_circuit() {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(AdderCircuit::<Fr>::blank(), rng).unwrap();
+        let circuit = AdderCircuit::new(u32::MAX, 7);
+        let inputs = circuit.public_inputs().unwrap();
+        assert_eq!(inputs, vec![Fr::from(u32::MAX as u64 + 7)]);
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+        // the sum wrapped around 32 bits
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &[Fr::from(6u8)], &proof).unwrap());
+    }
+}
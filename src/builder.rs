@@ -0,0 +1,22 @@
+use ark_std::fmt;
+
+// why a circuit builder refused to build, see e.g. `CubicDemoCircuit::builder`. Catching
+// these before proving saves a proof that would never verify.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    // a value the proof needs was not given
+    Missing(&'static str),
+    // the values do not satisfy the relation, named as it is checked
+    Unsatisfied(&'static str),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Missing(name) => write!(f, "no value for `{}`", name),
+            BuildError::Unsatisfied(relation) => write!(f, "`{}` does not hold", relation),
+        }
+    }
+}
+
+impl ark_std::error::Error for BuildError {}
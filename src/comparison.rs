@@ -0,0 +1,178 @@
+use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+use ark_groth16::Groth16;
+use ark_marlin::Marlin;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::marlin_pc::MarlinKZG10;
+use ark_snark::SNARK;
+use ark_std::rand::{CryptoRng, RngCore};
+use blake2::Blake2s;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::cubic_demo::CubicDemoCircuit;
+use crate::marlin_srs::MarlinSrsSize;
+use crate::sizes::{artifact_sizes, ArtifactSizes};
+use crate::Error;
+
+type MarlinInst = Marlin<BlsFr, MarlinKZG10<Bls12_381, DensePolynomial<BlsFr>>, Blake2s>;
+
+// measurements for one backend running the cubic circuit end to end
+#[derive(Clone, Debug)]
+pub struct BackendReport {
+    pub backend: &'static str,
+    // Marlin only: the circuit independent SRS generation
+    pub universal_setup_time: Option<Duration>,
+    // Groth16: circuit specific setup, Marlin: indexing
+    pub setup_time: Duration,
+    pub prove_time: Duration,
+    pub verify_time: Duration,
+    pub sizes: ArtifactSizes,
+    pub verified: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct ComparisonReport {
+    pub groth16: BackendReport,
+    pub marlin: BackendReport,
+}
+
+impl fmt::Display for ComparisonReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<10} {:>14} {:>12} {:>12} {:>12} {:>10} {:>10} {:>10}",
+            "backend",
+            "universal(ms)",
+            "setup(ms)",
+            "prove(ms)",
+            "verify(ms)",
+            "proof(B)",
+            "pk(B)",
+            "vk(B)"
+        )?;
+        for report in [&self.groth16, &self.marlin].iter() {
+            let universal = report
+                .universal_setup_time
+                .map_or_else(|| "-".to_string(), |t| t.as_millis().to_string());
+            writeln!(
+                f,
+                "{:<10} {:>14} {:>12} {:>12} {:>12} {:>10} {:>10} {:>10}",
+                report.backend,
+                universal,
+                report.setup_time.as_millis(),
+                report.prove_time.as_millis(),
+                report.verify_time.as_millis(),
+                report.sizes.proof.compressed,
+                report.sizes.proving_key.compressed,
+                report.sizes.verifying_key.compressed,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+// public output of the cubic circuit x^3 + x + 5
+fn cubic_output(x: BlsFr) -> BlsFr {
+    x * x * x + x + BlsFr::from(5u32)
+}
+
+pub fn groth16_cubic_report<R: RngCore + CryptoRng>(
+    x: BlsFr,
+    rng: &mut R,
+) -> Result<BackendReport, Error> {
+    let (keys, setup_time) = timed(|| {
+        Groth16::<Bls12_381>::circuit_specific_setup(CubicDemoCircuit::<BlsFr> { x: None }, rng)
+    });
+    let (pk, vk) = keys?;
+    let (proof, prove_time) =
+        timed(|| Groth16::<Bls12_381>::prove(&pk, CubicDemoCircuit { x: Some(x) }, rng));
+    let proof = proof?;
+    let (verified, verify_time) =
+        timed(|| Groth16::<Bls12_381>::verify(&vk, &[cubic_output(x)], &proof));
+
+    Ok(BackendReport {
+        backend: "groth16",
+        universal_setup_time: None,
+        setup_time,
+        prove_time,
+        verify_time,
+        sizes: artifact_sizes(&pk, &vk, &proof),
+        verified: verified?,
+    })
+}
+
+pub fn marlin_cubic_report<R: RngCore + CryptoRng>(
+    x: BlsFr,
+    rng: &mut R,
+) -> Result<BackendReport, Error> {
+    // the smallest SRS the cubic circuit fits in
+    let size = MarlinSrsSize::of(CubicDemoCircuit::<BlsFr> { x: None })?;
+    let (srs, universal_setup_time) = timed(|| {
+        MarlinInst::universal_setup(
+            size.num_constraints,
+            size.num_variables,
+            size.num_non_zero,
+            rng,
+        )
+    });
+    let srs = srs.map_err(|e| format!("{:?}", e))?;
+    let (keys, setup_time) = timed(|| MarlinInst::index(&srs, CubicDemoCircuit { x: None }));
+    let (pk, vk) = keys.map_err(|e| format!("{:?}", e))?;
+    let (proof, prove_time) =
+        timed(|| MarlinInst::prove(&pk, CubicDemoCircuit { x: Some(x) }, rng));
+    let proof = proof.map_err(|e| format!("{:?}", e))?;
+    let (verified, verify_time) =
+        timed(|| MarlinInst::verify(&vk, &[cubic_output(x)], &proof, rng));
+
+    Ok(BackendReport {
+        backend: "marlin",
+        universal_setup_time: Some(universal_setup_time),
+        setup_time,
+        prove_time,
+        verify_time,
+        sizes: artifact_sizes(&pk, &vk, &proof),
+        verified: verified.map_err(|e| format!("{:?}", e))?,
+    })
+}
+
+// run the cubic circuit with witness x through both backends
+pub fn compare_cubic<R: RngCore + CryptoRng>(
+    x: BlsFr,
+    rng: &mut R,
+) -> Result<ComparisonReport, Error> {
+    Ok(ComparisonReport {
+        groth16: groth16_cubic_report(x, rng)?,
+        marlin: marlin_cubic_report(x, rng)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compare_cubic() {
+        let rng = &mut ark_std::test_rng();
+        let report = compare_cubic(BlsFr::from(3u8), rng).unwrap();
+
+        assert!(report.groth16.verified);
+        assert!(report.marlin.verified);
+        assert!(report.groth16.universal_setup_time.is_none());
+        assert!(report.marlin.universal_setup_time.is_some());
+
+        // Groth16 proofs are 2 G1 + 1 G2 compressed elements
+        assert_eq!(report.groth16.sizes.proof.compressed, 48 + 96 + 48);
+        // Marlin pays for universality with much larger proofs
+        assert!(report.marlin.sizes.proof.compressed > report.groth16.sizes.proof.compressed);
+
+        let table = report.to_string();
+        assert!(table.contains("groth16"));
+        assert!(table.contains("marlin"));
+    }
+}
@@ -0,0 +1,138 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+
+use crate::cubic_demo::CubicWitness;
+
+// A circuit has the same constraints whatever the witness, so an `if` in Rust on a
+// witness value does not make a constraint optional: setup and prover would build
+// different circuits. A constraint that only holds in one branch is enforced for both,
+// multiplied by the flag of its branch, flag * (lhs - rhs) == 0, which any assignment
+// satisfies when the flag is off.
+
+// lhs == rhs when `flag` is set, nothing otherwise. One constraint.
+pub fn enforce_if<F: PrimeField>(
+    flag: &Boolean<F>,
+    lhs: &FpVar<F>,
+    rhs: &FpVar<F>,
+) -> Result<(), SynthesisError> {
+    FpVar::from(flag.clone()).mul_equals(&(lhs - rhs), &FpVar::zero())
+}
+
+// knows x with x^3 + x + 5 == out or x^2 == out, without revealing which. Both
+// relations are built, the private flag `cubic` picks the one enforced.
+#[derive(Clone, Copy)]
+pub struct OptionalBranchCircuit<F: PrimeField> {
+    pub x: Option<F>,
+    pub cubic: Option<bool>,
+}
+
+impl<F: PrimeField> OptionalBranchCircuit<F> {
+    pub fn new(x: F, cubic: bool) -> Self {
+        Self {
+            x: Some(x),
+            cubic: Some(cubic),
+        }
+    }
+
+    // no assignment, for setup and for counting constraints
+    pub fn blank() -> Self {
+        Self {
+            x: None,
+            cubic: None,
+        }
+    }
+
+    // out, from the branch taken
+    pub fn public_inputs(&self) -> Option<Vec<F>> {
+        let x = self.x?;
+        let out = match self.cubic? {
+            true => CubicWitness { x }.instance().out,
+            false => x.square(),
+        };
+        Some(vec![out])
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for OptionalBranchCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let public = self.public_inputs();
+
+        let x = FpVar::new_witness(ns!(cs, "x"), || {
+            self.x.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let cubic = Boolean::new_witness(ns!(cs, "cubic"), || {
+            self.cubic.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let out = FpVar::new_input(ns!(cs, "out"), || {
+            public
+                .map(|inputs| inputs[0])
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let square = x.square()?;
+        let cubic_out = &square * &x + &x + F::from(5u8);
+        enforce_if(&cubic, &cubic_out, &out)?;
+        enforce_if(&cubic.not(), &square, &out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    fn num_constraints(circuit: OptionalBranchCircuit<Fr>) -> usize {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        cs.num_constraints()
+    }
+
+    #[test]
+    fn test_enforce_if() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let one = FpVar::new_witness(cs.clone(), || Ok(Fr::from(1u8))).unwrap();
+        let two = FpVar::new_witness(cs.clone(), || Ok(Fr::from(2u8))).unwrap();
+        let off = Boolean::new_witness(cs.clone(), || Ok(false)).unwrap();
+        enforce_if(&off, &one, &two).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        let on = Boolean::new_witness(cs.clone(), || Ok(true)).unwrap();
+        enforce_if(&on, &one, &one).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        enforce_if(&on, &one, &two).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_optional_branch() {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(OptionalBranchCircuit::blank(), rng)
+                .unwrap();
+
+        // the same keys for either branch, and the same shape
+        let x = Fr::from(3u8);
+        let cubic = OptionalBranchCircuit::new(x, true);
+        let square = OptionalBranchCircuit::new(x, false);
+        assert_eq!(num_constraints(cubic), num_constraints(square));
+        for circuit in [cubic, square].iter() {
+            let inputs = circuit.public_inputs().unwrap();
+            let proof = Groth16::<Bls12_381>::prove(&pk, *circuit, rng).unwrap();
+            assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+        }
+        assert_eq!(cubic.public_inputs(), Some(vec![Fr::from(35u8)]));
+        assert_eq!(square.public_inputs(), Some(vec![Fr::from(9u8)]));
+
+        // a proof for x^2 == 9 says nothing about 35
+        let proof = Groth16::<Bls12_381>::prove(&pk, square, rng).unwrap();
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &[Fr::from(35u8)], &proof).unwrap());
+    }
+}
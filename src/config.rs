@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// runtime selection of curve, proving system and circuit, loaded from a TOML file such as
+//
+//   curve = "bn254"
+//   backend = "groth16"
+//   circuit = "cubic"
+//   threads = 4
+//
+//   [srs]
+//   proving_key = "keys/pk.bin"
+//   verifying_key = "keys/vk.bin"
+//
+// every key is optional, missing ones fall back to `Config::default()`
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Curve {
+    #[default]
+    #[serde(rename = "bls12-381")]
+    Bls12_381,
+    #[serde(rename = "bn254")]
+    Bn254,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Groth16,
+    Marlin,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CircuitKind {
+    // a * b == c, witnesses `a` and `b`
+    #[default]
+    Multiply,
+    // x^3 + x + 5 == out, witness `x`
+    Cubic,
+}
+
+// locations of the setup artifacts, `universal` is the Marlin SRS
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SrsPaths {
+    pub proving_key: PathBuf,
+    pub verifying_key: PathBuf,
+    pub universal: Option<PathBuf>,
+}
+
+impl Default for SrsPaths {
+    fn default() -> Self {
+        Self {
+            proving_key: PathBuf::from("pk.bin"),
+            verifying_key: PathBuf::from("vk.bin"),
+            universal: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub curve: Curve,
+    pub backend: Backend,
+    pub circuit: CircuitKind,
+    pub srs: SrsPaths,
+    // worker threads for parallel proving, `None` uses one per core
+    pub threads: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "cannot read config: {}", e),
+            ConfigError::Parse(e) => write!(f, "invalid config: {}", e),
+            ConfigError::Invalid(msg) => write!(f, "invalid config: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> Self {
+        ConfigError::Parse(e)
+    }
+}
+
+impl Config {
+    pub fn from_toml(s: &str) -> Result<Self, ConfigError> {
+        let config: Config = toml::from_str(s)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.threads == Some(0) {
+            return Err(ConfigError::Invalid("`threads` must be at least 1".into()));
+        }
+        Ok(())
+    }
+}
+
+// names as used in the TOML file, so command line overrides accept the same values
+macro_rules! impl_name {
+    ($ty:ty, $($variant:path => $name:literal),+) => {
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    $($variant => f.write_str($name),)+
+                }
+            }
+        }
+
+        impl FromStr for $ty {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($name => Ok($variant),)+
+                    _ => Err(format!(
+                        "unknown {} {:?}, expected one of: {}",
+                        stringify!($ty).to_lowercase(),
+                        s,
+                        [$($name),+].join(", ")
+                    )),
+                }
+            }
+        }
+    };
+}
+
+impl_name!(Curve, Curve::Bls12_381 => "bls12-381", Curve::Bn254 => "bn254");
+impl_name!(Backend, Backend::Groth16 => "groth16", Backend::Marlin => "marlin");
+impl_name!(CircuitKind, CircuitKind::Multiply => "multiply", CircuitKind::Cubic => "cubic");
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::from_toml(
+            r#"
+            curve = "bn254"
+            backend = "marlin"
+            circuit = "cubic"
+            threads = 2
+
+            [srs]
+            proving_key = "keys/pk.bin"
+            universal = "keys/srs.bin"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.curve, Curve::Bn254);
+        assert_eq!(config.backend, Backend::Marlin);
+        assert_eq!(config.circuit, CircuitKind::Cubic);
+        assert_eq!(config.threads, Some(2));
+        assert_eq!(config.srs.proving_key, PathBuf::from("keys/pk.bin"));
+        // missing keys keep their defaults
+        assert_eq!(config.srs.verifying_key, PathBuf::from("vk.bin"));
+        assert_eq!(config.srs.universal, Some(PathBuf::from("keys/srs.bin")));
+
+        assert_eq!(Config::from_toml("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(matches!(
+            Config::from_toml("curve = \"secp256k1\""),
+            Err(ConfigError::Parse(_))
+        ));
+        assert!(matches!(
+            Config::from_toml("curves = \"bn254\""),
+            Err(ConfigError::Parse(_))
+        ));
+        assert!(matches!(
+            Config::from_toml("threads = 0"),
+            Err(ConfigError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        for curve in [Curve::Bls12_381, Curve::Bn254].iter() {
+            assert_eq!(curve.to_string().parse::<Curve>(), Ok(*curve));
+        }
+        assert_eq!("marlin".parse::<Backend>(), Ok(Backend::Marlin));
+        assert!("plonk"
+            .parse::<Backend>()
+            .unwrap_err()
+            .contains("groth16, marlin"));
+        assert_eq!(CircuitKind::Cubic.to_string(), "cubic");
+    }
+}
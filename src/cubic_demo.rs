@@ -1,15 +1,101 @@
 use ark_ff::Field;
 use ark_relations::{
-    lc,
-    r1cs::{ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError},
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
 };
 
+use crate::builder::BuildError;
+use crate::expr::{Compiler, Expr};
+
 // verifier wants to prove that she knows some x such that x^3 + x + 5 == 35
 // or more general x^3 + x + 5 == (a public value)
-struct CubicDemoCircuit<F: Field> {
+pub struct CubicDemoCircuit<F: Field> {
     pub x: Option<F>,
 }
 
+// what the verifier sees: the public value out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CubicInstance<F: Field> {
+    pub out: F,
+}
+
+impl<F: Field> CubicInstance<F> {
+    pub fn public_inputs(&self) -> Vec<F> {
+        vec![self.out]
+    }
+}
+
+// what only the prover knows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CubicWitness<F: Field> {
+    pub x: F,
+}
+
+impl<F: Field> CubicWitness<F> {
+    pub fn instance(&self) -> CubicInstance<F> {
+        CubicInstance {
+            out: self.x.square() * self.x + self.x + F::from(5u32),
+        }
+    }
+}
+
+impl<F: Field> CubicDemoCircuit<F> {
+    // no assignment, for setup and for counting constraints
+    pub fn blank() -> Self {
+        Self { x: None }
+    }
+}
+
+impl<F: Field> From<CubicWitness<F>> for CubicDemoCircuit<F> {
+    fn from(witness: CubicWitness<F>) -> Self {
+        Self { x: Some(witness.x) }
+    }
+}
+
+impl<F: Field> CubicDemoCircuit<F> {
+    // CubicDemoCircuit::builder().x(3u64).out(35u64).build()?
+    pub fn builder() -> CubicBuilder<F> {
+        CubicBuilder::default()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CubicBuilder<F: Field> {
+    x: Option<F>,
+    out: Option<F>,
+}
+
+impl<F: Field> CubicBuilder<F> {
+    pub fn x(mut self, x: impl Into<F>) -> Self {
+        self.x = Some(x.into());
+        self
+    }
+
+    // optional, the public value the proof is for; `build` then checks the relation
+    pub fn out(mut self, out: impl Into<F>) -> Self {
+        self.out = Some(out.into());
+        self
+    }
+
+    // the circuit for proving
+    pub fn build(self) -> Result<CubicDemoCircuit<F>, BuildError> {
+        let witness = CubicWitness {
+            x: self.x.ok_or(BuildError::Missing("x"))?,
+        };
+        match self.out {
+            Some(out) if witness.instance().out != out => {
+                Err(BuildError::Unsatisfied("x^3 + x + 5 == out"))
+            }
+            _ => Ok(witness.into()),
+        }
+    }
+
+    // the circuit for setup, any values given are ignored
+    pub fn build_setup(self) -> CubicDemoCircuit<F> {
+        CubicDemoCircuit::blank()
+    }
+}
+
 impl<F: Field> ConstraintSynthesizer<F> for CubicDemoCircuit<F> {
     fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
         // With two intermediate variables sym_1, y,
@@ -19,41 +105,70 @@ impl<F: Field> ConstraintSynthesizer<F> for CubicDemoCircuit<F> {
         // y + x = tmp_2
         // tmp_2 + 5 = out
         // so R1CS  w = [one, x, tmp_1, y, tmp_2, out]
+        // tmp_2 is not allocated on its own, the compiler folds it into the linear
+        // combination of the last constraint.
 
-        // allocate witness x
-        let x_val = self.x;
-        let x = cs.new_witness_variable(|| x_val.ok_or(SynthesisError::AssignmentMissing))?;
-
-        // x * x = tmp_1, allocate tmp_1
-        let tmp_1_val = x_val.map(|e| e.square());
-        let tmp_1 =
-            cs.new_witness_variable(|| tmp_1_val.ok_or(SynthesisError::AssignmentMissing))?;
-        // enforce constraints x * x = tmp_1
-        cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + tmp_1)?;
-
-        // tmp_1 * x = y, allocate y
-        let x_cubed_val = tmp_1_val.map(|mut e| {
-            e.mul_assign(&x_val.unwrap());
-            e
-        });
-        let x_cubed =
-            cs.new_witness_variable(|| x_cubed_val.ok_or(SynthesisError::AssignmentMissing))?;
-        // enforce constraints tmp_1 * x = y
-        cs.enforce_constraint(lc!() + tmp_1, lc!() + x, lc!() + x_cubed)?;
-
-        // allocate the public output variable out
-        let out = cs.new_input_variable(|| {
-            let mut tmp = x_cubed_val.unwrap();
-            tmp.add_assign(&x_val.unwrap());
-            tmp.add_assign(F::from(5u32));
-            Ok(tmp)
-        })?;
-        // enforce constraints tmp_2 + 5 = out
-        cs.enforce_constraint(
-            lc!() + x_cubed + x + (F::from(5u32), ConstraintSystem::<F>::one()),
-            lc!() + ConstraintSystem::<F>::one(),
-            lc!() + out,
-        )?;
+        // every value is derived from the witness, and missing as a whole without it
+        let witness = self.x.map(|x| CubicWitness { x });
+
+        let mut c = Compiler::new(cs.clone());
+        let x = c.witness("x", self.x)?;
+        let tmp_1 = {
+            let _ns = ns!(cs, "x * x = tmp_1");
+            c.define("tmp_1", x.clone() * x.clone())?
+        };
+        let y = {
+            let _ns = ns!(cs, "tmp_1 * x = y");
+            c.define("y", tmp_1 * x.clone())?
+        };
+        let out = c.input("out", witness.map(|w| w.instance().out))?;
+
+        let _ns = ns!(cs, "(y + x + 5) * 1 = out");
+        c.enforce((y + x + Expr::constant(5u32)).equals(out))?;
+
+        Ok(())
+    }
+}
+
+// the same relation in two constraints instead of three. An R1CS constraint is a product
+// of two linear combinations equal to a third, and additions are free inside any of
+// them, so x^3 + x + 5 == out is
+//   x * x = tmp_1
+//   (tmp_1 + 1) * x = out - 5
+// the second folds the addition of x into the product (x^3 + x == (x^2 + 1) x) and the
+// constant into the right-hand side. y and its constraint are gone: w = [one, out, x,
+// tmp_1]. Proofs of the two circuits verify against the same public input, but each
+// needs its own setup.
+pub struct CubicDemoCircuitOptimized<F: Field> {
+    pub x: Option<F>,
+}
+
+impl<F: Field> CubicDemoCircuitOptimized<F> {
+    pub fn blank() -> Self {
+        Self { x: None }
+    }
+}
+
+impl<F: Field> From<CubicWitness<F>> for CubicDemoCircuitOptimized<F> {
+    fn from(witness: CubicWitness<F>) -> Self {
+        Self { x: Some(witness.x) }
+    }
+}
+
+impl<F: Field> ConstraintSynthesizer<F> for CubicDemoCircuitOptimized<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let witness = self.x.map(|x| CubicWitness { x });
+
+        let mut c = Compiler::new(cs.clone());
+        let x = c.witness("x", self.x)?;
+        let tmp_1 = {
+            let _ns = ns!(cs, "x * x = tmp_1");
+            c.define("tmp_1", x.clone() * x.clone())?
+        };
+        let out = c.input("out", witness.map(|w| w.instance().out))?;
+
+        let _ns = ns!(cs, "(tmp_1 + 1) * x = out - 5");
+        c.enforce(((tmp_1 + Expr::constant(1u32)) * x).equals(out - Expr::constant(5u32)))?;
 
         Ok(())
     }
@@ -64,8 +179,56 @@ mod test {
     use super::*;
     use ark_bls12_381::{Bls12_381, Fr as BlsFr};
     use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
     use ark_snark::SNARK;
 
+    #[test]
+    fn test_instance_witness() {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(CubicDemoCircuit::<BlsFr>::blank(), rng)
+                .unwrap();
+
+        let witness = CubicWitness {
+            x: BlsFr::from(3u8),
+        };
+        let instance = witness.instance();
+        assert_eq!(instance.out, BlsFr::from(35u8));
+        let proof = Groth16::<Bls12_381>::prove(&pk, CubicDemoCircuit::from(witness), rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &instance.public_inputs(), &proof).unwrap());
+
+        // without an assignment, synthesizing for proving reports the missing assignment
+        // instead of panicking
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        assert_eq!(
+            CubicDemoCircuit::blank().generate_constraints(cs),
+            Err(SynthesisError::AssignmentMissing)
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let rng = &mut ark_std::test_rng();
+        let builder = CubicDemoCircuit::<BlsFr>::builder().x(3u64);
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(builder.build_setup(), rng).unwrap();
+        let proof = Groth16::<Bls12_381>::prove(&pk, builder.build().unwrap(), rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &[BlsFr::from(35u8)], &proof).unwrap());
+
+        assert!(builder.out(35u64).build().is_ok());
+        assert_eq!(
+            builder.out(36u64).build().err(),
+            Some(BuildError::Unsatisfied("x^3 + x + 5 == out"))
+        );
+        assert_eq!(
+            CubicDemoCircuit::<BlsFr>::builder()
+                .out(35u64)
+                .build()
+                .err(),
+            Some(BuildError::Missing("x"))
+        );
+    }
+
     #[test]
     fn test_groth16_circuit_cubic() {
         let rng = &mut ark_std::test_rng();
@@ -104,4 +267,48 @@ mod test {
         assert!(!Groth16::<Bls12_381>::verify(&vk, &[BlsFr::from(35)], &proof2).unwrap());
         assert!(!Groth16::<Bls12_381>::verify(&vk, &[BlsFr::from(73)], &proof1).unwrap());
     }
+
+    #[test]
+    fn test_optimized() {
+        let rng = &mut ark_std::test_rng();
+        let count = |circuit: &dyn Fn(ConstraintSystemRef<BlsFr>)| {
+            let cs = ConstraintSystem::new_ref();
+            circuit(cs.clone());
+            assert!(cs.is_satisfied().unwrap());
+            (cs.num_constraints(), cs.num_witness_variables())
+        };
+        let witness = CubicWitness {
+            x: BlsFr::from(3u8),
+        };
+        let plain = count(&|cs| {
+            CubicDemoCircuit::from(witness)
+                .generate_constraints(cs)
+                .unwrap()
+        });
+        let optimized = count(&|cs| {
+            CubicDemoCircuitOptimized::from(witness)
+                .generate_constraints(cs)
+                .unwrap()
+        });
+        assert_eq!(plain, (3, 3));
+        assert_eq!(optimized, (2, 2));
+
+        // both prove the same statement: x^3 + x + 5 == 35
+        let inputs = witness.instance().public_inputs();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(CubicDemoCircuit::<BlsFr>::blank(), rng)
+                .unwrap();
+        let proof = Groth16::<Bls12_381>::prove(&pk, CubicDemoCircuit::from(witness), rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            CubicDemoCircuitOptimized::<BlsFr>::blank(),
+            rng,
+        )
+        .unwrap();
+        let proof = Groth16::<Bls12_381>::prove(&pk, CubicDemoCircuitOptimized::from(witness), rng)
+            .unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &[BlsFr::from(36u8)], &proof).unwrap());
+    }
 }
@@ -1,9 +1,12 @@
 use ark_ff::{Field, PrimeField};
 use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
-use ark_relations::r1cs::{Namespace, SynthesisError};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, Namespace, SynthesisError};
 use std::borrow::Borrow;
 
 use super::{CubicRootTrait, ParamType, SolutionDemo};
+use crate::builder::BuildError;
+use crate::cubic_demo::{CubicInstance, CubicWitness};
+use crate::equivalence::Equivalence;
 
 // r1cs constraints
 
@@ -35,18 +38,18 @@ impl<ConstraintF: PrimeField> AllocVar<ParamType<ConstraintF>, ConstraintF>
         let ns = cs.into();
         let cs = ns.cs();
 
-        f().and_then(|val| {
-            let inner = FpVar::new_variable(
-                ark_relations::ns!(cs, "inner"),
-                || Ok(val.borrow().inner.clone()),
-                mode,
-            )?;
-            Ok(ParamTypeVar { inner })
-        })
+        // `f` is only evaluated when a value is needed, so the variable can also be
+        // allocated in setup mode without an assignment
+        let inner = FpVar::new_variable(
+            ark_relations::ns!(cs, "inner"),
+            || f().map(|val| val.borrow().inner),
+            mode,
+        )?;
+        Ok(ParamTypeVar { inner })
     }
 }
 pub struct SolutionDemoGadget<ConstraintF: PrimeField> {
-    x: ParamTypeVar<ConstraintF>,
+    pub x: ParamTypeVar<ConstraintF>,
 }
 
 impl<ConstraintF: PrimeField> CubicRootGadgetTrait<SolutionDemo<ConstraintF>, ConstraintF>
@@ -57,7 +60,147 @@ impl<ConstraintF: PrimeField> CubicRootGadgetTrait<SolutionDemo<ConstraintF>, Co
     fn verify(&self, y: &Self::ParamTypeVar) -> Result<Boolean<ConstraintF>, SynthesisError> {
         let x = &self.x.inner;
         let eval = x * x * x + x + ConstraintF::from(5u8);
-        Ok(eval.is_eq(&y.inner)?)
+        eval.is_eq(&y.inner)
+    }
+}
+
+// inputs x, y
+impl<F: PrimeField> Equivalence<F> for SolutionDemoGadget<F> {
+    const NUM_INPUTS: usize = 2;
+    type Native = bool;
+    type Var = Boolean<F>;
+
+    fn native(inputs: &[F]) -> Result<bool, crate::Error> {
+        SolutionDemo::new(ParamType::new(inputs[0])).verify(&ParamType::new(inputs[1]))
+    }
+
+    fn gadget(
+        _: ConstraintSystemRef<F>,
+        inputs: &[FpVar<F>],
+    ) -> Result<Boolean<F>, SynthesisError> {
+        let gadget = SolutionDemoGadget {
+            x: ParamTypeVar::new(inputs[0].clone()),
+        };
+        gadget.verify(&ParamTypeVar::new(inputs[1].clone()))
+    }
+
+    // the y that x solves
+    fn related(inputs: &[F]) -> Option<Vec<F>> {
+        let witness = CubicWitness { x: inputs[0] };
+        Some(vec![witness.x, witness.instance().out])
+    }
+}
+
+// the gadget as a circuit: x is the witness, y the public input, and the boolean
+// returned by the gadget is enforced to be true
+#[derive(Clone, Copy)]
+pub struct CubicGadgetCircuit<ConstraintF: PrimeField> {
+    pub x: Option<ConstraintF>,
+    pub y: Option<ConstraintF>,
+}
+
+impl<ConstraintF: PrimeField> ConstraintSynthesizer<ConstraintF>
+    for CubicGadgetCircuit<ConstraintF>
+{
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        let x = ParamTypeVar::new_witness(ark_relations::ns!(cs, "x"), || {
+            self.x
+                .map(ParamType::new)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let y = ParamTypeVar::new_input(ark_relations::ns!(cs, "y"), || {
+            self.y
+                .map(ParamType::new)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let is_root = SolutionDemoGadget { x }.verify(&y)?;
+        is_root.enforce_equal(&Boolean::TRUE)
+    }
+}
+
+impl<ConstraintF: PrimeField> CubicGadgetCircuit<ConstraintF> {
+    // no assignment, for setup and for counting constraints
+    pub fn blank() -> Self {
+        Self { x: None, y: None }
+    }
+}
+
+// y is an input of its own here, so the instance can be given separately from the witness,
+// e.g. to show that a wrong y is rejected
+impl<ConstraintF: PrimeField> From<(CubicInstance<ConstraintF>, CubicWitness<ConstraintF>)>
+    for CubicGadgetCircuit<ConstraintF>
+{
+    fn from((instance, witness): (CubicInstance<ConstraintF>, CubicWitness<ConstraintF>)) -> Self {
+        Self {
+            x: Some(witness.x),
+            y: Some(instance.out),
+        }
+    }
+}
+
+impl<ConstraintF: PrimeField> From<CubicWitness<ConstraintF>> for CubicGadgetCircuit<ConstraintF> {
+    fn from(witness: CubicWitness<ConstraintF>) -> Self {
+        (witness.instance(), witness).into()
+    }
+}
+
+impl<ConstraintF: PrimeField> CubicGadgetCircuit<ConstraintF> {
+    // CubicGadgetCircuit::builder().x(3u64).y(35u64).build()?
+    pub fn builder() -> CubicGadgetBuilder<ConstraintF> {
+        CubicGadgetBuilder {
+            x: None,
+            y: None,
+            check: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CubicGadgetBuilder<ConstraintF: PrimeField> {
+    x: Option<ConstraintF>,
+    y: Option<ConstraintF>,
+    check: bool,
+}
+
+impl<ConstraintF: PrimeField> CubicGadgetBuilder<ConstraintF> {
+    pub fn x(mut self, x: impl Into<ConstraintF>) -> Self {
+        self.x = Some(x.into());
+        self
+    }
+
+    // without y the circuit is built for the y that x solves
+    pub fn y(mut self, y: impl Into<ConstraintF>) -> Self {
+        self.y = Some(y.into());
+        self
+    }
+
+    // skip the relation check, e.g. to show that a wrong y gives an unsatisfied circuit
+    pub fn unchecked(mut self) -> Self {
+        self.check = false;
+        self
+    }
+
+    // the circuit for proving
+    pub fn build(self) -> Result<CubicGadgetCircuit<ConstraintF>, BuildError> {
+        let witness = CubicWitness {
+            x: self.x.ok_or(BuildError::Missing("x"))?,
+        };
+        let instance = match self.y {
+            Some(out) => CubicInstance { out },
+            None => witness.instance(),
+        };
+        if self.check && witness.instance() != instance {
+            return Err(BuildError::Unsatisfied("x^3 + x + 5 == y"));
+        }
+        Ok((instance, witness).into())
+    }
+
+    // the circuit for setup, any values given are ignored
+    pub fn build_setup(self) -> CubicGadgetCircuit<ConstraintF> {
+        CubicGadgetCircuit::blank()
     }
 }
 
@@ -79,7 +222,55 @@ mod test {
         let y = ParamTypeVar::new(y_val);
 
         let demo_gaget = SolutionDemoGadget { x };
-        assert_eq!(true, demo_gaget.verify(&y).unwrap().value().unwrap());
+        assert!(demo_gaget.verify(&y).unwrap().value().unwrap());
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_cubic_gadget_circuit() {
+        let circuit = |x: u8, out: u8| {
+            let instance = CubicInstance {
+                out: BlsFr::from(out),
+            };
+            CubicGadgetCircuit::from((instance, CubicWitness { x: BlsFr::from(x) }))
+        };
+
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        circuit(3, 35).generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        circuit(3, 36).generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        let witness = CubicWitness {
+            x: BlsFr::from(4u8),
+        };
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        CubicGadgetCircuit::from(witness)
+            .generate_constraints(cs.clone())
+            .unwrap();
         assert!(cs.is_satisfied().unwrap());
     }
+
+    #[test]
+    fn test_builder() {
+        let builder = CubicGadgetCircuit::<BlsFr>::builder().x(3u64);
+        let circuit = builder.build().unwrap();
+        assert_eq!(circuit.y, Some(BlsFr::from(35u8)));
+        assert!(builder.y(35u64).build().is_ok());
+        assert_eq!(
+            builder.y(36u64).build().err(),
+            Some(BuildError::Unsatisfied("x^3 + x + 5 == y"))
+        );
+
+        // unchecked, the wrong y reaches the constraint system and is caught there
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        let circuit = builder.y(36u64).unchecked().build().unwrap();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        let setup = builder.build_setup();
+        assert_eq!((setup.x, setup.y), (None, None));
+    }
 }
@@ -25,6 +25,12 @@ pub struct SolutionDemo<F: Field> {
     x: ParamType<F>,
 }
 
+impl<F: Field> SolutionDemo<F> {
+    pub fn new(x: ParamType<F>) -> Self {
+        Self { x }
+    }
+}
+
 impl<F: Field> CubicRootTrait for SolutionDemo<F> {
     type ParamType = ParamType<F>;
 
@@ -44,6 +50,6 @@ mod test {
         let x = ParamType::new(BlsFr::from(3u8));
         let y = ParamType::new(BlsFr::from(35u8));
         let demo = SolutionDemo { x };
-        assert_eq!(true, demo.verify(&y).unwrap());
+        assert!(demo.verify(&y).unwrap());
     }
 }
@@ -0,0 +1,190 @@
+use ark_crypto_primitives::snark::SNARKGadget;
+use ark_ec::{AffineCurve, PairingEngine};
+use ark_ff::PrimeField;
+use ark_groth16::{constraints::Groth16VerifierGadget, Groth16};
+use ark_mnt4_298::{constraints::PairingVar as Mnt4PairingVar, MNT4_298};
+use ark_mnt6_298::{constraints::PairingVar as Mnt6PairingVar, MNT6_298};
+use ark_r1cs_std::{pairing::PairingVar, ToConstraintFieldGadget};
+use ark_sponge::Absorb;
+use num_bigint::BigUint;
+
+// the curves of the recursive demos in one place. A proof on a curve is verified by a
+// pairing over the curve's base field, so a circuit verifying it is cheap over that
+// field, the scalar field of another curve:
+//   TwoChain  Inner, whose proofs are verified, and Outer, whose scalar field is the base
+//             field of Inner, with the pairing gadget of Inner over that field
+//   Cycle     a two-chain that also goes the other way round, the base field of Outer
+//             being the scalar field of Inner; `Reverse` is the chain from Outer to Inner
+// `verifier_gadget_demo` and `aggregation_demo` verify MNT4-298 proofs on MNT6-298
+// (`Mnt4Mnt6`), `ivc_demo` alternates between both directions of the cycle.
+//
+// The bounds the demos need on the associated types of ark-ec and ark-r1cs-std (the
+// points and their gadgets go into Poseidon) are stated once here, on associated types
+// of the chain that the inner curve's types are equal to.
+
+pub trait TwoChain: 'static {
+    type Inner: PairingEngine<
+        Fq = Self::InnerFq,
+        G1Affine = Self::InnerG1,
+        G2Affine = Self::InnerG2,
+    >;
+    type Outer: PairingEngine<Fr = Self::InnerFq>;
+    type InnerFq: PrimeField + Absorb;
+    type InnerG1: AffineCurve + ark_ff::ToConstraintField<Self::InnerFq>;
+    type InnerG2: AffineCurve + ark_ff::ToConstraintField<Self::InnerFq>;
+    type InnerPairingVar: PairingVar<
+        Self::Inner,
+        Self::InnerFq,
+        G1Var = Self::InnerG1Var,
+        G2Var = Self::InnerG2Var,
+    >;
+    type InnerG1Var: ToConstraintFieldGadget<Self::InnerFq>;
+    type InnerG2Var: ToConstraintFieldGadget<Self::InnerFq>;
+
+    // the same number in the outer scalar field, None if it is too large for it
+    fn to_outer(value: InnerFr<Self>) -> Option<OuterFr<Self>> {
+        convert(value)
+    }
+
+    fn to_inner(value: OuterFr<Self>) -> Option<InnerFr<Self>> {
+        convert(value)
+    }
+}
+
+pub trait Cycle: TwoChain {
+    type Reverse: TwoChain<Inner = Self::Outer, Outer = Self::Inner>;
+}
+
+pub type InnerFr<C> = <<C as TwoChain>::Inner as PairingEngine>::Fr;
+pub type OuterFr<C> = <<C as TwoChain>::Outer as PairingEngine>::Fr;
+pub type InnerSnark<C> = Groth16<<C as TwoChain>::Inner>;
+pub type OuterSnark<C> = Groth16<<C as TwoChain>::Outer>;
+pub type InnerVerifierGadget<C> =
+    Groth16VerifierGadget<<C as TwoChain>::Inner, <C as TwoChain>::InnerPairingVar>;
+// the public inputs of an inner proof in the outer circuit
+pub type InnerInputVar<C> =
+    <InnerVerifierGadget<C> as SNARKGadget<InnerFr<C>, OuterFr<C>, InnerSnark<C>>>::InputVar;
+
+// a number from one prime field into another, None unless it is below both moduli
+pub fn convert<A: PrimeField, B: PrimeField>(value: A) -> Option<B> {
+    let n: BigUint = value.into();
+    let converted = B::from_le_bytes_mod_order(&n.to_bytes_le());
+    (Into::<BigUint>::into(converted) == n).then_some(converted)
+}
+
+// MNT4-298 proofs verified on MNT6-298
+pub struct Mnt4Mnt6;
+
+// MNT6-298 proofs verified on MNT4-298
+pub struct Mnt6Mnt4;
+
+impl TwoChain for Mnt4Mnt6 {
+    type Inner = MNT4_298;
+    type Outer = MNT6_298;
+    type InnerFq = ark_mnt4_298::Fq;
+    type InnerG1 = ark_mnt4_298::G1Affine;
+    type InnerG2 = ark_mnt4_298::G2Affine;
+    type InnerPairingVar = Mnt4PairingVar;
+    type InnerG1Var = ark_mnt4_298::constraints::G1Var;
+    type InnerG2Var = ark_mnt4_298::constraints::G2Var;
+}
+
+impl Cycle for Mnt4Mnt6 {
+    type Reverse = Mnt6Mnt4;
+}
+
+impl TwoChain for Mnt6Mnt4 {
+    type Inner = MNT6_298;
+    type Outer = MNT4_298;
+    type InnerFq = ark_mnt6_298::Fq;
+    type InnerG1 = ark_mnt6_298::G1Affine;
+    type InnerG2 = ark_mnt6_298::G2Affine;
+    type InnerPairingVar = Mnt6PairingVar;
+    type InnerG1Var = ark_mnt6_298::constraints::G1Var;
+    type InnerG2Var = ark_mnt6_298::constraints::G2Var;
+}
+
+impl Cycle for Mnt6Mnt4 {
+    type Reverse = Mnt4Mnt6;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::multiply_demo::{MultiplyDemoCircuit, MultiplyWitness};
+    use ark_crypto_primitives::snark::constraints::SNARKGadget;
+    use ark_ff::FpParameters;
+    use ark_groth16::constraints::ProofVar;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    fn modulus<F: PrimeField>() -> BigUint {
+        F::Params::MODULUS.into()
+    }
+
+    // a multiply proof on the inner curve, checked in a circuit over the outer field
+    fn check_chain<C: TwoChain>() {
+        let rng = &mut ark_std::test_rng();
+        assert_eq!(modulus::<C::InnerFq>(), modulus::<OuterFr<C>>());
+
+        let (pk, vk) =
+            InnerSnark::<C>::circuit_specific_setup(MultiplyDemoCircuit::blank(), rng).unwrap();
+        let witness = MultiplyWitness {
+            a: InnerFr::<C>::from(7u8),
+            b: InnerFr::<C>::from(11u8),
+        };
+        let proof = InnerSnark::<C>::prove(&pk, MultiplyDemoCircuit::from(witness), rng).unwrap();
+        let inputs = witness.instance().public_inputs();
+        assert_eq!(
+            C::to_outer(inputs[0]).and_then(C::to_inner),
+            Some(inputs[0])
+        );
+
+        let cs = ConstraintSystem::<OuterFr<C>>::new_ref();
+        let vk = InnerVerifierGadget::<C>::new_verification_key_unchecked(
+            cs.clone(),
+            || Ok(vk),
+            AllocationMode::Constant,
+        )
+        .unwrap();
+        let proof = ProofVar::new_witness(cs.clone(), || Ok(proof)).unwrap();
+        let inputs = InnerInputVar::<C>::new_input(cs.clone(), || Ok(inputs)).unwrap();
+        InnerVerifierGadget::<C>::verify(&vk, &inputs, &proof)
+            .unwrap()
+            .enforce_equal(&Boolean::TRUE)
+            .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_cycle() {
+        check_chain::<Mnt4Mnt6>();
+        check_chain::<<Mnt4Mnt6 as Cycle>::Reverse>();
+        // the other way round the fields swap
+        assert_eq!(
+            modulus::<OuterFr<Mnt6Mnt4>>(),
+            modulus::<InnerFr<Mnt4Mnt6>>()
+        );
+    }
+
+    #[test]
+    fn test_convert() {
+        let rng = &mut ark_std::test_rng();
+        // every MNT4 scalar is an MNT6 one, the other way round only the smaller ones
+        assert!(modulus::<InnerFr<Mnt4Mnt6>>() < modulus::<OuterFr<Mnt4Mnt6>>());
+        for _ in 0..100 {
+            let x = InnerFr::<Mnt4Mnt6>::rand(rng);
+            let y = Mnt4Mnt6::to_outer(x).unwrap();
+            assert_eq!(Into::<BigUint>::into(x), Into::<BigUint>::into(y));
+            assert_eq!(Mnt4Mnt6::to_inner(y), Some(x));
+        }
+        let largest = -OuterFr::<Mnt4Mnt6>::from(1u8);
+        assert_eq!(Mnt4Mnt6::to_inner(largest), None);
+        assert_eq!(
+            convert::<_, InnerFr<Mnt4Mnt6>>(OuterFr::<Mnt4Mnt6>::from(5u8)),
+            Some(InnerFr::<Mnt4Mnt6>::from(5u8))
+        );
+    }
+}
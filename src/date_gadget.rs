@@ -0,0 +1,202 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSystemRef, SynthesisError},
+};
+
+use crate::boolean_demo::to_bits;
+use crate::div_rem_demo::div_rem;
+
+// Dates in circuits, encoded as the number of days since 1970-01-01 (day 0), the way
+// credentials and timestamps usually carry them. Differences of dates are a subtraction
+// and a range check; the year of a date needs the Gregorian leap year rule, which the
+// circuit evaluates with `div_rem` on the year: the days before January 1st of y are
+// 365 * (y - 1970) plus the leap years in 1970..y, (y - 1) / 4 - (y - 1) / 100 +
+// (y - 1) / 400 minus the 477 leap years before 1970.
+
+// dates are below 2^20 days, up to the year 4840
+pub const DAY_BITS: usize = 20;
+pub const EPOCH_YEAR: u32 = 1970;
+// years since 1970 of the dates above
+const YEAR_BITS: usize = 12;
+// bits of the year itself, for `div_rem`
+const FULL_YEAR_BITS: usize = 13;
+// 1969 / 4 - 1969 / 100 + 1969 / 400
+const LEAP_YEARS_BEFORE_EPOCH: u32 = 477;
+
+pub fn is_leap_year(year: u32) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+// the day of January 1st of `year`, from 1970 on
+pub fn first_day_of_year(year: u32) -> u32 {
+    let before = year - 1;
+    365 * (year - EPOCH_YEAR) + before / 4 - before / 100 + before / 400 - LEAP_YEARS_BEFORE_EPOCH
+}
+
+pub fn year_of_day(day: u32) -> u32 {
+    // at least day / 366 years have passed, at most 366 days each
+    let mut year = EPOCH_YEAR + day / 366;
+    while first_day_of_year(year + 1) <= day {
+        year += 1;
+    }
+    year
+}
+
+// to - from, None if `to` is before `from`
+pub fn days_between(from: u32, to: u32) -> Option<u32> {
+    to.checked_sub(from)
+}
+
+// `day` as a u32 if it is a date, 0 otherwise: the witnesses for a day out of range only
+// have to exist, the range checks fail on it
+fn day_value<F: PrimeField>(day: &FpVar<F>) -> Result<u32, SynthesisError> {
+    let repr = day.value()?.into_repr();
+    let limbs = repr.as_ref();
+    match limbs[1..].iter().all(|&limb| limb == 0) && limbs[0] < 1 << DAY_BITS {
+        true => Ok(limbs[0] as u32),
+        false => Ok(0),
+    }
+}
+
+// to - from, unsatisfiable if either is not a date or `to` is before `from`
+pub fn days_between_var<F: PrimeField>(
+    from: &FpVar<F>,
+    to: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    to_bits(from, DAY_BITS)?;
+    to_bits(to, DAY_BITS)?;
+    let days = to - from;
+    to_bits(&days, DAY_BITS)?;
+    Ok(days)
+}
+
+// whether `year` is a leap year, from the remainders of year - 1 by 4, 100 and 400
+fn leap_from_previous_remainders<F: PrimeField>(
+    r4: &FpVar<F>,
+    r100: &FpVar<F>,
+    r400: &FpVar<F>,
+) -> Result<Boolean<F>, SynthesisError> {
+    let divisible_by_4 = r4.is_eq(&FpVar::constant(F::from(3u8)))?;
+    let divisible_by_100 = r100.is_eq(&FpVar::constant(F::from(99u8)))?;
+    let divisible_by_400 = r400.is_eq(&FpVar::constant(F::from(399u16)))?;
+    divisible_by_4
+        .and(&divisible_by_100.not())?
+        .or(&divisible_by_400)
+}
+
+// the year of `day`, unsatisfiable if it is not a date
+pub fn year_of_day_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    day: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    to_bits(day, DAY_BITS)?;
+    let year = FpVar::new_witness(ns!(cs, "year"), || {
+        Ok(F::from(year_of_day(day_value(day)?)))
+    })?;
+    let since_epoch = &year - F::from(EPOCH_YEAR);
+    to_bits(&since_epoch, YEAR_BITS)?;
+
+    let before = &year - F::one();
+    let mut leap_years = FpVar::zero();
+    let mut remainders = Vec::with_capacity(3);
+    for (divisor, sign) in [(4u16, true), (100, false), (400, true)].iter() {
+        let divisor = FpVar::constant(F::from(*divisor));
+        let (q, r) = div_rem(cs.clone(), &before, &divisor, FULL_YEAR_BITS)?;
+        leap_years = match sign {
+            true => leap_years + q,
+            false => leap_years - q,
+        };
+        remainders.push(r);
+    }
+    let first_day = since_epoch * F::from(365u16) + leap_years - F::from(LEAP_YEARS_BEFORE_EPOCH);
+    let leap = leap_from_previous_remainders(&remainders[0], &remainders[1], &remainders[2])?;
+
+    // first_day <= day < first_day + 365 + leap
+    let day_of_year = day - &first_day;
+    to_bits(&day_of_year, 9)?;
+    let days_in_year = FpVar::from(leap) + F::from(365u16);
+    to_bits(&(days_in_year - &day_of_year - F::one()), 9)?;
+    Ok(year)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::Fr;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::Rng;
+    use chrono::{Datelike, Days, NaiveDate};
+
+    fn chrono_date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(1970, 1, 1)
+            .unwrap()
+            .checked_add_days(Days::new(day as u64))
+            .unwrap()
+    }
+
+    fn year_in_circuit(day: u32) -> Option<u32> {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let day = FpVar::new_witness(cs.clone(), || Ok(Fr::from(day))).unwrap();
+        let year = year_of_day_var(cs.clone(), &day).unwrap();
+        match cs.is_satisfied().unwrap() {
+            true => Some(year.value().unwrap().into_repr().as_ref()[0] as u32),
+            false => None,
+        }
+    }
+
+    #[test]
+    fn test_against_chrono() {
+        let rng = &mut ark_std::test_rng();
+        assert_eq!(first_day_of_year(1970), 0);
+        for year in 1970..2500 {
+            let first = NaiveDate::from_ymd_opt(year as i32, 1, 1).unwrap();
+            assert_eq!(chrono_date(first_day_of_year(year)), first);
+            assert_eq!(is_leap_year(year), first.leap_year());
+        }
+        for _ in 0..1000 {
+            let day = rng.gen_range(0..1 << DAY_BITS);
+            assert_eq!(year_of_day(day) as i32, chrono_date(day).year());
+        }
+    }
+
+    #[test]
+    fn test_year_of_day_var() {
+        let rng = &mut ark_std::test_rng();
+        // the days around the turns of a few years, 2000 and 2400 are leap years, 2100 not
+        let mut days: Vec<u32> = [1970, 1971, 1972, 1973, 2000, 2001, 2100, 2101, 2400, 2401]
+            .iter()
+            .flat_map(|&year| {
+                let first = first_day_of_year(year);
+                vec![first - (year > 1970) as u32, first, first + 1]
+            })
+            .collect();
+        days.extend((0..10).map(|_| rng.gen_range(0..1 << DAY_BITS)));
+        days.push((1 << DAY_BITS) - 1);
+        for day in days {
+            assert_eq!(
+                year_in_circuit(day),
+                Some(chrono_date(day).year() as u32),
+                "day {}",
+                day
+            );
+        }
+        assert_eq!(year_in_circuit(1 << DAY_BITS), None);
+    }
+
+    #[test]
+    fn test_days_between() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let from = FpVar::new_witness(cs.clone(), || Ok(Fr::from(10u8))).unwrap();
+        let to = FpVar::new_witness(cs.clone(), || Ok(Fr::from(375u16))).unwrap();
+        let days = days_between_var(&from, &to).unwrap();
+        assert_eq!(days.value().unwrap(), Fr::from(365u16));
+        assert_eq!(days_between(10, 375), Some(365));
+        assert!(cs.is_satisfied().unwrap());
+        let backwards = days_between_var(&to, &from).unwrap();
+        assert_eq!(backwards.value().unwrap(), -Fr::from(365u16));
+        assert_eq!(days_between(375, 10), None);
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
@@ -0,0 +1,250 @@
+use ark_bls12_381::Fr;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::poseidon::PoseidonParameters;
+
+use crate::boolean_demo::to_bits;
+use crate::div_rem_demo::div_rem;
+use crate::encoding::{field_to_bytes, Endian};
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+
+// "Provably fair" rolls: before a session the server commits to a secret seed with
+// H(server seed), the player picks a client seed, and roll number `nonce` of the session
+// is derived from H(server seed, client seed, nonce) as a number below `sides`. Neither
+// side can steer the outcome, the server is bound by its commitment and the client seed
+// comes after it. Usually the server reveals its seed at the end so the player can
+// recompute the rolls; with the proof it never has to and can keep using it.
+//
+// Reducing a hash modulo `sides` is biased unless `sides` divides its range, 2^32 mod 6
+// values below 2^32 give one more residue than the others. The digest is split into
+// `CHUNKS` numbers of 32 bits and the roll uses the first below the largest multiple of
+// `sides` (rejection sampling), which is uniform. A circuit has no loops, so all chunks
+// are checked and the first accepted one is selected; in the rare case that all are
+// rejected there is no roll.
+
+pub const CHUNK_BITS: usize = 32;
+// 7 * 32 bits of the 255 of the digest
+pub const CHUNKS: usize = 7;
+
+pub fn seed_commitment(params: &PoseidonParameters<Fr>, server_seed: Fr) -> Fr {
+    hash_inputs(params, &[server_seed])
+}
+
+fn chunks(
+    params: &PoseidonParameters<Fr>,
+    server_seed: Fr,
+    client_seed: Fr,
+    nonce: u64,
+) -> Vec<u64> {
+    let digest = hash_inputs(params, &[server_seed, client_seed, Fr::from(nonce)]);
+    let bytes = field_to_bytes(digest, Endian::Little);
+    bytes
+        .chunks(CHUNK_BITS / 8)
+        .take(CHUNKS)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .rev()
+                .fold(0, |value, &byte| value << 8 | byte as u64)
+        })
+        .collect()
+}
+
+// the roll in 0..sides, None if every chunk is rejected or `sides` is not in 1..2^32
+pub fn roll(
+    params: &PoseidonParameters<Fr>,
+    server_seed: Fr,
+    client_seed: Fr,
+    nonce: u64,
+    sides: u64,
+) -> Option<u64> {
+    if sides == 0 || sides >= 1 << CHUNK_BITS {
+        return None;
+    }
+    // the values from here on, 2^32 mod sides of them, are rejected
+    let limit = (1 << CHUNK_BITS) - (1 << CHUNK_BITS) % sides;
+    chunks(params, server_seed, client_seed, nonce)
+        .into_iter()
+        .find(|&value| value < limit)
+        .map(|value| value % sides)
+}
+
+#[derive(Clone)]
+pub struct DiceCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub commitment: Fr,
+    pub client_seed: Fr,
+    pub nonce: u64,
+    pub sides: u64,
+    pub outcome: u64,
+    pub server_seed: Option<Fr>,
+}
+
+impl DiceCircuit {
+    // None if the seeds give no roll or `sides` is out of range (see `roll`)
+    pub fn new(
+        params: PoseidonParameters<Fr>,
+        server_seed: Fr,
+        client_seed: Fr,
+        nonce: u64,
+        sides: u64,
+    ) -> Option<Self> {
+        Some(Self {
+            commitment: seed_commitment(&params, server_seed),
+            outcome: roll(&params, server_seed, client_seed, nonce, sides)?,
+            params,
+            client_seed,
+            nonce,
+            sides,
+            server_seed: Some(server_seed),
+        })
+    }
+
+    // everything but the server seed is public
+    pub fn blank(params: PoseidonParameters<Fr>) -> Self {
+        Self {
+            params,
+            commitment: Fr::from(0u8),
+            client_seed: Fr::from(0u8),
+            nonce: 0,
+            sides: 1,
+            outcome: 0,
+            server_seed: None,
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        vec![
+            self.commitment,
+            self.client_seed,
+            Fr::from(self.nonce),
+            Fr::from(self.sides),
+            Fr::from(self.outcome),
+        ]
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for DiceCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let commitment = FpVar::new_input(ns!(cs, "commitment"), || Ok(self.commitment))?;
+        let client_seed = FpVar::new_input(ns!(cs, "client_seed"), || Ok(self.client_seed))?;
+        let nonce = FpVar::new_input(ns!(cs, "nonce"), || Ok(Fr::from(self.nonce)))?;
+        let sides = FpVar::new_input(ns!(cs, "sides"), || Ok(Fr::from(self.sides)))?;
+        let outcome = FpVar::new_input(ns!(cs, "outcome"), || Ok(Fr::from(self.outcome)))?;
+        let server_seed = FpVar::new_witness(ns!(cs, "server_seed"), || {
+            self.server_seed.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        hash_inputs_var(
+            cs.clone(),
+            &self.params,
+            ark_std::slice::from_ref(&server_seed),
+        )?
+        .enforce_equal(&commitment)?;
+        let digest = hash_inputs_var(cs.clone(), &self.params, &[server_seed, client_seed, nonce])?;
+        // the canonical bits, a non-canonical decomposition would give other chunks
+        let bits = digest.to_bits_le()?;
+
+        // 2^32 mod sides, a value is accepted if value + rejected < 2^32
+        let range = FpVar::constant(Fr::from(1u64 << CHUNK_BITS));
+        let (_, rejected) = div_rem(cs.clone(), &range, &sides, CHUNK_BITS + 1)?;
+        let mut found = Boolean::FALSE;
+        let mut selected = FpVar::zero();
+        for chunk in bits.chunks(CHUNK_BITS).take(CHUNKS) {
+            let value = Boolean::le_bits_to_fp_var(chunk)?;
+            let accepted = to_bits(&(&value + &rejected), CHUNK_BITS + 1)?[CHUNK_BITS].not();
+            selected = accepted.and(&found.not())?.select(&value, &selected)?;
+            found = found.or(&accepted)?;
+        }
+        found.enforce_equal(&Boolean::TRUE)?;
+        let (_, remainder) = div_rem(cs, &selected, &sides, CHUNK_BITS)?;
+        remainder.enforce_equal(&outcome)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    fn is_satisfied(circuit: DiceCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_rolls() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (server_seed, client_seed) = (Fr::rand(rng), Fr::rand(rng));
+
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(DiceCircuit::blank(params.clone()), rng)
+                .unwrap();
+        for nonce in 0..3 {
+            let circuit =
+                DiceCircuit::new(params.clone(), server_seed, client_seed, nonce, 6).unwrap();
+            assert!(circuit.outcome < 6);
+            let inputs = circuit.public_inputs();
+            let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+            assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+            // any other outcome
+            let mut other = inputs.clone();
+            other[4] = Fr::from((inputs[4] == Fr::from(0u8)) as u8);
+            assert!(!Groth16::<Bls12_381>::verify(&vk, &other, &proof).unwrap());
+        }
+
+        // another server seed than the committed one
+        let mut circuit = DiceCircuit::new(params.clone(), server_seed, client_seed, 0, 6).unwrap();
+        circuit.server_seed = Some(server_seed + Fr::from(1u8));
+        assert!(!is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_rejection_sampling() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (server_seed, client_seed) = (Fr::rand(rng), Fr::rand(rng));
+        // a quarter of the chunks are rejected
+        let sides = 3 << 30;
+
+        let mut rejected_first = 0;
+        for nonce in 0..12 {
+            let chunks = chunks(&params, server_seed, client_seed, nonce);
+            let circuit =
+                DiceCircuit::new(params.clone(), server_seed, client_seed, nonce, sides).unwrap();
+            assert!(is_satisfied(circuit.clone()));
+            // the plain reduction of the first chunk is not the roll when it is rejected
+            if chunks[0] >= sides {
+                rejected_first += 1;
+                let mut biased = circuit.clone();
+                biased.outcome = chunks[0] % sides;
+                assert_ne!(biased.outcome, circuit.outcome);
+                assert!(!is_satisfied(biased));
+            }
+        }
+        assert!(rejected_first > 0);
+
+        // no bias to remove for a power of two, every chunk is accepted
+        for nonce in 0..4 {
+            let chunks = chunks(&params, server_seed, client_seed, nonce);
+            let outcome = roll(&params, server_seed, client_seed, nonce, 1 << 31);
+            assert_eq!(outcome, Some(chunks[0] % (1 << 31)));
+        }
+
+        // a die the chunks can't roll
+        for &sides in [0, 1 << CHUNK_BITS].iter() {
+            assert_eq!(roll(&params, server_seed, client_seed, 0, sides), None);
+            assert!(DiceCircuit::new(params.clone(), server_seed, client_seed, 0, sides).is_none());
+        }
+    }
+}
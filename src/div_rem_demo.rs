@@ -0,0 +1,168 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use num_bigint::BigUint;
+
+use crate::boolean_demo::to_bits;
+
+// Integer division in a prime field. The prover gives q and r and the circuit checks
+// a == q * b + r, but that equation alone holds for any a, b != 0 and r: it is an
+// equation modulo p, and q = (a - r) / b always exists in the field. Range checks make
+// it one over the integers, q, b and r are below 2^n and 0 <= r < b, so q * b + r is
+// below 2^(2n + 1) and cannot wrap around p. Without them "a is divisible by b" is true
+// for every a, see `test_unchecked_division_is_unsound`.
+
+// q and r with a == q * b + r and r < b, for b of `num_bits` bits and a of `2 * num_bits`,
+// unsatisfiable otherwise (also for b == 0)
+pub fn div_rem<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    a: &FpVar<F>,
+    b: &FpVar<F>,
+    num_bits: usize,
+) -> Result<(FpVar<F>, FpVar<F>), SynthesisError> {
+    assert!(
+        2 * num_bits + 1 < F::size_in_bits(),
+        "q * b + r could wrap around the modulus"
+    );
+    let division = || -> Result<(BigUint, BigUint), SynthesisError> {
+        let a: BigUint = a.value()?.into();
+        let b: BigUint = b.value()?.into();
+        // any witness will do for b == 0, the constraints fail on r < b
+        match b == BigUint::from(0u8) {
+            true => Ok((BigUint::from(0u8), BigUint::from(0u8))),
+            false => Ok((&a / &b, &a % &b)),
+        }
+    };
+    let q = FpVar::new_witness(ns!(cs, "q"), || Ok(F::from(division()?.0)))?;
+    let r = FpVar::new_witness(ns!(cs, "r"), || Ok(F::from(division()?.1)))?;
+
+    (&q * b + &r).enforce_equal(a)?;
+    // the range checks, q, b, r and b - 1 - r (r < b) are n bit numbers
+    to_bits(&q, num_bits)?;
+    to_bits(b, num_bits)?;
+    to_bits(&r, num_bits)?;
+    to_bits(&(b - &r - F::one()), num_bits)?;
+    Ok((q, r))
+}
+
+// knows a of 64 bits that is a multiple of a public modulus
+#[derive(Clone, Copy)]
+pub struct DivisibleCircuit<F: PrimeField> {
+    pub modulus: F,
+    pub a: Option<F>,
+}
+
+impl<F: PrimeField> DivisibleCircuit<F> {
+    pub fn new(modulus: u64, a: u64) -> Self {
+        Self {
+            modulus: F::from(modulus),
+            a: Some(F::from(a)),
+        }
+    }
+
+    // the modulus is a public input, any value will do for the setup
+    pub fn blank() -> Self {
+        Self {
+            modulus: F::one(),
+            a: None,
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<F> {
+        vec![self.modulus]
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for DivisibleCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let modulus = FpVar::new_input(ns!(cs, "modulus"), || Ok(self.modulus))?;
+        let a = FpVar::new_witness(ns!(cs, "a"), || {
+            self.a.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        to_bits(&a, 64)?;
+        let (_, r) = div_rem(cs, &a, &modulus, 64)?;
+        r.enforce_equal(&FpVar::zero())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_ff::Field;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    fn div_rem_values(a: u64, b: u64) -> Option<(Fr, Fr)> {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let a = FpVar::new_witness(cs.clone(), || Ok(Fr::from(a))).unwrap();
+        let b = FpVar::new_witness(cs.clone(), || Ok(Fr::from(b))).unwrap();
+        let (q, r) = div_rem(cs.clone(), &a, &b, 32).unwrap();
+        match cs.is_satisfied().unwrap() {
+            true => Some((q.value().unwrap(), r.value().unwrap())),
+            false => None,
+        }
+    }
+
+    #[test]
+    fn test_div_rem() {
+        assert_eq!(div_rem_values(17, 5), Some((Fr::from(3u8), Fr::from(2u8))));
+        assert_eq!(div_rem_values(15, 5), Some((Fr::from(3u8), Fr::from(0u8))));
+        assert_eq!(div_rem_values(4, 5), Some((Fr::from(0u8), Fr::from(4u8))));
+        assert_eq!(div_rem_values(0, 0), None);
+        // b does not fit in 32 bits
+        assert_eq!(div_rem_values(1 << 33, 1 << 32), None);
+    }
+
+    #[test]
+    fn test_remainder_must_be_below_divisor() {
+        // 17 = 2 * 5 + 7 holds, but 7 is not a remainder modulo 5
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let a = FpVar::new_witness(cs.clone(), || Ok(Fr::from(17u8))).unwrap();
+        let b = FpVar::new_witness(cs.clone(), || Ok(Fr::from(5u8))).unwrap();
+        let q = FpVar::new_witness(cs.clone(), || Ok(Fr::from(2u8))).unwrap();
+        let r = FpVar::new_witness(cs.clone(), || Ok(Fr::from(7u8))).unwrap();
+        (&q * &b + &r).enforce_equal(&a).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        to_bits(&(&b - &r - Fr::from(1u8)), 32).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_unchecked_division_is_unsound() {
+        // 7 = q * 5 for q = 7 / 5 in the field, nothing but a range check on q rejects it
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let a = FpVar::new_witness(cs.clone(), || Ok(Fr::from(7u8))).unwrap();
+        let b = FpVar::new_input(cs.clone(), || Ok(Fr::from(5u8))).unwrap();
+        let q = FpVar::new_witness(cs.clone(), || {
+            Ok(Fr::from(7u8) * Fr::from(5u8).inverse().unwrap())
+        })
+        .unwrap();
+        (&q * &b).enforce_equal(&a).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        to_bits(&q, 64).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_divisible() {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(DivisibleCircuit::<Fr>::blank(), rng)
+                .unwrap();
+        let circuit = DivisibleCircuit::<Fr>::new(97, 97 * 1_000_003);
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &circuit.public_inputs(), &proof).unwrap());
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &[Fr::from(89u8)], &proof).unwrap());
+
+        let cs = ConstraintSystem::new_ref();
+        DivisibleCircuit::<Fr>::new(97, 97 * 1_000_003 + 1)
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
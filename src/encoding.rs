@@ -0,0 +1,195 @@
+use ark_ff::{BigInteger, FpParameters, FromBytes, PrimeField};
+use ark_std::vec::Vec;
+
+// Bytes, integers and field elements, with the byte order always spelled out. arkworks
+// is little-endian throughout (`to_bytes_le` of a `BigInteger`, `CanonicalSerialize`),
+// Ethereum, gnark, bellman and most hashes-as-numbers big-endian, and a byte string read
+// in the wrong order still gives a valid element, only the wrong one:
+//   field_to_bytes / field_from_bytes   the number in `num_bytes` bytes, the modulus
+//                                       rounded up to whole bytes (32 for BN254 and
+//                                       BLS12-381 scalars, 48 for BLS12-381 base field
+//                                       elements); `field_from_bytes` refuses numbers
+//                                       not below the modulus
+//   field_from_bytes_mod_order          any bytes reduced, for digests and labels
+//   field_to_word / field_from_word     32 byte big-endian words, Solidity's uint256
+//   field_to_u64 / field_to_u128        back to an integer when the element is one
+// ark-serialize writes the whole limbs of the representation, 40 bytes for the 298 bit
+// MNT fields where `num_bytes` is 38.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+impl Endian {
+    // little-endian bytes from bytes in this order, and the other way round
+    fn little(self, bytes: &[u8]) -> Vec<u8> {
+        let mut bytes = bytes.to_vec();
+        if self == Endian::Big {
+            bytes.reverse();
+        }
+        bytes
+    }
+}
+
+pub type Word = [u8; 32];
+
+pub fn num_bytes<F: PrimeField>() -> usize {
+    (F::Params::MODULUS_BITS as usize).div_ceil(8)
+}
+
+pub fn field_to_bytes<F: PrimeField>(value: F, endian: Endian) -> Vec<u8> {
+    let mut bytes = value.into_repr().to_bytes_le();
+    bytes.truncate(num_bytes::<F>());
+    endian.little(&bytes)
+}
+
+// None if the number is not below the modulus; leading zero bytes (trailing, in
+// little-endian) are allowed up to any length
+pub fn field_from_bytes<F: PrimeField>(bytes: &[u8], endian: Endian) -> Option<F> {
+    let mut le = endian.little(bytes);
+    let repr_bytes = F::zero().into_repr().as_ref().len() * 8;
+    if le.len() > repr_bytes {
+        if le[repr_bytes..].iter().any(|&byte| byte != 0) {
+            return None;
+        }
+        le.truncate(repr_bytes);
+    }
+    le.resize(repr_bytes, 0);
+    F::from_repr(F::BigInt::read(&le[..]).ok()?)
+}
+
+pub fn field_from_bytes_mod_order<F: PrimeField>(bytes: &[u8], endian: Endian) -> F {
+    match endian {
+        Endian::Big => F::from_be_bytes_mod_order(bytes),
+        Endian::Little => F::from_le_bytes_mod_order(bytes),
+    }
+}
+
+// fields of up to 256 bits
+pub fn field_to_word<F: PrimeField>(value: F) -> Word {
+    assert!(num_bytes::<F>() <= 32, "the field does not fit in a word");
+    let bytes = field_to_bytes(value, Endian::Big);
+    let mut word = [0u8; 32];
+    word[32 - bytes.len()..].copy_from_slice(&bytes);
+    word
+}
+
+pub fn field_from_word<F: PrimeField>(word: &Word) -> Option<F> {
+    field_from_bytes(word, Endian::Big)
+}
+
+pub fn field_to_u64<F: PrimeField>(value: F) -> Option<u64> {
+    let repr = value.into_repr();
+    let limbs = repr.as_ref();
+    limbs[1..].iter().all(|&limb| limb == 0).then_some(limbs[0])
+}
+
+pub fn field_to_u128<F: PrimeField>(value: F) -> Option<u128> {
+    let repr = value.into_repr();
+    let limbs = repr.as_ref();
+    let high = limbs.get(1).copied().unwrap_or(0);
+    limbs[2.min(limbs.len())..]
+        .iter()
+        .all(|&limb| limb == 0)
+        .then_some((high as u128) << 64 | limbs[0] as u128)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Fq as BlsFq, Fr as BlsFr};
+    use ark_ff::Field;
+    use ark_std::{One, UniformRand};
+    use num_bigint::BigUint;
+
+    fn modulus<F: PrimeField>() -> BigUint {
+        F::Params::MODULUS.into()
+    }
+
+    // the same number as num-bigint reads it, in both orders
+    fn check_against_biguint<F: PrimeField>() {
+        let rng = &mut ark_std::test_rng();
+        for _ in 0..50 {
+            let x = F::rand(rng);
+            let n: BigUint = x.into();
+            let be = field_to_bytes(x, Endian::Big);
+            let le = field_to_bytes(x, Endian::Little);
+            assert_eq!(be.len(), num_bytes::<F>());
+            assert_eq!(BigUint::from_bytes_be(&be), n);
+            assert_eq!(BigUint::from_bytes_le(&le), n);
+            assert_eq!(field_from_bytes::<F>(&be, Endian::Big), Some(x));
+            assert_eq!(field_from_bytes::<F>(&le, Endian::Little), Some(x));
+
+            // reversed, almost always another element or none at all
+            let reversed = field_from_bytes::<F>(&be, Endian::Little);
+            assert!(reversed.is_none() || reversed == Some(F::from(BigUint::from_bytes_le(&be))));
+
+            // 64 bytes of a digest, reduced
+            let digest: Vec<u8> = (0..64).map(|_| u8::rand(rng)).collect();
+            for &endian in [Endian::Big, Endian::Little].iter() {
+                let n = match endian {
+                    Endian::Big => BigUint::from_bytes_be(&digest),
+                    Endian::Little => BigUint::from_bytes_le(&digest),
+                };
+                let reduced: BigUint = field_from_bytes_mod_order::<F>(&digest, endian).into();
+                assert_eq!(reduced, n % modulus::<F>());
+            }
+        }
+
+        // the modulus itself, and one below it
+        let p = modulus::<F>();
+        assert_eq!(field_from_bytes::<F>(&p.to_bytes_be(), Endian::Big), None);
+        assert_eq!(
+            field_from_bytes::<F>(&p.to_bytes_le(), Endian::Little),
+            None
+        );
+        let largest = (p - 1u8).to_bytes_be();
+        assert_eq!(
+            field_from_bytes::<F>(&largest, Endian::Big),
+            Some(-F::one())
+        );
+    }
+
+    #[test]
+    fn test_bytes() {
+        check_against_biguint::<BlsFr>();
+        check_against_biguint::<BlsFq>();
+        assert_eq!(num_bytes::<BlsFr>(), 32);
+        assert_eq!(num_bytes::<BlsFq>(), 48);
+
+        // leading zeros of any length, but nothing else
+        let x = BlsFr::from(0x0102u16);
+        assert_eq!(field_to_bytes(x, Endian::Big)[30..], [1, 2]);
+        assert_eq!(field_to_bytes(x, Endian::Little)[..2], [2, 1]);
+        assert_eq!(field_from_bytes(&[1, 2], Endian::Big), Some(x));
+        let mut long = vec![0u8; 100];
+        long[98..].copy_from_slice(&[1, 2]);
+        assert_eq!(field_from_bytes(&long, Endian::Big), Some(x));
+        long[0] = 1;
+        assert_eq!(field_from_bytes::<BlsFr>(&long, Endian::Big), None);
+    }
+
+    #[test]
+    fn test_words_and_integers() {
+        let rng = &mut ark_std::test_rng();
+        let x = ark_bn254::Fr::rand(rng);
+        let word = field_to_word(x);
+        assert_eq!(BigUint::from_bytes_be(&word), x.into());
+        assert_eq!(field_from_word(&word), Some(x));
+        assert_eq!(field_from_word::<ark_bn254::Fr>(&[0xff; 32]), None);
+        // 255 bits, the top byte of the word is never full
+        let word = field_to_word(-BlsFr::one());
+        assert!(word[0] < 0x80);
+
+        assert_eq!(field_to_u64(BlsFr::from(u64::MAX)), Some(u64::MAX));
+        assert_eq!(field_to_u64(BlsFr::from(u64::MAX) + BlsFr::one()), None);
+        assert_eq!(field_to_u128(BlsFr::from(u128::MAX)), Some(u128::MAX));
+        assert_eq!(field_to_u128(-BlsFr::one()), None);
+        assert_eq!(
+            field_to_u128(BlsFr::from(2u8).pow([100])),
+            Some(1u128 << 100)
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef, SynthesisError, SynthesisMode};
+use ark_std::rand::Rng;
+use std::fmt;
+
+use crate::visualize::format_coefficient;
+
+// cross-checks a gadget against the native code it mirrors. `check_equivalence` runs both
+// on random inputs and on the edge cases of the field, and on every input the gadget has
+// to give the native result, be satisfied exactly when the native code succeeds, and have
+// the same number of constraints as a symbolic run in setup mode (the shape of a circuit
+// must not depend on the witness). Every gadget with a native counterpart implements
+// `Equivalence` and is checked in tests/equivalence.rs.
+
+pub trait Equivalence<F: PrimeField> {
+    // field elements the gadget takes, allocated as witnesses
+    const NUM_INPUTS: usize;
+    type Native: PartialEq + fmt::Debug;
+    type Var: R1CSVar<F, Value = Self::Native>;
+
+    fn native(inputs: &[F]) -> Result<Self::Native, crate::Error>;
+
+    fn gadget(cs: ConstraintSystemRef<F>, inputs: &[FpVar<F>])
+        -> Result<Self::Var, SynthesisError>;
+
+    // random inputs rarely satisfy a relation, a gadget can map them to inputs that do,
+    // e.g. by computing an output from the other inputs. Checked next to the originals.
+    fn related(_inputs: &[F]) -> Option<Vec<F>> {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub enum MismatchKind {
+    Output { native: String, gadget: String },
+    // the native code accepts the inputs, the constraints are not satisfied
+    Unsatisfied,
+    // the native code rejects the inputs, the gadget gives a satisfied system
+    Accepted { native_error: String },
+    Shape { setup: usize, prove: usize },
+    Synthesis(SynthesisError),
+}
+
+#[derive(Debug)]
+pub struct Mismatch<F: PrimeField> {
+    pub inputs: Vec<F>,
+    pub kind: MismatchKind,
+}
+
+impl<F: PrimeField> fmt::Display for Mismatch<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inputs: Vec<String> = self.inputs.iter().map(|&v| format_coefficient(v)).collect();
+        write!(f, "inputs [{}]: ", inputs.join(", "))?;
+        match &self.kind {
+            MismatchKind::Output { native, gadget } => {
+                write!(f, "native gives {}, the gadget {}", native, gadget)
+            }
+            MismatchKind::Unsatisfied => write!(f, "the gadget is not satisfied"),
+            MismatchKind::Accepted { native_error } => write!(
+                f,
+                "the native code fails ({}), the gadget is satisfied",
+                native_error
+            ),
+            MismatchKind::Shape { setup, prove } => write!(
+                f,
+                "{} constraints in setup mode, {} with the witness",
+                setup, prove
+            ),
+            MismatchKind::Synthesis(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<F: PrimeField> std::error::Error for Mismatch<F> {}
+
+// 0, 1, 2, -1 (the modulus minus one), -2 and the two elements around the middle of the
+// field, where signed interpretations flip
+pub fn edge_cases<F: PrimeField>() -> Vec<F> {
+    let half = F::from_repr(F::modulus_minus_one_div_two()).unwrap_or_else(F::zero);
+    vec![
+        F::zero(),
+        F::one(),
+        F::from(2u8),
+        -F::one(),
+        -F::from(2u8),
+        half,
+        half + F::one(),
+    ]
+}
+
+// every combination of edge cases for gadgets of up to three inputs, each edge case in
+// each position among random values otherwise, then `samples` random inputs. Returns the
+// number of inputs checked.
+pub fn check_equivalence<F, G, R>(rng: &mut R, samples: usize) -> Result<usize, Mismatch<F>>
+where
+    F: PrimeField,
+    G: Equivalence<F>,
+    R: Rng,
+{
+    let setup = symbolic_constraints::<F, G>().map_err(|e| Mismatch {
+        inputs: Vec::new(),
+        kind: MismatchKind::Synthesis(e),
+    })?;
+
+    let edges = edge_cases::<F>();
+    let mut cases: Vec<Vec<F>> = vec![Vec::new()];
+    if G::NUM_INPUTS <= 3 {
+        for _ in 0..G::NUM_INPUTS {
+            cases = cases
+                .iter()
+                .flat_map(|case| {
+                    edges.iter().map(move |&edge| {
+                        let mut case = case.clone();
+                        case.push(edge);
+                        case
+                    })
+                })
+                .collect();
+        }
+    } else {
+        cases.clear();
+        for i in 0..G::NUM_INPUTS {
+            for &edge in &edges {
+                let mut case: Vec<F> = (0..G::NUM_INPUTS).map(|_| F::rand(rng)).collect();
+                case[i] = edge;
+                cases.push(case);
+            }
+        }
+    }
+    cases.extend((0..samples).map(|_| (0..G::NUM_INPUTS).map(|_| F::rand(rng)).collect()));
+
+    let mut checked = 0;
+    for case in cases {
+        let related = G::related(&case);
+        for inputs in std::iter::once(case).chain(related) {
+            check_one::<F, G>(&inputs, setup).map_err(|kind| Mismatch { inputs, kind })?;
+            checked += 1;
+        }
+    }
+    Ok(checked)
+}
+
+fn symbolic_constraints<F: PrimeField, G: Equivalence<F>>() -> Result<usize, SynthesisError> {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_mode(SynthesisMode::Setup);
+    let inputs = (0..G::NUM_INPUTS)
+        .map(|_| {
+            FpVar::new_witness(cs.clone(), || {
+                Err::<F, _>(SynthesisError::AssignmentMissing)
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    G::gadget(cs.clone(), &inputs)?;
+    Ok(cs.num_constraints())
+}
+
+fn check_one<F: PrimeField, G: Equivalence<F>>(
+    inputs: &[F],
+    setup: usize,
+) -> Result<(), MismatchKind> {
+    let cs = ConstraintSystem::new_ref();
+    let vars = inputs
+        .iter()
+        .map(|&v| FpVar::new_witness(cs.clone(), || Ok(v)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(MismatchKind::Synthesis)?;
+    let native = G::native(inputs);
+    // a gadget may refuse inputs the native code rejects, e.g. an inverse of zero
+    let var = match (G::gadget(cs.clone(), &vars), &native) {
+        (Ok(var), _) => var,
+        (Err(_), Err(_)) => return Ok(()),
+        (Err(e), Ok(_)) => return Err(MismatchKind::Synthesis(e)),
+    };
+    if cs.num_constraints() != setup {
+        return Err(MismatchKind::Shape {
+            setup,
+            prove: cs.num_constraints(),
+        });
+    }
+    let satisfied = cs.is_satisfied().map_err(MismatchKind::Synthesis)?;
+    match native {
+        Ok(_) if !satisfied => Err(MismatchKind::Unsatisfied),
+        Ok(native) => {
+            let gadget = var.value().map_err(MismatchKind::Synthesis)?;
+            if gadget != native {
+                return Err(MismatchKind::Output {
+                    native: format!("{:?}", native),
+                    gadget: format!("{:?}", gadget),
+                });
+            }
+            Ok(())
+        }
+        Err(e) if satisfied => Err(MismatchKind::Accepted {
+            native_error: e.to_string(),
+        }),
+        Err(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::Fr;
+    use ark_ff::{Field, One};
+    use ark_r1cs_std::prelude::*;
+
+    // the inverse, natively an error for zero
+    struct Inverse;
+
+    impl Equivalence<Fr> for Inverse {
+        const NUM_INPUTS: usize = 1;
+        type Native = Fr;
+        type Var = FpVar<Fr>;
+
+        fn native(inputs: &[Fr]) -> Result<Fr, crate::Error> {
+            inputs[0]
+                .inverse()
+                .ok_or_else(|| "zero has no inverse".into())
+        }
+
+        fn gadget(
+            _: ConstraintSystemRef<Fr>,
+            inputs: &[FpVar<Fr>],
+        ) -> Result<FpVar<Fr>, SynthesisError> {
+            inputs[0].inverse()
+        }
+    }
+
+    // a wrong gadget, off by one only at -1
+    struct Square;
+
+    impl Equivalence<Fr> for Square {
+        const NUM_INPUTS: usize = 1;
+        type Native = Fr;
+        type Var = FpVar<Fr>;
+
+        fn native(inputs: &[Fr]) -> Result<Fr, crate::Error> {
+            Ok(inputs[0].square())
+        }
+
+        fn gadget(
+            _: ConstraintSystemRef<Fr>,
+            inputs: &[FpVar<Fr>],
+        ) -> Result<FpVar<Fr>, SynthesisError> {
+            let x = &inputs[0];
+            let is_minus_one = x.is_eq(&FpVar::constant(-Fr::one()))?;
+            let square = x.square()?;
+            is_minus_one.select(&(&square + Fr::one()), &square)
+        }
+    }
+
+    #[test]
+    fn test_equivalence() {
+        let rng = &mut ark_std::test_rng();
+        // 7 edge cases and 20 random inputs
+        assert_eq!(check_equivalence::<Fr, Inverse, _>(rng, 20).unwrap(), 27);
+
+        // random inputs alone do not find it
+        let mismatch = check_equivalence::<Fr, Square, _>(rng, 20).unwrap_err();
+        assert_eq!(mismatch.inputs, vec![-Fr::one()]);
+        assert!(matches!(mismatch.kind, MismatchKind::Output { .. }));
+        assert!(mismatch
+            .to_string()
+            .starts_with("inputs [-1]: native gives"));
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        let edges = edge_cases::<Fr>();
+        let (half, above) = (edges[5], edges[6]);
+        assert_eq!(half.double() + Fr::one(), Fr::from(0u8));
+        assert_eq!(above, -half);
+    }
+}
@@ -0,0 +1,273 @@
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ff::{PrimeField, Zero};
+use ark_groth16::Proof;
+use ark_std::{fmt, format, string::String, vec::Vec};
+
+use crate::encoding::{self, field_to_word};
+
+// BN254 Groth16 proofs in the layout Solidity verifiers (snarkjs' verifier.sol, EIP-197
+// precompiles) take them: `verifyProof(uint[2] a, uint[2][2] b, uint[2] c, uint[n] input)`.
+// Every coordinate is a big-endian uint256, the point at infinity is (0, 0) and the
+// coordinates of `b` over Fq2 are ordered (c1, c0), the reverse of arkworks.
+
+pub use crate::encoding::Word;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CalldataError {
+    Length { expected: usize, found: usize },
+    // a word is not below the field modulus, words are counted from 0
+    NotInField { word: usize },
+    NotOnCurve { point: &'static str },
+}
+
+impl fmt::Display for CalldataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalldataError::Length { expected, found } => {
+                write!(f, "expected {} bytes of calldata, got {}", expected, found)
+            }
+            CalldataError::NotInField { word } => {
+                write!(f, "word {} is not a canonical field element", word)
+            }
+            CalldataError::NotOnCurve { point } => {
+                write!(f, "`{}` is not a point of the prime order subgroup", point)
+            }
+        }
+    }
+}
+
+impl ark_std::error::Error for CalldataError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofCalldata {
+    pub a: [Word; 2],
+    pub b: [[Word; 2]; 2],
+    pub c: [Word; 2],
+}
+
+impl ProofCalldata {
+    // a, b and c are static arrays, ABI encoded inline as 8 words
+    pub const LEN: usize = 8 * 32;
+
+    pub fn from_proof(proof: &Proof<Bn254>) -> Self {
+        Self {
+            a: g1_to_words(&proof.a),
+            b: g2_to_words(&proof.b),
+            c: g1_to_words(&proof.c),
+        }
+    }
+
+    pub fn to_proof(&self) -> Result<Proof<Bn254>, CalldataError> {
+        Ok(Proof {
+            a: g1_from_words(&self.a, 0, "a")?,
+            b: g2_from_words(&self.b, 2, "b")?,
+            c: g1_from_words(&self.c, 6, "c")?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let words = [
+            &self.a[0],
+            &self.a[1],
+            &self.b[0][0],
+            &self.b[0][1],
+            &self.b[1][0],
+            &self.b[1][1],
+            &self.c[0],
+            &self.c[1],
+        ];
+        words.iter().flat_map(|word| word.iter().copied()).collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CalldataError> {
+        if bytes.len() != Self::LEN {
+            return Err(CalldataError::Length {
+                expected: Self::LEN,
+                found: bytes.len(),
+            });
+        }
+        let word = |i: usize| {
+            let mut word = [0u8; 32];
+            word.copy_from_slice(&bytes[32 * i..32 * (i + 1)]);
+            word
+        };
+        Ok(Self {
+            a: [word(0), word(1)],
+            b: [[word(2), word(3)], [word(4), word(5)]],
+            c: [word(6), word(7)],
+        })
+    }
+}
+
+pub fn inputs_to_words(inputs: &[Fr]) -> Vec<Word> {
+    inputs.iter().map(|&input| field_to_word(input)).collect()
+}
+
+pub fn inputs_from_words(words: &[Word]) -> Result<Vec<Fr>, CalldataError> {
+    words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| field_from_word(word, i))
+        .collect()
+}
+
+// the arguments of `verifyProof` as printed by `snarkjs zkey export soliditycalldata`
+pub fn solidity_calldata(proof: &Proof<Bn254>, inputs: &[Fr]) -> String {
+    let calldata = ProofCalldata::from_proof(proof);
+    let inputs: Vec<String> = inputs_to_words(inputs)
+        .iter()
+        .map(|word| format!("\"{}\"", hex_word(word)))
+        .collect();
+    format!(
+        "[\"{}\", \"{}\"],[[\"{}\", \"{}\"],[\"{}\", \"{}\"]],[\"{}\", \"{}\"],[{}]",
+        hex_word(&calldata.a[0]),
+        hex_word(&calldata.a[1]),
+        hex_word(&calldata.b[0][0]),
+        hex_word(&calldata.b[0][1]),
+        hex_word(&calldata.b[1][0]),
+        hex_word(&calldata.b[1][1]),
+        hex_word(&calldata.c[0]),
+        hex_word(&calldata.c[1]),
+        inputs.join(",")
+    )
+}
+
+fn hex_word(word: &Word) -> String {
+    let digits: String = word.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("0x{}", digits)
+}
+
+// `index` is the position of the word in the calldata, for the error
+fn field_from_word<F: PrimeField>(word: &Word, index: usize) -> Result<F, CalldataError> {
+    encoding::field_from_word(word).ok_or(CalldataError::NotInField { word: index })
+}
+
+fn g1_to_words(p: &G1Affine) -> [Word; 2] {
+    if p.is_zero() {
+        return [[0u8; 32]; 2];
+    }
+    [field_to_word(p.x), field_to_word(p.y)]
+}
+
+fn g2_to_words(p: &G2Affine) -> [[Word; 2]; 2] {
+    if p.is_zero() {
+        return [[[0u8; 32]; 2]; 2];
+    }
+    [
+        [field_to_word(p.x.c1), field_to_word(p.x.c0)],
+        [field_to_word(p.y.c1), field_to_word(p.y.c0)],
+    ]
+}
+
+fn g1_from_words(
+    words: &[Word; 2],
+    first: usize,
+    point: &'static str,
+) -> Result<G1Affine, CalldataError> {
+    let x: Fq = field_from_word(&words[0], first)?;
+    let y: Fq = field_from_word(&words[1], first + 1)?;
+    if x.is_zero() && y.is_zero() {
+        return Ok(G1Affine::zero());
+    }
+    let p = G1Affine::new(x, y, false);
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(CalldataError::NotOnCurve { point });
+    }
+    Ok(p)
+}
+
+fn g2_from_words(
+    words: &[[Word; 2]; 2],
+    first: usize,
+    point: &'static str,
+) -> Result<G2Affine, CalldataError> {
+    let x = Fq2::new(
+        field_from_word(&words[0][1], first + 1)?,
+        field_from_word(&words[0][0], first)?,
+    );
+    let y = Fq2::new(
+        field_from_word(&words[1][1], first + 3)?,
+        field_from_word(&words[1][0], first + 2)?,
+    );
+    if x.is_zero() && y.is_zero() {
+        return Ok(G2Affine::zero());
+    }
+    let p = G2Affine::new(x, y, false);
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(CalldataError::NotOnCurve { point });
+    }
+    Ok(p)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use ark_groth16::Groth16;
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    #[test]
+    fn test_calldata_round_trip() {
+        let rng = &mut ark_std::test_rng();
+        let (a, b) = (Fr::rand(rng), Fr::rand(rng));
+        let (pk, vk) =
+            Groth16::<Bn254>::circuit_specific_setup(MultiplyDemoCircuit { a: None, b: None }, rng)
+                .unwrap();
+        for _ in 0..4 {
+            let circuit = MultiplyDemoCircuit {
+                a: Some(a),
+                b: Some(b),
+            };
+            let proof = Groth16::<Bn254>::prove(&pk, circuit, rng).unwrap();
+            let bytes = ProofCalldata::from_proof(&proof).to_bytes();
+            assert_eq!(bytes.len(), ProofCalldata::LEN);
+
+            let decoded = ProofCalldata::from_bytes(&bytes)
+                .unwrap()
+                .to_proof()
+                .unwrap();
+            assert_eq!(decoded, proof);
+            let inputs = inputs_from_words(&inputs_to_words(&[a * b])).unwrap();
+            assert!(Groth16::<Bn254>::verify(&vk, &inputs, &decoded).unwrap());
+        }
+
+        let zero = Proof::<Bn254>::default();
+        let calldata = ProofCalldata::from_proof(&zero);
+        assert!(calldata.to_bytes().iter().all(|&byte| byte == 0));
+        assert_eq!(calldata.to_proof().unwrap(), zero);
+    }
+
+    #[test]
+    fn test_calldata_rejects_invalid() {
+        assert_eq!(
+            ProofCalldata::from_bytes(&[0u8; 255]),
+            Err(CalldataError::Length {
+                expected: 256,
+                found: 255
+            })
+        );
+
+        // the field modulus itself is not canonical
+        let modulus = field_to_word(-Fq::from(1u8));
+        let mut modulus_word = modulus;
+        modulus_word[31] += 1;
+        let mut calldata = ProofCalldata::from_bytes(&[0u8; 256]).unwrap();
+        calldata.c[1] = modulus_word;
+        assert_eq!(
+            calldata.to_proof(),
+            Err(CalldataError::NotInField { word: 7 })
+        );
+        assert_eq!(
+            inputs_from_words(&[field_to_word(Fr::from(1u8)), [0xff; 32]]),
+            Err(CalldataError::NotInField { word: 1 })
+        );
+
+        // (1, 1) is not on y^2 = x^3 + 3
+        calldata.c = [field_to_word(Fq::from(1u8)), field_to_word(Fq::from(1u8))];
+        assert_eq!(
+            calldata.to_proof(),
+            Err(CalldataError::NotOnCurve { point: "c" })
+        );
+    }
+}
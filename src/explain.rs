@@ -0,0 +1,227 @@
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::PrimeField;
+use ark_groth16::{Proof, VerifyingKey};
+use num_bigint::BigUint;
+use std::fmt;
+
+use crate::text::hex_encode;
+
+// what a Groth16 verifier looks at, spelled out for debugging and teaching. The proof is
+// three points, A and C in G1 and B in G2. The public inputs x_1..x_n weigh the points
+// gamma_abc_g1[1..] of the verifying key into
+//   L = gamma_abc_g1[0] + x_1 * gamma_abc_g1[1] + ... + x_n * gamma_abc_g1[n]
+// and the proof is valid when
+//   e(A, B) = e(alpha, beta) * e(L, gamma) * e(C, delta)
+// `explain` redoes this step by step instead of calling `verify_proof`, so a failing
+// proof shows where it fails: a point outside the subgroup, a wrong number of inputs or
+// only the pairing equation.
+//
+//   gadgets-cli verify --vk keys/vk.bin --proof proof.bin --public 33 --explain
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PointCheck {
+    pub name: &'static str,
+    pub group: &'static str,
+    // compressed, as in `text::to_hex`
+    pub hex: String,
+    pub identity: bool,
+    // on the curve and of order r, the check of `Validation::Checked`: the compressed
+    // form decodes back to the same point
+    pub in_subgroup: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    pub points: Vec<PointCheck>,
+    // decimal
+    pub inputs: Vec<String>,
+    // gamma_abc_g1 has one point more
+    pub expected_inputs: usize,
+    // None when the number of inputs is wrong and there is no L
+    pub pairing_holds: Option<bool>,
+}
+
+impl Explanation {
+    pub fn is_valid(&self) -> bool {
+        self.points.iter().all(|point| point.in_subgroup) && self.pairing_holds == Some(true)
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        writeln!(f, "proof")?;
+        for point in &self.points {
+            writeln!(
+                f,
+                "  {}  {}  in subgroup: {:<3}  identity: {:<3}  {}",
+                point.name,
+                point.group,
+                yes_no(point.in_subgroup),
+                yes_no(point.identity),
+                point.hex
+            )?;
+        }
+        writeln!(
+            f,
+            "public inputs: {}, the key expects {}",
+            self.inputs.len(),
+            self.expected_inputs
+        )?;
+        for (i, input) in self.inputs.iter().enumerate() {
+            writeln!(f, "  x{} = {}", i + 1, input)?;
+        }
+        match self.pairing_holds {
+            None => writeln!(f, "no linear combination, the number of inputs is wrong"),
+            Some(holds) => {
+                write!(f, "linear combination\n  L = gamma_abc_g1[0]")?;
+                for (i, input) in self.inputs.iter().enumerate() {
+                    // zero inputs add nothing to L
+                    if input != "0" {
+                        write!(f, " + {} * gamma_abc_g1[{}]", input, i + 1)?;
+                    }
+                }
+                writeln!(f)?;
+                writeln!(f, "pairing check")?;
+                writeln!(
+                    f,
+                    "  e(A, B) = e(alpha, beta) * e(L, gamma) * e(C, delta): {}",
+                    if holds { "holds" } else { "fails" }
+                )
+            }
+        }
+    }
+}
+
+fn check_point<G: AffineCurve>(name: &'static str, group: &'static str, point: &G) -> PointCheck {
+    let mut bytes = Vec::with_capacity(point.serialized_size());
+    point.serialize(&mut bytes).expect("writing to a vector");
+    let decoded = G::deserialize(&bytes[..]).ok();
+    PointCheck {
+        name,
+        group,
+        hex: hex_encode(&bytes),
+        identity: point.is_zero(),
+        in_subgroup: decoded.as_ref() == Some(point),
+    }
+}
+
+pub fn explain_proof<E: PairingEngine>(proof: &Proof<E>) -> Vec<PointCheck> {
+    vec![
+        check_point("A", "G1", &proof.a),
+        check_point("B", "G2", &proof.b),
+        check_point("C", "G1", &proof.c),
+    ]
+}
+
+pub fn explain<E: PairingEngine>(
+    vk: &VerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::Fr],
+) -> Explanation {
+    let expected_inputs = vk.gamma_abc_g1.len() - 1;
+    let pairing_holds = (public_inputs.len() == expected_inputs).then(|| {
+        let l = public_inputs
+            .iter()
+            .zip(&vk.gamma_abc_g1[1..])
+            .fold(vk.gamma_abc_g1[0].into_projective(), |l, (x, point)| {
+                l + point.mul(x.into_repr())
+            });
+        E::pairing(proof.a, proof.b)
+            == E::pairing(vk.alpha_g1, vk.beta_g2)
+                * E::pairing(l.into_affine(), vk.gamma_g2)
+                * E::pairing(proof.c, vk.delta_g2)
+    });
+    Explanation {
+        points: explain_proof(proof),
+        inputs: public_inputs
+            .iter()
+            .map(|&x| Into::<BigUint>::into(x).to_string())
+            .collect(),
+        expected_inputs,
+        pairing_holds,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr, G1Affine};
+    use ark_ff::{Field, One, Zero};
+    use ark_groth16::Groth16;
+    use ark_snark::SNARK;
+
+    use crate::multiply_demo::{MultiplyDemoCircuit, MultiplyWitness};
+    use crate::text::to_hex;
+
+    fn multiply_proof() -> (VerifyingKey<Bls12_381>, Proof<Bls12_381>) {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(MultiplyDemoCircuit::blank(), rng)
+                .unwrap();
+        let witness = MultiplyWitness {
+            a: Fr::from(3u8),
+            b: Fr::from(11u8),
+        };
+        let proof =
+            Groth16::<Bls12_381>::prove(&pk, MultiplyDemoCircuit::from(witness), rng).unwrap();
+        (vk, proof)
+    }
+
+    #[test]
+    fn test_agrees_with_verify() {
+        let (vk, proof) = multiply_proof();
+        for input in [Fr::from(33u8), Fr::from(34u8), Fr::zero()].iter() {
+            let explanation = explain(&vk, &proof, &[*input]);
+            assert_eq!(
+                explanation.is_valid(),
+                Groth16::<Bls12_381>::verify(&vk, &[*input], &proof).unwrap()
+            );
+            assert!(explanation
+                .points
+                .iter()
+                .all(|p| p.in_subgroup && !p.identity));
+        }
+
+        let explanation = explain(&vk, &proof, &[Fr::from(33u8)]);
+        assert!(explanation.is_valid());
+        assert_eq!(explanation.inputs, ["33"]);
+        assert_eq!(explanation.points[0].hex, to_hex(&proof.a));
+        let text = explanation.to_string();
+        assert!(text.contains("L = gamma_abc_g1[0] + 33 * gamma_abc_g1[1]"));
+        assert!(text.contains("holds"));
+
+        // one input too many: no L to pair
+        let explanation = explain(&vk, &proof, &[Fr::one(), Fr::one()]);
+        assert_eq!(explanation.pairing_holds, None);
+        assert!(!explanation.is_valid());
+        assert!(explanation.to_string().contains("the key expects 1"));
+    }
+
+    #[test]
+    fn test_bad_points() {
+        let (vk, mut proof) = multiply_proof();
+        proof.a = G1Affine::zero();
+        let explanation = explain(&vk, &proof, &[Fr::from(33u8)]);
+        assert!(explanation.points[0].identity && explanation.points[0].in_subgroup);
+        assert_eq!(explanation.pairing_holds, Some(false));
+
+        // a point of the curve outside the subgroup of order r, the cofactor of G1 is
+        // large so almost every x on the curve gives one
+        let (vk, mut proof) = multiply_proof();
+        proof.c = (1u64..)
+            .filter_map(|x| G1Affine::get_point_from_x(x.into(), true))
+            .find(|p| !p.is_in_correct_subgroup_assuming_on_curve())
+            .unwrap();
+        let explanation = explain(&vk, &proof, &[Fr::from(33u8)]);
+        assert!(!explanation.points[2].in_subgroup);
+        assert!(!explanation.is_valid());
+        assert!(explanation.to_string().contains("C  G1  in subgroup: no"));
+
+        // and the x coordinate of a point with another y, off the curve
+        let mut off_curve = vk.alpha_g1;
+        off_curve.y.double_in_place();
+        proof.c = off_curve;
+        assert!(!explain(&vk, &proof, &[Fr::from(33u8)]).points[2].in_subgroup);
+    }
+}
@@ -0,0 +1,434 @@
+use ark_ff::{Field, PrimeField};
+use ark_relations::{
+    lc,
+    r1cs::{ConstraintSystemRef, LinearCombination, SynthesisError, Variable},
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::visualize::format_coefficient;
+
+// arithmetic expressions over named variables, compiled to R1CS by `Compiler`:
+//
+//   let x = compiler.witness("x", Some(x_val))?;
+//   let out = compiler.input("out", Some(out_val))?;
+//   compiler.enforce((x.clone() * x.clone() * x.clone() + x + Expr::constant(5u64)).equals(out))?;
+//
+// Sums and products with a constant only build linear combinations and are free. Every
+// product of two non-constant expressions costs one constraint, and one witness variable
+// for its value unless it is the top of an equation.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr<F: Field> {
+    Var(String),
+    Const(F),
+    Add(Box<Expr<F>>, Box<Expr<F>>),
+    Mul(Box<Expr<F>>, Box<Expr<F>>),
+    Neg(Box<Expr<F>>),
+}
+
+impl<F: Field> Expr<F> {
+    pub fn var(name: impl Into<String>) -> Self {
+        Expr::Var(name.into())
+    }
+
+    pub fn constant(value: impl Into<F>) -> Self {
+        Expr::Const(value.into())
+    }
+
+    pub fn equals(self, rhs: Self) -> Equation<F> {
+        Equation { lhs: self, rhs }
+    }
+
+    // the value under an assignment of the variables, None if one of them is missing
+    pub fn eval(&self, values: &BTreeMap<String, F>) -> Option<F> {
+        match self {
+            Expr::Var(name) => values.get(name).copied(),
+            Expr::Const(c) => Some(*c),
+            Expr::Add(a, b) => Some(a.eval(values)? + b.eval(values)?),
+            Expr::Mul(a, b) => Some(a.eval(values)? * b.eval(values)?),
+            Expr::Neg(a) => Some(-a.eval(values)?),
+        }
+    }
+}
+
+impl<F: Field> Add for Expr<F> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Expr::Add(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl<F: Field> Sub for Expr<F> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl<F: Field> Mul for Expr<F> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Expr::Mul(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl<F: Field> Neg for Expr<F> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Expr::Neg(Box::new(self))
+    }
+}
+
+impl<F: PrimeField> fmt::Display for Expr<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // operands that bind looser than the operator are put in parentheses
+        fn operand<F: PrimeField>(
+            f: &mut fmt::Formatter<'_>,
+            expr: &Expr<F>,
+            parenthesize: bool,
+        ) -> fmt::Result {
+            if parenthesize {
+                write!(f, "({})", expr)
+            } else {
+                write!(f, "{}", expr)
+            }
+        }
+
+        match self {
+            Expr::Var(name) => write!(f, "{}", name),
+            Expr::Const(c) => write!(f, "{}", format_coefficient(*c)),
+            Expr::Add(a, b) => match b.as_ref() {
+                Expr::Neg(b) => {
+                    write!(f, "{} - ", a)?;
+                    operand(f, b, matches!(b.as_ref(), Expr::Add(..)))
+                }
+                _ => write!(f, "{} + {}", a, b),
+            },
+            Expr::Mul(a, b) => {
+                operand(f, a, matches!(a.as_ref(), Expr::Add(..)))?;
+                write!(f, " * ")?;
+                operand(f, b, matches!(b.as_ref(), Expr::Add(..)))
+            }
+            Expr::Neg(a) => {
+                write!(f, "-")?;
+                operand(f, a, matches!(a.as_ref(), Expr::Add(..) | Expr::Mul(..)))
+            }
+        }
+    }
+}
+
+// lhs == rhs, enforced by `Compiler::enforce`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Equation<F: Field> {
+    pub lhs: Expr<F>,
+    pub rhs: Expr<F>,
+}
+
+impl<F: PrimeField> fmt::Display for Equation<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} == {}", self.lhs, self.rhs)
+    }
+}
+
+#[derive(Debug)]
+pub enum ExprError {
+    UnknownVariable(String),
+    Redefined(String),
+    Synthesis(SynthesisError),
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::UnknownVariable(name) => write!(f, "unknown variable `{}`", name),
+            ExprError::Redefined(name) => write!(f, "`{}` is already defined", name),
+            ExprError::Synthesis(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+impl From<SynthesisError> for ExprError {
+    fn from(e: SynthesisError) -> Self {
+        ExprError::Synthesis(e)
+    }
+}
+
+// for `?` in `generate_constraints`. A malformed expression has no closer
+// `SynthesisError` than `Unsatisfiable`, the name is lost.
+impl From<ExprError> for SynthesisError {
+    fn from(e: ExprError) -> Self {
+        match e {
+            ExprError::Synthesis(e) => e,
+            ExprError::UnknownVariable(_) | ExprError::Redefined(_) => {
+                SynthesisError::Unsatisfiable
+            }
+        }
+    }
+}
+
+// a compiled expression: a linear combination and its value when assigned, constants
+// always have their value
+struct Linear<F: Field> {
+    lc: LinearCombination<F>,
+    value: Option<F>,
+    constant: bool,
+}
+
+enum Compiled<F: Field> {
+    Linear(Linear<F>),
+    // a product of two non-constant linear combinations, not allocated yet
+    Product(Linear<F>, Linear<F>),
+}
+
+pub struct Compiler<F: Field> {
+    cs: ConstraintSystemRef<F>,
+    // every input, witness and definition by name
+    scope: BTreeMap<String, (LinearCombination<F>, Option<F>)>,
+}
+
+impl<F: Field> Compiler<F> {
+    pub fn new(cs: ConstraintSystemRef<F>) -> Self {
+        Self {
+            cs,
+            scope: BTreeMap::new(),
+        }
+    }
+
+    // a public input, `value` is None in setup mode
+    pub fn input(&mut self, name: &str, value: Option<F>) -> Result<Expr<F>, ExprError> {
+        self.check_unbound(name)?;
+        let var = self
+            .cs
+            .new_input_variable(|| value.ok_or(SynthesisError::AssignmentMissing))?;
+        Ok(self.bind(name, lc!() + var, value))
+    }
+
+    // a private witness, `value` is None in setup mode
+    pub fn witness(&mut self, name: &str, value: Option<F>) -> Result<Expr<F>, ExprError> {
+        self.check_unbound(name)?;
+        let var = self
+            .cs
+            .new_witness_variable(|| value.ok_or(SynthesisError::AssignmentMissing))?;
+        Ok(self.bind(name, lc!() + var, value))
+    }
+
+    // names the value of `expr`. A product is allocated as a new witness with one
+    // constraint, anything else stays a linear combination.
+    pub fn define(&mut self, name: &str, expr: Expr<F>) -> Result<Expr<F>, ExprError> {
+        self.check_unbound(name)?;
+        let linear = self.linear(&expr)?;
+        Ok(self.bind(name, linear.lc, linear.value))
+    }
+
+    // one constraint: `a * b = rhs` when either side is a product, `lhs * 1 = rhs` otherwise
+    pub fn enforce(&mut self, equation: Equation<F>) -> Result<(), ExprError> {
+        let Equation { lhs, rhs } = equation;
+        let (lhs, rhs) = match (self.compile(&lhs)?, self.compile(&rhs)?) {
+            (Compiled::Product(a, b), rhs) => ((a, b), self.allocate(rhs)?),
+            (lhs, Compiled::Product(a, b)) => ((a, b), self.allocate(lhs)?),
+            (lhs, rhs) => {
+                let one = Linear {
+                    lc: lc!() + Variable::One,
+                    value: Some(F::one()),
+                    constant: true,
+                };
+                ((self.allocate(lhs)?, one), self.allocate(rhs)?)
+            }
+        };
+        self.cs.enforce_constraint(lhs.0.lc, lhs.1.lc, rhs.lc)?;
+        Ok(())
+    }
+
+    // the value of an input, witness or definition, None in setup mode
+    pub fn value(&self, name: &str) -> Option<F> {
+        self.scope.get(name).and_then(|(_, value)| *value)
+    }
+
+    fn check_unbound(&self, name: &str) -> Result<(), ExprError> {
+        if self.scope.contains_key(name) {
+            return Err(ExprError::Redefined(name.to_string()));
+        }
+        Ok(())
+    }
+
+    fn bind(&mut self, name: &str, lc: LinearCombination<F>, value: Option<F>) -> Expr<F> {
+        self.scope.insert(name.to_string(), (lc, value));
+        Expr::var(name)
+    }
+
+    fn compile(&mut self, expr: &Expr<F>) -> Result<Compiled<F>, ExprError> {
+        Ok(match expr {
+            Expr::Var(name) => {
+                let (lc, value) = self
+                    .scope
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| ExprError::UnknownVariable(name.clone()))?;
+                Compiled::Linear(Linear {
+                    lc,
+                    value,
+                    constant: false,
+                })
+            }
+            Expr::Const(c) => Compiled::Linear(Linear {
+                lc: lc!() + (*c, Variable::One),
+                value: Some(*c),
+                constant: true,
+            }),
+            Expr::Add(a, b) => {
+                let (a, b) = (self.linear(a)?, self.linear(b)?);
+                Compiled::Linear(Linear {
+                    lc: a.lc + b.lc,
+                    value: a.value.zip(b.value).map(|(a, b)| a + b),
+                    constant: a.constant && b.constant,
+                })
+            }
+            Expr::Neg(a) => {
+                let a = self.linear(a)?;
+                Compiled::Linear(Linear {
+                    lc: -a.lc,
+                    value: a.value.map(|a| -a),
+                    constant: a.constant,
+                })
+            }
+            Expr::Mul(a, b) => match (self.linear(a)?, self.linear(b)?) {
+                (
+                    Linear {
+                        constant: true,
+                        value: Some(factor),
+                        ..
+                    },
+                    other,
+                )
+                | (
+                    other,
+                    Linear {
+                        constant: true,
+                        value: Some(factor),
+                        ..
+                    },
+                ) => Compiled::Linear(Linear {
+                    lc: other.lc * factor,
+                    value: other.value.map(|v| v * factor),
+                    constant: other.constant,
+                }),
+                (a, b) => Compiled::Product(a, b),
+            },
+        })
+    }
+
+    fn linear(&mut self, expr: &Expr<F>) -> Result<Linear<F>, ExprError> {
+        let compiled = self.compile(expr)?;
+        self.allocate(compiled)
+    }
+
+    // a product nested in a larger expression gets its own witness
+    fn allocate(&mut self, compiled: Compiled<F>) -> Result<Linear<F>, ExprError> {
+        match compiled {
+            Compiled::Linear(linear) => Ok(linear),
+            Compiled::Product(a, b) => {
+                let value = a.value.zip(b.value).map(|(a, b)| a * b);
+                let var = self
+                    .cs
+                    .new_witness_variable(|| value.ok_or(SynthesisError::AssignmentMissing))?;
+                self.cs.enforce_constraint(a.lc, b.lc, lc!() + var)?;
+                Ok(Linear {
+                    lc: lc!() + var,
+                    value,
+                    constant: false,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::Fr;
+    use ark_relations::r1cs::{ConstraintSystem, SynthesisMode};
+    use ark_std::UniformRand;
+
+    #[test]
+    fn test_compile() {
+        let rng = &mut ark_std::test_rng();
+        let (x_val, y_val) = (Fr::rand(rng), Fr::rand(rng));
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut c = Compiler::new(cs.clone());
+        let x = c.witness("x", Some(x_val)).unwrap();
+        let y = c.witness("y", Some(y_val)).unwrap();
+
+        // linear definitions are free
+        let sum = c
+            .define("sum", x.clone() + y.clone() * Expr::constant(2u64))
+            .unwrap();
+        assert_eq!(cs.num_constraints(), 0);
+        assert_eq!(c.value("sum"), Some(x_val + y_val.double()));
+
+        // (x + 2y) * (x - y) * 3 - 1 == out, one witness for the nested product
+        let out_val = (x_val + y_val.double()) * (x_val - y_val) * Fr::from(3u8) - Fr::from(1u8);
+        let out = c.input("out", Some(out_val)).unwrap();
+        let lhs = sum * (x - y) * Expr::constant(3u64) - Expr::constant(1u64);
+        c.enforce(lhs.equals(out)).unwrap();
+        assert_eq!(cs.num_constraints(), 2);
+        assert_eq!(cs.num_witness_variables(), 3);
+        assert!(cs.is_satisfied().unwrap());
+
+        // a product on the right is enforced directly
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut c = Compiler::new(cs.clone());
+        let x = c.witness("x", Some(x_val)).unwrap();
+        let wrong = c.input("wrong", Some(x_val)).unwrap();
+        c.enforce(wrong.equals(x.clone() * x)).unwrap();
+        assert_eq!(cs.num_constraints(), 1);
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        let mut c = Compiler::new(cs);
+        let x = c.witness("x", None).unwrap();
+        assert!(matches!(
+            c.witness("x", None),
+            Err(ExprError::Redefined(name)) if name == "x"
+        ));
+        assert!(matches!(
+            c.enforce(x.equals(Expr::var("y"))),
+            Err(ExprError::UnknownVariable(name)) if name == "y"
+        ));
+    }
+
+    #[test]
+    fn test_eval_and_display() {
+        let x = Expr::<Fr>::var("x");
+        let expr = x.clone() * x.clone() * x.clone() + x.clone() + Expr::constant(5u64);
+        assert_eq!(expr.to_string(), "x * x * x + x + 5");
+        assert_eq!(
+            ((x.clone() + Expr::constant(1u64)) * -x.clone()).to_string(),
+            "(x + 1) * -x"
+        );
+        assert_eq!(
+            (x.clone() - (x.clone() + Expr::constant(1u64))).to_string(),
+            "x - (x + 1)"
+        );
+        assert_eq!(
+            expr.clone().equals(Expr::var("out")).to_string(),
+            "x * x * x + x + 5 == out"
+        );
+
+        let mut values = BTreeMap::new();
+        assert_eq!(expr.eval(&values), None);
+        values.insert("x".to_string(), Fr::from(3u8));
+        assert_eq!(expr.eval(&values), Some(Fr::from(35u8)));
+    }
+}
@@ -0,0 +1,219 @@
+use ark_ff::{FftField, Zero};
+use ark_poly::{
+    univariate::DensePolynomial, EvaluationDomain, Polynomial, Radix2EvaluationDomain, UVPolynomial,
+};
+
+// polynomial building blocks used under the hood by Groth16 and Marlin:
+// a radix-2 domain H = {1, w, w^2, ..., w^(n-1)} lets us switch between the
+// coefficient form and the evaluation form of a polynomial in O(n log n)
+
+// smallest radix-2 domain holding at least `num_points` points
+pub fn domain_for<F: FftField>(num_points: usize) -> Option<Radix2EvaluationDomain<F>> {
+    Radix2EvaluationDomain::new(num_points)
+}
+
+// unique polynomial of degree < |H| with p(w^i) == evals[i] (inverse FFT)
+pub fn interpolate<F: FftField>(
+    domain: &Radix2EvaluationDomain<F>,
+    evals: &[F],
+) -> DensePolynomial<F> {
+    DensePolynomial::from_coefficients_vec(domain.ifft(evals))
+}
+
+// [p(1), p(w), ..., p(w^(n-1))] (forward FFT)
+pub fn evaluate<F: FftField>(
+    domain: &Radix2EvaluationDomain<F>,
+    poly: &DensePolynomial<F>,
+) -> Vec<F> {
+    domain.fft(&poly.coeffs)
+}
+
+// [p(g), p(g*w), ..., p(g*w^(n-1))] where g is the multiplicative generator of F,
+// i.e. evaluations over a coset gH which is disjoint from H
+pub fn coset_evaluate<F: FftField>(
+    domain: &Radix2EvaluationDomain<F>,
+    poly: &DensePolynomial<F>,
+) -> Vec<F> {
+    domain.coset_fft(&poly.coeffs)
+}
+
+// inverse of `coset_evaluate`
+pub fn coset_interpolate<F: FftField>(
+    domain: &Radix2EvaluationDomain<F>,
+    evals: &[F],
+) -> DensePolynomial<F> {
+    DensePolynomial::from_coefficients_vec(domain.coset_ifft(evals))
+}
+
+// divide p by the vanishing polynomial Z_H(X) = X^n - 1, returns (quotient, remainder)
+// p vanishes on every point of H iff the remainder is zero
+pub fn divide_by_vanishing<F: FftField>(
+    domain: &Radix2EvaluationDomain<F>,
+    poly: &DensePolynomial<F>,
+) -> (DensePolynomial<F>, DensePolynomial<F>) {
+    poly.divide_by_vanishing_poly(*domain)
+        .expect("radix-2 domains always divide")
+}
+
+pub fn vanishes_on_domain<F: FftField>(
+    domain: &Radix2EvaluationDomain<F>,
+    poly: &DensePolynomial<F>,
+) -> bool {
+    divide_by_vanishing(domain, poly).1.is_zero()
+}
+
+// given evaluations a_i, b_i, c_i over H with a_i * b_i == c_i, compute the quotient
+// h(X) = (a(X) * b(X) - c(X)) / Z_H(X). This is the same trick the Groth16 prover uses:
+// a*b - c is evaluated on the coset gH where Z_H is a non-zero constant, divided
+// pointwise, and interpolated back. h has degree <= n - 2 so n coset points are enough.
+pub fn quotient_polynomial<F: FftField>(
+    domain: &Radix2EvaluationDomain<F>,
+    a: &[F],
+    b: &[F],
+    c: &[F],
+) -> DensePolynomial<F> {
+    let a_coset = domain.coset_fft(&domain.ifft(a));
+    let b_coset = domain.coset_fft(&domain.ifft(b));
+    let c_coset = domain.coset_fft(&domain.ifft(c));
+
+    let mut h: Vec<F> = a_coset
+        .iter()
+        .zip(b_coset.iter())
+        .zip(c_coset.iter())
+        .map(|((a, b), c)| *a * b - c)
+        .collect();
+    domain.divide_by_vanishing_poly_on_coset_in_place(&mut h);
+    coset_interpolate(domain, &h)
+}
+
+// evaluate p at an arbitrary point using the Lagrange basis of H
+// (sum_i p(w^i) * L_i(tau)), without going back to coefficient form
+pub fn evaluate_from_evals<F: FftField>(
+    domain: &Radix2EvaluationDomain<F>,
+    evals: &[F],
+    tau: F,
+) -> F {
+    domain
+        .evaluate_all_lagrange_coefficients(tau)
+        .iter()
+        .zip(evals.iter())
+        .map(|(l, e)| *l * e)
+        .sum()
+}
+
+pub fn evaluate_at<F: FftField>(poly: &DensePolynomial<F>, point: F) -> F {
+    poly.evaluate(&point)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::Fr as BlsFr;
+    use ark_ff::{Field, One, PrimeField};
+    use proptest::prelude::*;
+
+    fn field() -> impl Strategy<Value = BlsFr> {
+        any::<[u8; 32]>().prop_map(|bytes| BlsFr::from_le_bytes_mod_order(&bytes))
+    }
+
+    // a polynomial of degree < `len`
+    fn poly(len: usize) -> impl Strategy<Value = DensePolynomial<BlsFr>> {
+        prop::collection::vec(field(), len).prop_map(DensePolynomial::from_coefficients_vec)
+    }
+
+    // a domain of 1 to 128 points and a polynomial filling it
+    fn domain_and_poly(
+    ) -> impl Strategy<Value = (Radix2EvaluationDomain<BlsFr>, DensePolynomial<BlsFr>)> {
+        (0usize..8).prop_flat_map(|log_size| {
+            let domain = domain_for::<BlsFr>(1 << log_size).unwrap();
+            (Just(domain), poly(domain.size()))
+        })
+    }
+
+    #[test]
+    fn test_domain_is_rounded_up() {
+        let domain = domain_for::<BlsFr>(5).unwrap();
+        assert_eq!(domain.size(), 8);
+        assert_eq!(domain.group_gen.pow([8u64]), BlsFr::one());
+        assert_ne!(domain.group_gen.pow([4u64]), BlsFr::one());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        #[test]
+        fn fft_roundtrip((domain, poly) in domain_and_poly()) {
+            let evals = evaluate(&domain, &poly);
+            prop_assert_eq!(evals.len(), domain.size());
+            for (i, e) in evals.iter().enumerate() {
+                prop_assert_eq!(*e, evaluate_at(&poly, domain.element(i)));
+            }
+            prop_assert_eq!(interpolate(&domain, &evals), poly);
+        }
+
+        #[test]
+        fn coset_fft_roundtrip((domain, poly) in domain_and_poly()) {
+            let g = BlsFr::multiplicative_generator();
+            let evals = coset_evaluate(&domain, &poly);
+            for (i, e) in evals.iter().enumerate() {
+                prop_assert_eq!(*e, evaluate_at(&poly, g * domain.element(i)));
+            }
+            prop_assert_eq!(coset_interpolate(&domain, &evals), poly);
+        }
+
+        #[test]
+        fn vanishing_polynomial_division(q in poly(11), r in poly(21)) {
+            let domain = domain_for::<BlsFr>(16).unwrap();
+            // q * Z_H vanishes on H and divides back to q exactly
+            let p = q.mul_by_vanishing_poly(domain);
+            for x in domain.elements() {
+                prop_assert!(evaluate_at(&p, x).is_zero());
+            }
+            let (quotient, remainder) = divide_by_vanishing(&domain, &p);
+            prop_assert_eq!(quotient, q);
+            prop_assert!(remainder.is_zero());
+            prop_assert!(vanishes_on_domain(&domain, &p));
+
+            // a random polynomial is (overwhelmingly likely) not a multiple of Z_H
+            prop_assert!(!vanishes_on_domain(&domain, &r));
+        }
+
+        #[test]
+        fn quotient_polynomial_divides(
+            a in prop::collection::vec(field(), 8),
+            b in prop::collection::vec(field(), 8),
+            tau in field(),
+        ) {
+            let domain = domain_for::<BlsFr>(8).unwrap();
+            prop_assume!(!domain.evaluate_vanishing_polynomial(tau).is_zero());
+            let c: Vec<BlsFr> = a.iter().zip(b.iter()).map(|(a, b)| *a * b).collect();
+
+            let h = quotient_polynomial(&domain, &a, &b, &c);
+            prop_assert!(h.degree() <= domain.size() - 2);
+
+            // h * Z_H == a * b - c as polynomials
+            let a_poly = interpolate(&domain, &a);
+            let b_poly = interpolate(&domain, &b);
+            let c_poly = interpolate(&domain, &c);
+            let lhs = h.mul_by_vanishing_poly(domain);
+            let rhs = &(&a_poly * &b_poly) - &c_poly;
+            prop_assert_eq!(lhs, rhs);
+
+            // at a point outside the domain
+            prop_assert_eq!(
+                evaluate_at(&h, tau) * domain.evaluate_vanishing_polynomial(tau),
+                evaluate_at(&a_poly, tau) * evaluate_at(&b_poly, tau) - evaluate_at(&c_poly, tau)
+            );
+        }
+
+        #[test]
+        fn evaluate_from_evals_matches(poly in poly(32), tau in field()) {
+            let domain = domain_for::<BlsFr>(32).unwrap();
+            let evals = evaluate(&domain, &poly);
+            prop_assert_eq!(
+                evaluate_from_evals(&domain, &evals, tau),
+                evaluate_at(&poly, tau)
+            );
+        }
+    }
+}
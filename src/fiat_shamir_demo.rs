@@ -0,0 +1,303 @@
+use ark_bls12_381::Fr;
+use ark_ff::{One, PrimeField, Zero};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::{
+    constraints::CryptographicSpongeVar,
+    poseidon::{constraints::PoseidonSpongeVar, PoseidonParameters, PoseidonSponge},
+    Absorb, CryptographicSponge, FieldBasedCryptographicSponge,
+};
+
+use crate::encoding::{field_from_bytes_mod_order, Endian};
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+
+// Fiat–Shamir inside a circuit: a challenge the prover can't choose, squeezed from a
+// Poseidon sponge (`poseidon_params`) that absorbed everything the prover committed to
+// before. `Transcript` is the native side, `TranscriptVar` the same in constraints with
+// the sponge gadget of ark-sponge, so a verifier that runs in a circuit (a recursive
+// verifier, a folding step) derives the challenges the native verifier would. Every
+// transcript starts with a label, and every message is absorbed with its length.
+//
+// The demo is a permutation check by a grand product: a public list is a shuffle of a
+// committed private one iff prod (r - a_i) == prod (r - b_i) as polynomials in r, and
+// checking it at one random r is wrong with probability at most n / |F|. 2n
+// multiplications instead of a sorting network. The r is random only if the prover
+// can't pick the values after seeing it: the transcript absorbs the commitment to them
+// first, if it didn't any list with the right product at the known r would pass.
+
+pub const LABEL: &[u8] = b"fiat_shamir_demo permutation";
+
+pub struct Transcript<F: PrimeField + Absorb> {
+    sponge: PoseidonSponge<F>,
+}
+
+impl<F: PrimeField + Absorb> Transcript<F> {
+    pub fn new(params: &PoseidonParameters<F>, label: &[u8]) -> Self {
+        let mut sponge = PoseidonSponge::new(params);
+        sponge.absorb(&field_from_bytes_mod_order::<F>(label, Endian::Little));
+        Self { sponge }
+    }
+
+    pub fn append(&mut self, message: &[F]) {
+        self.sponge.absorb(&F::from(message.len() as u64));
+        self.sponge.absorb(&message);
+    }
+
+    // the challenges of a transcript depend on all messages before them, and on the
+    // challenges before them
+    pub fn challenge(&mut self) -> F {
+        self.sponge.squeeze_native_field_elements(1)[0]
+    }
+}
+
+pub struct TranscriptVar<F: PrimeField> {
+    sponge: PoseidonSpongeVar<F>,
+}
+
+impl<F: PrimeField> TranscriptVar<F> {
+    pub fn new(
+        cs: ConstraintSystemRef<F>,
+        params: &PoseidonParameters<F>,
+        label: &[u8],
+    ) -> Result<Self, SynthesisError> {
+        let mut sponge = PoseidonSpongeVar::new(cs, params);
+        sponge.absorb(&FpVar::constant(field_from_bytes_mod_order(
+            label,
+            Endian::Little,
+        )))?;
+        Ok(Self { sponge })
+    }
+
+    pub fn append(&mut self, message: &[FpVar<F>]) -> Result<(), SynthesisError> {
+        self.sponge
+            .absorb(&FpVar::constant(F::from(message.len() as u64)))?;
+        self.sponge.absorb(&message)
+    }
+
+    pub fn challenge(&mut self) -> Result<FpVar<F>, SynthesisError> {
+        Ok(self.sponge.squeeze_field_elements(1)?.remove(0))
+    }
+}
+
+pub fn commitment(params: &PoseidonParameters<Fr>, blinding: Fr, values: &[Fr]) -> Fr {
+    let mut inputs = vec![blinding];
+    inputs.extend_from_slice(values);
+    hash_inputs(params, &inputs)
+}
+
+// the r of a permutation proof
+pub fn challenge(params: &PoseidonParameters<Fr>, commitment: Fr, shuffled: &[Fr]) -> Fr {
+    let mut transcript = Transcript::new(params, LABEL);
+    transcript.append(&[commitment]);
+    transcript.append(shuffled);
+    transcript.challenge()
+}
+
+// prod (r - v_i)
+pub fn grand_product(r: Fr, values: &[Fr]) -> Fr {
+    values
+        .iter()
+        .fold(Fr::one(), |product, v| product * (r - v))
+}
+
+#[derive(Clone)]
+pub struct PermutationCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub commitment: Fr,
+    pub shuffled: Vec<Fr>,
+    pub blinding: Option<Fr>,
+    pub values: Option<Vec<Fr>>,
+}
+
+impl PermutationCircuit {
+    // `shuffled` is public, the values stay behind the commitment
+    pub fn new(
+        params: PoseidonParameters<Fr>,
+        blinding: Fr,
+        values: Vec<Fr>,
+        shuffled: Vec<Fr>,
+    ) -> Self {
+        Self {
+            commitment: commitment(&params, blinding, &values),
+            params,
+            shuffled,
+            blinding: Some(blinding),
+            values: Some(values),
+        }
+    }
+
+    // the commitment and the shuffled list of `len` values are public inputs
+    pub fn blank(params: PoseidonParameters<Fr>, len: usize) -> Self {
+        Self {
+            params,
+            commitment: Fr::zero(),
+            shuffled: vec![Fr::zero(); len],
+            blinding: None,
+            values: None,
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs = vec![self.commitment];
+        inputs.extend_from_slice(&self.shuffled);
+        inputs
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for PermutationCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let commitment = FpVar::new_input(ns!(cs, "commitment"), || Ok(self.commitment))?;
+        let shuffled =
+            Vec::<FpVar<Fr>>::new_input(ns!(cs, "shuffled"), || Ok(self.shuffled.as_slice()))?;
+        let blinding = FpVar::new_witness(ns!(cs, "blinding"), || {
+            self.blinding.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let values = (0..shuffled.len())
+            .map(|i| {
+                FpVar::new_witness(ns!(cs, "value"), || {
+                    self.values
+                        .as_ref()
+                        .map(|v| v[i])
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut opened = vec![blinding];
+        opened.extend_from_slice(&values);
+        hash_inputs_var(cs.clone(), &self.params, &opened)?.enforce_equal(&commitment)?;
+
+        // r after the commitment, then the products
+        let mut transcript = TranscriptVar::new(cs, &self.params, LABEL)?;
+        transcript.append(&[commitment])?;
+        transcript.append(&shuffled)?;
+        let r = transcript.challenge()?;
+        let product = |values: &[FpVar<Fr>]| {
+            values
+                .iter()
+                .fold(FpVar::one(), |product, v| product * (&r - v))
+        };
+        product(&values).enforce_equal(&product(&shuffled))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    fn is_satisfied(circuit: PermutationCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_transcript() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters::<Fr>();
+        let messages: Vec<Vec<Fr>> = (0..3)
+            .map(|n| (0..n).map(|_| Fr::rand(rng)).collect())
+            .collect();
+
+        // two challenges, messages before and after the first
+        let mut transcript = Transcript::new(&params, LABEL);
+        transcript.append(&messages[2]);
+        let first = transcript.challenge();
+        transcript.append(&messages[1]);
+        transcript.append(&messages[0]);
+        let second = transcript.challenge();
+        assert_ne!(first, second);
+
+        let cs = ConstraintSystem::new_ref();
+        let vars: Vec<Vec<FpVar<Fr>>> = messages
+            .iter()
+            .map(|m| Vec::new_witness(cs.clone(), || Ok(m.as_slice())).unwrap())
+            .collect();
+        let mut transcript_var = TranscriptVar::new(cs.clone(), &params, LABEL).unwrap();
+        transcript_var.append(&vars[2]).unwrap();
+        assert_eq!(transcript_var.challenge().unwrap().value().unwrap(), first);
+        transcript_var.append(&vars[1]).unwrap();
+        transcript_var.append(&vars[0]).unwrap();
+        assert_eq!(transcript_var.challenge().unwrap().value().unwrap(), second);
+        assert!(cs.is_satisfied().unwrap());
+
+        // another label, the same message split differently
+        let mut other = Transcript::new(&params, b"another protocol");
+        other.append(&messages[2]);
+        assert_ne!(other.challenge(), first);
+        let mut other = Transcript::new(&params, LABEL);
+        other.append(&messages[2][..1]);
+        other.append(&messages[2][1..]);
+        assert_ne!(other.challenge(), first);
+    }
+
+    #[test]
+    fn test_permutation() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let values: Vec<Fr> = (0..8u8).map(|i| Fr::from(i) * Fr::from(11u8)).collect();
+        let shuffled: Vec<Fr> = [3, 7, 0, 5, 1, 6, 2, 4]
+            .iter()
+            .map(|&i| values[i])
+            .collect();
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            PermutationCircuit::blank(params.clone(), values.len()),
+            rng,
+        )
+        .unwrap();
+
+        let circuit =
+            PermutationCircuit::new(params.clone(), Fr::rand(rng), values.clone(), shuffled);
+        let inputs = circuit.public_inputs();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        // another order is also a shuffle but another statement
+        let mut other = inputs.clone();
+        other.swap(1, 2);
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &other, &proof).unwrap());
+
+        // repeated values count
+        let mut repeated = values.clone();
+        repeated[1] = repeated[0];
+        let circuit = PermutationCircuit::new(params, Fr::rand(rng), repeated, values);
+        assert!(!is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_challenge_binds_the_commitment() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let shuffled: Vec<Fr> = (0..4).map(|_| Fr::rand(rng)).collect();
+
+        // r from the public list alone, the prover knows it before choosing the values
+        // and can make the products equal for a list that is no shuffle
+        let mut transcript = Transcript::new(&params, LABEL);
+        transcript.append(&shuffled);
+        let r = transcript.challenge();
+        let mut forged = shuffled.clone();
+        forged[0] += Fr::one();
+        let rest = (r - shuffled[0]) * (r - shuffled[1]) / (r - forged[0]);
+        forged[1] = r - rest;
+        assert_eq!(grand_product(r, &forged), grand_product(r, &shuffled));
+
+        // with the commitment in the transcript the forgery's r is another one
+        let blinding = Fr::rand(rng);
+        let forged_r = challenge(&params, commitment(&params, blinding, &forged), &shuffled);
+        assert_ne!(
+            grand_product(forged_r, &forged),
+            grand_product(forged_r, &shuffled)
+        );
+        let circuit = PermutationCircuit::new(params, blinding, forged, shuffled);
+        assert!(!is_satisfied(circuit));
+    }
+}
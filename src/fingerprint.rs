@@ -0,0 +1,381 @@
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{ConstraintMatrices, ConstraintSynthesizer, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use blake2::{Blake2s, Digest};
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::persist::{self, ArtifactKind, PersistError, Validation};
+use crate::visualize::constraint_matrices;
+
+// short stable identities for keys and circuits, so a service can pin the circuit
+// version it accepts proofs for and notice a redeployed key or a changed circuit
+// before it verifies anything. Both are Blake2s hashes with a domain label:
+//   key      the compressed serialization, any backend's key (Groth16, Marlin, ...)
+//   circuit  the field modulus, the variable counts and every entry of A, B and C,
+//            from a setup mode synthesis, so no witness is needed
+// Two setups of one circuit have the same circuit fingerprint and different key
+// fingerprints. The circuit fingerprint changes with any constraint, coefficient or
+// variable order, not with namespaces or the witness.
+//
+// `persist::write_artifact_for_circuit` keeps the circuit fingerprint in the envelope of
+// a key, and `read_for_circuit`/`load_for_circuit` only hand the key out for a circuit
+// with that fingerprint, so a verifier that synthesizes its own (blank) circuit refuses
+// a key made for another version of it instead of rejecting every proof, or worse,
+// accepting proofs of the old statement.
+
+const KEY_LABEL: &[u8] = b"gadgets key fingerprint v1";
+const CIRCUIT_LABEL: &[u8] = b"gadgets circuit fingerprint v1";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Fingerprint(pub [u8; 32]);
+
+impl Fingerprint {
+    fn from_hasher(hasher: Blake2s) -> Self {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&hasher.finalize());
+        Fingerprint(bytes)
+    }
+}
+
+// lowercase hex, 64 digits
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = FingerprintError;
+
+    fn from_str(hex: &str) -> Result<Self, Self::Err> {
+        let hex = hex.trim();
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(FingerprintError::InvalidHex(hex.to_string()));
+        }
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+                .map_err(|_| FingerprintError::InvalidHex(hex.to_string()))?;
+        }
+        Ok(Fingerprint(bytes))
+    }
+}
+
+#[derive(Debug)]
+pub enum FingerprintError {
+    Mismatch {
+        expected: Fingerprint,
+        found: Fingerprint,
+    },
+    InvalidHex(String),
+    // a version 1 envelope, made before circuits were recorded
+    Missing,
+    Persist(PersistError),
+    Serialization(ark_serialize::SerializationError),
+    Synthesis(SynthesisError),
+}
+
+impl fmt::Display for FingerprintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FingerprintError::Mismatch { expected, found } => {
+                write!(
+                    f,
+                    "fingerprint mismatch: expected {}, found {}",
+                    expected, found
+                )
+            }
+            FingerprintError::InvalidHex(hex) => write!(f, "invalid fingerprint {:?}", hex),
+            FingerprintError::Missing => write!(f, "the envelope records no circuit fingerprint"),
+            FingerprintError::Persist(e) => write!(f, "{}", e),
+            FingerprintError::Serialization(e) => write!(f, "serialization error: {}", e),
+            FingerprintError::Synthesis(e) => write!(f, "synthesis error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FingerprintError {}
+
+impl From<ark_serialize::SerializationError> for FingerprintError {
+    fn from(e: ark_serialize::SerializationError) -> Self {
+        FingerprintError::Serialization(e)
+    }
+}
+
+impl From<PersistError> for FingerprintError {
+    fn from(e: PersistError) -> Self {
+        FingerprintError::Persist(e)
+    }
+}
+
+impl From<SynthesisError> for FingerprintError {
+    fn from(e: SynthesisError) -> Self {
+        FingerprintError::Synthesis(e)
+    }
+}
+
+pub fn key_fingerprint<K: CanonicalSerialize>(key: &K) -> Result<Fingerprint, FingerprintError> {
+    let mut bytes = Vec::new();
+    key.serialize(&mut bytes)?;
+    let mut hasher = Blake2s::new();
+    hasher.update(KEY_LABEL);
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(&bytes);
+    Ok(Fingerprint::from_hasher(hasher))
+}
+
+pub fn matrices_fingerprint<F: PrimeField>(matrices: &ConstraintMatrices<F>) -> Fingerprint {
+    let mut hasher = Blake2s::new();
+    hasher.update(CIRCUIT_LABEL);
+    for limb in F::characteristic() {
+        hasher.update(limb.to_le_bytes());
+    }
+    for &count in [
+        matrices.num_instance_variables,
+        matrices.num_witness_variables,
+        matrices.num_constraints,
+    ]
+    .iter()
+    {
+        hasher.update((count as u64).to_le_bytes());
+    }
+    let mut coeff = Vec::new();
+    for matrix in [&matrices.a, &matrices.b, &matrices.c].iter() {
+        for row in matrix.iter() {
+            hasher.update((row.len() as u64).to_le_bytes());
+            for (value, column) in row.iter() {
+                hasher.update((*column as u64).to_le_bytes());
+                coeff.clear();
+                value.serialize(&mut coeff).unwrap();
+                hasher.update(&coeff);
+            }
+        }
+    }
+    Fingerprint::from_hasher(hasher)
+}
+
+pub fn circuit_fingerprint<F, C>(circuit: C) -> Result<Fingerprint, SynthesisError>
+where
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+{
+    Ok(matrices_fingerprint(&constraint_matrices(circuit)?))
+}
+
+// Ok if the key is the pinned one
+pub fn check_key<K: CanonicalSerialize>(
+    key: &K,
+    expected: Fingerprint,
+) -> Result<(), FingerprintError> {
+    check(expected, key_fingerprint(key)?)
+}
+
+// Ok if the circuit has the pinned shape, e.g. to refuse to prove with a circuit that
+// changed since its keys were made
+pub fn check_circuit<F, C>(circuit: C, expected: Fingerprint) -> Result<(), FingerprintError>
+where
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+{
+    check(expected, circuit_fingerprint(circuit)?)
+}
+
+// the artifact of a version 2 envelope whose circuit fingerprint is the one of `circuit`,
+// a blank of the circuit the caller is about to verify (or prove) for
+pub fn read_for_circuit<T, F, C, R>(
+    reader: R,
+    kind: ArtifactKind,
+    validation: Validation,
+    circuit: C,
+) -> Result<T, FingerprintError>
+where
+    T: CanonicalDeserialize,
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+    R: Read,
+{
+    let (artifact, recorded) = persist::read_artifact_with_circuit(reader, kind, validation)?;
+    let recorded = recorded.ok_or(FingerprintError::Missing)?;
+    check_circuit(circuit, Fingerprint(recorded))?;
+    Ok(artifact)
+}
+
+pub fn load_for_circuit<T, F, C, P>(
+    path: P,
+    kind: ArtifactKind,
+    validation: Validation,
+    circuit: C,
+) -> Result<T, FingerprintError>
+where
+    T: CanonicalDeserialize,
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+    P: AsRef<Path>,
+{
+    read_for_circuit(persist::open(path)?, kind, validation, circuit)
+}
+
+fn check(expected: Fingerprint, found: Fingerprint) -> Result<(), FingerprintError> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(FingerprintError::Mismatch { expected, found })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::CubicDemoCircuit;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use crate::padding::Padded;
+    use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+    use ark_bn254::Fr as BnFr;
+    use ark_groth16::{Groth16, VerifyingKey};
+    use ark_serialize::CanonicalDeserialize;
+    use ark_snark::SNARK;
+
+    // the multiply circuit over BLS12-381, it must keep its shape
+    const MULTIPLY: &str = "9c2ae6713567260ace086f1e6dabf8c02250811575923249ed27a9e0703aa67f";
+
+    #[test]
+    fn test_circuit_fingerprint() {
+        let blank = MultiplyDemoCircuit::<BlsFr>::blank;
+        let multiply = circuit_fingerprint(blank()).unwrap();
+        assert_eq!(multiply.to_string(), MULTIPLY);
+
+        // the witness doesn't matter, the constraints and the field do
+        let filled = MultiplyDemoCircuit {
+            a: Some(BlsFr::from(3u8)),
+            b: Some(BlsFr::from(5u8)),
+        };
+        assert_eq!(circuit_fingerprint(filled).unwrap(), multiply);
+        let others = [
+            circuit_fingerprint(CubicDemoCircuit::<BlsFr>::blank()).unwrap(),
+            circuit_fingerprint(Padded::new(blank(), 2, 0)).unwrap(),
+            circuit_fingerprint(Padded::new(blank(), 1, 5)).unwrap(),
+            circuit_fingerprint(MultiplyDemoCircuit::<BnFr>::blank()).unwrap(),
+        ];
+        for (i, other) in others.iter().enumerate() {
+            assert_ne!(*other, multiply, "{}", i);
+        }
+
+        assert!(check_circuit(blank(), MULTIPLY.parse().unwrap()).is_ok());
+        let result = check_circuit(CubicDemoCircuit::<BlsFr>::blank(), multiply);
+        assert!(
+            matches!(result, Err(FingerprintError::Mismatch { expected, .. }) if expected == multiply)
+        );
+    }
+
+    #[test]
+    fn test_key_fingerprint() {
+        let rng = &mut ark_std::test_rng();
+        let blank = MultiplyDemoCircuit::<BlsFr>::blank();
+        let (_, vk) = Groth16::<Bls12_381>::circuit_specific_setup(blank, rng).unwrap();
+        let (_, other) = Groth16::<Bls12_381>::circuit_specific_setup(blank, rng).unwrap();
+        let pinned = key_fingerprint(&vk).unwrap();
+        assert_ne!(key_fingerprint(&other).unwrap(), pinned);
+
+        // a key that went through any encoding is the same key
+        let mut bytes = Vec::new();
+        vk.serialize_uncompressed(&mut bytes).unwrap();
+        let loaded = VerifyingKey::<Bls12_381>::deserialize_uncompressed(&bytes[..]).unwrap();
+        assert_eq!(key_fingerprint(&loaded).unwrap(), pinned);
+        assert!(check_key(&loaded, pinned).is_ok());
+
+        // one element changed
+        let mut changed = vk.clone();
+        changed.gamma_abc_g1.swap(0, 1);
+        let result = check_key(&changed, pinned);
+        assert!(matches!(result, Err(FingerprintError::Mismatch { .. })));
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains(&pinned.to_string()));
+    }
+
+    #[test]
+    fn test_key_for_circuit_version() {
+        use crate::persist::{write_artifact, write_artifact_for_circuit, SerializationMode};
+
+        // version 2 of the circuit pads version 1 with a constraint
+        let v1 = MultiplyDemoCircuit::<BlsFr>::blank;
+        let v2 = || Padded::new(v1(), 2, 0);
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(v1(), rng).unwrap();
+        let mut bytes = Vec::new();
+        write_artifact_for_circuit(
+            &vk,
+            ArtifactKind::VerifyingKey,
+            SerializationMode::Compressed,
+            circuit_fingerprint(v1()).unwrap().0,
+            &mut bytes,
+        )
+        .unwrap();
+
+        let loaded: VerifyingKey<Bls12_381> = read_for_circuit(
+            &bytes[..],
+            ArtifactKind::VerifyingKey,
+            Validation::Checked,
+            v1(),
+        )
+        .unwrap();
+        let circuit = MultiplyDemoCircuit {
+            a: Some(BlsFr::from(3u8)),
+            b: Some(BlsFr::from(5u8)),
+        };
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&loaded, &[BlsFr::from(15u8)], &proof).unwrap());
+
+        // a verifier on the next version refuses the key before it sees a proof
+        let result: Result<VerifyingKey<Bls12_381>, _> = read_for_circuit(
+            &bytes[..],
+            ArtifactKind::VerifyingKey,
+            Validation::Checked,
+            v2(),
+        );
+        assert!(
+            matches!(result, Err(FingerprintError::Mismatch { expected, found })
+                if expected == circuit_fingerprint(v1()).unwrap()
+                    && found == circuit_fingerprint(v2()).unwrap())
+        );
+
+        // a key from before fingerprints were recorded is refused too
+        let mut bytes = Vec::new();
+        write_artifact(
+            &vk,
+            ArtifactKind::VerifyingKey,
+            SerializationMode::Compressed,
+            &mut bytes,
+        )
+        .unwrap();
+        let result: Result<VerifyingKey<Bls12_381>, _> = read_for_circuit(
+            &bytes[..],
+            ArtifactKind::VerifyingKey,
+            Validation::Checked,
+            v1(),
+        );
+        assert!(matches!(result, Err(FingerprintError::Missing)));
+    }
+
+    #[test]
+    fn test_hex() {
+        let fingerprint = circuit_fingerprint(CubicDemoCircuit::<BlsFr>::blank()).unwrap();
+        let hex = fingerprint.to_string();
+        assert_eq!(hex.len(), 64);
+        assert_eq!(hex.parse::<Fingerprint>().unwrap(), fingerprint);
+        assert_eq!(
+            hex.to_uppercase().parse::<Fingerprint>().unwrap(),
+            fingerprint
+        );
+        for wrong in [&hex[1..], "", &hex.replace(&hex[..1], "g")].iter() {
+            assert!(wrong.parse::<Fingerprint>().is_err());
+        }
+    }
+}
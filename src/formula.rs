@@ -0,0 +1,420 @@
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::expr::{Compiler, Equation, Expr};
+
+// toy circuits from a single equation, e.g. "x^3 + x + 5 == out":
+//
+//   equation := expr "==" expr
+//   expr     := term (("+" | "-") term)*
+//   term     := unary ("*" unary)*
+//   unary    := "-" unary | power
+//   power    := atom ("^" integer)?
+//   atom     := name | integer | "(" expr ")"
+//
+// Variables on the right hand side are the public inputs, the others are witnesses, both
+// in order of first appearance. `x^n` is expanded into n - 1 multiplications.
+
+// exponents are unrolled, keep the circuit small
+pub const MAX_EXPONENT: u32 = 64;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FormulaError {
+    // `at` is a byte offset into the formula
+    Syntax { at: usize, message: String },
+    Missing(String),
+    Unsatisfied,
+}
+
+impl fmt::Display for FormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormulaError::Syntax { at, message } => write!(f, "at offset {}: {}", at, message),
+            FormulaError::Missing(name) => write!(f, "no value for `{}`", name),
+            FormulaError::Unsatisfied => write!(f, "the values do not satisfy the formula"),
+        }
+    }
+}
+
+impl std::error::Error for FormulaError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Formula<F: PrimeField> {
+    pub equation: Equation<F>,
+    pub inputs: Vec<String>,
+    pub witnesses: Vec<String>,
+}
+
+impl<F: PrimeField> Formula<F> {
+    pub fn parse(formula: &str) -> Result<Self, FormulaError> {
+        let mut parser = Parser {
+            tokens: tokenize(formula)?,
+            pos: 0,
+            end: formula.len(),
+        };
+        let lhs = parser.expr()?;
+        parser.expect(&Token::Eq, "`==`")?;
+        let rhs = parser.expr()?;
+        if let Some((at, _)) = parser.tokens.get(parser.pos) {
+            return Err(syntax(*at, "expected the end of the formula"));
+        }
+
+        let mut inputs = Vec::new();
+        variables(&rhs, &mut inputs);
+        let mut witnesses = Vec::new();
+        variables(&lhs, &mut witnesses);
+        witnesses.retain(|name| !inputs.contains(name));
+        Ok(Self {
+            equation: lhs.equals(rhs),
+            inputs,
+            witnesses,
+        })
+    }
+
+    // no assignment, for setup
+    pub fn blank(&self) -> FormulaCircuit<F> {
+        FormulaCircuit {
+            formula: self.clone(),
+            values: None,
+        }
+    }
+
+    // the circuit for proving. A right hand side that is a single variable is computed
+    // when no value is given for it, every other variable needs a value.
+    pub fn assign(&self, values: &BTreeMap<String, F>) -> Result<FormulaCircuit<F>, FormulaError> {
+        let mut values = values.clone();
+        if let Expr::Var(out) = &self.equation.rhs {
+            if !values.contains_key(out) {
+                if let Some(value) = self.equation.lhs.eval(&values) {
+                    values.insert(out.clone(), value);
+                }
+            }
+        }
+        if let Some(name) = self
+            .witnesses
+            .iter()
+            .chain(&self.inputs)
+            .find(|name| !values.contains_key(*name))
+        {
+            return Err(FormulaError::Missing(name.clone()));
+        }
+        if self.equation.lhs.eval(&values) != self.equation.rhs.eval(&values) {
+            return Err(FormulaError::Unsatisfied);
+        }
+        Ok(FormulaCircuit {
+            formula: self.clone(),
+            values: Some(values),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FormulaCircuit<F: PrimeField> {
+    pub formula: Formula<F>,
+    pub values: Option<BTreeMap<String, F>>,
+}
+
+impl<F: PrimeField> FormulaCircuit<F> {
+    // in the order the verifier takes them, None without an assignment
+    pub fn public_inputs(&self) -> Option<Vec<F>> {
+        let values = self.values.as_ref()?;
+        self.formula
+            .inputs
+            .iter()
+            .map(|name| values.get(name).copied())
+            .collect()
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for FormulaCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let value = |name: &String| self.values.as_ref().and_then(|v| v.get(name).copied());
+        let mut compiler = Compiler::new(cs);
+        for name in &self.formula.witnesses {
+            compiler.witness(name, value(name))?;
+        }
+        for name in &self.formula.inputs {
+            compiler.input(name, value(name))?;
+        }
+        compiler.enforce(self.formula.equation)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Name(String),
+    Number(String),
+    Plus,
+    Minus,
+    Star,
+    Caret,
+    Open,
+    Close,
+    Eq,
+}
+
+fn syntax(at: usize, message: impl Into<String>) -> FormulaError {
+    FormulaError::Syntax {
+        at,
+        message: message.into(),
+    }
+}
+
+fn tokenize(formula: &str) -> Result<Vec<(usize, Token)>, FormulaError> {
+    let mut tokens = Vec::new();
+    let mut chars = formula.char_indices().peekable();
+    while let Some((at, ch)) = chars.next() {
+        let token = match ch {
+            ' ' | '\t' | '\n' => continue,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '^' => Token::Caret,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '=' if chars.peek().map(|&(_, c)| c) == Some('=') => {
+                chars.next();
+                Token::Eq
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(&(_, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                if word.chars().all(|c| c.is_ascii_digit()) {
+                    Token::Number(word)
+                } else if c.is_ascii_digit() {
+                    return Err(syntax(at, format!("invalid name `{}`", word)));
+                } else {
+                    Token::Name(word)
+                }
+            }
+            c => return Err(syntax(at, format!("unexpected `{}`", c))),
+        };
+        tokens.push((at, token));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    // offset reported for a formula that ends too early
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |&(at, _)| at)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, token: &Token, what: &str) -> Result<(), FormulaError> {
+        if !self.eat(token) {
+            return Err(syntax(self.offset(), format!("expected {}", what)));
+        }
+        Ok(())
+    }
+
+    fn expr<F: PrimeField>(&mut self) -> Result<Expr<F>, FormulaError> {
+        let mut expr = self.term()?;
+        loop {
+            if self.eat(&Token::Plus) {
+                expr = expr + self.term()?;
+            } else if self.eat(&Token::Minus) {
+                expr = expr - self.term()?;
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn term<F: PrimeField>(&mut self) -> Result<Expr<F>, FormulaError> {
+        let mut term = self.unary()?;
+        while self.eat(&Token::Star) {
+            term = term * self.unary()?;
+        }
+        Ok(term)
+    }
+
+    fn unary<F: PrimeField>(&mut self) -> Result<Expr<F>, FormulaError> {
+        if self.eat(&Token::Minus) {
+            return Ok(-self.unary()?);
+        }
+        let base = self.atom()?;
+        if !self.eat(&Token::Caret) {
+            return Ok(base);
+        }
+        let at = self.offset();
+        let exponent = match self.peek() {
+            Some(Token::Number(n)) => n.parse::<u32>().ok().filter(|&n| n <= MAX_EXPONENT),
+            _ => return Err(syntax(at, "expected an integer exponent")),
+        }
+        .ok_or_else(|| syntax(at, format!("exponents go up to {}", MAX_EXPONENT)))?;
+        self.pos += 1;
+        Ok((1..exponent).fold(
+            if exponent == 0 {
+                Expr::constant(1u64)
+            } else {
+                base.clone()
+            },
+            |power, _| power * base.clone(),
+        ))
+    }
+
+    fn atom<F: PrimeField>(&mut self) -> Result<Expr<F>, FormulaError> {
+        let at = self.offset();
+        match self.tokens.get(self.pos).map(|(_, token)| token.clone()) {
+            Some(Token::Name(name)) => {
+                self.pos += 1;
+                Ok(Expr::var(name))
+            }
+            Some(Token::Number(n)) => {
+                self.pos += 1;
+                n.parse()
+                    .map(Expr::Const)
+                    .map_err(|_| syntax(at, format!("`{}` is not a field element", n)))
+            }
+            Some(Token::Open) => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                self.expect(&Token::Close, "`)`")?;
+                Ok(expr)
+            }
+            _ => Err(syntax(at, "expected a name, a number or `(`")),
+        }
+    }
+}
+
+// names in order of first appearance
+fn variables<F: PrimeField>(expr: &Expr<F>, names: &mut Vec<String>) {
+    match expr {
+        Expr::Var(name) => {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        Expr::Const(_) => {}
+        Expr::Add(a, b) | Expr::Mul(a, b) => {
+            variables(a, names);
+            variables(b, names);
+        }
+        Expr::Neg(a) => variables(a, names),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::CubicDemoCircuit;
+    use crate::matrices::named_constraint_matrices;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::Groth16;
+    use ark_snark::SNARK;
+
+    fn values(pairs: &[(&str, u64)]) -> BTreeMap<String, Fr> {
+        pairs
+            .iter()
+            .map(|&(name, value)| (name.to_string(), Fr::from(value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let formula = Formula::<Fr>::parse("x^3 + x + 5 == out").unwrap();
+        assert_eq!(formula.witnesses, vec!["x"]);
+        assert_eq!(formula.inputs, vec!["out"]);
+        assert_eq!(formula.equation.to_string(), "x * x * x + x + 5 == out");
+
+        // x^0 is the constant 1, x is not a variable of the circuit
+        let formula = Formula::<Fr>::parse("-(a - b) * (a+b) == c - a*2 + x^0").unwrap();
+        assert_eq!(formula.witnesses, vec!["b"]);
+        assert_eq!(formula.inputs, vec!["c", "a"]);
+        assert_eq!(
+            formula.equation.to_string(),
+            "-(a - b) * (a + b) == c - a * 2 + 1"
+        );
+
+        for (formula, at) in [
+            ("x + == y", 4),
+            ("x == y)", 6),
+            ("x * (y == z", 7),
+            ("x^y == z", 2),
+            ("x^65 == z", 2),
+            ("x = y", 2),
+            ("2x == y", 0),
+            ("x + y", 5),
+        ]
+        .iter()
+        {
+            match Formula::<Fr>::parse(formula) {
+                Err(FormulaError::Syntax { at: found, .. }) => {
+                    assert_eq!(found, *at, "{}", formula)
+                }
+                other => panic!("{}: {:?}", formula, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_formula_circuit() {
+        // the same constraint system as the hand written cubic circuit
+        let formula = Formula::<Fr>::parse("x^3 + x + 5 == out").unwrap();
+        let ours = named_constraint_matrices(formula.blank()).unwrap().matrices;
+        let cubic = named_constraint_matrices(CubicDemoCircuit::<Fr>::blank())
+            .unwrap()
+            .matrices;
+        assert_eq!((ours.a, ours.b, ours.c), (cubic.a, cubic.b, cubic.c));
+
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(formula.blank(), rng).unwrap();
+        let circuit = formula.assign(&values(&[("x", 3)])).unwrap();
+        let inputs = circuit.public_inputs().unwrap();
+        assert_eq!(inputs, vec![Fr::from(35u8)]);
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        assert_eq!(
+            formula.assign(&values(&[("x", 3), ("out", 36)])).err(),
+            Some(FormulaError::Unsatisfied)
+        );
+        assert_eq!(
+            formula.assign(&values(&[("out", 35)])).err(),
+            Some(FormulaError::Missing("x".to_string()))
+        );
+
+        // a right hand side that is not a single variable is never computed
+        let formula = Formula::<Fr>::parse("a * b == c + d").unwrap();
+        assert_eq!(
+            formula
+                .assign(&values(&[("a", 3), ("b", 5), ("c", 10)]))
+                .err(),
+            Some(FormulaError::Missing("d".to_string()))
+        );
+        let circuit = formula
+            .assign(&values(&[("a", 3), ("b", 5), ("c", 10), ("d", 5)]))
+            .unwrap();
+        assert_eq!(
+            circuit.public_inputs().unwrap(),
+            vec![Fr::from(10u8), Fr::from(5u8)]
+        );
+    }
+}
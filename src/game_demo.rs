@@ -0,0 +1,177 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+
+use crate::private_index_demo::{one_hot, select_index};
+
+// A winning move in a subtraction game, a one pile Nim: the players take turns removing
+// a number of stones from the pile that the rules allow, whoever takes the last stone
+// wins. A position is winning when some allowed move leaves the opponent a losing one,
+//   win(p) = any(allowed(t) && t <= p && !win(p - t)), win(0) = false,
+// a recursion a circuit can't run: it has no loops or calls whose depth depends on the
+// inputs. The bounds make it a fixed computation, the pile has at most `MAX_STONES`
+// stones, so the recursion is unrolled into the table of win(0..=MAX_STONES) built
+// bottom up, each entry from the `MAX_TAKE` before it (the memoized game tree, unrolled
+// as a tree it would have up to MAX_TAKE^MAX_STONES nodes). The rules are public input
+// bits, so the table is computed in the circuit and not a constant, and the data
+// dependent steps, the rule of the move and the entry of the pile after it, are selects
+// with one-hot selectors (`private_index_demo`).
+
+pub const MAX_STONES: usize = 32;
+pub const MAX_TAKE: usize = 4;
+
+// the winning positions up to `max_stones`, `rules[t - 1]` allows taking t stones
+pub fn winning_positions(rules: &[bool; MAX_TAKE], max_stones: usize) -> Vec<bool> {
+    let mut win = vec![false; max_stones + 1];
+    for pile in 1..=max_stones {
+        win[pile] = (1..=MAX_TAKE.min(pile)).any(|take| rules[take - 1] && !win[pile - take]);
+    }
+    win
+}
+
+pub fn is_winning_move(rules: &[bool; MAX_TAKE], pile: usize, take: usize) -> bool {
+    (1..=MAX_TAKE).contains(&take)
+        && rules[take - 1]
+        && take <= pile
+        && !winning_positions(rules, pile - take)[pile - take]
+}
+
+// taking `take` stones from the pile wins under the rules
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SubtractionGameCircuit {
+    pub rules: [bool; MAX_TAKE],
+    pub pile: u8,
+    pub take: u8,
+}
+
+impl SubtractionGameCircuit {
+    pub fn public_inputs<F: PrimeField>(&self) -> Vec<F> {
+        let mut inputs: Vec<F> = self.rules.iter().map(|&allowed| F::from(allowed)).collect();
+        inputs.push(F::from(self.pile));
+        inputs.push(F::from(self.take));
+        inputs
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for SubtractionGameCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let rules = self
+            .rules
+            .iter()
+            .map(|&allowed| Boolean::new_input(ns!(cs, "rule"), || Ok(allowed)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let pile = FpVar::new_input(ns!(cs, "pile"), || Ok(F::from(self.pile)))?;
+        let take = FpVar::new_input(ns!(cs, "take"), || Ok(F::from(self.take)))?;
+
+        // the unrolled recursion
+        let mut win = vec![Boolean::FALSE];
+        for p in 1..=MAX_STONES {
+            let moves = (1..=MAX_TAKE.min(p))
+                .map(|t| rules[t - 1].and(&win[p - t].not()))
+                .collect::<Result<Vec<_>, _>>()?;
+            win.push(Boolean::kary_or(&moves)?);
+        }
+
+        // the move is allowed, 1 <= take <= MAX_TAKE
+        let selector = one_hot(cs.clone(), &(&take - F::one()), MAX_TAKE)?;
+        let mut allowed = FpVar::zero();
+        for (bit, rule) in selector.iter().zip(&rules) {
+            allowed += FpVar::from(bit.and(rule)?);
+        }
+        allowed.enforce_equal(&FpVar::one())?;
+
+        // and leaves a losing position, unsatisfiable if take > pile
+        let win: Vec<FpVar<F>> = win.into_iter().map(FpVar::from).collect();
+        select_index(cs, &win, &(pile - take))?.enforce_equal(&FpVar::zero())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    // the game tree itself, exponential
+    fn win_recursive(rules: &[bool; MAX_TAKE], pile: usize) -> bool {
+        (1..=MAX_TAKE.min(pile)).any(|take| rules[take - 1] && !win_recursive(rules, pile - take))
+    }
+
+    fn is_satisfied(circuit: SubtractionGameCircuit) -> bool {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_table_matches_recursion() {
+        for mask in 0..1 << MAX_TAKE {
+            let mut rules = [false; MAX_TAKE];
+            for (t, rule) in rules.iter_mut().enumerate() {
+                *rule = mask >> t & 1 == 1;
+            }
+            let win = winning_positions(&rules, 16);
+            for (pile, &w) in win.iter().enumerate() {
+                assert_eq!(w, win_recursive(&rules, pile), "{:?} {}", rules, pile);
+            }
+        }
+        // taking 1 to 3, the losing positions are the multiples of 4
+        let win = winning_positions(&[true, true, true, false], MAX_STONES);
+        assert!(win
+            .iter()
+            .enumerate()
+            .all(|(pile, &w)| w != (pile % 4 == 0)));
+    }
+
+    #[test]
+    fn test_winning_moves() {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(SubtractionGameCircuit::default(), rng)
+                .unwrap();
+        let circuit = SubtractionGameCircuit {
+            rules: [true, true, true, false],
+            pile: 10,
+            take: 2,
+        };
+        let inputs = circuit.public_inputs::<Fr>();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+        let mut other_take = inputs.clone();
+        other_take[5] = Fr::from(1u8);
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &other_take, &proof).unwrap());
+
+        // every move of a few games against the native check, also the moves the rules
+        // forbid, more stones than the pile and taking none
+        for rules in [
+            [true, true, true, false],
+            [true, false, true, true],
+            [false, true, false, true],
+        ]
+        .iter()
+        {
+            for pile in [0u8, 1, 5, 7, 12, 32].iter() {
+                for take in 0..=MAX_TAKE as u8 + 1 {
+                    let circuit = SubtractionGameCircuit {
+                        rules: *rules,
+                        pile: *pile,
+                        take,
+                    };
+                    assert_eq!(
+                        is_satisfied(circuit),
+                        is_winning_move(rules, *pile as usize, take as usize),
+                        "{:?} {} {}",
+                        rules,
+                        pile,
+                        take
+                    );
+                }
+            }
+        }
+    }
+}
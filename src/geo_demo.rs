@@ -0,0 +1,326 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+
+use crate::boolean_demo::to_bits;
+
+// Geofencing: a private location inside a public area. Coordinates are fixed point,
+// integer micro-degrees, shifted by 90 and 180 degrees to be non-negative so they are
+// small field elements (below 2^29), and a <= b is the range check of b - a. The
+// rectangle is four comparisons; a convex polygon, its vertices counter-clockwise,
+// contains a point when the point is left of or on every edge, a cross product of
+// differences of coordinates that is at least zero. The products stay far below the
+// modulus, a negative one is a huge field element and fails the range check. Areas
+// across the antimeridian are not supported.
+
+pub const MICRO_DEGREES: i64 = 1_000_000;
+const LAT_OFFSET: i64 = 90 * MICRO_DEGREES;
+const LON_OFFSET: i64 = 180 * MICRO_DEGREES;
+pub const COORD_BITS: usize = 29;
+// |cross product| < 2^(2 * 29 + 1)
+const CROSS_BITS: usize = 2 * COORD_BITS + 1;
+
+// in micro-degrees
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Coordinate {
+    pub lat: i64,
+    pub lon: i64,
+}
+
+impl Coordinate {
+    pub fn new(lat: i64, lon: i64) -> Option<Self> {
+        match lat.abs() <= LAT_OFFSET && lon.abs() <= LON_OFFSET {
+            true => Some(Self { lat, lon }),
+            false => None,
+        }
+    }
+
+    // rounded to the nearest micro-degree
+    pub fn from_degrees(lat: f64, lon: f64) -> Option<Self> {
+        let fixed = |degrees: f64| (degrees * MICRO_DEGREES as f64).round() as i64;
+        Self::new(fixed(lat), fixed(lon))
+    }
+
+    // the shifted values in the circuit, lat then lon
+    pub fn to_field_elements<F: PrimeField>(&self) -> [F; 2] {
+        [
+            F::from((self.lat + LAT_OFFSET) as u64),
+            F::from((self.lon + LON_OFFSET) as u64),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rectangle {
+    // south west and north east corners
+    pub min: Coordinate,
+    pub max: Coordinate,
+}
+
+impl Rectangle {
+    pub fn contains(&self, point: &Coordinate) -> bool {
+        (self.min.lat..=self.max.lat).contains(&point.lat)
+            && (self.min.lon..=self.max.lon).contains(&point.lon)
+    }
+}
+
+// `vertices` counter-clockwise
+pub fn convex_polygon_contains(vertices: &[Coordinate], point: &Coordinate) -> bool {
+    (0..vertices.len()).all(|i| {
+        let (a, b) = (vertices[i], vertices[(i + 1) % vertices.len()]);
+        let cross = (b.lon - a.lon) as i128 * (point.lat - a.lat) as i128
+            - (b.lat - a.lat) as i128 * (point.lon - a.lon) as i128;
+        cross >= 0
+    })
+}
+
+// a <= b for values of `num_bits` bits
+pub fn enforce_le<F: PrimeField>(
+    a: &FpVar<F>,
+    b: &FpVar<F>,
+    num_bits: usize,
+) -> Result<(), SynthesisError> {
+    to_bits(&(b - a), num_bits).map(|_| ())
+}
+
+#[derive(Clone)]
+pub struct CoordinateVar<F: PrimeField> {
+    pub lat: FpVar<F>,
+    pub lon: FpVar<F>,
+}
+
+impl<F: PrimeField> CoordinateVar<F> {
+    // a private location, range checked
+    pub fn new_witness(
+        cs: ConstraintSystemRef<F>,
+        location: Option<Coordinate>,
+    ) -> Result<Self, SynthesisError> {
+        let values = location.map(|l| l.to_field_elements::<F>());
+        let lat = FpVar::new_witness(ns!(cs, "lat"), || {
+            values
+                .map(|v| v[0])
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let lon = FpVar::new_witness(ns!(cs, "lon"), || {
+            values
+                .map(|v| v[1])
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        to_bits(&lat, COORD_BITS)?;
+        to_bits(&lon, COORD_BITS)?;
+        Ok(Self { lat, lon })
+    }
+
+    // a public coordinate, the verifier knows it is valid
+    pub fn new_input(
+        cs: ConstraintSystemRef<F>,
+        value: Coordinate,
+    ) -> Result<Self, SynthesisError> {
+        let [lat, lon] = value.to_field_elements::<F>();
+        Ok(Self {
+            lat: FpVar::new_input(ns!(cs, "lat"), || Ok(lat))?,
+            lon: FpVar::new_input(ns!(cs, "lon"), || Ok(lon))?,
+        })
+    }
+}
+
+// knows a location inside a public rectangle
+#[derive(Clone, Copy)]
+pub struct RectangleCircuit {
+    pub area: Rectangle,
+    pub location: Option<Coordinate>,
+}
+
+impl RectangleCircuit {
+    // the area is a public input, any one will do for the setup
+    pub fn blank() -> Self {
+        let origin = Coordinate { lat: 0, lon: 0 };
+        Self {
+            area: Rectangle {
+                min: origin,
+                max: origin,
+            },
+            location: None,
+        }
+    }
+
+    pub fn public_inputs<F: PrimeField>(&self) -> Vec<F> {
+        let mut inputs = self.area.min.to_field_elements::<F>().to_vec();
+        inputs.extend_from_slice(&self.area.max.to_field_elements::<F>());
+        inputs
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for RectangleCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let min = CoordinateVar::new_input(cs.clone(), self.area.min)?;
+        let max = CoordinateVar::new_input(cs.clone(), self.area.max)?;
+        let location = CoordinateVar::new_witness(cs, self.location)?;
+        enforce_le(&min.lat, &location.lat, COORD_BITS)?;
+        enforce_le(&location.lat, &max.lat, COORD_BITS)?;
+        enforce_le(&min.lon, &location.lon, COORD_BITS)?;
+        enforce_le(&location.lon, &max.lon, COORD_BITS)
+    }
+}
+
+// knows a location inside a public convex polygon, the number of vertices is fixed by
+// the setup
+#[derive(Clone)]
+pub struct ConvexPolygonCircuit {
+    // counter-clockwise
+    pub vertices: Vec<Coordinate>,
+    pub location: Option<Coordinate>,
+}
+
+impl ConvexPolygonCircuit {
+    pub fn blank(num_vertices: usize) -> Self {
+        Self {
+            vertices: vec![Coordinate { lat: 0, lon: 0 }; num_vertices],
+            location: None,
+        }
+    }
+
+    pub fn public_inputs<F: PrimeField>(&self) -> Vec<F> {
+        self.vertices
+            .iter()
+            .flat_map(|v| v.to_field_elements::<F>().to_vec())
+            .collect()
+    }
+}
+
+// the point is left of or on the edge from a to b
+fn enforce_left_of<F: PrimeField>(
+    a: &CoordinateVar<F>,
+    b: &CoordinateVar<F>,
+    point: &CoordinateVar<F>,
+) -> Result<(), SynthesisError> {
+    let cross =
+        (&b.lon - &a.lon) * (&point.lat - &a.lat) - (&b.lat - &a.lat) * (&point.lon - &a.lon);
+    to_bits(&cross, CROSS_BITS).map(|_| ())
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for ConvexPolygonCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let vertices = self
+            .vertices
+            .iter()
+            .map(|&v| CoordinateVar::new_input(cs.clone(), v))
+            .collect::<Result<Vec<_>, _>>()?;
+        let location = CoordinateVar::new_witness(cs, self.location)?;
+        for i in 0..vertices.len() {
+            let next = &vertices[(i + 1) % vertices.len()];
+            enforce_left_of(&vertices[i], next, &location)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    fn is_satisfied<C: ConstraintSynthesizer<Fr>>(circuit: C) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    fn point(lat: f64, lon: f64) -> Coordinate {
+        Coordinate::from_degrees(lat, lon).unwrap()
+    }
+
+    #[test]
+    fn test_coordinates() {
+        assert_eq!(
+            Coordinate::from_degrees(52.520008, 13.404954),
+            Coordinate::new(52_520_008, 13_404_954)
+        );
+        assert_eq!(Coordinate::from_degrees(90.000001, 0.0), None);
+        assert_eq!(Coordinate::from_degrees(0.0, -180.5), None);
+        let corner = point(-90.0, -180.0).to_field_elements::<Fr>();
+        assert_eq!(corner, [Fr::from(0u8), Fr::from(0u8)]);
+    }
+
+    #[test]
+    fn test_rectangle() {
+        let rng = &mut ark_std::test_rng();
+        // around Berlin
+        let area = Rectangle {
+            min: point(52.3, 13.0),
+            max: point(52.7, 13.8),
+        };
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(RectangleCircuit::blank(), rng).unwrap();
+        let circuit = RectangleCircuit {
+            area,
+            location: Some(point(52.520008, 13.404954)),
+        };
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        let inputs = circuit.public_inputs::<Fr>();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        // Potsdam, west of the area, the corners and a point on the edge
+        for (location, inside) in [
+            (point(52.39, 13.06), true),
+            (point(52.39, 12.99), false),
+            (area.min, true),
+            (area.max, true),
+            (point(52.7, 13.5), true),
+            (point(52.700001, 13.5), false),
+            (point(-52.5, 13.5), false),
+        ]
+        .iter()
+        {
+            assert_eq!(area.contains(location), *inside);
+            let circuit = RectangleCircuit {
+                area,
+                location: Some(*location),
+            };
+            assert_eq!(is_satisfied(circuit), *inside, "{:?}", location);
+        }
+    }
+
+    #[test]
+    fn test_convex_polygon() {
+        let rng = &mut ark_std::test_rng();
+        // a house, counter-clockwise with the longitude as x and the latitude as y
+        let vertices = vec![
+            point(0.0, 0.0),
+            point(0.0, 2.0),
+            point(2.0, 2.0),
+            point(3.0, 1.0),
+            point(2.0, 0.0),
+        ];
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(ConvexPolygonCircuit::blank(5), rng)
+                .unwrap();
+        let circuit = ConvexPolygonCircuit {
+            vertices: vertices.clone(),
+            location: Some(point(1.0, 1.0)),
+        };
+        let inputs = circuit.public_inputs::<Fr>();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        for lat in -1..8 {
+            for lon in -1..8 {
+                let location = point(lat as f64 * 0.5, lon as f64 * 0.5);
+                let inside = convex_polygon_contains(&vertices, &location);
+                let circuit = ConvexPolygonCircuit {
+                    vertices: vertices.clone(),
+                    location: Some(location),
+                };
+                assert_eq!(is_satisfied(circuit), inside, "{:?}", location);
+            }
+        }
+        assert!(convex_polygon_contains(&vertices, &point(2.5, 1.0)));
+        assert!(!convex_polygon_contains(&vertices, &point(2.5, 1.6)));
+    }
+}
@@ -0,0 +1,196 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_sponge::{
+    constraints::CryptographicSpongeVar,
+    poseidon::{constraints::PoseidonSpongeVar, PoseidonParameters, PoseidonSponge},
+    Absorb, CryptographicSponge, FieldBasedCryptographicSponge,
+};
+
+// Groth16 verification costs one scalar multiplication per public input. A statement
+// about many public values can expose only their hash instead: the values become
+// witnesses, the circuit hashes them and the digest is the single public input. The
+// verifier hashes the values it knows with `hash_inputs` and checks the proof against
+// the digest. Here the statement is n products c_i = a_i * b_i with public c_i.
+//
+// The hash is a Poseidon sponge with the parameters of `poseidon_params`, the same for
+// every hash based demo.
+
+// the digest the verifier checks the proof against. The number of values is absorbed
+// first, the sponge has no padding and [x] and [x, 0] would collide otherwise. Every
+// arkworks prime field is `Absorb`.
+pub fn hash_inputs<F: PrimeField + Absorb>(params: &PoseidonParameters<F>, inputs: &[F]) -> F {
+    let mut sponge = PoseidonSponge::new(params);
+    sponge.absorb(&F::from(inputs.len() as u64));
+    sponge.absorb(&inputs);
+    sponge.squeeze_native_field_elements(1)[0]
+}
+
+// `hash_inputs` in the circuit
+pub fn hash_inputs_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    params: &PoseidonParameters<F>,
+    inputs: &[FpVar<F>],
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut sponge = PoseidonSpongeVar::new(cs, params);
+    sponge.absorb(&FpVar::constant(F::from(inputs.len() as u64)))?;
+    sponge.absorb(&inputs)?;
+    Ok(sponge.squeeze_field_elements(1)?.remove(0))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublicInputs {
+    // every c_i is a public input
+    Plain,
+    // only the hash of all c_i is
+    Hashed,
+}
+
+#[derive(Clone)]
+pub struct ProductsCircuit<F: PrimeField> {
+    pub params: PoseidonParameters<F>,
+    pub mode: PublicInputs,
+    pub num_products: usize,
+    // (a_i, b_i)
+    pub factors: Option<Vec<(F, F)>>,
+}
+
+impl<F: PrimeField + Absorb> ProductsCircuit<F> {
+    pub fn new(params: PoseidonParameters<F>, mode: PublicInputs, factors: Vec<(F, F)>) -> Self {
+        Self {
+            params,
+            mode,
+            num_products: factors.len(),
+            factors: Some(factors),
+        }
+    }
+
+    // shape only, for the setup
+    pub fn blank(params: PoseidonParameters<F>, mode: PublicInputs, num_products: usize) -> Self {
+        Self {
+            params,
+            mode,
+            num_products,
+            factors: None,
+        }
+    }
+
+    // the c_i, what the verifier knows
+    pub fn products(&self) -> Option<Vec<F>> {
+        let factors = self.factors.as_ref()?;
+        Some(factors.iter().map(|&(a, b)| a * b).collect())
+    }
+
+    // in allocation order: the c_i, or their hash
+    pub fn public_inputs(&self) -> Option<Vec<F>> {
+        let products = self.products()?;
+        Some(match self.mode {
+            PublicInputs::Plain => products,
+            PublicInputs::Hashed => vec![hash_inputs(&self.params, &products)],
+        })
+    }
+}
+
+impl<F: PrimeField + Absorb> ConstraintSynthesizer<F> for ProductsCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let products = self.products();
+        let digest = self.public_inputs().map(|inputs| inputs[0]);
+
+        let mut product_vars = Vec::with_capacity(self.num_products);
+        for i in 0..self.num_products {
+            // a list shorter than `num_products` is missing the rest of the assignment
+            let (a, b) = match self.factors.as_ref().and_then(|f| f.get(i).copied()) {
+                Some((a, b)) => (Some(a), Some(b)),
+                None => (None, None),
+            };
+            let a = FpVar::new_witness(cs.clone(), || a.ok_or(SynthesisError::AssignmentMissing))?;
+            let b = FpVar::new_witness(cs.clone(), || b.ok_or(SynthesisError::AssignmentMissing))?;
+            let c = products.as_ref().and_then(|p| p.get(i).copied());
+            let c = match self.mode {
+                PublicInputs::Plain => {
+                    FpVar::new_input(cs.clone(), || c.ok_or(SynthesisError::AssignmentMissing))?
+                }
+                PublicInputs::Hashed => {
+                    FpVar::new_witness(cs.clone(), || c.ok_or(SynthesisError::AssignmentMissing))?
+                }
+            };
+            a.mul_equals(&b, &c)?;
+            product_vars.push(c);
+        }
+
+        if self.mode == PublicInputs::Hashed {
+            let digest = FpVar::new_input(cs.clone(), || {
+                digest.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            hash_inputs_var(cs, &self.params, &product_vars)?.enforce_equal(&digest)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    #[test]
+    fn test_hash_native_and_gadget_agree() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters::<Fr>();
+        for n in 0..5 {
+            let inputs: Vec<Fr> = (0..n).map(|_| Fr::rand(rng)).collect();
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let vars: Vec<_> = inputs
+                .iter()
+                .map(|&v| FpVar::new_witness(cs.clone(), || Ok(v)).unwrap())
+                .collect();
+            let digest = hash_inputs_var(cs.clone(), &params, &vars).unwrap();
+            assert_eq!(digest.value().unwrap(), hash_inputs(&params, &inputs));
+            assert!(cs.is_satisfied().unwrap());
+        }
+
+        // the length is part of the hash
+        let x = Fr::rand(rng);
+        assert_ne!(
+            hash_inputs(&params, &[x]),
+            hash_inputs(&params, &[x, Fr::from(0u8)])
+        );
+    }
+
+    #[test]
+    fn test_hashed_inputs() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters::<Fr>();
+        let factors: Vec<(Fr, Fr)> = (0..16).map(|_| (Fr::rand(rng), Fr::rand(rng))).collect();
+
+        for &mode in [PublicInputs::Plain, PublicInputs::Hashed].iter() {
+            let blank = ProductsCircuit::blank(params.clone(), mode, factors.len());
+            let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(blank, rng).unwrap();
+            let circuit = ProductsCircuit::new(params.clone(), mode, factors.clone());
+            let inputs = circuit.public_inputs().unwrap();
+            let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+            assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+            // one point per public input (plus the constant one) in the verifying key
+            match mode {
+                PublicInputs::Plain => assert_eq!(vk.gamma_abc_g1.len(), 17),
+                PublicInputs::Hashed => {
+                    assert_eq!(vk.gamma_abc_g1.len(), 2);
+                    // the verifier hashes the products it expects
+                    let mut products = ProductsCircuit::new(params.clone(), mode, factors.clone())
+                        .products()
+                        .unwrap();
+                    assert_eq!(inputs, vec![hash_inputs(&params, &products)]);
+                    products[3] += Fr::from(1u8);
+                    let wrong = hash_inputs(&params, &products);
+                    assert!(!Groth16::<Bls12_381>::verify(&vk, &[wrong], &proof).unwrap());
+                }
+            }
+        }
+    }
+}
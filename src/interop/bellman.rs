@@ -0,0 +1,353 @@
+use ark_bls12_381::{Bls12_381, Fq, Fq2, G1Affine, G2Affine};
+use ark_ff::Zero;
+use ark_groth16::{Proof, ProvingKey, VerifyingKey};
+use ark_std::fmt;
+use ark_std::io::{self, Read, Write};
+use ark_std::{vec, vec::Vec};
+
+use crate::encoding::{field_from_bytes, field_to_bytes, Endian};
+
+// Groth16 over BLS12-381 in bellman's byte layout (bellman 0.x `Proof::write` and
+// `VerifyingKey::write`, as used by the Zcash tooling):
+//
+//   proof: a (G1) | b (G2) | c (G1), compressed, 192 bytes
+//   vk:    [α]1 | [β]1 | [β]2 | [γ]2 | [δ]1 | [δ]2 | ic (u32 count, G1s), uncompressed
+//
+// Points use the Zcash BLS12-381 encoding: big-endian coordinates, G2 coordinates ordered
+// (c1, c0), and three flags in the top bits of the first byte: compressed, infinity and,
+// for compressed points, whether y is the larger of y and -y. The count is big-endian.
+// bellman refuses points at infinity in proofs and verifying keys, so does this module.
+//
+// Proving keys (bellman's `Parameters`) are not converted: bellman drops the points at
+// infinity from its A and B queries, which cannot be put back without the circuit.
+
+const COMPRESSED: u8 = 1 << 7;
+const INFINITY: u8 = 1 << 6;
+const LARGEST: u8 = 1 << 5;
+const FLAGS: u8 = COMPRESSED | INFINITY | LARGEST;
+
+const FQ_BYTES: usize = 48;
+
+#[derive(Debug)]
+pub enum BellmanError {
+    Io(io::Error),
+    // wrong flags, a coordinate not below the modulus, or a point not on the curve or
+    // not in the prime order subgroup
+    InvalidPoint(&'static str),
+    PointAtInfinity(&'static str),
+}
+
+impl fmt::Display for BellmanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BellmanError::Io(e) => write!(f, "i/o error: {}", e),
+            BellmanError::InvalidPoint(point) => write!(f, "invalid {} point", point),
+            BellmanError::PointAtInfinity(point) => write!(f, "{} is the point at infinity", point),
+        }
+    }
+}
+
+impl ark_std::error::Error for BellmanError {}
+
+impl From<io::Error> for BellmanError {
+    fn from(e: io::Error) -> Self {
+        BellmanError::Io(e)
+    }
+}
+
+// bellman's verifying key also carries [β]1 and [δ]1, which arkworks keeps in the
+// proving key only. Verification does not use them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BellmanVerifyingKey {
+    pub vk: VerifyingKey<Bls12_381>,
+    pub beta_g1: G1Affine,
+    pub delta_g1: G1Affine,
+}
+
+impl From<&ProvingKey<Bls12_381>> for BellmanVerifyingKey {
+    fn from(pk: &ProvingKey<Bls12_381>) -> Self {
+        Self {
+            vk: pk.vk.clone(),
+            beta_g1: pk.beta_g1,
+            delta_g1: pk.delta_g1,
+        }
+    }
+}
+
+pub fn write_proof<W: Write>(proof: &Proof<Bls12_381>, mut writer: W) -> Result<(), BellmanError> {
+    writer.write_all(&g1_compressed(&proof.a))?;
+    writer.write_all(&g2_compressed(&proof.b))?;
+    Ok(writer.write_all(&g1_compressed(&proof.c))?)
+}
+
+pub fn read_proof<R: Read>(mut reader: R) -> Result<Proof<Bls12_381>, BellmanError> {
+    Ok(Proof {
+        a: read_g1(&mut reader, true, "a")?,
+        b: read_g2(&mut reader, true, "b")?,
+        c: read_g1(&mut reader, true, "c")?,
+    })
+}
+
+pub fn write_verifying_key<W: Write>(
+    vk: &BellmanVerifyingKey,
+    mut writer: W,
+) -> Result<(), BellmanError> {
+    writer.write_all(&g1_uncompressed(&vk.vk.alpha_g1))?;
+    writer.write_all(&g1_uncompressed(&vk.beta_g1))?;
+    writer.write_all(&g2_uncompressed(&vk.vk.beta_g2))?;
+    writer.write_all(&g2_uncompressed(&vk.vk.gamma_g2))?;
+    writer.write_all(&g1_uncompressed(&vk.delta_g1))?;
+    writer.write_all(&g2_uncompressed(&vk.vk.delta_g2))?;
+    writer.write_all(&(vk.vk.gamma_abc_g1.len() as u32).to_be_bytes())?;
+    for p in vk.vk.gamma_abc_g1.iter() {
+        writer.write_all(&g1_uncompressed(p))?;
+    }
+    Ok(())
+}
+
+pub fn read_verifying_key<R: Read>(mut reader: R) -> Result<BellmanVerifyingKey, BellmanError> {
+    let alpha_g1 = read_g1(&mut reader, false, "alpha_g1")?;
+    let beta_g1 = read_g1(&mut reader, false, "beta_g1")?;
+    let beta_g2 = read_g2(&mut reader, false, "beta_g2")?;
+    let gamma_g2 = read_g2(&mut reader, false, "gamma_g2")?;
+    let delta_g1 = read_g1(&mut reader, false, "delta_g1")?;
+    let delta_g2 = read_g2(&mut reader, false, "delta_g2")?;
+    let mut count = [0u8; 4];
+    reader.read_exact(&mut count)?;
+    // no preallocation from an untrusted count, each point is read or the reader runs dry
+    let mut gamma_abc_g1 = Vec::new();
+    for _ in 0..u32::from_be_bytes(count) {
+        gamma_abc_g1.push(read_g1(&mut reader, false, "ic")?);
+    }
+    Ok(BellmanVerifyingKey {
+        vk: VerifyingKey {
+            alpha_g1,
+            beta_g2,
+            gamma_g2,
+            delta_g2,
+            gamma_abc_g1,
+        },
+        beta_g1,
+        delta_g1,
+    })
+}
+
+fn fq_to_be(value: Fq) -> Vec<u8> {
+    field_to_bytes(value, Endian::Big)
+}
+
+fn fq_from_be(bytes: &[u8]) -> Option<Fq> {
+    field_from_bytes(bytes, Endian::Big)
+}
+
+fn flagged(mut bytes: Vec<u8>, flags: u8) -> Vec<u8> {
+    bytes[0] |= flags;
+    bytes
+}
+
+fn infinity(len: usize, flags: u8) -> Vec<u8> {
+    flagged(vec![0u8; len], flags | INFINITY)
+}
+
+fn largest_flag(largest: bool) -> u8 {
+    if largest {
+        LARGEST
+    } else {
+        0
+    }
+}
+
+fn g1_compressed(p: &G1Affine) -> Vec<u8> {
+    if p.is_zero() {
+        return infinity(FQ_BYTES, COMPRESSED);
+    }
+    flagged(fq_to_be(p.x), COMPRESSED | largest_flag(p.y > -p.y))
+}
+
+fn g1_uncompressed(p: &G1Affine) -> Vec<u8> {
+    if p.is_zero() {
+        return infinity(2 * FQ_BYTES, 0);
+    }
+    [fq_to_be(p.x), fq_to_be(p.y)].concat()
+}
+
+fn fq2_to_be(value: &Fq2) -> Vec<u8> {
+    [fq_to_be(value.c1), fq_to_be(value.c0)].concat()
+}
+
+// arkworks orders Fq2 by c1, then c0, which is the order of the Zcash sort flag
+fn g2_compressed(p: &G2Affine) -> Vec<u8> {
+    if p.is_zero() {
+        return infinity(2 * FQ_BYTES, COMPRESSED);
+    }
+    flagged(fq2_to_be(&p.x), COMPRESSED | largest_flag(p.y > -p.y))
+}
+
+fn g2_uncompressed(p: &G2Affine) -> Vec<u8> {
+    if p.is_zero() {
+        return infinity(4 * FQ_BYTES, 0);
+    }
+    [fq2_to_be(&p.x), fq2_to_be(&p.y)].concat()
+}
+
+// reads `len` bytes and splits off the flags, checking them against the expected
+// compression; `Ok(None)` for a well formed point at infinity
+fn read_flagged<R: Read>(
+    reader: &mut R,
+    len: usize,
+    compressed: bool,
+    point: &'static str,
+) -> Result<Option<(bool, Vec<u8>)>, BellmanError> {
+    let mut bytes = vec![0u8; len];
+    reader.read_exact(&mut bytes)?;
+    let flags = bytes[0] & FLAGS;
+    bytes[0] &= !FLAGS;
+    if (flags & COMPRESSED != 0) != compressed || (!compressed && flags & LARGEST != 0) {
+        return Err(BellmanError::InvalidPoint(point));
+    }
+    if flags & INFINITY != 0 {
+        if flags & LARGEST != 0 || bytes.iter().any(|&byte| byte != 0) {
+            return Err(BellmanError::InvalidPoint(point));
+        }
+        return Ok(None);
+    }
+    Ok(Some((flags & LARGEST != 0, bytes)))
+}
+
+fn read_g1<R: Read>(
+    reader: &mut R,
+    compressed: bool,
+    point: &'static str,
+) -> Result<G1Affine, BellmanError> {
+    let len = if compressed { FQ_BYTES } else { 2 * FQ_BYTES };
+    let (largest, bytes) = read_flagged(reader, len, compressed, point)?
+        .ok_or(BellmanError::PointAtInfinity(point))?;
+    let invalid = || BellmanError::InvalidPoint(point);
+    let x = fq_from_be(&bytes[..FQ_BYTES]).ok_or_else(invalid)?;
+    let p = if compressed {
+        G1Affine::get_point_from_x(x, largest).ok_or_else(invalid)?
+    } else {
+        G1Affine::new(
+            x,
+            fq_from_be(&bytes[FQ_BYTES..]).ok_or_else(invalid)?,
+            false,
+        )
+    };
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(invalid());
+    }
+    Ok(p)
+}
+
+fn read_g2<R: Read>(
+    reader: &mut R,
+    compressed: bool,
+    point: &'static str,
+) -> Result<G2Affine, BellmanError> {
+    let len = if compressed {
+        2 * FQ_BYTES
+    } else {
+        4 * FQ_BYTES
+    };
+    let (largest, bytes) = read_flagged(reader, len, compressed, point)?
+        .ok_or(BellmanError::PointAtInfinity(point))?;
+    let invalid = || BellmanError::InvalidPoint(point);
+    let fq2 = |bytes: &[u8]| -> Result<Fq2, BellmanError> {
+        let c1 = fq_from_be(&bytes[..FQ_BYTES]).ok_or_else(invalid)?;
+        let c0 = fq_from_be(&bytes[FQ_BYTES..]).ok_or_else(invalid)?;
+        Ok(Fq2::new(c0, c1))
+    };
+    let x = fq2(&bytes[..2 * FQ_BYTES])?;
+    let p = if compressed {
+        G2Affine::get_point_from_x(x, largest).ok_or_else(invalid)?
+    } else {
+        G2Affine::new(x, fq2(&bytes[2 * FQ_BYTES..])?, false)
+    };
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(invalid());
+    }
+    Ok(p)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use ark_bls12_381::Fr;
+    use ark_ec::AffineCurve;
+    use ark_groth16::Groth16;
+    use ark_snark::SNARK;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn test_zcash_generators() {
+        // the compressed generators of the Zcash BLS12-381 serialization
+        assert_eq!(
+            hex(&g1_compressed(&G1Affine::prime_subgroup_generator())),
+            "97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb"
+        );
+        assert_eq!(
+            hex(&g2_compressed(&G2Affine::prime_subgroup_generator())),
+            "93e02b6052719f607dacd3a088274f65596bd0d09920b61ab5da61bbdc7f5049334cf11213945d57e5ac7d055d042b7e\
+             024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb8"
+        );
+        assert_eq!(hex(&g1_compressed(&G1Affine::zero()))[..2], *"c0");
+        assert_eq!(hex(&g1_uncompressed(&G1Affine::zero()))[..2], *"40");
+    }
+
+    #[test]
+    fn test_bellman_round_trip() {
+        let rng = &mut ark_std::test_rng();
+        let (pk, _) = Groth16::<Bls12_381>::circuit_specific_setup(
+            MultiplyDemoCircuit::<Fr> { a: None, b: None },
+            rng,
+        )
+        .unwrap();
+        let (a, b) = (Fr::from(3u8), Fr::from(11u8));
+        let circuit = MultiplyDemoCircuit {
+            a: Some(a),
+            b: Some(b),
+        };
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+
+        let mut bytes = Vec::new();
+        write_proof(&proof, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 192);
+        let decoded = read_proof(&bytes[..]).unwrap();
+        assert_eq!(decoded, proof);
+
+        let vk = BellmanVerifyingKey::from(&pk);
+        let mut vk_bytes = Vec::new();
+        write_verifying_key(&vk, &mut vk_bytes).unwrap();
+        assert_eq!(vk_bytes.len(), 96 * 3 + 192 * 3 + 4 + 96 * 2);
+        let decoded_vk = read_verifying_key(&vk_bytes[..]).unwrap();
+        assert_eq!(decoded_vk, vk);
+        assert!(Groth16::<Bls12_381>::verify(&decoded_vk.vk, &[a * b], &decoded).unwrap());
+
+        // the sign flag of b selects -b
+        let mut flipped = bytes.clone();
+        flipped[48] ^= LARGEST;
+        assert_eq!(read_proof(&flipped[..]).unwrap().b, -proof.b);
+        // an uncompressed point where a compressed one is expected
+        let mut uncompressed = bytes.clone();
+        uncompressed[0] &= !COMPRESSED;
+        assert!(matches!(
+            read_proof(&uncompressed[..]),
+            Err(BellmanError::InvalidPoint("a"))
+        ));
+        // bellman refuses a proof with c at infinity
+        let mut at_infinity = bytes[..144].to_vec();
+        at_infinity.extend(g1_compressed(&G1Affine::zero()));
+        assert!(matches!(
+            read_proof(&at_infinity[..]),
+            Err(BellmanError::PointAtInfinity("c"))
+        ));
+        assert!(matches!(
+            read_verifying_key(&vk_bytes[..vk_bytes.len() - 1]),
+            Err(BellmanError::Io(_))
+        ));
+    }
+}
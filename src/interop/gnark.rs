@@ -0,0 +1,414 @@
+use ark_bn254::{Bn254, Fq, Fq2, G1Affine, G2Affine};
+use ark_ff::Zero;
+use ark_groth16::{Proof, ProvingKey, VerifyingKey};
+use ark_std::fmt;
+use ark_std::io::{self, Read, Write};
+use ark_std::vec::Vec;
+
+use crate::encoding::{field_from_word, field_to_word};
+use crate::persist::SerializationMode;
+
+// Groth16 over BN254 in gnark's binary format (gnark v0.9 and later, `WriteTo` for
+// compressed and `WriteRawTo` for uncompressed), so proofs and verifying keys move
+// between gnark and arkworks:
+//
+//   proof: Ar (G1) | Bs (G2) | Krs (G1) | commitments (u32 count, G1s) | commitment pok (G1)
+//   vk:    [α]1 | [β]1 | [β]2 | [γ]2 | [δ]1 | [δ]2 | K (u32 count, G1s)
+//          | committed public inputs (u32 count) | commitment keys (u32 count)
+//
+// Coordinates are big-endian, G2 coordinates are ordered (c1, c0). The two top bits of
+// the first byte of a point are flags: 00 uncompressed, 01 compressed infinity, 10 and
+// 11 compressed with the smaller resp. larger y. Uncompressed infinity is all zeros.
+// Counts are big-endian u32. Reading detects the flags point by point, like gnark.
+//
+// Circuits with Pedersen commitments (`api.Commit`) have no arkworks counterpart, their
+// proofs and keys are rejected. Proving keys are not converted: a gnark proving key only
+// works with the gnark constraint system it was compiled with (wire order, dropped
+// infinity points), so it cannot prove an arkworks circuit or the other way round.
+
+const MASK: u8 = 0b11 << 6;
+const UNCOMPRESSED: u8 = 0b00 << 6;
+const COMPRESSED_INFINITY: u8 = 0b01 << 6;
+const COMPRESSED_SMALLEST: u8 = 0b10 << 6;
+const COMPRESSED_LARGEST: u8 = 0b11 << 6;
+
+#[derive(Debug)]
+pub enum GnarkError {
+    Io(io::Error),
+    // a coordinate is not below the modulus, or the point is not on the curve or not in
+    // the prime order subgroup
+    InvalidPoint(&'static str),
+    UnsupportedCommitments,
+}
+
+impl fmt::Display for GnarkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GnarkError::Io(e) => write!(f, "i/o error: {}", e),
+            GnarkError::InvalidPoint(point) => write!(f, "invalid {} point", point),
+            GnarkError::UnsupportedCommitments => {
+                write!(f, "Pedersen commitments are not supported")
+            }
+        }
+    }
+}
+
+impl ark_std::error::Error for GnarkError {}
+
+impl From<io::Error> for GnarkError {
+    fn from(e: io::Error) -> Self {
+        GnarkError::Io(e)
+    }
+}
+
+// gnark's verifying key also carries [β]1 and [δ]1, which arkworks keeps in the proving
+// key only. Verification does not use them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GnarkVerifyingKey {
+    pub vk: VerifyingKey<Bn254>,
+    pub beta_g1: G1Affine,
+    pub delta_g1: G1Affine,
+}
+
+impl From<&ProvingKey<Bn254>> for GnarkVerifyingKey {
+    fn from(pk: &ProvingKey<Bn254>) -> Self {
+        Self {
+            vk: pk.vk.clone(),
+            beta_g1: pk.beta_g1,
+            delta_g1: pk.delta_g1,
+        }
+    }
+}
+
+pub fn write_proof<W: Write>(
+    proof: &Proof<Bn254>,
+    mode: SerializationMode,
+    mut writer: W,
+) -> Result<(), GnarkError> {
+    write_g1(&proof.a, mode, &mut writer)?;
+    write_g2(&proof.b, mode, &mut writer)?;
+    write_g1(&proof.c, mode, &mut writer)?;
+    // no commitments, and the proof of knowledge for them is the point at infinity
+    write_u32(0, &mut writer)?;
+    write_g1(&G1Affine::zero(), mode, &mut writer)
+}
+
+pub fn read_proof<R: Read>(mut reader: R) -> Result<Proof<Bn254>, GnarkError> {
+    let proof = Proof {
+        a: read_g1(&mut reader, "Ar")?,
+        b: read_g2(&mut reader, "Bs")?,
+        c: read_g1(&mut reader, "Krs")?,
+    };
+    if read_u32(&mut reader)? != 0 || !read_g1(&mut reader, "commitment pok")?.is_zero() {
+        return Err(GnarkError::UnsupportedCommitments);
+    }
+    Ok(proof)
+}
+
+pub fn write_verifying_key<W: Write>(
+    vk: &GnarkVerifyingKey,
+    mode: SerializationMode,
+    mut writer: W,
+) -> Result<(), GnarkError> {
+    write_g1(&vk.vk.alpha_g1, mode, &mut writer)?;
+    write_g1(&vk.beta_g1, mode, &mut writer)?;
+    write_g2(&vk.vk.beta_g2, mode, &mut writer)?;
+    write_g2(&vk.vk.gamma_g2, mode, &mut writer)?;
+    write_g1(&vk.delta_g1, mode, &mut writer)?;
+    write_g2(&vk.vk.delta_g2, mode, &mut writer)?;
+    write_u32(vk.vk.gamma_abc_g1.len() as u32, &mut writer)?;
+    for p in vk.vk.gamma_abc_g1.iter() {
+        write_g1(p, mode, &mut writer)?;
+    }
+    // no public inputs committed to, no commitment keys
+    write_u32(0, &mut writer)?;
+    write_u32(0, &mut writer)
+}
+
+pub fn read_verifying_key<R: Read>(mut reader: R) -> Result<GnarkVerifyingKey, GnarkError> {
+    let alpha_g1 = read_g1(&mut reader, "[α]1")?;
+    let beta_g1 = read_g1(&mut reader, "[β]1")?;
+    let beta_g2 = read_g2(&mut reader, "[β]2")?;
+    let gamma_g2 = read_g2(&mut reader, "[γ]2")?;
+    let delta_g1 = read_g1(&mut reader, "[δ]1")?;
+    let delta_g2 = read_g2(&mut reader, "[δ]2")?;
+    let num_k = read_u32(&mut reader)?;
+    // no preallocation from an untrusted count, each point is read or the reader runs dry
+    let mut gamma_abc_g1 = Vec::new();
+    for _ in 0..num_k {
+        gamma_abc_g1.push(read_g1(&mut reader, "K")?);
+    }
+    if read_u32(&mut reader)? != 0 || read_u32(&mut reader)? != 0 {
+        return Err(GnarkError::UnsupportedCommitments);
+    }
+    Ok(GnarkVerifyingKey {
+        vk: VerifyingKey {
+            alpha_g1,
+            beta_g2,
+            gamma_g2,
+            delta_g2,
+            gamma_abc_g1,
+        },
+        beta_g1,
+        delta_g1,
+    })
+}
+
+fn write_u32<W: Write>(value: u32, writer: &mut W) -> Result<(), GnarkError> {
+    Ok(writer.write_all(&value.to_be_bytes())?)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, GnarkError> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+fn write_g1<W: Write>(
+    p: &G1Affine,
+    mode: SerializationMode,
+    writer: &mut W,
+) -> Result<(), GnarkError> {
+    match mode {
+        SerializationMode::Uncompressed if p.is_zero() => writer.write_all(&[0u8; 64])?,
+        SerializationMode::Uncompressed => {
+            writer.write_all(&field_to_word(p.x))?;
+            writer.write_all(&field_to_word(p.y))?;
+        }
+        SerializationMode::Compressed => {
+            let mut x = [0u8; 32];
+            if p.is_zero() {
+                x[0] = COMPRESSED_INFINITY;
+            } else {
+                x = field_to_word(p.x);
+                x[0] |= compressed_flag(p.y > -p.y);
+            }
+            writer.write_all(&x)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_g2<W: Write>(
+    p: &G2Affine,
+    mode: SerializationMode,
+    writer: &mut W,
+) -> Result<(), GnarkError> {
+    match mode {
+        SerializationMode::Uncompressed if p.is_zero() => writer.write_all(&[0u8; 128])?,
+        SerializationMode::Uncompressed => {
+            write_fq2(&p.x, 0, writer)?;
+            write_fq2(&p.y, 0, writer)?;
+        }
+        SerializationMode::Compressed if p.is_zero() => {
+            let mut x = [0u8; 64];
+            x[0] = COMPRESSED_INFINITY;
+            writer.write_all(&x)?;
+        }
+        // arkworks orders Fq2 by c1, then c0, which is gnark's lexicographic order
+        SerializationMode::Compressed => write_fq2(&p.x, compressed_flag(p.y > -p.y), writer)?,
+    }
+    Ok(())
+}
+
+fn compressed_flag(largest: bool) -> u8 {
+    if largest {
+        COMPRESSED_LARGEST
+    } else {
+        COMPRESSED_SMALLEST
+    }
+}
+
+fn write_fq2<W: Write>(value: &Fq2, flag: u8, writer: &mut W) -> Result<(), GnarkError> {
+    let mut c1 = field_to_word(value.c1);
+    c1[0] |= flag;
+    writer.write_all(&c1)?;
+    Ok(writer.write_all(&field_to_word(value.c0))?)
+}
+
+// the first word of a point, with the flags split off
+fn read_flagged_word<R: Read>(reader: &mut R) -> Result<(u8, [u8; 32]), GnarkError> {
+    let mut word = [0u8; 32];
+    reader.read_exact(&mut word)?;
+    let flag = word[0] & MASK;
+    word[0] &= !MASK;
+    Ok((flag, word))
+}
+
+fn read_fq<R: Read>(reader: &mut R, point: &'static str) -> Result<Fq, GnarkError> {
+    let mut word = [0u8; 32];
+    reader.read_exact(&mut word)?;
+    field_from_word(&word).ok_or(GnarkError::InvalidPoint(point))
+}
+
+fn read_g1<R: Read>(reader: &mut R, point: &'static str) -> Result<G1Affine, GnarkError> {
+    let (flag, x) = read_flagged_word(reader)?;
+    if flag == COMPRESSED_INFINITY {
+        return match x.iter().all(|&byte| byte == 0) {
+            true => Ok(G1Affine::zero()),
+            false => Err(GnarkError::InvalidPoint(point)),
+        };
+    }
+    let x: Fq = field_from_word(&x).ok_or(GnarkError::InvalidPoint(point))?;
+    let p = match flag {
+        UNCOMPRESSED => {
+            let y = read_fq(reader, point)?;
+            if x.is_zero() && y.is_zero() {
+                return Ok(G1Affine::zero());
+            }
+            G1Affine::new(x, y, false)
+        }
+        _ => G1Affine::get_point_from_x(x, flag == COMPRESSED_LARGEST)
+            .ok_or(GnarkError::InvalidPoint(point))?,
+    };
+    check_point(
+        p.is_on_curve() && p.is_in_correct_subgroup_assuming_on_curve(),
+        point,
+    )?;
+    Ok(p)
+}
+
+fn read_g2<R: Read>(reader: &mut R, point: &'static str) -> Result<G2Affine, GnarkError> {
+    let (flag, x_c1) = read_flagged_word(reader)?;
+    let x_c1: Fq = field_from_word(&x_c1).ok_or(GnarkError::InvalidPoint(point))?;
+    let x = Fq2::new(read_fq(reader, point)?, x_c1);
+    if flag == COMPRESSED_INFINITY {
+        check_point(x.is_zero(), point)?;
+        return Ok(G2Affine::zero());
+    }
+    let p = match flag {
+        UNCOMPRESSED => {
+            let y_c1 = read_fq(reader, point)?;
+            let y = Fq2::new(read_fq(reader, point)?, y_c1);
+            if x.is_zero() && y.is_zero() {
+                return Ok(G2Affine::zero());
+            }
+            G2Affine::new(x, y, false)
+        }
+        _ => G2Affine::get_point_from_x(x, flag == COMPRESSED_LARGEST)
+            .ok_or(GnarkError::InvalidPoint(point))?,
+    };
+    check_point(
+        p.is_on_curve() && p.is_in_correct_subgroup_assuming_on_curve(),
+        point,
+    )?;
+    Ok(p)
+}
+
+fn check_point(valid: bool, point: &'static str) -> Result<(), GnarkError> {
+    match valid {
+        true => Ok(()),
+        false => Err(GnarkError::InvalidPoint(point)),
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::eth_calldata::ProofCalldata;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use ark_bn254::Fr;
+    use ark_groth16::Groth16;
+    use ark_snark::SNARK;
+
+    fn multiply_proof() -> (ProvingKey<Bn254>, Proof<Bn254>) {
+        let rng = &mut ark_std::test_rng();
+        let (pk, _) = Groth16::<Bn254>::circuit_specific_setup(
+            MultiplyDemoCircuit::<Fr> { a: None, b: None },
+            rng,
+        )
+        .unwrap();
+        let circuit = MultiplyDemoCircuit {
+            a: Some(Fr::from(3u8)),
+            b: Some(Fr::from(11u8)),
+        };
+        let proof = Groth16::<Bn254>::prove(&pk, circuit, rng).unwrap();
+        (pk, proof)
+    }
+
+    #[test]
+    fn test_gnark_round_trip() {
+        let (pk, proof) = multiply_proof();
+        let vk = GnarkVerifyingKey::from(&pk);
+        for &(mode, proof_len, vk_len) in [
+            // 3 points, an empty commitment list and the pok at infinity
+            (
+                SerializationMode::Compressed,
+                32 + 64 + 32 + 4 + 32,
+                32 * 3 + 64 * 3 + 4 + 2 * 32 + 8,
+            ),
+            (
+                SerializationMode::Uncompressed,
+                64 + 128 + 64 + 4 + 64,
+                64 * 3 + 128 * 3 + 4 + 2 * 64 + 8,
+            ),
+        ]
+        .iter()
+        {
+            let mut bytes = Vec::new();
+            write_proof(&proof, mode, &mut bytes).unwrap();
+            assert_eq!(bytes.len(), proof_len);
+            assert_eq!(read_proof(&bytes[..]).unwrap(), proof);
+
+            let mut bytes = Vec::new();
+            write_verifying_key(&vk, mode, &mut bytes).unwrap();
+            assert_eq!(bytes.len(), vk_len);
+            assert_eq!(read_verifying_key(&bytes[..]).unwrap(), vk);
+        }
+
+        // the raw proof starts with the proof as gnark's Solidity verifier takes it
+        let mut bytes = Vec::new();
+        write_proof(&proof, SerializationMode::Uncompressed, &mut bytes).unwrap();
+        assert_eq!(
+            bytes[..256],
+            ProofCalldata::from_proof(&proof).to_bytes()[..]
+        );
+
+        let zero = Proof::<Bn254>::default();
+        for &mode in [
+            SerializationMode::Compressed,
+            SerializationMode::Uncompressed,
+        ]
+        .iter()
+        {
+            let mut bytes = Vec::new();
+            write_proof(&zero, mode, &mut bytes).unwrap();
+            assert_eq!(read_proof(&bytes[..]).unwrap(), zero);
+        }
+    }
+
+    #[test]
+    fn test_gnark_rejects_invalid() {
+        let (_, proof) = multiply_proof();
+        let mut bytes = Vec::new();
+        write_proof(&proof, SerializationMode::Compressed, &mut bytes).unwrap();
+
+        // flipping the sign flag of Bs picks the negated point, which still decodes
+        let mut flipped = bytes.clone();
+        flipped[32] ^= COMPRESSED_LARGEST ^ COMPRESSED_SMALLEST;
+        assert_eq!(read_proof(&flipped[..]).unwrap().b, -proof.b);
+
+        // a commitment
+        let mut committed = bytes.clone();
+        committed[131] = 1;
+        assert!(matches!(
+            read_proof(&committed[..]),
+            Err(GnarkError::UnsupportedCommitments)
+        ));
+
+        // truncated
+        assert!(matches!(
+            read_proof(&bytes[..bytes.len() - 1]),
+            Err(GnarkError::Io(_))
+        ));
+
+        // (1, 1) is not on y^2 = x^3 + 3
+        let mut off_curve = Vec::new();
+        write_proof(&proof, SerializationMode::Uncompressed, &mut off_curve).unwrap();
+        off_curve[..64].copy_from_slice(&[field_to_word(Fq::from(1u8)); 2].concat());
+        assert!(matches!(
+            read_proof(&off_curve[..]),
+            Err(GnarkError::InvalidPoint("Ar"))
+        ));
+    }
+}
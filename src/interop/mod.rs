@@ -0,0 +1,4 @@
+// Groth16 artifacts in the formats of other proving stacks, next to this crate's own
+// `persist` envelope
+pub mod bellman;
+pub mod gnark;
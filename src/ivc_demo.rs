@@ -0,0 +1,475 @@
+use ark_crypto_primitives::snark::{constraints::SNARKGadget, BooleanInputVar};
+use ark_ec::{AffineCurve, PairingEngine};
+use ark_ff::{BigInteger, PrimeField, ToConstraintField};
+use ark_groth16::{constraints::ProofVar, Groth16, Proof, ProvingKey, VerifyingKey};
+use ark_mnt4_298::MNT4_298;
+use ark_mnt6_298::MNT6_298;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*, ToConstraintFieldGadget};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_snark::SNARK;
+use ark_sponge::{poseidon::PoseidonParameters, Absorb};
+use ark_std::rand::{CryptoRng, RngCore};
+
+use crate::boolean_demo::to_bits;
+use crate::conditional_demo::enforce_if;
+use crate::cycles::{convert, InnerVerifierGadget, Mnt4Mnt6, Mnt6Mnt4, TwoChain};
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+use crate::poseidon_params::poseidon_parameters;
+
+// Incrementally verifiable computation: a chain of proofs of state_n = f^n(start) where
+// the proof of step i checks one application of f and the proof of step i - 1, so the
+// last proof alone vouches for the whole chain, however long.
+//
+// A Groth16 proof is verified with a pairing over the base field Fq of its curve, and a
+// circuit is cheap only over its native field, so the proofs alternate between the
+// curves of the MNT4-298/MNT6-298 cycle, where the scalar field of each is the base
+// field of the other. A step proved on MNT4 verifies the MNT6 proof of the step before
+// it and the other way round. Both circuits are `StepCircuit`, generic over the
+// `cycles::TwoChain` from the curve whose proof it verifies to the curve it is proved on.
+//
+// The verifying keys can't be constants of the circuits, each would need the other's
+// before it exists. They are witnesses instead, bound by their Poseidon digests that
+// are public inputs and passed along the chain: a circuit hashes the key it verifies
+// with, the digest of its own key it can't compute (the coordinates are in the other
+// field) and hands it to the proof it verifies, whose circuit does hash it. A verifier
+// computes both digests once. All public inputs are below 2^DIGEST_BITS so they are
+// the same numbers in both scalar fields:
+//   [digest of the key verified with, digest of the own key, start, i, state_i].
+//
+// f is an LCG, x -> a x + c mod 2^64. Nearly all constraints are the verification,
+// whatever f is: a step is 147960 constraints on MNT4, where the pairing is over Fq6,
+// and 74719 on MNT6.
+
+pub const DIGEST_BITS: usize = 248;
+pub const NUM_INPUTS: usize = 5;
+const STEP_BITS: usize = 32;
+const STATE_BITS: usize = 64;
+// Knuth's MMIX constants
+const MULTIPLIER: u64 = 6364136223846793005;
+const INCREMENT: u64 = 1442695040888963407;
+
+pub fn step(state: u64) -> u64 {
+    state.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT)
+}
+
+// the low DIGEST_BITS bits of the hash of the coordinates of the key
+pub fn vk_digest<E: PairingEngine>(
+    params: &PoseidonParameters<E::Fq>,
+    vk: &VerifyingKey<E>,
+) -> E::Fq
+where
+    E::Fq: Absorb,
+    E::G1Affine: ToConstraintField<E::Fq>,
+    E::G2Affine: ToConstraintField<E::Fq>,
+{
+    let mut elements = vk.alpha_g1.to_field_elements().unwrap_or_default();
+    for g2 in [&vk.beta_g2, &vk.gamma_g2, &vk.delta_g2].iter() {
+        elements.extend(g2.to_field_elements().unwrap_or_default());
+    }
+    for g1 in vk.gamma_abc_g1.iter() {
+        elements.extend(g1.to_field_elements().unwrap_or_default());
+    }
+    let bits = hash_inputs(params, &elements).into_repr().to_bits_le();
+    E::Fq::from_repr(<E::Fq as PrimeField>::BigInt::from_bits_le(
+        &bits[..DIGEST_BITS],
+    ))
+    .expect("below the modulus")
+}
+
+// the shape of a key of a step circuit, for the setup
+fn placeholder_vk<E: PairingEngine>() -> VerifyingKey<E> {
+    let g1 = E::G1Affine::prime_subgroup_generator();
+    let g2 = E::G2Affine::prime_subgroup_generator();
+    VerifyingKey {
+        alpha_g1: g1,
+        beta_g2: g2,
+        gamma_g2: g2,
+        delta_g2: g2,
+        gamma_abc_g1: vec![g1; NUM_INPUTS + 1],
+    }
+}
+
+// the proof the first step doesn't verify
+pub(crate) fn placeholder_proof<E: PairingEngine>() -> Proof<E> {
+    Proof {
+        a: E::G1Affine::prime_subgroup_generator(),
+        b: E::G2Affine::prime_subgroup_generator(),
+        c: E::G1Affine::prime_subgroup_generator(),
+    }
+}
+
+// step `step` of the chain, verifying the proof of step `step - 1` on the curve C::Inner
+pub struct StepCircuit<C: TwoChain> {
+    pub params: PoseidonParameters<C::InnerFq>,
+    pub inner_digest: C::InnerFq,
+    pub outer_digest: C::InnerFq,
+    pub start: u64,
+    pub step: u32,
+    pub state: u64,
+    pub inner_vk: VerifyingKey<C::Inner>,
+    pub inner_proof: Proof<C::Inner>,
+    pub previous: Option<u64>,
+}
+
+// the chains aren't Clone, a derive would require it
+impl<C: TwoChain> Clone for StepCircuit<C> {
+    fn clone(&self) -> Self {
+        Self {
+            params: self.params.clone(),
+            inner_vk: self.inner_vk.clone(),
+            inner_proof: self.inner_proof.clone(),
+            ..*self
+        }
+    }
+}
+
+impl<C: TwoChain> StepCircuit<C> {
+    // the step after `previous`, the first if `step` is 1 and then the proof is not
+    // checked
+    pub fn new(
+        params: PoseidonParameters<C::InnerFq>,
+        inner_vk: VerifyingKey<C::Inner>,
+        inner_proof: Proof<C::Inner>,
+        outer_digest: C::InnerFq,
+        start: u64,
+        step: u32,
+        previous: u64,
+    ) -> Self {
+        Self {
+            inner_digest: vk_digest(&params, &inner_vk),
+            params,
+            outer_digest,
+            start,
+            step,
+            state: self::step(previous),
+            inner_vk,
+            inner_proof,
+            previous: Some(previous),
+        }
+    }
+
+    pub fn blank(params: PoseidonParameters<C::InnerFq>) -> Self {
+        Self {
+            params,
+            inner_digest: C::InnerFq::from(0u8),
+            outer_digest: C::InnerFq::from(0u8),
+            start: 0,
+            step: 1,
+            state: 0,
+            inner_vk: placeholder_vk(),
+            inner_proof: placeholder_proof(),
+            previous: None,
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<C::InnerFq> {
+        vec![
+            self.inner_digest,
+            self.outer_digest,
+            C::InnerFq::from(self.start),
+            C::InnerFq::from(self.step as u64),
+            C::InnerFq::from(self.state),
+        ]
+    }
+}
+
+impl<C: TwoChain> ConstraintSynthesizer<C::InnerFq> for StepCircuit<C> {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<C::InnerFq>,
+    ) -> Result<(), SynthesisError> {
+        let inputs = self.public_inputs();
+        let inputs =
+            Vec::<FpVar<C::InnerFq>>::new_input(ns!(cs, "inputs"), || Ok(inputs.as_slice()))?;
+        let (inner_digest, outer_digest) = (&inputs[0], &inputs[1]);
+        let (start, step, state) = (&inputs[2], &inputs[3], &inputs[4]);
+        let previous = FpVar::new_witness(ns!(cs, "previous"), || {
+            self.previous
+                .map(C::InnerFq::from)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        // the key is bound by its digest, the proof is checked to be in the subgroups
+        let vk = InnerVerifierGadget::<C>::new_verification_key_unchecked(
+            ns!(cs, "inner_vk"),
+            || Ok(&self.inner_vk),
+            AllocationMode::Witness,
+        )?;
+        let proof =
+            ProofVar::<C::Inner, C::InnerPairingVar>::new_witness(ns!(cs, "inner_proof"), || {
+                Ok(&self.inner_proof)
+            })?;
+
+        let mut elements = vk.alpha_g1.to_constraint_field()?;
+        for g2 in [&vk.beta_g2, &vk.gamma_g2, &vk.delta_g2].iter() {
+            elements.extend(g2.to_constraint_field()?);
+        }
+        for g1 in vk.gamma_abc_g1.iter() {
+            elements.extend(g1.to_constraint_field()?);
+        }
+        let mut digest_bits = hash_inputs_var(cs, &self.params, &elements)?.to_bits_le()?;
+        digest_bits.truncate(DIGEST_BITS);
+        Boolean::le_bits_to_fp_var(&digest_bits)?.enforce_equal(inner_digest)?;
+
+        // one step of f
+        let previous_bits = to_bits(&previous, STATE_BITS)?;
+        let product = &previous * C::InnerFq::from(MULTIPLIER) + C::InnerFq::from(INCREMENT);
+        let product_bits = to_bits(&product, 2 * STATE_BITS)?;
+        Boolean::le_bits_to_fp_var(&product_bits[..STATE_BITS])?.enforce_equal(state)?;
+
+        // the first step starts from `start`, the others verify the step before, whose
+        // digests are the other way round. step - 1 has STEP_BITS bits, so step >= 1.
+        let first = step.is_eq(&FpVar::one())?;
+        enforce_if(&first, &previous, start)?;
+        let inner_inputs = BooleanInputVar::new(vec![
+            to_bits(outer_digest, DIGEST_BITS)?,
+            digest_bits,
+            to_bits(start, STATE_BITS)?,
+            to_bits(&(step - FpVar::one()), STEP_BITS)?,
+            previous_bits,
+        ]);
+        let verified = InnerVerifierGadget::<C>::verify(&vk, &inner_inputs, &proof)?;
+        verified.or(&first)?.enforce_equal(&Boolean::TRUE)
+    }
+}
+
+// proved on MNT4, verifies MNT6 proofs, and the other way round
+pub type Mnt4StepCircuit = StepCircuit<Mnt6Mnt4>;
+pub type Mnt6StepCircuit = StepCircuit<Mnt4Mnt6>;
+
+#[derive(Clone)]
+pub enum StepProof {
+    Mnt4(Proof<MNT4_298>),
+    Mnt6(Proof<MNT6_298>),
+}
+
+// state_step = f^step(start), proved on MNT4 for odd steps and on MNT6 for even ones
+#[derive(Clone)]
+pub struct Ivc {
+    pub start: u64,
+    pub step: u32,
+    pub state: u64,
+    pub proof: StepProof,
+}
+
+type Mnt4Fr = <MNT4_298 as PairingEngine>::Fr;
+type Mnt6Fr = <MNT6_298 as PairingEngine>::Fr;
+
+pub struct IvcKeys {
+    pub mnt4: (ProvingKey<MNT4_298>, VerifyingKey<MNT4_298>),
+    pub mnt6: (ProvingKey<MNT6_298>, VerifyingKey<MNT6_298>),
+    // the digest of each verifying key in its base field, the scalar field of the other
+    mnt4_digest: Mnt6Fr,
+    mnt6_digest: Mnt4Fr,
+}
+
+impl IvcKeys {
+    pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, SynthesisError> {
+        let mnt4 = Groth16::<MNT4_298>::circuit_specific_setup(
+            Mnt4StepCircuit::blank(poseidon_parameters()),
+            rng,
+        )?;
+        let mnt6 = Groth16::<MNT6_298>::circuit_specific_setup(
+            Mnt6StepCircuit::blank(poseidon_parameters()),
+            rng,
+        )?;
+        Ok(Self {
+            mnt4_digest: vk_digest(&poseidon_parameters(), &mnt4.1),
+            mnt6_digest: vk_digest(&poseidon_parameters(), &mnt6.1),
+            mnt4,
+            mnt6,
+        })
+    }
+
+    fn mnt4_circuit(
+        &self,
+        proof: Proof<MNT6_298>,
+        start: u64,
+        step: u32,
+        previous: u64,
+    ) -> Mnt4StepCircuit {
+        let vk = self.mnt6.1.clone();
+        let outer = convert(self.mnt4_digest).expect("below 2^DIGEST_BITS");
+        StepCircuit::new(
+            poseidon_parameters(),
+            vk,
+            proof,
+            outer,
+            start,
+            step,
+            previous,
+        )
+    }
+
+    fn mnt6_circuit(
+        &self,
+        proof: Proof<MNT4_298>,
+        start: u64,
+        step: u32,
+        previous: u64,
+    ) -> Mnt6StepCircuit {
+        let vk = self.mnt4.1.clone();
+        let outer = convert(self.mnt6_digest).expect("below 2^DIGEST_BITS");
+        StepCircuit::new(
+            poseidon_parameters(),
+            vk,
+            proof,
+            outer,
+            start,
+            step,
+            previous,
+        )
+    }
+
+    // step 1, on MNT4
+    pub fn prove_first<R: RngCore + CryptoRng>(
+        &self,
+        start: u64,
+        rng: &mut R,
+    ) -> Result<Ivc, SynthesisError> {
+        let circuit = self.mnt4_circuit(placeholder_proof(), start, 1, start);
+        Ok(Ivc {
+            start,
+            step: 1,
+            state: circuit.state,
+            proof: StepProof::Mnt4(Groth16::<MNT4_298>::prove(&self.mnt4.0, circuit, rng)?),
+        })
+    }
+
+    pub fn prove_next<R: RngCore + CryptoRng>(
+        &self,
+        ivc: &Ivc,
+        rng: &mut R,
+    ) -> Result<Ivc, SynthesisError> {
+        let (start, step) = (ivc.start, ivc.step + 1);
+        let proof = match &ivc.proof {
+            StepProof::Mnt4(proof) => {
+                let circuit = self.mnt6_circuit(proof.clone(), start, step, ivc.state);
+                StepProof::Mnt6(Groth16::<MNT6_298>::prove(&self.mnt6.0, circuit, rng)?)
+            }
+            StepProof::Mnt6(proof) => {
+                let circuit = self.mnt4_circuit(proof.clone(), start, step, ivc.state);
+                StepProof::Mnt4(Groth16::<MNT4_298>::prove(&self.mnt4.0, circuit, rng)?)
+            }
+        };
+        Ok(Ivc {
+            start,
+            step,
+            state: self::step(ivc.state),
+            proof,
+        })
+    }
+
+    // one Groth16 verification, however long the chain
+    pub fn verify(&self, ivc: &Ivc) -> Result<bool, SynthesisError> {
+        match &ivc.proof {
+            StepProof::Mnt4(proof) => {
+                let inputs = vec![
+                    self.mnt6_digest,
+                    convert(self.mnt4_digest).expect("below 2^DIGEST_BITS"),
+                    Mnt4Fr::from(ivc.start),
+                    Mnt4Fr::from(ivc.step as u64),
+                    Mnt4Fr::from(ivc.state),
+                ];
+                Groth16::<MNT4_298>::verify(&self.mnt4.1, &inputs, proof)
+            }
+            StepProof::Mnt6(proof) => {
+                let inputs = vec![
+                    self.mnt4_digest,
+                    convert(self.mnt6_digest).expect("below 2^DIGEST_BITS"),
+                    Mnt6Fr::from(ivc.start),
+                    Mnt6Fr::from(ivc.step as u64),
+                    Mnt6Fr::from(ivc.state),
+                ];
+                Groth16::<MNT6_298>::verify(&self.mnt6.1, &inputs, proof)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    fn is_satisfied<C: TwoChain>(circuit: StepCircuit<C>) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    // the first step of either circuit, any key hashes to some digest
+    fn check_first_step<C: TwoChain>() {
+        let outer = C::InnerFq::from(12345u16);
+        let circuit = StepCircuit::<C>::new(
+            poseidon_parameters(),
+            placeholder_vk(),
+            placeholder_proof(),
+            outer,
+            7,
+            1,
+            7,
+        );
+        assert_eq!(circuit.state, step(7));
+        assert!(is_satisfied(circuit.clone()));
+
+        // another state, another start, another key than the digest says
+        let mut wrong = circuit.clone();
+        wrong.state += 1;
+        assert!(!is_satisfied(wrong));
+        let mut wrong = circuit.clone();
+        wrong.start = 8;
+        assert!(!is_satisfied(wrong));
+        let mut wrong = circuit.clone();
+        wrong.inner_digest += C::InnerFq::from(1u8);
+        assert!(!is_satisfied(wrong));
+        // a later step needs a proof, there is no step 0
+        for &number in [2, 0].iter() {
+            let mut wrong = circuit.clone();
+            wrong.step = number;
+            assert!(!is_satisfied(wrong));
+        }
+    }
+
+    #[test]
+    fn test_first_step() {
+        assert_eq!(step(u64::MAX), INCREMENT.wrapping_sub(MULTIPLIER));
+        check_first_step::<Mnt6Mnt4>();
+        check_first_step::<Mnt4Mnt6>();
+    }
+
+    // cargo test --release ivc -- --ignored, setups and proofs on both curves
+    #[test]
+    #[ignore]
+    fn test_chain() {
+        let rng = &mut ark_std::test_rng();
+        let keys = IvcKeys::setup(rng).unwrap();
+        let mut ivc = keys.prove_first(42, rng).unwrap();
+        assert!(keys.verify(&ivc).unwrap());
+        let mut state = step(42);
+        for number in 2..=4 {
+            ivc = keys.prove_next(&ivc, rng).unwrap();
+            state = step(state);
+            assert_eq!((ivc.step, ivc.state), (number, state));
+            assert!(keys.verify(&ivc).unwrap());
+        }
+
+        // the last proof is for that start, that number of steps and that state
+        let mut wrong = ivc.clone();
+        wrong.state += 1;
+        assert!(!keys.verify(&wrong).unwrap());
+        let mut wrong = ivc.clone();
+        wrong.start = 43;
+        assert!(!keys.verify(&wrong).unwrap());
+        let mut wrong = ivc;
+        wrong.step = 5;
+        assert!(!keys.verify(&wrong).unwrap());
+
+        // keys of another setup have other digests, the chain doesn't carry over
+        let other = IvcKeys::setup(rng).unwrap();
+        let first = keys.prove_first(42, rng).unwrap();
+        assert!(!other.verify(&first).unwrap());
+    }
+}
@@ -0,0 +1,318 @@
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{ConstraintSynthesizer, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::fingerprint::{circuit_fingerprint, Fingerprint};
+use crate::persist::{self, ArtifactKind, PersistError, SerializationMode, Validation};
+
+// Keys made once per (circuit, curve, backend) and kept in a directory, so the CLI and
+// the servers set a circuit up on its first use and load the keys on every later one.
+// An entry is a directory named after the tuple,
+//   <dir>/<circuit>.<curve>.<backend>/pk.bin, vk.bin, circuit.fp
+// with the keys in `persist` envelopes and in circuit.fp the fingerprint of the circuit
+// they were made for (`fingerprint::circuit_fingerprint`, hex). Every lookup fingerprints
+// the circuit again: a circuit that changed since, a constraint added or a coefficient
+// edited, no longer matches and is set up anew, as is an entry with a missing or
+// unreadable key. circuit.fp is written last and removed first, so an entry that was
+// interrupted while being written never counts as a hit.
+//
+// The cache does not know how to set up, the caller passes the backend's setup, which
+// makes it work the same for Groth16 and for Marlin indexing against a universal SRS.
+
+pub const PROVING_KEY_FILE: &str = "pk.bin";
+pub const VERIFYING_KEY_FILE: &str = "vk.bin";
+pub const FINGERPRINT_FILE: &str = "circuit.fp";
+
+#[derive(Debug)]
+pub enum KeyCacheError {
+    Io(io::Error),
+    Persist(PersistError),
+    Synthesis(SynthesisError),
+    Setup(crate::Error),
+    // a name that is not a single path component of letters, digits, '-' and '_'
+    InvalidName(String),
+}
+
+impl fmt::Display for KeyCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyCacheError::Io(e) => write!(f, "io error: {}", e),
+            KeyCacheError::Persist(e) => write!(f, "cannot store keys: {}", e),
+            KeyCacheError::Synthesis(e) => write!(f, "synthesis error: {}", e),
+            KeyCacheError::Setup(e) => write!(f, "setup failed: {}", e),
+            KeyCacheError::InvalidName(name) => write!(f, "invalid cache name {:?}", name),
+        }
+    }
+}
+
+impl std::error::Error for KeyCacheError {}
+
+impl From<io::Error> for KeyCacheError {
+    fn from(e: io::Error) -> Self {
+        KeyCacheError::Io(e)
+    }
+}
+
+impl From<PersistError> for KeyCacheError {
+    fn from(e: PersistError) -> Self {
+        KeyCacheError::Persist(e)
+    }
+}
+
+impl From<SynthesisError> for KeyCacheError {
+    fn from(e: SynthesisError) -> Self {
+        KeyCacheError::Synthesis(e)
+    }
+}
+
+// what an entry is cached under, e.g. ("cubic", "bls12-381", "groth16")
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CacheId {
+    pub circuit: String,
+    pub curve: String,
+    pub backend: String,
+}
+
+impl CacheId {
+    pub fn new<C, E, B>(circuit: C, curve: E, backend: B) -> Result<Self, KeyCacheError>
+    where
+        C: ToString,
+        E: ToString,
+        B: ToString,
+    {
+        let id = Self {
+            circuit: circuit.to_string(),
+            curve: curve.to_string(),
+            backend: backend.to_string(),
+        };
+        for name in [&id.circuit, &id.curve, &id.backend].iter() {
+            let valid = name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if name.is_empty() || !valid {
+                return Err(KeyCacheError::InvalidName(name.to_string()));
+            }
+        }
+        Ok(id)
+    }
+}
+
+impl fmt::Display for CacheId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.circuit, self.curve, self.backend)
+    }
+}
+
+pub struct KeyCache {
+    dir: PathBuf,
+}
+
+impl KeyCache {
+    // the cache in `dir`, created if it does not exist
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self, KeyCacheError> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn entry_dir(&self, id: &CacheId) -> PathBuf {
+        self.dir.join(id.to_string())
+    }
+
+    // the keys of `id` if they were made for the circuit `blank()` builds, otherwise
+    // `setup(blank())` and the new keys stored. `blank` is called once for the
+    // fingerprint and once more on a miss.
+    pub fn get_or_setup<F, C, PK, VK, S, E>(
+        &self,
+        id: &CacheId,
+        blank: impl Fn() -> C,
+        setup: S,
+    ) -> Result<(PK, VK), KeyCacheError>
+    where
+        F: PrimeField,
+        C: ConstraintSynthesizer<F>,
+        PK: CanonicalSerialize + CanonicalDeserialize,
+        VK: CanonicalSerialize + CanonicalDeserialize,
+        S: FnOnce(C) -> Result<(PK, VK), E>,
+        E: Into<crate::Error>,
+    {
+        let fingerprint = circuit_fingerprint(blank())?;
+        if let Some(keys) = self.load(id, fingerprint) {
+            return Ok(keys);
+        }
+        let (pk, vk) = setup(blank()).map_err(|e| KeyCacheError::Setup(e.into()))?;
+        self.store(id, fingerprint, &pk, &vk)?;
+        Ok((pk, vk))
+    }
+
+    // the fingerprint of the circuit the keys of `id` were made for, None if there are none
+    pub fn fingerprint(&self, id: &CacheId) -> Option<Fingerprint> {
+        fs::read_to_string(self.entry_dir(id).join(FINGERPRINT_FILE))
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    // removes the entry, the next lookup sets up again
+    pub fn invalidate(&self, id: &CacheId) -> Result<(), KeyCacheError> {
+        match fs::remove_dir_all(self.entry_dir(id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn load<PK, VK>(&self, id: &CacheId, fingerprint: Fingerprint) -> Option<(PK, VK)>
+    where
+        PK: CanonicalDeserialize,
+        VK: CanonicalDeserialize,
+    {
+        if self.fingerprint(id)? != fingerprint {
+            return None;
+        }
+        let dir = self.entry_dir(id);
+        let pk = persist::load(
+            dir.join(PROVING_KEY_FILE),
+            ArtifactKind::ProvingKey,
+            Validation::Checked,
+        );
+        let vk = persist::load(
+            dir.join(VERIFYING_KEY_FILE),
+            ArtifactKind::VerifyingKey,
+            Validation::Checked,
+        );
+        pk.ok().zip(vk.ok())
+    }
+
+    fn store<PK, VK>(
+        &self,
+        id: &CacheId,
+        fingerprint: Fingerprint,
+        pk: &PK,
+        vk: &VK,
+    ) -> Result<(), KeyCacheError>
+    where
+        PK: CanonicalSerialize,
+        VK: CanonicalSerialize,
+    {
+        let dir = self.entry_dir(id);
+        fs::create_dir_all(&dir)?;
+        let fingerprint_path = dir.join(FINGERPRINT_FILE);
+        if let Err(e) = fs::remove_file(&fingerprint_path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                return Err(e.into());
+            }
+        }
+        let mode = SerializationMode::Compressed;
+        persist::save(
+            dir.join(PROVING_KEY_FILE),
+            pk,
+            ArtifactKind::ProvingKey,
+            mode,
+        )?;
+        persist::save(
+            dir.join(VERIFYING_KEY_FILE),
+            vk,
+            ArtifactKind::VerifyingKey,
+            mode,
+        )?;
+        // renamed into place, a reader sees the whole fingerprint or none
+        let tmp = dir.join(format!("{}.tmp", FINGERPRINT_FILE));
+        fs::write(&tmp, fingerprint.to_string())?;
+        fs::rename(&tmp, &fingerprint_path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::CubicDemoCircuit;
+    use crate::padding::Padded;
+    use crate::prover;
+    use crate::rng::RngConfig;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::{Groth16, ProvingKey, VerifyingKey};
+    use std::cell::Cell;
+
+    type Keys = (ProvingKey<Bls12_381>, VerifyingKey<Bls12_381>);
+
+    #[test]
+    fn test_lazy_setup() {
+        let dir =
+            std::env::temp_dir().join(format!("gadgets_key_cache_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let cache = KeyCache::new(&dir).unwrap();
+        let id = CacheId::new("cubic", "bls12-381", "groth16").unwrap();
+
+        let setups = Cell::new(0);
+        let get = |constraints: usize| -> Keys {
+            cache
+                .get_or_setup(
+                    &id,
+                    || Padded::new(CubicDemoCircuit::<Fr>::blank(), constraints, 0),
+                    |circuit| {
+                        setups.set(setups.get() + 1);
+                        prover::setup::<_, Groth16<Bls12_381>, _>(circuit, &RngConfig::seeded(1))
+                    },
+                )
+                .unwrap()
+        };
+
+        let (_, vk) = get(0);
+        assert_eq!(setups.get(), 1);
+        let (_, cached) = get(0);
+        assert_eq!(setups.get(), 1);
+        assert_eq!(cached, vk);
+        // another process, the same directory
+        let (_, cached) = KeyCache::new(&dir)
+            .unwrap()
+            .get_or_setup(
+                &id,
+                CubicDemoCircuit::<Fr>::blank,
+                |_| -> Result<Keys, crate::Error> { Err("not cached".into()) },
+            )
+            .unwrap();
+        assert_eq!(cached, vk);
+
+        // the circuit changed, padded to 8 constraints, its keys are made again
+        get(8);
+        assert_eq!(setups.get(), 2);
+        assert_eq!(
+            cache.fingerprint(&id),
+            Some(circuit_fingerprint(Padded::new(CubicDemoCircuit::<Fr>::blank(), 8, 0)).unwrap())
+        );
+        get(8);
+        assert_eq!(setups.get(), 2);
+
+        // a damaged key, and an entry removed
+        fs::write(cache.entry_dir(&id).join(PROVING_KEY_FILE), b"ARKW").unwrap();
+        get(8);
+        assert_eq!(setups.get(), 3);
+        cache.invalidate(&id).unwrap();
+        assert_eq!(cache.fingerprint(&id), None);
+        get(8);
+        assert_eq!(setups.get(), 4);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache_id() {
+        let id = CacheId::new("formula-0a1b", "bn254", "marlin").unwrap();
+        assert_eq!(id.to_string(), "formula-0a1b.bn254.marlin");
+        for name in ["", "..", "a/b", "a.b"].iter() {
+            assert!(matches!(
+                CacheId::new(name, "bn254", "groth16"),
+                Err(KeyCacheError::InvalidName(_))
+            ));
+        }
+    }
+}
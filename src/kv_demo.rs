@@ -0,0 +1,257 @@
+use ark_bls12_381::Fr;
+use ark_ff::Zero;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::poseidon::PoseidonParameters;
+
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+use crate::merkle_gadget::{MerklePath, MerklePathVar, SparseMerkleTree};
+
+// A key-value store committed to by a root, the state of most applications: balances
+// by account, records by id. The map is a sparse Merkle tree (`merkle_gadget`) with a
+// leaf per key of `KEY_BITS` bits, the leaf of a key is H(value) if it is set and zero
+// if not, so store[key] == value is a path from that leaf to the root with the bits of
+// the key as the index. One of the key and the value is public:
+//   - `Public::Key`, the value at a key, e.g. a balance, stays private,
+//   - `Public::Value`, some key has the value, e.g. a registered name, without which.
+
+pub const KEY_BITS: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Public {
+    Key,
+    Value,
+}
+
+pub fn value_leaf(params: &PoseidonParameters<Fr>, value: Fr) -> Fr {
+    hash_inputs(params, &[value])
+}
+
+#[derive(Clone)]
+pub struct KvStore {
+    params: PoseidonParameters<Fr>,
+    tree: SparseMerkleTree<Fr>,
+}
+
+impl KvStore {
+    pub fn new(params: PoseidonParameters<Fr>) -> Self {
+        Self {
+            tree: SparseMerkleTree::new(params.clone(), KEY_BITS),
+            params,
+        }
+    }
+
+    pub fn root(&self) -> Fr {
+        self.tree.root()
+    }
+
+    pub fn insert(&mut self, key: u32, value: Fr) {
+        let leaf = value_leaf(&self.params, value);
+        self.tree
+            .update(key as usize, leaf)
+            .expect("a key of KEY_BITS");
+    }
+
+    pub fn remove(&mut self, key: u32) {
+        self.tree
+            .update(key as usize, Fr::zero())
+            .expect("a key of KEY_BITS");
+    }
+
+    pub fn contains(&self, key: u32, value: Fr) -> bool {
+        self.tree.leaf(key as usize) == value_leaf(&self.params, value)
+    }
+
+    pub fn path(&self, key: u32) -> MerklePath<Fr> {
+        self.tree.path(key as usize).expect("a key of KEY_BITS")
+    }
+}
+
+#[derive(Clone)]
+pub struct LookupCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub public: Public,
+    pub root: Fr,
+    pub key: Option<u32>,
+    pub value: Option<Fr>,
+    pub path: Option<MerklePath<Fr>>,
+}
+
+impl LookupCircuit {
+    // None if the store doesn't map the key to the value
+    pub fn new(
+        params: PoseidonParameters<Fr>,
+        public: Public,
+        store: &KvStore,
+        key: u32,
+        value: Fr,
+    ) -> Option<Self> {
+        if !store.contains(key, value) {
+            return None;
+        }
+        Some(Self {
+            params,
+            public,
+            root: store.root(),
+            key: Some(key),
+            value: Some(value),
+            path: Some(store.path(key)),
+        })
+    }
+
+    // the root and the public one of the key and the value are public inputs
+    pub fn blank(params: PoseidonParameters<Fr>, public: Public) -> Self {
+        Self {
+            params,
+            public,
+            root: Fr::zero(),
+            key: None,
+            value: None,
+            path: None,
+        }
+    }
+
+    pub fn public_inputs(&self) -> Option<Vec<Fr>> {
+        let public = match self.public {
+            Public::Key => Fr::from(self.key?),
+            Public::Value => self.value?,
+        };
+        Some(vec![self.root, public])
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for LookupCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let root = FpVar::new_input(ns!(cs, "root"), || Ok(self.root))?;
+        let key = || {
+            self.key
+                .map(Fr::from)
+                .ok_or(SynthesisError::AssignmentMissing)
+        };
+        let value = || self.value.ok_or(SynthesisError::AssignmentMissing);
+        let (key, value) = match self.public {
+            Public::Key => (
+                FpVar::new_input(ns!(cs, "key"), key)?,
+                FpVar::new_witness(ns!(cs, "value"), value)?,
+            ),
+            Public::Value => (
+                FpVar::new_witness(ns!(cs, "key"), key)?,
+                FpVar::new_input(ns!(cs, "value"), value)?,
+            ),
+        };
+
+        // the bits of the index are the key, so it has KEY_BITS bits
+        let path = MerklePathVar::new_witness(cs.clone(), KEY_BITS, self.path.as_ref())?;
+        path.index()?.enforce_equal(&key)?;
+        let leaf = hash_inputs_var(cs.clone(), &self.params, &[value])?;
+        path.root(cs, &self.params, &leaf)?.enforce_equal(&root)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    fn is_satisfied(circuit: LookupCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    // balances by account
+    fn store() -> KvStore {
+        let mut store = KvStore::new(poseidon_parameters());
+        for &(key, value) in [(7, 100u64), (0xdead_beef, 2500), (u32::MAX, 1)].iter() {
+            store.insert(key, Fr::from(value));
+        }
+        store
+    }
+
+    #[test]
+    fn test_kv_store() {
+        let mut store = store();
+        let root = store.root();
+        assert!(store.contains(7, Fr::from(100u8)));
+        assert!(!store.contains(7, Fr::from(101u8)));
+        assert!(!store.contains(8, Fr::from(0u8)));
+
+        store.insert(8, Fr::from(5u8));
+        assert_ne!(store.root(), root);
+        store.remove(8);
+        assert_eq!(store.root(), root);
+        // a key set to zero is not an absent key
+        store.insert(8, Fr::from(0u8));
+        assert_ne!(store.root(), root);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let store = store();
+
+        for &public in [Public::Key, Public::Value].iter() {
+            let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+                LookupCircuit::blank(params.clone(), public),
+                rng,
+            )
+            .unwrap();
+            let circuit = LookupCircuit::new(
+                params.clone(),
+                public,
+                &store,
+                0xdead_beef,
+                Fr::from(2500u16),
+            )
+            .unwrap();
+            let inputs = circuit.public_inputs().unwrap();
+            let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+            assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+            // another key or value, another state of the store
+            let mut other = inputs.clone();
+            other[1] += Fr::from(1u8);
+            assert!(!Groth16::<Bls12_381>::verify(&vk, &other, &proof).unwrap());
+            let mut changed = store.clone();
+            changed.insert(7, Fr::from(90u8));
+            let other = vec![changed.root(), inputs[1]];
+            assert!(!Groth16::<Bls12_381>::verify(&vk, &other, &proof).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_wrong_lookups() {
+        let params = poseidon_parameters();
+        let store = store();
+        assert!(
+            LookupCircuit::new(params.clone(), Public::Key, &store, 7, Fr::from(99u8)).is_none()
+        );
+
+        for &public in [Public::Key, Public::Value].iter() {
+            let circuit =
+                LookupCircuit::new(params.clone(), public, &store, 7, Fr::from(100u8)).unwrap();
+            assert!(is_satisfied(circuit.clone()));
+            // the path of the key with another value, or of another key
+            let mut wrong = circuit.clone();
+            wrong.value = Some(Fr::from(99u8));
+            assert!(!is_satisfied(wrong));
+            let mut wrong = circuit.clone();
+            wrong.key = Some(u32::MAX);
+            assert!(!is_satisfied(wrong));
+            // an absent key, its leaf is zero and not the hash of zero
+            let mut absent = circuit;
+            absent.key = Some(8);
+            absent.value = Some(Fr::zero());
+            absent.path = Some(store.path(8));
+            assert!(!is_satisfied(absent));
+        }
+    }
+}
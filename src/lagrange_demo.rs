@@ -0,0 +1,234 @@
+use ark_ff::PrimeField;
+use ark_poly::{univariate::DensePolynomial, EvaluationDomain, Radix2EvaluationDomain};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_serialize::CanonicalSerialize;
+use blake2::{Blake2s, Digest};
+
+use crate::encoding::{field_from_bytes_mod_order, Endian};
+use crate::fft_demo;
+
+// a polynomial p is defined by its public evaluations y_i = p(w^i) over a domain H.
+// Natively the prover commits to p (KZG) and opens it at a fresh point z derived from
+// the commitment. The circuit below proves that the claimed value v is consistent with
+// the public evaluations, i.e. v == sum_i y_i * L_i(z), using the barycentric form
+//   L_i(z) = Z_H(z) / n * w^i / (z - w^i)
+// so both worlds (polynomial commitments and R1CS) agree on p(z).
+
+// the polynomial through the public evaluation points
+pub fn interpolate<F: PrimeField>(
+    domain: &Radix2EvaluationDomain<F>,
+    evals: &[F],
+) -> DensePolynomial<F> {
+    fft_demo::interpolate(domain, evals)
+}
+
+// Fiat-Shamir: the evaluation point is derived from the commitment, so the prover
+// cannot choose it after seeing it
+pub fn challenge_point<F: PrimeField, C: CanonicalSerialize>(commitment: &C) -> F {
+    let mut bytes = vec![0; commitment.serialized_size()];
+    commitment.serialize(&mut bytes[..]).unwrap();
+    field_from_bytes_mod_order(&Blake2s::digest(&bytes), Endian::Little)
+}
+
+// native counterpart of the circuit
+pub fn evaluate<F: PrimeField>(domain: &Radix2EvaluationDomain<F>, evals: &[F], point: F) -> F {
+    fft_demo::evaluate_from_evals(domain, evals, point)
+}
+
+pub struct LagrangeEvalCircuit<F: PrimeField> {
+    pub domain: Radix2EvaluationDomain<F>,
+    pub evals: Option<Vec<F>>,
+    pub point: Option<F>,
+    pub value: Option<F>,
+}
+
+impl<F: PrimeField> LagrangeEvalCircuit<F> {
+    pub fn new(domain: Radix2EvaluationDomain<F>, evals: Vec<F>, point: F, value: F) -> Self {
+        Self {
+            domain,
+            evals: Some(evals),
+            point: Some(point),
+            value: Some(value),
+        }
+    }
+
+    // shape only, used for the setup
+    pub fn blank(domain: Radix2EvaluationDomain<F>) -> Self {
+        Self {
+            domain,
+            evals: None,
+            point: None,
+            value: None,
+        }
+    }
+
+    // public inputs in allocation order: evaluations, point, value
+    pub fn public_inputs(evals: &[F], point: F, value: F) -> Vec<F> {
+        let mut inputs = evals.to_vec();
+        inputs.push(point);
+        inputs.push(value);
+        inputs
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for LagrangeEvalCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let n = self.domain.size();
+
+        let mut evals = Vec::with_capacity(n);
+        for i in 0..n {
+            // a list shorter than the domain is missing the rest of the assignment
+            let eval = self.evals.as_ref().and_then(|e| e.get(i).copied());
+            evals.push(FpVar::new_input(cs.clone(), || {
+                eval.ok_or(SynthesisError::AssignmentMissing)
+            })?);
+        }
+        let point = FpVar::new_input(cs.clone(), || {
+            self.point.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let value = FpVar::new_input(cs, || self.value.ok_or(SynthesisError::AssignmentMissing))?;
+
+        // Z_H(z) = z^n - 1, n is a power of two so this is log(n) squarings
+        let mut z_n = point.clone();
+        for _ in 0..self.domain.log_size_of_group {
+            z_n = z_n.square()?;
+        }
+        let vanishing = z_n - F::one();
+
+        // sum_i y_i * w^i / (z - w^i), the inverse is unsatisfiable when z is in H
+        let mut sum = FpVar::zero();
+        for (i, eval) in evals.iter().enumerate() {
+            let omega_i = self.domain.element(i);
+            let inv = (&point - omega_i).inverse()?;
+            sum += eval * &inv * omega_i;
+        }
+
+        let result = sum * vanishing * self.domain.size_inv;
+        result.enforce_equal(&value)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+    use ark_groth16::Groth16;
+    use ark_poly::Polynomial;
+    use ark_poly_commit::{marlin_pc::MarlinKZG10, LabeledPolynomial, PolynomialCommitment};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    type PC = MarlinKZG10<Bls12_381, DensePolynomial<BlsFr>>;
+
+    #[test]
+    fn test_native_lagrange_evaluation() {
+        let rng = &mut ark_std::test_rng();
+        let domain = Radix2EvaluationDomain::<BlsFr>::new(8).unwrap();
+
+        let evals: Vec<BlsFr> = (0..8).map(|_| BlsFr::rand(rng)).collect();
+        let poly = interpolate(&domain, &evals);
+        for _ in 0..5 {
+            let z = BlsFr::rand(rng);
+            assert_eq!(evaluate(&domain, &evals, z), poly.evaluate(&z));
+        }
+    }
+
+    #[test]
+    fn test_commitment_and_circuit_agree() {
+        let rng = &mut ark_std::test_rng();
+        let domain = Radix2EvaluationDomain::<BlsFr>::new(8).unwrap();
+        let evals: Vec<BlsFr> = (0..domain.size()).map(|_| BlsFr::rand(rng)).collect();
+
+        // commit to the polynomial defined by the public evaluations
+        let poly = interpolate(&domain, &evals);
+        let pp = PC::setup(domain.size() - 1, None, rng).unwrap();
+        let (ck, pc_vk) = PC::trim(&pp, domain.size() - 1, 0, None).unwrap();
+        let labeled = LabeledPolynomial::new("p".to_string(), poly.clone(), None, None);
+        let (comms, rands) = PC::commit(&ck, std::slice::from_ref(&labeled), None).unwrap();
+
+        // open at the fresh point
+        let z: BlsFr = challenge_point(comms[0].commitment());
+        let value = poly.evaluate(&z);
+        let opening_challenge = BlsFr::rand(rng);
+        let opening =
+            PC::open(&ck, &[labeled], &comms, &z, opening_challenge, &rands, None).unwrap();
+        assert!(PC::check(
+            &pc_vk,
+            &comms,
+            &z,
+            [value],
+            &opening,
+            opening_challenge,
+            None
+        )
+        .unwrap());
+        assert!(!PC::check(
+            &pc_vk,
+            &comms,
+            &z,
+            [value + BlsFr::from(1u8)],
+            &opening,
+            opening_challenge,
+            None
+        )
+        .unwrap());
+
+        // prove in-circuit that the opened value is consistent with the public evaluations
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            LagrangeEvalCircuit::<BlsFr>::blank(domain),
+            rng,
+        )
+        .unwrap();
+        let proof = Groth16::<Bls12_381>::prove(
+            &pk,
+            LagrangeEvalCircuit::new(domain, evals.clone(), z, value),
+            rng,
+        )
+        .unwrap();
+
+        let inputs = LagrangeEvalCircuit::public_inputs(&evals, z, value);
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        let wrong_inputs = LagrangeEvalCircuit::public_inputs(&evals, z, value + BlsFr::from(1u8));
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &wrong_inputs, &proof).unwrap());
+    }
+
+    #[test]
+    fn test_wrong_value_is_unsatisfied() {
+        let rng = &mut ark_std::test_rng();
+        let domain = Radix2EvaluationDomain::<BlsFr>::new(4).unwrap();
+        let evals: Vec<BlsFr> = (0..domain.size()).map(|_| BlsFr::rand(rng)).collect();
+        let z = BlsFr::rand(rng);
+        let value = evaluate(&domain, &evals, z);
+
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        LagrangeEvalCircuit::new(domain, evals.clone(), z, value)
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        LagrangeEvalCircuit::new(domain, evals, z, value + BlsFr::from(1u8))
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_point_inside_domain_is_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let domain = Radix2EvaluationDomain::<BlsFr>::new(4).unwrap();
+        let evals: Vec<BlsFr> = (0..domain.size()).map(|_| BlsFr::rand(rng)).collect();
+
+        // z - w^1 has no inverse, so the inverse constraint cannot be satisfied
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        LagrangeEvalCircuit::new(domain, evals.clone(), domain.element(1), evals[1])
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
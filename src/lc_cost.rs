@@ -0,0 +1,289 @@
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{ConstraintSynthesizer, SynthesisError};
+use std::fmt;
+
+use crate::matrices::{named_constraint_matrices, NamedMatrices};
+use crate::visualize::variable_name;
+
+// the terms of every linear combination of a circuit, and the constraints that could go.
+// A constraint is a product of two linear combinations equal to a third; one where A or
+// B is a constant is linear, and a linear constraint costs a whole row for what the
+// other constraints could say for free inside their linear combinations:
+//   fold    the linear constraint uses a witness that a product constraint defines
+//           alone in C and nothing else uses: the linear relation goes into that C
+//           instead, one constraint and one witness fewer, like the cubic circuit in two
+//           constraints (`CubicDemoCircuitOptimized`)
+//   inline  otherwise, a witness of the linear constraint is substituted by the rest of
+//           it wherever else it is used, one constraint and one witness fewer for more
+//           terms in those uses, see `marlin_demo::Formulation` for when that costs more
+// Candidates are found one at a time: taking one can rule out another.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintCost {
+    pub name: String,
+    // terms of A, B and C
+    pub a: usize,
+    pub b: usize,
+    pub c: usize,
+    pub linear: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Suggestion {
+    Fold {
+        constraint: usize,
+        into: usize,
+        variable: usize,
+    },
+    Inline {
+        constraint: usize,
+        variable: usize,
+        // the other constraints `variable` appears in
+        uses: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LcReport {
+    pub constraints: Vec<ConstraintCost>,
+    pub suggestions: Vec<Suggestion>,
+    pub num_instance_variables: usize,
+}
+
+impl LcReport {
+    pub fn num_non_zero(&self) -> [usize; 3] {
+        self.constraints.iter().fold([0; 3], |[a, b, c], cost| {
+            [a + cost.a, b + cost.b, c + cost.c]
+        })
+    }
+}
+
+type Row<F> = Vec<(F, usize)>;
+
+// nothing but the constant one, or nothing at all
+fn is_constant<F>(row: &Row<F>) -> bool {
+    row.iter().all(|&(_, var)| var == 0)
+}
+
+pub fn analyze<F: PrimeField>(named: &NamedMatrices<F>) -> LcReport {
+    let m = &named.matrices;
+    let num_instance = m.num_instance_variables;
+    let rows = |i: usize| [&m.a[i], &m.b[i], &m.c[i]];
+
+    // the constraints each variable appears in
+    let mut uses = vec![Vec::new(); num_instance + m.num_witness_variables];
+    for i in 0..m.num_constraints {
+        let mut vars: Vec<usize> = rows(i).iter().flat_map(|r| r.iter().map(|t| t.1)).collect();
+        vars.sort_unstable();
+        vars.dedup();
+        for var in vars {
+            uses[var].push(i);
+        }
+    }
+
+    let mut constraints = Vec::with_capacity(m.num_constraints);
+    let mut suggestions = Vec::new();
+    for (i, name) in named.constraint_names.iter().enumerate() {
+        let linear = is_constant(&m.a[i]) || is_constant(&m.b[i]);
+        constraints.push(ConstraintCost {
+            name: name.clone(),
+            a: m.a[i].len(),
+            b: m.b[i].len(),
+            c: m.c[i].len(),
+            linear,
+        });
+        if !linear {
+            continue;
+        }
+        let mut witnesses: Vec<usize> = rows(i)
+            .iter()
+            .flat_map(|r| r.iter().map(|t| t.1))
+            .filter(|&var| var >= num_instance)
+            .collect();
+        witnesses.sort_unstable();
+        witnesses.dedup();
+
+        let fold = witnesses.iter().find_map(|&var| match uses[var][..] {
+            [j, k] => {
+                let into = if j == i { k } else { j };
+                let defines = m.c[into].len() == 1 && m.c[into][0].1 == var;
+                let product = !is_constant(&m.a[into]) && !is_constant(&m.b[into]);
+                let only_c = !m.a[into].iter().chain(&m.b[into]).any(|t| t.1 == var);
+                (defines && product && only_c).then_some(Suggestion::Fold {
+                    constraint: i,
+                    into,
+                    variable: var,
+                })
+            }
+            _ => None,
+        });
+        let inline = || {
+            witnesses
+                .iter()
+                .min_by_key(|&&var| uses[var].len())
+                .map(|&var| Suggestion::Inline {
+                    constraint: i,
+                    variable: var,
+                    uses: uses[var].len() - 1,
+                })
+        };
+        suggestions.extend(fold.or_else(inline));
+    }
+    LcReport {
+        constraints,
+        suggestions,
+        num_instance_variables: num_instance,
+    }
+}
+
+pub fn analyze_circuit<F, C>(circuit: C) -> Result<LcReport, SynthesisError>
+where
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+{
+    Ok(analyze(&named_constraint_matrices(circuit)?))
+}
+
+impl fmt::Display for LcReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .constraints
+            .iter()
+            .map(|c| c.name.len())
+            .chain(Some("constraint".len()))
+            .max()
+            .unwrap_or(0);
+        writeln!(
+            f,
+            "{:<w$} {:>5} {:>5} {:>5}",
+            "constraint",
+            "A",
+            "B",
+            "C",
+            w = width
+        )?;
+        for cost in &self.constraints {
+            let kind = if cost.linear { "  linear" } else { "" };
+            writeln!(
+                f,
+                "{:<w$} {:>5} {:>5} {:>5}{}",
+                cost.name,
+                cost.a,
+                cost.b,
+                cost.c,
+                kind,
+                w = width
+            )?;
+        }
+        let [a, b, c] = self.num_non_zero();
+        writeln!(
+            f,
+            "{:<w$} {:>5} {:>5} {:>5}",
+            "non-zero",
+            a,
+            b,
+            c,
+            w = width
+        )?;
+
+        let name = |i: usize| &self.constraints[i].name;
+        let var = |v: usize| variable_name(self.num_instance_variables, v);
+        for suggestion in &self.suggestions {
+            match *suggestion {
+                Suggestion::Fold {
+                    constraint,
+                    into,
+                    variable,
+                } => writeln!(
+                    f,
+                    "fold {} into the C of {}, {} goes",
+                    name(constraint),
+                    name(into),
+                    var(variable)
+                )?,
+                Suggestion::Inline {
+                    constraint,
+                    variable,
+                    uses,
+                } => writeln!(
+                    f,
+                    "inline {} solved for {} into its {} other uses",
+                    name(constraint),
+                    var(variable),
+                    uses
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::{CubicDemoCircuit, CubicDemoCircuitOptimized};
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use ark_bls12_381::Fr as BlsFr;
+
+    #[test]
+    fn test_cubic() {
+        let report = analyze_circuit(CubicDemoCircuit::<BlsFr>::blank()).unwrap();
+        // [one, out | x, tmp_1, y]
+        let costs: Vec<_> = report
+            .constraints
+            .iter()
+            .map(|c| (c.a, c.b, c.c, c.linear))
+            .collect();
+        assert_eq!(
+            costs,
+            vec![(1, 1, 1, false), (1, 1, 1, false), (3, 1, 1, true)]
+        );
+        assert_eq!(report.num_non_zero(), [5, 3, 3]);
+        // (y + x + 5) * 1 = out folds into tmp_1 * x = y
+        assert_eq!(
+            report.suggestions,
+            vec![Suggestion::Fold {
+                constraint: 2,
+                into: 1,
+                variable: 4
+            }]
+        );
+        let text = report.to_string();
+        assert!(text.contains("linear"));
+        assert!(text.contains("fold"));
+        assert!(text.contains("w2 goes"));
+
+        // which is what the two-constraint version did
+        let report = analyze_circuit(CubicDemoCircuitOptimized::<BlsFr>::blank()).unwrap();
+        assert_eq!(report.constraints.len(), 2);
+        assert!(report.constraints.iter().all(|c| !c.linear));
+        assert!(report.suggestions.is_empty());
+        assert!(analyze_circuit(MultiplyDemoCircuit::<BlsFr>::blank())
+            .unwrap()
+            .suggestions
+            .is_empty());
+    }
+
+    #[cfg(feature = "marlin")]
+    #[test]
+    fn test_inline() {
+        use crate::marlin_demo::{Formulation, PrefixProductCircuit};
+
+        // every prefix sum of the sparse formulation is a linear constraint
+        let k = 4;
+        let report =
+            analyze_circuit(PrefixProductCircuit::<BlsFr>::blank(k, Formulation::Sparse)).unwrap();
+        let linear = report.constraints.iter().filter(|c| c.linear).count();
+        assert_eq!(linear, k - 1);
+        assert_eq!(report.suggestions.len(), k - 1);
+        assert!(report
+            .suggestions
+            .iter()
+            .all(|s| matches!(s, Suggestion::Inline { .. })));
+
+        // the dense one is what inlining them gives
+        let report =
+            analyze_circuit(PrefixProductCircuit::<BlsFr>::blank(k, Formulation::Dense)).unwrap();
+        assert!(report.suggestions.is_empty());
+    }
+}
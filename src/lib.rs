@@ -1,4 +1,170 @@
+// without `std` only the Groth16 verifier and the artifact envelope are built, see the
+// `verifier-only` feature
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "gadgets")]
+pub mod accumulator_demo;
+#[cfg(feature = "recursion")]
+pub mod aggregation_demo;
+#[cfg(feature = "gadgets")]
+pub mod airdrop_demo;
+#[cfg(feature = "cli")]
+pub mod any_artifact;
+#[cfg(feature = "tokio")]
+pub mod async_prover;
+#[cfg(feature = "cli")]
+pub mod batch;
+#[cfg(feature = "gadgets")]
+pub mod battleship_demo;
+#[cfg(feature = "gadgets")]
+pub mod boolean_demo;
+#[cfg(feature = "std")]
+pub mod builder;
+#[cfg(all(feature = "groth16", feature = "marlin"))]
+pub mod comparison;
+#[cfg(feature = "gadgets")]
+pub mod conditional_demo;
+#[cfg(feature = "cli")]
+pub mod config;
+#[cfg(feature = "std")]
 pub mod cubic_demo;
+#[cfg(feature = "gadgets")]
 pub mod cubic_gadget;
+#[cfg(feature = "recursion")]
+pub mod cycles;
+#[cfg(feature = "gadgets")]
+pub mod date_gadget;
+#[cfg(feature = "gadgets")]
+pub mod dice_demo;
+#[cfg(feature = "gadgets")]
+pub mod div_rem_demo;
+pub mod encoding;
+#[cfg(feature = "gadgets")]
+pub mod equivalence;
+#[cfg(feature = "groth16")]
+pub mod eth_calldata;
+#[cfg(all(feature = "std", feature = "groth16"))]
+pub mod explain;
+#[cfg(feature = "std")]
+pub mod expr;
+#[cfg(feature = "std")]
+pub mod fft_demo;
+#[cfg(feature = "gadgets")]
+pub mod fiat_shamir_demo;
+#[cfg(feature = "std")]
+pub mod fingerprint;
+#[cfg(feature = "std")]
+pub mod formula;
+#[cfg(feature = "gadgets")]
+pub mod game_demo;
+#[cfg(feature = "gadgets")]
+pub mod geo_demo;
+#[cfg(feature = "gadgets")]
+pub mod hashed_inputs_demo;
+#[cfg(feature = "groth16")]
+pub mod interop;
+#[cfg(feature = "recursion")]
+pub mod ivc_demo;
+#[cfg(feature = "std")]
+pub mod key_cache;
+#[cfg(feature = "gadgets")]
+pub mod kv_demo;
+#[cfg(feature = "gadgets")]
+pub mod lagrange_demo;
+#[cfg(feature = "std")]
+pub mod lc_cost;
+#[cfg(feature = "gadgets")]
+pub mod limbs;
+#[cfg(feature = "gadgets")]
+pub mod linked_proofs_demo;
+#[cfg(feature = "gadgets")]
+pub mod lottery_demo;
+#[cfg(feature = "marlin")]
 pub mod marlin_demo;
+#[cfg(feature = "marlin")]
+pub mod marlin_srs;
+#[cfg(feature = "std")]
+pub mod matmul_demo;
+#[cfg(feature = "std")]
+pub mod matrices;
+#[cfg(feature = "gadgets")]
+pub mod merkle_gadget;
+#[cfg(all(feature = "std", feature = "groth16"))]
+pub mod msm;
+#[cfg(feature = "std")]
 pub mod multiply_demo;
+#[cfg(feature = "gadgets")]
+pub mod multiproof_demo;
+#[cfg(feature = "std")]
+pub mod nullifier_set;
+#[cfg(feature = "gadgets")]
+pub mod ownership_demo;
+#[cfg(feature = "gadgets")]
+pub mod packing;
+#[cfg(feature = "std")]
+pub mod padding;
+#[cfg(feature = "recursion")]
+pub mod pairing_demo;
+pub mod persist;
+#[cfg(feature = "gadgets")]
+pub mod poker_demo;
+#[cfg(feature = "gadgets")]
+pub mod poseidon_params;
+#[cfg(feature = "gadgets")]
+pub mod private_index_demo;
+#[cfg(feature = "std")]
+pub mod progress;
+#[cfg(feature = "std")]
+pub mod prover;
+#[cfg(feature = "std")]
+pub mod prover_context;
+#[cfg(feature = "gadgets")]
+pub mod redaction_demo;
+#[cfg(feature = "cli")]
+pub mod registry;
+#[cfg(feature = "std")]
+pub mod rng;
+#[cfg(feature = "gadgets")]
+pub mod schnorr_gadget;
+#[cfg(feature = "gadgets")]
+pub mod selective_disclosure_demo;
+#[cfg(feature = "std")]
+pub mod sizes;
+#[cfg(feature = "gadgets")]
+pub mod square_demo;
+#[cfg(feature = "gadgets")]
+pub mod state;
+#[cfg(all(feature = "std", feature = "groth16"))]
+pub mod streaming_prover;
+#[cfg(feature = "substrate")]
+pub mod substrate_support;
+#[cfg(feature = "std")]
+pub mod synthetic;
+#[cfg(all(feature = "cli", feature = "insecure"))]
+pub mod test_vectors;
+#[cfg(feature = "std")]
+pub mod text;
+#[cfg(feature = "gadgets")]
+pub mod threshold_demo;
+#[cfg(feature = "gadgets")]
+pub mod timestamp_demo;
+#[cfg(feature = "insecure-demos")]
+pub mod trusted_setup_demo;
+#[cfg(feature = "groth16")]
+pub mod verifier;
+#[cfg(feature = "recursion")]
+pub mod verifier_gadget_demo;
+#[cfg(feature = "std")]
+pub mod visualize;
+#[cfg(feature = "gadgets")]
+pub mod vrf_demo;
+#[cfg(feature = "std")]
+pub mod witness_cache;
+#[cfg(feature = "cli")]
+pub mod witness_json;
+#[cfg(feature = "std")]
+pub mod workload;
+
+// same as `ark_crypto_primitives::Error`, so the backend only builds do not need it
+#[cfg(feature = "std")]
+pub type Error = Box<dyn ark_std::error::Error>;
@@ -0,0 +1,325 @@
+use ark_ff::{BigInteger, FpParameters, PrimeField};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{Namespace, SynthesisError};
+use ark_std::marker::PhantomData;
+
+// an element of a field A in a circuit over another field F, as little-endian limbs of
+// `limb_bits` bits, each an F element. A recursive circuit meets these for the fields of
+// the inner curve: the inner scalars are public inputs of the inner proof, the inner
+// base field coordinates of its points, and only on a cycle (`cycles`) is one of them
+// the native field. `cycles::convert` covers a number that fits in both fields, the
+// limbs any element of A:
+//   to_limbs / from_limbs   outside the circuit, `from_limbs` refusing limbs of more
+//                           than `limb_bits` bits and numbers not below the modulus of A
+//   LimbsVar                the limbs in the circuit and the bits of A they hold, made
+//                           from a value, from the bits (e.g. those of a
+//                           `BooleanInputVar`) or from limbs already in the circuit
+// Every way in constrains the limbs to their width and the number to below the modulus
+// of A, so each element has exactly one encoding. A limb is at most the capacity of F,
+// so packing its bits can't wrap around.
+
+// the widest limb F holds without wrapping
+pub fn max_limb_bits<F: PrimeField>() -> usize {
+    F::Params::CAPACITY as usize
+}
+
+pub fn num_limbs<A: PrimeField>(limb_bits: usize) -> usize {
+    (A::Params::MODULUS_BITS as usize).div_ceil(limb_bits)
+}
+
+fn check_limb_bits<F: PrimeField>(limb_bits: usize) {
+    assert!(
+        limb_bits > 0 && limb_bits <= max_limb_bits::<F>(),
+        "limbs of {} bits don't fit the field",
+        limb_bits
+    );
+}
+
+fn from_bits_le<F: PrimeField>(bits: &[bool]) -> Option<F> {
+    F::from_repr(F::BigInt::from_bits_le(bits))
+}
+
+pub fn to_limbs<A: PrimeField, F: PrimeField>(value: A, limb_bits: usize) -> Vec<F> {
+    check_limb_bits::<F>(limb_bits);
+    let bits = value.into_repr().to_bits_le();
+    bits[..A::Params::MODULUS_BITS as usize]
+        .chunks(limb_bits)
+        .map(|chunk| from_bits_le(chunk).unwrap())
+        .collect()
+}
+
+// None unless the limbs are what `to_limbs` gives for some element
+pub fn from_limbs<A: PrimeField, F: PrimeField>(limbs: &[F], limb_bits: usize) -> Option<A> {
+    check_limb_bits::<F>(limb_bits);
+    let num_bits = A::Params::MODULUS_BITS as usize;
+    if limbs.len() != num_limbs::<A>(limb_bits) {
+        return None;
+    }
+    let mut bits = Vec::with_capacity(num_bits);
+    for (i, limb) in limbs.iter().enumerate() {
+        let width = limb_bits.min(num_bits - i * limb_bits);
+        let limb = limb.into_repr().to_bits_le();
+        if limb[width..].iter().any(|&bit| bit) {
+            return None;
+        }
+        bits.extend_from_slice(&limb[..width]);
+    }
+    from_bits_le(&bits)
+}
+
+// the modulus of A minus one, the largest canonical element
+fn largest<A: PrimeField>() -> A::BigInt {
+    let mut largest = A::Params::MODULUS;
+    largest.sub_noborrow(&A::BigInt::from(1));
+    largest
+}
+
+pub struct LimbsVar<A: PrimeField, F: PrimeField> {
+    limbs: Vec<FpVar<F>>,
+    // the MODULUS_BITS bits of the element, little-endian
+    bits: Vec<Boolean<F>>,
+    limb_bits: usize,
+    _field: PhantomData<A>,
+}
+
+impl<A: PrimeField, F: PrimeField> LimbsVar<A, F> {
+    // the limbs as public inputs with `AllocationMode::Input`, the bits as witnesses
+    // otherwise, the limbs then being sums of them
+    pub fn new<T>(
+        cs: impl Into<Namespace<F>>,
+        value: T,
+        limb_bits: usize,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError>
+    where
+        T: FnOnce() -> Result<A, SynthesisError>,
+    {
+        check_limb_bits::<F>(limb_bits);
+        let cs = cs.into().cs();
+        let value = if cs.is_in_setup_mode() && mode != AllocationMode::Constant {
+            Err(SynthesisError::AssignmentMissing)
+        } else {
+            value()
+        };
+        if mode == AllocationMode::Input {
+            let limbs = (0..num_limbs::<A>(limb_bits))
+                .map(|i| {
+                    FpVar::new_input(cs.clone(), || {
+                        value.map(|value| to_limbs::<A, F>(value, limb_bits)[i])
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            return Self::from_limbs(limbs, limb_bits);
+        }
+        let bits = (0..A::Params::MODULUS_BITS as usize)
+            .map(|i| {
+                Boolean::new_variable(
+                    cs.clone(),
+                    || value.map(|value| value.into_repr().get_bit(i)),
+                    mode,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_bits(&bits, limb_bits)
+    }
+
+    pub fn from_bits(bits: &[Boolean<F>], limb_bits: usize) -> Result<Self, SynthesisError> {
+        check_limb_bits::<F>(limb_bits);
+        assert_eq!(bits.len(), A::Params::MODULUS_BITS as usize);
+        Boolean::enforce_smaller_or_equal_than_le(bits, largest::<A>())?;
+        let limbs = bits
+            .chunks(limb_bits)
+            .map(Boolean::le_bits_to_fp_var)
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            limbs,
+            bits: bits.to_vec(),
+            limb_bits,
+            _field: PhantomData,
+        })
+    }
+
+    // the bits of each limb as witnesses, equal to the limb when packed
+    pub fn from_limbs(limbs: Vec<FpVar<F>>, limb_bits: usize) -> Result<Self, SynthesisError> {
+        check_limb_bits::<F>(limb_bits);
+        let num_bits = A::Params::MODULUS_BITS as usize;
+        assert_eq!(limbs.len(), num_limbs::<A>(limb_bits));
+        let mut bits = Vec::with_capacity(num_bits);
+        for (i, limb) in limbs.iter().enumerate() {
+            let width = limb_bits.min(num_bits - i * limb_bits);
+            let mode = match limb.is_constant() {
+                true => AllocationMode::Constant,
+                false => AllocationMode::Witness,
+            };
+            let limb_value = limb.value();
+            let limb_bits = (0..width)
+                .map(|j| {
+                    Boolean::new_variable(
+                        limb.cs(),
+                        || limb_value.map(|v| v.into_repr().get_bit(j)),
+                        mode,
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Boolean::le_bits_to_fp_var(&limb_bits)?.enforce_equal(limb)?;
+            bits.extend(limb_bits);
+        }
+        Boolean::enforce_smaller_or_equal_than_le(&bits, largest::<A>())?;
+        Ok(Self {
+            limbs,
+            bits,
+            limb_bits,
+            _field: PhantomData,
+        })
+    }
+
+    pub fn limbs(&self) -> &[FpVar<F>] {
+        &self.limbs
+    }
+
+    pub fn bits(&self) -> &[Boolean<F>] {
+        &self.bits
+    }
+
+    pub fn limb_bits(&self) -> usize {
+        self.limb_bits
+    }
+
+    pub fn value(&self) -> Result<A, SynthesisError> {
+        from_bits_le(&self.bits.value()?).ok_or(SynthesisError::Unsatisfiable)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Fq as BlsFq, Fr as BlsFr};
+    use ark_ff::Field;
+    use ark_relations::r1cs::{ConstraintSystem, SynthesisMode};
+    use ark_std::{One, UniformRand};
+
+    #[test]
+    fn test_roundtrip() {
+        let rng = &mut ark_std::test_rng();
+        // 381 bits in a field of 255
+        for &limb_bits in [64, 100, max_limb_bits::<BlsFr>()].iter() {
+            for _ in 0..20 {
+                let x = BlsFq::rand(rng);
+                let limbs = to_limbs::<_, BlsFr>(x, limb_bits);
+                assert_eq!(limbs.len(), num_limbs::<BlsFq>(limb_bits));
+                assert_eq!(from_limbs(&limbs, limb_bits), Some(x));
+            }
+            let largest = -BlsFq::one();
+            assert_eq!(
+                from_limbs(&to_limbs::<_, BlsFr>(largest, limb_bits), limb_bits),
+                Some(largest)
+            );
+        }
+        assert_eq!(num_limbs::<BlsFq>(64), 6);
+        assert_eq!(num_limbs::<BlsFq>(254), 2);
+
+        // a limb too wide, too few limbs, the modulus itself
+        let mut limbs = to_limbs::<_, BlsFr>(BlsFq::from(5u8), 64);
+        limbs[0] += BlsFr::from(1u128 << 64);
+        assert_eq!(from_limbs::<BlsFq, _>(&limbs, 64), None);
+        assert_eq!(from_limbs::<BlsFq, _>(&limbs[1..], 64), None);
+        let modulus: Vec<BlsFr> = <BlsFq as Field>::characteristic()
+            .iter()
+            .map(|&limb| BlsFr::from(limb))
+            .collect();
+        assert_eq!(from_limbs::<BlsFq, _>(&modulus, 64), None);
+    }
+
+    #[test]
+    fn test_gadget() {
+        let rng = &mut ark_std::test_rng();
+        let x = BlsFq::rand(rng);
+        let limb_bits = 128;
+        for &mode in [
+            AllocationMode::Witness,
+            AllocationMode::Input,
+            AllocationMode::Constant,
+        ]
+        .iter()
+        {
+            let cs = ConstraintSystem::<BlsFr>::new_ref();
+            let var = LimbsVar::<BlsFq, _>::new(cs.clone(), || Ok(x), limb_bits, mode).unwrap();
+            assert_eq!(var.limbs().value().unwrap(), to_limbs(x, limb_bits));
+            assert_eq!(var.value().unwrap(), x);
+            assert_eq!(var.bits().len(), 381);
+            assert!(cs.is_satisfied().unwrap());
+            match mode {
+                AllocationMode::Input => assert_eq!(cs.num_instance_variables(), 1 + 3),
+                AllocationMode::Witness => assert_eq!(cs.num_instance_variables(), 1),
+                AllocationMode::Constant => assert_eq!(cs.num_constraints(), 0),
+            }
+        }
+
+        // the same element from the limbs and from the bits
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        let var =
+            LimbsVar::<BlsFq, _>::new(cs.clone(), || Ok(x), 64, AllocationMode::Witness).unwrap();
+        let back = LimbsVar::<BlsFq, _>::from_limbs(var.limbs().to_vec(), 64).unwrap();
+        back.bits().enforce_equal(var.bits()).unwrap();
+        let other = LimbsVar::<BlsFq, _>::from_bits(back.bits(), 128).unwrap();
+        assert_eq!(other.value().unwrap(), x);
+        assert!(cs.is_satisfied().unwrap());
+
+        // setup needs no value
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        LimbsVar::<BlsFq, _>::new(
+            cs.clone(),
+            || Err(SynthesisError::AssignmentMissing),
+            limb_bits,
+            AllocationMode::Input,
+        )
+        .unwrap();
+        assert_eq!(cs.num_instance_variables(), 1 + 3);
+    }
+
+    #[test]
+    fn test_not_canonical() {
+        // limbs of the modulus of Fq: every limb in range, the number not
+        let modulus: Vec<BlsFr> = <BlsFq as Field>::characteristic()
+            .iter()
+            .map(|&limb| BlsFr::from(limb))
+            .collect();
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        let limbs = Vec::<FpVar<_>>::new_input(cs.clone(), || Ok(modulus)).unwrap();
+        LimbsVar::<BlsFq, _>::from_limbs(limbs, 64).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // a limb above its width
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        let mut limbs = to_limbs::<_, BlsFr>(BlsFq::from(5u8), 64);
+        limbs[0] += BlsFr::from(1u128 << 64);
+        limbs[1] -= BlsFr::one();
+        let limbs = Vec::<FpVar<_>>::new_witness(cs.clone(), || Ok(limbs)).unwrap();
+        LimbsVar::<BlsFq, _>::from_limbs(limbs, 64).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    // the scalars of MNT6-298 on MNT4-298: one bit more than its field holds
+    #[cfg(feature = "recursion")]
+    #[test]
+    fn test_cycle_scalars() {
+        use crate::cycles::{self, Mnt6Mnt4, TwoChain};
+
+        type InnerFr = cycles::InnerFr<Mnt6Mnt4>;
+        type OuterFr = cycles::OuterFr<Mnt6Mnt4>;
+        let rng = &mut ark_std::test_rng();
+        let limb_bits = max_limb_bits::<OuterFr>();
+        assert_eq!(num_limbs::<InnerFr>(limb_bits), 2);
+        let x = -InnerFr::one();
+        assert_eq!(Mnt6Mnt4::to_outer(x), None);
+        let limbs = to_limbs::<_, OuterFr>(x, limb_bits);
+        assert_eq!(from_limbs(&limbs, limb_bits), Some(x));
+
+        let y = InnerFr::rand(rng);
+        let cs = ConstraintSystem::<OuterFr>::new_ref();
+        let var = LimbsVar::new(cs.clone(), || Ok(y), limb_bits, AllocationMode::Input).unwrap();
+        assert_eq!(var.value().unwrap(), y);
+        assert!(cs.is_satisfied().unwrap());
+    }
+}
@@ -0,0 +1,321 @@
+use ark_bls12_381::Fr;
+use ark_crypto_primitives::{
+    commitment::{
+        pedersen::{constraints::CommGadget, Commitment, Parameters, Randomness, Window},
+        CommitmentGadget, CommitmentScheme,
+    },
+    Error,
+};
+use ark_ed_on_bls12_381::{constraints::EdwardsVar, EdwardsAffine, EdwardsProjective};
+use ark_ff::to_bytes;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_snark::SNARK;
+use ark_std::rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::cubic_demo::CubicWitness;
+use crate::multiply_demo::MultiplyWitness;
+
+// two different circuits proving statements about the same secret. Each one exposes a
+// Pedersen commitment to the secret as a public input next to its own, and a verifier
+// who sees the same commitment in both proofs knows the same value was used (the
+// commitment is binding) without learning it (it is hiding). Here the x of the cubic
+// circuit, x^3 + x + 5 == out, is the factor a of the multiply circuit, a * b == c.
+//
+// The commitment lives on Jubjub, the twisted Edwards curve whose base field is the
+// BLS12-381 scalar field, so the demo is for BLS12-381 only.
+
+// 64 windows of 4 bits, the 32 bytes of a field element
+#[derive(Clone)]
+pub struct ValueWindow;
+
+impl Window for ValueWindow {
+    const WINDOW_SIZE: usize = 4;
+    const NUM_WINDOWS: usize = 64;
+}
+
+pub type ValueCommitment = Commitment<EdwardsProjective, ValueWindow>;
+type ValueCommitmentGadget = CommGadget<EdwardsProjective, EdwardsVar, ValueWindow>;
+type ParametersVar =
+    <ValueCommitmentGadget as CommitmentGadget<ValueCommitment, Fr>>::ParametersVar;
+type RandomnessVar =
+    <ValueCommitmentGadget as CommitmentGadget<ValueCommitment, Fr>>::RandomnessVar;
+
+// the generators both circuits are built with, from a seeded ChaCha stream so that
+// prover and verifier derive the same ones
+pub fn commitment_parameters() -> Parameters<EdwardsProjective> {
+    let mut rng = ChaCha20Rng::from_seed(*b"linked_proofs_demo pedersen 0001");
+    ValueCommitment::setup(&mut rng).expect("the Pedersen setup does not fail")
+}
+
+pub fn commit(
+    params: &Parameters<EdwardsProjective>,
+    value: Fr,
+    randomness: &Randomness<EdwardsProjective>,
+) -> Result<EdwardsAffine, Error> {
+    ValueCommitment::commit(params, &to_bytes![value]?, randomness)
+}
+
+// the public inputs of a commitment, its two coordinates
+pub fn commitment_inputs(commitment: &EdwardsAffine) -> Vec<Fr> {
+    vec![commitment.x, commitment.y]
+}
+
+// enforces that `commitment`, allocated as a public input, opens to `value`
+fn enforce_commitment(
+    cs: ConstraintSystemRef<Fr>,
+    params: &Parameters<EdwardsProjective>,
+    value: &FpVar<Fr>,
+    randomness: Option<Randomness<EdwardsProjective>>,
+    commitment: Option<EdwardsAffine>,
+) -> Result<(), SynthesisError> {
+    let params_var = ParametersVar::new_constant(ns!(cs, "parameters"), params)?;
+    let randomness = RandomnessVar::new_witness(ns!(cs, "randomness"), || {
+        randomness.ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    let commitment = EdwardsVar::new_input(ns!(cs, "commitment"), || {
+        commitment.ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    ValueCommitmentGadget::commit(&params_var, &value.to_bytes()?, &randomness)?
+        .enforce_equal(&commitment)
+}
+
+// knows x with x^3 + x + 5 == out, committed to x
+#[derive(Clone)]
+pub struct LinkedCubicCircuit {
+    pub params: Parameters<EdwardsProjective>,
+    pub x: Option<Fr>,
+    pub randomness: Option<Randomness<EdwardsProjective>>,
+}
+
+impl LinkedCubicCircuit {
+    pub fn new(
+        params: Parameters<EdwardsProjective>,
+        x: Fr,
+        randomness: Randomness<EdwardsProjective>,
+    ) -> Self {
+        Self {
+            params,
+            x: Some(x),
+            randomness: Some(randomness),
+        }
+    }
+
+    // shape only, for the setup
+    pub fn blank(params: Parameters<EdwardsProjective>) -> Self {
+        Self {
+            params,
+            x: None,
+            randomness: None,
+        }
+    }
+
+    pub fn commitment(&self) -> Option<EdwardsAffine> {
+        commit(&self.params, self.x?, self.randomness.as_ref()?).ok()
+    }
+
+    // out, then the commitment
+    pub fn public_inputs(&self) -> Option<Vec<Fr>> {
+        let out = CubicWitness { x: self.x? }.instance().out;
+        let mut inputs = vec![out];
+        inputs.extend(commitment_inputs(&self.commitment()?));
+        Some(inputs)
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for LinkedCubicCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let public = self.public_inputs();
+        let commitment = self.commitment();
+
+        let x = FpVar::new_witness(ns!(cs, "x"), || {
+            self.x.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let out = FpVar::new_input(ns!(cs, "out"), || {
+            public
+                .as_ref()
+                .map(|inputs| inputs[0])
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        (x.square()? * &x + &x + Fr::from(5u8)).enforce_equal(&out)?;
+
+        enforce_commitment(cs, &self.params, &x, self.randomness, commitment)
+    }
+}
+
+// knows a and b with a * b == c, committed to a
+#[derive(Clone)]
+pub struct LinkedMultiplyCircuit {
+    pub params: Parameters<EdwardsProjective>,
+    pub a: Option<Fr>,
+    pub b: Option<Fr>,
+    pub randomness: Option<Randomness<EdwardsProjective>>,
+}
+
+impl LinkedMultiplyCircuit {
+    pub fn new(
+        params: Parameters<EdwardsProjective>,
+        a: Fr,
+        b: Fr,
+        randomness: Randomness<EdwardsProjective>,
+    ) -> Self {
+        Self {
+            params,
+            a: Some(a),
+            b: Some(b),
+            randomness: Some(randomness),
+        }
+    }
+
+    // shape only, for the setup
+    pub fn blank(params: Parameters<EdwardsProjective>) -> Self {
+        Self {
+            params,
+            a: None,
+            b: None,
+            randomness: None,
+        }
+    }
+
+    pub fn commitment(&self) -> Option<EdwardsAffine> {
+        commit(&self.params, self.a?, self.randomness.as_ref()?).ok()
+    }
+
+    // c, then the commitment
+    pub fn public_inputs(&self) -> Option<Vec<Fr>> {
+        let c = MultiplyWitness {
+            a: self.a?,
+            b: self.b?,
+        }
+        .instance()
+        .c;
+        let mut inputs = vec![c];
+        inputs.extend(commitment_inputs(&self.commitment()?));
+        Some(inputs)
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for LinkedMultiplyCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let public = self.public_inputs();
+        let commitment = self.commitment();
+
+        let a = FpVar::new_witness(ns!(cs, "a"), || {
+            self.a.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let b = FpVar::new_witness(ns!(cs, "b"), || {
+            self.b.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let c = FpVar::new_input(ns!(cs, "c"), || {
+            public
+                .as_ref()
+                .map(|inputs| inputs[0])
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        a.mul_equals(&b, &c)?;
+
+        enforce_commitment(cs, &self.params, &a, self.randomness, commitment)
+    }
+}
+
+// checks both proofs against the one commitment, so they can only both verify if they
+// were made for the same committed value
+pub fn verify_linked<S: SNARK<Fr>>(
+    cubic_vk: &S::VerifyingKey,
+    out: Fr,
+    cubic_proof: &S::Proof,
+    multiply_vk: &S::VerifyingKey,
+    c: Fr,
+    multiply_proof: &S::Proof,
+    commitment: &EdwardsAffine,
+) -> Result<bool, S::Error> {
+    let mut cubic_inputs = vec![out];
+    cubic_inputs.extend(commitment_inputs(commitment));
+    let mut multiply_inputs = vec![c];
+    multiply_inputs.extend(commitment_inputs(commitment));
+    Ok(S::verify(cubic_vk, &cubic_inputs, cubic_proof)?
+        && S::verify(multiply_vk, &multiply_inputs, multiply_proof)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_std::UniformRand;
+
+    #[test]
+    fn test_linked_proofs() {
+        let rng = &mut ark_std::test_rng();
+        let params = commitment_parameters();
+        let (cubic_pk, cubic_vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            LinkedCubicCircuit::blank(params.clone()),
+            rng,
+        )
+        .unwrap();
+        let (multiply_pk, multiply_vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            LinkedMultiplyCircuit::blank(params.clone()),
+            rng,
+        )
+        .unwrap();
+        // out, and the two coordinates of the commitment
+        assert_eq!(cubic_vk.gamma_abc_g1.len(), 1 + 3);
+
+        let secret = Fr::from(3u8);
+        let randomness = Randomness(UniformRand::rand(rng));
+        let cubic = LinkedCubicCircuit::new(params.clone(), secret, randomness.clone());
+        let commitment = cubic.commitment().unwrap();
+        let out = cubic.public_inputs().unwrap()[0];
+        assert_eq!(out, Fr::from(35u8));
+        let cubic_proof = Groth16::<Bls12_381>::prove(&cubic_pk, cubic, rng).unwrap();
+
+        let b = Fr::rand(rng);
+        let multiply = LinkedMultiplyCircuit::new(params.clone(), secret, b, randomness.clone());
+        assert_eq!(multiply.commitment(), Some(commitment));
+        let c = secret * b;
+        let multiply_proof = Groth16::<Bls12_381>::prove(&multiply_pk, multiply, rng).unwrap();
+        assert!(verify_linked::<Groth16<Bls12_381>>(
+            &cubic_vk,
+            out,
+            &cubic_proof,
+            &multiply_vk,
+            c,
+            &multiply_proof,
+            &commitment,
+        )
+        .unwrap());
+
+        // a multiply proof for another a carries another commitment, and does not verify
+        // against the one of the cubic proof
+        let other =
+            LinkedMultiplyCircuit::new(params.clone(), secret + Fr::from(1u8), b, randomness);
+        let other_inputs = other.public_inputs().unwrap();
+        assert_ne!(other.commitment(), Some(commitment));
+        let other_proof = Groth16::<Bls12_381>::prove(&multiply_pk, other, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&multiply_vk, &other_inputs, &other_proof).unwrap());
+        assert!(!verify_linked::<Groth16<Bls12_381>>(
+            &cubic_vk,
+            out,
+            &cubic_proof,
+            &multiply_vk,
+            other_inputs[0],
+            &other_proof,
+            &commitment,
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_commitment_hides_with_fresh_randomness() {
+        let rng = &mut ark_std::test_rng();
+        let params = commitment_parameters();
+        let x = Fr::from(3u8);
+        let first = commit(&params, x, &Randomness(UniformRand::rand(rng))).unwrap();
+        let second = commit(&params, x, &Randomness(UniformRand::rand(rng))).unwrap();
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,214 @@
+use ark_bls12_381::Fr;
+use ark_ff::{PrimeField, UniformRand, Zero};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::poseidon::PoseidonParameters;
+use ark_std::rand::Rng;
+
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+use crate::merkle_gadget::{MerklePath, MerklePathVar, MerkleTree};
+
+// A lottery: a ticket is a number of `NUMBER_BITS` bits and a secret, the buyer keeps
+// both and the operator adds the commitment H(number, secret) to the tree of sold
+// tickets (`merkle_gadget`). When sales close the operator publishes the root and a
+// random beacon, the winning number is the low bits of H(round, beacon). The holder of a
+// winning ticket proves that it is in the tree and that its number is the winning one
+// without revealing which ticket it is, and publishes the nullifier H(secret, round) so
+// it can't claim twice.
+
+pub const NUMBER_BITS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ticket {
+    pub number: u64,
+    pub secret: Fr,
+}
+
+impl Ticket {
+    pub fn buy<R: Rng>(number: u64, rng: &mut R) -> Self {
+        assert!(number < 1 << NUMBER_BITS, "the number is too large");
+        Self {
+            number,
+            secret: Fr::rand(rng),
+        }
+    }
+
+    pub fn commitment(&self, params: &PoseidonParameters<Fr>) -> Fr {
+        hash_inputs(params, &[Fr::from(self.number), self.secret])
+    }
+
+    pub fn nullifier(&self, params: &PoseidonParameters<Fr>, round: Fr) -> Fr {
+        hash_inputs(params, &[self.secret, round])
+    }
+}
+
+pub fn winning_number(params: &PoseidonParameters<Fr>, round: Fr, beacon: Fr) -> u64 {
+    let digest = hash_inputs(params, &[round, beacon]).into_repr();
+    digest.as_ref()[0] & ((1 << NUMBER_BITS) - 1)
+}
+
+// None if there are more than 2^height tickets
+pub fn sold_tickets(
+    params: &PoseidonParameters<Fr>,
+    height: usize,
+    tickets: &[Ticket],
+) -> Option<MerkleTree<Fr>> {
+    let leaves: Vec<Fr> = tickets.iter().map(|t| t.commitment(params)).collect();
+    MerkleTree::new(params.clone(), height, &leaves)
+}
+
+#[derive(Clone)]
+pub struct LotteryCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub height: usize,
+    pub root: Fr,
+    pub round: Fr,
+    pub beacon: Fr,
+    pub ticket: Option<Ticket>,
+    pub path: Option<MerklePath<Fr>>,
+}
+
+impl LotteryCircuit {
+    // a claim of the ticket at `index` of `sold`, None if there is no such leaf
+    pub fn new(
+        params: PoseidonParameters<Fr>,
+        sold: &MerkleTree<Fr>,
+        round: Fr,
+        beacon: Fr,
+        ticket: Ticket,
+        index: usize,
+    ) -> Option<Self> {
+        Some(Self {
+            params,
+            height: sold.height(),
+            root: sold.root(),
+            round,
+            beacon,
+            ticket: Some(ticket),
+            path: Some(sold.path(index)?),
+        })
+    }
+
+    pub fn blank(params: PoseidonParameters<Fr>, height: usize) -> Self {
+        Self {
+            params,
+            height,
+            root: Fr::zero(),
+            round: Fr::zero(),
+            beacon: Fr::zero(),
+            ticket: None,
+            path: None,
+        }
+    }
+
+    // the root, the round, the beacon and the nullifier
+    pub fn public_inputs(&self) -> Option<Vec<Fr>> {
+        let nullifier = self.ticket?.nullifier(&self.params, self.round);
+        Some(vec![self.root, self.round, self.beacon, nullifier])
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for LotteryCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let public = self.public_inputs();
+        let root = FpVar::new_input(ns!(cs, "root"), || Ok(self.root))?;
+        let round = FpVar::new_input(ns!(cs, "round"), || Ok(self.round))?;
+        let beacon = FpVar::new_input(ns!(cs, "beacon"), || Ok(self.beacon))?;
+        let nullifier = FpVar::new_input(ns!(cs, "nullifier"), || {
+            public
+                .map(|inputs| inputs[3])
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let number = FpVar::new_witness(ns!(cs, "number"), || {
+            self.ticket
+                .map(|t| Fr::from(t.number))
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let secret = FpVar::new_witness(ns!(cs, "secret"), || {
+            self.ticket
+                .map(|t| t.secret)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let path = MerklePathVar::new_witness(cs.clone(), self.height, self.path.as_ref())?;
+
+        // the ticket was sold
+        let commitment =
+            hash_inputs_var(cs.clone(), &self.params, &[number.clone(), secret.clone()])?;
+        path.root(cs.clone(), &self.params, &commitment)?
+            .enforce_equal(&root)?;
+
+        // and won, the low bits of the digest, from its canonical bits
+        let digest = hash_inputs_var(cs.clone(), &self.params, &[round.clone(), beacon])?;
+        let winning = Boolean::le_bits_to_fp_var(&digest.to_bits_le()?[..NUMBER_BITS])?;
+        number.enforce_equal(&winning)?;
+
+        hash_inputs_var(cs, &self.params, &[secret, round])?.enforce_equal(&nullifier)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    fn is_satisfied(circuit: LotteryCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_lottery() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (round, beacon) = (Fr::from(1u8), Fr::rand(rng));
+        let winning = winning_number(&params, round, beacon);
+        assert!(winning < 1 << NUMBER_BITS);
+
+        let tickets = [
+            Ticket::buy((winning + 1) % (1 << NUMBER_BITS), rng),
+            Ticket::buy(winning, rng),
+            Ticket::buy((winning + 2) % (1 << NUMBER_BITS), rng),
+        ];
+        let sold = sold_tickets(&params, 2, &tickets).unwrap();
+
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            LotteryCircuit::blank(params.clone(), 2),
+            rng,
+        )
+        .unwrap();
+        let circuit =
+            LotteryCircuit::new(params.clone(), &sold, round, beacon, tickets[1], 1).unwrap();
+        let inputs = circuit.public_inputs().unwrap();
+        assert_eq!(inputs[3], tickets[1].nullifier(&params, round));
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+        // the claim of another round
+        let mut next_round = inputs.clone();
+        next_round[1] += Fr::from(1u8);
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &next_round, &proof).unwrap());
+
+        // losing tickets, and a winning number on a ticket that wasn't sold
+        for index in [0, 2].iter() {
+            let circuit = LotteryCircuit::new(
+                params.clone(),
+                &sold,
+                round,
+                beacon,
+                tickets[*index],
+                *index,
+            );
+            assert!(!is_satisfied(circuit.unwrap()));
+        }
+        let forged = Ticket::buy(winning, rng);
+        let circuit = LotteryCircuit::new(params.clone(), &sold, round, beacon, forged, 3);
+        assert!(!is_satisfied(circuit.unwrap()));
+    }
+}
@@ -1,15 +1,33 @@
 use ark_ff::Field;
 use ark_relations::{
     lc,
-    r1cs::{ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError},
+    r1cs::{
+        ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError, Variable,
+    },
 };
 
+use crate::cubic_demo::CubicWitness;
+use crate::multiply_demo::MultiplyWitness;
+
 // verifier wants to prove that she knows some x such that x^3 + x + 5 == 35
 // or more general x^3 + x + 5 == (a public value)
-struct CubicDemoCircuit<F: Field> {
+// built from the witness of `cubic_demo`, the relation is the same
+pub struct CubicDemoCircuit<F: Field> {
     pub x: Option<F>,
 }
 
+impl<F: Field> CubicDemoCircuit<F> {
+    pub fn blank() -> Self {
+        Self { x: None }
+    }
+}
+
+impl<F: Field> From<CubicWitness<F>> for CubicDemoCircuit<F> {
+    fn from(witness: CubicWitness<F>) -> Self {
+        Self { x: Some(witness.x) }
+    }
+}
+
 impl<F: Field> ConstraintSynthesizer<F> for CubicDemoCircuit<F> {
     fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
         // With two intermediate variables sym_1, y,
@@ -21,6 +39,9 @@ impl<F: Field> ConstraintSynthesizer<F> for CubicDemoCircuit<F> {
         // so R1CS  w = [one, x, tmp_1, y, tmp_2, out]
 
         // allocate witness x
+        // every value is derived from the witness, and missing as a whole without it
+        let witness = self.x.map(|x| CubicWitness { x });
+
         let x_val = self.x;
         let x = cs.new_witness_variable(|| x_val.ok_or(SynthesisError::AssignmentMissing))?;
 
@@ -32,10 +53,7 @@ impl<F: Field> ConstraintSynthesizer<F> for CubicDemoCircuit<F> {
         cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + tmp_1)?;
 
         // tmp_1 * x = y, allocate y
-        let x_cubed_val = tmp_1_val.map(|mut e| {
-            e.mul_assign(&x_val.unwrap());
-            e
-        });
+        let x_cubed_val = x_val.map(|e| e.square() * e);
         let x_cubed =
             cs.new_witness_variable(|| x_cubed_val.ok_or(SynthesisError::AssignmentMissing))?;
         // enforce constraints tmp_1 * x = y
@@ -43,10 +61,9 @@ impl<F: Field> ConstraintSynthesizer<F> for CubicDemoCircuit<F> {
 
         // allocate the public output variable out
         let out = cs.new_input_variable(|| {
-            let mut tmp = x_cubed_val.unwrap();
-            tmp.add_assign(&x_val.unwrap());
-            tmp.add_assign(F::from(5u32));
-            Ok(tmp)
+            witness
+                .map(|w| w.instance().out)
+                .ok_or(SynthesisError::AssignmentMissing)
         })?;
         // enforce constraints tmp_2 + 5 = out
         cs.enforce_constraint(
@@ -59,13 +76,27 @@ impl<F: Field> ConstraintSynthesizer<F> for CubicDemoCircuit<F> {
     }
 }
 
-// circuit: prover claims that she knows two factors a and b of some public value c
+// circuit: prover claims that she knows two factors a and b of some public value c.
+// A single constraint is too small for Marlin's indexer, wrap it in `padding::Padded`.
 #[derive(Copy, Clone)]
-struct MultiplyDemoCircuit<F: Field> {
-    a: Option<F>,
-    b: Option<F>,
-    num_constraints: usize,
-    num_variables: usize,
+pub struct MultiplyDemoCircuit<F: Field> {
+    pub a: Option<F>,
+    pub b: Option<F>,
+}
+
+impl<F: Field> MultiplyDemoCircuit<F> {
+    pub fn blank() -> Self {
+        Self { a: None, b: None }
+    }
+}
+
+impl<F: Field> From<MultiplyWitness<F>> for MultiplyDemoCircuit<F> {
+    fn from(witness: MultiplyWitness<F>) -> Self {
+        Self {
+            a: Some(witness.a),
+            b: Some(witness.b),
+        }
+    }
 }
 
 impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for MultiplyDemoCircuit<ConstraintF> {
@@ -83,14 +114,129 @@ impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for MultiplyDemoCirc
             Ok(a)
         })?;
 
-        for _ in 0..(self.num_variables - 3) {
-            let _ = cs.new_witness_variable(|| self.a.ok_or(SynthesisError::AssignmentMissing))?;
+        cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + c)?;
+
+        Ok(())
+    }
+}
+
+// prover knows x_1, ..., x_k with (x_1) (x_1 + x_2) ... (x_1 + ... + x_k) == y for a
+// public y: the product of the multiply circuit over k factors that share their terms.
+// The same relation can be written two ways:
+//   Sparse  every prefix sum gets a variable and a constraint of its own, so no row of A,
+//           B or C holds more than two entries: 2k - 2 constraints, O(k) non-zeros
+//   Dense   the prefix sums stay linear combinations and go into the product constraints
+//           as they are: k - 1 constraints, but the i-th has i entries in B, O(k^2 / 2)
+// For Groth16 only the constraints count. Marlin's indexer pays for the non-zero entries
+// too, they size the third of its domains, and the denser system can come out the more
+// expensive one even with half the constraints. `benches/sparsity.rs` indexes and proves
+// both.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Formulation {
+    Sparse,
+    Dense,
+}
+
+#[derive(Clone, Debug)]
+pub struct PrefixProductCircuit<F: Field> {
+    pub k: usize,
+    pub xs: Option<Vec<F>>,
+    pub formulation: Formulation,
+}
+
+impl<F: Field> PrefixProductCircuit<F> {
+    pub fn new(xs: Vec<F>, formulation: Formulation) -> Self {
+        Self {
+            k: xs.len(),
+            xs: Some(xs),
+            formulation,
+        }
+    }
+
+    pub fn blank(k: usize, formulation: Formulation) -> Self {
+        Self {
+            k,
+            xs: None,
+            formulation,
         }
+    }
 
-        for _ in 0..(self.num_constraints - 1) {
-            cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + c)?;
+    // y
+    pub fn output(&self) -> Option<F> {
+        let mut sum = F::zero();
+        let mut product = F::one();
+        for x in self.xs.as_ref()? {
+            sum += x;
+            product *= &sum;
         }
+        Some(product)
+    }
+}
 
+impl<F: Field> ConstraintSynthesizer<F> for PrefixProductCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        // an empty product has no constraint to put y in
+        if self.k == 0 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let value = |i: usize| {
+            self.xs
+                .as_ref()
+                .map(|xs| xs[i])
+                .ok_or(SynthesisError::AssignmentMissing)
+        };
+        let xs = (0..self.k)
+            .map(|i| cs.new_witness_variable(|| value(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let y = cs.new_input_variable(|| self.output().ok_or(SynthesisError::AssignmentMissing))?;
+
+        // the values of the prefix sums and products
+        let sums: Option<Vec<F>> = self.xs.as_ref().map(|xs| {
+            xs.iter()
+                .scan(F::zero(), |sum, x| {
+                    *sum += x;
+                    Some(*sum)
+                })
+                .collect()
+        });
+        let products: Option<Vec<F>> = sums.as_ref().map(|sums| {
+            sums.iter()
+                .scan(F::one(), |product, sum| {
+                    *product *= sum;
+                    Some(*product)
+                })
+                .collect()
+        });
+        let at = |values: &Option<Vec<F>>, i: usize| {
+            values
+                .as_ref()
+                .map(|v| v[i])
+                .ok_or(SynthesisError::AssignmentMissing)
+        };
+
+        let mut sum = lc!() + xs[0];
+        let mut product = sum.clone();
+        for (i, &x) in xs.iter().enumerate().skip(1) {
+            sum = match self.formulation {
+                Formulation::Sparse => {
+                    let next = cs.new_witness_variable(|| at(&sums, i))?;
+                    cs.enforce_constraint(sum + x, lc!() + Variable::One, lc!() + next)?;
+                    lc!() + next
+                }
+                Formulation::Dense => sum + x,
+            };
+            // the last product is y itself
+            let next = if i + 1 == self.k {
+                y
+            } else {
+                cs.new_witness_variable(|| at(&products, i))?
+            };
+            cs.enforce_constraint(product, sum.clone(), lc!() + next)?;
+            product = lc!() + next;
+        }
+        if self.k == 1 {
+            cs.enforce_constraint(product, lc!() + Variable::One, lc!() + y)?;
+        }
         Ok(())
     }
 }
@@ -98,6 +244,10 @@ impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for MultiplyDemoCirc
 #[cfg(test)]
 mod test {
     use super::*;
+    #[cfg(feature = "gadgets")]
+    use crate::cubic_gadget::constraints::CubicGadgetCircuit;
+    use crate::marlin_srs::{self, MarlinSrsSize, UniversalSetup};
+    use crate::padding::Padded;
     use ark_bls12_381::{Bls12_381, Fr as BlsFr};
     use ark_marlin::Marlin;
     use ark_poly::univariate::DensePolynomial;
@@ -107,21 +257,21 @@ mod test {
 
     #[test]
     fn test_marlin_multi_circuit_cubic_multiply() {
-        type MultiPC = MarlinKZG10<Bls12_381, DensePolynomial<BlsFr>>;
-        type MarlinInst = Marlin<BlsFr, MultiPC, Blake2s>;
+        type MarlinInst = marlin_srs::MarlinInst<Bls12_381>;
 
-        let num_constraints: usize = 24;
-        let num_variables: usize = 24;
         let rng = &mut ark_std::test_rng();
 
-        let universal_srs =
-            MarlinInst::universal_setup(num_constraints, num_variables, num_variables, rng)
-                .unwrap();
+        // the multiply circuit is padded to the shape of the cubic one, so a single SRS
+        // sized for the cubic circuit covers both
+        let size = MarlinSrsSize::of(CubicDemoCircuit::<BlsFr> { x: None }).unwrap();
+        let pad = |circuit| Padded::new(circuit, size.num_constraints, size.num_variables);
+
+        let setup = UniversalSetup::<Bls12_381>::new(size, rng).unwrap();
 
         let circuit_cubic = CubicDemoCircuit { x: None };
 
         // generate the setup parameters
-        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circuit_cubic).unwrap();
+        let (index_pk, index_vk) = setup.index_circuit(circuit_cubic).unwrap();
 
         // calculate the proof by passing witness variable value
         let x = BlsFr::from(3);
@@ -132,25 +282,18 @@ mod test {
         assert!(MarlinInst::verify(&index_vk, &[BlsFr::from(35)], &proof, rng).unwrap());
 
         // multiply circuit
-        let circuit_mul = MultiplyDemoCircuit {
-            a: None,
-            b: None,
-            num_variables,
-            num_constraints,
-        };
+        let circuit_mul = pad(MultiplyDemoCircuit { a: None, b: None });
 
         // generate the setup parameters
-        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circuit_mul).unwrap();
+        let (index_pk, index_vk) = setup.index_circuit(circuit_mul).unwrap();
 
         // calculate the proof by passing witness variable value
         let a = BlsFr::rand(rng);
         let b = BlsFr::rand(rng);
-        let circuit_mul_instance = MultiplyDemoCircuit {
+        let circuit_mul_instance = pad(MultiplyDemoCircuit {
             a: Some(a),
             b: Some(b),
-            num_variables,
-            num_constraints,
-        };
+        });
         let proof = MarlinInst::prove(&index_pk, circuit_mul_instance, rng).unwrap();
 
         // validate the proof
@@ -166,18 +309,19 @@ mod test {
 
         let rng = &mut ark_std::test_rng();
 
-        let num_constraints: usize = 3;
-        let num_variables: usize = 3;
-        let universal_srs =
-            MarlinInst::universal_setup(num_constraints, num_variables, num_variables, rng)
-                .unwrap();
-
-        let circuit = MultiplyDemoCircuit {
-            a: None,
-            b: None,
-            num_variables,
-            num_constraints,
-        };
+        // the indexer needs two constraints, everything else is the circuit's own size
+        let pad = |circuit| Padded::new(circuit, 2, 0);
+        let size =
+            MarlinSrsSize::of(pad(MultiplyDemoCircuit::<BlsFr> { a: None, b: None })).unwrap();
+        let universal_srs = MarlinInst::universal_setup(
+            size.num_constraints,
+            size.num_variables,
+            size.num_non_zero,
+            rng,
+        )
+        .unwrap();
+
+        let circuit = pad(MultiplyDemoCircuit { a: None, b: None });
         // generate the setup parameters
         let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circuit).unwrap();
 
@@ -185,12 +329,10 @@ mod test {
         let b = BlsFr::rand(rng);
 
         // calculate the proof by passing witness variable value
-        let circuit_instance = MultiplyDemoCircuit {
+        let circuit_instance = pad(MultiplyDemoCircuit {
             a: Some(a),
             b: Some(b),
-            num_variables,
-            num_constraints,
-        };
+        });
 
         let proof1 = MarlinInst::prove(&index_pk, circuit_instance, rng).unwrap();
 
@@ -201,4 +343,85 @@ mod test {
 
         assert!(!MarlinInst::verify(&index_vk, &[BlsFr::from(5u32)], &proof1, rng).unwrap());
     }
+
+    #[test]
+    #[cfg(feature = "gadgets")]
+    fn test_marlin_cubic_gadget() {
+        type MultiPC = MarlinKZG10<Bls12_381, DensePolynomial<BlsFr>>;
+        type MarlinInst = Marlin<BlsFr, MultiPC, Blake2s>;
+
+        let rng = &mut ark_std::test_rng();
+
+        // same relation as the cubic circuit, built from `SolutionDemoGadget`
+        let setup_circuit = CubicGadgetCircuit::<BlsFr> { x: None, y: None };
+        let size = MarlinSrsSize::of(setup_circuit).unwrap();
+        let universal_srs = MarlinInst::universal_setup(
+            size.num_constraints,
+            size.num_variables,
+            size.num_non_zero,
+            rng,
+        )
+        .unwrap();
+        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, setup_circuit).unwrap();
+
+        let (x, y) = (BlsFr::from(3u8), BlsFr::from(35u8));
+        let circuit = CubicGadgetCircuit {
+            x: Some(x),
+            y: Some(y),
+        };
+        let proof = MarlinInst::prove(&index_pk, circuit, rng).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[y], &proof, rng).unwrap());
+        assert!(!MarlinInst::verify(&index_vk, &[BlsFr::from(36u8)], &proof, rng).unwrap());
+    }
+
+    #[test]
+    fn test_prefix_product_formulations() {
+        type MarlinInst = marlin_srs::MarlinInst<Bls12_381>;
+
+        let rng = &mut ark_std::test_rng();
+        let k = 8;
+        let xs: Vec<BlsFr> = (0..k).map(|_| BlsFr::rand(rng)).collect();
+        let sparse = PrefixProductCircuit::new(xs.clone(), Formulation::Sparse);
+        let dense = PrefixProductCircuit::new(xs, Formulation::Dense);
+        let y = sparse.output().unwrap();
+        assert_eq!(dense.output(), Some(y));
+
+        let sparse_size = MarlinSrsSize::of(sparse.clone()).unwrap();
+        let dense_size = MarlinSrsSize::of(dense.clone()).unwrap();
+        assert_eq!(sparse_size.num_constraints, 2 * k - 2);
+        assert_eq!(dense_size.num_constraints, k - 1);
+        // the sums of two to k terms, all in B
+        assert_eq!(dense_size.num_non_zero, (2..=k).sum::<usize>());
+        // in A, a sum of two terms for each new sum and a factor for each product
+        assert_eq!(sparse_size.num_non_zero, 3 * (k - 1));
+
+        // one SRS for both
+        let setup = UniversalSetup::<Bls12_381>::new(sparse_size.max(dense_size), rng).unwrap();
+        for circuit in [sparse, dense].iter() {
+            let blank = PrefixProductCircuit::<BlsFr>::blank(k, circuit.formulation);
+            let (index_pk, index_vk) = setup.index_circuit(blank).unwrap();
+            let proof = MarlinInst::prove(&index_pk, circuit.clone(), rng).unwrap();
+            assert!(MarlinInst::verify(&index_vk, &[y], &proof, rng).unwrap());
+            assert!(!MarlinInst::verify(&index_vk, &[y + y], &proof, rng).unwrap());
+        }
+
+        // a single factor, padded for the indexer
+        let x = BlsFr::from(7u8);
+        for &formulation in [Formulation::Sparse, Formulation::Dense].iter() {
+            let cs = ConstraintSystem::<BlsFr>::new_ref();
+            Padded::new(PrefixProductCircuit::new(vec![x], formulation), 2, 0)
+                .generate_constraints(cs.clone())
+                .unwrap();
+            assert!(cs.is_satisfied().unwrap());
+            assert_eq!(cs.num_constraints(), 2);
+        }
+
+        // no factors at all
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        assert_eq!(
+            PrefixProductCircuit::<BlsFr>::new(vec![], Formulation::Sparse)
+                .generate_constraints(cs),
+            Err(SynthesisError::Unsatisfiable)
+        );
+    }
 }
@@ -0,0 +1,363 @@
+use ark_ec::PairingEngine;
+use ark_ff::PrimeField;
+use ark_marlin::{IndexProverKey, IndexVerifierKey, Marlin, Proof, UniversalSRS};
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::marlin_pc::MarlinKZG10;
+use ark_relations::r1cs::{ConstraintSynthesizer, SynthesisError};
+use ark_std::io::{Read, Write};
+use ark_std::rand::RngCore;
+use blake2::Blake2s;
+use std::fmt;
+use std::path::Path;
+
+use crate::persist::{self, ArtifactKind, PersistError, SerializationMode, Validation};
+use crate::sizes::{artifact_sizes, ArtifactSizes, SerializedSize};
+use crate::visualize::constraint_matrices;
+use crate::Error;
+
+// the three bounds taken by `Marlin::universal_setup`, measured on a circuit instead of
+// guessed. The circuit is synthesized in setup mode, so no witness is needed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MarlinSrsSize {
+    pub num_constraints: usize,
+    // the indexer rounds the public inputs (constant one included) up to a power of two
+    pub num_variables: usize,
+    // non-zero entries of the densest of A, B and C
+    pub num_non_zero: usize,
+}
+
+impl MarlinSrsSize {
+    pub fn of<F, C>(circuit: C) -> Result<Self, SynthesisError>
+    where
+        F: PrimeField,
+        C: ConstraintSynthesizer<F>,
+    {
+        let m = constraint_matrices(circuit)?;
+        Ok(Self {
+            num_constraints: m.num_constraints,
+            num_variables: m.num_instance_variables.next_power_of_two() + m.num_witness_variables,
+            num_non_zero: m.a_num_non_zero.max(m.b_num_non_zero).max(m.c_num_non_zero),
+        })
+    }
+
+    // an SRS large enough for both, to index several circuits against the same SRS
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            num_constraints: self.num_constraints.max(other.num_constraints),
+            num_variables: self.num_variables.max(other.num_variables),
+            num_non_zero: self.num_non_zero.max(other.num_non_zero),
+        }
+    }
+}
+
+pub type MarlinPC<E> = MarlinKZG10<E, DensePolynomial<<E as PairingEngine>::Fr>>;
+pub type MarlinInst<E> = Marlin<<E as PairingEngine>::Fr, MarlinPC<E>, Blake2s>;
+pub type MarlinProvingKey<E> = IndexProverKey<<E as PairingEngine>::Fr, MarlinPC<E>>;
+pub type MarlinVerifyingKey<E> = IndexVerifierKey<<E as PairingEngine>::Fr, MarlinPC<E>>;
+pub type MarlinProof<E> = Proof<<E as PairingEngine>::Fr, MarlinPC<E>>;
+
+// one universal SRS, generated once (or loaded) and reused to index any number of
+// circuits, as long as each fits in the size it was generated for
+pub struct UniversalSetup<E: PairingEngine> {
+    srs: UniversalSRS<E::Fr, MarlinPC<E>>,
+}
+
+impl<E: PairingEngine> UniversalSetup<E> {
+    pub fn new<R: RngCore>(size: MarlinSrsSize, rng: &mut R) -> Result<Self, Error> {
+        let srs = MarlinInst::<E>::universal_setup(
+            size.num_constraints,
+            size.num_variables,
+            size.num_non_zero,
+            rng,
+        )
+        .map_err(|e| format!("{:?}", e))?;
+        Ok(Self { srs })
+    }
+
+    pub fn from_srs(srs: UniversalSRS<E::Fr, MarlinPC<E>>) -> Self {
+        Self { srs }
+    }
+
+    pub fn srs(&self) -> &UniversalSRS<E::Fr, MarlinPC<E>> {
+        &self.srs
+    }
+
+    pub fn index_circuit<C: ConstraintSynthesizer<E::Fr>>(
+        &self,
+        circuit: C,
+    ) -> Result<(MarlinProvingKey<E>, MarlinVerifyingKey<E>), Error> {
+        Ok(MarlinInst::<E>::index(&self.srs, circuit).map_err(|e| format!("{:?}", e))?)
+    }
+
+    // the SRS in a `persist` envelope, index keys and proofs use `persist` directly with
+    // the ProvingKey, VerifyingKey and Proof kinds
+    pub fn write<W: Write>(&self, mode: SerializationMode, writer: W) -> Result<(), PersistError> {
+        persist::write_artifact(&self.srs, ArtifactKind::UniversalSrs, mode, writer)
+    }
+
+    pub fn read<R: Read>(reader: R, validation: Validation) -> Result<Self, PersistError> {
+        persist::read_artifact(reader, ArtifactKind::UniversalSrs, validation).map(Self::from_srs)
+    }
+
+    pub fn save<P: AsRef<Path>>(
+        &self,
+        path: P,
+        mode: SerializationMode,
+    ) -> Result<(), PersistError> {
+        persist::save(path, &self.srs, ArtifactKind::UniversalSrs, mode)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P, validation: Validation) -> Result<Self, PersistError> {
+        persist::load(path, ArtifactKind::UniversalSrs, validation).map(Self::from_srs)
+    }
+}
+
+// Marlin artifacts next to each other: the SRS grows with the largest circuit it was
+// sized for, the prover key carries the verifier key and the committed index, and a
+// proof is close to a kilobyte where a Groth16 proof is 192 bytes on BLS12-381
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarlinArtifactSizes {
+    pub srs: SerializedSize,
+    pub artifacts: ArtifactSizes,
+}
+
+pub fn marlin_artifact_sizes<E: PairingEngine>(
+    setup: &UniversalSetup<E>,
+    pk: &MarlinProvingKey<E>,
+    vk: &MarlinVerifyingKey<E>,
+    proof: &MarlinProof<E>,
+) -> MarlinArtifactSizes {
+    MarlinArtifactSizes {
+        srs: SerializedSize::of(setup.srs()),
+        artifacts: artifact_sizes(pk, vk, proof),
+    }
+}
+
+impl fmt::Display for MarlinArtifactSizes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.artifacts)?;
+        writeln!(
+            f,
+            "{:<14} {:>16} {:>18}",
+            "universal srs", self.srs.compressed, self.srs.uncompressed
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::CubicDemoCircuit;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use crate::padding::Padded;
+    use crate::synthetic::SyntheticCircuit;
+    use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+
+    type MarlinInst = super::MarlinInst<Bls12_381>;
+
+    #[test]
+    fn test_cubic_size() {
+        let size = MarlinSrsSize::of(CubicDemoCircuit::<BlsFr> { x: None }).unwrap();
+        // one and out are padded to two, x, tmp_1 and y are witnesses; A has the most
+        // entries: x, tmp_1 and y + x + 5
+        assert_eq!(
+            size,
+            MarlinSrsSize {
+                num_constraints: 3,
+                num_variables: 5,
+                num_non_zero: 5,
+            }
+        );
+    }
+
+    #[test]
+    fn test_index_with_measured_srs() {
+        let rng = &mut ark_std::test_rng();
+        let small = || CubicDemoCircuit::<BlsFr> { x: None };
+        let large = SyntheticCircuit::new(64, 32, 2).with_public_inputs(3);
+        let size = MarlinSrsSize::of(small())
+            .unwrap()
+            .max(MarlinSrsSize::of::<BlsFr, _>(large).unwrap());
+        assert_eq!(size.num_constraints, 64);
+        assert_eq!(size.num_variables, 4 + 32);
+
+        let srs = MarlinInst::universal_setup(
+            size.num_constraints,
+            size.num_variables,
+            size.num_non_zero,
+            rng,
+        )
+        .unwrap();
+        assert!(MarlinInst::index(&srs, small()).is_ok());
+        let (pk, vk) = MarlinInst::index(&srs, large).unwrap();
+        let proof = MarlinInst::prove(&pk, large, rng).unwrap();
+        assert!(MarlinInst::verify(&vk, &large.public_inputs(), &proof, rng).unwrap());
+
+        // an SRS measured on the small circuit only is too small for the large one
+        let size = MarlinSrsSize::of(small()).unwrap();
+        let srs = MarlinInst::universal_setup(
+            size.num_constraints,
+            size.num_variables,
+            size.num_non_zero,
+            rng,
+        )
+        .unwrap();
+        assert!(MarlinInst::index(&srs, large).is_err());
+    }
+
+    #[test]
+    fn test_universal_setup_reuse() {
+        let rng = &mut ark_std::test_rng();
+        // multiply is padded to the two constraints the indexer needs, the synthetic
+        // circuit stands in for any user circuit
+        let multiply = |a, b| Padded::new(MultiplyDemoCircuit::<BlsFr> { a, b }, 2, 0);
+        let user = SyntheticCircuit::new(16, 8, 2).with_public_inputs(2);
+        let size = MarlinSrsSize::of(CubicDemoCircuit::<BlsFr> { x: None })
+            .unwrap()
+            .max(MarlinSrsSize::of(multiply(None, None)).unwrap())
+            .max(MarlinSrsSize::of::<BlsFr, _>(user).unwrap());
+        let setup = UniversalSetup::<Bls12_381>::new(size, rng).unwrap();
+
+        let (pk, cubic_vk) = setup
+            .index_circuit(CubicDemoCircuit::<BlsFr> { x: None })
+            .unwrap();
+        let x = BlsFr::from(3u8);
+        let proof = MarlinInst::prove(&pk, CubicDemoCircuit { x: Some(x) }, rng).unwrap();
+        assert!(MarlinInst::verify(&cubic_vk, &[BlsFr::from(35u8)], &proof, rng).unwrap());
+
+        let (pk, vk) = setup.index_circuit(multiply(None, None)).unwrap();
+        let (a, b) = (BlsFr::from(3u8), BlsFr::from(11u8));
+        let proof = MarlinInst::prove(&pk, multiply(Some(a), Some(b)), rng).unwrap();
+        assert!(MarlinInst::verify(&vk, &[a * b], &proof, rng).unwrap());
+        // keys from the same SRS are still bound to their circuit
+        assert!(!MarlinInst::verify(&cubic_vk, &[a * b], &proof, rng).unwrap());
+
+        let (pk, vk) = setup.index_circuit(user).unwrap();
+        let proof = MarlinInst::prove(&pk, user, rng).unwrap();
+        assert!(MarlinInst::verify(&vk, &user.public_inputs(), &proof, rng).unwrap());
+    }
+
+    fn to_bytes<T: ark_serialize::CanonicalSerialize>(artifact: &T) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        artifact.serialize_uncompressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    // the SRS, index keys and a proof of the cubic circuit
+    fn cubic_artifacts() -> (
+        UniversalSetup<Bls12_381>,
+        MarlinProvingKey<Bls12_381>,
+        MarlinVerifyingKey<Bls12_381>,
+        MarlinProof<Bls12_381>,
+    ) {
+        let rng = &mut ark_std::test_rng();
+        let size = MarlinSrsSize::of(CubicDemoCircuit::<BlsFr> { x: None }).unwrap();
+        let setup = UniversalSetup::new(size, rng).unwrap();
+        let (pk, vk) = setup
+            .index_circuit(CubicDemoCircuit::<BlsFr> { x: None })
+            .unwrap();
+        let x = Some(BlsFr::from(3u8));
+        let proof = MarlinInst::prove(&pk, CubicDemoCircuit { x }, rng).unwrap();
+        (setup, pk, vk, proof)
+    }
+
+    #[test]
+    fn test_artifacts_roundtrip() {
+        let rng = &mut ark_std::test_rng();
+        let (setup, pk, vk, proof) = cubic_artifacts();
+
+        for &(mode, validation) in [
+            (SerializationMode::Compressed, Validation::Checked),
+            (SerializationMode::Uncompressed, Validation::Checked),
+            (SerializationMode::Uncompressed, Validation::Unchecked),
+        ]
+        .iter()
+        {
+            let mut bytes = Vec::new();
+            setup.write(mode, &mut bytes).unwrap();
+            let setup2 = UniversalSetup::<Bls12_381>::read(&bytes[..], validation).unwrap();
+            assert_eq!(to_bytes(setup2.srs()), to_bytes(setup.srs()));
+
+            let mut bytes = Vec::new();
+            persist::write_artifact(&pk, ArtifactKind::ProvingKey, mode, &mut bytes).unwrap();
+            let pk2: MarlinProvingKey<Bls12_381> =
+                persist::read_artifact(&bytes[..], ArtifactKind::ProvingKey, validation).unwrap();
+            assert_eq!(to_bytes(&pk2), to_bytes(&pk));
+
+            let mut bytes = Vec::new();
+            persist::write_artifact(&vk, ArtifactKind::VerifyingKey, mode, &mut bytes).unwrap();
+            let vk2: MarlinVerifyingKey<Bls12_381> =
+                persist::read_artifact(&bytes[..], ArtifactKind::VerifyingKey, validation).unwrap();
+            assert_eq!(to_bytes(&vk2), to_bytes(&vk));
+
+            let mut bytes = Vec::new();
+            persist::write_artifact(&proof, ArtifactKind::Proof, mode, &mut bytes).unwrap();
+            let proof2: MarlinProof<Bls12_381> =
+                persist::read_artifact(&bytes[..], ArtifactKind::Proof, validation).unwrap();
+            assert_eq!(to_bytes(&proof2), to_bytes(&proof));
+
+            // the loaded artifacts still work, a key indexed from the loaded SRS too
+            assert!(MarlinInst::verify(&vk2, &[BlsFr::from(35u8)], &proof2, rng).unwrap());
+            let x = Some(BlsFr::from(4u8));
+            let proof3 = MarlinInst::prove(&pk2, CubicDemoCircuit { x }, rng).unwrap();
+            assert!(MarlinInst::verify(&vk, &[BlsFr::from(73u8)], &proof3, rng).unwrap());
+            let (_, vk3) = setup2
+                .index_circuit(CubicDemoCircuit::<BlsFr> { x: None })
+                .unwrap();
+            assert_eq!(to_bytes(&vk3), to_bytes(&vk));
+        }
+    }
+
+    #[test]
+    fn test_srs_envelope() {
+        let (setup, _, _, _) = cubic_artifacts();
+        let mut bytes = Vec::new();
+        setup
+            .write(SerializationMode::Compressed, &mut bytes)
+            .unwrap();
+
+        // an SRS is not a verifying key, nor a Groth16 one
+        let result: Result<MarlinVerifyingKey<Bls12_381>, _> =
+            persist::read_artifact(&bytes[..], ArtifactKind::VerifyingKey, Validation::Checked);
+        assert!(matches!(result, Err(PersistError::WrongKind { .. })));
+        let result: Result<ark_groth16::VerifyingKey<Bls12_381>, _> =
+            persist::read_artifact(&bytes[..], ArtifactKind::VerifyingKey, Validation::Checked);
+        assert!(matches!(result, Err(PersistError::WrongKind { .. })));
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(UniversalSetup::<Bls12_381>::read(truncated, Validation::Checked).is_err());
+
+        let path = std::env::temp_dir().join(format!(
+            "gadgets_marlin_srs_test_{}.bin",
+            std::process::id()
+        ));
+        setup.save(&path, SerializationMode::Uncompressed).unwrap();
+        let loaded = UniversalSetup::<Bls12_381>::load(&path, Validation::Unchecked).unwrap();
+        assert_eq!(to_bytes(loaded.srs()), to_bytes(setup.srs()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_artifact_sizes() {
+        let (setup, pk, vk, proof) = cubic_artifacts();
+        let sizes = marlin_artifact_sizes(&setup, &pk, &vk, &proof);
+        assert_eq!(sizes.srs, SerializedSize::of(setup.srs()));
+        assert_eq!(sizes.artifacts.proof, SerializedSize::of(&proof));
+        // some 950 bytes for the cubic circuit against the 192 of a Groth16 proof, the
+        // prover key contains the verifier key
+        assert!(sizes.artifacts.proof.compressed > 4 * 192);
+        assert!(sizes.artifacts.proving_key.compressed > sizes.artifacts.verifying_key.compressed);
+        for size in [
+            sizes.srs,
+            sizes.artifacts.proving_key,
+            sizes.artifacts.proof,
+        ]
+        .iter()
+        {
+            assert!(size.compressed < size.uncompressed);
+        }
+
+        let table = sizes.to_string();
+        assert!(table.contains("universal srs"));
+        assert!(table.contains(&sizes.srs.uncompressed.to_string()));
+    }
+}
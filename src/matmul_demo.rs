@@ -0,0 +1,203 @@
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystemRef, LinearCombination, SynthesisError, Variable,
+};
+use ark_std::{cfg_chunks, cfg_into_iter};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// knows n x n matrices A and B with A B == C for a public C. Every product a_ik b_kj is
+// a constraint and a witness, every entry of C one more constraint on the sum of n of
+// them: n^3 + n^2 constraints, 2^18 and a bit for n = 64. Matrices are row major.
+//
+// The n^3 products are independent of each other, so `precompute` works them out on all
+// cores (`parallel` feature) before synthesis, which then only copies them into
+// the constraint system; without it synthesis computes each in its closure. Both give
+// the same constraint system. `benches/witness.rs` compares the two.
+
+#[derive(Clone)]
+pub struct MatMulCircuit<F: PrimeField> {
+    pub n: usize,
+    pub a: Option<Vec<F>>,
+    pub b: Option<Vec<F>>,
+    // a_ik b_kj in (i, j, k) order, from `precompute`
+    products: Option<Vec<F>>,
+}
+
+// A B, native
+pub fn multiply<F: PrimeField>(n: usize, a: &[F], b: &[F]) -> Vec<F> {
+    cfg_into_iter!(0..n * n)
+        .map(|ij| (0..n).map(|k| a[ij / n * n + k] * b[k * n + ij % n]).sum())
+        .collect()
+}
+
+impl<F: PrimeField> MatMulCircuit<F> {
+    pub fn new(n: usize, a: Vec<F>, b: Vec<F>) -> Self {
+        assert!(a.len() == n * n && b.len() == n * n, "not n x n matrices");
+        Self {
+            n,
+            a: Some(a),
+            b: Some(b),
+            products: None,
+        }
+    }
+
+    // no assignment, for setup and for counting constraints
+    pub fn blank(n: usize) -> Self {
+        Self {
+            n,
+            a: None,
+            b: None,
+            products: None,
+        }
+    }
+
+    // the products, all before synthesis and in parallel where possible
+    pub fn precompute(mut self) -> Self {
+        if let (Some(a), Some(b)) = (&self.a, &self.b) {
+            let n = self.n;
+            let products = cfg_into_iter!(0..n * n * n)
+                .map(|ijk| {
+                    let (i, j, k) = (ijk / (n * n), ijk / n % n, ijk % n);
+                    a[i * n + k] * b[k * n + j]
+                })
+                .collect();
+            self.products = Some(products);
+        }
+        self
+    }
+
+    // C, row major
+    pub fn public_inputs(&self) -> Option<Vec<F>> {
+        match &self.products {
+            Some(products) => Some(
+                cfg_chunks!(products, self.n)
+                    .map(|row| row.iter().sum())
+                    .collect(),
+            ),
+            None => Some(multiply(self.n, self.a.as_ref()?, self.b.as_ref()?)),
+        }
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for MatMulCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let n = self.n;
+        let c = self.public_inputs();
+        let c = (0..n * n)
+            .map(|ij| {
+                cs.new_input_variable(|| {
+                    c.as_ref()
+                        .map(|c| c[ij])
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let witness = |values: &Option<Vec<F>>| {
+            (0..n * n)
+                .map(|i| {
+                    cs.new_witness_variable(|| {
+                        values
+                            .as_ref()
+                            .map(|v| v[i])
+                            .ok_or(SynthesisError::AssignmentMissing)
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let a = witness(&self.a)?;
+        let b = witness(&self.b)?;
+
+        for i in 0..n {
+            for j in 0..n {
+                let mut sum = LinearCombination::zero();
+                for k in 0..n {
+                    let product = cs.new_witness_variable(|| match &self.products {
+                        Some(products) => Ok(products[(i * n + j) * n + k]),
+                        None => match (&self.a, &self.b) {
+                            (Some(a), Some(b)) => Ok(a[i * n + k] * b[k * n + j]),
+                            _ => Err(SynthesisError::AssignmentMissing),
+                        },
+                    })?;
+                    cs.enforce_constraint(
+                        a[i * n + k].into(),
+                        b[k * n + j].into(),
+                        product.into(),
+                    )?;
+                    sum += (F::one(), product);
+                }
+                cs.enforce_constraint(sum, Variable::One.into(), c[i * n + j].into())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+    use ark_std::{One, UniformRand};
+
+    fn random(n: usize) -> (Vec<Fr>, Vec<Fr>) {
+        let rng = &mut ark_std::test_rng();
+        let mut matrix = || (0..n * n).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+        (matrix(), matrix())
+    }
+
+    #[test]
+    fn test_multiply() {
+        let one = Fr::one();
+        let two = one + one;
+        // [[1, 2], [0, 1]]^2 == [[1, 4], [0, 1]]
+        let a = vec![one, two, Fr::from(0u8), one];
+        assert_eq!(
+            multiply(2, &a, &a),
+            vec![one, two + two, Fr::from(0u8), one]
+        );
+    }
+
+    #[test]
+    fn test_precomputed_synthesis_is_the_same() {
+        let n = 5;
+        let (a, b) = random(n);
+        let inline = MatMulCircuit::new(n, a, b);
+        let precomputed = inline.clone().precompute();
+        assert_eq!(precomputed.public_inputs(), inline.public_inputs());
+
+        let synthesize = |circuit: MatMulCircuit<Fr>| {
+            let cs = ConstraintSystem::new_ref();
+            circuit.generate_constraints(cs.clone()).unwrap();
+            assert!(cs.is_satisfied().unwrap());
+            assert_eq!(cs.num_constraints(), n * n * n + n * n);
+            cs.finalize();
+            let matrices = cs.to_matrices().unwrap();
+            let cs = cs.into_inner().unwrap();
+            (
+                (matrices.a, matrices.b, matrices.c),
+                cs.instance_assignment,
+                cs.witness_assignment,
+            )
+        };
+        assert!(synthesize(inline) == synthesize(precomputed));
+    }
+
+    #[test]
+    fn test_groth16() {
+        let rng = &mut ark_std::test_rng();
+        let n = 3;
+        let (a, b) = random(n);
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(MatMulCircuit::blank(n), rng).unwrap();
+        let circuit = MatMulCircuit::new(n, a, b).precompute();
+        let mut inputs = circuit.public_inputs().unwrap();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        inputs[4] += Fr::one();
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+    }
+}
@@ -0,0 +1,187 @@
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{
+    ConstraintLayer, ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, SynthesisError,
+    SynthesisMode, TracingMode,
+};
+use std::fmt::Write;
+use tracing_subscriber::layer::SubscriberExt;
+
+use crate::visualize::{format_coefficient, variable_name};
+
+// human readable A, B, C matrices of a circuit. Rows are constraints, labeled with the
+// namespace (`ns!`) they were enforced in, columns are the variables in the order
+// arkworks lays them out: the constant one, the public inputs, then the witnesses.
+
+pub struct NamedMatrices<F: PrimeField> {
+    pub matrices: ConstraintMatrices<F>,
+    // one label per constraint, `c<i>` for constraints outside of any namespace
+    pub constraint_names: Vec<String>,
+}
+
+// synthesizes the circuit in setup mode with a `ConstraintLayer` installed, so the
+// namespace of every constraint is recorded
+pub fn named_constraint_matrices<F, C>(circuit: C) -> Result<NamedMatrices<F>, SynthesisError>
+where
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+{
+    let subscriber = tracing_subscriber::Registry::default()
+        .with(ConstraintLayer::new(TracingMode::OnlyConstraints));
+    tracing::subscriber::with_default(subscriber, || {
+        let cs = ConstraintSystem::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        circuit.generate_constraints(cs.clone())?;
+        cs.finalize();
+
+        let matrices = cs.to_matrices().ok_or(SynthesisError::MissingCS)?;
+        let constraint_names = cs
+            .constraint_names()
+            .unwrap_or_default()
+            .into_iter()
+            .chain(std::iter::repeat(String::new()))
+            .take(matrices.num_constraints)
+            .enumerate()
+            .map(|(i, name)| {
+                if name.is_empty() {
+                    format!("c{}", i)
+                } else {
+                    name
+                }
+            })
+            .collect();
+        Ok(NamedMatrices {
+            matrices,
+            constraint_names,
+        })
+    })
+}
+
+// default column labels: one, x1.., w0..
+pub fn column_labels<F: PrimeField>(matrices: &ConstraintMatrices<F>) -> Vec<String> {
+    let num_instance = matrices.num_instance_variables;
+    (0..num_instance + matrices.num_witness_variables)
+        .map(|i| variable_name(num_instance, i))
+        .collect()
+}
+
+pub fn format_matrix<F: PrimeField>(
+    name: &str,
+    matrix: &[Vec<(F, usize)>],
+    row_labels: &[String],
+    column_labels: &[String],
+) -> String {
+    let mut dense = vec![vec![".".to_string(); column_labels.len()]; matrix.len()];
+    for (row, entries) in matrix.iter().enumerate() {
+        for &(coeff, column) in entries.iter() {
+            dense[row][column] = format_coefficient(coeff);
+        }
+    }
+
+    let label_width = row_labels.iter().map(|l| l.len()).max().unwrap_or(0);
+    let width = column_labels
+        .iter()
+        .chain(dense.iter().flatten())
+        .map(|l| l.len())
+        .max()
+        .unwrap_or(1);
+
+    let mut out = String::new();
+    write!(out, "{:<w$}", name, w = label_width).unwrap();
+    for label in column_labels.iter() {
+        write!(out, " {:>w$}", label, w = width).unwrap();
+    }
+    writeln!(out).unwrap();
+    for (label, row) in row_labels.iter().zip(dense.iter()) {
+        write!(out, "{:<w$}", label, w = label_width).unwrap();
+        for value in row.iter() {
+            write!(out, " {:>w$}", value, w = width).unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+    out
+}
+
+// all three matrices, `column_labels` overrides the generic variable names
+pub fn format_matrices<F: PrimeField>(
+    named: &NamedMatrices<F>,
+    column_labels: Option<&[String]>,
+) -> String {
+    let default_labels = self::column_labels(&named.matrices);
+    let columns = column_labels.unwrap_or(&default_labels);
+    let rows = &named.constraint_names;
+    [
+        format_matrix("A", &named.matrices.a, rows, columns),
+        format_matrix("B", &named.matrices.b, rows, columns),
+        format_matrix("C", &named.matrices.c, rows, columns),
+    ]
+    .join("\n")
+}
+
+pub fn circuit_matrices_table<F, C>(circuit: C) -> Result<String, SynthesisError>
+where
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+{
+    Ok(format_matrices(&named_constraint_matrices(circuit)?, None))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::CubicDemoCircuit;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use ark_bls12_381::Fr as BlsFr;
+
+    #[test]
+    fn test_cubic_matrices() {
+        let named = named_constraint_matrices(CubicDemoCircuit::<BlsFr> { x: None }).unwrap();
+        let m = &named.matrices;
+
+        // the comment in cubic_demo lists w = [one, x, tmp_1, y, tmp_2, out]. arkworks puts
+        // the public inputs first, [one, out | x, tmp_1, y], and never allocates tmp_2:
+        // y + x + 5 stays a linear combination inside the last constraint
+        assert_eq!(m.num_instance_variables, 2);
+        assert_eq!(m.num_witness_variables, 3);
+        assert_eq!(m.num_constraints, 3);
+        let (one, out, x, tmp_1, y) = (0, 1, 2, 3, 4);
+        let five = BlsFr::from(5u8);
+        let unit = BlsFr::from(1u8);
+
+        // x * x = tmp_1
+        assert_eq!(m.a[0], vec![(unit, x)]);
+        assert_eq!(m.b[0], vec![(unit, x)]);
+        assert_eq!(m.c[0], vec![(unit, tmp_1)]);
+        // tmp_1 * x = y
+        assert_eq!(m.a[1], vec![(unit, tmp_1)]);
+        assert_eq!(m.b[1], vec![(unit, x)]);
+        assert_eq!(m.c[1], vec![(unit, y)]);
+        // (y + x + 5) * 1 = out
+        assert_eq!(m.a[2], vec![(five, one), (unit, x), (unit, y)]);
+        assert_eq!(m.b[2], vec![(unit, one)]);
+        assert_eq!(m.c[2], vec![(unit, out)]);
+
+        assert!(named.constraint_names[0].ends_with("x * x = tmp_1"));
+        assert!(named.constraint_names[1].ends_with("tmp_1 * x = y"));
+        assert!(named.constraint_names[2].ends_with("(y + x + 5) * 1 = out"));
+
+        let labels: Vec<String> = ["one", "out", "x", "tmp_1", "y"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let table = format_matrices(&named, Some(&labels));
+        assert!(table.contains("tmp_1 * x = y"));
+        assert!(table
+            .lines()
+            .any(|l| l.starts_with("A") && l.contains("tmp_1")));
+    }
+
+    #[test]
+    fn test_unnamed_constraints() {
+        let table =
+            circuit_matrices_table(MultiplyDemoCircuit::<BlsFr> { a: None, b: None }).unwrap();
+        // a * b = c without namespaces: columns one, c (x1), a (w0), b (w1)
+        assert!(table.contains("c0"));
+        assert!(table.lines().next().unwrap().contains("x1"));
+        assert_eq!(table.lines().filter(|l| l.starts_with("c0")).count(), 3);
+    }
+}
@@ -0,0 +1,530 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::{poseidon::PoseidonParameters, Absorb};
+
+use std::collections::HashMap;
+
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+
+// A binary Merkle tree of field elements with the Poseidon hash of `hashed_inputs_demo`,
+// an inner node is the hash of its two children. The tree has a fixed height, leaves past
+// the ones given are zero, so that the circuit of a membership proof, a path of `height`
+// siblings and the bits of the leaf index, has the same shape for every leaf.
+// ark-crypto-primitives has Merkle trees over byte oriented hashes, much more expensive
+// in a circuit.
+
+pub fn hash_pair<F: PrimeField + Absorb>(params: &PoseidonParameters<F>, left: F, right: F) -> F {
+    hash_inputs(params, &[left, right])
+}
+
+#[derive(Clone)]
+pub struct MerkleTree<F: PrimeField> {
+    params: PoseidonParameters<F>,
+    // the leaves first, the root last
+    layers: Vec<Vec<F>>,
+}
+
+// the siblings from the leaf up
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerklePath<F: PrimeField> {
+    pub index: usize,
+    pub siblings: Vec<F>,
+}
+
+// The membership of several leaves at once. Their paths share the nodes where they
+// meet, and a node on one path is often the sibling on another, so the proof only has
+// the siblings that can't be computed from the leaves, level by level from the leaves
+// up and left to right (`fold_multiproof`). The indices are sorted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleMultiproof<F: PrimeField> {
+    pub indices: Vec<usize>,
+    pub hashes: Vec<F>,
+}
+
+impl<F: PrimeField + Absorb> MerkleTree<F> {
+    // None if there are more than 2^height leaves
+    pub fn new(params: PoseidonParameters<F>, height: usize, leaves: &[F]) -> Option<Self> {
+        if leaves.len() > 1 << height {
+            return None;
+        }
+        let mut layer = leaves.to_vec();
+        layer.resize(1 << height, F::zero());
+        let mut layers = vec![layer];
+        for _ in 0..height {
+            let below = layers.last().unwrap();
+            let layer = below
+                .chunks(2)
+                .map(|pair| hash_pair(&params, pair[0], pair[1]))
+                .collect();
+            layers.push(layer);
+        }
+        Some(Self { params, layers })
+    }
+
+    pub fn height(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    pub fn root(&self) -> F {
+        self.layers[self.height()][0]
+    }
+
+    pub fn leaves(&self) -> &[F] {
+        &self.layers[0]
+    }
+
+    pub fn path(&self, index: usize) -> Option<MerklePath<F>> {
+        if index >= self.leaves().len() {
+            return None;
+        }
+        let siblings = (0..self.height())
+            .map(|level| self.layers[level][(index >> level) ^ 1])
+            .collect();
+        Some(MerklePath { index, siblings })
+    }
+
+    // None unless the indices are increasing and in the tree
+    pub fn multiproof(&self, indices: &[usize]) -> Option<MerkleMultiproof<F>> {
+        if !is_multiproof_shape(self.height(), indices) {
+            return None;
+        }
+        let mut hashes = Vec::new();
+        let leaves = indices.iter().map(|&i| self.layers[0][i]).collect();
+        fold_multiproof::<_, (), _, _>(
+            self.height(),
+            indices,
+            leaves,
+            |level, index| {
+                let sibling = self.layers[level][index ^ 1];
+                hashes.push(sibling);
+                Ok(sibling)
+            },
+            |left, right| Ok(hash_pair(&self.params, left, right)),
+        )
+        .ok()?;
+        Some(MerkleMultiproof {
+            indices: indices.to_vec(),
+            hashes,
+        })
+    }
+
+    // sets a leaf and rehashes the nodes above it
+    pub fn update(&mut self, index: usize, leaf: F) -> Option<()> {
+        *self.layers[0].get_mut(index)? = leaf;
+        for level in 1..=self.height() {
+            let i = index >> level;
+            let (left, right) = (
+                self.layers[level - 1][2 * i],
+                self.layers[level - 1][2 * i + 1],
+            );
+            self.layers[level][i] = hash_pair(&self.params, left, right);
+        }
+        Some(())
+    }
+}
+
+// A tree with a leaf for every index below 2^height that only stores the non-empty
+// nodes, for trees too tall to build, e.g. one indexed by 32 or 64 bit keys. An empty
+// leaf is zero and the hash of an empty subtree only depends on its height, so the
+// nodes missing from the map are `empty[level]`. Its paths are the ones of `MerkleTree`,
+// the circuit can't tell them apart.
+#[derive(Clone)]
+pub struct SparseMerkleTree<F: PrimeField> {
+    params: PoseidonParameters<F>,
+    // the roots of empty subtrees by height
+    empty: Vec<F>,
+    nodes: HashMap<(usize, usize), F>,
+}
+
+impl<F: PrimeField + Absorb> SparseMerkleTree<F> {
+    pub fn new(params: PoseidonParameters<F>, height: usize) -> Self {
+        assert!(height < usize::BITS as usize, "the indices are usize");
+        let mut empty = vec![F::zero()];
+        for level in 0..height {
+            empty.push(hash_pair(&params, empty[level], empty[level]));
+        }
+        Self {
+            params,
+            empty,
+            nodes: HashMap::new(),
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.empty.len() - 1
+    }
+
+    fn node(&self, level: usize, index: usize) -> F {
+        self.nodes
+            .get(&(level, index))
+            .copied()
+            .unwrap_or(self.empty[level])
+    }
+
+    pub fn root(&self) -> F {
+        self.node(self.height(), 0)
+    }
+
+    // zero for an empty leaf
+    pub fn leaf(&self, index: usize) -> F {
+        self.node(0, index)
+    }
+
+    pub fn path(&self, index: usize) -> Option<MerklePath<F>> {
+        if index >= 1 << self.height() {
+            return None;
+        }
+        let siblings = (0..self.height())
+            .map(|level| self.node(level, (index >> level) ^ 1))
+            .collect();
+        Some(MerklePath { index, siblings })
+    }
+
+    // sets a leaf, zero to empty it, and rehashes the nodes above it
+    pub fn update(&mut self, index: usize, leaf: F) -> Option<()> {
+        if index >= 1 << self.height() {
+            return None;
+        }
+        let mut node = leaf;
+        for level in 0..=self.height() {
+            let i = index >> level;
+            if level > 0 {
+                let (left, right) = (self.node(level - 1, 2 * i), self.node(level - 1, 2 * i + 1));
+                node = hash_pair(&self.params, left, right);
+            }
+            // empty subtrees are not stored
+            match node == self.empty[level] {
+                true => self.nodes.remove(&(level, i)),
+                false => self.nodes.insert((level, i), node),
+            };
+        }
+        Some(())
+    }
+}
+
+fn is_multiproof_shape(height: usize, indices: &[usize]) -> bool {
+    !indices.is_empty()
+        && indices.windows(2).all(|pair| pair[0] < pair[1])
+        && indices.last().is_some_and(|&last| last < 1 << height)
+}
+
+// The walk of a multiproof from the leaves at `indices` to the root, the same for the
+// values, the variables and the counting. A node is hashed with the next one if that is
+// its sibling and otherwise with a sibling from the proof, `sibling(level, index)`.
+fn fold_multiproof<T: Clone, E, S, H>(
+    height: usize,
+    indices: &[usize],
+    leaves: Vec<T>,
+    mut sibling: S,
+    mut hash: H,
+) -> Result<T, E>
+where
+    S: FnMut(usize, usize) -> Result<T, E>,
+    H: FnMut(T, T) -> Result<T, E>,
+{
+    let mut nodes: Vec<(usize, T)> = indices.iter().copied().zip(leaves).collect();
+    for level in 0..height {
+        let mut parents = Vec::with_capacity(nodes.len());
+        let mut i = 0;
+        while i < nodes.len() {
+            let (index, node) = nodes[i].clone();
+            let parent = match nodes.get(i + 1) {
+                Some((next, right)) if index.is_multiple_of(2) && *next == index + 1 => {
+                    i += 1;
+                    hash(node, right.clone())?
+                }
+                _ if index.is_multiple_of(2) => hash(node, sibling(level, index)?)?,
+                _ => hash(sibling(level, index)?, node)?,
+            };
+            parents.push((index / 2, parent));
+            i += 1;
+        }
+        nodes = parents;
+    }
+    Ok(nodes.swap_remove(0).1)
+}
+
+// the number of hashes of a multiproof of the leaves at `indices`, None if they are not
+// increasing and in a tree of `height`
+pub fn multiproof_size(height: usize, indices: &[usize]) -> Option<usize> {
+    if !is_multiproof_shape(height, indices) {
+        return None;
+    }
+    let mut size = 0;
+    fold_multiproof::<_, (), _, _>(
+        height,
+        indices,
+        vec![(); indices.len()],
+        |_, _| {
+            size += 1;
+            Ok(())
+        },
+        |_, _| Ok(()),
+    )
+    .ok()?;
+    Some(size)
+}
+
+impl<F: PrimeField + Absorb> MerkleMultiproof<F> {
+    // the root of a tree of `height` with `leaves` at the indices of the proof
+    pub fn root(&self, params: &PoseidonParameters<F>, height: usize, leaves: &[F]) -> Option<F> {
+        if !is_multiproof_shape(height, &self.indices) || leaves.len() != self.indices.len() {
+            return None;
+        }
+        let mut hashes = self.hashes.iter().copied();
+        let root = fold_multiproof(
+            height,
+            &self.indices,
+            leaves.to_vec(),
+            |_, _| hashes.next().ok_or(()),
+            |left, right| Ok(hash_pair(params, left, right)),
+        )
+        .ok()?;
+        // every hash is used
+        match hashes.next() {
+            None => Some(root),
+            Some(_) => None,
+        }
+    }
+}
+
+impl<F: PrimeField + Absorb> MerklePath<F> {
+    // the root of a tree with `leaf` at the index of the path
+    pub fn root(&self, params: &PoseidonParameters<F>, leaf: F) -> F {
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(leaf, |node, (level, &sibling)| {
+                match (self.index >> level) & 1 == 1 {
+                    true => hash_pair(params, sibling, node),
+                    false => hash_pair(params, node, sibling),
+                }
+            })
+    }
+}
+
+#[derive(Clone)]
+pub struct MerklePathVar<F: PrimeField> {
+    // least significant first, a set bit is a right child
+    pub index_bits: Vec<Boolean<F>>,
+    pub siblings: Vec<FpVar<F>>,
+}
+
+impl<F: PrimeField> MerklePathVar<F> {
+    // a private path in a tree of `height`, `path` is None for the setup
+    pub fn new_witness(
+        cs: ConstraintSystemRef<F>,
+        height: usize,
+        path: Option<&MerklePath<F>>,
+    ) -> Result<Self, SynthesisError> {
+        if let Some(path) = path {
+            assert_eq!(
+                path.siblings.len(),
+                height,
+                "the path is for another height"
+            );
+        }
+        let index_bits = (0..height)
+            .map(|level| {
+                Boolean::new_witness(ns!(cs, "index"), || {
+                    path.map(|p| (p.index >> level) & 1 == 1)
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let siblings = (0..height)
+            .map(|level| {
+                FpVar::new_witness(ns!(cs, "sibling"), || {
+                    path.map(|p| p.siblings[level])
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            index_bits,
+            siblings,
+        })
+    }
+
+    // the index of the leaf as a field element
+    pub fn index(&self) -> Result<FpVar<F>, SynthesisError> {
+        Boolean::le_bits_to_fp_var(&self.index_bits)
+    }
+
+    // the root of a tree with `leaf` on this path
+    pub fn root(
+        &self,
+        cs: ConstraintSystemRef<F>,
+        params: &PoseidonParameters<F>,
+        leaf: &FpVar<F>,
+    ) -> Result<FpVar<F>, SynthesisError> {
+        let mut node = leaf.clone();
+        for (is_right, sibling) in self.index_bits.iter().zip(&self.siblings) {
+            let left = is_right.select(sibling, &node)?;
+            let right = is_right.select(&node, sibling)?;
+            node = hash_inputs_var(cs.clone(), params, &[left, right])?;
+        }
+        Ok(node)
+    }
+}
+
+// the root of a tree of `height` with `leaves` at `indices`, from the hashes of a
+// multiproof. The indices are constants, they fix which nodes are hashed together.
+pub fn multiproof_root_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    params: &PoseidonParameters<F>,
+    height: usize,
+    indices: &[usize],
+    leaves: &[FpVar<F>],
+    hashes: &[FpVar<F>],
+) -> Result<FpVar<F>, SynthesisError> {
+    assert_eq!(
+        multiproof_size(height, indices),
+        Some(hashes.len()),
+        "the multiproof is for other indices"
+    );
+    let mut hashes = hashes.iter().cloned();
+    fold_multiproof(
+        height,
+        indices,
+        leaves.to_vec(),
+        |_, _| hashes.next().ok_or(SynthesisError::AssignmentMissing),
+        |left, right| hash_inputs_var(cs.clone(), params, &[left, right]),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_bls12_381::Fr;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::UniformRand;
+
+    #[test]
+    fn test_merkle_tree() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters::<Fr>();
+        let leaves: Vec<Fr> = (0..5).map(|_| Fr::rand(rng)).collect();
+        let mut tree = MerkleTree::new(params.clone(), 3, &leaves).unwrap();
+        assert!(MerkleTree::new(params.clone(), 2, &leaves).is_none());
+        assert_eq!(tree.leaves().len(), 8);
+        assert_eq!(tree.path(8), None);
+
+        for (index, &leaf) in tree.leaves().iter().enumerate() {
+            let path = tree.path(index).unwrap();
+            assert_eq!(path.root(&params, leaf), tree.root());
+            assert_ne!(path.root(&params, leaf + Fr::from(1u8)), tree.root());
+
+            let cs = ConstraintSystem::new_ref();
+            let path_var = MerklePathVar::new_witness(cs.clone(), 3, Some(&path)).unwrap();
+            let leaf_var = FpVar::new_witness(cs.clone(), || Ok(leaf)).unwrap();
+            let root = path_var.root(cs.clone(), &params, &leaf_var).unwrap();
+            assert_eq!(root.value().unwrap(), tree.root());
+            assert_eq!(
+                path_var.index().unwrap().value().unwrap(),
+                Fr::from(index as u64)
+            );
+            assert!(cs.is_satisfied().unwrap());
+        }
+
+        let before = tree.root();
+        tree.update(6, Fr::from(7u8)).unwrap();
+        let rebuilt = {
+            let mut leaves = leaves.clone();
+            leaves.resize(6, Fr::from(0u8));
+            leaves.push(Fr::from(7u8));
+            MerkleTree::new(params.clone(), 3, &leaves).unwrap()
+        };
+        assert_ne!(tree.root(), before);
+        assert_eq!(tree.root(), rebuilt.root());
+    }
+
+    #[test]
+    fn test_multiproof() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters::<Fr>();
+        let leaves: Vec<Fr> = (0..16).map(|_| Fr::rand(rng)).collect();
+        let tree = MerkleTree::new(params.clone(), 4, &leaves).unwrap();
+
+        // siblings of each other, far apart, all of them, one
+        let cases: [(&[usize], usize); 4] = [
+            (&[4, 5], 3),
+            (&[0, 15], 6),
+            (&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15], 0),
+            (&[6], 4),
+        ];
+        for &(indices, size) in cases.iter() {
+            let proof = tree.multiproof(indices).unwrap();
+            assert_eq!(proof.hashes.len(), size);
+            assert_eq!(multiproof_size(4, indices), Some(size));
+            let values: Vec<Fr> = indices.iter().map(|&i| leaves[i]).collect();
+            assert_eq!(proof.root(&params, 4, &values), Some(tree.root()));
+            let mut changed = values.clone();
+            changed[0] += Fr::from(1u8);
+            assert_ne!(proof.root(&params, 4, &changed), Some(tree.root()));
+
+            let cs = ConstraintSystem::new_ref();
+            let leaf_vars = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(values)).unwrap();
+            let hash_vars =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(proof.hashes.clone())).unwrap();
+            let root = multiproof_root_var(cs.clone(), &params, 4, indices, &leaf_vars, &hash_vars)
+                .unwrap();
+            assert_eq!(root.value().unwrap(), tree.root());
+        }
+
+        // unsorted, repeated or out of the tree, a hash too many
+        assert_eq!(tree.multiproof(&[5, 4]), None);
+        assert_eq!(tree.multiproof(&[4, 4]), None);
+        assert_eq!(tree.multiproof(&[16]), None);
+        assert_eq!(tree.multiproof(&[]), None);
+        let mut proof = tree.multiproof(&[3]).unwrap();
+        proof.hashes.push(Fr::from(0u8));
+        assert_eq!(proof.root(&params, 4, &[leaves[3]]), None);
+    }
+
+    #[test]
+    fn test_sparse_merkle_tree() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters::<Fr>();
+        let leaves: Vec<(usize, Fr)> = vec![(1, Fr::rand(rng)), (6, Fr::rand(rng))];
+        // the same tree as a dense one
+        let mut dense = vec![Fr::from(0u8); 8];
+        let mut sparse = SparseMerkleTree::new(params.clone(), 3);
+        let empty_root = sparse.root();
+        assert_eq!(
+            empty_root,
+            MerkleTree::new(params.clone(), 3, &dense).unwrap().root()
+        );
+        for &(index, leaf) in leaves.iter() {
+            dense[index] = leaf;
+            sparse.update(index, leaf).unwrap();
+        }
+        let tree = MerkleTree::new(params.clone(), 3, &dense).unwrap();
+        assert_eq!(sparse.root(), tree.root());
+        for (index, &leaf) in dense.iter().enumerate() {
+            assert_eq!(sparse.leaf(index), leaf);
+            assert_eq!(sparse.path(index), tree.path(index));
+        }
+        assert_eq!(sparse.path(8), None);
+        assert_eq!(sparse.update(8, Fr::from(1u8)), None);
+
+        // emptied again
+        for &(index, _) in leaves.iter() {
+            sparse.update(index, Fr::from(0u8)).unwrap();
+        }
+        assert_eq!(sparse.root(), empty_root);
+        assert!(sparse.nodes.is_empty());
+
+        // a tall one
+        let mut tall = SparseMerkleTree::new(params.clone(), 40);
+        tall.update(1 << 39, Fr::from(7u8)).unwrap();
+        let path = tall.path(1 << 39).unwrap();
+        assert_eq!(path.root(&params, Fr::from(7u8)), tall.root());
+        assert_eq!(tall.nodes.len(), 41);
+    }
+}
@@ -0,0 +1,310 @@
+use ark_ec::{msm::VariableBaseMSM, AffineCurve, PairingEngine};
+use ark_ff::{PrimeField, UniformRand};
+use ark_groth16::{Proof, ProvingKey};
+use ark_relations::r1cs::{ConstraintSynthesizer, SynthesisError};
+#[cfg(feature = "msm-hook")]
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::{CryptoRng, RngCore};
+
+use crate::progress::{Progress, GROTH16_MSMS};
+use crate::streaming_prover::{assemble, witness, QuerySums};
+
+// Multi-scalar multiplications are most of the time of a Groth16 proof (five of them,
+// one in G2), and the part that GPUs and other accelerators speed up. `Groth16::prove`
+// runs them inside ark-groth16 where nothing can swap them out, so the provers here that
+// do their own MSMs take an `MsmBackend`: `create_proof_with_backend` for a key in
+// memory and `streaming_prover::create_proof_streaming_with` for a key on disk. Both
+// make the proof `ark_groth16::create_proof` makes.
+//
+// `CpuMsm` is ark-ec's Pippenger, what ark-groth16 uses. With the `msm-hook` feature
+// `HookMsm` hands MSMs to an external library through a plain function over bytes, the
+// shape of the C interfaces GPU MSM crates export. Anything else implements the trait.
+
+type BigInt<F> = <F as PrimeField>::BigInt;
+
+pub trait MsmBackend {
+    // sum scalars_i * bases_i over the shorter of the two slices, like
+    // `VariableBaseMSM::multi_scalar_mul`
+    fn msm<G: AffineCurve>(&self, bases: &[G], scalars: &[BigInt<G::ScalarField>])
+        -> G::Projective;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CpuMsm;
+
+impl MsmBackend for CpuMsm {
+    fn msm<G: AffineCurve>(
+        &self,
+        bases: &[G],
+        scalars: &[BigInt<G::ScalarField>],
+    ) -> G::Projective {
+        VariableBaseMSM::multi_scalar_mul(bases, scalars)
+    }
+}
+
+// an external MSM: `group` is the `core::any::type_name` of the affine point type, `bases`
+// the points uncompressed as ark-serialize writes them, `scalars` the canonical integers
+// as little-endian limbs, `count` of each. Returns the sum as one uncompressed point, or
+// None to leave the MSM to the CPU (no device, a group it doesn't do, ...)
+#[cfg(feature = "msm-hook")]
+pub type MsmHook =
+    fn(group: &'static str, count: usize, bases: &[u8], scalars: &[u8]) -> Option<Vec<u8>>;
+
+// MSMs of at least `min_size` points go to the hook, smaller ones are not worth the copy.
+// A result that doesn't decode to a point of the group is dropped for the CPU's
+#[cfg(feature = "msm-hook")]
+#[derive(Clone, Copy, Debug)]
+pub struct HookMsm {
+    pub hook: MsmHook,
+    pub min_size: usize,
+}
+
+#[cfg(feature = "msm-hook")]
+impl HookMsm {
+    pub fn new(hook: MsmHook) -> Self {
+        Self {
+            hook,
+            min_size: 1 << 10,
+        }
+    }
+
+    pub fn with_min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    fn run<G: AffineCurve>(&self, bases: &[G], scalars: &[BigInt<G::ScalarField>]) -> Option<G> {
+        let count = bases.len().min(scalars.len());
+        let mut base_bytes = Vec::with_capacity(count * G::zero().uncompressed_size());
+        let mut scalar_bytes = Vec::new();
+        for (base, scalar) in bases.iter().zip(scalars) {
+            base.serialize_uncompressed(&mut base_bytes).ok()?;
+            scalar.serialize(&mut scalar_bytes).ok()?;
+        }
+        let group = core::any::type_name::<G>();
+        let sum = (self.hook)(group, count, &base_bytes, &scalar_bytes)?;
+        G::deserialize_uncompressed(&sum[..]).ok()
+    }
+}
+
+#[cfg(feature = "msm-hook")]
+impl MsmBackend for HookMsm {
+    fn msm<G: AffineCurve>(
+        &self,
+        bases: &[G],
+        scalars: &[BigInt<G::ScalarField>],
+    ) -> G::Projective {
+        if bases.len().min(scalars.len()) >= self.min_size {
+            if let Some(sum) = self.run(bases, scalars) {
+                return sum.into_projective();
+            }
+        }
+        CpuMsm.msm(bases, scalars)
+    }
+}
+
+// `ark_groth16::create_proof(circuit, pk, r, s)` with the MSMs on `backend`
+pub fn create_proof_with_backend<E, C, M>(
+    circuit: C,
+    pk: &ProvingKey<E>,
+    r: E::Fr,
+    s: E::Fr,
+    backend: &M,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: PairingEngine,
+    C: ConstraintSynthesizer<E::Fr>,
+    M: MsmBackend,
+{
+    create_proof_with_progress(circuit, pk, r, s, backend, &mut |_| {})
+}
+
+// the same, with `progress` told when synthesis, the witness map and each of the five
+// MSMs are done
+pub fn create_proof_with_progress<E, C, M>(
+    circuit: C,
+    pk: &ProvingKey<E>,
+    r: E::Fr,
+    s: E::Fr,
+    backend: &M,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: PairingEngine,
+    C: ConstraintSynthesizer<E::Fr>,
+    M: MsmBackend,
+{
+    let witness = witness(circuit, progress)?;
+    let assignment = &witness.assignment;
+    let mut done = 0;
+    let mut msm_done = || {
+        done += 1;
+        progress(Progress::Committed {
+            done,
+            total: GROTH16_MSMS,
+        });
+    };
+    let a = backend.msm(&pk.a_query, assignment);
+    msm_done();
+    let b_g1 = backend.msm(&pk.b_g1_query, assignment);
+    msm_done();
+    let b_g2 = backend.msm(&pk.b_g2_query, assignment);
+    msm_done();
+    let h = backend.msm(&pk.h_query, &witness.h);
+    msm_done();
+    let l = backend.msm(&pk.l_query, witness.aux());
+    msm_done();
+    let sums = QuerySums {
+        a,
+        b_g1,
+        b_g2,
+        h,
+        l,
+    };
+    Ok(assemble(&pk.vk, pk.beta_g1, pk.delta_g1, sums, r, s))
+}
+
+// r and s drawn as `Groth16::prove` draws them
+pub fn prove_with_backend<E, C, M, R>(
+    pk: &ProvingKey<E>,
+    circuit: C,
+    backend: &M,
+    rng: &mut R,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: PairingEngine,
+    C: ConstraintSynthesizer<E::Fr>,
+    M: MsmBackend,
+    R: RngCore + CryptoRng,
+{
+    let r = E::Fr::rand(rng);
+    let s = E::Fr::rand(rng);
+    create_proof_with_backend(circuit, pk, r, s, backend)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::synthetic::SyntheticCircuit;
+    use ark_bls12_381::{Bls12_381, Fr, G1Affine, G1Projective};
+    use ark_ec::ProjectiveCurve;
+    use ark_groth16::{create_proof, Groth16};
+    use ark_snark::SNARK;
+    use ark_std::Zero;
+
+    #[test]
+    fn test_cpu_msm() {
+        let rng = &mut ark_std::test_rng();
+        let bases: Vec<G1Affine> = (0..50)
+            .map(|_| G1Projective::rand(rng).into_affine())
+            .collect();
+        let scalars: Vec<Fr> = (0..40).map(|_| Fr::rand(rng)).collect();
+        let reprs: Vec<_> = scalars.iter().map(|x| x.into_repr()).collect();
+
+        // the extra bases are ignored
+        let expected = bases
+            .iter()
+            .zip(&scalars)
+            .fold(G1Projective::zero(), |sum, (base, scalar)| {
+                sum + base.mul(*scalar)
+            });
+        assert_eq!(CpuMsm.msm(&bases, &reprs), expected);
+        assert_eq!(CpuMsm.msm(&bases[..0], &reprs), G1Projective::zero());
+    }
+
+    #[test]
+    fn test_same_proof_as_ark() {
+        let rng = &mut ark_std::test_rng();
+        let circuit = SyntheticCircuit::new(64, 48, 3).with_public_inputs(2);
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(circuit, rng).unwrap();
+        let (r, s) = (Fr::rand(rng), Fr::rand(rng));
+        let proof = create_proof_with_backend(circuit, &pk, r, s, &CpuMsm).unwrap();
+        assert_eq!(proof, create_proof(circuit, &pk, r, s).unwrap());
+
+        let proof = prove_with_backend(&pk, circuit, &CpuMsm, rng).unwrap();
+        let inputs = circuit.public_inputs();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+    }
+
+    #[cfg(feature = "msm-hook")]
+    mod hook {
+        use super::*;
+        use crate::persist::{self, ArtifactKind, SerializationMode, Validation};
+        use crate::streaming_prover::{create_proof_streaming_with, StreamingOptions};
+        use ark_bls12_381::g1::Parameters;
+        use ark_ec::short_weierstrass_jacobian::GroupAffine;
+        use ark_ff::BigInteger256;
+        use ark_serialize::CanonicalDeserialize;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        // a "device" for BLS12-381 G1 only, decoding the bytes like a foreign library would
+        fn bls12_381_g1(
+            group: &str,
+            count: usize,
+            bases: &[u8],
+            scalars: &[u8],
+        ) -> Option<Vec<u8>> {
+            if group != core::any::type_name::<GroupAffine<Parameters>>() {
+                return None;
+            }
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            let bases: Vec<G1Affine> = bases
+                .chunks(bases.len() / count)
+                .map(|b| G1Affine::deserialize_uncompressed(b).unwrap())
+                .collect();
+            let scalars: Vec<BigInteger256> = scalars
+                .chunks(32)
+                .map(|s| BigInteger256::deserialize(s).unwrap())
+                .collect();
+            let mut sum = Vec::new();
+            let result = CpuMsm.msm(&bases, &scalars).into_affine();
+            result.serialize_uncompressed(&mut sum).unwrap();
+            Some(sum)
+        }
+
+        fn garbage(_: &str, _: usize, _: &[u8], _: &[u8]) -> Option<Vec<u8>> {
+            Some(vec![0xff; 96])
+        }
+
+        #[test]
+        fn test_hook() {
+            let rng = &mut ark_std::test_rng();
+            let circuit = SyntheticCircuit::new(64, 48, 3);
+            let (pk, _) = Groth16::<Bls12_381>::circuit_specific_setup(circuit, rng).unwrap();
+            let (r, s) = (Fr::rand(rng), Fr::rand(rng));
+            let expected = create_proof(circuit, &pk, r, s).unwrap();
+
+            // the four G1 MSMs go to the hook, G2 falls back
+            let backend = HookMsm::new(bls12_381_g1).with_min_size(1);
+            let proof = create_proof_with_backend(circuit, &pk, r, s, &backend).unwrap();
+            assert_eq!(proof, expected);
+            assert_eq!(CALLS.swap(0, Ordering::SeqCst), 4);
+
+            // below the minimum size nothing leaves the CPU
+            let backend = HookMsm::new(bls12_381_g1);
+            let proof = create_proof_with_backend(circuit, &pk, r, s, &backend).unwrap();
+            assert_eq!(proof, expected);
+            assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+
+            // and the streaming prover, one call per chunk
+            let mut bytes = Vec::new();
+            let mode = SerializationMode::Uncompressed;
+            persist::write_artifact(&pk, ArtifactKind::ProvingKey, mode, &mut bytes).unwrap();
+            let options = StreamingOptions {
+                chunk_size: 32,
+                validation: Validation::Unchecked,
+            };
+            let backend = HookMsm::new(bls12_381_g1).with_min_size(1);
+            let proof =
+                create_proof_streaming_with(&bytes[..], circuit, r, s, &options, &backend).unwrap();
+            assert_eq!(proof, expected);
+            assert!(CALLS.swap(0, Ordering::SeqCst) > 4);
+
+            let backend = HookMsm::new(garbage).with_min_size(1);
+            let proof = create_proof_with_backend(circuit, &pk, r, s, &backend).unwrap();
+            assert_eq!(proof, expected);
+        }
+    }
+}
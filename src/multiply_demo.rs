@@ -1,14 +1,104 @@
 use ark_ff::Field;
-use ark_relations::{
-    lc,
-    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
-};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+use crate::builder::BuildError;
+use crate::expr::Compiler;
 
 // circuit: prover claims that she knows two factors a and b of some public value c
 #[derive(Copy, Clone)]
-struct MultiplyDemoCircuit<F: Field> {
+pub struct MultiplyDemoCircuit<F: Field> {
+    pub a: Option<F>,
+    pub b: Option<F>,
+}
+
+// what the verifier sees: the product c
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MultiplyInstance<F: Field> {
+    pub c: F,
+}
+
+impl<F: Field> MultiplyInstance<F> {
+    pub fn public_inputs(&self) -> Vec<F> {
+        vec![self.c]
+    }
+}
+
+// what only the prover knows: the factors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MultiplyWitness<F: Field> {
+    pub a: F,
+    pub b: F,
+}
+
+impl<F: Field> MultiplyWitness<F> {
+    pub fn instance(&self) -> MultiplyInstance<F> {
+        MultiplyInstance { c: self.a * self.b }
+    }
+}
+
+impl<F: Field> MultiplyDemoCircuit<F> {
+    // no assignment, for setup and for counting constraints
+    pub fn blank() -> Self {
+        Self { a: None, b: None }
+    }
+}
+
+impl<F: Field> From<MultiplyWitness<F>> for MultiplyDemoCircuit<F> {
+    fn from(witness: MultiplyWitness<F>) -> Self {
+        Self {
+            a: Some(witness.a),
+            b: Some(witness.b),
+        }
+    }
+}
+
+impl<F: Field> MultiplyDemoCircuit<F> {
+    // MultiplyDemoCircuit::builder().a(3u64).b(11u64).c(33u64).build()?
+    pub fn builder() -> MultiplyBuilder<F> {
+        MultiplyBuilder::default()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MultiplyBuilder<F: Field> {
     a: Option<F>,
     b: Option<F>,
+    c: Option<F>,
+}
+
+impl<F: Field> MultiplyBuilder<F> {
+    pub fn a(mut self, a: impl Into<F>) -> Self {
+        self.a = Some(a.into());
+        self
+    }
+
+    pub fn b(mut self, b: impl Into<F>) -> Self {
+        self.b = Some(b.into());
+        self
+    }
+
+    // optional, the public product the proof is for; `build` then checks the relation
+    pub fn c(mut self, c: impl Into<F>) -> Self {
+        self.c = Some(c.into());
+        self
+    }
+
+    // the circuit for proving
+    pub fn build(self) -> Result<MultiplyDemoCircuit<F>, BuildError> {
+        let witness = MultiplyWitness {
+            a: self.a.ok_or(BuildError::Missing("a"))?,
+            b: self.b.ok_or(BuildError::Missing("b"))?,
+        };
+        match self.c {
+            Some(c) if witness.instance().c != c => Err(BuildError::Unsatisfied("a * b == c")),
+            _ => Ok(witness.into()),
+        }
+    }
+
+    // the circuit for setup, any values given are ignored
+    pub fn build_setup(self) -> MultiplyDemoCircuit<F> {
+        MultiplyDemoCircuit::blank()
+    }
 }
 
 impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for MultiplyDemoCircuit<ConstraintF> {
@@ -16,17 +106,13 @@ impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for MultiplyDemoCirc
         self,
         cs: ConstraintSystemRef<ConstraintF>,
     ) -> Result<(), SynthesisError> {
-        let a = cs.new_witness_variable(|| self.a.ok_or(SynthesisError::AssignmentMissing))?;
-        let b = cs.new_witness_variable(|| self.b.ok_or(SynthesisError::AssignmentMissing))?;
-        let c = cs.new_input_variable(|| {
-            let mut a = self.a.ok_or(SynthesisError::AssignmentMissing)?;
-            let b = self.b.ok_or(SynthesisError::AssignmentMissing)?;
-
-            a.mul_assign(&b);
-            Ok(a)
-        })?;
+        let witness = self.a.zip(self.b).map(|(a, b)| MultiplyWitness { a, b });
 
-        cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + c)?;
+        let mut compiler = Compiler::new(cs);
+        let a = compiler.witness("a", self.a)?;
+        let b = compiler.witness("b", self.b)?;
+        let c = compiler.input("c", witness.map(|w| w.instance().c))?;
+        compiler.enforce((a * b).equals(c))?;
 
         Ok(())
     }
@@ -41,6 +127,50 @@ mod test {
     use ark_snark::SNARK;
     use ark_std::{ops::*, UniformRand};
 
+    #[test]
+    fn test_instance_witness() {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            MultiplyDemoCircuit::<BlsFr>::blank(),
+            rng,
+        )
+        .unwrap();
+
+        let witness = MultiplyWitness {
+            a: BlsFr::from(3u8),
+            b: BlsFr::from(11u8),
+        };
+        let instance = witness.instance();
+        assert_eq!(instance.public_inputs(), vec![BlsFr::from(33u8)]);
+        let proof =
+            Groth16::<Bls12_381>::prove(&pk, MultiplyDemoCircuit::from(witness), rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &instance.public_inputs(), &proof).unwrap());
+    }
+
+    #[test]
+    fn test_builder() {
+        let builder = MultiplyDemoCircuit::<BlsFr>::builder().a(3u64).b(11u64);
+        let circuit = builder.c(33u64).build().unwrap();
+        assert_eq!(
+            (circuit.a, circuit.b),
+            (Some(BlsFr::from(3u8)), Some(BlsFr::from(11u8)))
+        );
+        let setup = builder.build_setup();
+        assert_eq!((setup.a, setup.b), (None, None));
+
+        assert_eq!(
+            builder.c(34u64).build().err(),
+            Some(BuildError::Unsatisfied("a * b == c"))
+        );
+        assert_eq!(
+            MultiplyDemoCircuit::<BlsFr>::builder()
+                .a(3u64)
+                .build()
+                .err(),
+            Some(BuildError::Missing("b"))
+        );
+    }
+
     #[test]
     fn test_groth16_circuit_multiply() {
         let rng = &mut ark_std::test_rng();
@@ -0,0 +1,249 @@
+use ark_bls12_381::Fr;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{
+        ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError, SynthesisMode,
+    },
+};
+use ark_sponge::poseidon::PoseidonParameters;
+
+use crate::merkle_gadget::{
+    multiproof_root_var, multiproof_size, MerkleMultiproof, MerklePath, MerklePathVar,
+};
+
+// Batch membership: the leaves at some public indices of a tree of `merkle_gadget` are
+// the public values, against a public root. `MultiproofCircuit` checks one multiproof,
+// the nodes the paths share are hashed once and a sibling that is on another path is
+// not a witness, `PathsCircuit` checks a path per leaf like `ownership_demo`. Both take
+// the indices as constants, so a key is for one set of indices, e.g. the positions a
+// verifier samples with a fixed seed; a universal setup like `marlin_demo` avoids a
+// setup per set.
+//
+// Nearly all constraints are the hashes. k paths have k * height of them, a multiproof
+// one per node of the union of the paths: for leaves close together that is about
+// k + height, for k leaves spread evenly about k * (height - log2 k) + k - 1.
+// `constraint_counts` compares the two, for 8 leaves of a tree of height 16:
+//   - leaves 0 to 7:          9601 constraints, 61840 with paths,
+//   - every 2^13-th leaf:    53281 constraints.
+
+#[derive(Clone)]
+pub struct MultiproofCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub height: usize,
+    pub indices: Vec<usize>,
+    pub root: Fr,
+    pub leaves: Vec<Fr>,
+    pub hashes: Option<Vec<Fr>>,
+}
+
+impl MultiproofCircuit {
+    pub fn new(
+        params: PoseidonParameters<Fr>,
+        height: usize,
+        root: Fr,
+        leaves: Vec<Fr>,
+        proof: MerkleMultiproof<Fr>,
+    ) -> Self {
+        Self {
+            params,
+            height,
+            indices: proof.indices,
+            root,
+            leaves,
+            hashes: Some(proof.hashes),
+        }
+    }
+
+    // the root and the leaves are public inputs
+    pub fn blank(params: PoseidonParameters<Fr>, height: usize, indices: Vec<usize>) -> Self {
+        Self {
+            params,
+            height,
+            leaves: vec![Fr::from(0u8); indices.len()],
+            indices,
+            root: Fr::from(0u8),
+            hashes: None,
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs = vec![self.root];
+        inputs.extend_from_slice(&self.leaves);
+        inputs
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for MultiproofCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let root = FpVar::new_input(ns!(cs, "root"), || Ok(self.root))?;
+        let leaves = Vec::<FpVar<Fr>>::new_input(ns!(cs, "leaves"), || Ok(self.leaves.as_slice()))?;
+        let size = multiproof_size(self.height, &self.indices).expect("increasing indices");
+        let hashes = (0..size)
+            .map(|i| {
+                FpVar::new_witness(ns!(cs, "hash"), || {
+                    self.hashes
+                        .as_ref()
+                        .map(|h| h[i])
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        multiproof_root_var(
+            cs,
+            &self.params,
+            self.height,
+            &self.indices,
+            &leaves,
+            &hashes,
+        )?
+        .enforce_equal(&root)
+    }
+}
+
+// the same statement with a path per leaf
+#[derive(Clone)]
+pub struct PathsCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub height: usize,
+    pub indices: Vec<usize>,
+    pub root: Fr,
+    pub leaves: Vec<Fr>,
+    pub paths: Option<Vec<MerklePath<Fr>>>,
+}
+
+impl ConstraintSynthesizer<Fr> for PathsCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let root = FpVar::new_input(ns!(cs, "root"), || Ok(self.root))?;
+        let leaves = Vec::<FpVar<Fr>>::new_input(ns!(cs, "leaves"), || Ok(self.leaves.as_slice()))?;
+        for (i, (&index, leaf)) in self.indices.iter().zip(&leaves).enumerate() {
+            let path = self.paths.as_ref().map(|paths| &paths[i]);
+            let path = MerklePathVar::new_witness(cs.clone(), self.height, path)?;
+            path.index()?
+                .enforce_equal(&FpVar::constant(Fr::from(index as u64)))?;
+            path.root(cs.clone(), &self.params, leaf)?
+                .enforce_equal(&root)?;
+        }
+        Ok(())
+    }
+}
+
+// the constraints of a multiproof and of separate paths for the leaves at `indices`
+pub fn constraint_counts(
+    params: &PoseidonParameters<Fr>,
+    height: usize,
+    indices: &[usize],
+) -> Result<(usize, usize), SynthesisError> {
+    let multiproof = MultiproofCircuit::blank(params.clone(), height, indices.to_vec());
+    let paths = PathsCircuit {
+        params: params.clone(),
+        height,
+        indices: indices.to_vec(),
+        root: Fr::from(0u8),
+        leaves: vec![Fr::from(0u8); indices.len()],
+        paths: None,
+    };
+    let count = |circuit: &dyn Fn(ConstraintSystemRef<Fr>) -> Result<(), SynthesisError>| {
+        let cs = ConstraintSystem::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        circuit(cs.clone())?;
+        Ok::<_, SynthesisError>(cs.num_constraints())
+    };
+    Ok((
+        count(&|cs| multiproof.clone().generate_constraints(cs))?,
+        count(&|cs| paths.clone().generate_constraints(cs))?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::merkle_gadget::MerkleTree;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    #[test]
+    fn test_batch_membership() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let leaves: Vec<Fr> = (0..50).map(|_| Fr::rand(rng)).collect();
+        let tree = MerkleTree::new(params.clone(), 6, &leaves).unwrap();
+        let indices = vec![3, 4, 5, 17, 40];
+        let values: Vec<Fr> = indices.iter().map(|&i| leaves[i]).collect();
+
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            MultiproofCircuit::blank(params.clone(), 6, indices.clone()),
+            rng,
+        )
+        .unwrap();
+        let proof = tree.multiproof(&indices).unwrap();
+        let circuit = MultiproofCircuit::new(params.clone(), 6, tree.root(), values, proof);
+        let inputs = circuit.public_inputs();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        // the leaves of other indices, another root
+        let mut other = inputs.clone();
+        other[2] = leaves[6];
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &other, &proof).unwrap());
+        let mut other = inputs.clone();
+        other[0] += Fr::from(1u8);
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &other, &proof).unwrap());
+    }
+
+    #[test]
+    fn test_both_circuits_agree() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let leaves: Vec<Fr> = (0..16).map(|_| Fr::rand(rng)).collect();
+        let tree = MerkleTree::new(params.clone(), 4, &leaves).unwrap();
+        let indices = vec![0, 1, 9];
+        let values: Vec<Fr> = indices.iter().map(|&i| leaves[i]).collect();
+
+        let is_satisfied = |values: Vec<Fr>| {
+            let multiproof = MultiproofCircuit::new(
+                params.clone(),
+                4,
+                tree.root(),
+                values.clone(),
+                tree.multiproof(&indices).unwrap(),
+            );
+            let paths = PathsCircuit {
+                params: params.clone(),
+                height: 4,
+                indices: indices.clone(),
+                root: tree.root(),
+                leaves: values,
+                paths: Some(indices.iter().map(|&i| tree.path(i).unwrap()).collect()),
+            };
+            let cs = ConstraintSystem::new_ref();
+            multiproof.generate_constraints(cs.clone()).unwrap();
+            let other = ConstraintSystem::new_ref();
+            paths.generate_constraints(other.clone()).unwrap();
+            (cs.is_satisfied().unwrap(), other.is_satisfied().unwrap())
+        };
+        assert_eq!(is_satisfied(values.clone()), (true, true));
+        let mut swapped = values;
+        swapped.swap(0, 1);
+        assert_eq!(is_satisfied(swapped), (false, false));
+    }
+
+    #[test]
+    fn test_constraint_counts() {
+        let params = poseidon_parameters();
+        let (close, paths) = constraint_counts(&params, 16, &(0..8).collect::<Vec<_>>()).unwrap();
+        assert!(close * 5 < paths, "{} {}", close, paths);
+        let spread: Vec<usize> = (0..8).map(|i| i << 13).collect();
+        let (spread, same) = constraint_counts(&params, 16, &spread).unwrap();
+        assert_eq!(same, paths);
+        assert!(
+            spread < paths && spread * 5 > paths * 4,
+            "{} {}",
+            spread,
+            paths
+        );
+    }
+}
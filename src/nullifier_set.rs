@@ -0,0 +1,182 @@
+use ark_ff::PrimeField;
+use ark_serialize::SerializationError;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+// The nullifiers a verifier has accepted, so that a second proof with the same nullifier
+// (a second claim of an airdrop, a second withdrawal of a deposit) is refused. Proofs
+// of one statement can be rerandomized (tests/malleability.rs), so the set holds the
+// nullifier, a public input, never proof bytes. In memory with `new`, or with `open`
+// also in a file that every accepted nullifier is appended to,
+//   magic (4 bytes) | version (1 byte) | nullifier*
+// the nullifiers in `CanonicalSerialize` form, read back by the next `open`.
+
+pub const MAGIC: [u8; 4] = *b"ARKN";
+pub const VERSION: u8 = 1;
+
+#[derive(Debug)]
+pub enum NullifierError {
+    Io(io::Error),
+    Serialization(SerializationError),
+    InvalidHeader,
+    UnsupportedVersion(u8),
+}
+
+impl fmt::Display for NullifierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NullifierError::Io(e) => write!(f, "io error: {}", e),
+            NullifierError::Serialization(e) => write!(f, "serialization error: {}", e),
+            NullifierError::InvalidHeader => write!(f, "not a nullifier file"),
+            NullifierError::UnsupportedVersion(v) => {
+                write!(f, "unsupported nullifier file version {}", v)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NullifierError {}
+
+impl From<io::Error> for NullifierError {
+    fn from(e: io::Error) -> Self {
+        NullifierError::Io(e)
+    }
+}
+
+impl From<SerializationError> for NullifierError {
+    fn from(e: SerializationError) -> Self {
+        NullifierError::Serialization(e)
+    }
+}
+
+pub struct NullifierSet<F: PrimeField> {
+    seen: HashSet<F>,
+    log: Option<File>,
+}
+
+impl<F: PrimeField> Default for NullifierSet<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: PrimeField> NullifierSet<F> {
+    // in memory only
+    pub fn new() -> Self {
+        Self {
+            seen: HashSet::new(),
+            log: None,
+        }
+    }
+
+    // the set in the file at `path`, an empty one written there if there is no file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, NullifierError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            let mut file = File::create(path)?;
+            file.write_all(&MAGIC)?;
+            file.write_all(&[VERSION])?;
+            file.sync_all()?;
+        }
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header)?;
+        if header[..4] != MAGIC {
+            return Err(NullifierError::InvalidHeader);
+        }
+        if header[4] != VERSION {
+            return Err(NullifierError::UnsupportedVersion(header[4]));
+        }
+        let mut seen = HashSet::new();
+        // a file that ends inside a nullifier, e.g. after a crash, fails to read
+        while !reader.fill_buf()?.is_empty() {
+            seen.insert(F::deserialize(&mut reader)?);
+        }
+        Ok(Self {
+            seen,
+            log: Some(OpenOptions::new().append(true).open(path)?),
+        })
+    }
+
+    pub fn contains(&self, nullifier: &F) -> bool {
+        self.seen.contains(nullifier)
+    }
+
+    // false if the nullifier was already in the set. A new one goes to the file before
+    // it counts as seen.
+    pub fn insert(&mut self, nullifier: F) -> Result<bool, NullifierError> {
+        if self.contains(&nullifier) {
+            return Ok(false);
+        }
+        if let Some(log) = self.log.as_mut() {
+            let mut bytes = Vec::with_capacity(nullifier.serialized_size());
+            nullifier.serialize(&mut bytes)?;
+            log.write_all(&bytes)?;
+            log.sync_data()?;
+        }
+        self.seen.insert(nullifier);
+        Ok(true)
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::Fr;
+    use ark_std::UniformRand;
+    use std::fs;
+
+    #[test]
+    fn test_nullifier_set() {
+        let rng = &mut ark_std::test_rng();
+        let path = std::env::temp_dir().join(format!(
+            "gadgets_nullifier_set_test_{}.bin",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        let nullifiers: Vec<Fr> = (0..4).map(|_| Fr::rand(rng)).collect();
+        let mut memory = NullifierSet::new();
+        let mut file = NullifierSet::open(&path).unwrap();
+        for set in [&mut memory, &mut file].iter_mut() {
+            for nullifier in &nullifiers {
+                assert!(set.insert(*nullifier).unwrap());
+                assert!(!set.insert(*nullifier).unwrap());
+            }
+            assert_eq!(set.len(), 4);
+        }
+        drop(file);
+
+        let mut file = NullifierSet::<Fr>::open(&path).unwrap();
+        assert_eq!(file.len(), 4);
+        assert!(nullifiers.iter().all(|n| file.contains(n)));
+        assert!(!file.insert(nullifiers[2]).unwrap());
+        assert!(file.insert(Fr::from(5u8)).unwrap());
+        drop(file);
+        assert!(NullifierSet::<Fr>::open(&path)
+            .unwrap()
+            .contains(&Fr::from(5u8)));
+
+        // a torn last entry, another file
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(NullifierSet::<Fr>::open(&path).is_err());
+        fs::write(&path, b"ARKT\x01").unwrap();
+        assert!(matches!(
+            NullifierSet::<Fr>::open(&path),
+            Err(NullifierError::InvalidHeader)
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+}
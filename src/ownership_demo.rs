@@ -0,0 +1,194 @@
+use ark_bls12_381::Fr;
+use ark_ed_on_bls12_381::{EdwardsAffine, Fr as JubjubScalar};
+use ark_ff::Zero;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::poseidon::PoseidonParameters;
+
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+use crate::merkle_gadget::{MerklePath, MerklePathVar, MerkleTree};
+use crate::schnorr_gadget::{generator_mul_var, secret_key_var};
+
+// Token ownership: a registry commits to who owns which token as the root of a Merkle
+// tree (`merkle_gadget`) whose leaves are the hashes of (owner key, token id), and the
+// owner of a token proves that the token is in the registry under a key they hold,
+// without revealing the key or where in the registry the token is. The circuit derives
+// the public key from the private secret key with `generator_mul_var` of
+// `schnorr_gadget`, hashes it with the token id into the leaf and recomputes the root
+// from the private path. The proof is bound to a public challenge the verifier picks,
+// so it can't be replayed to another verifier; Groth16 ties every public input to the
+// proof even if no other constraint uses it.
+
+pub fn leaf(params: &PoseidonParameters<Fr>, owner: &EdwardsAffine, token_id: Fr) -> Fr {
+    hash_inputs(params, &[owner.x, owner.y, token_id])
+}
+
+// the registry of (owner, token id) entries, None if there are more than 2^height
+pub fn registry(
+    params: &PoseidonParameters<Fr>,
+    height: usize,
+    entries: &[(EdwardsAffine, Fr)],
+) -> Option<MerkleTree<Fr>> {
+    let leaves: Vec<Fr> = entries
+        .iter()
+        .map(|(owner, token_id)| leaf(params, owner, *token_id))
+        .collect();
+    MerkleTree::new(params.clone(), height, &leaves)
+}
+
+#[derive(Clone)]
+pub struct OwnershipCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub height: usize,
+    pub root: Fr,
+    pub token_id: Fr,
+    pub challenge: Fr,
+    pub secret_key: Option<JubjubScalar>,
+    pub path: Option<MerklePath<Fr>>,
+}
+
+impl OwnershipCircuit {
+    // the owner of the token at `index` of `registry`, None if there is no such entry
+    pub fn new(
+        params: PoseidonParameters<Fr>,
+        registry: &MerkleTree<Fr>,
+        index: usize,
+        token_id: Fr,
+        challenge: Fr,
+        secret_key: JubjubScalar,
+    ) -> Option<Self> {
+        Some(Self {
+            params,
+            height: registry.height(),
+            root: registry.root(),
+            token_id,
+            challenge,
+            secret_key: Some(secret_key),
+            path: Some(registry.path(index)?),
+        })
+    }
+
+    // the root, the token and the challenge are public inputs, any values will do for
+    // the setup
+    pub fn blank(params: PoseidonParameters<Fr>, height: usize) -> Self {
+        Self {
+            params,
+            height,
+            root: Fr::zero(),
+            token_id: Fr::zero(),
+            challenge: Fr::zero(),
+            secret_key: None,
+            path: None,
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        vec![self.root, self.token_id, self.challenge]
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for OwnershipCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let root = FpVar::new_input(ns!(cs, "root"), || Ok(self.root))?;
+        let token_id = FpVar::new_input(ns!(cs, "token_id"), || Ok(self.token_id))?;
+        let _ = FpVar::new_input(ns!(cs, "challenge"), || Ok(self.challenge))?;
+
+        let secret_key = secret_key_var(cs.clone(), self.secret_key)?;
+        let owner = generator_mul_var(&secret_key)?;
+
+        let leaf = hash_inputs_var(cs.clone(), &self.params, &[owner.x, owner.y, token_id])?;
+        let path = MerklePathVar::new_witness(cs.clone(), self.height, self.path.as_ref())?;
+        path.root(cs, &self.params, &leaf)?.enforce_equal(&root)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use crate::schnorr_gadget::keygen;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    fn is_satisfied(circuit: OwnershipCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_ownership() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (alice_sk, alice) = keygen(rng);
+        let (_, bob) = keygen(rng);
+        let entries = [
+            (alice, Fr::from(1u8)),
+            (bob, Fr::from(2u8)),
+            (alice, Fr::from(3u8)),
+        ];
+        let registry = registry(&params, 3, &entries).unwrap();
+
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            OwnershipCircuit::blank(params.clone(), 3),
+            rng,
+        )
+        .unwrap();
+        let challenge = Fr::from(1234u16);
+        let circuit = OwnershipCircuit::new(
+            params.clone(),
+            &registry,
+            2,
+            Fr::from(3u8),
+            challenge,
+            alice_sk,
+        )
+        .unwrap();
+        let inputs = circuit.public_inputs();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        // another token, another challenge
+        for i in 1..3 {
+            let mut other = inputs.clone();
+            other[i] += Fr::from(1u8);
+            assert!(!Groth16::<Bls12_381>::verify(&vk, &other, &proof).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_not_the_owner() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (alice_sk, alice) = keygen(rng);
+        let (bob_sk, bob) = keygen(rng);
+        let entries = [(alice, Fr::from(1u8)), (bob, Fr::from(2u8))];
+        let registry = registry(&params, 2, &entries).unwrap();
+        let circuit = |index, token_id: u8, sk| {
+            OwnershipCircuit::new(
+                params.clone(),
+                &registry,
+                index,
+                token_id.into(),
+                Fr::zero(),
+                sk,
+            )
+            .unwrap()
+        };
+
+        assert!(is_satisfied(circuit(0, 1, alice_sk)));
+        assert!(is_satisfied(circuit(1, 2, bob_sk)));
+        // the token of bob with the key of alice, at the entry of bob or of alice
+        assert!(!is_satisfied(circuit(1, 2, alice_sk)));
+        assert!(!is_satisfied(circuit(0, 2, alice_sk)));
+        // a token that is not in the registry
+        assert!(!is_satisfied(circuit(0, 3, alice_sk)));
+        // an empty leaf
+        assert!(!is_satisfied(circuit(3, 0, alice_sk)));
+    }
+}
@@ -0,0 +1,356 @@
+use ark_ff::{BigInteger, FpParameters, PrimeField};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{Namespace, SynthesisError};
+
+// Small public values packed into few public inputs. Every public input costs the
+// verifier a scalar multiplication (and gas, in a Solidity verifier), so a circuit with a
+// handful of flags and u32 amounts is cheaper to verify with them packed than with one
+// input each. A `Layout` lists the widths of the values in order; they go into inputs
+// of up to the capacity of the field, least significant bits first, a value never split
+// between two inputs:
+//   Layout::pack / unpack   the inputs for `verify` from the values and back, `unpack`
+//                           refusing inputs with bits outside the values
+//   Layout::pack_var        the same packing of values already in the circuit
+//   Layout::new_variable    the inputs allocated, and the bits of each value as
+//                           witnesses summing to them: the range check of a value is
+//                           the booleanity of its bits, one constraint per input ties
+//                           them to it
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Layout {
+    widths: Vec<usize>,
+}
+
+impl Layout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bits(mut self, width: usize) -> Self {
+        assert!(width > 0 && width <= 64, "values are at most 64 bits");
+        self.widths.push(width);
+        self
+    }
+
+    pub fn bool(self) -> Self {
+        self.bits(1)
+    }
+
+    pub fn u32(self) -> Self {
+        self.bits(32)
+    }
+
+    pub fn widths(&self) -> &[usize] {
+        &self.widths
+    }
+
+    pub fn len(&self) -> usize {
+        self.widths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.widths.is_empty()
+    }
+
+    // the input of each value and its offset in it
+    fn positions<F: PrimeField>(&self) -> Vec<(usize, usize)> {
+        let capacity = F::Params::CAPACITY as usize;
+        let mut input = 0;
+        let mut offset = 0;
+        let mut positions = Vec::with_capacity(self.widths.len());
+        for &width in &self.widths {
+            if offset + width > capacity {
+                input += 1;
+                offset = 0;
+            }
+            positions.push((input, offset));
+            offset += width;
+        }
+        positions
+    }
+
+    pub fn num_inputs<F: PrimeField>(&self) -> usize {
+        self.positions::<F>()
+            .last()
+            .map_or(0, |&(input, _)| input + 1)
+    }
+
+    // the values in order; panics on a value wider than its place in the layout
+    pub fn pack<F: PrimeField>(&self, values: &[u64]) -> Vec<F> {
+        assert_eq!(
+            values.len(),
+            self.len(),
+            "one value per place in the layout"
+        );
+        let mut inputs = vec![F::zero(); self.num_inputs::<F>()];
+        let positions = self.positions::<F>();
+        for ((&value, &width), &(input, offset)) in values.iter().zip(&self.widths).zip(&positions)
+        {
+            assert!(
+                width == 64 || value >> width == 0,
+                "{} does not fit in {} bits",
+                value,
+                width
+            );
+            inputs[input] += F::from(value) * F::from(2u8).pow([offset as u64]);
+        }
+        inputs
+    }
+
+    // None if there are other bits set, or a different number of inputs
+    pub fn unpack<F: PrimeField>(&self, inputs: &[F]) -> Option<Vec<u64>> {
+        if inputs.len() != self.num_inputs::<F>() {
+            return None;
+        }
+        let mut bits: Vec<Vec<bool>> = inputs.iter().map(|x| x.into_repr().to_bits_le()).collect();
+        let mut values = Vec::with_capacity(self.len());
+        for (&width, &(input, offset)) in self.widths.iter().zip(&self.positions::<F>()) {
+            let value_bits = &mut bits[input][offset..offset + width];
+            let value = value_bits
+                .iter()
+                .rev()
+                .fold(0u64, |value, &bit| value << 1 | bit as u64);
+            value_bits.iter_mut().for_each(|bit| *bit = false);
+            values.push(value);
+        }
+        bits.iter().flatten().all(|&bit| !bit).then_some(values)
+    }
+
+    // the bits of each value, least significant first, packed into the inputs
+    pub fn pack_var<F: PrimeField>(
+        &self,
+        values: &[Vec<Boolean<F>>],
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        assert_eq!(
+            values.len(),
+            self.len(),
+            "one value per place in the layout"
+        );
+        let mut inputs = vec![Vec::new(); self.num_inputs::<F>()];
+        for ((bits, &width), &(input, _)) in
+            values.iter().zip(&self.widths).zip(&self.positions::<F>())
+        {
+            assert_eq!(bits.len(), width, "a value of the wrong width");
+            inputs[input].extend_from_slice(bits);
+        }
+        inputs
+            .iter()
+            .map(|bits| Boolean::le_bits_to_fp_var(bits))
+            .collect()
+    }
+
+    // the inputs as public inputs with `AllocationMode::Input`, the packed witnesses
+    // otherwise
+    pub fn new_variable<F, T>(
+        &self,
+        cs: impl Into<Namespace<F>>,
+        values: T,
+        mode: AllocationMode,
+    ) -> Result<PackedVar<F>, SynthesisError>
+    where
+        F: PrimeField,
+        T: FnOnce() -> Result<Vec<u64>, SynthesisError>,
+    {
+        let cs = cs.into().cs();
+        let values = if cs.is_in_setup_mode() && mode != AllocationMode::Constant {
+            Err(SynthesisError::AssignmentMissing)
+        } else {
+            values()
+        };
+        let bit_mode = match mode {
+            AllocationMode::Constant => AllocationMode::Constant,
+            _ => AllocationMode::Witness,
+        };
+        let mut bits = Vec::with_capacity(self.len());
+        for (i, &width) in self.widths.iter().enumerate() {
+            let value = values.as_ref().map(|values| values[i]).map_err(|e| *e);
+            bits.push(
+                (0..width)
+                    .map(|j| {
+                        Boolean::new_variable(
+                            cs.clone(),
+                            || value.map(|v| v >> j & 1 == 1),
+                            bit_mode,
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            );
+        }
+        let packed = self.pack_var(&bits)?;
+        let inputs = match mode {
+            AllocationMode::Input => {
+                let inputs = values.map(|values| self.pack::<F>(&values));
+                let vars = (0..packed.len())
+                    .map(|i| {
+                        FpVar::new_input(cs.clone(), || {
+                            inputs.as_ref().map(|inputs| inputs[i]).map_err(|e| *e)
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                vars.enforce_equal(&packed)?;
+                vars
+            }
+            _ => packed,
+        };
+        Ok(PackedVar { inputs, bits })
+    }
+}
+
+pub struct PackedVar<F: PrimeField> {
+    pub inputs: Vec<FpVar<F>>,
+    bits: Vec<Vec<Boolean<F>>>,
+}
+
+impl<F: PrimeField> PackedVar<F> {
+    pub fn bits(&self, i: usize) -> &[Boolean<F>] {
+        &self.bits[i]
+    }
+
+    pub fn boolean(&self, i: usize) -> Boolean<F> {
+        assert_eq!(self.bits[i].len(), 1, "not a bool");
+        self.bits[i][0].clone()
+    }
+
+    pub fn uint32(&self, i: usize) -> UInt32<F> {
+        assert_eq!(self.bits[i].len(), 32, "not a u32");
+        UInt32::from_bits_le(&self.bits[i])
+    }
+
+    // the value as a field element, a sum of its bits
+    pub fn fp_var(&self, i: usize) -> Result<FpVar<F>, SynthesisError> {
+        Boolean::le_bits_to_fp_var(&self.bits[i])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_ff::Field;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::{
+        ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisMode,
+    };
+    use ark_snark::SNARK;
+
+    // three flags and eight u32s, 8 * 32 + 3 bits over two inputs of up to 254
+    fn layout() -> Layout {
+        (0..8).fold(Layout::new().bool().bool().bool(), |layout, _| layout.u32())
+    }
+
+    fn values() -> Vec<u64> {
+        let mut values = vec![1, 0, 1];
+        values.extend((0..8).map(|i| u32::MAX as u64 - i * 1000));
+        values
+    }
+
+    #[test]
+    fn test_pack() {
+        let layout = layout();
+        assert_eq!(layout.num_inputs::<Fr>(), 2);
+        // seven u32s after the flags, the last one in the next input
+        assert_eq!(layout.positions::<Fr>()[9], (0, 3 + 6 * 32));
+        assert_eq!(layout.positions::<Fr>()[10], (1, 0));
+        let inputs = layout.pack::<Fr>(&values());
+        assert_eq!(inputs[1], Fr::from(u32::MAX as u64 - 7000));
+        assert_eq!(layout.unpack(&inputs), Some(values()));
+
+        // a bit between the values, or past them
+        let mut other = inputs.clone();
+        other[1] += Fr::from(1u64 << 32);
+        assert_eq!(layout.unpack(&other), None);
+        assert_eq!(layout.unpack(&inputs[..1]), None);
+        assert_eq!(Layout::new().num_inputs::<Fr>(), 0);
+    }
+
+    #[derive(Clone)]
+    struct FlagsCircuit {
+        values: Option<Vec<u64>>,
+    }
+
+    // the sum of the u32s that have their flag set, the first three
+    impl ConstraintSynthesizer<Fr> for FlagsCircuit {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            let layout = layout();
+            let values = self.values;
+            let packed = layout.new_variable(
+                cs.clone(),
+                || values.ok_or(SynthesisError::AssignmentMissing),
+                AllocationMode::Input,
+            )?;
+            let mut sum = FpVar::zero();
+            for i in 0..3 {
+                let amount = packed.fp_var(3 + i)?;
+                sum += packed.boolean(i).select(&amount, &FpVar::zero())?;
+            }
+            packed.fp_var(10)?.enforce_equal(&sum)
+        }
+    }
+
+    #[test]
+    fn test_circuit() {
+        // flags 1, 0, 1 and the sum of the first and third amount in the last
+        let mut values = values();
+        values[3] = 1000;
+        values[5] = 2000;
+        values[10] = 3000;
+        let cs = ConstraintSystem::new_ref();
+        FlagsCircuit {
+            values: Some(values.clone()),
+        }
+        .generate_constraints(cs.clone())
+        .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        // the two packed inputs
+        assert_eq!(cs.num_instance_variables(), 1 + 2);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let packed = layout()
+            .new_variable(cs, || Ok(values.clone()), AllocationMode::Witness)
+            .unwrap();
+        assert_eq!(packed.uint32(10).value().unwrap(), 3000);
+        assert!(packed.boolean(0).value().unwrap());
+        assert_eq!(packed.bits(1).len(), 1);
+
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(FlagsCircuit { values: None }, rng)
+                .unwrap();
+        let circuit = FlagsCircuit {
+            values: Some(values.clone()),
+        };
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        let inputs = layout().pack::<Fr>(&values);
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+        let mut wrong = values;
+        wrong[1] = 1;
+        let inputs = layout().pack::<Fr>(&wrong);
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+    }
+
+    #[test]
+    fn test_range_checks() {
+        // a bit past the values of the first input, no assignment of the bits makes it
+        let mut inputs = layout().pack::<Fr>(&values());
+        inputs[0] += Fr::from(2u8).pow([240]);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let packed = layout()
+            .new_variable(cs.clone(), || Ok(values()), AllocationMode::Witness)
+            .unwrap();
+        let expected = Vec::<FpVar<Fr>>::new_input(cs.clone(), || Ok(inputs)).unwrap();
+        packed.inputs.enforce_equal(&expected).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // no values in setup
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        layout()
+            .new_variable(
+                cs.clone(),
+                || Err(SynthesisError::AssignmentMissing),
+                AllocationMode::Input,
+            )
+            .unwrap();
+        assert_eq!(cs.num_instance_variables(), 1 + 2);
+    }
+}
@@ -0,0 +1,106 @@
+use ark_ff::Field;
+use ark_relations::{
+    lc,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError, Variable},
+};
+
+// padding of a constraint system up to a fixed shape, e.g. to index several circuits with
+// the same dimensions or to meet the minimum size of a backend (Marlin's indexer needs
+// at least two constraints). Padding variables are zero valued witnesses and padding
+// constraints are 1 * 0 = 0, so they are satisfied by any assignment and do not change
+// what the circuit proves.
+
+// adds witnesses until the system has `num_variables` variables (the constant one and
+// the public inputs included) and constraints until it has `num_constraints`. A system
+// that is already larger is left as is.
+pub fn pad_to<F: Field>(
+    cs: &ConstraintSystemRef<F>,
+    num_constraints: usize,
+    num_variables: usize,
+) -> Result<(), SynthesisError> {
+    while cs.num_instance_variables() + cs.num_witness_variables() < num_variables {
+        cs.new_witness_variable(|| Ok(F::zero()))?;
+    }
+    while cs.num_constraints() < num_constraints {
+        cs.enforce_constraint(lc!() + Variable::One, lc!(), lc!())?;
+    }
+    Ok(())
+}
+
+// any circuit, padded after synthesis
+#[derive(Clone, Copy, Debug)]
+pub struct Padded<C> {
+    pub circuit: C,
+    pub num_constraints: usize,
+    pub num_variables: usize,
+}
+
+impl<C> Padded<C> {
+    pub fn new(circuit: C, num_constraints: usize, num_variables: usize) -> Self {
+        Self {
+            circuit,
+            num_constraints,
+            num_variables,
+        }
+    }
+}
+
+impl<F: Field, C: ConstraintSynthesizer<F>> ConstraintSynthesizer<F> for Padded<C> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        self.circuit.generate_constraints(cs.clone())?;
+        pad_to(&cs, self.num_constraints, self.num_variables)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::CubicDemoCircuit;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    #[test]
+    fn test_pad_to() {
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        let circuit = CubicDemoCircuit {
+            x: Some(BlsFr::from(3u8)),
+        };
+        Padded::new(circuit, 10, 8)
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(cs.num_constraints(), 10);
+        assert_eq!(cs.num_instance_variables(), 2);
+        assert_eq!(cs.num_witness_variables(), 6);
+
+        // smaller targets are a no-op
+        pad_to(&cs, 4, 4).unwrap();
+        assert_eq!(cs.num_constraints(), 10);
+        assert_eq!(cs.num_witness_variables(), 6);
+    }
+
+    #[test]
+    fn test_padded_groth16() {
+        let rng = &mut ark_std::test_rng();
+        let (a, b) = (BlsFr::from(3u8), BlsFr::from(11u8));
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            Padded::new(MultiplyDemoCircuit::<BlsFr> { a: None, b: None }, 16, 16),
+            rng,
+        )
+        .unwrap();
+        let circuit = Padded::new(
+            MultiplyDemoCircuit {
+                a: Some(a),
+                b: Some(b),
+            },
+            16,
+            16,
+        );
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &[a * b], &proof).unwrap());
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &[a], &proof).unwrap());
+    }
+}
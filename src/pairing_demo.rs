@@ -0,0 +1,299 @@
+use ark_ec::{
+    short_weierstrass_jacobian::GroupAffine, AffineCurve, PairingEngine, ProjectiveCurve,
+    SWModelParameters,
+};
+use ark_ff::{One, ToConstraintField, Zero};
+use ark_mnt4_298::{
+    constraints::{G1Var, G2Var, PairingVar as Mnt4PairingVar},
+    Fr as Mnt4Fr, G1Affine, G2Affine, MNT4_298,
+};
+use ark_r1cs_std::{pairing::PairingVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{
+        ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError, SynthesisMode,
+    },
+};
+use std::fmt;
+
+use crate::cycles::{self, Mnt4Mnt6, TwoChain};
+
+// pairings in a circuit, the core of the in-circuit Groth16 verifier of
+// `verifier_gadget_demo` without the rest of it. The circuit, over the scalar field of
+// MNT6-298, checks a BLS signature on MNT4-298: a public key pk = sk g2 in G2, a message
+// point H in G1 and a signature sig = sk H, with
+//   e(sig, g2) == e(H, pk),  or as one product  e(sig, g2) e(-H, pk) == 1
+// pk and H are public inputs, the signature a witness: the proof shows a valid
+// signature exists without showing it. H is any point here, a real scheme hashes the
+// message to it.
+//
+// A pairing is a Miller loop and a final exponentiation. `PairingCheck::Separate`
+// computes both pairings and compares them, `PairingCheck::Product` multiplies the
+// Miller loops and exponentiates once, the way the Groth16 gadget checks its three
+// pairings. `pairing_cost` counts each step.
+
+pub type OuterFr = cycles::OuterFr<Mnt4Mnt6>;
+type GtVar = <<Mnt4Mnt6 as TwoChain>::InnerPairingVar as PairingVar<MNT4_298, OuterFr>>::GTVar;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PairingCheck {
+    Separate,
+    Product,
+}
+
+pub fn public_key(sk: Mnt4Fr) -> G2Affine {
+    G2Affine::prime_subgroup_generator().mul(sk).into_affine()
+}
+
+pub fn sign(sk: Mnt4Fr, message: G1Affine) -> G1Affine {
+    message.mul(sk).into_affine()
+}
+
+pub fn verify(public_key: G2Affine, message: G1Affine, signature: G1Affine) -> bool {
+    let g2 = G2Affine::prime_subgroup_generator();
+    MNT4_298::product_of_pairings(&[
+        (signature.into(), g2.into()),
+        ((-message).into(), public_key.into()),
+    ])
+    .is_one()
+}
+
+#[derive(Clone)]
+pub struct SignatureCircuit {
+    pub public_key: G2Affine,
+    pub message: G1Affine,
+    pub signature: Option<G1Affine>,
+    pub check: PairingCheck,
+}
+
+// the public inputs of a point as `new_input` lays them out: x, y and z of its
+// projective form, z = 1 unless it is the point at infinity
+fn point_inputs<P>(point: &GroupAffine<P>) -> Vec<OuterFr>
+where
+    P: SWModelParameters,
+    P::BaseField: ToConstraintField<OuterFr>,
+{
+    let (x, y, z) = if point.is_zero() {
+        (
+            P::BaseField::zero(),
+            P::BaseField::one(),
+            P::BaseField::zero(),
+        )
+    } else {
+        (point.x, point.y, P::BaseField::one())
+    };
+    [x, y, z]
+        .iter()
+        .flat_map(|c| c.to_field_elements().unwrap())
+        .collect()
+}
+
+impl SignatureCircuit {
+    pub fn new(public_key: G2Affine, message: G1Affine, signature: G1Affine) -> Self {
+        Self {
+            public_key,
+            message,
+            signature: Some(signature),
+            check: PairingCheck::Product,
+        }
+    }
+
+    pub fn blank(check: PairingCheck) -> Self {
+        Self {
+            public_key: G2Affine::prime_subgroup_generator(),
+            message: G1Affine::prime_subgroup_generator(),
+            signature: None,
+            check,
+        }
+    }
+
+    pub fn with_check(mut self, check: PairingCheck) -> Self {
+        self.check = check;
+        self
+    }
+
+    // pk then H
+    pub fn public_inputs(&self) -> Vec<OuterFr> {
+        let mut inputs = point_inputs(&self.public_key);
+        inputs.extend(point_inputs(&self.message));
+        inputs
+    }
+
+    fn synthesize(self, cs: ConstraintSystemRef<OuterFr>) -> Result<PairingCost, SynthesisError> {
+        let mut counted = 0;
+        let mut count = || {
+            let step = cs.num_constraints() - counted;
+            counted = cs.num_constraints();
+            step
+        };
+
+        let pk = G2Var::new_input(ns!(cs, "pk"), || Ok(self.public_key.into_projective()))?;
+        let message = G1Var::new_input(ns!(cs, "message"), || Ok(self.message.into_projective()))?;
+        let signature = G1Var::new_witness(ns!(cs, "signature"), || {
+            self.signature
+                .map(|s| s.into_projective())
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let g2 = G2Var::new_constant(
+            ns!(cs, "g2"),
+            G2Affine::prime_subgroup_generator().into_projective(),
+        )?;
+        let allocate = count();
+
+        let p = [
+            Mnt4PairingVar::prepare_g1(&signature)?,
+            Mnt4PairingVar::prepare_g1(&message.negate()?)?,
+        ];
+        let q = [
+            Mnt4PairingVar::prepare_g2(&g2)?,
+            Mnt4PairingVar::prepare_g2(&pk)?,
+        ];
+        let prepare = count();
+
+        let (miller_loop, final_exponentiation) = match self.check {
+            PairingCheck::Product => {
+                let f = Mnt4PairingVar::miller_loop(&p, &q)?;
+                let miller_loop = count();
+                Mnt4PairingVar::final_exponentiation(&f)?.enforce_equal(&GtVar::one())?;
+                (miller_loop, count())
+            }
+            PairingCheck::Separate => {
+                let f = Mnt4PairingVar::miller_loop(&p[..1], &q[..1])?;
+                let g = Mnt4PairingVar::miller_loop(&p[1..], &q[1..])?;
+                let miller_loop = count();
+                // e(-H, pk) is the inverse of e(H, pk)
+                let left = Mnt4PairingVar::final_exponentiation(&f)?;
+                let right = Mnt4PairingVar::final_exponentiation(&g)?;
+                (left * right).enforce_equal(&GtVar::one())?;
+                (miller_loop, count())
+            }
+        };
+        Ok(PairingCost {
+            allocate,
+            prepare,
+            miller_loop,
+            final_exponentiation,
+        })
+    }
+}
+
+impl ConstraintSynthesizer<OuterFr> for SignatureCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<OuterFr>) -> Result<(), SynthesisError> {
+        self.synthesize(cs).map(|_| ())
+    }
+}
+
+// constraints of each step, the comparison in GT counted with the final exponentiation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PairingCost {
+    pub allocate: usize,
+    pub prepare: usize,
+    pub miller_loop: usize,
+    pub final_exponentiation: usize,
+}
+
+impl PairingCost {
+    pub fn total(&self) -> usize {
+        self.allocate + self.prepare + self.miller_loop + self.final_exponentiation
+    }
+}
+
+impl fmt::Display for PairingCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "allocate             {:>8}", self.allocate)?;
+        writeln!(f, "prepare              {:>8}", self.prepare)?;
+        writeln!(f, "miller loop          {:>8}", self.miller_loop)?;
+        writeln!(f, "final exponentiation {:>8}", self.final_exponentiation)?;
+        writeln!(f, "total                {:>8}", self.total())
+    }
+}
+
+// synthesized in setup mode, no signature needed
+pub fn pairing_cost(check: PairingCheck) -> Result<PairingCost, SynthesisError> {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_mode(SynthesisMode::Setup);
+    SignatureCircuit::blank(check).synthesize(cs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_groth16::Groth16;
+    use ark_mnt6_298::MNT6_298;
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    fn is_satisfied(circuit: SignatureCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    fn signed() -> SignatureCircuit {
+        let rng = &mut ark_std::test_rng();
+        let sk = Mnt4Fr::rand(rng);
+        let message = G1Affine::prime_subgroup_generator()
+            .mul(Mnt4Fr::rand(rng))
+            .into_affine();
+        SignatureCircuit::new(public_key(sk), message, sign(sk, message))
+    }
+
+    #[test]
+    fn test_signature_check() {
+        let circuit = signed();
+        let signature = circuit.signature.unwrap();
+        assert!(verify(circuit.public_key, circuit.message, signature));
+
+        for &check in [PairingCheck::Product, PairingCheck::Separate].iter() {
+            let circuit = circuit.clone().with_check(check);
+            assert!(is_satisfied(circuit.clone()));
+
+            // another message, a signature of another key
+            let mut wrong = circuit.clone();
+            wrong.message = circuit.message + circuit.message;
+            assert!(!is_satisfied(wrong));
+            let mut wrong = circuit.clone();
+            wrong.signature = Some(sign(Mnt4Fr::from(7u8), circuit.message));
+            assert!(!is_satisfied(wrong));
+        }
+
+        // the public inputs are laid out as `public_inputs` says
+        let cs = ConstraintSystem::new_ref();
+        let inputs = circuit.public_inputs();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        let cs = cs.into_inner().unwrap();
+        assert_eq!(cs.instance_assignment[1..], inputs[..]);
+    }
+
+    #[test]
+    fn test_cost() {
+        let product = pairing_cost(PairingCheck::Product).unwrap();
+        let separate = pairing_cost(PairingCheck::Separate).unwrap();
+        // the same points, prepared the same way
+        assert_eq!(product.allocate, separate.allocate);
+        assert_eq!(product.prepare, separate.prepare);
+        // one final exponentiation instead of two, most of a pairing
+        assert!(separate.final_exponentiation > product.final_exponentiation * 3 / 2);
+        assert!(product.total() < separate.total());
+        assert!(product.to_string().contains("miller loop"));
+    }
+
+    // cargo test --release signature_proof -- --ignored, the proof on MNT6
+    #[test]
+    #[ignore]
+    fn test_signature_proof() {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) = Groth16::<MNT6_298>::circuit_specific_setup(
+            SignatureCircuit::blank(PairingCheck::Product),
+            rng,
+        )
+        .unwrap();
+        let circuit = signed();
+        let inputs = circuit.public_inputs();
+        let proof = Groth16::<MNT6_298>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<MNT6_298>::verify(&vk, &inputs, &proof).unwrap());
+        let mut other = inputs;
+        other[0] += OuterFr::one();
+        assert!(!Groth16::<MNT6_298>::verify(&vk, &other, &proof).unwrap());
+    }
+}
@@ -0,0 +1,619 @@
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::fmt;
+use ark_std::io::{self, Read, Write};
+#[cfg(feature = "std")]
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter},
+    path::Path,
+};
+
+// helpers to persist keys and proofs. Every artifact is written inside a small envelope
+//   magic (4 bytes) | version (1 byte) | kind (1 byte) | mode (1 byte) | payload
+// so the loader knows which encoding was used and refuses to load a proof as a key.
+// A version 2 envelope has the 32 byte fingerprint of the circuit the artifact was made
+// for (`fingerprint::circuit_fingerprint`) between the mode and the payload, so a key can
+// be refused for a circuit that changed since its setup; readers that don't ask for it
+// skip it.
+// Reading and writing envelopes works without std, only `save` and `load` need files.
+// With the `zstd` feature `save_zstd` writes the whole envelope as a zstd frame, and
+// `load` (`open`) recognizes such files by the frame magic and decompresses on the fly.
+
+pub const MAGIC: [u8; 4] = *b"ARKW";
+pub const VERSION: u8 = 1;
+pub const VERSION_WITH_CIRCUIT: u8 = 2;
+// the start of a zstd frame, never of an envelope
+pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArtifactKind {
+    ProvingKey,
+    VerifyingKey,
+    Proof,
+    // universal (Marlin) SRS
+    UniversalSrs,
+}
+
+impl ArtifactKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            ArtifactKind::ProvingKey => 0,
+            ArtifactKind::VerifyingKey => 1,
+            ArtifactKind::Proof => 2,
+            ArtifactKind::UniversalSrs => 3,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, PersistError> {
+        match byte {
+            0 => Ok(ArtifactKind::ProvingKey),
+            1 => Ok(ArtifactKind::VerifyingKey),
+            2 => Ok(ArtifactKind::Proof),
+            3 => Ok(ArtifactKind::UniversalSrs),
+            _ => Err(PersistError::InvalidHeader),
+        }
+    }
+}
+
+// compressed points store x plus a sign flag and are recovered with a square root on
+// load, uncompressed points store (x, y): roughly twice the size, but faster to load
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerializationMode {
+    Compressed,
+    Uncompressed,
+}
+
+impl SerializationMode {
+    fn to_byte(self) -> u8 {
+        match self {
+            SerializationMode::Compressed => 0,
+            SerializationMode::Uncompressed => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, PersistError> {
+        match byte {
+            0 => Ok(SerializationMode::Compressed),
+            1 => Ok(SerializationMode::Uncompressed),
+            _ => Err(PersistError::InvalidHeader),
+        }
+    }
+}
+
+// Checked runs the on-curve and prime-order subgroup checks for every point.
+// Unchecked skips them and must only be used for artifacts from a trusted source
+// (e.g. a key this process wrote itself). ark-serialize 0.3 always checks compressed
+// points, so Unchecked is only available for uncompressed artifacts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Validation {
+    Checked,
+    Unchecked,
+}
+
+#[derive(Debug)]
+pub enum PersistError {
+    Io(io::Error),
+    Serialization(SerializationError),
+    InvalidHeader,
+    UnsupportedVersion(u8),
+    WrongKind {
+        expected: ArtifactKind,
+        found: ArtifactKind,
+    },
+    UnsupportedMode(SerializationMode, Validation),
+    // a zstd file and a build without the `zstd` feature
+    UnsupportedCompression,
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PersistError::Io(e) => write!(f, "i/o error: {}", e),
+            PersistError::Serialization(e) => write!(f, "serialization error: {}", e),
+            PersistError::InvalidHeader => write!(f, "invalid artifact header"),
+            PersistError::UnsupportedVersion(v) => write!(f, "unsupported envelope version {}", v),
+            PersistError::WrongKind { expected, found } => {
+                write!(f, "expected {:?}, found {:?}", expected, found)
+            }
+            PersistError::UnsupportedMode(mode, validation) => {
+                write!(
+                    f,
+                    "{:?} loading is not supported for {:?} data",
+                    validation, mode
+                )
+            }
+            PersistError::UnsupportedCompression => {
+                write!(f, "zstd compressed artifact, needs the `zstd` feature")
+            }
+        }
+    }
+}
+
+impl ark_std::error::Error for PersistError {}
+
+impl From<io::Error> for PersistError {
+    fn from(e: io::Error) -> Self {
+        PersistError::Io(e)
+    }
+}
+
+impl From<SerializationError> for PersistError {
+    fn from(e: SerializationError) -> Self {
+        PersistError::Serialization(e)
+    }
+}
+
+// raw (envelope-less) encoding
+pub fn serialize_with_mode<T: CanonicalSerialize, W: Write>(
+    artifact: &T,
+    mode: SerializationMode,
+    writer: W,
+) -> Result<(), PersistError> {
+    match mode {
+        SerializationMode::Compressed => artifact.serialize(writer)?,
+        SerializationMode::Uncompressed => artifact.serialize_uncompressed(writer)?,
+    }
+    Ok(())
+}
+
+pub fn deserialize_with_mode<T: CanonicalDeserialize, R: Read>(
+    reader: R,
+    mode: SerializationMode,
+    validation: Validation,
+) -> Result<T, PersistError> {
+    let artifact = match (mode, validation) {
+        (SerializationMode::Compressed, Validation::Checked) => T::deserialize(reader)?,
+        (SerializationMode::Uncompressed, Validation::Checked) => {
+            T::deserialize_uncompressed(reader)?
+        }
+        (SerializationMode::Uncompressed, Validation::Unchecked) => {
+            T::deserialize_unchecked(reader)?
+        }
+        (SerializationMode::Compressed, Validation::Unchecked) => {
+            return Err(PersistError::UnsupportedMode(mode, validation))
+        }
+    };
+    Ok(artifact)
+}
+
+pub fn write_artifact<T: CanonicalSerialize, W: Write>(
+    artifact: &T,
+    kind: ArtifactKind,
+    mode: SerializationMode,
+    mut writer: W,
+) -> Result<(), PersistError> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&[VERSION, kind.to_byte(), mode.to_byte()])?;
+    serialize_with_mode(artifact, mode, &mut writer)
+}
+
+// a version 2 envelope, `circuit` is the fingerprint of the circuit `artifact` was made for
+pub fn write_artifact_for_circuit<T: CanonicalSerialize, W: Write>(
+    artifact: &T,
+    kind: ArtifactKind,
+    mode: SerializationMode,
+    circuit: [u8; 32],
+    mut writer: W,
+) -> Result<(), PersistError> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&[VERSION_WITH_CIRCUIT, kind.to_byte(), mode.to_byte()])?;
+    writer.write_all(&circuit)?;
+    serialize_with_mode(artifact, mode, &mut writer)
+}
+
+// the serialization mode is taken from the envelope, only the validation is up to the caller
+pub fn read_artifact<T: CanonicalDeserialize, R: Read>(
+    mut reader: R,
+    kind: ArtifactKind,
+    validation: Validation,
+) -> Result<T, PersistError> {
+    let mode = read_header(&mut reader, kind)?;
+    deserialize_with_mode(reader, mode, validation)
+}
+
+// the artifact and the circuit fingerprint of its envelope, None for a version 1 envelope
+pub fn read_artifact_with_circuit<T: CanonicalDeserialize, R: Read>(
+    mut reader: R,
+    kind: ArtifactKind,
+    validation: Validation,
+) -> Result<(T, Option<[u8; 32]>), PersistError> {
+    let (mode, circuit) = read_header_with_circuit(&mut reader, kind)?;
+    Ok((deserialize_with_mode(reader, mode, validation)?, circuit))
+}
+
+// checks the envelope and returns the mode of the payload that follows, for readers that
+// take the payload apart themselves (`streaming_prover`)
+pub fn read_header<R: Read>(
+    reader: R,
+    kind: ArtifactKind,
+) -> Result<SerializationMode, PersistError> {
+    read_header_with_circuit(reader, kind).map(|(mode, _)| mode)
+}
+
+// `read_header` with the circuit fingerprint of a version 2 envelope
+pub fn read_header_with_circuit<R: Read>(
+    mut reader: R,
+    kind: ArtifactKind,
+) -> Result<(SerializationMode, Option<[u8; 32]>), PersistError> {
+    let mut header = [0u8; 7];
+    reader.read_exact(&mut header)?;
+    if header[..4] != MAGIC {
+        return Err(PersistError::InvalidHeader);
+    }
+    if header[4] != VERSION && header[4] != VERSION_WITH_CIRCUIT {
+        return Err(PersistError::UnsupportedVersion(header[4]));
+    }
+    let found = ArtifactKind::from_byte(header[5])?;
+    if found != kind {
+        return Err(PersistError::WrongKind {
+            expected: kind,
+            found,
+        });
+    }
+    let mode = SerializationMode::from_byte(header[6])?;
+    if header[4] == VERSION {
+        return Ok((mode, None));
+    }
+    let mut circuit = [0u8; 32];
+    reader.read_exact(&mut circuit)?;
+    Ok((mode, Some(circuit)))
+}
+
+#[cfg(feature = "std")]
+pub fn save<T: CanonicalSerialize, P: AsRef<Path>>(
+    path: P,
+    artifact: &T,
+    kind: ArtifactKind,
+    mode: SerializationMode,
+) -> Result<(), PersistError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_artifact(artifact, kind, mode, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+pub fn save_for_circuit<T: CanonicalSerialize, P: AsRef<Path>>(
+    path: P,
+    artifact: &T,
+    kind: ArtifactKind,
+    mode: SerializationMode,
+    circuit: [u8; 32],
+) -> Result<(), PersistError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_artifact_for_circuit(artifact, kind, mode, circuit, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+// `save` through a zstd encoder at `level` (1 to 22, zstd's default is 3). Points are close
+// to random bytes and do not compress, the gain comes from the zeros of a key: points at
+// infinity of variables a query doesn't use and the spare bits of every coordinate. On
+// a Blake2s circuit's key that is some 15%, level 3 gets nearly all of it
+// (`benches/zstd.rs`).
+#[cfg(feature = "zstd")]
+pub fn save_zstd<T: CanonicalSerialize, P: AsRef<Path>>(
+    path: P,
+    artifact: &T,
+    kind: ArtifactKind,
+    mode: SerializationMode,
+    level: i32,
+) -> Result<(), PersistError> {
+    let mut encoder = zstd::Encoder::new(BufWriter::new(File::create(path)?), level)?;
+    write_artifact(artifact, kind, mode, &mut encoder)?;
+    encoder.finish()?.flush()?;
+    Ok(())
+}
+
+// the envelope in the file at `path`, decompressed if the file is a zstd frame
+#[cfg(feature = "std")]
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Box<dyn Read>, PersistError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if !reader.fill_buf()?.starts_with(&ZSTD_MAGIC) {
+        return Ok(Box::new(reader));
+    }
+    #[cfg(feature = "zstd")]
+    return Ok(Box::new(zstd::Decoder::with_buffer(reader)?));
+    #[cfg(not(feature = "zstd"))]
+    Err(PersistError::UnsupportedCompression)
+}
+
+#[cfg(feature = "std")]
+pub fn load<T: CanonicalDeserialize, P: AsRef<Path>>(
+    path: P,
+    kind: ArtifactKind,
+    validation: Validation,
+) -> Result<T, PersistError> {
+    read_artifact(open(path)?, kind, validation)
+}
+
+#[cfg(feature = "std")]
+pub fn load_with_circuit<T: CanonicalDeserialize, P: AsRef<Path>>(
+    path: P,
+    kind: ArtifactKind,
+    validation: Validation,
+) -> Result<(T, Option<[u8; 32]>), PersistError> {
+    read_artifact_with_circuit(open(path)?, kind, validation)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+    use ark_groth16::{Groth16, Proof, ProvingKey, VerifyingKey};
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    fn multiply_artifacts() -> (
+        ProvingKey<Bls12_381>,
+        VerifyingKey<Bls12_381>,
+        Proof<Bls12_381>,
+    ) {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            MultiplyDemoCircuit::<BlsFr> { a: None, b: None },
+            rng,
+        )
+        .unwrap();
+        let circuit = MultiplyDemoCircuit {
+            a: Some(BlsFr::rand(rng)),
+            b: Some(BlsFr::rand(rng)),
+        };
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        (pk, vk, proof)
+    }
+
+    #[test]
+    fn test_roundtrip_all_modes() {
+        let (pk, vk, proof) = multiply_artifacts();
+
+        for &(mode, validation) in [
+            (SerializationMode::Compressed, Validation::Checked),
+            (SerializationMode::Uncompressed, Validation::Checked),
+            (SerializationMode::Uncompressed, Validation::Unchecked),
+        ]
+        .iter()
+        {
+            let mut bytes = Vec::new();
+            write_artifact(&pk, ArtifactKind::ProvingKey, mode, &mut bytes).unwrap();
+            let pk2: ProvingKey<Bls12_381> =
+                read_artifact(&bytes[..], ArtifactKind::ProvingKey, validation).unwrap();
+            assert_eq!(pk, pk2);
+
+            let mut bytes = Vec::new();
+            write_artifact(&vk, ArtifactKind::VerifyingKey, mode, &mut bytes).unwrap();
+            let vk2: VerifyingKey<Bls12_381> =
+                read_artifact(&bytes[..], ArtifactKind::VerifyingKey, validation).unwrap();
+            assert_eq!(vk, vk2);
+
+            let mut bytes = Vec::new();
+            write_artifact(&proof, ArtifactKind::Proof, mode, &mut bytes).unwrap();
+            let proof2: Proof<Bls12_381> =
+                read_artifact(&bytes[..], ArtifactKind::Proof, validation).unwrap();
+            assert_eq!(proof, proof2);
+        }
+    }
+
+    #[test]
+    fn test_uncompressed_is_larger() {
+        let (_, _, proof) = multiply_artifacts();
+
+        let mut compressed = Vec::new();
+        serialize_with_mode(&proof, SerializationMode::Compressed, &mut compressed).unwrap();
+        let mut uncompressed = Vec::new();
+        serialize_with_mode(&proof, SerializationMode::Uncompressed, &mut uncompressed).unwrap();
+
+        assert_eq!(compressed.len(), proof.serialized_size());
+        assert_eq!(uncompressed.len(), proof.uncompressed_size());
+        assert!(compressed.len() < uncompressed.len());
+    }
+
+    #[test]
+    fn test_rejects_bad_envelopes() {
+        let (_, vk, _) = multiply_artifacts();
+        let mut bytes = Vec::new();
+        write_artifact(
+            &vk,
+            ArtifactKind::VerifyingKey,
+            SerializationMode::Compressed,
+            &mut bytes,
+        )
+        .unwrap();
+
+        // a verifying key is not a proof
+        let result: Result<Proof<Bls12_381>, _> =
+            read_artifact(&bytes[..], ArtifactKind::Proof, Validation::Checked);
+        assert!(matches!(result, Err(PersistError::WrongKind { .. })));
+
+        // compressed points are always validated by ark-serialize 0.3
+        let result: Result<VerifyingKey<Bls12_381>, _> = read_artifact(
+            &bytes[..],
+            ArtifactKind::VerifyingKey,
+            Validation::Unchecked,
+        );
+        assert!(matches!(result, Err(PersistError::UnsupportedMode(..))));
+
+        let mut corrupted = bytes.clone();
+        corrupted[0] = b'X';
+        let result: Result<VerifyingKey<Bls12_381>, _> = read_artifact(
+            &corrupted[..],
+            ArtifactKind::VerifyingKey,
+            Validation::Checked,
+        );
+        assert!(matches!(result, Err(PersistError::InvalidHeader)));
+
+        let mut corrupted = bytes.clone();
+        corrupted[4] = VERSION_WITH_CIRCUIT + 1;
+        let result: Result<VerifyingKey<Bls12_381>, _> = read_artifact(
+            &corrupted[..],
+            ArtifactKind::VerifyingKey,
+            Validation::Checked,
+        );
+        assert!(matches!(result, Err(PersistError::UnsupportedVersion(_))));
+
+        let truncated = &bytes[..bytes.len() - 1];
+        let result: Result<VerifyingKey<Bls12_381>, _> =
+            read_artifact(truncated, ArtifactKind::VerifyingKey, Validation::Checked);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_unchecked_skips_curve_checks() {
+        let (_, _, proof) = multiply_artifacts();
+        let mut bytes = Vec::new();
+        serialize_with_mode(&proof, SerializationMode::Uncompressed, &mut bytes).unwrap();
+
+        // flip a bit in the y coordinate of `a`, the point is no longer on the curve
+        bytes[60] ^= 1;
+        let checked: Result<Proof<Bls12_381>, _> = deserialize_with_mode(
+            &bytes[..],
+            SerializationMode::Uncompressed,
+            Validation::Checked,
+        );
+        assert!(checked.is_err());
+        let unchecked: Proof<Bls12_381> = deserialize_with_mode(
+            &bytes[..],
+            SerializationMode::Uncompressed,
+            Validation::Unchecked,
+        )
+        .unwrap();
+        assert_ne!(unchecked, proof);
+    }
+
+    #[test]
+    fn test_save_and_load_file() {
+        let (_, vk, _) = multiply_artifacts();
+        let path = std::env::temp_dir().join(format!(
+            "gadgets_persist_test_vk_{}.bin",
+            std::process::id()
+        ));
+
+        save(
+            &path,
+            &vk,
+            ArtifactKind::VerifyingKey,
+            SerializationMode::Uncompressed,
+        )
+        .unwrap();
+        let vk2: VerifyingKey<Bls12_381> =
+            load(&path, ArtifactKind::VerifyingKey, Validation::Checked).unwrap();
+        assert_eq!(vk, vk2);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_circuit_envelope() {
+        let (_, vk, _) = multiply_artifacts();
+        let circuit = [7u8; 32];
+        let mut bytes = Vec::new();
+        write_artifact_for_circuit(
+            &vk,
+            ArtifactKind::VerifyingKey,
+            SerializationMode::Compressed,
+            circuit,
+            &mut bytes,
+        )
+        .unwrap();
+        assert_eq!(bytes[4], VERSION_WITH_CIRCUIT);
+
+        let (vk2, found): (VerifyingKey<Bls12_381>, _) =
+            read_artifact_with_circuit(&bytes[..], ArtifactKind::VerifyingKey, Validation::Checked)
+                .unwrap();
+        assert_eq!((vk2, found), (vk.clone(), Some(circuit)));
+
+        // readers that don't ask for the fingerprint skip it
+        let vk2: VerifyingKey<Bls12_381> =
+            read_artifact(&bytes[..], ArtifactKind::VerifyingKey, Validation::Checked).unwrap();
+        assert_eq!(vk2, vk);
+
+        // and a version 1 envelope has none
+        let mut bytes = Vec::new();
+        write_artifact(
+            &vk,
+            ArtifactKind::VerifyingKey,
+            SerializationMode::Compressed,
+            &mut bytes,
+        )
+        .unwrap();
+        let (_, found): (VerifyingKey<Bls12_381>, _) =
+            read_artifact_with_circuit(&bytes[..], ArtifactKind::VerifyingKey, Validation::Checked)
+                .unwrap();
+        assert_eq!(found, None);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_files() {
+        let (pk, vk, _) = multiply_artifacts();
+        let dir = std::env::temp_dir();
+        let plain = dir.join(format!(
+            "gadgets_persist_test_pk_{}.bin",
+            std::process::id()
+        ));
+        let packed = dir.join(format!(
+            "gadgets_persist_test_pk_{}.bin.zst",
+            std::process::id()
+        ));
+
+        for &mode in [
+            SerializationMode::Compressed,
+            SerializationMode::Uncompressed,
+        ]
+        .iter()
+        {
+            save(&plain, &pk, ArtifactKind::ProvingKey, mode).unwrap();
+            save_zstd(&packed, &pk, ArtifactKind::ProvingKey, mode, 3).unwrap();
+            let size = |path| std::fs::metadata(path).unwrap().len();
+            assert!(size(&packed) < size(&plain), "{:?}", mode);
+
+            // `load` tells the two apart on its own
+            for path in [&plain, &packed].iter() {
+                let loaded: ProvingKey<Bls12_381> =
+                    load(path, ArtifactKind::ProvingKey, Validation::Checked).unwrap();
+                assert_eq!(loaded, pk);
+            }
+            // and still checks the envelope inside
+            let result: Result<VerifyingKey<Bls12_381>, _> =
+                load(&packed, ArtifactKind::VerifyingKey, Validation::Checked);
+            assert!(matches!(result, Err(PersistError::WrongKind { .. })));
+        }
+
+        // a frame cut short is an error, not a shorter key
+        let bytes = std::fs::read(&packed).unwrap();
+        std::fs::write(&packed, &bytes[..bytes.len() / 2]).unwrap();
+        let result: Result<ProvingKey<Bls12_381>, _> =
+            load(&packed, ArtifactKind::ProvingKey, Validation::Checked);
+        assert!(result.is_err());
+
+        save_zstd(
+            &packed,
+            &vk,
+            ArtifactKind::VerifyingKey,
+            SerializationMode::Compressed,
+            19,
+        )
+        .unwrap();
+        let loaded: VerifyingKey<Bls12_381> =
+            load(&packed, ArtifactKind::VerifyingKey, Validation::Checked).unwrap();
+        assert_eq!(loaded, vk);
+
+        std::fs::remove_file(&plain).unwrap();
+        std::fs::remove_file(&packed).unwrap();
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn test_zstd_needs_the_feature() {
+        let path = std::env::temp_dir().join(format!(
+            "gadgets_persist_test_frame_{}.bin.zst",
+            std::process::id()
+        ));
+        std::fs::write(&path, [&ZSTD_MAGIC[..], &[0; 16]].concat()).unwrap();
+        let result: Result<VerifyingKey<Bls12_381>, _> =
+            load(&path, ArtifactKind::VerifyingKey, Validation::Checked);
+        assert!(matches!(result, Err(PersistError::UnsupportedCompression)));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
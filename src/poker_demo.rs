@@ -0,0 +1,481 @@
+use ark_bls12_381::Fr;
+use ark_ff::{UniformRand, Zero};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::poseidon::PoseidonParameters;
+use ark_std::rand::Rng;
+
+use crate::boolean_demo::to_bits;
+use crate::div_rem_demo::div_rem;
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+use crate::merkle_gadget::{MerklePath, MerklePathVar, MerkleTree};
+
+// A poker hand: the dealer shuffles the deck and commits to it, a Merkle tree
+// (`merkle_gadget`) of H(card, salt) in deck order, and a player who got the five cards
+// from a public position on and their salts proves which category the hand is, e.g. a
+// flush, without showing the cards. A card is rank * 4 + suit, ranks 0 to 12 are two to
+// ace. The circuit checks that the cards are in the deck at those positions, sorts them
+// (a sorted copy whose product of (x - card) equals the one of the hand at a point x
+// hashed from both, and that is strictly increasing) and counts equal neighbouring ranks
+// and suits: in a sorted hand the pattern of equal ranks tells pairs, three and four of
+// a kind and full houses apart.
+
+pub const DECK_SIZE: usize = 52;
+pub const HAND_SIZE: usize = 5;
+// 64 leaves
+pub const DECK_HEIGHT: usize = 6;
+const CARD_BITS: usize = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    HighCard,
+    Pair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+}
+
+pub fn rank(card: u8) -> u8 {
+    card / 4
+}
+
+pub fn suit(card: u8) -> u8 {
+    card % 4
+}
+
+pub fn category(cards: &[u8; HAND_SIZE]) -> Category {
+    let mut counts = [0u8; 13];
+    for &card in cards.iter() {
+        counts[rank(card) as usize] += 1;
+    }
+    let mut groups: Vec<u8> = counts.iter().copied().filter(|&n| n > 0).collect();
+    groups.sort_unstable_by(|a, b| b.cmp(a));
+    let mut ranks: Vec<u8> = cards.iter().map(|&card| rank(card)).collect();
+    ranks.sort_unstable();
+    // the ace is low in a wheel, ace to five
+    let straight =
+        groups.len() == HAND_SIZE && (ranks[4] - ranks[0] == 4 || ranks == [0, 1, 2, 3, 12]);
+    let flush = cards.iter().all(|&card| suit(card) == suit(cards[0]));
+    match (groups.as_slice(), straight, flush) {
+        (_, true, true) => Category::StraightFlush,
+        ([4, 1], _, _) => Category::FourOfAKind,
+        ([3, 2], _, _) => Category::FullHouse,
+        (_, false, true) => Category::Flush,
+        (_, true, false) => Category::Straight,
+        ([3, 1, 1], _, _) => Category::ThreeOfAKind,
+        ([2, 2, 1], _, _) => Category::TwoPair,
+        ([2, 1, 1, 1], _, _) => Category::Pair,
+        _ => Category::HighCard,
+    }
+}
+
+pub fn card_leaf(params: &PoseidonParameters<Fr>, card: u8, salt: Fr) -> Fr {
+    hash_inputs(params, &[Fr::from(card), salt])
+}
+
+#[derive(Clone)]
+pub struct Deck {
+    pub cards: Vec<u8>,
+    pub salts: Vec<Fr>,
+    tree: MerkleTree<Fr>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Hand {
+    // the position of the first card in the deck
+    pub first: usize,
+    pub cards: [u8; HAND_SIZE],
+    pub salts: [Fr; HAND_SIZE],
+    pub paths: Vec<MerklePath<Fr>>,
+}
+
+impl Deck {
+    pub fn shuffle<R: Rng>(params: &PoseidonParameters<Fr>, rng: &mut R) -> Self {
+        let mut cards: Vec<u8> = (0..DECK_SIZE as u8).collect();
+        for i in (1..DECK_SIZE).rev() {
+            cards.swap(i, rng.gen_range(0..=i));
+        }
+        let salts: Vec<Fr> = (0..DECK_SIZE).map(|_| Fr::rand(rng)).collect();
+        let leaves: Vec<Fr> = cards
+            .iter()
+            .zip(&salts)
+            .map(|(&card, &salt)| card_leaf(params, card, salt))
+            .collect();
+        let tree = MerkleTree::new(params.clone(), DECK_HEIGHT, &leaves).unwrap();
+        Self { cards, salts, tree }
+    }
+
+    // the commitment the dealer publishes
+    pub fn root(&self) -> Fr {
+        self.tree.root()
+    }
+
+    // the cards from `first` on, with what the player needs to prove their hand
+    pub fn deal(&self, first: usize) -> Option<Hand> {
+        if first + HAND_SIZE > DECK_SIZE {
+            return None;
+        }
+        let mut cards = [0; HAND_SIZE];
+        cards.copy_from_slice(&self.cards[first..first + HAND_SIZE]);
+        let mut salts = [Fr::zero(); HAND_SIZE];
+        salts.copy_from_slice(&self.salts[first..first + HAND_SIZE]);
+        let paths = (first..first + HAND_SIZE)
+            .map(|position| self.tree.path(position))
+            .collect::<Option<_>>()?;
+        Some(Hand {
+            first,
+            cards,
+            salts,
+            paths,
+        })
+    }
+}
+
+// `sorted` holds `values` in increasing order and all are distinct, for values below
+// 2^num_bits. The permutation check is the product of (x - v) at a point x hashed from
+// both lists, which two different multisets only agree on for few x.
+pub fn enforce_sorted_permutation(
+    cs: ConstraintSystemRef<Fr>,
+    params: &PoseidonParameters<Fr>,
+    values: &[FpVar<Fr>],
+    sorted: &[FpVar<Fr>],
+    num_bits: usize,
+) -> Result<(), SynthesisError> {
+    assert_eq!(values.len(), sorted.len());
+    for pair in sorted.windows(2) {
+        to_bits(&(&pair[1] - &pair[0] - Fr::from(1u8)), num_bits)?;
+    }
+    let mut inputs = values.to_vec();
+    inputs.extend_from_slice(sorted);
+    let x = hash_inputs_var(cs, params, &inputs)?;
+    let product = |list: &[FpVar<Fr>]| {
+        list.iter()
+            .fold(FpVar::Constant(Fr::from(1u8)), |product, v| {
+                product * (&x - v)
+            })
+    };
+    product(values).enforce_equal(&product(sorted))
+}
+
+#[derive(Clone)]
+pub struct PokerCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub root: Fr,
+    pub category: Category,
+    pub hand: Option<Hand>,
+    // the position of the first card, public
+    pub first: usize,
+}
+
+impl PokerCircuit {
+    pub fn new(params: PoseidonParameters<Fr>, root: Fr, hand: Hand) -> Self {
+        Self {
+            params,
+            root,
+            category: category(&hand.cards),
+            first: hand.first,
+            hand: Some(hand),
+        }
+    }
+
+    pub fn blank(params: PoseidonParameters<Fr>) -> Self {
+        Self {
+            params,
+            root: Fr::zero(),
+            category: Category::HighCard,
+            first: 0,
+            hand: None,
+        }
+    }
+
+    // the deck, the position of the hand and its category
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        vec![
+            self.root,
+            Fr::from(self.first as u64),
+            Fr::from(self.category as u8),
+        ]
+    }
+}
+
+// the category of a sorted hand from its ranks and suits
+fn category_var(ranks: &[FpVar<Fr>], suits: &[FpVar<Fr>]) -> Result<FpVar<Fr>, SynthesisError> {
+    let constant = |value: u8| FpVar::Constant(Fr::from(value));
+    // equal neighbouring ranks, their number and runs of them
+    let equal = ranks
+        .windows(2)
+        .map(|pair| pair[0].is_eq(&pair[1]))
+        .collect::<Result<Vec<_>, _>>()?;
+    let num_equal = equal
+        .iter()
+        .fold(FpVar::zero(), |sum, e| sum + FpVar::from(e.clone()));
+    let three = Boolean::kary_or(&[
+        equal[0].and(&equal[1])?,
+        equal[1].and(&equal[2])?,
+        equal[2].and(&equal[3])?,
+    ])?;
+    let four = Boolean::kary_or(&[
+        Boolean::kary_and(&equal[..3])?,
+        Boolean::kary_and(&equal[1..])?,
+    ])?;
+    let pairs = [
+        num_equal.is_eq(&constant(0))?,
+        num_equal.is_eq(&constant(1))?,
+        num_equal.is_eq(&constant(2))?,
+        num_equal.is_eq(&constant(3))?,
+    ];
+
+    // with distinct ranks, ace high or a wheel
+    let wide = (&ranks[4] - &ranks[0]).is_eq(&constant(4))?;
+    let wheel = ranks[3]
+        .is_eq(&constant(3))?
+        .and(&ranks[4].is_eq(&constant(12))?)?;
+    let straight = pairs[0].and(&wide.or(&wheel)?)?;
+    let flush = Boolean::kary_and(
+        &suits[1..]
+            .iter()
+            .map(|s| s.is_eq(&suits[0]))
+            .collect::<Result<Vec<_>, _>>()?,
+    )?;
+
+    // exactly one of these holds
+    let categories = [
+        (Category::StraightFlush, straight.and(&flush)?),
+        (Category::FourOfAKind, four.clone()),
+        (Category::FullHouse, pairs[3].and(&four.not())?),
+        (Category::Flush, flush.and(&straight.not())?),
+        (Category::Straight, straight.and(&flush.not())?),
+        (Category::ThreeOfAKind, pairs[2].and(&three)?),
+        (Category::TwoPair, pairs[2].and(&three.not())?),
+        (Category::Pair, pairs[1].clone()),
+    ];
+    Ok(categories
+        .iter()
+        .fold(FpVar::zero(), |sum, (category, holds)| {
+            sum + FpVar::from(holds.clone()) * Fr::from(*category as u8)
+        }))
+}
+
+impl ConstraintSynthesizer<Fr> for PokerCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let root = FpVar::new_input(ns!(cs, "root"), || Ok(self.root))?;
+        let first = FpVar::new_input(ns!(cs, "first"), || Ok(Fr::from(self.first as u64)))?;
+        let claimed = FpVar::new_input(ns!(cs, "category"), || Ok(Fr::from(self.category as u8)))?;
+
+        let hand = self.hand.as_ref();
+        let mut sorted_cards = hand.map(|h| h.cards);
+        if let Some(cards) = sorted_cards.as_mut() {
+            cards.sort_unstable();
+        }
+        let mut cards = Vec::with_capacity(HAND_SIZE);
+        let mut sorted = Vec::with_capacity(HAND_SIZE);
+        for i in 0..HAND_SIZE {
+            let card = FpVar::new_witness(ns!(cs, "card"), || {
+                hand.map(|h| Fr::from(h.cards[i]))
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let salt = FpVar::new_witness(ns!(cs, "salt"), || {
+                hand.map(|h| h.salts[i])
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let path =
+                MerklePathVar::new_witness(cs.clone(), DECK_HEIGHT, hand.map(|h| &h.paths[i]))?;
+            // in the deck at first + i
+            let leaf = hash_inputs_var(cs.clone(), &self.params, &[card.clone(), salt])?;
+            path.root(cs.clone(), &self.params, &leaf)?
+                .enforce_equal(&root)?;
+            path.index()?
+                .enforce_equal(&(&first + Fr::from(i as u64)))?;
+            to_bits(
+                &(FpVar::Constant(Fr::from(DECK_SIZE as u64 - 1)) - &card),
+                CARD_BITS,
+            )?;
+            cards.push(card);
+
+            sorted.push(FpVar::new_witness(ns!(cs, "sorted"), || {
+                sorted_cards
+                    .map(|s| Fr::from(s[i]))
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?);
+        }
+        enforce_sorted_permutation(cs.clone(), &self.params, &cards, &sorted, CARD_BITS)?;
+
+        let four = FpVar::Constant(Fr::from(4u8));
+        let mut ranks = Vec::with_capacity(HAND_SIZE);
+        let mut suits = Vec::with_capacity(HAND_SIZE);
+        for card in sorted.iter() {
+            let (rank, suit) = div_rem(cs.clone(), card, &four, CARD_BITS)?;
+            ranks.push(rank);
+            suits.push(suit);
+        }
+        category_var(&ranks, &suits)?.enforce_equal(&claimed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    fn card(rank: u8, suit: u8) -> u8 {
+        rank * 4 + suit
+    }
+
+    fn is_satisfied(circuit: PokerCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    // a deck with `cards` on top
+    fn stacked_deck(params: &PoseidonParameters<Fr>, cards: [u8; HAND_SIZE]) -> Deck {
+        let rng = &mut ark_std::test_rng();
+        let mut deck = Deck::shuffle(params, rng);
+        for (i, &c) in cards.iter().enumerate() {
+            let j = deck.cards.iter().position(|&d| d == c).unwrap();
+            deck.cards.swap(i, j);
+        }
+        let leaves: Vec<Fr> = deck
+            .cards
+            .iter()
+            .zip(&deck.salts)
+            .map(|(&c, &salt)| card_leaf(params, c, salt))
+            .collect();
+        deck.tree = MerkleTree::new(params.clone(), DECK_HEIGHT, &leaves).unwrap();
+        deck
+    }
+
+    #[test]
+    fn test_categories() {
+        let params = poseidon_parameters();
+        let hands = [
+            (
+                [card(0, 0), card(5, 1), card(7, 2), card(9, 3), card(12, 0)],
+                Category::HighCard,
+            ),
+            (
+                [card(9, 0), card(5, 1), card(9, 2), card(2, 3), card(12, 0)],
+                Category::Pair,
+            ),
+            (
+                [card(9, 0), card(5, 1), card(9, 2), card(5, 3), card(12, 0)],
+                Category::TwoPair,
+            ),
+            (
+                [card(9, 0), card(9, 1), card(9, 2), card(5, 3), card(12, 0)],
+                Category::ThreeOfAKind,
+            ),
+            (
+                [card(3, 0), card(4, 1), card(5, 2), card(6, 3), card(7, 0)],
+                Category::Straight,
+            ),
+            (
+                [card(12, 0), card(0, 1), card(1, 2), card(2, 3), card(3, 0)],
+                Category::Straight,
+            ),
+            (
+                [card(0, 2), card(4, 2), card(5, 2), card(9, 2), card(11, 2)],
+                Category::Flush,
+            ),
+            (
+                [card(5, 0), card(9, 1), card(9, 2), card(5, 3), card(9, 0)],
+                Category::FullHouse,
+            ),
+            (
+                [card(2, 0), card(2, 1), card(2, 2), card(2, 3), card(9, 0)],
+                Category::FourOfAKind,
+            ),
+            (
+                [
+                    card(8, 1),
+                    card(9, 1),
+                    card(10, 1),
+                    card(11, 1),
+                    card(12, 1),
+                ],
+                Category::StraightFlush,
+            ),
+            // not a straight, the ace is only low in a wheel
+            (
+                [card(11, 0), card(12, 1), card(0, 2), card(1, 3), card(2, 0)],
+                Category::HighCard,
+            ),
+        ];
+        for (cards, expected) in hands.iter() {
+            assert_eq!(category(cards), *expected, "{:?}", cards);
+            let deck = stacked_deck(&params, *cards);
+            let circuit = PokerCircuit::new(params.clone(), deck.root(), deck.deal(0).unwrap());
+            assert!(is_satisfied(circuit.clone()), "{:?}", cards);
+            // any other claim fails
+            let mut wrong = circuit;
+            wrong.category = match *expected {
+                Category::HighCard => Category::Pair,
+                _ => Category::HighCard,
+            };
+            assert!(!is_satisfied(wrong), "{:?}", cards);
+        }
+    }
+
+    #[test]
+    fn test_poker_hand() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let deck = Deck::shuffle(&params, rng);
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(PokerCircuit::blank(params.clone()), rng)
+                .unwrap();
+        let hand = deck.deal(10).unwrap();
+        let circuit = PokerCircuit::new(params.clone(), deck.root(), hand.clone());
+        let inputs = circuit.public_inputs();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        // the cards of another player
+        let mut other_position = inputs.clone();
+        other_position[1] = Fr::from(15u8);
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &other_position, &proof).unwrap());
+
+        // a card replaced by one from further down the deck, with its salt and path
+        let mut swapped = hand;
+        swapped.cards[4] = deck.cards[20];
+        swapped.salts[4] = deck.salts[20];
+        swapped.paths[4] = deck.deal(20).unwrap().paths[0].clone();
+        assert!(!is_satisfied(PokerCircuit::new(
+            params.clone(),
+            deck.root(),
+            swapped
+        )));
+    }
+
+    #[test]
+    fn test_sorted_permutation() {
+        let params = poseidon_parameters();
+        let check = |values: &[u8], sorted: &[u8]| {
+            let cs = ConstraintSystem::new_ref();
+            let alloc = |list: &[u8]| -> Vec<FpVar<Fr>> {
+                list.iter()
+                    .map(|&v| FpVar::new_witness(cs.clone(), || Ok(Fr::from(v))).unwrap())
+                    .collect()
+            };
+            let (values, sorted) = (alloc(values), alloc(sorted));
+            enforce_sorted_permutation(cs.clone(), &params, &values, &sorted, CARD_BITS).unwrap();
+            cs.is_satisfied().unwrap()
+        };
+        assert!(check(&[30, 4, 51, 0, 9], &[0, 4, 9, 30, 51]));
+        // not sorted, a repeated card, not the same cards
+        assert!(!check(&[30, 4, 51, 0, 9], &[0, 4, 30, 9, 51]));
+        assert!(!check(&[30, 4, 4, 0, 9], &[0, 4, 4, 9, 30]));
+        assert!(!check(&[30, 4, 51, 0, 9], &[0, 4, 9, 30, 50]));
+    }
+}
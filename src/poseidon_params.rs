@@ -0,0 +1,408 @@
+use ark_ff::{BigInteger, PrimeField};
+use ark_sponge::poseidon::PoseidonParameters;
+use ark_std::rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+// The Poseidon parameters of the crate, for the native sponge (`hash_inputs`) and the
+// gadget (`hash_inputs_var`) alike, so every hash based demo hashes the same way. The
+// sponge of ark-sponge 0.3 has width 3 (rate 2, capacity 1) and x^alpha on the last
+// element in partial rounds. Two sets of constants:
+//   - `Constants::Seeded`, round constants from a seeded ChaCha stream and a fixed
+//     Cauchy MDS matrix, what the demos and their test vectors use,
+//   - `Constants::Grain`, the Grain LFSR generation of the Poseidon reference
+//     implementation (generate_parameters_grain.sage) for the field, the constants of
+//     circomlib for BN254. The security checks of the reference on the matrix are
+//     not repeated.
+// Generating constants takes a moment and a demo asks for them in every call, so
+// `parameters` caches them by field and spec. Constants from elsewhere, e.g. the hex
+// listings of the reference implementation, can be loaded with `load`.
+
+pub const WIDTH: usize = 3;
+pub const FULL_ROUNDS: u32 = 8;
+pub const PARTIAL_ROUNDS: u32 = 57;
+pub const ALPHA: u64 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Constants {
+    Seeded,
+    Grain,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PoseidonSpec {
+    pub constants: Constants,
+    pub full_rounds: u32,
+    pub partial_rounds: u32,
+    pub alpha: u64,
+}
+
+impl PoseidonSpec {
+    // x^5 with 8 full and 57 partial rounds, 128 bit security for 254 and 255 bit fields
+    pub const fn new(constants: Constants) -> Self {
+        Self {
+            constants,
+            full_rounds: FULL_ROUNDS,
+            partial_rounds: PARTIAL_ROUNDS,
+            alpha: ALPHA,
+        }
+    }
+
+    // not cached, see `parameters`
+    pub fn generate<F: PrimeField>(&self) -> PoseidonParameters<F> {
+        let (ark, mds) = match self.constants {
+            Constants::Seeded => seeded_constants(self.full_rounds + self.partial_rounds),
+            Constants::Grain => grain_constants(self),
+        };
+        PoseidonParameters::new(self.full_rounds, self.partial_rounds, self.alpha, mds, ark)
+    }
+}
+
+impl Default for PoseidonSpec {
+    fn default() -> Self {
+        Self::new(Constants::Seeded)
+    }
+}
+
+type Cache = HashMap<(TypeId, PoseidonSpec), Box<dyn Any + Send>>;
+
+// the parameters of `spec` for F, generated once per process
+pub fn parameters<F: PrimeField>(spec: PoseidonSpec) -> PoseidonParameters<F> {
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+    let mut cache = CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .expect("a panic while generating parameters");
+    cache
+        .entry((TypeId::of::<F>(), spec))
+        .or_insert_with(|| Box::new(spec.generate::<F>()))
+        .downcast_ref::<PoseidonParameters<F>>()
+        .expect("the cache is keyed by the field")
+        .clone()
+}
+
+// the parameters of the demos
+pub fn poseidon_parameters<F: PrimeField>() -> PoseidonParameters<F> {
+    parameters(PoseidonSpec::default())
+}
+
+fn seeded_constants<F: PrimeField>(rounds: u32) -> (Vec<Vec<F>>, Vec<Vec<F>>) {
+    let mut rng = ChaCha20Rng::from_seed(*b"hashed_inputs_demo poseidon 0001");
+    let ark = PoseidonParameters::random_ark(rounds, &mut rng);
+    // 1 / (x_i + y_j) for x = (0, 1, 2) and y = (3, 4, 5)
+    let mds = (0..WIDTH as u64)
+        .map(|i| {
+            (0..WIDTH as u64)
+                .map(|j| {
+                    F::from(i + j + 3)
+                        .inverse()
+                        .expect("3..=7 are invertible in any field of characteristic above 7")
+                })
+                .collect()
+        })
+        .collect();
+    (ark, mds)
+}
+
+// The 80 bit LFSR of the reference implementation, initialized with the field, the
+// S-box, the width and the rounds. Its output bits are the second of each pair of bits
+// whose first is set.
+struct Grain {
+    state: [bool; 80],
+}
+
+impl Grain {
+    fn new(field_bits: u32, spec: &PoseidonSpec) -> Self {
+        // a prime field (1, 2 bits), x^alpha (0, 4 bits), then the sizes
+        let fields = [
+            (1, 2),
+            (0, 4),
+            (field_bits as u64, 12),
+            (WIDTH as u64, 12),
+            (spec.full_rounds as u64, 10),
+            (spec.partial_rounds as u64, 10),
+            ((1 << 30) - 1, 30),
+        ];
+        let mut state = [false; 80];
+        let mut position = 0;
+        for &(value, bits) in fields.iter() {
+            for i in (0..bits).rev() {
+                state[position] = (value >> i) & 1 == 1;
+                position += 1;
+            }
+        }
+        let mut grain = Self { state };
+        for _ in 0..160 {
+            grain.clock();
+        }
+        grain
+    }
+
+    fn clock(&mut self) -> bool {
+        let s = &self.state;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.rotate_left(1);
+        self.state[79] = bit;
+        bit
+    }
+
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let (keep, bit) = (self.clock(), self.clock());
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    // the next `F::size_in_bits()` bits, most significant first
+    fn next_repr<F: PrimeField>(&mut self) -> F::BigInt {
+        let bits: Vec<bool> = (0..F::size_in_bits()).map(|_| self.next_bit()).collect();
+        F::BigInt::from_bits_be(&bits)
+    }
+
+    // values not below the modulus are dropped
+    fn next_element<F: PrimeField>(&mut self) -> F {
+        loop {
+            if let Some(element) = F::from_repr(self.next_repr::<F>()) {
+                return element;
+            }
+        }
+    }
+
+    fn next_element_mod_order<F: PrimeField>(&mut self) -> F {
+        F::from_be_bytes_mod_order(&self.next_repr::<F>().to_bytes_be())
+    }
+}
+
+fn grain_constants<F: PrimeField>(spec: &PoseidonSpec) -> (Vec<Vec<F>>, Vec<Vec<F>>) {
+    let mut grain = Grain::new(F::size_in_bits() as u32, spec);
+    let rounds = spec.full_rounds + spec.partial_rounds;
+    let ark = (0..rounds)
+        .map(|_| (0..WIDTH).map(|_| grain.next_element()).collect())
+        .collect();
+    // a Cauchy matrix 1 / (x_i + y_j) for 2 * WIDTH distinct elements, drawn again if
+    // some x_i + y_j is zero
+    loop {
+        let elements: Vec<F> = (0..2 * WIDTH)
+            .map(|_| grain.next_element_mod_order())
+            .collect();
+        let distinct = (0..2 * WIDTH).all(|i| !elements[..i].contains(&elements[i]));
+        if !distinct {
+            continue;
+        }
+        let (xs, ys) = elements.split_at(WIDTH);
+        let mds: Option<Vec<Vec<F>>> = xs
+            .iter()
+            .map(|x| ys.iter().map(|y| (*x + y).inverse()).collect())
+            .collect();
+        if let Some(mds) = mds {
+            return (ark, mds);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamsError {
+    // constants are counted from 0, the round constants first
+    Hex { constant: usize },
+    NotInField { constant: usize },
+    Shape { expected: usize, found: usize },
+}
+
+impl fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamsError::Hex { constant } => write!(f, "constant {} is not hex", constant),
+            ParamsError::NotInField { constant } => {
+                write!(f, "constant {} is not below the modulus", constant)
+            }
+            ParamsError::Shape { expected, found } => {
+                write!(f, "expected {} constants, got {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParamsError {}
+
+fn parse_hex<F: PrimeField>(hex: &str, constant: usize) -> Result<F, ParamsError> {
+    let digits = hex.trim().trim_start_matches("0x");
+    let bits = F::size_in_bits().div_ceil(4) * 4;
+    if digits.is_empty() || digits.len() * 4 > bits {
+        return Err(ParamsError::Hex { constant });
+    }
+    let mut bits = vec![false; bits - digits.len() * 4];
+    for digit in digits.chars() {
+        let value = digit.to_digit(16).ok_or(ParamsError::Hex { constant })?;
+        bits.extend((0..4).rev().map(|i| (value >> i) & 1 == 1));
+    }
+    F::from_repr(F::BigInt::from_bits_be(&bits)).ok_or(ParamsError::NotInField { constant })
+}
+
+// parameters from big-endian hex constants, the round constants round by round and the
+// matrix row by row
+pub fn load<F: PrimeField>(
+    full_rounds: u32,
+    partial_rounds: u32,
+    alpha: u64,
+    ark: &[&str],
+    mds: &[&str],
+) -> Result<PoseidonParameters<F>, ParamsError> {
+    let expected = (full_rounds + partial_rounds) as usize * WIDTH + WIDTH * WIDTH;
+    if ark.len() + mds.len() != expected || mds.len() != WIDTH * WIDTH {
+        return Err(ParamsError::Shape {
+            expected,
+            found: ark.len() + mds.len(),
+        });
+    }
+    let constants = ark
+        .iter()
+        .chain(mds)
+        .enumerate()
+        .map(|(i, hex)| parse_hex(hex, i))
+        .collect::<Result<Vec<F>, _>>()?;
+    let (ark, mds) = constants.split_at(ark.len());
+    Ok(PoseidonParameters::new(
+        full_rounds,
+        partial_rounds,
+        alpha,
+        mds.chunks(WIDTH).map(<[F]>::to_vec).collect(),
+        ark.chunks(WIDTH).map(<[F]>::to_vec).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+    use ark_bls12_381::Fr;
+    use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+    use ark_relations::r1cs::ConstraintSystem;
+
+    // the permutation of the reference, x^alpha on the first element in partial rounds
+    fn reference_permutation<F: PrimeField>(
+        spec: &PoseidonSpec,
+        ark: &[Vec<F>],
+        mds: &[Vec<F>],
+        mut state: Vec<F>,
+    ) -> Vec<F> {
+        let half = spec.full_rounds / 2;
+        for (round, constants) in ark.iter().enumerate() {
+            let round = round as u32;
+            for (s, c) in state.iter_mut().zip(constants) {
+                *s += c;
+            }
+            let full = round < half || round >= half + spec.partial_rounds;
+            let sbox = if full { WIDTH } else { 1 };
+            for s in state.iter_mut().take(sbox) {
+                *s = s.pow([spec.alpha]);
+            }
+            state = mds
+                .iter()
+                .map(|row| row.iter().zip(&state).map(|(m, s)| *m * s).sum())
+                .collect();
+        }
+        state
+    }
+
+    #[test]
+    fn test_grain_constants_of_circomlib() {
+        use ark_bn254::Fr as Bn254Fr;
+        let spec = PoseidonSpec::new(Constants::Grain);
+        let (ark, mds) = grain_constants::<Bn254Fr>(&spec);
+        let first = "0x0ee9a592ba9a9518d05986d656f40c2114c4993c11bb29938d21d47304cd8e6e";
+        assert_eq!(ark[0][0], parse_hex(first, 0).unwrap());
+
+        // poseidon([1, 2]) of circomlib, the state [0, 1, 2] permuted
+        let state = (0..3u8).map(Bn254Fr::from).collect();
+        let expected = "0x115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a";
+        let output = reference_permutation(&spec, &ark, &mds, state);
+        assert_eq!(output[0], parse_hex(expected, 0).unwrap());
+    }
+
+    #[test]
+    fn test_cached_parameters() {
+        // the seeded constants hash as they always did
+        let params = poseidon_parameters::<Fr>();
+        let digest = hash_inputs(&params, &[Fr::from(1u8), Fr::from(2u8)]);
+        let generated = PoseidonSpec::default().generate::<Fr>();
+        assert_eq!(
+            hash_inputs(&generated, &[Fr::from(1u8), Fr::from(2u8)]),
+            digest
+        );
+
+        // other constants, other fields, other rounds hash differently
+        let grain = parameters::<Fr>(PoseidonSpec::new(Constants::Grain));
+        assert_ne!(hash_inputs(&grain, &[Fr::from(1u8), Fr::from(2u8)]), digest);
+        let fewer = PoseidonSpec {
+            partial_rounds: 56,
+            ..PoseidonSpec::new(Constants::Grain)
+        };
+        let fewer = parameters::<Fr>(fewer);
+        assert_ne!(
+            hash_inputs(&fewer, &[Fr::from(1u8), Fr::from(2u8)]),
+            hash_inputs(&grain, &[Fr::from(1u8), Fr::from(2u8)])
+        );
+
+        // the gadget agrees
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let inputs = vec![
+            FpVar::new_witness(cs.clone(), || Ok(Fr::from(1u8))).unwrap(),
+            FpVar::new_witness(cs.clone(), || Ok(Fr::from(2u8))).unwrap(),
+        ];
+        let digest_var = hash_inputs_var(cs, &grain, &inputs).unwrap();
+        assert_eq!(
+            digest_var.value().unwrap(),
+            hash_inputs(&grain, &[Fr::from(1u8), Fr::from(2u8)])
+        );
+    }
+
+    #[test]
+    fn test_load() {
+        let spec = PoseidonSpec {
+            full_rounds: 2,
+            partial_rounds: 1,
+            ..PoseidonSpec::new(Constants::Grain)
+        };
+        let (ark, mds) = grain_constants::<Fr>(&spec);
+        let hex: Vec<String> = ark
+            .iter()
+            .chain(&mds)
+            .flatten()
+            .map(|c| format!("0x{}", c.into_repr()))
+            .collect();
+        let hex: Vec<&str> = hex.iter().map(String::as_str).collect();
+        let (ark_hex, mds_hex) = hex.split_at(9);
+        let loaded = load::<Fr>(2, 1, ALPHA, ark_hex, mds_hex).unwrap();
+        let generated = spec.generate::<Fr>();
+        let inputs = [Fr::from(3u8)];
+        assert_eq!(
+            hash_inputs(&loaded, &inputs),
+            hash_inputs(&generated, &inputs)
+        );
+
+        assert_eq!(
+            load::<Fr>(2, 1, ALPHA, &ark_hex[1..], mds_hex).unwrap_err(),
+            ParamsError::Shape {
+                expected: 18,
+                found: 17
+            }
+        );
+        let mut bad = ark_hex.to_vec();
+        bad[4] = "0xzz";
+        assert_eq!(
+            load::<Fr>(2, 1, ALPHA, &bad, mds_hex).unwrap_err(),
+            ParamsError::Hex { constant: 4 }
+        );
+        // the modulus itself
+        bad[4] = "0x73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001";
+        assert_eq!(
+            load::<Fr>(2, 1, ALPHA, &bad, mds_hex).unwrap_err(),
+            ParamsError::NotInField { constant: 4 }
+        );
+    }
+}
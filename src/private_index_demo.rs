@@ -0,0 +1,154 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+
+// Reading array[i] for a private i. The circuit cannot index with a witness, instead
+// the prover gives a one-hot selector, one bit per position with exactly the bit at i
+// set, and the element is the inner product of the selector and the array. The bits
+// sum to one and their weighted sum, sum j * s_j, is i, so the selector has to be the
+// one of i and i has to be an index of the array.
+
+// the one-hot selector of `index` over `len` positions
+pub fn one_hot<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    index: &FpVar<F>,
+    len: usize,
+) -> Result<Vec<Boolean<F>>, SynthesisError> {
+    let selector = (0..len)
+        .map(|j| {
+            Boolean::new_witness(ns!(cs, "selector"), || {
+                Ok(index.value()? == F::from(j as u64))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut count = FpVar::zero();
+    let mut weighted = FpVar::zero();
+    for (j, bit) in selector.iter().enumerate() {
+        let bit = FpVar::from(bit.clone());
+        weighted += &bit * F::from(j as u64);
+        count += bit;
+    }
+    count.enforce_equal(&FpVar::one())?;
+    weighted.enforce_equal(index)?;
+    Ok(selector)
+}
+
+// array[index], unsatisfiable if the index is out of range
+pub fn select_index<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    array: &[FpVar<F>],
+    index: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    let selector = one_hot(cs, index, array.len())?;
+    let mut element = FpVar::zero();
+    for (bit, value) in selector.iter().zip(array) {
+        element += FpVar::from(bit.clone()) * value;
+    }
+    Ok(element)
+}
+
+// knows i with array[i] == y for a public array and a public y
+#[derive(Clone)]
+pub struct PrivateIndexCircuit<F: PrimeField> {
+    pub array: Vec<F>,
+    pub index: Option<usize>,
+}
+
+impl<F: PrimeField> PrivateIndexCircuit<F> {
+    pub fn new(array: Vec<F>, index: usize) -> Self {
+        Self {
+            array,
+            index: Some(index),
+        }
+    }
+
+    // the shape only depends on the length of the array
+    pub fn blank(len: usize) -> Self {
+        Self {
+            array: vec![F::zero(); len],
+            index: None,
+        }
+    }
+
+    // the array, then y
+    pub fn public_inputs(&self) -> Option<Vec<F>> {
+        let y = *self.array.get(self.index?)?;
+        let mut inputs = self.array.clone();
+        inputs.push(y);
+        Some(inputs)
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for PrivateIndexCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let array = self
+            .array
+            .iter()
+            .map(|&value| FpVar::new_input(ns!(cs, "array"), || Ok(value)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let y = self.index.and_then(|i| self.array.get(i).copied());
+        let y = FpVar::new_input(ns!(cs, "y"), || y.ok_or(SynthesisError::AssignmentMissing))?;
+        let index = FpVar::new_witness(ns!(cs, "index"), || {
+            self.index
+                .map(|i| F::from(i as u64))
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        select_index(cs, &array, &index)?.enforce_equal(&y)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    #[test]
+    fn test_select_index() {
+        let rng = &mut ark_std::test_rng();
+        let values: Vec<Fr> = (0..5).map(|_| Fr::rand(rng)).collect();
+        for i in 0..6u64 {
+            let cs = ConstraintSystem::new_ref();
+            let array: Vec<_> = values
+                .iter()
+                .map(|&v| FpVar::new_witness(cs.clone(), || Ok(v)).unwrap())
+                .collect();
+            let index = FpVar::new_witness(cs.clone(), || Ok(Fr::from(i))).unwrap();
+            let element = select_index(cs.clone(), &array, &index).unwrap();
+            match values.get(i as usize) {
+                Some(&value) => {
+                    assert!(cs.is_satisfied().unwrap());
+                    assert_eq!(element.value().unwrap(), value);
+                }
+                // no bit set
+                None => assert!(!cs.is_satisfied().unwrap()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_private_index() {
+        let rng = &mut ark_std::test_rng();
+        let array: Vec<Fr> = (0..8).map(|_| Fr::rand(rng)).collect();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(PrivateIndexCircuit::blank(8), rng)
+                .unwrap();
+
+        let circuit = PrivateIndexCircuit::new(array.clone(), 5);
+        let inputs = circuit.public_inputs().unwrap();
+        assert_eq!(inputs[8], array[5]);
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        // a y that is not in the array
+        let mut wrong = inputs;
+        wrong[8] = Fr::rand(rng);
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &wrong, &proof).unwrap());
+    }
+}
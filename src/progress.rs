@@ -0,0 +1,313 @@
+use std::fmt;
+
+#[cfg(any(feature = "groth16", feature = "marlin"))]
+use ark_ec::PairingEngine;
+#[cfg(any(feature = "groth16", feature = "marlin"))]
+use ark_ff::UniformRand;
+#[cfg(feature = "groth16")]
+use ark_groth16::{Proof, ProvingKey};
+#[cfg(any(feature = "groth16", feature = "marlin"))]
+use ark_relations::r1cs::ConstraintSynthesizer;
+#[cfg(feature = "groth16")]
+use ark_relations::r1cs::SynthesisError;
+#[cfg(any(feature = "groth16", feature = "marlin"))]
+use tracing::info_span;
+
+#[cfg(feature = "groth16")]
+use crate::msm::{create_proof_with_progress, CpuMsm};
+#[cfg(any(feature = "groth16", feature = "marlin"))]
+use crate::rng::{RngConfig, PROVE_STREAM};
+
+// Proving wrappers that say how far a proof has got, for a CLI or a GUI to show progress
+// on a proof that takes minutes instead of looking hung. `SNARK::prove` is one call that
+// reports nothing, so these run the backends' provers step by step, the way ark-groth16
+// and ark-marlin do, and call `progress` after every phase:
+//   Groth16  synthesis, the witness map (the FFTs for h), then each of the five MSMs
+//   Marlin   synthesis, each of the three rounds of polynomial commitments, the openings
+// They make the proofs `prover::prove` and `MarlinInst::prove` make from the same
+// randomness. `progress` can be any `FnMut`, e.g. one that sends to a channel.
+
+// MSMs of a Groth16 proof: A and B in G1, B in G2, h and l
+pub const GROTH16_MSMS: usize = 5;
+// commitment rounds of a Marlin proof
+pub const MARLIN_ROUNDS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Progress {
+    // the constraint system with its witness
+    Synthesized,
+    // Groth16: the coefficients of h
+    WitnessMapped,
+    // Groth16: `done` of the `GROTH16_MSMS` MSMs, Marlin: `done` of the `MARLIN_ROUNDS`
+    // commitment rounds
+    Committed { done: usize, total: usize },
+    // Marlin: the evaluations and their opening proof
+    Opened,
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Progress::Synthesized => write!(f, "synthesis done"),
+            Progress::WitnessMapped => write!(f, "witness map done"),
+            Progress::Committed { done, total } => write!(f, "commitments {}/{}", done, total),
+            Progress::Opened => write!(f, "openings done"),
+        }
+    }
+}
+
+// `prover::prove::<_, Groth16<E>, _>` with progress, the same proof for the same
+// `rng_config`
+#[cfg(feature = "groth16")]
+pub fn prove_groth16<E, C>(
+    pk: &ProvingKey<E>,
+    circuit: C,
+    rng_config: &RngConfig,
+    mut progress: impl FnMut(Progress),
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: PairingEngine,
+    C: ConstraintSynthesizer<E::Fr>,
+{
+    let _span = info_span!("prove").entered();
+    let mut rng = rng_config.build_stream(PROVE_STREAM);
+    let r = E::Fr::rand(&mut rng);
+    let s = E::Fr::rand(&mut rng);
+    create_proof_with_progress(circuit, pk, r, s, &CpuMsm, &mut progress)
+}
+
+#[cfg(feature = "marlin")]
+pub use marlin::prove_marlin;
+
+#[cfg(feature = "marlin")]
+mod marlin {
+    use super::*;
+    use ark_ff::to_bytes;
+    use ark_marlin::ahp::{AHPForR1CS, EvaluationsProvider};
+    use ark_marlin::rng::FiatShamirRng;
+    use ark_poly_commit::{LabeledCommitment, PolynomialCommitment};
+    use blake2::Blake2s;
+
+    use crate::marlin_srs::{MarlinInst, MarlinPC, MarlinProof, MarlinProvingKey};
+    use crate::Error;
+
+    // `Marlin::prove` of ark-marlin 0.3, round by round. The transcript, the order of
+    // the oracles and the draws from the zero knowledge rng are Marlin's, so the proof is
+    // the one `MarlinInst::prove` makes with the rng `rng_config` builds.
+    pub fn prove_marlin<E, C>(
+        pk: &MarlinProvingKey<E>,
+        circuit: C,
+        rng_config: &RngConfig,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<MarlinProof<E>, Error>
+    where
+        E: PairingEngine,
+        C: ConstraintSynthesizer<E::Fr>,
+    {
+        let _span = info_span!("prove").entered();
+        let zk_rng = &mut rng_config.build_stream(PROVE_STREAM);
+        let marlin_err = |e| format!("{:?}", e);
+        let pc_err = |e| format!("{:?}", e);
+        let committed = |done| Progress::Committed {
+            done,
+            total: MARLIN_ROUNDS,
+        };
+
+        let state = AHPForR1CS::prover_init(&pk.index, circuit).map_err(marlin_err)?;
+        let public_input = state.public_input();
+        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(
+            &to_bytes![&MarlinInst::<E>::PROTOCOL_NAME, &pk.index_vk, &public_input].unwrap(),
+        );
+        progress(Progress::Synthesized);
+
+        let (first_msg, first_oracles, state) =
+            AHPForR1CS::prover_first_round(state, zk_rng).map_err(marlin_err)?;
+        let (first_comms, first_rands) =
+            MarlinPC::<E>::commit(&pk.committer_key, first_oracles.iter(), Some(zk_rng))
+                .map_err(pc_err)?;
+        fs_rng.absorb(&to_bytes![first_comms, first_msg].unwrap());
+        let (verifier_first_msg, verifier_state) =
+            AHPForR1CS::verifier_first_round(pk.index_vk.index_info, &mut fs_rng)
+                .map_err(marlin_err)?;
+        progress(committed(1));
+
+        let (second_msg, second_oracles, state) =
+            AHPForR1CS::prover_second_round(&verifier_first_msg, state, zk_rng);
+        let (second_comms, second_rands) =
+            MarlinPC::<E>::commit(&pk.committer_key, second_oracles.iter(), Some(zk_rng))
+                .map_err(pc_err)?;
+        fs_rng.absorb(&to_bytes![second_comms, second_msg].unwrap());
+        let (verifier_second_msg, verifier_state) =
+            AHPForR1CS::verifier_second_round(verifier_state, &mut fs_rng);
+        progress(committed(2));
+
+        let (third_msg, third_oracles) =
+            AHPForR1CS::prover_third_round(&verifier_second_msg, state, zk_rng)
+                .map_err(marlin_err)?;
+        let (third_comms, third_rands) =
+            MarlinPC::<E>::commit(&pk.committer_key, third_oracles.iter(), Some(zk_rng))
+                .map_err(pc_err)?;
+        fs_rng.absorb(&to_bytes![third_comms, third_msg].unwrap());
+        let verifier_state = AHPForR1CS::verifier_third_round(verifier_state, &mut fs_rng);
+        progress(committed(3));
+
+        let polynomials: Vec<_> = pk
+            .index
+            .iter()
+            .chain(first_oracles.iter())
+            .chain(second_oracles.iter())
+            .chain(third_oracles.iter())
+            .collect();
+        let commitments = vec![
+            first_comms.iter().map(|c| *c.commitment()).collect(),
+            second_comms.iter().map(|c| *c.commitment()).collect(),
+            third_comms.iter().map(|c| *c.commitment()).collect(),
+        ];
+        let labeled_comms: Vec<_> = pk
+            .index_vk
+            .iter()
+            .cloned()
+            .zip(AHPForR1CS::<E::Fr>::INDEXER_POLYNOMIALS.iter())
+            .map(|(c, l)| LabeledCommitment::new(l.to_string(), c, None))
+            .chain(first_comms.iter().cloned())
+            .chain(second_comms.iter().cloned())
+            .chain(third_comms.iter().cloned())
+            .collect();
+        let comm_rands: Vec<_> = pk
+            .index_comm_rands
+            .iter()
+            .cloned()
+            .chain(first_rands)
+            .chain(second_rands)
+            .chain(third_rands)
+            .collect();
+
+        let (query_set, verifier_state) =
+            AHPForR1CS::verifier_query_set(verifier_state, &mut fs_rng);
+        let lc_s =
+            AHPForR1CS::construct_linear_combinations(&public_input, &polynomials, &verifier_state)
+                .map_err(marlin_err)?;
+        let mut evaluations = Vec::new();
+        for (label, (_, point)) in &query_set {
+            let lc = lc_s
+                .iter()
+                .find(|lc| &lc.label == label)
+                .ok_or_else(|| format!("no linear combination {}", label))?;
+            let eval = polynomials.get_lc_eval(lc, *point).map_err(marlin_err)?;
+            if !AHPForR1CS::<E::Fr>::LC_WITH_ZERO_EVAL.contains(&lc.label.as_ref()) {
+                evaluations.push((label.to_string(), eval));
+            }
+        }
+        evaluations.sort_by(|a, b| a.0.cmp(&b.0));
+        let evaluations: Vec<E::Fr> = evaluations.into_iter().map(|(_, eval)| eval).collect();
+        fs_rng.absorb(&evaluations);
+        let opening_challenge: E::Fr = u128::rand(&mut fs_rng).into();
+
+        let pc_proof = MarlinPC::<E>::open_combinations(
+            &pk.committer_key,
+            &lc_s,
+            polynomials,
+            &labeled_comms,
+            &query_set,
+            opening_challenge,
+            &comm_rands,
+            Some(zk_rng),
+        )
+        .map_err(pc_err)?;
+        progress(Progress::Opened);
+
+        Ok(MarlinProof::<E>::new(
+            commitments,
+            evaluations,
+            vec![first_msg, second_msg, third_msg],
+            pc_proof,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "groth16")]
+    #[test]
+    fn test_groth16_progress() {
+        use crate::prover;
+        use crate::synthetic::SyntheticCircuit;
+        use ark_bls12_381::Bls12_381;
+        use ark_groth16::Groth16;
+
+        let config = RngConfig::seeded(1);
+        let circuit = SyntheticCircuit::new(64, 48, 3).with_public_inputs(2);
+        let (pk, vk) = prover::setup::<_, Groth16<Bls12_381>, _>(circuit, &config).unwrap();
+
+        let mut phases = Vec::new();
+        let proof = prove_groth16(&pk, circuit, &config, |p| phases.push(p)).unwrap();
+        assert_eq!(
+            proof,
+            prover::prove::<_, Groth16<Bls12_381>, _>(&pk, circuit, &config).unwrap()
+        );
+        let inputs = circuit.public_inputs();
+        assert!(prover::verify::<_, Groth16<Bls12_381>>(&vk, &inputs, &proof).unwrap());
+
+        let mut expected = vec![Progress::Synthesized, Progress::WitnessMapped];
+        expected.extend((1..=GROTH16_MSMS).map(|done| Progress::Committed {
+            done,
+            total: GROTH16_MSMS,
+        }));
+        assert_eq!(phases, expected);
+        assert_eq!(phases[2].to_string(), "commitments 1/5");
+    }
+
+    #[cfg(feature = "marlin")]
+    #[test]
+    fn test_marlin_progress() {
+        use crate::cubic_demo::{CubicDemoCircuit, CubicWitness};
+        use crate::marlin_srs::{MarlinInst, MarlinSrsSize, UniversalSetup};
+        use ark_bls12_381::{Bls12_381, Fr};
+        use ark_serialize::CanonicalSerialize;
+        use std::sync::mpsc;
+
+        let config = RngConfig::seeded(1);
+        let size = MarlinSrsSize::of(CubicDemoCircuit::<Fr>::blank()).unwrap();
+        let setup = UniversalSetup::<Bls12_381>::new(size, &mut config.build()).unwrap();
+        let (pk, vk) = setup.index_circuit(CubicDemoCircuit::blank()).unwrap();
+        let witness = CubicWitness { x: Fr::from(3u8) };
+
+        // through a channel, as a GUI thread would get them
+        let (sender, receiver) = mpsc::channel();
+        let proof = prove_marlin(&pk, CubicDemoCircuit::from(witness), &config, |p| {
+            sender.send(p).unwrap()
+        })
+        .unwrap();
+        let expected = MarlinInst::<Bls12_381>::prove(
+            &pk,
+            CubicDemoCircuit::from(witness),
+            &mut config.build_stream(PROVE_STREAM),
+        )
+        .unwrap();
+        let bytes = |proof: &crate::marlin_srs::MarlinProof<Bls12_381>| {
+            let mut bytes = Vec::new();
+            proof.serialize(&mut bytes).unwrap();
+            bytes
+        };
+        assert_eq!(bytes(&proof), bytes(&expected));
+        let inputs = witness.instance().public_inputs();
+        assert!(
+            MarlinInst::<Bls12_381>::verify(&vk, &inputs, &proof, &mut config.build()).unwrap()
+        );
+
+        drop(sender);
+        let phases: Vec<_> = receiver.iter().collect();
+        assert_eq!(phases.len(), 2 + MARLIN_ROUNDS);
+        assert_eq!(phases[0], Progress::Synthesized);
+        assert_eq!(
+            phases[MARLIN_ROUNDS],
+            Progress::Committed {
+                done: MARLIN_ROUNDS,
+                total: MARLIN_ROUNDS
+            }
+        );
+        assert_eq!(phases[MARLIN_ROUNDS + 1], Progress::Opened);
+    }
+}
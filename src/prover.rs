@@ -0,0 +1,565 @@
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{
+    ConstraintLayer, ConstraintSynthesizer, ConstraintSystem, SynthesisError, TracingMode,
+};
+use ark_snark::SNARK;
+use ark_std::rand::{CryptoRng, RngCore};
+use std::fmt;
+use std::time::{Duration, Instant};
+use tracing::{debug, info_span};
+use tracing_subscriber::layer::SubscriberExt;
+
+use crate::rng::{RngConfig, PROVE_STREAM, SETUP_STREAM};
+use crate::Error;
+
+// thin wrappers around any `SNARK` (e.g. Groth16) that take the randomness source as a
+// `RngConfig` instead of an rng instance, so the same code path can run with OS
+// randomness in production and a fixed seed in tests (`insecure` feature only).
+// `setup_with_rng` and `prove_with_rng` take the caller's CSPRNG instead, e.g. one
+// backed by an HSM.
+// Every phase runs inside a `tracing` span (setup, witness_generation, prove, verify).
+// The MSMs and FFTs inside the backends are not visible from here, build with the
+// `print-trace` feature to get the internal arkworks timers for those.
+
+pub fn setup<F, S, C>(
+    circuit: C,
+    rng_config: &RngConfig,
+) -> Result<(S::ProvingKey, S::VerifyingKey), S::Error>
+where
+    F: PrimeField,
+    S: SNARK<F>,
+    C: ConstraintSynthesizer<F>,
+{
+    let _span = info_span!("setup").entered();
+    let mut rng = rng_config.build_stream(SETUP_STREAM);
+    S::circuit_specific_setup(circuit, &mut rng)
+}
+
+pub fn prove<F, S, C>(
+    pk: &S::ProvingKey,
+    circuit: C,
+    rng_config: &RngConfig,
+) -> Result<S::Proof, S::Error>
+where
+    F: PrimeField,
+    S: SNARK<F>,
+    C: ConstraintSynthesizer<F>,
+{
+    let _span = info_span!("prove").entered();
+    let mut rng = rng_config.build_stream(PROVE_STREAM);
+    S::prove(pk, circuit, &mut rng)
+}
+
+pub fn setup_with_rng<F, S, C, R>(
+    circuit: C,
+    rng: &mut R,
+) -> Result<(S::ProvingKey, S::VerifyingKey), S::Error>
+where
+    F: PrimeField,
+    S: SNARK<F>,
+    C: ConstraintSynthesizer<F>,
+    R: RngCore + CryptoRng,
+{
+    let _span = info_span!("setup").entered();
+    S::circuit_specific_setup(circuit, rng)
+}
+
+pub fn prove_with_rng<F, S, C, R>(
+    pk: &S::ProvingKey,
+    circuit: C,
+    rng: &mut R,
+) -> Result<S::Proof, S::Error>
+where
+    F: PrimeField,
+    S: SNARK<F>,
+    C: ConstraintSynthesizer<F>,
+    R: RngCore + CryptoRng,
+{
+    let _span = info_span!("prove").entered();
+    S::prove(pk, circuit, rng)
+}
+
+// opt-in checks of `prove_with_options`, all off by default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProveOptions {
+    // synthesize the circuit into a debug constraint system first and fail with the first
+    // unsatisfied constraint, instead of returning a proof that does not verify (release
+    // builds of ark-groth16 prove anyway, debug builds panic). Costs one extra witness
+    // generation.
+    pub check_satisfaction_before_prove: bool,
+}
+
+// the first constraint a witness violates, `path` is the namespace (`ns!`) it was
+// enforced in. Like `matrices`, constraints are labeled `c<index>` unless every one of
+// them is enforced inside a namespace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsatisfiedConstraint {
+    pub index: usize,
+    pub path: String,
+}
+
+impl fmt::Display for UnsatisfiedConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "constraint {} `{}` is not satisfied",
+            self.index, self.path
+        )
+    }
+}
+
+impl std::error::Error for UnsatisfiedConstraint {}
+
+pub fn prove_with_options<F, S, C>(
+    pk: &S::ProvingKey,
+    circuit: C,
+    rng_config: &RngConfig,
+    options: &ProveOptions,
+) -> Result<S::Proof, Error>
+where
+    F: PrimeField,
+    S: SNARK<F>,
+    C: ConstraintSynthesizer<F> + Clone,
+{
+    if options.check_satisfaction_before_prove {
+        let _span = info_span!("check_satisfaction").entered();
+        if let Some(unsatisfied) = which_is_unsatisfied(circuit.clone())? {
+            return Err(unsatisfied.into());
+        }
+    }
+    Ok(prove::<F, S, C>(pk, circuit, rng_config)?)
+}
+
+// synthesizes the circuit with its witness and a `ConstraintLayer` installed, None if
+// every constraint holds
+pub fn which_is_unsatisfied<F, C>(
+    circuit: C,
+) -> Result<Option<UnsatisfiedConstraint>, SynthesisError>
+where
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+{
+    let subscriber = tracing_subscriber::Registry::default()
+        .with(ConstraintLayer::new(TracingMode::OnlyConstraints));
+    tracing::subscriber::with_default(subscriber, || {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone())?;
+        if cs.is_satisfied()? {
+            return Ok(None);
+        }
+        cs.finalize();
+
+        let matrices = cs.to_matrices().ok_or(SynthesisError::MissingCS)?;
+        let assignment: Vec<F> = {
+            let cs = cs.borrow().ok_or(SynthesisError::MissingCS)?;
+            cs.instance_assignment
+                .iter()
+                .chain(&cs.witness_assignment)
+                .copied()
+                .collect()
+        };
+        let eval = |row: &[(F, usize)]| -> F {
+            row.iter()
+                .map(|&(coeff, column)| coeff * assignment[column])
+                .sum()
+        };
+        let index = (0..matrices.num_constraints)
+            .find(|&i| eval(&matrices.a[i]) * eval(&matrices.b[i]) != eval(&matrices.c[i]))
+            .ok_or(SynthesisError::Unsatisfiable)?;
+        let path = cs
+            .constraint_names()
+            .and_then(|names| names.get(index).cloned())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("c{}", index));
+        Ok(Some(UnsatisfiedConstraint { index, path }))
+    })
+}
+
+// the error of one instance of `prove_many`, Send so the instances can run on threads
+type InstanceError = Box<dyn std::error::Error + Send + Sync>;
+
+// proves every witness with the same key, one result per witness in their order. A
+// witness `circuit` rejects (e.g. a value that does not parse) or whose proof fails is an
+// Err in its slot and the others are proved all the same. Groth16 proves an unsatisfied
+// witness without complaint (see `ProveOptions`); with
+// `check_satisfaction_before_prove` that is an Err too. Every instance draws from its own
+// rng stream, so seeded runs are reproducible in any order.
+pub fn prove_many<F, S, W, C, E>(
+    pk: &S::ProvingKey,
+    witnesses: &[W],
+    circuit: impl Fn(&W) -> Result<C, E>,
+    rng_config: &RngConfig,
+    options: &ProveOptions,
+) -> Vec<Result<S::Proof, Error>>
+where
+    F: PrimeField,
+    S: SNARK<F>,
+    S::Error: Send + Sync,
+    C: ConstraintSynthesizer<F> + Clone,
+    E: Into<InstanceError>,
+{
+    let _span = info_span!("prove_many", instances = witnesses.len()).entered();
+    witnesses
+        .iter()
+        .enumerate()
+        .map(|(index, witness)| {
+            prove_instance::<F, S, W, C, E>(pk, index, witness, &circuit, rng_config, options)
+                .map_err(|e| e as Error)
+        })
+        .collect()
+}
+
+// `prove_many` on all cores
+#[cfg(feature = "parallel")]
+pub fn par_prove_many<F, S, W, C, E>(
+    pk: &S::ProvingKey,
+    witnesses: &[W],
+    circuit: impl Fn(&W) -> Result<C, E> + Sync,
+    rng_config: &RngConfig,
+    options: &ProveOptions,
+) -> Vec<Result<S::Proof, Error>>
+where
+    F: PrimeField,
+    S: SNARK<F>,
+    S::ProvingKey: Sync,
+    S::Proof: Send,
+    S::Error: Send + Sync,
+    W: Sync,
+    C: ConstraintSynthesizer<F> + Clone,
+    E: Into<InstanceError>,
+{
+    use rayon::prelude::*;
+
+    let _span = info_span!("prove_many", instances = witnesses.len()).entered();
+    let results: Vec<_> = witnesses
+        .par_iter()
+        .enumerate()
+        .map(|(index, witness)| {
+            prove_instance::<F, S, W, C, E>(pk, index, witness, &circuit, rng_config, options)
+        })
+        .collect();
+    results
+        .into_iter()
+        .map(|result| result.map_err(|e| e as Error))
+        .collect()
+}
+
+fn prove_instance<F, S, W, C, E>(
+    pk: &S::ProvingKey,
+    index: usize,
+    witness: &W,
+    circuit: &impl Fn(&W) -> Result<C, E>,
+    rng_config: &RngConfig,
+    options: &ProveOptions,
+) -> Result<S::Proof, InstanceError>
+where
+    F: PrimeField,
+    S: SNARK<F>,
+    S::Error: Send + Sync,
+    C: ConstraintSynthesizer<F> + Clone,
+    E: Into<InstanceError>,
+{
+    let _span = info_span!("prove", index).entered();
+    let circuit = circuit(witness).map_err(Into::into)?;
+    if options.check_satisfaction_before_prove {
+        if let Some(unsatisfied) = which_is_unsatisfied(circuit.clone())? {
+            return Err(unsatisfied.into());
+        }
+    }
+    let mut rng = rng_config.build_stream(PROVE_STREAM + index as u64);
+    Ok(S::prove(pk, circuit, &mut rng)?)
+}
+
+pub fn verify<F, S>(
+    vk: &S::VerifyingKey,
+    public_inputs: &[F],
+    proof: &S::Proof,
+) -> Result<bool, S::Error>
+where
+    F: PrimeField,
+    S: SNARK<F>,
+{
+    let _span = info_span!("verify", num_inputs = public_inputs.len()).entered();
+    S::verify(vk, public_inputs, proof)
+}
+
+// where the time goes for one circuit: size of the constraint system and wall clock
+// time of each phase
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProverSummary {
+    pub num_constraints: usize,
+    pub num_instance_variables: usize,
+    pub num_witness_variables: usize,
+    pub setup: Duration,
+    pub witness_generation: Duration,
+    pub prove: Duration,
+    pub verify: Duration,
+    pub verified: bool,
+}
+
+impl fmt::Display for ProverSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "constraints: {}, instance variables: {}, witness variables: {}",
+            self.num_constraints, self.num_instance_variables, self.num_witness_variables
+        )?;
+        for (phase, time) in [
+            ("setup", self.setup),
+            ("witness generation", self.witness_generation),
+            ("prove", self.prove),
+            ("verify", self.verify),
+        ]
+        .iter()
+        {
+            writeln!(f, "{:<20} {:>10.3} ms", phase, time.as_secs_f64() * 1e3)?;
+        }
+        writeln!(f, "verified: {}", self.verified)
+    }
+}
+
+// runs setup, prove and verify once and reports the time of every phase. Witness
+// generation is measured on its own by synthesizing a copy of the circuit, proving
+// synthesizes it again internally.
+pub fn profile<F, S, C>(
+    circuit: C,
+    public_inputs: &[F],
+    rng_config: &RngConfig,
+) -> Result<ProverSummary, Error>
+where
+    F: PrimeField,
+    S: SNARK<F>,
+    C: ConstraintSynthesizer<F> + Clone,
+{
+    let mut summary = ProverSummary::default();
+
+    let start = Instant::now();
+    let (pk, vk) = setup::<F, S, C>(circuit.clone(), rng_config)?;
+    summary.setup = start.elapsed();
+
+    let start = Instant::now();
+    {
+        let _span = info_span!("witness_generation").entered();
+        let cs = ConstraintSystem::new_ref();
+        circuit.clone().generate_constraints(cs.clone())?;
+        summary.num_constraints = cs.num_constraints();
+        summary.num_instance_variables = cs.num_instance_variables();
+        summary.num_witness_variables = cs.num_witness_variables();
+    }
+    summary.witness_generation = start.elapsed();
+
+    let start = Instant::now();
+    let proof = prove::<F, S, C>(&pk, circuit, rng_config)?;
+    summary.prove = start.elapsed();
+
+    let start = Instant::now();
+    summary.verified = verify::<F, S>(&vk, public_inputs, &proof)?;
+    summary.verify = start.elapsed();
+
+    debug!(?summary, "profiled circuit");
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+    use ark_groth16::Groth16;
+    use ark_relations::{lc, ns, r1cs::ConstraintSystemRef};
+
+    type G16 = Groth16<Bls12_381>;
+
+    // x * x = y with y taken as given, so a wrong y is a witness that does not satisfy it
+    #[derive(Clone, Copy)]
+    struct Square {
+        x: Option<BlsFr>,
+        y: Option<BlsFr>,
+    }
+
+    impl ConstraintSynthesizer<BlsFr> for Square {
+        fn generate_constraints(
+            self,
+            cs: ConstraintSystemRef<BlsFr>,
+        ) -> Result<(), SynthesisError> {
+            let x = cs.new_witness_variable(|| self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.new_input_variable(|| self.y.ok_or(SynthesisError::AssignmentMissing))?;
+            {
+                let _ns = ns!(cs, "x * x = x");
+                cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + x)?;
+            }
+            let _ns = ns!(cs, "x * x = y");
+            cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + y)
+        }
+    }
+
+    #[test]
+    fn test_seeded_setup_and_prove_are_reproducible() {
+        let config = RngConfig::seeded(1);
+        let blank = MultiplyDemoCircuit::<BlsFr> { a: None, b: None };
+        let circuit = MultiplyDemoCircuit {
+            a: Some(BlsFr::from(3u8)),
+            b: Some(BlsFr::from(5u8)),
+        };
+
+        let (pk1, vk1) = setup::<_, G16, _>(blank, &config).unwrap();
+        let (pk2, vk2) = setup::<_, G16, _>(blank, &config).unwrap();
+        assert_eq!(pk1, pk2);
+        assert_eq!(vk1, vk2);
+
+        let proof1 = prove::<_, G16, _>(&pk1, circuit, &config).unwrap();
+        let proof2 = prove::<_, G16, _>(&pk2, circuit, &config).unwrap();
+        assert_eq!(proof1, proof2);
+        assert!(verify::<_, G16>(&vk1, &[BlsFr::from(15u8)], &proof1).unwrap());
+
+        // a different seed gives different parameters
+        let (_, vk3) = setup::<_, G16, _>(blank, &RngConfig::seeded(2)).unwrap();
+        assert_ne!(vk1, vk3);
+    }
+
+    #[test]
+    fn test_os_randomness() {
+        let config = RngConfig::Os;
+        let blank = MultiplyDemoCircuit::<BlsFr> { a: None, b: None };
+        let circuit = MultiplyDemoCircuit {
+            a: Some(BlsFr::from(3u8)),
+            b: Some(BlsFr::from(5u8)),
+        };
+
+        let (pk, vk) = setup::<_, G16, _>(blank, &config).unwrap();
+        let proof1 = prove::<_, G16, _>(&pk, circuit, &config).unwrap();
+        let proof2 = prove::<_, G16, _>(&pk, circuit, &config).unwrap();
+        assert_ne!(proof1, proof2);
+        assert!(verify::<_, G16>(&vk, &[BlsFr::from(15u8)], &proof1).unwrap());
+        assert!(verify::<_, G16>(&vk, &[BlsFr::from(15u8)], &proof2).unwrap());
+    }
+
+    #[test]
+    fn test_caller_rng() {
+        let rng = &mut ark_std::test_rng();
+        let blank = MultiplyDemoCircuit::<BlsFr> { a: None, b: None };
+        let circuit = MultiplyDemoCircuit {
+            a: Some(BlsFr::from(3u8)),
+            b: Some(BlsFr::from(5u8)),
+        };
+
+        let (pk, vk) = setup_with_rng::<_, G16, _, _>(blank, rng).unwrap();
+        let proof = prove_with_rng::<_, G16, _, _>(&pk, circuit, rng).unwrap();
+        assert!(verify::<_, G16>(&vk, &[BlsFr::from(15u8)], &proof).unwrap());
+        // the keys work with the other entry points
+        let proof = prove::<_, G16, _>(&pk, circuit, &RngConfig::Os).unwrap();
+        assert!(verify::<_, G16>(&vk, &[BlsFr::from(15u8)], &proof).unwrap());
+    }
+
+    #[test]
+    fn test_profile() {
+        let circuit = MultiplyDemoCircuit {
+            a: Some(BlsFr::from(3u8)),
+            b: Some(BlsFr::from(5u8)),
+        };
+        let config = RngConfig::seeded(1);
+
+        let summary = profile::<_, G16, _>(circuit, &[BlsFr::from(15u8)], &config).unwrap();
+        assert!(summary.verified);
+        assert_eq!(summary.num_constraints, 1);
+        // the constant one plus c
+        assert_eq!(summary.num_instance_variables, 2);
+        assert_eq!(summary.num_witness_variables, 2);
+        assert!(summary.setup > Duration::from_secs(0));
+        assert!(summary.to_string().contains("witness generation"));
+
+        let summary = profile::<_, G16, _>(circuit, &[BlsFr::from(16u8)], &config).unwrap();
+        assert!(!summary.verified);
+    }
+
+    #[test]
+    fn test_check_satisfaction_before_prove() {
+        let config = RngConfig::seeded(1);
+        let blank = Square { x: None, y: None };
+        let (pk, vk) = setup::<_, G16, _>(blank, &config).unwrap();
+        let check = ProveOptions {
+            check_satisfaction_before_prove: true,
+        };
+
+        // x = 1 keeps the first constraint, x * x = x, satisfied
+        let one = BlsFr::from(1u8);
+        let honest = Square {
+            x: Some(one),
+            y: Some(one),
+        };
+        assert_eq!(which_is_unsatisfied(honest).unwrap(), None);
+        let proof = prove_with_options::<_, G16, _>(&pk, honest, &config, &check).unwrap();
+        assert!(verify::<_, G16>(&vk, &[one], &proof).unwrap());
+
+        let wrong = Square {
+            x: Some(one),
+            y: Some(BlsFr::from(2u8)),
+        };
+        let unsatisfied = which_is_unsatisfied(wrong).unwrap().unwrap();
+        assert_eq!(unsatisfied.index, 1);
+        // the namespace is prefixed with the module the circuit lives in
+        assert!(unsatisfied.path.ends_with("x * x = y"));
+        let err = prove_with_options::<_, G16, _>(&pk, wrong, &config, &check).unwrap_err();
+        assert_eq!(err.to_string(), unsatisfied.to_string());
+
+        let wrong = Square {
+            x: Some(BlsFr::from(2u8)),
+            y: Some(BlsFr::from(4u8)),
+        };
+        let unsatisfied = which_is_unsatisfied(wrong).unwrap().unwrap();
+        assert_eq!(unsatisfied.index, 0);
+        assert!(unsatisfied.path.ends_with("x * x = x"));
+    }
+
+    #[test]
+    fn test_prove_many() {
+        let config = RngConfig::seeded(1);
+        let (pk, vk) = setup::<_, G16, _>(Square { x: None, y: None }, &config).unwrap();
+        let check = ProveOptions {
+            check_satisfaction_before_prove: true,
+        };
+        // (x, y) as text: an honest one, one that does not parse, one with y missing, one
+        // that does not satisfy x * x = y, and another honest one
+        let witnesses = ["1,1", "1,one", "1", "1,2", "0,0"];
+        let circuit = |witness: &&str| -> Result<Square, String> {
+            let mut values = witness.split(',').map(|v| {
+                v.parse::<BlsFr>()
+                    .map_err(|_| format!("not a number: {:?}", v))
+            });
+            let x = values.next().transpose()?;
+            let y = values.next().transpose()?;
+            Ok(Square { x, y })
+        };
+
+        let proofs = prove_many::<_, G16, _, _, _>(&pk, &witnesses, circuit, &config, &check);
+        assert_eq!(proofs.len(), witnesses.len());
+        assert!(verify::<_, G16>(&vk, &[BlsFr::from(1u8)], proofs[0].as_ref().unwrap()).unwrap());
+        assert!(proofs[1]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .contains("not a number"));
+        assert!(proofs[2].as_ref().unwrap_err().is::<SynthesisError>());
+        assert!(proofs[3]
+            .as_ref()
+            .unwrap_err()
+            .is::<UnsatisfiedConstraint>());
+        assert!(verify::<_, G16>(&vk, &[BlsFr::from(0u8)], proofs[4].as_ref().unwrap()).unwrap());
+
+        // the same proofs from the same seed, on threads
+        #[cfg(feature = "parallel")]
+        {
+            let parallel =
+                par_prove_many::<_, G16, _, _, _>(&pk, &witnesses, circuit, &config, &check);
+            for (serial, parallel) in proofs.iter().zip(&parallel) {
+                match (serial, parallel) {
+                    (Ok(serial), Ok(parallel)) => assert_eq!(serial, parallel),
+                    (Err(serial), Err(parallel)) => {
+                        assert_eq!(serial.to_string(), parallel.to_string())
+                    }
+                    _ => panic!("serial and parallel results differ"),
+                }
+            }
+        }
+    }
+}
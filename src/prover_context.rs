@@ -0,0 +1,125 @@
+use ark_ff::PrimeField;
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_snark::SNARK;
+use ark_std::rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::prover;
+use crate::rng::RngConfig;
+
+// The keys of one circuit, loaded once and shared by every thread or request handler
+// of a server. The proving key is by far the largest artifact (hundreds of megabytes
+// for real circuits) and proving only reads it, so the context keeps it, the verifying
+// key and the processed verifying key behind `Arc`: cloning a context is three reference
+// counts, and it is `Send + Sync` whenever the SNARK's keys are, as Groth16's are.
+// Every proof draws fresh randomness from the OS, proofs made concurrently never share
+// their blinding; `prove_with_rng` takes the caller's CSPRNG instead.
+//
+// A Marlin prover key (`marlin_srs::MarlinProvingKey`) carries the part of the SRS
+// proving needs and is shared the same way, in an `Arc` of its own; `ark-marlin` 0.3
+// does not implement `SNARK`.
+
+pub struct ProverContext<F: PrimeField, S: SNARK<F>> {
+    pk: Arc<S::ProvingKey>,
+    vk: Arc<S::VerifyingKey>,
+    pvk: Arc<S::ProcessedVerifyingKey>,
+    // a function pointer, so the field does not decide whether the context is Send
+    _field: PhantomData<fn() -> F>,
+}
+
+impl<F: PrimeField, S: SNARK<F>> Clone for ProverContext<F, S> {
+    fn clone(&self) -> Self {
+        Self {
+            pk: self.pk.clone(),
+            vk: self.vk.clone(),
+            pvk: self.pvk.clone(),
+            _field: PhantomData,
+        }
+    }
+}
+
+impl<F: PrimeField, S: SNARK<F>> ProverContext<F, S> {
+    pub fn new(pk: S::ProvingKey, vk: S::VerifyingKey) -> Result<Self, S::Error> {
+        let pvk = S::process_vk(&vk)?;
+        Ok(Self {
+            pk: Arc::new(pk),
+            vk: Arc::new(vk),
+            pvk: Arc::new(pvk),
+            _field: PhantomData,
+        })
+    }
+
+    // a circuit specific setup with OS randomness
+    pub fn setup<C: ConstraintSynthesizer<F>>(circuit: C) -> Result<Self, S::Error> {
+        let (pk, vk) = prover::setup::<F, S, C>(circuit, &RngConfig::Os)?;
+        Self::new(pk, vk)
+    }
+
+    pub fn prove<C: ConstraintSynthesizer<F>>(&self, circuit: C) -> Result<S::Proof, S::Error> {
+        prover::prove::<F, S, C>(&self.pk, circuit, &RngConfig::Os)
+    }
+
+    pub fn prove_with_rng<C, R>(&self, circuit: C, rng: &mut R) -> Result<S::Proof, S::Error>
+    where
+        C: ConstraintSynthesizer<F>,
+        R: RngCore + CryptoRng,
+    {
+        prover::prove_with_rng::<F, S, C, R>(&self.pk, circuit, rng)
+    }
+
+    pub fn verify(&self, public_inputs: &[F], proof: &S::Proof) -> Result<bool, S::Error> {
+        S::verify_with_processed_vk(&self.pvk, public_inputs, proof)
+    }
+
+    // the shared key itself, e.g. for `async_prover::prove_async`
+    pub fn proving_key(&self) -> &Arc<S::ProvingKey> {
+        &self.pk
+    }
+
+    pub fn verifying_key(&self) -> &Arc<S::VerifyingKey> {
+        &self.vk
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::{CubicDemoCircuit, CubicWitness};
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::Groth16;
+    use std::thread;
+
+    type Context = ProverContext<Fr, Groth16<Bls12_381>>;
+
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn test_concurrent_proofs() {
+        assert_send_sync::<Context>();
+        let context = Context::setup(CubicDemoCircuit::blank()).unwrap();
+
+        // each thread its own witness, all against the one key
+        let handles: Vec<_> = (0..4u64)
+            .map(|x| {
+                let context = context.clone();
+                thread::spawn(move || {
+                    let witness = CubicWitness { x: Fr::from(x) };
+                    let proof = context.prove(CubicDemoCircuit::from(witness)).unwrap();
+                    (witness.instance().public_inputs(), proof)
+                })
+            })
+            .collect();
+        let proofs: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        for (inputs, proof) in &proofs {
+            assert!(context.verify(inputs, proof).unwrap());
+        }
+        assert!(!context.verify(&proofs[0].0, &proofs[1].1).unwrap());
+        // nothing was copied
+        assert_eq!(Arc::strong_count(context.proving_key()), 1);
+        assert!(Arc::ptr_eq(
+            context.verifying_key(),
+            context.clone().verifying_key()
+        ));
+    }
+}
@@ -0,0 +1,261 @@
+use ark_bls12_381::Fr;
+use ark_ff::Zero;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::poseidon::PoseidonParameters;
+
+use crate::boolean_demo::to_bits;
+use crate::encoding::{field_from_bytes, Endian};
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+use crate::private_index_demo::one_hot;
+
+// Quoting an undisclosed document: the hash of a document is public, e.g. signed or
+// timestamped, and its holder shows that a quote appears in it at some offset without
+// revealing anything else, a redacted copy that can be checked.
+//
+// A document is up to `MAX_DOCUMENT_BYTES` bytes, zero padded and hashed in chunks of
+// `CHUNK_BYTES` bytes, as many as fit in a field element, together with its length:
+// H(length, chunk_0, ..., chunk_k). The bytes are `UInt8` witnesses, so each is range
+// checked by its bits, and a chunk is the linear combination of the bits of its bytes.
+// The quote is public and packed the same way into one chunk. To find it at the public
+// offset the document is shifted left by the offset, one conditional shift by 2^k per
+// bit of the offset (a barrel shifter), and the first bytes of the result are compared
+// with the quote up to its length.
+
+pub const CHUNK_BYTES: usize = 31;
+pub const DOCUMENT_CHUNKS: usize = 8;
+pub const MAX_DOCUMENT_BYTES: usize = DOCUMENT_CHUNKS * CHUNK_BYTES;
+pub const MAX_QUOTE_BYTES: usize = CHUNK_BYTES;
+// offsets and lengths up to 255
+const OFFSET_BITS: usize = 8;
+
+// little endian chunks of `CHUNK_BYTES` bytes
+pub fn pack(bytes: &[u8]) -> Vec<Fr> {
+    bytes
+        .chunks(CHUNK_BYTES)
+        .map(|chunk| field_from_bytes(chunk, Endian::Little).expect("a chunk is below the modulus"))
+        .collect()
+}
+
+pub fn document_hash(params: &PoseidonParameters<Fr>, document: &[u8]) -> Fr {
+    assert!(
+        document.len() <= MAX_DOCUMENT_BYTES,
+        "the document is too long"
+    );
+    let mut padded = document.to_vec();
+    padded.resize(MAX_DOCUMENT_BYTES, 0);
+    let mut inputs = vec![Fr::from(document.len() as u64)];
+    inputs.extend(pack(&padded));
+    hash_inputs(params, &inputs)
+}
+
+#[derive(Clone)]
+pub struct RedactionCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub hash: Fr,
+    pub offset: usize,
+    pub quote: Vec<u8>,
+    pub document: Option<Vec<u8>>,
+}
+
+impl RedactionCircuit {
+    // the quote of `length` bytes at `offset`, None if it is not within the document
+    pub fn new(
+        params: PoseidonParameters<Fr>,
+        document: &[u8],
+        offset: usize,
+        length: usize,
+    ) -> Option<Self> {
+        if length > MAX_QUOTE_BYTES || offset + length > document.len() {
+            return None;
+        }
+        Some(Self {
+            hash: document_hash(&params, document),
+            params,
+            offset,
+            quote: document[offset..offset + length].to_vec(),
+            document: Some(document.to_vec()),
+        })
+    }
+
+    // the hash, the offset and the quote are public inputs
+    pub fn blank(params: PoseidonParameters<Fr>) -> Self {
+        Self {
+            params,
+            hash: Fr::zero(),
+            offset: 0,
+            quote: Vec::new(),
+            document: None,
+        }
+    }
+
+    // the hash, the offset, the length of the quote and the quote
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        vec![
+            self.hash,
+            Fr::from(self.offset as u64),
+            Fr::from(self.quote.len() as u64),
+            pack(&self.quote).pop().unwrap_or_else(Fr::zero),
+        ]
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for RedactionCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let inputs = self.public_inputs();
+        let hash = FpVar::new_input(ns!(cs, "hash"), || Ok(inputs[0]))?;
+        let offset = FpVar::new_input(ns!(cs, "offset"), || Ok(inputs[1]))?;
+        let quote_length = FpVar::new_input(ns!(cs, "quote_length"), || Ok(inputs[2]))?;
+        let quote = FpVar::new_input(ns!(cs, "quote"), || Ok(inputs[3]))?;
+
+        let document = self.document.as_ref();
+        let length = FpVar::new_witness(ns!(cs, "length"), || {
+            document
+                .map(|d| Fr::from(d.len() as u64))
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let bytes = (0..MAX_DOCUMENT_BYTES)
+            .map(|i| {
+                UInt8::new_witness(ns!(cs, "byte"), || {
+                    document
+                        .map(|d| d.get(i).copied().unwrap_or(0))
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let bytes = bytes
+            .iter()
+            .map(|byte| Boolean::le_bits_to_fp_var(&byte.to_bits_le()?))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // the chunks are sums of the bytes times powers of 256
+        let mut chunks = vec![length.clone()];
+        for chunk in bytes.chunks(CHUNK_BYTES) {
+            let mut packed = FpVar::zero();
+            for byte in chunk.iter().rev() {
+                packed = packed * Fr::from(256u16) + byte;
+            }
+            chunks.push(packed);
+        }
+        hash_inputs_var(cs.clone(), &self.params, &chunks)?.enforce_equal(&hash)?;
+
+        // the quote is within the document, offset + quote length <= length
+        let offset_bits = to_bits(&offset, OFFSET_BITS)?;
+        to_bits(&(length - &offset - &quote_length), OFFSET_BITS)?;
+
+        // the document from the offset on
+        let mut window = bytes;
+        for (k, bit) in offset_bits.iter().enumerate() {
+            window = (0..window.len())
+                .map(|i| {
+                    let shifted = window.get(i + (1 << k)).cloned();
+                    bit.select(&shifted.unwrap_or_else(FpVar::zero), &window[i])
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+
+        // the bytes of the quote, then its first `quote_length` bytes against the window
+        let quote_bytes = to_bits(&quote, 8 * MAX_QUOTE_BYTES)?
+            .chunks(8)
+            .map(Boolean::le_bits_to_fp_var)
+            .collect::<Result<Vec<_>, _>>()?;
+        let ends = one_hot(cs, &quote_length, MAX_QUOTE_BYTES + 1)?;
+        let mut in_quote = Boolean::TRUE;
+        for ((byte, expected), end) in window.iter().zip(&quote_bytes).zip(&ends) {
+            in_quote = in_quote.and(&end.not())?;
+            FpVar::from(in_quote.clone()).mul_equals(&(byte - expected), &FpVar::zero())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    const DOCUMENT: &[u8] =
+        b"The quarterly figures were revised on 3 May: revenue fell by 4 percent \
+        while costs rose, and the board approved the merger in a closed session.";
+
+    fn is_satisfied(circuit: RedactionCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    fn find(quote: &[u8]) -> usize {
+        DOCUMENT
+            .windows(quote.len())
+            .position(|w| w == quote)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_quote() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            RedactionCircuit::blank(params.clone()),
+            rng,
+        )
+        .unwrap();
+
+        let quote = b"the board approved the merger";
+        let circuit =
+            RedactionCircuit::new(params.clone(), DOCUMENT, find(quote), quote.len()).unwrap();
+        assert_eq!(circuit.quote, quote);
+        let inputs = circuit.public_inputs();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        // a quote changed in the verifier's copy, the hash of another document
+        let changed = RedactionCircuit {
+            quote: b"the board rejected the merger".to_vec(),
+            ..RedactionCircuit::blank(params.clone())
+        };
+        let mut other = inputs.clone();
+        other[3] = changed.public_inputs()[3];
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &other, &proof).unwrap());
+        let mut other = inputs.clone();
+        other[0] = document_hash(&params, b"The quarterly figures were not revised.");
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &other, &proof).unwrap());
+    }
+
+    #[test]
+    fn test_offsets() {
+        let params = poseidon_parameters();
+        assert!(DOCUMENT.len() <= MAX_DOCUMENT_BYTES);
+        // the start, the end, across chunks, empty and the longest quote
+        let cases = [(0, 3), (DOCUMENT.len() - 8, 8), (25, 12), (40, 0), (60, 31)];
+        for &(offset, length) in cases.iter() {
+            let circuit = RedactionCircuit::new(params.clone(), DOCUMENT, offset, length).unwrap();
+            assert!(is_satisfied(circuit.clone()), "{} {}", offset, length);
+
+            // the same quote claimed one byte further
+            let mut shifted = circuit;
+            shifted.offset += 1;
+            assert_eq!(
+                is_satisfied(shifted),
+                length == 0 && offset < DOCUMENT.len(),
+                "{} {}",
+                offset,
+                length
+            );
+        }
+
+        // the quote may not run past the end of the document
+        assert!(RedactionCircuit::new(params.clone(), DOCUMENT, DOCUMENT.len() - 2, 3).is_none());
+        let mut past_end = RedactionCircuit::new(params, DOCUMENT, DOCUMENT.len() - 3, 3).unwrap();
+        past_end.offset += 1;
+        past_end.quote = vec![past_end.quote[1], past_end.quote[2], 0];
+        assert!(!is_satisfied(past_end));
+    }
+}
@@ -0,0 +1,519 @@
+use ark_bls12_381::Bls12_381;
+use ark_bn254::Bn254;
+use ark_ec::PairingEngine;
+use ark_ff::Field;
+use ark_groth16::{Groth16, Proof, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::batch::{self, Manifest, MultiplyRow};
+use crate::config::{Backend, CircuitKind, Curve};
+use crate::cubic_demo::CubicDemoCircuit;
+use crate::explain;
+use crate::marlin_srs::{
+    MarlinInst, MarlinProof, MarlinProvingKey, MarlinSrsSize, MarlinVerifyingKey, UniversalSetup,
+};
+use crate::multiply_demo::MultiplyDemoCircuit;
+use crate::padding::Padded;
+use crate::persist::{self, ArtifactKind, SerializationMode, Validation};
+use crate::progress::{self, Progress};
+use crate::prover;
+use crate::rng::{RngConfig, PROVE_STREAM, SETUP_STREAM};
+use crate::witness_json::{from_json, CubicWitnessJson, FieldValue, MultiplyWitnessJson};
+use crate::Error;
+
+// the (curve, backend) pairs this crate proves with, picked at runtime. Generic code
+// names the pairing engine and the backend as types (`prover::prove::<_, Groth16<Bn254>, _>`),
+// a server or the CLI gets them as strings from a request or a config file. Every
+// supported pair has an entry here behind the object safe `ErasedScheme`, which takes
+// and returns keys and proofs in `persist` envelopes, witnesses as `witness_json` and
+// field elements as `FieldValue`, so nothing in its signature depends on the curve:
+//
+//   let scheme = registry::lookup("groth16-bn254".parse()?);
+//   let (pk, vk) = scheme.setup(CircuitKind::Cubic, &rng_config)?;
+//   let (proof, public) = scheme.prove(CircuitKind::Cubic, &pk, r#"{"x": "3"}"#, &rng_config)?;
+//   assert!(scheme.verify(&vk, &public, &proof)?);
+//
+// Marlin's setup makes a universal SRS just large enough for the one circuit, to index
+// several circuits against one SRS use `marlin_srs::UniversalSetup` directly.
+//
+// Code that needs the concrete key types, like the CLI, is written against
+// `TypedBackend` instead and run for a `Scheme` with `dispatch`.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scheme {
+    pub backend: Backend,
+    pub curve: Curve,
+}
+
+impl Scheme {
+    pub const fn new(backend: Backend, curve: Curve) -> Self {
+        Self { backend, curve }
+    }
+}
+
+// every pair `lookup` has an entry for
+pub const SUPPORTED: [Scheme; 4] = [
+    Scheme::new(Backend::Groth16, Curve::Bls12_381),
+    Scheme::new(Backend::Groth16, Curve::Bn254),
+    Scheme::new(Backend::Marlin, Curve::Bls12_381),
+    Scheme::new(Backend::Marlin, Curve::Bn254),
+];
+
+// "groth16-bn254", the backend first since curve names contain '-'
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.backend, self.curve)
+    }
+}
+
+impl FromStr for Scheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (backend, curve) = s
+            .split_once('-')
+            .ok_or_else(|| format!("expected <backend>-<curve>, got {:?}", s))?;
+        Ok(Scheme::new(backend.parse()?, curve.parse()?))
+    }
+}
+
+// one (curve, backend) pair with its types erased
+pub trait ErasedScheme: Send + Sync {
+    fn scheme(&self) -> Scheme;
+
+    // proving and verifying key of the circuit
+    fn setup(
+        &self,
+        circuit: CircuitKind,
+        rng_config: &RngConfig,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error>;
+
+    // `witness` is the circuit's `witness_json` schema, returns the proof and the public
+    // inputs it is verified against
+    fn prove(
+        &self,
+        circuit: CircuitKind,
+        proving_key: &[u8],
+        witness: &str,
+        rng_config: &RngConfig,
+    ) -> Result<(Vec<u8>, Vec<FieldValue>), Error>;
+
+    fn verify(
+        &self,
+        verifying_key: &[u8],
+        public_inputs: &[FieldValue],
+        proof: &[u8],
+    ) -> Result<bool, Error>;
+}
+
+// the entry of `scheme`
+pub fn lookup(scheme: Scheme) -> &'static dyn ErasedScheme {
+    match (scheme.backend, scheme.curve) {
+        (Backend::Groth16, Curve::Bls12_381) => &GROTH16_BLS12_381,
+        (Backend::Groth16, Curve::Bn254) => &GROTH16_BN254,
+        (Backend::Marlin, Curve::Bls12_381) => &MARLIN_BLS12_381,
+        (Backend::Marlin, Curve::Bn254) => &MARLIN_BN254,
+    }
+}
+
+// all entries, in the order of `SUPPORTED`
+pub fn all() -> impl Iterator<Item = &'static dyn ErasedScheme> {
+    SUPPORTED.iter().map(|scheme| lookup(*scheme))
+}
+
+static GROTH16_BLS12_381: Entry<Bls12_381, Groth16Backend> = Entry::new(Curve::Bls12_381);
+static GROTH16_BN254: Entry<Bn254, Groth16Backend> = Entry::new(Curve::Bn254);
+static MARLIN_BLS12_381: Entry<Bls12_381, MarlinBackend> = Entry::new(Curve::Bls12_381);
+static MARLIN_BN254: Entry<Bn254, MarlinBackend> = Entry::new(Curve::Bn254);
+
+// the typed side of an entry, one impl per backend. Generic code written against it runs
+// with every supported scheme, `dispatch` picks the types for a `Scheme` at runtime.
+pub trait TypedBackend<E: PairingEngine> {
+    type ProvingKey: CanonicalSerialize + CanonicalDeserialize;
+    type VerifyingKey: CanonicalSerialize + CanonicalDeserialize;
+    type Proof: CanonicalSerialize + CanonicalDeserialize;
+
+    const BACKEND: Backend;
+    // smaller circuits are padded up to this many constraints, see `padding`
+    const MIN_CONSTRAINTS: usize = 0;
+
+    // `circuit` the way keys for this backend are made from it and proofs with them
+    fn pad<C: ConstraintSynthesizer<E::Fr>>(circuit: C) -> Padded<C> {
+        Padded::new(circuit, Self::MIN_CONSTRAINTS, 0)
+    }
+
+    // keys made from fresh parameters, for Marlin a universal SRS just large enough for
+    // the circuit
+    fn setup<C: ConstraintSynthesizer<E::Fr>>(
+        blank: impl Fn() -> C,
+        rng_config: &RngConfig,
+    ) -> Result<(Self::ProvingKey, Self::VerifyingKey), Error>;
+
+    // `setup` indexing against the universal SRS `srs` returns, for Marlin; Groth16 has
+    // no universal parameters and never calls it
+    fn setup_with_srs<C: ConstraintSynthesizer<E::Fr>>(
+        blank: C,
+        srs: impl FnOnce() -> Result<UniversalSetup<E>, Error>,
+        rng_config: &RngConfig,
+    ) -> Result<(Self::ProvingKey, Self::VerifyingKey), Error>;
+
+    fn prove<C: ConstraintSynthesizer<E::Fr>>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng_config: &RngConfig,
+    ) -> Result<Self::Proof, Error>;
+
+    // `prove` telling `progress` about every phase it completes
+    fn prove_with_progress<C: ConstraintSynthesizer<E::Fr>>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng_config: &RngConfig,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<Self::Proof, Error>;
+
+    fn verify(
+        vk: &Self::VerifyingKey,
+        inputs: &[E::Fr],
+        proof: &Self::Proof,
+    ) -> Result<bool, Error>;
+
+    // `batch::prove_batch`, Groth16 only
+    fn prove_batch(
+        _pk: &Self::ProvingKey,
+        _rows: &[MultiplyRow],
+        _out_dir: &Path,
+        _rng_config: &RngConfig,
+        _parallel: bool,
+    ) -> Result<Manifest, Error> {
+        Err(format!(
+            "batch proving is only available for the {} backend",
+            Backend::Groth16
+        )
+        .into())
+    }
+
+    // `explain::explain`, Groth16 only
+    fn explain(
+        _vk: &Self::VerifyingKey,
+        _inputs: &[E::Fr],
+        _proof: &Self::Proof,
+    ) -> Result<String, Error> {
+        Err(format!(
+            "explaining is only available for the {} backend",
+            Backend::Groth16
+        )
+        .into())
+    }
+}
+
+pub struct Groth16Backend;
+
+impl<E: PairingEngine> TypedBackend<E> for Groth16Backend {
+    type ProvingKey = ProvingKey<E>;
+    type VerifyingKey = VerifyingKey<E>;
+    type Proof = Proof<E>;
+
+    const BACKEND: Backend = Backend::Groth16;
+
+    fn setup<C: ConstraintSynthesizer<E::Fr>>(
+        blank: impl Fn() -> C,
+        rng_config: &RngConfig,
+    ) -> Result<(Self::ProvingKey, Self::VerifyingKey), Error> {
+        Ok(prover::setup::<_, Groth16<E>, _>(blank(), rng_config)?)
+    }
+
+    fn setup_with_srs<C: ConstraintSynthesizer<E::Fr>>(
+        blank: C,
+        _srs: impl FnOnce() -> Result<UniversalSetup<E>, Error>,
+        rng_config: &RngConfig,
+    ) -> Result<(Self::ProvingKey, Self::VerifyingKey), Error> {
+        Ok(prover::setup::<_, Groth16<E>, _>(blank, rng_config)?)
+    }
+
+    fn prove<C: ConstraintSynthesizer<E::Fr>>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng_config: &RngConfig,
+    ) -> Result<Self::Proof, Error> {
+        Ok(prover::prove::<_, Groth16<E>, _>(pk, circuit, rng_config)?)
+    }
+
+    fn prove_with_progress<C: ConstraintSynthesizer<E::Fr>>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng_config: &RngConfig,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<Self::Proof, Error> {
+        Ok(progress::prove_groth16(pk, circuit, rng_config, progress)?)
+    }
+
+    fn verify(
+        vk: &Self::VerifyingKey,
+        inputs: &[E::Fr],
+        proof: &Self::Proof,
+    ) -> Result<bool, Error> {
+        Ok(prover::verify::<_, Groth16<E>>(vk, inputs, proof)?)
+    }
+
+    fn prove_batch(
+        pk: &Self::ProvingKey,
+        rows: &[MultiplyRow],
+        out_dir: &Path,
+        rng_config: &RngConfig,
+        parallel: bool,
+    ) -> Result<Manifest, Error> {
+        Ok(batch::prove_batch(pk, rows, out_dir, rng_config, parallel)?)
+    }
+
+    fn explain(
+        vk: &Self::VerifyingKey,
+        inputs: &[E::Fr],
+        proof: &Self::Proof,
+    ) -> Result<String, Error> {
+        Ok(explain::explain(vk, proof, inputs).to_string())
+    }
+}
+
+pub struct MarlinBackend;
+
+impl<E: PairingEngine> TypedBackend<E> for MarlinBackend {
+    type ProvingKey = MarlinProvingKey<E>;
+    type VerifyingKey = MarlinVerifyingKey<E>;
+    type Proof = MarlinProof<E>;
+
+    const BACKEND: Backend = Backend::Marlin;
+    // the indexer fails on single constraint circuits such as multiply
+    const MIN_CONSTRAINTS: usize = 2;
+
+    fn setup<C: ConstraintSynthesizer<E::Fr>>(
+        blank: impl Fn() -> C,
+        rng_config: &RngConfig,
+    ) -> Result<(Self::ProvingKey, Self::VerifyingKey), Error> {
+        let mut rng = rng_config.build_stream(SETUP_STREAM);
+        UniversalSetup::<E>::new(MarlinSrsSize::of(blank())?, &mut rng)?.index_circuit(blank())
+    }
+
+    fn setup_with_srs<C: ConstraintSynthesizer<E::Fr>>(
+        blank: C,
+        srs: impl FnOnce() -> Result<UniversalSetup<E>, Error>,
+        _rng_config: &RngConfig,
+    ) -> Result<(Self::ProvingKey, Self::VerifyingKey), Error> {
+        srs()?.index_circuit(blank)
+    }
+
+    fn prove<C: ConstraintSynthesizer<E::Fr>>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng_config: &RngConfig,
+    ) -> Result<Self::Proof, Error> {
+        let mut rng = rng_config.build_stream(PROVE_STREAM);
+        Ok(MarlinInst::<E>::prove(pk, circuit, &mut rng).map_err(|e| format!("{:?}", e))?)
+    }
+
+    fn prove_with_progress<C: ConstraintSynthesizer<E::Fr>>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng_config: &RngConfig,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<Self::Proof, Error> {
+        progress::prove_marlin(pk, circuit, rng_config, progress)
+    }
+
+    fn verify(
+        vk: &Self::VerifyingKey,
+        inputs: &[E::Fr],
+        proof: &Self::Proof,
+    ) -> Result<bool, Error> {
+        // the verifier randomness only drives the batched opening check
+        let mut rng = RngConfig::default().build();
+        Ok(MarlinInst::<E>::verify(vk, inputs, proof, &mut rng).map_err(|e| format!("{:?}", e))?)
+    }
+}
+
+// a demo circuit picked at runtime, as one type
+pub enum DemoCircuit<F: Field> {
+    Multiply(MultiplyDemoCircuit<F>),
+    Cubic(CubicDemoCircuit<F>),
+}
+
+impl<F: Field> ConstraintSynthesizer<F> for DemoCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        match self {
+            DemoCircuit::Multiply(circuit) => circuit.generate_constraints(cs),
+            DemoCircuit::Cubic(circuit) => circuit.generate_constraints(cs),
+        }
+    }
+}
+
+// the blank that keys for `circuit` are set up from with `B`, for the entries here and
+// the CLI alike so their keys and fingerprints agree
+pub fn blank<E: PairingEngine, B: TypedBackend<E>>(
+    circuit: CircuitKind,
+) -> Padded<DemoCircuit<E::Fr>> {
+    B::pad(match circuit {
+        CircuitKind::Multiply => DemoCircuit::Multiply(MultiplyDemoCircuit::blank()),
+        CircuitKind::Cubic => DemoCircuit::Cubic(CubicDemoCircuit::blank()),
+    })
+}
+
+// generic code over the curve and the backend, run by `dispatch` with the types of a
+// `Scheme`
+pub trait SchemeVisitor {
+    type Output;
+
+    fn visit<E: PairingEngine, B: TypedBackend<E>>(self) -> Self::Output;
+}
+
+pub fn dispatch<V: SchemeVisitor>(scheme: Scheme, visitor: V) -> V::Output {
+    match (scheme.backend, scheme.curve) {
+        (Backend::Groth16, Curve::Bls12_381) => visitor.visit::<Bls12_381, Groth16Backend>(),
+        (Backend::Groth16, Curve::Bn254) => visitor.visit::<Bn254, Groth16Backend>(),
+        (Backend::Marlin, Curve::Bls12_381) => visitor.visit::<Bls12_381, MarlinBackend>(),
+        (Backend::Marlin, Curve::Bn254) => visitor.visit::<Bn254, MarlinBackend>(),
+    }
+}
+
+struct Entry<E, B> {
+    curve: Curve,
+    _types: PhantomData<fn() -> (E, B)>,
+}
+
+impl<E, B> Entry<E, B> {
+    const fn new(curve: Curve) -> Self {
+        Self {
+            curve,
+            _types: PhantomData,
+        }
+    }
+}
+
+fn to_bytes<T: CanonicalSerialize>(value: &T, kind: ArtifactKind) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    persist::write_artifact(value, kind, SerializationMode::Compressed, &mut bytes)?;
+    Ok(bytes)
+}
+
+// the bytes come from the caller, every point is checked
+fn from_bytes<T: CanonicalDeserialize>(bytes: &[u8], kind: ArtifactKind) -> Result<T, Error> {
+    Ok(persist::read_artifact(bytes, kind, Validation::Checked)?)
+}
+
+impl<E: PairingEngine, B: TypedBackend<E>> ErasedScheme for Entry<E, B> {
+    fn scheme(&self) -> Scheme {
+        Scheme::new(B::BACKEND, self.curve)
+    }
+
+    fn setup(
+        &self,
+        circuit: CircuitKind,
+        rng_config: &RngConfig,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let (pk, vk) = B::setup(|| blank::<E, B>(circuit), rng_config)?;
+        Ok((
+            to_bytes(&pk, ArtifactKind::ProvingKey)?,
+            to_bytes(&vk, ArtifactKind::VerifyingKey)?,
+        ))
+    }
+
+    fn prove(
+        &self,
+        circuit: CircuitKind,
+        proving_key: &[u8],
+        witness: &str,
+        rng_config: &RngConfig,
+    ) -> Result<(Vec<u8>, Vec<FieldValue>), Error> {
+        let pk: B::ProvingKey = from_bytes(proving_key, ArtifactKind::ProvingKey)?;
+        let (proof, public) = match circuit {
+            CircuitKind::Multiply => {
+                let witness = from_json::<E::Fr, MultiplyWitnessJson>(witness)?;
+                let circuit = B::pad(MultiplyDemoCircuit::from(witness));
+                (
+                    B::prove(&pk, circuit, rng_config)?,
+                    witness.instance().public_inputs(),
+                )
+            }
+            CircuitKind::Cubic => {
+                let witness = from_json::<E::Fr, CubicWitnessJson>(witness)?;
+                let circuit = B::pad(CubicDemoCircuit::from(witness));
+                (
+                    B::prove(&pk, circuit, rng_config)?,
+                    witness.instance().public_inputs(),
+                )
+            }
+        };
+        let public = public.into_iter().map(FieldValue::from_field).collect();
+        Ok((to_bytes(&proof, ArtifactKind::Proof)?, public))
+    }
+
+    fn verify(
+        &self,
+        verifying_key: &[u8],
+        public_inputs: &[FieldValue],
+        proof: &[u8],
+    ) -> Result<bool, Error> {
+        let vk: B::VerifyingKey = from_bytes(verifying_key, ArtifactKind::VerifyingKey)?;
+        let proof: B::Proof = from_bytes(proof, ArtifactKind::Proof)?;
+        let inputs = public_inputs
+            .iter()
+            .enumerate()
+            .map(|(i, value)| value.parse(&format!("public[{}]", i)))
+            .collect::<Result<Vec<E::Fr>, _>>()?;
+        B::verify(&vk, &inputs, &proof)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_schemes() {
+        for scheme in SUPPORTED.iter() {
+            assert_eq!(lookup(*scheme).scheme(), *scheme);
+            assert_eq!(scheme.to_string().parse::<Scheme>(), Ok(*scheme));
+        }
+        assert_eq!(
+            "marlin-bls12-381".parse::<Scheme>(),
+            Ok(Scheme::new(Backend::Marlin, Curve::Bls12_381))
+        );
+        for name in ["groth16", "plonk-bn254", "groth16-secp256k1"].iter() {
+            assert!(name.parse::<Scheme>().is_err());
+        }
+    }
+
+    #[test]
+    fn test_prove_with_every_scheme() {
+        let rng_config = RngConfig::seeded(7);
+        for scheme in all() {
+            let (pk, vk) = scheme.setup(CircuitKind::Cubic, &rng_config).unwrap();
+            let (proof, public) = scheme
+                .prove(CircuitKind::Cubic, &pk, r#"{"x": "3"}"#, &rng_config)
+                .unwrap();
+            assert_eq!(
+                public,
+                vec![FieldValue::from_field(ark_bn254::Fr::from(35u64))]
+            );
+            assert!(scheme.verify(&vk, &public, &proof).unwrap());
+            let wrong = [FieldValue::from_field(ark_bn254::Fr::from(36u64))];
+            assert!(matches!(scheme.verify(&vk, &wrong, &proof), Ok(false)));
+        }
+
+        // keys of one curve are not read as another's
+        let (pk, _) = lookup(SUPPORTED[0])
+            .setup(CircuitKind::Multiply, &rng_config)
+            .unwrap();
+        assert!(lookup(SUPPORTED[1])
+            .prove(
+                CircuitKind::Multiply,
+                &pk,
+                r#"{"a": "3", "b": "5"}"#,
+                &rng_config
+            )
+            .is_err());
+    }
+}
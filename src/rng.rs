@@ -0,0 +1,138 @@
+#[cfg(any(test, feature = "insecure"))]
+use ark_std::rand::SeedableRng;
+use ark_std::rand::{CryptoRng, Error, RngCore};
+#[cfg(any(test, feature = "insecure"))]
+use rand_chacha::ChaCha20Rng;
+use rand_core::OsRng;
+
+// where the randomness for setup and proving comes from.
+// Os is the only choice for production. Seeded makes every run reproducible, which is
+// what tests and CI want, but anyone who knows the seed knows the setup trapdoor and the
+// proof blinding factors, so seeded parameters must never be deployed. It only exists
+// with the `insecure` feature (and in the unit tests), a build without it can't make
+// development parameters by accident.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RngConfig {
+    #[default]
+    Os,
+    #[cfg(any(test, feature = "insecure"))]
+    Seeded([u8; 32]),
+}
+
+// independent ChaCha streams so that setup and proving never share randomness
+// even when they are driven by the same seed
+pub const SETUP_STREAM: u64 = 0;
+pub const PROVE_STREAM: u64 = 1;
+
+impl RngConfig {
+    #[cfg(any(test, feature = "insecure"))]
+    pub fn seeded(seed: u64) -> Self {
+        let mut bytes = [0u8; 32];
+        bytes[..8].copy_from_slice(&seed.to_le_bytes());
+        RngConfig::Seeded(bytes)
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        match self {
+            RngConfig::Os => false,
+            #[cfg(any(test, feature = "insecure"))]
+            RngConfig::Seeded(_) => true,
+        }
+    }
+
+    pub fn build(&self) -> ConfiguredRng {
+        self.build_stream(0)
+    }
+
+    // `stream` only matters for a seed, the OS generator has no streams
+    #[cfg_attr(not(any(test, feature = "insecure")), allow(unused_variables))]
+    pub fn build_stream(&self, stream: u64) -> ConfiguredRng {
+        match self {
+            RngConfig::Os => ConfiguredRng::Os(OsRng),
+            #[cfg(any(test, feature = "insecure"))]
+            RngConfig::Seeded(seed) => {
+                let mut rng = ChaCha20Rng::from_seed(*seed);
+                rng.set_stream(stream);
+                ConfiguredRng::Seeded(Box::new(rng))
+            }
+        }
+    }
+}
+
+pub enum ConfiguredRng {
+    Os(OsRng),
+    #[cfg(any(test, feature = "insecure"))]
+    Seeded(Box<ChaCha20Rng>),
+}
+
+impl RngCore for ConfiguredRng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            ConfiguredRng::Os(rng) => rng.next_u32(),
+            #[cfg(any(test, feature = "insecure"))]
+            ConfiguredRng::Seeded(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            ConfiguredRng::Os(rng) => rng.next_u64(),
+            #[cfg(any(test, feature = "insecure"))]
+            ConfiguredRng::Seeded(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            ConfiguredRng::Os(rng) => rng.fill_bytes(dest),
+            #[cfg(any(test, feature = "insecure"))]
+            ConfiguredRng::Seeded(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        match self {
+            ConfiguredRng::Os(rng) => rng.try_fill_bytes(dest),
+            #[cfg(any(test, feature = "insecure"))]
+            ConfiguredRng::Seeded(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+// both variants are cryptographically secure generators, the seeded one is only as
+// secret as its seed
+impl CryptoRng for ConfiguredRng {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_seeded_is_reproducible() {
+        let config = RngConfig::seeded(42);
+        let mut a = config.build();
+        let mut b = config.build();
+        assert_eq!(a.next_u64(), b.next_u64());
+
+        let mut other = RngConfig::seeded(43).build();
+        assert_ne!(config.build().next_u64(), other.next_u64());
+    }
+
+    #[test]
+    fn test_streams_are_independent() {
+        let config = RngConfig::seeded(7);
+        let mut setup = config.build_stream(SETUP_STREAM);
+        let mut prove = config.build_stream(PROVE_STREAM);
+        assert_ne!(setup.next_u64(), prove.next_u64());
+    }
+
+    #[test]
+    fn test_os_rng() {
+        let config = RngConfig::default();
+        assert!(!config.is_deterministic());
+        let mut rng = config.build();
+        let mut bytes = [0u8; 32];
+        rng.fill_bytes(&mut bytes);
+        assert_ne!(bytes, [0u8; 32]);
+    }
+}
@@ -0,0 +1,210 @@
+use ark_bls12_381::Fr;
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ed_on_bls12_381::{constraints::EdwardsVar, EdwardsAffine, Fr as JubjubScalar};
+use ark_ff::{to_bytes, One, PrimeField, Zero};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSystemRef, Namespace, SynthesisError},
+};
+use ark_sponge::poseidon::PoseidonParameters;
+use ark_std::{borrow::Borrow, rand::Rng, UniformRand};
+
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+
+// Schnorr signatures that are cheap to verify in a BLS12-381 circuit: the group is
+// Jubjub, whose base field is the circuit field, and the challenge is a Poseidon hash
+// (see `hashed_inputs_demo`) of the nonce point, the public key and the message, so
+// messages are field elements. Arkworks' own Schnorr hashes bytes with a `Digest` and
+// has no verification gadget.
+//
+// sign: R = k * G, e = H(R, pk, m), z = k + e * sk; verify: z * G == R + e * pk
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub r: EdwardsAffine,
+    pub z: JubjubScalar,
+}
+
+pub fn generator() -> EdwardsAffine {
+    EdwardsAffine::prime_subgroup_generator()
+}
+
+// the secret key and the public key
+pub fn keygen<R: Rng>(rng: &mut R) -> (JubjubScalar, EdwardsAffine) {
+    let sk = JubjubScalar::rand(rng);
+    (sk, generator().mul(sk).into_affine())
+}
+
+// the hash as a scalar, reduced modulo the order of Jubjub like the bits the gadget
+// multiplies with
+fn challenge(
+    params: &PoseidonParameters<Fr>,
+    r: &EdwardsAffine,
+    pk: &EdwardsAffine,
+    message: &[Fr],
+) -> JubjubScalar {
+    let mut inputs = vec![r.x, r.y, pk.x, pk.y];
+    inputs.extend_from_slice(message);
+    let e = hash_inputs(params, &inputs);
+    JubjubScalar::from_le_bytes_mod_order(&to_bytes![e].expect("writing to a vector"))
+}
+
+pub fn sign<R: Rng>(
+    params: &PoseidonParameters<Fr>,
+    sk: JubjubScalar,
+    message: &[Fr],
+    rng: &mut R,
+) -> Signature {
+    let pk = generator().mul(sk).into_affine();
+    let k = JubjubScalar::rand(rng);
+    let r = generator().mul(k).into_affine();
+    let z = k + challenge(params, &r, &pk, message) * sk;
+    Signature { r, z }
+}
+
+pub fn verify(
+    params: &PoseidonParameters<Fr>,
+    pk: &EdwardsAffine,
+    message: &[Fr],
+    signature: &Signature,
+) -> bool {
+    let e = challenge(params, &signature.r, pk, message);
+    generator().mul(signature.z) == signature.r.into_projective() + pk.mul(e)
+}
+
+#[derive(Clone)]
+pub struct SignatureVar {
+    pub r: EdwardsVar,
+    // the bits of z, least significant first
+    pub z: Vec<Boolean<Fr>>,
+}
+
+impl AllocVar<Signature, Fr> for SignatureVar {
+    fn new_variable<T: Borrow<Signature>>(
+        cs: impl Into<Namespace<Fr>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let signature = f().map(|s| *s.borrow());
+        // R + e * pk is in the prime order subgroup only if R is, no need to check
+        let r = EdwardsVar::new_variable_omit_prime_order_check(
+            ns!(cs, "r"),
+            || signature.map(|s| s.r.into_projective()),
+            mode,
+        )?;
+        let z = signature
+            .map(|s| s.z)
+            .unwrap_or_else(|_| JubjubScalar::zero());
+        let z = Vec::<UInt8<Fr>>::new_variable(ns!(cs, "z"), || Ok(to_bytes![z].unwrap()), mode)?;
+        let z = z
+            .iter()
+            .flat_map(|byte| byte.to_bits_le().unwrap())
+            .collect();
+        Ok(Self { r, z })
+    }
+}
+
+// the bits of a private secret key, least significant first, `sk` is None for the setup.
+// The key is checked to be below the order of Jubjub, so each key has one encoding: a
+// gadget that hashes the key, e.g. into a nullifier, can't be given another multiple
+// of it.
+pub fn secret_key_var(
+    cs: ConstraintSystemRef<Fr>,
+    sk: Option<JubjubScalar>,
+) -> Result<Vec<Boolean<Fr>>, SynthesisError> {
+    // any value for the setup
+    let sk = sk.unwrap_or_else(JubjubScalar::zero);
+    let bytes =
+        Vec::<UInt8<Fr>>::new_witness(ns!(cs, "secret_key"), || Ok(to_bytes![sk].unwrap()))?;
+    let bits: Vec<_> = bytes
+        .iter()
+        .flat_map(|byte| byte.to_bits_le().unwrap())
+        .collect();
+    Boolean::enforce_smaller_or_equal_than_le(&bits, (-JubjubScalar::one()).into_repr())?;
+    Ok(bits)
+}
+
+// the secret key as a field element of the circuit, the order of Jubjub is smaller than
+// the modulus
+pub fn secret_key_to_field(sk: JubjubScalar) -> Fr {
+    Fr::from_le_bytes_mod_order(&to_bytes![sk].expect("writing to a vector"))
+}
+
+// the generator times the scalar with bits `scalar`, least significant first, with
+// precomputed powers of the generator
+pub fn generator_mul_var(scalar: &[Boolean<Fr>]) -> Result<EdwardsVar, SynthesisError> {
+    let mut powers = Vec::with_capacity(scalar.len());
+    let mut power = generator().into_projective();
+    for _ in 0..scalar.len() {
+        powers.push(power);
+        power.double_in_place();
+    }
+    let mut result = EdwardsVar::zero();
+    result.precomputed_base_scalar_mul_le(scalar.iter().zip(&powers))?;
+    Ok(result)
+}
+
+// whether `signature` is a signature of `message` under `pk`
+pub fn verify_var(
+    cs: ConstraintSystemRef<Fr>,
+    params: &PoseidonParameters<Fr>,
+    pk: &EdwardsVar,
+    message: &[FpVar<Fr>],
+    signature: &SignatureVar,
+) -> Result<Boolean<Fr>, SynthesisError> {
+    let mut inputs = vec![
+        signature.r.x.clone(),
+        signature.r.y.clone(),
+        pk.x.clone(),
+        pk.y.clone(),
+    ];
+    inputs.extend_from_slice(message);
+    let e = hash_inputs_var(cs, params, &inputs)?;
+
+    let z_g = generator_mul_var(&signature.z)?;
+    let e_pk = pk.scalar_mul_le(e.to_bits_le()?.iter())?;
+    z_g.is_eq(&(e_pk + &signature.r))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    fn verify_in_circuit(pk: EdwardsAffine, message: &[Fr], signature: Signature) -> bool {
+        let params = poseidon_parameters();
+        let cs = ConstraintSystem::new_ref();
+        let pk = EdwardsVar::new_witness(cs.clone(), || Ok(pk)).unwrap();
+        let message: Vec<_> = message
+            .iter()
+            .map(|&m| FpVar::new_witness(cs.clone(), || Ok(m)).unwrap())
+            .collect();
+        let signature = SignatureVar::new_witness(cs.clone(), || Ok(signature)).unwrap();
+        let valid = verify_var(cs.clone(), &params, &pk, &message, &signature).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        valid.value().unwrap()
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (sk, pk) = keygen(rng);
+        let message = [Fr::from(1u8), Fr::from(2u8)];
+        let signature = sign(&params, sk, &message, rng);
+        assert!(verify(&params, &pk, &message, &signature));
+        assert!(verify_in_circuit(pk, &message, signature));
+
+        let other_message = [Fr::from(1u8), Fr::from(3u8)];
+        assert!(!verify(&params, &pk, &other_message, &signature));
+        assert!(!verify_in_circuit(pk, &other_message, signature));
+
+        let (_, other_pk) = keygen(rng);
+        assert!(!verify(&params, &other_pk, &message, &signature));
+        assert!(!verify_in_circuit(other_pk, &message, signature));
+    }
+}
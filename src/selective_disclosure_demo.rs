@@ -0,0 +1,301 @@
+use ark_bls12_381::Fr;
+use ark_ed_on_bls12_381::{constraints::EdwardsVar, EdwardsAffine, Fr as JubjubScalar};
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::poseidon::PoseidonParameters;
+use ark_std::rand::Rng;
+
+use crate::boolean_demo::to_bits;
+use crate::conditional_demo::enforce_if;
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+use crate::schnorr_gadget::{generator, sign, verify_var, Signature, SignatureVar};
+
+// Anonymous credentials with selective disclosure: the issuer signs (`schnorr_gadget`)
+// the Poseidon hash of a fixed number of attributes, e.g. a hashed name, a date of birth
+// as days since 1970 and a country code, and the holder shows a verifier some of them
+// and facts about others without the signature, the hash or the rest. Per attribute the
+// verifier picks a `Disclosure`, all public inputs, so one setup serves any choice:
+//   - a flag and the value, the attribute equals the value if the flag is set,
+//   - a flag and bounds, lower <= attribute <= upper if set, a range check of
+//     flag * (attribute - lower) and flag * (upper - attribute) that is trivially met
+//     with the flag unset. Attributes under a predicate have to be below
+//     2^PREDICATE_BITS, the others can be any field element.
+
+pub const NUM_ATTRIBUTES: usize = 4;
+pub const PREDICATE_BITS: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Disclosure {
+    Hidden,
+    Revealed(Fr),
+    // both bounds included
+    InRange(u64, u64),
+}
+
+pub fn credential_commitment(params: &PoseidonParameters<Fr>, attributes: &[Fr]) -> Fr {
+    hash_inputs(params, attributes)
+}
+
+// the issuer's signature of the attributes
+pub fn issue<R: Rng>(
+    params: &PoseidonParameters<Fr>,
+    issuer_sk: JubjubScalar,
+    attributes: &[Fr],
+    rng: &mut R,
+) -> Signature {
+    assert_eq!(attributes.len(), NUM_ATTRIBUTES);
+    sign(
+        params,
+        issuer_sk,
+        &[credential_commitment(params, attributes)],
+        rng,
+    )
+}
+
+// what the verifier checks the proof against, the same on both sides
+pub fn public_inputs(issuer: &EdwardsAffine, disclosures: &[Disclosure]) -> Vec<Fr> {
+    let mut inputs = vec![issuer.x, issuer.y];
+    for disclosure in disclosures {
+        let (revealed, value) = match disclosure {
+            Disclosure::Revealed(value) => (true, *value),
+            _ => (false, Fr::from(0u8)),
+        };
+        let (in_range, lower, upper) = match disclosure {
+            Disclosure::InRange(lower, upper) => (true, *lower, *upper),
+            _ => (false, 0, 0),
+        };
+        inputs.extend_from_slice(&[
+            Fr::from(revealed),
+            value,
+            Fr::from(in_range),
+            Fr::from(lower),
+            Fr::from(upper),
+        ]);
+    }
+    inputs
+}
+
+pub fn satisfies(attribute: Fr, disclosure: &Disclosure) -> bool {
+    match disclosure {
+        Disclosure::Hidden => true,
+        Disclosure::Revealed(value) => attribute == *value,
+        Disclosure::InRange(lower, upper) => {
+            let repr = attribute.into_repr();
+            let limbs = repr.as_ref();
+            limbs[1..].iter().all(|&limb| limb == 0) && (*lower..=*upper).contains(&limbs[0])
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DisclosureCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub issuer: EdwardsAffine,
+    pub disclosures: Vec<Disclosure>,
+    pub attributes: Option<Vec<Fr>>,
+    pub signature: Option<Signature>,
+}
+
+impl DisclosureCircuit {
+    pub fn new(
+        params: PoseidonParameters<Fr>,
+        issuer: EdwardsAffine,
+        disclosures: Vec<Disclosure>,
+        attributes: Vec<Fr>,
+        signature: Signature,
+    ) -> Self {
+        assert_eq!(disclosures.len(), NUM_ATTRIBUTES);
+        assert_eq!(attributes.len(), NUM_ATTRIBUTES);
+        Self {
+            params,
+            issuer,
+            disclosures,
+            attributes: Some(attributes),
+            signature: Some(signature),
+        }
+    }
+
+    // the issuer and the disclosures are public inputs
+    pub fn blank(params: PoseidonParameters<Fr>) -> Self {
+        Self {
+            params,
+            issuer: generator(),
+            disclosures: vec![Disclosure::Hidden; NUM_ATTRIBUTES],
+            attributes: None,
+            signature: None,
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        public_inputs(&self.issuer, &self.disclosures)
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for DisclosureCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let public = self.public_inputs();
+        let issuer = EdwardsVar::new_input(ns!(cs, "issuer"), || Ok(self.issuer))?;
+        let attributes = (0..NUM_ATTRIBUTES)
+            .map(|i| {
+                FpVar::new_witness(ns!(cs, "attribute"), || {
+                    self.attributes
+                        .as_ref()
+                        .map(|a| a[i])
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let signature = SignatureVar::new_witness(ns!(cs, "signature"), || {
+            self.signature.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let commitment = hash_inputs_var(cs.clone(), &self.params, &attributes)?;
+        verify_var(
+            cs.clone(),
+            &self.params,
+            &issuer,
+            ark_std::slice::from_ref(&commitment),
+            &signature,
+        )?
+        .enforce_equal(&Boolean::TRUE)?;
+
+        // the five inputs of each disclosure
+        for (attribute, inputs) in attributes.iter().zip(public[2..].chunks(5)) {
+            let revealed =
+                Boolean::new_input(ns!(cs, "revealed"), || Ok(inputs[0] == Fr::from(1u8)))?;
+            let value = FpVar::new_input(ns!(cs, "value"), || Ok(inputs[1]))?;
+            let in_range =
+                Boolean::new_input(ns!(cs, "in_range"), || Ok(inputs[2] == Fr::from(1u8)))?;
+            let lower = FpVar::new_input(ns!(cs, "lower"), || Ok(inputs[3]))?;
+            let upper = FpVar::new_input(ns!(cs, "upper"), || Ok(inputs[4]))?;
+
+            enforce_if(&revealed, attribute, &value)?;
+            let in_range = FpVar::from(in_range);
+            to_bits(&(&in_range * (attribute - lower)), PREDICATE_BITS)?;
+            to_bits(&(in_range * (upper - attribute)), PREDICATE_BITS)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::date_gadget::first_day_of_year;
+    use crate::poseidon_params::poseidon_parameters;
+    use crate::schnorr_gadget::keygen;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    fn is_satisfied(circuit: DisclosureCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    // a hashed name, a date of birth, a country code and a driving licence class
+    fn attributes(params: &PoseidonParameters<Fr>) -> Vec<Fr> {
+        let name = hash_inputs(params, &[Fr::from(0x4a616e6520446f65u64)]);
+        vec![
+            name,
+            Fr::from(first_day_of_year(1990) + 100),
+            Fr::from(276u16),
+            Fr::from(2u8),
+        ]
+    }
+
+    #[test]
+    fn test_selective_disclosure() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (issuer_sk, issuer) = keygen(rng);
+        let attributes = attributes(&params);
+        let signature = issue(&params, issuer_sk, &attributes, rng);
+
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            DisclosureCircuit::blank(params.clone()),
+            rng,
+        )
+        .unwrap();
+
+        // born before 2006, living in Germany, nothing about the name and the licence
+        let disclosures = vec![
+            Disclosure::Hidden,
+            Disclosure::InRange(0, first_day_of_year(2006) as u64 - 1),
+            Disclosure::Revealed(Fr::from(276u16)),
+            Disclosure::Hidden,
+        ];
+        let circuit = DisclosureCircuit::new(
+            params.clone(),
+            issuer,
+            disclosures.clone(),
+            attributes.clone(),
+            signature,
+        );
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        let inputs = public_inputs(&issuer, &disclosures);
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        // the proof says nothing else
+        let mut other = disclosures.clone();
+        other[2] = Disclosure::Revealed(Fr::from(250u16));
+        assert!(
+            !Groth16::<Bls12_381>::verify(&vk, &public_inputs(&issuer, &other), &proof).unwrap()
+        );
+        let (_, other_issuer) = keygen(rng);
+        assert!(!Groth16::<Bls12_381>::verify(
+            &vk,
+            &public_inputs(&other_issuer, &disclosures),
+            &proof
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_disclosures() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (issuer_sk, issuer) = keygen(rng);
+        let attributes = attributes(&params);
+        let signature = issue(&params, issuer_sk, &attributes, rng);
+        let born = first_day_of_year(1990) as u64 + 100;
+
+        let cases = [
+            (1, Disclosure::InRange(born, born), true),
+            (1, Disclosure::InRange(born + 1, born + 1000), false),
+            (1, Disclosure::InRange(0, born - 1), false),
+            (0, Disclosure::Revealed(attributes[0]), true),
+            (0, Disclosure::Revealed(attributes[1]), false),
+            (3, Disclosure::InRange(1, 3), true),
+            // the range check needs an attribute of 32 bits, the hash isn't one
+            (0, Disclosure::InRange(0, u32::MAX as u64), false),
+        ];
+        for &(i, disclosure, holds) in cases.iter() {
+            assert_eq!(satisfies(attributes[i], &disclosure), holds);
+            let mut disclosures = vec![Disclosure::Hidden; NUM_ATTRIBUTES];
+            disclosures[i] = disclosure;
+            let circuit = DisclosureCircuit::new(
+                params.clone(),
+                issuer,
+                disclosures,
+                attributes.clone(),
+                signature,
+            );
+            assert_eq!(is_satisfied(circuit), holds, "{} {:?}", i, disclosure);
+        }
+
+        // attributes the issuer did not sign
+        let mut forged = attributes.clone();
+        forged[1] = Fr::from(first_day_of_year(1980));
+        let disclosures = vec![Disclosure::Hidden; NUM_ATTRIBUTES];
+        let circuit =
+            DisclosureCircuit::new(params.clone(), issuer, disclosures, forged, signature);
+        assert!(!is_satisfied(circuit));
+    }
+}
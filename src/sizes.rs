@@ -0,0 +1,110 @@
+use ark_serialize::CanonicalSerialize;
+use std::fmt;
+
+// serialized size of a single artifact in both encodings. Compressed points only store
+// the x coordinate plus a flag, uncompressed ones store both coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SerializedSize {
+    pub compressed: usize,
+    pub uncompressed: usize,
+}
+
+impl SerializedSize {
+    pub fn of<T: CanonicalSerialize>(artifact: &T) -> Self {
+        Self {
+            compressed: artifact.serialized_size(),
+            uncompressed: artifact.uncompressed_size(),
+        }
+    }
+}
+
+// sizes of the keys and proof produced by any backend (Groth16, Marlin, ...)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArtifactSizes {
+    pub proving_key: SerializedSize,
+    pub verifying_key: SerializedSize,
+    pub proof: SerializedSize,
+}
+
+pub fn artifact_sizes<PK, VK, P>(pk: &PK, vk: &VK, proof: &P) -> ArtifactSizes
+where
+    PK: CanonicalSerialize,
+    VK: CanonicalSerialize,
+    P: CanonicalSerialize,
+{
+    ArtifactSizes {
+        proving_key: SerializedSize::of(pk),
+        verifying_key: SerializedSize::of(vk),
+        proof: SerializedSize::of(proof),
+    }
+}
+
+impl fmt::Display for ArtifactSizes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<14} {:>16} {:>18}",
+            "artifact", "compressed(B)", "uncompressed(B)"
+        )?;
+        for (name, size) in [
+            ("proving key", self.proving_key),
+            ("verifying key", self.verifying_key),
+            ("proof", self.proof),
+        ]
+        .iter()
+        {
+            writeln!(
+                f,
+                "{:<14} {:>16} {:>18}",
+                name, size.compressed, size.uncompressed
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::CubicDemoCircuit;
+    use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+    use ark_groth16::Groth16;
+    use ark_snark::SNARK;
+
+    #[test]
+    fn test_groth16_artifact_sizes() {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            CubicDemoCircuit::<BlsFr> { x: None },
+            rng,
+        )
+        .unwrap();
+        let proof = Groth16::<Bls12_381>::prove(
+            &pk,
+            CubicDemoCircuit::<BlsFr> {
+                x: Some(BlsFr::from(3)),
+            },
+            rng,
+        )
+        .unwrap();
+
+        let sizes = artifact_sizes(&pk, &vk, &proof);
+        // A, C in G1 (48 / 96 bytes) and B in G2 (96 / 192 bytes)
+        assert_eq!(
+            sizes.proof,
+            SerializedSize {
+                compressed: 48 + 96 + 48,
+                uncompressed: 96 + 192 + 96,
+            }
+        );
+        assert!(sizes.verifying_key.compressed < sizes.verifying_key.uncompressed);
+        assert!(sizes.proving_key.compressed < sizes.proving_key.uncompressed);
+        assert!(sizes.verifying_key.compressed < sizes.proving_key.compressed);
+        assert_eq!(sizes.proving_key.compressed, pk.serialized_size());
+        assert_eq!(sizes.verifying_key.compressed, vk.serialized_size());
+
+        let table = sizes.to_string();
+        assert!(table.contains("proving key"));
+        assert!(table.contains("192"));
+    }
+}
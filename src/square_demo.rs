@@ -0,0 +1,301 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+
+use crate::private_index_demo::one_hot;
+
+// Knowledge of an n x n Latin or magic square that agrees with the cells the verifier
+// sees, like a Sudoku with its clues. A Latin square has each of 1..=n once in every
+// row and every column, a magic square each of 1..=n^2 once and rows, columns and both
+// diagonals adding up to n (n^2 + 1) / 2.
+//
+// "All different" is the part that needs care in a circuit. Comparing every pair costs
+// a non-zero check per pair; instead each cell gets a one-hot selector over the values
+// (`private_index_demo`), which also range checks it, and a value is used exactly once
+// in a line when the sum of its selector bits over the line is 1, a linear constraint.
+// A public input per cell is its value or 0 if hidden: r * (r - cell) == 0.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Square {
+    Latin,
+    Magic,
+}
+
+impl Square {
+    // the values are 1..=max_value
+    pub fn max_value(&self, n: usize) -> usize {
+        match self {
+            Square::Latin => n,
+            Square::Magic => n * n,
+        }
+    }
+}
+
+pub fn magic_constant(n: usize) -> u64 {
+    (n * (n * n + 1) / 2) as u64
+}
+
+// the rows, the columns and for magic squares the diagonals, as cell indices
+fn lines(square: Square, n: usize) -> Vec<Vec<usize>> {
+    let mut lines: Vec<Vec<usize>> = (0..n)
+        .map(|i| (0..n).map(|j| i * n + j).collect())
+        .chain((0..n).map(|j| (0..n).map(|i| i * n + j).collect()))
+        .collect();
+    if square == Square::Magic {
+        lines.push((0..n).map(|i| i * n + i).collect());
+        lines.push((0..n).map(|i| i * n + n - 1 - i).collect());
+    }
+    lines
+}
+
+// the cells row by row
+pub fn is_valid(square: Square, n: usize, cells: &[u64]) -> bool {
+    let max = square.max_value(n) as u64;
+    if cells.len() != n * n || cells.iter().any(|&c| c == 0 || c > max) {
+        return false;
+    }
+    let distinct = |indices: &[usize]| {
+        let mut seen = vec![false; max as usize + 1];
+        indices
+            .iter()
+            .all(|&i| !std::mem::replace(&mut seen[cells[i] as usize], true))
+    };
+    match square {
+        Square::Latin => lines(square, n).iter().all(|line| distinct(line)),
+        Square::Magic => {
+            distinct(&(0..n * n).collect::<Vec<_>>())
+                && lines(square, n)
+                    .iter()
+                    .all(|line| line.iter().map(|&i| cells[i]).sum::<u64>() == magic_constant(n))
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SquareCircuit {
+    pub square: Square,
+    pub n: usize,
+    // the value of each cell the verifier sees, 0 for the hidden ones
+    pub revealed: Vec<u64>,
+    pub cells: Option<Vec<u64>>,
+}
+
+impl SquareCircuit {
+    // `cells` with the cells at `shown` revealed
+    pub fn new(square: Square, n: usize, cells: Vec<u64>, shown: &[usize]) -> Self {
+        let mut revealed = vec![0; n * n];
+        for &i in shown {
+            revealed[i] = cells[i];
+        }
+        Self {
+            square,
+            n,
+            revealed,
+            cells: Some(cells),
+        }
+    }
+
+    // the revealed cells are public inputs, the setup only fixes the kind and n
+    pub fn blank(square: Square, n: usize) -> Self {
+        Self {
+            square,
+            n,
+            revealed: vec![0; n * n],
+            cells: None,
+        }
+    }
+
+    pub fn public_inputs<F: PrimeField>(&self) -> Vec<F> {
+        self.revealed.iter().map(|&r| F::from(r)).collect()
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for SquareCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let n = self.n;
+        let max = self.square.max_value(n);
+        let revealed = self
+            .revealed
+            .iter()
+            .map(|&r| FpVar::new_input(ns!(cs, "revealed"), || Ok(F::from(r))))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut cells = Vec::with_capacity(n * n);
+        let mut selectors = Vec::with_capacity(n * n);
+        for (i, r) in revealed.iter().enumerate() {
+            let cell = FpVar::new_witness(ns!(cs, "cell"), || {
+                self.cells
+                    .as_ref()
+                    .map(|c| F::from(c[i]))
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            // 1 <= cell <= max
+            selectors.push(one_hot(cs.clone(), &(&cell - F::one()), max)?);
+            r.mul_equals(&(r - &cell), &FpVar::zero())?;
+            cells.push(cell);
+        }
+
+        // each value once per line, or once overall
+        let enforce_once = |indices: &[usize]| -> Result<(), SynthesisError> {
+            let mut counts = vec![FpVar::zero(); max];
+            for &i in indices {
+                for (count, bit) in counts.iter_mut().zip(&selectors[i]) {
+                    *count += FpVar::from(bit.clone());
+                }
+            }
+            for count in counts {
+                count.enforce_equal(&FpVar::one())?;
+            }
+            Ok(())
+        };
+        match self.square {
+            Square::Latin => {
+                for line in lines(self.square, n) {
+                    enforce_once(&line)?;
+                }
+            }
+            Square::Magic => {
+                enforce_once(&(0..n * n).collect::<Vec<_>>())?;
+                let constant = FpVar::constant(F::from(magic_constant(n)));
+                for line in lines(self.square, n) {
+                    let mut sum = FpVar::zero();
+                    for i in line {
+                        sum += &cells[i];
+                    }
+                    sum.enforce_equal(&constant)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    fn is_satisfied(circuit: SquareCircuit) -> bool {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    // the cyclic one, row i is 1..=n shifted by i
+    fn latin(n: usize) -> Vec<u64> {
+        (0..n * n)
+            .map(|c| ((c / n + c % n) % n + 1) as u64)
+            .collect()
+    }
+
+    // Siamese method, odd n
+    fn magic(n: usize) -> Vec<u64> {
+        let mut cells = vec![0; n * n];
+        let (mut i, mut j) = (0, n / 2);
+        for value in 1..=(n * n) as u64 {
+            cells[i * n + j] = value;
+            let (up, right) = ((i + n - 1) % n, (j + 1) % n);
+            match cells[up * n + right] {
+                0 => {
+                    i = up;
+                    j = right;
+                }
+                _ => i = (i + 1) % n,
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn test_latin_squares() {
+        for n in 2..6 {
+            let cells = latin(n);
+            assert!(is_valid(Square::Latin, n, &cells));
+            let circuit = SquareCircuit::new(Square::Latin, n, cells.clone(), &[0, n + 1]);
+            assert!(is_satisfied(circuit));
+
+            // two swapped cells of a row break the columns, a value out of range
+            let mut swapped = cells.clone();
+            swapped.swap(0, 1);
+            assert!(!is_valid(Square::Latin, n, &swapped));
+            assert!(!is_satisfied(SquareCircuit::new(
+                Square::Latin,
+                n,
+                swapped,
+                &[]
+            )));
+            let mut out_of_range = cells.clone();
+            out_of_range[0] = n as u64 + 1;
+            assert!(!is_satisfied(SquareCircuit::new(
+                Square::Latin,
+                n,
+                out_of_range,
+                &[]
+            )));
+        }
+    }
+
+    #[test]
+    fn test_magic_squares() {
+        let rng = &mut ark_std::test_rng();
+        // Duerer's, and the Siamese ones
+        let melencolia = vec![16, 3, 2, 13, 5, 10, 11, 8, 9, 6, 7, 12, 4, 15, 14, 1];
+        let squares = vec![(4, melencolia), (3, magic(3)), (5, magic(5))];
+        for (n, cells) in squares {
+            assert!(is_valid(Square::Magic, n, &cells), "{:?}", cells);
+            assert!(is_satisfied(SquareCircuit::new(
+                Square::Magic,
+                n,
+                cells.clone(),
+                &[1, 4]
+            )));
+
+            // a transposed square is magic too, swapping two corners breaks the sums
+            let transposed: Vec<u64> = (0..n * n).map(|c| cells[(c % n) * n + c / n]).collect();
+            assert!(is_satisfied(SquareCircuit::new(
+                Square::Magic,
+                n,
+                transposed,
+                &[]
+            )));
+            let mut swapped = cells.clone();
+            swapped.swap(0, n * n - 1);
+            assert!(!is_valid(Square::Magic, n, &swapped));
+            assert!(!is_satisfied(SquareCircuit::new(
+                Square::Magic,
+                n,
+                swapped,
+                &[]
+            )));
+        }
+
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            SquareCircuit::blank(Square::Magic, 3),
+            rng,
+        )
+        .unwrap();
+        let circuit = SquareCircuit::new(Square::Magic, 3, magic(3), &[0, 4, 8]);
+        let inputs = circuit.public_inputs::<Fr>();
+        assert_eq!(inputs[4], Fr::from(5u8));
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+        let mut other = inputs.clone();
+        other[4] = Fr::from(6u8);
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &other, &proof).unwrap());
+    }
+
+    #[test]
+    fn test_revealed_cells() {
+        let cells = latin(4);
+        let mut circuit = SquareCircuit::new(Square::Latin, 4, cells, &[5, 10]);
+        assert!(is_satisfied(circuit.clone()));
+        // the prover's square doesn't have the value the verifier sees
+        circuit.revealed[5] = circuit.revealed[5] % 4 + 1;
+        assert!(!is_satisfied(circuit));
+    }
+}
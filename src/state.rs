@@ -0,0 +1,300 @@
+use ark_ff::PrimeField;
+use ark_serialize::SerializationError;
+use ark_sponge::{poseidon::PoseidonParameters, Absorb};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+
+use crate::merkle_gadget::{MerklePath, MerkleTree};
+
+// The Merkle tree of a stateful demo (deposits, accounts, registered keys) with its
+// bookkeeping: leaves are appended at the next free index or updated in place, and
+// with `open` every change also goes to a file, so the tree outlives the process. The
+// file is a log,
+//   magic (4 bytes) | version (1 byte) | height (1 byte) | (index u64 LE | leaf)*
+// with the leaves in `CanonicalSerialize` form, appended to on every change and replayed
+// by `open`. The Poseidon parameters are not stored, the caller passes the ones the
+// circuit uses; a log replayed with other parameters gives another root.
+
+pub const MAGIC: [u8; 4] = *b"ARKT";
+pub const VERSION: u8 = 1;
+
+#[derive(Debug)]
+pub enum StateError {
+    Io(io::Error),
+    Serialization(SerializationError),
+    InvalidHeader,
+    UnsupportedVersion(u8),
+    WrongHeight { expected: usize, found: usize },
+    // every index below 2^height is taken
+    Full,
+    // an update of an index that was never inserted, or a log entry past the tree
+    NoLeaf(usize),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::Io(e) => write!(f, "io error: {}", e),
+            StateError::Serialization(e) => write!(f, "serialization error: {}", e),
+            StateError::InvalidHeader => write!(f, "not a tree state file"),
+            StateError::UnsupportedVersion(v) => write!(f, "unsupported state version {}", v),
+            StateError::WrongHeight { expected, found } => write!(
+                f,
+                "the file holds a tree of height {}, expected {}",
+                found, expected
+            ),
+            StateError::Full => write!(f, "the tree is full"),
+            StateError::NoLeaf(index) => write!(f, "no leaf at index {}", index),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+impl From<io::Error> for StateError {
+    fn from(e: io::Error) -> Self {
+        StateError::Io(e)
+    }
+}
+
+impl From<SerializationError> for StateError {
+    fn from(e: SerializationError) -> Self {
+        StateError::Serialization(e)
+    }
+}
+
+pub struct TreeState<F: PrimeField> {
+    tree: MerkleTree<F>,
+    // leaves inserted so far, the next free index
+    len: usize,
+    log: Option<File>,
+}
+
+impl<F: PrimeField + Absorb> TreeState<F> {
+    // in memory only
+    pub fn new(params: PoseidonParameters<F>, height: usize) -> Self {
+        assert!(height < 64, "the indices are u64 in the log");
+        Self {
+            tree: MerkleTree::new(params, height, &[]).expect("an empty tree"),
+            len: 0,
+            log: None,
+        }
+    }
+
+    // the tree in the file at `path`, an empty one written there if there is no file
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        params: PoseidonParameters<F>,
+        height: usize,
+    ) -> Result<Self, StateError> {
+        let mut state = Self::new(params, height);
+        let path = path.as_ref();
+        if !path.exists() {
+            let mut file = File::create(path)?;
+            file.write_all(&MAGIC)?;
+            file.write_all(&[VERSION, height as u8])?;
+            file.sync_all()?;
+        }
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut header = [0u8; 6];
+        reader.read_exact(&mut header)?;
+        if header[..4] != MAGIC {
+            return Err(StateError::InvalidHeader);
+        }
+        if header[4] != VERSION {
+            return Err(StateError::UnsupportedVersion(header[4]));
+        }
+        if header[5] as usize != height {
+            return Err(StateError::WrongHeight {
+                expected: height,
+                found: header[5] as usize,
+            });
+        }
+        let mut index = [0u8; 8];
+        // a log that ends inside an entry, e.g. after a crash, fails to read
+        while read_index(&mut reader, &mut index)? {
+            let index = u64::from_le_bytes(index) as usize;
+            let leaf = F::deserialize(&mut reader)?;
+            state.set(index, leaf)?;
+        }
+        state.log = Some(OpenOptions::new().append(true).open(path)?);
+        Ok(state)
+    }
+
+    // the index of the new leaf
+    pub fn insert(&mut self, leaf: F) -> Result<usize, StateError> {
+        let index = self.len;
+        if index >= 1 << self.height() {
+            return Err(StateError::Full);
+        }
+        self.write(index, leaf)?;
+        self.set(index, leaf)?;
+        Ok(index)
+    }
+
+    pub fn update(&mut self, index: usize, leaf: F) -> Result<(), StateError> {
+        if index >= self.len {
+            return Err(StateError::NoLeaf(index));
+        }
+        self.write(index, leaf)?;
+        self.set(index, leaf)
+    }
+
+    // the file first: a change that is not in the log never shows in the tree
+    fn write(&mut self, index: usize, leaf: F) -> Result<(), StateError> {
+        if let Some(log) = self.log.as_mut() {
+            let mut entry = (index as u64).to_le_bytes().to_vec();
+            leaf.serialize(&mut entry)?;
+            log.write_all(&entry)?;
+            log.sync_data()?;
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, index: usize, leaf: F) -> Result<(), StateError> {
+        self.tree
+            .update(index, leaf)
+            .ok_or(StateError::NoLeaf(index))?;
+        self.len = self.len.max(index + 1);
+        Ok(())
+    }
+
+    pub fn height(&self) -> usize {
+        self.tree.height()
+    }
+
+    pub fn root(&self) -> F {
+        self.tree.root()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn leaf(&self, index: usize) -> Option<F> {
+        self.leaves().get(index).copied()
+    }
+
+    // the inserted leaves, without the zeros after them
+    pub fn leaves(&self) -> &[F] {
+        &self.tree.leaves()[..self.len]
+    }
+
+    // None for an index that was never inserted
+    pub fn path(&self, index: usize) -> Option<MerklePath<F>> {
+        match index < self.len {
+            true => self.tree.path(index),
+            false => None,
+        }
+    }
+
+    pub fn tree(&self) -> &MerkleTree<F> {
+        &self.tree
+    }
+}
+
+// false at the end of the log
+fn read_index<R: Read>(reader: &mut R, index: &mut [u8; 8]) -> Result<bool, StateError> {
+    let mut read = 0;
+    while read < index.len() {
+        match reader.read(&mut index[read..])? {
+            0 if read == 0 => return Ok(false),
+            0 => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            n => read += n,
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use ark_bls12_381::Fr;
+    use ark_std::UniformRand;
+    use std::fs;
+
+    #[test]
+    fn test_in_memory() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let mut state = TreeState::<Fr>::new(params.clone(), 3);
+        let leaves: Vec<Fr> = (0..8).map(|_| Fr::rand(rng)).collect();
+        for (i, leaf) in leaves.iter().enumerate() {
+            assert_eq!(state.insert(*leaf).unwrap(), i);
+        }
+        assert!(matches!(state.insert(Fr::from(1u8)), Err(StateError::Full)));
+        assert_eq!(
+            state.root(),
+            MerkleTree::new(params.clone(), 3, &leaves).unwrap().root()
+        );
+
+        let mut state = TreeState::<Fr>::new(params.clone(), 3);
+        state.insert(leaves[0]).unwrap();
+        assert!(matches!(
+            state.update(1, leaves[1]),
+            Err(StateError::NoLeaf(1))
+        ));
+        assert_eq!(state.path(1), None);
+        state.update(0, leaves[1]).unwrap();
+        let path = state.path(0).unwrap();
+        assert_eq!(path.root(&params, leaves[1]), state.root());
+        assert_eq!(state.leaves(), &leaves[1..2]);
+    }
+
+    #[test]
+    fn test_file() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let path =
+            std::env::temp_dir().join(format!("gadgets_state_test_{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut state = TreeState::<Fr>::open(&path, params.clone(), 4).unwrap();
+        assert!(state.is_empty());
+        for _ in 0..5 {
+            state.insert(Fr::rand(rng)).unwrap();
+        }
+        state.update(2, Fr::from(7u8)).unwrap();
+        let (root, leaves) = (state.root(), state.leaves().to_vec());
+        drop(state);
+
+        // replayed, and appended to again
+        let mut state = TreeState::<Fr>::open(&path, params.clone(), 4).unwrap();
+        assert_eq!(state.root(), root);
+        assert_eq!(state.leaves(), &leaves[..]);
+        assert_eq!(state.insert(Fr::from(9u8)).unwrap(), 5);
+        let root = state.root();
+        drop(state);
+        assert_eq!(
+            TreeState::<Fr>::open(&path, params.clone(), 4)
+                .unwrap()
+                .root(),
+            root
+        );
+
+        assert!(matches!(
+            TreeState::<Fr>::open(&path, params.clone(), 5),
+            Err(StateError::WrongHeight {
+                expected: 5,
+                found: 4
+            })
+        ));
+        // a torn last entry
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+        assert!(TreeState::<Fr>::open(&path, params.clone(), 4).is_err());
+        fs::write(&path, b"ARKW\x01\x04").unwrap();
+        assert!(matches!(
+            TreeState::<Fr>::open(&path, params, 4),
+            Err(StateError::InvalidHeader)
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+}
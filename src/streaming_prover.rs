@@ -0,0 +1,495 @@
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{PrimeField, UniformRand, Zero};
+use ark_groth16::{Proof, VerifyingKey};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisError,
+};
+use ark_serialize::CanonicalDeserialize;
+use ark_std::rand::{CryptoRng, RngCore};
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+
+use crate::msm::{CpuMsm, MsmBackend};
+use crate::persist::{self, ArtifactKind, PersistError, SerializationMode, Validation};
+use crate::progress::Progress;
+
+// Groth16 proving without the proving key in memory. The key is read from its `persist`
+// envelope in one pass, in the order it is stored (vk, beta, delta, then the A, B in G1,
+// B in G2, H and L queries), and every query goes into its MSM a chunk of points at a
+// time, so the key costs `chunk_size` points of memory instead of about a kilobyte per
+// variable. Synthesis, the witness and the QAP coefficients still take what they take
+// in `Groth16::prove`, `tests/streaming_memory.rs` measures both.
+//
+// The proof is the one ark-groth16 makes from the same r and s. Reading the file is the
+// price: an uncompressed key loaded unchecked is the fast choice for a key this machine
+// wrote itself, a compressed one costs a square root per point.
+
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamingOptions {
+    // points per MSM chunk
+    pub chunk_size: usize,
+    pub validation: Validation,
+}
+
+impl Default for StreamingOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            validation: Validation::Checked,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum StreamingError {
+    Persist(PersistError),
+    Synthesis(SynthesisError),
+    // the key is for a circuit of another size
+    KeyMismatch {
+        query: &'static str,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for StreamingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamingError::Persist(e) => write!(f, "{}", e),
+            StreamingError::Synthesis(e) => write!(f, "synthesis error: {}", e),
+            StreamingError::KeyMismatch {
+                query,
+                expected,
+                found,
+            } => write!(
+                f,
+                "proving key does not fit the circuit: {} has {} elements, expected {}",
+                query, found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StreamingError {}
+
+impl From<PersistError> for StreamingError {
+    fn from(e: PersistError) -> Self {
+        StreamingError::Persist(e)
+    }
+}
+
+impl From<SynthesisError> for StreamingError {
+    fn from(e: SynthesisError) -> Self {
+        StreamingError::Synthesis(e)
+    }
+}
+
+type BigInt<F> = <F as PrimeField>::BigInt;
+
+// the scalars of all MSMs: the full assignment (constant one first) and the
+// coefficients of h
+pub(crate) struct Witness<F: PrimeField> {
+    pub(crate) assignment: Vec<BigInt<F>>,
+    pub(crate) num_witness_variables: usize,
+    pub(crate) h: Vec<BigInt<F>>,
+}
+
+impl<F: PrimeField> Witness<F> {
+    // the witness part, the scalars of the L query
+    pub(crate) fn aux(&self) -> &[BigInt<F>] {
+        &self.assignment[self.assignment.len() - self.num_witness_variables..]
+    }
+}
+
+fn evaluate<F: PrimeField>(terms: &[(F, usize)], assignment: &[F]) -> F {
+    terms
+        .iter()
+        .map(|&(coeff, i)| {
+            if coeff.is_one() {
+                assignment[i]
+            } else {
+                coeff * assignment[i]
+            }
+        })
+        .sum()
+}
+
+// synthesis and the R1CS to QAP witness map of ark-groth16 (private there): h with
+// A(x) B(x) - C(x) = h(x) Z(x), the public inputs added as extra A rows
+pub(crate) fn witness<F: PrimeField, C: ConstraintSynthesizer<F>>(
+    circuit: C,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<Witness<F>, SynthesisError> {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+    let matrices = cs.to_matrices().ok_or(SynthesisError::MissingCS)?;
+    progress(Progress::Synthesized);
+    // the rest of the constraint system goes here, the matrices are all we need of it
+    let ConstraintSystem {
+        instance_assignment,
+        witness_assignment,
+        ..
+    } = cs.into_inner().ok_or(SynthesisError::MissingCS)?;
+    let num_inputs = instance_assignment.len();
+    let num_witness_variables = witness_assignment.len();
+    let assignment = [instance_assignment, witness_assignment].concat();
+
+    let num_constraints = matrices.num_constraints;
+    let domain = GeneralEvaluationDomain::<F>::new(num_constraints + num_inputs)
+        .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+    let mut a = vec![F::zero(); domain.size()];
+    let mut b = vec![F::zero(); domain.size()];
+    let mut c = vec![F::zero(); domain.size()];
+    for (i, ((a_row, b_row), c_row)) in matrices
+        .a
+        .iter()
+        .zip(matrices.b.iter())
+        .zip(matrices.c.iter())
+        .enumerate()
+    {
+        a[i] = evaluate(a_row, &assignment);
+        b[i] = evaluate(b_row, &assignment);
+        c[i] = evaluate(c_row, &assignment);
+    }
+    drop(matrices);
+    a[num_constraints..num_constraints + num_inputs].copy_from_slice(&assignment[..num_inputs]);
+
+    for v in [&mut a, &mut b, &mut c].iter_mut() {
+        domain.ifft_in_place(v);
+        domain.coset_fft_in_place(v);
+    }
+    let mut h = domain.mul_polynomials_in_evaluation_domain(&a, &b);
+    drop((a, b));
+    for (h, c) in h.iter_mut().zip(c) {
+        *h -= c;
+    }
+    domain.divide_by_vanishing_poly_on_coset_in_place(&mut h);
+    domain.coset_ifft_in_place(&mut h);
+    progress(Progress::WitnessMapped);
+
+    Ok(Witness {
+        assignment: assignment.iter().map(|x| x.into_repr()).collect(),
+        num_witness_variables,
+        h: h.iter().map(|x| x.into_repr()).collect(),
+    })
+}
+
+// the queries of a key in a reader, summed a chunk at a time
+struct Queries<'a, R, M> {
+    reader: R,
+    mode: SerializationMode,
+    options: &'a StreamingOptions,
+    backend: &'a M,
+}
+
+impl<'a, R: Read, M: MsmBackend> Queries<'a, R, M> {
+    // sum scalars_i * query_i over the next query
+    fn sum<G: AffineCurve>(
+        &mut self,
+        query: &'static str,
+        expected: usize,
+        scalars: &[BigInt<G::ScalarField>],
+    ) -> Result<G::Projective, StreamingError> {
+        let len = u64::deserialize(&mut self.reader).map_err(PersistError::from)? as usize;
+        if len != expected {
+            return Err(StreamingError::KeyMismatch {
+                query,
+                expected,
+                found: len,
+            });
+        }
+        let chunk_size = self.options.chunk_size.max(1);
+        let mut chunk: Vec<G> = Vec::with_capacity(chunk_size.min(len));
+        let mut sum = G::Projective::zero();
+        for start in (0..len).step_by(chunk_size) {
+            let end = (start + chunk_size).min(len);
+            chunk.clear();
+            for _ in start..end {
+                chunk.push(persist::deserialize_with_mode(
+                    &mut self.reader,
+                    self.mode,
+                    self.options.validation,
+                )?);
+            }
+            // h has one coefficient more than its query, always zero
+            let scalars = &scalars[start.min(scalars.len())..end.min(scalars.len())];
+            sum += self.backend.msm(&chunk, scalars);
+        }
+        Ok(sum)
+    }
+}
+
+// the MSM results of a proof: every query of the key against its scalars
+pub(crate) struct QuerySums<E: PairingEngine> {
+    pub(crate) a: E::G1Projective,
+    pub(crate) b_g1: E::G1Projective,
+    pub(crate) b_g2: E::G2Projective,
+    pub(crate) h: E::G1Projective,
+    pub(crate) l: E::G1Projective,
+}
+
+// A = alpha + sum a_i(x) w_i + r delta, B likewise with beta and s, and
+// C = s A + r B - r s delta + sum l_i w_i + h
+pub(crate) fn assemble<E: PairingEngine>(
+    vk: &VerifyingKey<E>,
+    beta_g1: E::G1Affine,
+    delta_g1: E::G1Affine,
+    sums: QuerySums<E>,
+    r: E::Fr,
+    s: E::Fr,
+) -> Proof<E> {
+    let g_a = delta_g1.mul(r) + sums.a + vk.alpha_g1.into_projective();
+    let g1_b = if r.is_zero() {
+        E::G1Projective::zero()
+    } else {
+        delta_g1.mul(s) + sums.b_g1 + beta_g1.into_projective()
+    };
+    let g2_b = vk.delta_g2.mul(s) + sums.b_g2 + vk.beta_g2.into_projective();
+    let g_c =
+        g_a.mul(s.into_repr()) + g1_b.mul(r.into_repr()) - delta_g1.mul(r * s) + sums.l + sums.h;
+    Proof {
+        a: g_a.into_affine(),
+        b: g2_b.into_affine(),
+        c: g_c.into_affine(),
+    }
+}
+
+// the proof of `ark_groth16::create_proof(circuit, pk, r, s)`, `reader` at the start of
+// the proving key's envelope
+pub fn create_proof_streaming<E, C, R>(
+    reader: R,
+    circuit: C,
+    r: E::Fr,
+    s: E::Fr,
+    options: &StreamingOptions,
+) -> Result<Proof<E>, StreamingError>
+where
+    E: PairingEngine,
+    C: ConstraintSynthesizer<E::Fr>,
+    R: Read,
+{
+    create_proof_streaming_with(reader, circuit, r, s, options, &CpuMsm)
+}
+
+// the same with the MSMs on `backend`
+pub fn create_proof_streaming_with<E, C, R, M>(
+    mut reader: R,
+    circuit: C,
+    r: E::Fr,
+    s: E::Fr,
+    options: &StreamingOptions,
+    backend: &M,
+) -> Result<Proof<E>, StreamingError>
+where
+    E: PairingEngine,
+    C: ConstraintSynthesizer<E::Fr>,
+    R: Read,
+    M: MsmBackend,
+{
+    let witness = witness(circuit, &mut |_| {})?;
+    let num_variables = witness.assignment.len();
+
+    let mode = persist::read_header(&mut reader, ArtifactKind::ProvingKey)?;
+    let validation = options.validation;
+    let vk: VerifyingKey<E> = persist::deserialize_with_mode(&mut reader, mode, validation)?;
+    let beta_g1: E::G1Affine = persist::deserialize_with_mode(&mut reader, mode, validation)?;
+    let delta_g1: E::G1Affine = persist::deserialize_with_mode(&mut reader, mode, validation)?;
+
+    let mut queries = Queries {
+        reader,
+        mode,
+        options,
+        backend,
+    };
+    let assignment = &witness.assignment;
+    let a = queries.sum::<E::G1Affine>("a_query", num_variables, assignment)?;
+    let b_g1 = queries.sum::<E::G1Affine>("b_g1_query", num_variables, assignment)?;
+    let b_g2 = queries.sum::<E::G2Affine>("b_g2_query", num_variables, assignment)?;
+    let h = queries.sum::<E::G1Affine>("h_query", witness.h.len() - 1, &witness.h)?;
+    let aux = witness.aux();
+    let l = queries.sum::<E::G1Affine>("l_query", aux.len(), aux)?;
+
+    let sums = QuerySums {
+        a,
+        b_g1,
+        b_g2,
+        h,
+        l,
+    };
+    Ok(assemble(&vk, beta_g1, delta_g1, sums, r, s))
+}
+
+// r and s drawn as `Groth16::prove` draws them
+pub fn prove_streaming<E, C, R, G>(
+    reader: R,
+    circuit: C,
+    options: &StreamingOptions,
+    rng: &mut G,
+) -> Result<Proof<E>, StreamingError>
+where
+    E: PairingEngine,
+    C: ConstraintSynthesizer<E::Fr>,
+    R: Read,
+    G: RngCore + CryptoRng,
+{
+    let r = E::Fr::rand(rng);
+    let s = E::Fr::rand(rng);
+    create_proof_streaming(reader, circuit, r, s, options)
+}
+
+// for a key written by `persist::save(path, &pk, ArtifactKind::ProvingKey, mode)`, or by
+// `save_zstd`, decompressed as it is read
+pub fn prove_from_file<E, C, P, G>(
+    path: P,
+    circuit: C,
+    options: &StreamingOptions,
+    rng: &mut G,
+) -> Result<Proof<E>, StreamingError>
+where
+    E: PairingEngine,
+    C: ConstraintSynthesizer<E::Fr>,
+    P: AsRef<Path>,
+    G: RngCore + CryptoRng,
+{
+    prove_streaming(persist::open(path)?, circuit, options, rng)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::synthetic::SyntheticCircuit;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::{create_proof, Groth16, ProvingKey};
+    use ark_snark::SNARK;
+
+    fn key_bytes(pk: &ProvingKey<Bls12_381>, mode: SerializationMode) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        persist::write_artifact(pk, ArtifactKind::ProvingKey, mode, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_same_proof_as_in_memory() {
+        let rng = &mut ark_std::test_rng();
+        let circuit = SyntheticCircuit::new(50, 40, 3).with_public_inputs(3);
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(circuit, rng).unwrap();
+        let (r, s) = (Fr::rand(rng), Fr::rand(rng));
+        let expected = create_proof(circuit, &pk, r, s).unwrap();
+
+        for &(mode, validation) in [
+            (SerializationMode::Compressed, Validation::Checked),
+            (SerializationMode::Uncompressed, Validation::Unchecked),
+        ]
+        .iter()
+        {
+            let bytes = key_bytes(&pk, mode);
+            for &chunk_size in [1, 7, DEFAULT_CHUNK_SIZE].iter() {
+                let options = StreamingOptions {
+                    chunk_size,
+                    validation,
+                };
+                let proof = create_proof_streaming(&bytes[..], circuit, r, s, &options).unwrap();
+                assert_eq!(proof, expected, "{:?} chunks of {}", mode, chunk_size);
+            }
+        }
+
+        // without blinding too, B in G1 is skipped then
+        let bytes = key_bytes(&pk, SerializationMode::Uncompressed);
+        let zero = Fr::zero();
+        let proof = create_proof_streaming(
+            &bytes[..],
+            circuit,
+            zero,
+            zero,
+            &StreamingOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(proof, create_proof(circuit, &pk, zero, zero).unwrap());
+
+        let proof =
+            prove_streaming(&bytes[..], circuit, &StreamingOptions::default(), rng).unwrap();
+        let inputs = circuit.public_inputs();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+    }
+
+    #[test]
+    fn test_prove_from_file() {
+        let rng = &mut ark_std::test_rng();
+        let circuit = SyntheticCircuit::new(32, 16, 2);
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(circuit, rng).unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "gadgets_streaming_prover_pk_{}.bin",
+            std::process::id()
+        ));
+        persist::save(
+            &path,
+            &pk,
+            ArtifactKind::ProvingKey,
+            SerializationMode::Uncompressed,
+        )
+        .unwrap();
+
+        let options = StreamingOptions {
+            chunk_size: 8,
+            validation: Validation::Unchecked,
+        };
+        let proof: Proof<Bls12_381> = prove_from_file(&path, circuit, &options, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &circuit.public_inputs(), &proof).unwrap());
+
+        // a key of another circuit size is refused, not misused
+        let other = SyntheticCircuit::new(32, 17, 2);
+        let result = prove_from_file::<Bls12_381, _, _, _>(&path, other, &options, rng);
+        assert!(matches!(
+            result,
+            Err(StreamingError::KeyMismatch {
+                query: "a_query",
+                ..
+            })
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_other_artifacts() {
+        let rng = &mut ark_std::test_rng();
+        let circuit = SyntheticCircuit::new(8, 4, 2);
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(circuit, rng).unwrap();
+        let options = StreamingOptions::default();
+
+        let mut bytes = Vec::new();
+        persist::write_artifact(
+            &vk,
+            ArtifactKind::VerifyingKey,
+            SerializationMode::Compressed,
+            &mut bytes,
+        )
+        .unwrap();
+        let result = prove_streaming::<Bls12_381, _, _, _>(&bytes[..], circuit, &options, rng);
+        assert!(matches!(
+            result,
+            Err(StreamingError::Persist(PersistError::WrongKind { .. }))
+        ));
+
+        // compressed points are always checked, as in `persist`
+        let bytes = key_bytes(&pk, SerializationMode::Compressed);
+        let unchecked = StreamingOptions {
+            validation: Validation::Unchecked,
+            ..options
+        };
+        let result = prove_streaming::<Bls12_381, _, _, _>(&bytes[..], circuit, &unchecked, rng);
+        assert!(matches!(
+            result,
+            Err(StreamingError::Persist(PersistError::UnsupportedMode(..)))
+        ));
+
+        let truncated = &bytes[..bytes.len() - 10];
+        let result = prove_streaming::<Bls12_381, _, _, _>(truncated, circuit, &options, rng);
+        assert!(matches!(result, Err(StreamingError::Persist(_))));
+    }
+}
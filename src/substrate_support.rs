@@ -0,0 +1,324 @@
+use ark_ec::PairingEngine;
+use ark_ff::PrimeField;
+use ark_groth16::{Proof, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{fmt, vec::Vec};
+use parity_scale_codec::{Decode, DecodeAll, Encode, Error as CodecError, Input, Output};
+
+use crate::verifier::{prepare_verifying_key, verify_proof, VerifierError};
+
+// SCALE codec wrappers for Groth16 verifying keys, proofs and public inputs, and no_std
+// verification entry points for the multiply and cubic circuits, so a pallet can take
+// them as call arguments or keep them in storage:
+//
+//   cargo build --no-default-features --features substrate --target thumbv7em-none-eabi
+//
+// Each value is encoded as a SCALE `Vec<u8>` (compact length, then the bytes) holding
+// its compressed canonical serialization. Decoding checks every point, since the bytes
+// come from the chain.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScaleVerifyingKey<E: PairingEngine>(pub VerifyingKey<E>);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScaleProof<E: PairingEngine>(pub Proof<E>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScaleField<F: PrimeField>(pub F);
+
+fn encode_canonical<T: CanonicalSerialize>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(value.serialized_size());
+    value
+        .serialize(&mut bytes)
+        .expect("serializing into a Vec cannot fail");
+    bytes
+}
+
+fn decode_canonical<T: CanonicalDeserialize, I: Input>(
+    input: &mut I,
+    what: &'static str,
+) -> Result<T, CodecError> {
+    let bytes = Vec::<u8>::decode(input)?;
+    let mut reader = &bytes[..];
+    let value = T::deserialize(&mut reader).map_err(|_| CodecError::from(what))?;
+    if !reader.is_empty() {
+        return Err(CodecError::from(what));
+    }
+    Ok(value)
+}
+
+impl<E: PairingEngine> Encode for ScaleVerifyingKey<E> {
+    fn size_hint(&self) -> usize {
+        self.0.serialized_size() + 5
+    }
+
+    fn encode_to<O: Output + ?Sized>(&self, dest: &mut O) {
+        encode_canonical(&self.0).encode_to(dest)
+    }
+}
+
+impl<E: PairingEngine> Decode for ScaleVerifyingKey<E> {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, CodecError> {
+        Ok(Self(decode_canonical(input, "invalid verifying key")?))
+    }
+}
+
+impl<E: PairingEngine> Encode for ScaleProof<E> {
+    fn size_hint(&self) -> usize {
+        self.0.serialized_size() + 5
+    }
+
+    fn encode_to<O: Output + ?Sized>(&self, dest: &mut O) {
+        encode_canonical(&self.0).encode_to(dest)
+    }
+}
+
+impl<E: PairingEngine> Decode for ScaleProof<E> {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, CodecError> {
+        Ok(Self(decode_canonical(input, "invalid proof")?))
+    }
+}
+
+impl<F: PrimeField> Encode for ScaleField<F> {
+    fn size_hint(&self) -> usize {
+        self.0.serialized_size() + 5
+    }
+
+    fn encode_to<O: Output + ?Sized>(&self, dest: &mut O) {
+        encode_canonical(&self.0).encode_to(dest)
+    }
+}
+
+impl<F: PrimeField> Decode for ScaleField<F> {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, CodecError> {
+        Ok(Self(decode_canonical(input, "invalid field element")?))
+    }
+}
+
+// the circuits a pallet can verify proofs for, e.g. as a call argument
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum DemoCircuit {
+    // public input: the product c = a * b
+    Multiply,
+    // public input: out = x^3 + x + 5
+    Cubic,
+}
+
+impl DemoCircuit {
+    pub fn num_public_inputs(self) -> usize {
+        match self {
+            DemoCircuit::Multiply | DemoCircuit::Cubic => 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SubstrateError {
+    Codec(CodecError),
+    WrongInputCount {
+        circuit: DemoCircuit,
+        expected: usize,
+        found: usize,
+    },
+    Verifier(VerifierError),
+}
+
+impl fmt::Display for SubstrateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubstrateError::Codec(e) => write!(f, "cannot decode: {}", e),
+            SubstrateError::WrongInputCount {
+                circuit,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{:?} takes {} public inputs, got {}",
+                circuit, expected, found
+            ),
+            SubstrateError::Verifier(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl ark_std::error::Error for SubstrateError {}
+
+impl From<CodecError> for SubstrateError {
+    fn from(e: CodecError) -> Self {
+        SubstrateError::Codec(e)
+    }
+}
+
+impl From<VerifierError> for SubstrateError {
+    fn from(e: VerifierError) -> Self {
+        SubstrateError::Verifier(e)
+    }
+}
+
+pub fn verify_decoded<E: PairingEngine>(
+    circuit: DemoCircuit,
+    vk: &ScaleVerifyingKey<E>,
+    proof: &ScaleProof<E>,
+    public_inputs: &[ScaleField<E::Fr>],
+) -> Result<bool, SubstrateError> {
+    if public_inputs.len() != circuit.num_public_inputs() {
+        return Err(SubstrateError::WrongInputCount {
+            circuit,
+            expected: circuit.num_public_inputs(),
+            found: public_inputs.len(),
+        });
+    }
+    let inputs: Vec<E::Fr> = public_inputs.iter().map(|input| input.0).collect();
+    Ok(verify_proof(
+        &prepare_verifying_key(&vk.0),
+        &proof.0,
+        &inputs,
+    )?)
+}
+
+fn decode_exact<T: Decode>(bytes: &[u8]) -> Result<T, CodecError> {
+    T::decode_all(&mut &bytes[..])
+}
+
+// the SCALE encodings of a `ScaleVerifyingKey`, a `ScaleProof` and a `Vec<ScaleField>`,
+// trailing bytes are rejected
+pub fn verify<E: PairingEngine>(
+    circuit: DemoCircuit,
+    vk: &[u8],
+    proof: &[u8],
+    public_inputs: &[u8],
+) -> Result<bool, SubstrateError> {
+    let public_inputs: Vec<ScaleField<E::Fr>> = decode_exact(public_inputs)?;
+    verify_decoded::<E>(
+        circuit,
+        &decode_exact(vk)?,
+        &decode_exact(proof)?,
+        &public_inputs,
+    )
+}
+
+// `c` is the SCALE encoding of a single `ScaleField`
+pub fn verify_multiply<E: PairingEngine>(
+    vk: &[u8],
+    proof: &[u8],
+    c: &[u8],
+) -> Result<bool, SubstrateError> {
+    let c: ScaleField<E::Fr> = decode_exact(c)?;
+    verify_decoded::<E>(
+        DemoCircuit::Multiply,
+        &decode_exact(vk)?,
+        &decode_exact(proof)?,
+        &[c],
+    )
+}
+
+// `out` is the SCALE encoding of a single `ScaleField`
+pub fn verify_cubic<E: PairingEngine>(
+    vk: &[u8],
+    proof: &[u8],
+    out: &[u8],
+) -> Result<bool, SubstrateError> {
+    let out: ScaleField<E::Fr> = decode_exact(out)?;
+    verify_decoded::<E>(
+        DemoCircuit::Cubic,
+        &decode_exact(vk)?,
+        &decode_exact(proof)?,
+        &[out],
+    )
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::cubic_demo::CubicDemoCircuit;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+    use ark_groth16::Groth16;
+    use ark_snark::SNARK;
+
+    #[test]
+    fn test_scale_round_trip() {
+        let rng = &mut ark_std::test_rng();
+        let (_, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            MultiplyDemoCircuit::<BlsFr> { a: None, b: None },
+            rng,
+        )
+        .unwrap();
+        let vk = ScaleVerifyingKey(vk);
+        let encoded = vk.encode();
+        assert_eq!(
+            ScaleVerifyingKey::decode_all(&mut &encoded[..]).unwrap(),
+            vk
+        );
+
+        let inputs = vec![ScaleField(BlsFr::from(33u8)), ScaleField(BlsFr::from(35u8))];
+        let encoded = inputs.encode();
+        assert_eq!(
+            Vec::<ScaleField<BlsFr>>::decode_all(&mut &encoded[..]).unwrap(),
+            inputs
+        );
+        assert!(DemoCircuit::decode_all(&mut &DemoCircuit::Cubic.encode()[..]).is_ok());
+
+        // a truncated field element, and one with a trailing byte
+        let mut encoded = ScaleField(BlsFr::from(33u8)).encode();
+        assert!(ScaleField::<BlsFr>::decode_all(&mut &encoded[..encoded.len() - 1]).is_err());
+        encoded.push(0);
+        assert!(ScaleField::<BlsFr>::decode_all(&mut &encoded[..]).is_err());
+    }
+
+    #[test]
+    fn test_verify_demo_circuits() {
+        let rng = &mut ark_std::test_rng();
+
+        let (a, b) = (BlsFr::from(3u8), BlsFr::from(11u8));
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            MultiplyDemoCircuit::<BlsFr> { a: None, b: None },
+            rng,
+        )
+        .unwrap();
+        let circuit = MultiplyDemoCircuit {
+            a: Some(a),
+            b: Some(b),
+        };
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        let multiply_vk = ScaleVerifyingKey(vk).encode();
+        let multiply_proof = ScaleProof(proof).encode();
+
+        let c = ScaleField(a * b).encode();
+        assert!(verify_multiply::<Bls12_381>(&multiply_vk, &multiply_proof, &c).unwrap());
+        let wrong = ScaleField(a).encode();
+        assert!(!verify_multiply::<Bls12_381>(&multiply_vk, &multiply_proof, &wrong).unwrap());
+
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            CubicDemoCircuit::<BlsFr> { x: None },
+            rng,
+        )
+        .unwrap();
+        let circuit = CubicDemoCircuit {
+            x: Some(BlsFr::from(3u8)),
+        };
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        let cubic_vk = ScaleVerifyingKey(vk).encode();
+        let cubic_proof = ScaleProof(proof).encode();
+
+        let out = ScaleField(BlsFr::from(35u8)).encode();
+        assert!(verify_cubic::<Bls12_381>(&cubic_vk, &cubic_proof, &out).unwrap());
+        // keys are bound to their circuit
+        assert!(!verify_cubic::<Bls12_381>(&multiply_vk, &cubic_proof, &out).unwrap());
+
+        let two = vec![ScaleField(a), ScaleField(b)].encode();
+        assert!(matches!(
+            verify::<Bls12_381>(DemoCircuit::Multiply, &multiply_vk, &multiply_proof, &two),
+            Err(SubstrateError::WrongInputCount {
+                expected: 1,
+                found: 2,
+                ..
+            })
+        ));
+        // a proof is not a key
+        assert!(matches!(
+            verify_cubic::<Bls12_381>(&cubic_proof, &cubic_proof, &out),
+            Err(SubstrateError::Codec(_))
+        ));
+    }
+}
@@ -0,0 +1,312 @@
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystemRef, LinearCombination, SynthesisError, Variable,
+};
+use ark_std::cfg_iter;
+use ark_std::rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// random but always satisfiable R1CS of a requested size, for stress tests and benchmarks.
+// Unlike `padding::pad_to`, which only adds trivial constraints, every constraint is
+// <A, w> * <B, w> = c * v where A and B have `density` random terms each, v is a random
+// variable and c is picked so the constraint holds for the (also random) assignment.
+//
+// The seed fixes shape and assignment, so setup and proving see the same circuit and
+// `public_inputs` returns what the verifier needs.
+//
+// `precompute` takes the random choices first (one rng stream, so in order) and then
+// works out every c, an inversion each, on all cores with the `parallel`
+// feature. Synthesizing the `PrecomputedSynthetic` gives the same constraint system as
+// synthesizing the circuit itself.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyntheticCircuit {
+    pub num_constraints: usize,
+    // without the constant one
+    pub num_public_inputs: usize,
+    pub num_witness_variables: usize,
+    // non-zero terms in every A and B row
+    pub density: usize,
+    pub seed: u64,
+}
+
+impl SyntheticCircuit {
+    pub fn new(num_constraints: usize, num_witness_variables: usize, density: usize) -> Self {
+        Self {
+            num_constraints,
+            num_public_inputs: 1,
+            num_witness_variables,
+            density,
+            seed: 0,
+        }
+    }
+
+    // 2^log_size constraints over as many witnesses, 2 terms per A and B row
+    pub fn of_log_size(log_size: u32) -> Self {
+        Self::new(1 << log_size, 1 << log_size, 2)
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_public_inputs(mut self, num_public_inputs: usize) -> Self {
+        self.num_public_inputs = num_public_inputs;
+        self
+    }
+
+    fn rng(&self) -> ChaCha20Rng {
+        ChaCha20Rng::seed_from_u64(self.seed)
+    }
+
+    // the assignment: public inputs first, then the witnesses, as allocated
+    fn assignment<F: PrimeField>(&self, rng: &mut ChaCha20Rng) -> Vec<F> {
+        (0..self.num_public_inputs + self.num_witness_variables)
+            .map(|_| F::rand(rng))
+            .collect()
+    }
+
+    pub fn public_inputs<F: PrimeField>(&self) -> Vec<F> {
+        let mut assignment = self.assignment(&mut self.rng());
+        assignment.truncate(self.num_public_inputs);
+        assignment
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for SyntheticCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        if self.density == 0 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let mut rng = self.rng();
+        let assignment: Vec<F> = self.assignment(&mut rng);
+
+        // index 0 is the constant one
+        let mut variables = vec![Variable::One];
+        let mut values = vec![F::one()];
+        for (i, &value) in assignment.iter().enumerate() {
+            let variable = if i < self.num_public_inputs {
+                cs.new_input_variable(|| Ok(value))?
+            } else {
+                cs.new_witness_variable(|| Ok(value))?
+            };
+            variables.push(variable);
+            values.push(value);
+        }
+
+        let random_lc = |rng: &mut ChaCha20Rng| {
+            let mut lc = LinearCombination::zero();
+            let mut value = F::zero();
+            for _ in 0..self.density {
+                let i = rng.gen_range(0..variables.len());
+                let coeff = F::rand(rng);
+                lc += (coeff, variables[i]);
+                value += coeff * values[i];
+            }
+            (lc, value)
+        };
+        for _ in 0..self.num_constraints {
+            let (a, a_value) = random_lc(&mut rng);
+            let (b, b_value) = random_lc(&mut rng);
+            // c * v = a * b, fall back to the constant one if v happens to be zero
+            let mut i = rng.gen_range(0..variables.len());
+            if values[i].is_zero() {
+                i = 0;
+            }
+            let coeff = a_value * b_value * values[i].inverse().unwrap();
+            cs.enforce_constraint(a, b, LinearCombination::from((coeff, variables[i])))?;
+        }
+        Ok(())
+    }
+}
+
+// the random terms of one constraint, as (coefficient, variable index) with the
+// constant one at index 0
+struct Row<F> {
+    a: Vec<(F, usize)>,
+    b: Vec<(F, usize)>,
+    v: usize,
+}
+
+pub struct PrecomputedSynthetic<F: PrimeField> {
+    circuit: SyntheticCircuit,
+    // the constant one first
+    values: Vec<F>,
+    rows: Vec<Row<F>>,
+    coeffs: Vec<F>,
+}
+
+impl SyntheticCircuit {
+    pub fn precompute<F: PrimeField>(&self) -> PrecomputedSynthetic<F> {
+        let mut rng = self.rng();
+        let mut values = vec![F::one()];
+        values.extend(self.assignment::<F>(&mut rng));
+
+        // the same draws as `generate_constraints`, in the same order
+        let random_terms = |rng: &mut ChaCha20Rng| {
+            (0..self.density)
+                .map(|_| {
+                    let i = rng.gen_range(0..values.len());
+                    (F::rand(rng), i)
+                })
+                .collect::<Vec<_>>()
+        };
+        let rows: Vec<Row<F>> = (0..self.num_constraints)
+            .map(|_| {
+                let a = random_terms(&mut rng);
+                let b = random_terms(&mut rng);
+                let mut v = rng.gen_range(0..values.len());
+                if values[v].is_zero() {
+                    v = 0;
+                }
+                Row { a, b, v }
+            })
+            .collect();
+
+        let value =
+            |terms: &[(F, usize)]| -> F { terms.iter().map(|&(coeff, i)| coeff * values[i]).sum() };
+        let coeffs = cfg_iter!(rows)
+            .map(|row| value(&row.a) * value(&row.b) * values[row.v].inverse().unwrap())
+            .collect();
+        PrecomputedSynthetic {
+            circuit: *self,
+            values,
+            rows,
+            coeffs,
+        }
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for PrecomputedSynthetic<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        if self.circuit.density == 0 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let mut variables = vec![Variable::One];
+        for (i, &value) in self.values[1..].iter().enumerate() {
+            variables.push(if i < self.circuit.num_public_inputs {
+                cs.new_input_variable(|| Ok(value))?
+            } else {
+                cs.new_witness_variable(|| Ok(value))?
+            });
+        }
+        let lc = |terms: &[(F, usize)]| {
+            terms
+                .iter()
+                .fold(LinearCombination::zero(), |lc, &(coeff, i)| {
+                    lc + (coeff, variables[i])
+                })
+        };
+        for (row, &coeff) in self.rows.iter().zip(&self.coeffs) {
+            let c = LinearCombination::from((coeff, variables[row.v]));
+            cs.enforce_constraint(lc(&row.a), lc(&row.b), c)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "marlin")]
+    use crate::marlin_srs::{self, MarlinSrsSize};
+    use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    #[test]
+    fn test_shape_and_satisfiability() {
+        for &density in [1, 3, 8].iter() {
+            let circuit = SyntheticCircuit::new(100, 40, density)
+                .with_public_inputs(3)
+                .with_seed(density as u64);
+            let cs = ConstraintSystem::<BlsFr>::new_ref();
+            circuit.generate_constraints(cs.clone()).unwrap();
+
+            assert!(cs.is_satisfied().unwrap());
+            assert_eq!(cs.num_constraints(), 100);
+            assert_eq!(cs.num_instance_variables(), 4);
+            assert_eq!(cs.num_witness_variables(), 40);
+        }
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        assert!(SyntheticCircuit::new(1, 1, 0)
+            .generate_constraints(cs)
+            .is_err());
+    }
+
+    #[test]
+    fn test_precomputed_synthesis_is_the_same() {
+        let circuit = SyntheticCircuit::new(300, 120, 3)
+            .with_public_inputs(2)
+            .with_seed(5);
+        let synthesize = |circuit: &dyn Fn(ConstraintSystemRef<BlsFr>)| {
+            let cs = ConstraintSystem::<BlsFr>::new_ref();
+            circuit(cs.clone());
+            assert!(cs.is_satisfied().unwrap());
+            cs.finalize();
+            let matrices = cs.to_matrices().unwrap();
+            let cs = cs.into_inner().unwrap();
+            (
+                (matrices.a, matrices.b, matrices.c),
+                cs.instance_assignment,
+                cs.witness_assignment,
+            )
+        };
+        let inline = synthesize(&|cs| circuit.generate_constraints(cs).unwrap());
+        let precomputed = synthesize(&|cs| {
+            circuit
+                .precompute::<BlsFr>()
+                .generate_constraints(cs)
+                .unwrap()
+        });
+        assert!(inline == precomputed);
+    }
+
+    #[test]
+    #[cfg(feature = "marlin")]
+    fn test_groth16_and_marlin() {
+        let rng = &mut ark_std::test_rng();
+        let circuit = SyntheticCircuit::new(64, 32, 4).with_seed(7);
+        let inputs = circuit.public_inputs::<BlsFr>();
+
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(circuit, rng).unwrap();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        // a different seed is a different circuit
+        let other = circuit.with_seed(8);
+        assert_ne!(other.public_inputs::<BlsFr>(), inputs);
+
+        type MarlinInst = marlin_srs::MarlinInst<Bls12_381>;
+        let size = MarlinSrsSize::of::<BlsFr, _>(circuit).unwrap();
+        let srs = MarlinInst::universal_setup(
+            size.num_constraints,
+            size.num_variables,
+            size.num_non_zero,
+            rng,
+        )
+        .unwrap();
+        let (pk, vk) = MarlinInst::index(&srs, circuit).unwrap();
+        let proof = MarlinInst::prove(&pk, circuit, rng).unwrap();
+        assert!(MarlinInst::verify(&vk, &inputs, &proof, rng).unwrap());
+    }
+
+    // cargo test --release stress -- --ignored, SYNTHETIC_LOG_SIZE=20 for 2^20 constraints
+    #[test]
+    #[ignore]
+    fn test_groth16_stress() {
+        let log_size = std::env::var("SYNTHETIC_LOG_SIZE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(16);
+        let rng = &mut ark_std::test_rng();
+        let circuit = SyntheticCircuit::of_log_size(log_size);
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(circuit, rng).unwrap();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &circuit.public_inputs(), &proof).unwrap());
+    }
+}
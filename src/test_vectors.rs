@@ -0,0 +1,177 @@
+use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+use ark_groth16::{Groth16, Proof, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::batch::{format_field, parse_field};
+use crate::config::{CircuitKind, Curve};
+use crate::cubic_demo::{CubicDemoCircuit, CubicWitness};
+use crate::multiply_demo::{MultiplyDemoCircuit, MultiplyWitness};
+use crate::persist::{self, ArtifactKind, SerializationMode, Validation};
+use crate::prover;
+use crate::rng::RngConfig;
+use crate::Error;
+
+// known-answer vectors: for every circuit a fixed witness, the public input it implies and
+// a Groth16 vk + proof over BLS12-381, generated from a fixed seed. Stored vectors are
+// verified on every test run, and regenerating them must give the same bytes, so any
+// change in serialization or in the backend shows up as a failing test.
+//
+//   <dir>/<circuit>/vector.json   witness and public inputs (decimal)
+//   <dir>/<circuit>/vk.bin        persist envelope, compressed
+//   <dir>/<circuit>/proof.bin     persist envelope, compressed
+
+pub const VECTOR_SEED: u64 = 0x7e57;
+pub const CIRCUITS: [CircuitKind; 2] = [CircuitKind::Multiply, CircuitKind::Cubic];
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TestVector {
+    pub circuit: CircuitKind,
+    pub curve: Curve,
+    pub witness: BTreeMap<String, String>,
+    pub public_inputs: Vec<String>,
+}
+
+pub struct GeneratedVector {
+    pub vector: TestVector,
+    pub vk: VerifyingKey<Bls12_381>,
+    pub proof: Proof<Bls12_381>,
+}
+
+fn witness_of(circuit: CircuitKind) -> Vec<(&'static str, BlsFr)> {
+    match circuit {
+        CircuitKind::Multiply => vec![("a", BlsFr::from(3u8)), ("b", BlsFr::from(11u8))],
+        CircuitKind::Cubic => vec![("x", BlsFr::from(3u8))],
+    }
+}
+
+pub fn generate(circuit: CircuitKind) -> Result<GeneratedVector, Error> {
+    let rng_config = RngConfig::seeded(VECTOR_SEED);
+    let witness = witness_of(circuit);
+    let (vk, proof, public_input) = match circuit {
+        CircuitKind::Multiply => {
+            let witness = MultiplyWitness {
+                a: witness[0].1,
+                b: witness[1].1,
+            };
+            let (pk, vk) = prover::setup::<_, Groth16<Bls12_381>, _>(
+                MultiplyDemoCircuit::<BlsFr>::blank(),
+                &rng_config,
+            )?;
+            let circuit = MultiplyDemoCircuit::from(witness);
+            let proof = prover::prove::<_, Groth16<Bls12_381>, _>(&pk, circuit, &rng_config)?;
+            (vk, proof, witness.instance().c)
+        }
+        CircuitKind::Cubic => {
+            let witness = CubicWitness { x: witness[0].1 };
+            let (pk, vk) = prover::setup::<_, Groth16<Bls12_381>, _>(
+                CubicDemoCircuit::<BlsFr>::blank(),
+                &rng_config,
+            )?;
+            let circuit = CubicDemoCircuit::from(witness);
+            let proof = prover::prove::<_, Groth16<Bls12_381>, _>(&pk, circuit, &rng_config)?;
+            (vk, proof, witness.instance().out)
+        }
+    };
+
+    let vector = TestVector {
+        circuit,
+        curve: Curve::Bls12_381,
+        witness: witness
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), format_field(value)))
+            .collect(),
+        public_inputs: vec![format_field(public_input)],
+    };
+    Ok(GeneratedVector { vector, vk, proof })
+}
+
+pub fn write_vectors(dir: &Path) -> Result<(), Error> {
+    for &circuit in CIRCUITS.iter() {
+        let generated = generate(circuit)?;
+        let dir = dir.join(circuit.to_string());
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join("vector.json"),
+            serde_json::to_string_pretty(&generated.vector)? + "\n",
+        )?;
+        let mode = SerializationMode::Compressed;
+        persist::save(
+            dir.join("vk.bin"),
+            &generated.vk,
+            ArtifactKind::VerifyingKey,
+            mode,
+        )?;
+        persist::save(
+            dir.join("proof.bin"),
+            &generated.proof,
+            ArtifactKind::Proof,
+            mode,
+        )?;
+    }
+    Ok(())
+}
+
+// verifies one stored vector and checks that regenerating it gives identical artifacts
+pub fn check_vector(dir: &Path, circuit: CircuitKind) -> Result<(), Error> {
+    let dir = dir.join(circuit.to_string());
+    let vector: TestVector = serde_json::from_slice(&fs::read(dir.join("vector.json"))?)?;
+    let vk: VerifyingKey<Bls12_381> = persist::load(
+        dir.join("vk.bin"),
+        ArtifactKind::VerifyingKey,
+        Validation::Checked,
+    )?;
+    let proof: Proof<Bls12_381> = persist::load(
+        dir.join("proof.bin"),
+        ArtifactKind::Proof,
+        Validation::Checked,
+    )?;
+
+    let inputs = vector
+        .public_inputs
+        .iter()
+        .map(|v| parse_field("public input", v))
+        .collect::<Result<Vec<BlsFr>, _>>()?;
+    if !prover::verify::<_, Groth16<Bls12_381>>(&vk, &inputs, &proof)? {
+        return Err(format!("stored {} vector does not verify", circuit).into());
+    }
+
+    let generated = generate(circuit)?;
+    if generated.vector != vector {
+        return Err(format!("{} vector.json differs from a fresh generation", circuit).into());
+    }
+    if generated.vk != vk || generated.proof != proof {
+        return Err(format!("{} vk/proof differ from a fresh generation", circuit).into());
+    }
+    Ok(())
+}
+
+pub fn check_vectors(dir: &Path) -> Result<(), Error> {
+    for &circuit in CIRCUITS.iter() {
+        check_vector(dir, circuit)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_and_check_vectors() {
+        let dir = std::env::temp_dir().join(format!("gadgets_test_vectors_{}", std::process::id()));
+        write_vectors(&dir).unwrap();
+        check_vectors(&dir).unwrap();
+
+        // a tampered public input is caught
+        let path = dir.join("cubic").join("vector.json");
+        let mut vector: TestVector = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        vector.public_inputs[0] = "36".to_string();
+        fs::write(&path, serde_json::to_string(&vector).unwrap()).unwrap();
+        assert!(check_vector(&dir, CircuitKind::Cubic).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use std::fmt;
+use std::str::FromStr;
+
+// proofs, keys and field elements as text, for JSON bodies, environment variables and
+// command lines: the compressed `CanonicalSerialize` bytes in lowercase hex or in
+// base64 (RFC 4648, standard alphabet, padded). Decoding checks the points like
+// `Validation::Checked` and refuses bytes left over after the value. There is no
+// envelope here, the type comes from the caller; `persist` files carry their kind.
+//
+// A field element comes out little-endian, the arkworks order: 33 in the BLS12-381
+// scalar field is "2100...00". For the big-endian numbers other systems print, see
+// `encoding`.
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextFormat {
+    Hex,
+    Base64,
+}
+
+impl fmt::Display for TextFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextFormat::Hex => f.write_str("hex"),
+            TextFormat::Base64 => f.write_str("base64"),
+        }
+    }
+}
+
+impl FromStr for TextFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(TextFormat::Hex),
+            "base64" => Ok(TextFormat::Base64),
+            _ => Err(format!(
+                "unknown text format {:?}, expected one of: hex, base64",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TextError {
+    InvalidHex,
+    InvalidBase64,
+    Serialization(SerializationError),
+    // the value ended before the text did
+    TrailingBytes(usize),
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextError::InvalidHex => write!(f, "invalid hex"),
+            TextError::InvalidBase64 => write!(f, "invalid base64"),
+            TextError::Serialization(e) => write!(f, "serialization error: {}", e),
+            TextError::TrailingBytes(n) => write!(f, "{} bytes after the value", n),
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+impl From<SerializationError> for TextError {
+    fn from(e: SerializationError) -> Self {
+        TextError::Serialization(e)
+    }
+}
+
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// upper or lower case, an optional 0x prefix
+pub fn hex_decode(text: &str) -> Result<Vec<u8>, TextError> {
+    let text = text.trim();
+    let digits = text.strip_prefix("0x").unwrap_or(text).as_bytes();
+    if !digits.len().is_multiple_of(2) {
+        return Err(TextError::InvalidHex);
+    }
+    let digit = |c: u8| (c as char).to_digit(16).ok_or(TextError::InvalidHex);
+    digits
+        .chunks(2)
+        .map(|pair| Ok((digit(pair[0])? << 4 | digit(pair[1])?) as u8))
+        .collect()
+}
+
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => text.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char),
+                false => text.push('='),
+            }
+        }
+    }
+    text
+}
+
+// padding required, no line breaks
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, TextError> {
+    let text = text.trim().as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return Err(TextError::InvalidBase64);
+    }
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    for (k, chunk) in text.chunks(4).enumerate() {
+        let last = k == text.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return Err(TextError::InvalidBase64);
+        }
+        let mut n = 0u32;
+        for &c in &chunk[..4 - padding] {
+            let digit = BASE64
+                .iter()
+                .position(|&d| d == c)
+                .ok_or(TextError::InvalidBase64)?;
+            n = n << 6 | digit as u32;
+        }
+        n <<= 6 * padding;
+        // the bits below the last byte must be zero, so every text has one encoding
+        if n & ((1 << (8 * padding)) - 1) != 0 {
+            return Err(TextError::InvalidBase64);
+        }
+        bytes.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(bytes)
+}
+
+fn serialize<T: CanonicalSerialize>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(value.serialized_size());
+    value.serialize(&mut bytes).expect("writing to a vector");
+    bytes
+}
+
+fn deserialize<T: CanonicalDeserialize>(bytes: &[u8]) -> Result<T, TextError> {
+    let mut reader = bytes;
+    let value = T::deserialize(&mut reader)?;
+    match reader.len() {
+        0 => Ok(value),
+        n => Err(TextError::TrailingBytes(n)),
+    }
+}
+
+pub fn to_hex<T: CanonicalSerialize>(value: &T) -> String {
+    hex_encode(&serialize(value))
+}
+
+pub fn from_hex<T: CanonicalDeserialize>(text: &str) -> Result<T, TextError> {
+    deserialize(&hex_decode(text)?)
+}
+
+pub fn to_base64<T: CanonicalSerialize>(value: &T) -> String {
+    base64_encode(&serialize(value))
+}
+
+pub fn from_base64<T: CanonicalDeserialize>(text: &str) -> Result<T, TextError> {
+    deserialize(&base64_decode(text)?)
+}
+
+pub fn to_text<T: CanonicalSerialize>(value: &T, format: TextFormat) -> String {
+    match format {
+        TextFormat::Hex => to_hex(value),
+        TextFormat::Base64 => to_base64(value),
+    }
+}
+
+pub fn from_text<T: CanonicalDeserialize>(text: &str, format: TextFormat) -> Result<T, TextError> {
+    match format {
+        TextFormat::Hex => from_hex(text),
+        TextFormat::Base64 => from_base64(text),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fr, G1Affine};
+    use ark_ec::AffineCurve;
+    use ark_groth16::{Groth16, Proof};
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    use crate::multiply_demo::{MultiplyDemoCircuit, MultiplyWitness};
+
+    #[test]
+    fn test_rfc4648() {
+        // the test vectors of RFC 4648, section 10
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for &(bytes, text) in vectors.iter() {
+            assert_eq!(base64_encode(bytes.as_bytes()), text);
+            assert_eq!(base64_decode(text).unwrap(), bytes.as_bytes());
+        }
+        assert_eq!(hex_encode(b"foobar"), "666f6f626172");
+        assert_eq!(hex_decode("0x666F6F626172").unwrap(), b"foobar");
+
+        for text in ["Zg=", "Zg===", "Zg==Zg==", "Zh==", "Z!=="].iter() {
+            assert!(base64_decode(text).is_err(), "{}", text);
+        }
+        for text in ["abc", "0xzz", "+1", "é1"].iter() {
+            assert!(hex_decode(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_artifacts() {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(MultiplyDemoCircuit::blank(), rng)
+                .unwrap();
+        let witness = MultiplyWitness {
+            a: Fr::from(3u8),
+            b: Fr::from(11u8),
+        };
+        let proof =
+            Groth16::<Bls12_381>::prove(&pk, MultiplyDemoCircuit::from(witness), rng).unwrap();
+        for &format in [TextFormat::Hex, TextFormat::Base64].iter() {
+            let text = to_text(&proof, format);
+            assert_eq!(from_text::<Proof<Bls12_381>>(&text, format).unwrap(), proof);
+            assert_eq!(
+                from_text(&to_text(&vk, format), format).ok(),
+                Some(vk.clone())
+            );
+            assert_eq!(format.to_string().parse(), Ok(format));
+        }
+        // 48 + 96 + 48 compressed bytes
+        assert_eq!(to_hex(&proof).len(), 2 * 192);
+        assert_eq!(to_base64(&proof).len(), 256);
+
+        let x = Fr::rand(rng);
+        assert_eq!(from_base64::<Fr>(&to_base64(&x)).unwrap(), x);
+        assert_eq!(to_hex(&Fr::from(33u8)), format!("21{}", "0".repeat(62)));
+
+        // a value and more, a point off the curve
+        let long = to_hex(&x) + "00";
+        assert!(matches!(
+            from_hex::<Fr>(&long),
+            Err(TextError::TrailingBytes(1))
+        ));
+        let mut bytes = hex_decode(&to_hex(&G1Affine::prime_subgroup_generator())).unwrap();
+        bytes[0] ^= 1;
+        assert!(matches!(
+            from_hex::<G1Affine>(&hex_encode(&bytes)),
+            Err(TextError::Serialization(_))
+        ));
+    }
+}
@@ -0,0 +1,240 @@
+use ark_bls12_381::Fr;
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ed_on_bls12_381::{
+    constraints::EdwardsVar, EdwardsAffine, EdwardsProjective, Fr as JubjubScalar,
+};
+use ark_ff::{Field, One, Zero};
+use ark_poly::{univariate::DensePolynomial, Polynomial, UVPolynomial};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_std::rand::Rng;
+
+use crate::boolean_demo::to_bits;
+use crate::schnorr_gadget::{generator, generator_mul_var, secret_key_var};
+
+// Share correctness in threshold schemes. A dealer (or a DKG) shares a secret on Jubjub
+// with a random polynomial f of degree t - 1, f(0) the secret, participant i gets
+// s_i = f(i) and everyone gets the verification vector C_j = a_j * G of the
+// coefficients (Feldman). With a public base M, e.g. the hash of a message for a
+// threshold signature or the point of a threshold decryption, a participant publishes
+// the partial s_i * M, and any t partials combine to f(0) * M with Lagrange coefficients.
+//
+// The circuit shows a partial is right without revealing the share: with the bits of
+// s_i private it checks s_i * G == sum_j i^j * C_j, the polynomial evaluated "in the
+// exponent" (powers of the public index, each a scalar multiplication), and
+// s_i * M == partial. The index has `INDEX_BITS` bits, so its powers are small integers
+// and the same in both scalar fields.
+
+pub const INDEX_BITS: usize = 8;
+
+pub struct Dealing {
+    polynomial: DensePolynomial<JubjubScalar>,
+}
+
+impl Dealing {
+    pub fn new<R: Rng>(threshold: usize, rng: &mut R) -> Self {
+        Self {
+            polynomial: DensePolynomial::rand(threshold - 1, rng),
+        }
+    }
+
+    pub fn secret(&self) -> JubjubScalar {
+        self.polynomial.coeffs[0]
+    }
+
+    pub fn verification_vector(&self) -> Vec<EdwardsAffine> {
+        self.polynomial
+            .coeffs
+            .iter()
+            .map(|&a| generator().mul(a).into_affine())
+            .collect()
+    }
+
+    // participants are 1 to 2^INDEX_BITS - 1
+    pub fn share(&self, index: u64) -> JubjubScalar {
+        assert!(index > 0 && index < 1 << INDEX_BITS);
+        self.polynomial.evaluate(&JubjubScalar::from(index))
+    }
+}
+
+// sum_j index^j * C_j
+fn evaluate_in_exponent(vector: &[EdwardsAffine], index: u64) -> EdwardsProjective {
+    let x = JubjubScalar::from(index);
+    let mut power = JubjubScalar::one();
+    let mut sum = EdwardsProjective::zero();
+    for c in vector {
+        sum += &c.mul(power);
+        power *= x;
+    }
+    sum
+}
+
+pub fn verify_share(vector: &[EdwardsAffine], index: u64, share: JubjubScalar) -> bool {
+    generator().mul(share) == evaluate_in_exponent(vector, index)
+}
+
+pub fn partial(share: JubjubScalar, base: &EdwardsAffine) -> EdwardsAffine {
+    base.mul(share).into_affine()
+}
+
+// f(0) * M from t partials (index, s_i * M)
+pub fn combine(partials: &[(u64, EdwardsAffine)]) -> EdwardsAffine {
+    let mut sum = EdwardsProjective::zero();
+    for &(i, partial) in partials {
+        // the Lagrange coefficient of i at 0, prod_{j != i} j / (j - i)
+        let mut coefficient = JubjubScalar::one();
+        for &(j, _) in partials.iter().filter(|(j, _)| *j != i) {
+            let (i, j) = (JubjubScalar::from(i), JubjubScalar::from(j));
+            coefficient *= j * (j - i).inverse().expect("distinct indices");
+        }
+        sum += &partial.mul(coefficient);
+    }
+    sum.into_affine()
+}
+
+#[derive(Clone)]
+pub struct ShareCircuit {
+    pub vector: Vec<EdwardsAffine>,
+    pub index: u64,
+    pub base: EdwardsAffine,
+    pub partial: EdwardsAffine,
+    pub share: Option<JubjubScalar>,
+}
+
+impl ShareCircuit {
+    pub fn new(
+        vector: Vec<EdwardsAffine>,
+        index: u64,
+        base: EdwardsAffine,
+        share: JubjubScalar,
+    ) -> Self {
+        Self {
+            vector,
+            index,
+            base,
+            partial: partial(share, &base),
+            share: Some(share),
+        }
+    }
+
+    // everything but the share is public, the setup fixes the threshold
+    pub fn blank(threshold: usize) -> Self {
+        Self {
+            vector: vec![generator(); threshold],
+            index: 1,
+            base: generator(),
+            partial: generator(),
+            share: None,
+        }
+    }
+
+    // the verification vector, the index, the base and the partial
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs: Vec<Fr> = self.vector.iter().flat_map(|c| vec![c.x, c.y]).collect();
+        inputs.push(Fr::from(self.index));
+        inputs.extend_from_slice(&[self.base.x, self.base.y, self.partial.x, self.partial.y]);
+        inputs
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for ShareCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let vector = self
+            .vector
+            .iter()
+            .map(|&c| EdwardsVar::new_input(ns!(cs, "commitment"), || Ok(c)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let index = FpVar::new_input(ns!(cs, "index"), || Ok(Fr::from(self.index)))?;
+        let base = EdwardsVar::new_input(ns!(cs, "base"), || Ok(self.base))?;
+        let partial = EdwardsVar::new_input(ns!(cs, "partial"), || Ok(self.partial))?;
+        let share = secret_key_var(cs, self.share)?;
+
+        // sum_j index^j * C_j, index^j has j * INDEX_BITS bits
+        to_bits(&index, INDEX_BITS)?;
+        let mut expected = vector[0].clone();
+        let mut power = FpVar::one();
+        for (j, c) in vector.iter().enumerate().skip(1) {
+            power *= &index;
+            let bits = to_bits(&power, j * INDEX_BITS)?;
+            expected += c.scalar_mul_le(bits.iter())?;
+        }
+        generator_mul_var(&share)?.enforce_equal(&expected)?;
+        base.scalar_mul_le(share.iter())?.enforce_equal(&partial)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+    use ark_std::UniformRand;
+
+    fn is_satisfied(circuit: ShareCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_shares_combine() {
+        let rng = &mut ark_std::test_rng();
+        let dealing = Dealing::new(3, rng);
+        let vector = dealing.verification_vector();
+        let base = EdwardsProjective::rand(rng).into_affine();
+        for i in 1..6 {
+            assert!(verify_share(&vector, i, dealing.share(i)));
+            assert!(!verify_share(&vector, i + 1, dealing.share(i)));
+        }
+        let partials: Vec<_> = [2, 5, 4]
+            .iter()
+            .map(|&i| (i, partial(dealing.share(i), &base)))
+            .collect();
+        assert_eq!(combine(&partials), partial(dealing.secret(), &base));
+        // two are not enough
+        assert_ne!(combine(&partials[..2]), partial(dealing.secret(), &base));
+    }
+
+    #[test]
+    fn test_share_circuit() {
+        let rng = &mut ark_std::test_rng();
+        let dealing = Dealing::new(3, rng);
+        let vector = dealing.verification_vector();
+        let base = EdwardsProjective::rand(rng).into_affine();
+
+        let (pk, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(ShareCircuit::blank(3), rng).unwrap();
+        let circuit = ShareCircuit::new(vector.clone(), 7, base, dealing.share(7));
+        let inputs = circuit.public_inputs();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+        // claimed for another participant
+        let mut other_index = inputs.clone();
+        other_index[6] = Fr::from(8u8);
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &other_index, &proof).unwrap());
+
+        // the share of another participant, a wrong partial
+        assert!(!is_satisfied(ShareCircuit::new(
+            vector.clone(),
+            7,
+            base,
+            dealing.share(8)
+        )));
+        let mut wrong = ShareCircuit::new(vector.clone(), 7, base, dealing.share(7));
+        wrong.partial = partial(dealing.share(7) + JubjubScalar::one(), &base);
+        assert!(!is_satisfied(wrong));
+        // the largest index
+        let last = (1 << INDEX_BITS) - 1;
+        assert!(is_satisfied(ShareCircuit::new(
+            vector,
+            last,
+            base,
+            dealing.share(last)
+        )));
+    }
+}
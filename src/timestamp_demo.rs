@@ -0,0 +1,182 @@
+use ark_bls12_381::Fr;
+use ark_ed_on_bls12_381::{constraints::EdwardsVar, EdwardsAffine};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::poseidon::PoseidonParameters;
+
+use crate::date_gadget::{days_between_var, DAY_BITS};
+use crate::div_rem_demo::div_rem;
+use crate::schnorr_gadget::{generator, verify_var, Signature, SignatureVar};
+
+// An attestation with a validity window: an issuer signed a timestamp (seconds since
+// 1970-01-01 UTC, e.g. the time a ticket was bought) and the holder proves that the
+// timestamp falls within the public window without revealing it. The circuit checks the
+// Schnorr signature of the issuer (`schnorr_gadget`), takes the day of the timestamp
+// with `div_rem` and compares it with the first and last day of the window with the date
+// gadget.
+
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+pub fn day_of_timestamp(timestamp: u64) -> u32 {
+    (timestamp / SECONDS_PER_DAY) as u32
+}
+
+#[derive(Clone)]
+pub struct TimestampCircuit {
+    pub params: PoseidonParameters<Fr>,
+    pub issuer: EdwardsAffine,
+    // days since 1970-01-01, both included
+    pub valid_from: u32,
+    pub valid_until: u32,
+    pub timestamp: Option<u64>,
+    pub signature: Option<Signature>,
+}
+
+impl TimestampCircuit {
+    pub fn new(
+        params: PoseidonParameters<Fr>,
+        issuer: EdwardsAffine,
+        (valid_from, valid_until): (u32, u32),
+        timestamp: u64,
+        signature: Signature,
+    ) -> Self {
+        Self {
+            params,
+            issuer,
+            valid_from,
+            valid_until,
+            timestamp: Some(timestamp),
+            signature: Some(signature),
+        }
+    }
+
+    // the issuer and the window are public inputs, any values will do for the setup
+    pub fn blank(params: PoseidonParameters<Fr>) -> Self {
+        Self {
+            params,
+            issuer: generator(),
+            valid_from: 0,
+            valid_until: 0,
+            timestamp: None,
+            signature: None,
+        }
+    }
+
+    // the issuer key, then the window
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        vec![
+            self.issuer.x,
+            self.issuer.y,
+            Fr::from(self.valid_from),
+            Fr::from(self.valid_until),
+        ]
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for TimestampCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let issuer = EdwardsVar::new_input(ns!(cs, "issuer"), || Ok(self.issuer))?;
+        let valid_from = FpVar::new_input(ns!(cs, "valid_from"), || Ok(Fr::from(self.valid_from)))?;
+        let valid_until =
+            FpVar::new_input(ns!(cs, "valid_until"), || Ok(Fr::from(self.valid_until)))?;
+        let timestamp = FpVar::new_witness(ns!(cs, "timestamp"), || {
+            self.timestamp
+                .map(Fr::from)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let signature = SignatureVar::new_witness(ns!(cs, "signature"), || {
+            self.signature.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        verify_var(
+            cs.clone(),
+            &self.params,
+            &issuer,
+            ark_std::slice::from_ref(&timestamp),
+            &signature,
+        )?
+        .enforce_equal(&Boolean::TRUE)?;
+
+        let seconds_per_day = FpVar::constant(Fr::from(SECONDS_PER_DAY));
+        let (day, _) = div_rem(cs, &timestamp, &seconds_per_day, DAY_BITS)?;
+        // valid_from <= day <= valid_until, only the range checks matter
+        let _ = days_between_var(&valid_from, &day)?;
+        let _ = days_between_var(&day, &valid_until)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::date_gadget::first_day_of_year;
+    use crate::poseidon_params::poseidon_parameters;
+    use crate::schnorr_gadget::{keygen, sign};
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    fn is_satisfied(circuit: TimestampCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_timestamp_in_window() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (sk, issuer) = keygen(rng);
+        // 2024-03-01 12:00:00 UTC, in a window over the whole of 2024
+        let timestamp = 1_709_294_400;
+        let window = (first_day_of_year(2024), first_day_of_year(2025) - 1);
+        let signature = sign(&params, sk, &[Fr::from(timestamp)], rng);
+
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            TimestampCircuit::blank(params.clone()),
+            rng,
+        )
+        .unwrap();
+        let circuit = TimestampCircuit::new(params.clone(), issuer, window, timestamp, signature);
+        let inputs = circuit.public_inputs();
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+        // the window of 2023
+        let mut other_window = inputs.clone();
+        other_window[2] = Fr::from(first_day_of_year(2023));
+        other_window[3] = Fr::from(first_day_of_year(2024) - 1);
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &other_window, &proof).unwrap());
+    }
+
+    #[test]
+    fn test_window_bounds() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (sk, issuer) = keygen(rng);
+        let timestamp = 1_709_294_400;
+        let day = day_of_timestamp(timestamp);
+        let signature = sign(&params, sk, &[Fr::from(timestamp)], rng);
+        let circuit =
+            |window| TimestampCircuit::new(params.clone(), issuer, window, timestamp, signature);
+
+        // both ends are included
+        assert!(is_satisfied(circuit((day, day))));
+        assert!(!is_satisfied(circuit((day + 1, day + 10))));
+        assert!(!is_satisfied(circuit((day - 10, day - 1))));
+
+        // a timestamp the issuer did not sign
+        let mut forged = circuit((day, day));
+        forged.timestamp = Some(timestamp + 1);
+        assert!(!is_satisfied(forged));
+        // a signature of another issuer
+        let (other_sk, _) = keygen(rng);
+        let mut forged = circuit((day, day));
+        forged.signature = Some(sign(&params, other_sk, &[Fr::from(timestamp)], rng));
+        assert!(!is_satisfied(forged));
+    }
+}
@@ -0,0 +1,158 @@
+use ark_ec::{msm::VariableBaseMSM, PairingEngine, ProjectiveCurve};
+use ark_ff::{Field, PrimeField, UniformRand};
+use ark_groth16::{generator::generate_parameters, Proof, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::{ConstraintSynthesizer, SynthesisError};
+use ark_std::rand::{CryptoRng, RngCore};
+
+// INSECURE, for teaching only: what the "toxic waste" of a Groth16 setup is worth. The
+// setup samples alpha, beta, gamma, delta (and tau) and publishes them only in the
+// exponent; whoever keeps them can make a proof for any public inputs without a witness,
+// true statements and false ones alike, and the proof verifies like any other. Nothing
+// in the proof or the keys gives a forgery away.
+//
+// The forgery picks A = a G and B = b H at random and solves the verification equation
+//   e(A, B) == e(alpha G, beta H) * e(sum x_i [gamma_abc_i], gamma H) * e(C, delta H)
+// for C, which takes alpha, beta, gamma, delta and the generators but not tau. A real
+// ceremony runs the setup as a chain of contributions so that the waste exists only if
+// every participant keeps theirs; `generate_random_parameters` drops it on return.
+
+pub struct ToxicWaste<E: PairingEngine> {
+    pub alpha: E::Fr,
+    pub beta: E::Fr,
+    pub gamma: E::Fr,
+    pub delta: E::Fr,
+    pub g1: E::G1Projective,
+    pub g2: E::G2Projective,
+}
+
+impl<E: PairingEngine> ToxicWaste<E> {
+    pub fn sample<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self {
+            alpha: E::Fr::rand(rng),
+            beta: E::Fr::rand(rng),
+            gamma: E::Fr::rand(rng),
+            delta: E::Fr::rand(rng),
+            g1: E::G1Projective::rand(rng),
+            g2: E::G2Projective::rand(rng),
+        }
+    }
+}
+
+// the setup of `Groth16::circuit_specific_setup`, keeping the trapdoor
+pub fn setup_keeping_waste<E, C, R>(
+    circuit: C,
+    rng: &mut R,
+) -> Result<(ProvingKey<E>, ToxicWaste<E>), SynthesisError>
+where
+    E: PairingEngine,
+    C: ConstraintSynthesizer<E::Fr>,
+    R: RngCore + CryptoRng,
+{
+    let waste = ToxicWaste::sample(rng);
+    let pk = generate_parameters(
+        circuit,
+        waste.alpha,
+        waste.beta,
+        waste.gamma,
+        waste.delta,
+        waste.g1,
+        waste.g2,
+        rng,
+    )?;
+    Ok((pk, waste))
+}
+
+// a proof for any public inputs, the vk must be from the setup that left `waste`
+pub fn forge<E: PairingEngine, R: RngCore + CryptoRng>(
+    vk: &VerifyingKey<E>,
+    waste: &ToxicWaste<E>,
+    public_inputs: &[E::Fr],
+    rng: &mut R,
+) -> Result<Proof<E>, SynthesisError> {
+    if public_inputs.len() + 1 != vk.gamma_abc_g1.len() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+    let scalars: Vec<_> = ark_std::iter::once(E::Fr::from(1u8))
+        .chain(public_inputs.iter().copied())
+        .map(|x| x.into_repr())
+        .collect();
+    let inputs = VariableBaseMSM::multi_scalar_mul(&vk.gamma_abc_g1, &scalars);
+
+    let (a, b) = (E::Fr::rand(rng), E::Fr::rand(rng));
+    let delta_inverse = waste
+        .delta
+        .inverse()
+        .ok_or(SynthesisError::UnexpectedIdentity)?;
+    // delta C = (a b - alpha beta) G - gamma inputs
+    let c = (waste.g1.mul((a * b - waste.alpha * waste.beta).into_repr())
+        - inputs.mul(waste.gamma.into_repr()))
+    .mul(delta_inverse.into_repr());
+    Ok(Proof {
+        a: waste.g1.mul(a.into_repr()).into_affine(),
+        b: waste.g2.mul(b.into_repr()).into_affine(),
+        c: c.into_affine(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lagrange_demo::{self, LagrangeEvalCircuit};
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_ff::One;
+    use ark_groth16::Groth16;
+    use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+    use ark_snark::SNARK;
+
+    #[test]
+    fn test_forged_proof_of_a_false_statement() {
+        let rng = &mut ark_std::test_rng();
+        let domain = Radix2EvaluationDomain::<Fr>::new(4).unwrap();
+        let (pk, waste) =
+            setup_keeping_waste::<Bls12_381, _, _>(LagrangeEvalCircuit::blank(domain), rng)
+                .unwrap();
+        let vk = pk.vk.clone();
+
+        // p(z) is one value, claiming another is false and has no witness
+        let evals: Vec<Fr> = (0..4).map(|_| Fr::rand(rng)).collect();
+        let z = Fr::rand(rng);
+        let wrong = lagrange_demo::evaluate(&domain, &evals, z) + Fr::one();
+        let inputs = LagrangeEvalCircuit::public_inputs(&evals, z, wrong);
+        let forged = forge(&vk, &waste, &inputs, rng).unwrap();
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &forged).unwrap());
+
+        // honest proofs from the same keys still verify
+        let right = wrong - Fr::one();
+        let circuit = LagrangeEvalCircuit::new(domain, evals.clone(), z, right);
+        let honest = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        let inputs = LagrangeEvalCircuit::public_inputs(&evals, z, right);
+        assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &honest).unwrap());
+
+        // a forged proof is still bound to its inputs
+        let other = LagrangeEvalCircuit::public_inputs(&evals, z, wrong + Fr::one());
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &other, &forged).unwrap());
+        assert!(forge(&vk, &waste, &other[1..], rng).is_err());
+    }
+
+    #[test]
+    fn test_waste_of_another_setup_is_useless() {
+        let rng = &mut ark_std::test_rng();
+        let domain = Radix2EvaluationDomain::<Fr>::new(4).unwrap();
+        let (pk, _) =
+            setup_keeping_waste::<Bls12_381, _, _>(LagrangeEvalCircuit::blank(domain), rng)
+                .unwrap();
+        let (_, waste) =
+            setup_keeping_waste::<Bls12_381, _, _>(LagrangeEvalCircuit::blank(domain), rng)
+                .unwrap();
+        let inputs: Vec<Fr> = (0..6).map(|_| Fr::rand(rng)).collect();
+        let forged = forge(&pk.vk, &waste, &inputs, rng).unwrap();
+        assert!(!Groth16::<Bls12_381>::verify(&pk.vk, &inputs, &forged).unwrap());
+
+        // nor does an honest setup leave any behind
+        let (_, vk) =
+            Groth16::<Bls12_381>::circuit_specific_setup(LagrangeEvalCircuit::blank(domain), rng)
+                .unwrap();
+        let forged = forge(&vk, &waste, &inputs, rng).unwrap();
+        assert!(!Groth16::<Bls12_381>::verify(&vk, &inputs, &forged).unwrap());
+    }
+}
@@ -0,0 +1,313 @@
+use ark_ec::short_weierstrass_jacobian::GroupAffine;
+use ark_ec::{PairingEngine, SWModelParameters};
+use ark_groth16::{PreparedVerifyingKey, Proof, VerifyingKey};
+use ark_relations::r1cs::SynthesisError;
+use ark_std::fmt;
+
+use crate::persist::{read_artifact, ArtifactKind, PersistError, Validation};
+
+// the verifier side of Groth16 on its own: verifying keys, proofs and the pairing check,
+// nothing that touches proving keys, setup or witnesses. Together with `persist` this
+// is all that is built with `verifier-only`, which works without std, e.g. for light
+// clients:
+//
+//   cargo build --no-default-features --features verifier-only --target thumbv7em-none-eabi
+
+#[derive(Debug)]
+pub enum VerifierError {
+    Persist(PersistError),
+    // e.g. the number of public inputs does not match the verifying key
+    Synthesis(SynthesisError),
+    InvalidPoint(InvalidPoint),
+}
+
+impl fmt::Display for VerifierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifierError::Persist(e) => write!(f, "cannot read artifact: {}", e),
+            VerifierError::Synthesis(e) => write!(f, "cannot verify: {}", e),
+            VerifierError::InvalidPoint(e) => write!(f, "invalid point: {}", e),
+        }
+    }
+}
+
+impl ark_std::error::Error for VerifierError {}
+
+impl From<PersistError> for VerifierError {
+    fn from(e: PersistError) -> Self {
+        VerifierError::Persist(e)
+    }
+}
+
+impl From<SynthesisError> for VerifierError {
+    fn from(e: SynthesisError) -> Self {
+        VerifierError::Synthesis(e)
+    }
+}
+
+impl From<InvalidPoint> for VerifierError {
+    fn from(e: InvalidPoint) -> Self {
+        VerifierError::InvalidPoint(e)
+    }
+}
+
+// verifying keys and proofs in the `persist` envelope. The bytes come from the outside,
+// so every point is checked.
+pub fn read_verifying_key<E: PairingEngine>(bytes: &[u8]) -> Result<VerifyingKey<E>, PersistError> {
+    read_artifact(bytes, ArtifactKind::VerifyingKey, Validation::Checked)
+}
+
+pub fn read_proof<E: PairingEngine>(bytes: &[u8]) -> Result<Proof<E>, PersistError> {
+    read_artifact(bytes, ArtifactKind::Proof, Validation::Checked)
+}
+
+// prepare once when verifying many proofs against the same key
+pub fn prepare_verifying_key<E: PairingEngine>(vk: &VerifyingKey<E>) -> PreparedVerifyingKey<E> {
+    ark_groth16::prepare_verifying_key(vk)
+}
+
+pub fn verify_proof<E: PairingEngine>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::Fr],
+) -> Result<bool, VerifierError> {
+    Ok(ark_groth16::verify_proof(pvk, proof, public_inputs)?)
+}
+
+pub fn verify_bytes<E: PairingEngine>(
+    vk: &[u8],
+    proof: &[u8],
+    public_inputs: &[E::Fr],
+) -> Result<bool, VerifierError> {
+    let pvk = prepare_verifying_key(&read_verifying_key::<E>(vk)?);
+    verify_proof(&pvk, &read_proof(proof)?, public_inputs)
+}
+
+// Points from the network must be on the curve and in the subgroup of prime order r
+// before they go into a pairing: a point off the curve or of small order can make the
+// pairing check hold or leak where it should not. `read_proof`/`read_verifying_key`
+// (`Validation::Checked`) already refuse such points, a proof or key that arrived any
+// other way (`deserialize_unchecked`, a `Validation::Unchecked` load, an interop format,
+// built in code) goes through these before `verify_proof`:
+//
+//   validate_vk(&vk)?;
+//   validate_proof(&proof)?;
+//   verify_proof(&prepare_verifying_key(&vk), &proof, &inputs)?
+//
+// ark-serialize 0.3 checks the subgroup but not the curve equation when it reads
+// uncompressed points, and checks neither with `deserialize_unchecked`; compressed
+// points are recomputed from x and so always on the curve.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointError {
+    NotOnCurve,
+    NotInSubgroup,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidPoint {
+    // the field of the proof or key, with the index into gamma_abc_g1
+    pub element: &'static str,
+    pub index: Option<usize>,
+    pub error: PointError,
+}
+
+impl fmt::Display for InvalidPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.index {
+            Some(i) => write!(f, "{}[{}]", self.element, i)?,
+            None => write!(f, "{}", self.element)?,
+        }
+        match self.error {
+            PointError::NotOnCurve => write!(f, " is not on the curve"),
+            PointError::NotInSubgroup => write!(f, " is not in the prime order subgroup"),
+        }
+    }
+}
+
+impl ark_std::error::Error for InvalidPoint {}
+
+// the checks of a curve point, for the short Weierstrass curves of every pairing here
+// (BLS12, BN, MNT)
+pub trait CurvePoint {
+    fn check(&self) -> Result<(), PointError>;
+}
+
+impl<P: SWModelParameters> CurvePoint for GroupAffine<P> {
+    fn check(&self) -> Result<(), PointError> {
+        // the subgroup check multiplies by r and means nothing off the curve
+        if !self.is_on_curve() {
+            return Err(PointError::NotOnCurve);
+        }
+        if !self.is_in_correct_subgroup_assuming_on_curve() {
+            return Err(PointError::NotInSubgroup);
+        }
+        Ok(())
+    }
+}
+
+fn check_point<G: CurvePoint>(
+    element: &'static str,
+    index: Option<usize>,
+    point: &G,
+) -> Result<(), InvalidPoint> {
+    point.check().map_err(|error| InvalidPoint {
+        element,
+        index,
+        error,
+    })
+}
+
+pub fn validate_proof<E: PairingEngine>(proof: &Proof<E>) -> Result<(), InvalidPoint>
+where
+    E::G1Affine: CurvePoint,
+    E::G2Affine: CurvePoint,
+{
+    check_point("a", None, &proof.a)?;
+    check_point("b", None, &proof.b)?;
+    check_point("c", None, &proof.c)
+}
+
+pub fn validate_vk<E: PairingEngine>(vk: &VerifyingKey<E>) -> Result<(), InvalidPoint>
+where
+    E::G1Affine: CurvePoint,
+    E::G2Affine: CurvePoint,
+{
+    check_point("alpha_g1", None, &vk.alpha_g1)?;
+    check_point("beta_g2", None, &vk.beta_g2)?;
+    check_point("gamma_g2", None, &vk.gamma_g2)?;
+    check_point("delta_g2", None, &vk.delta_g2)?;
+    for (i, point) in vk.gamma_abc_g1.iter().enumerate() {
+        check_point("gamma_abc_g1", Some(i), point)?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use crate::persist::{write_artifact, SerializationMode};
+    use ark_bls12_381::{Bls12_381, Fr as BlsFr, G1Affine};
+    use ark_ff::Field;
+    use ark_groth16::Groth16;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_snark::SNARK;
+
+    #[test]
+    fn test_verify_bytes() {
+        let rng = &mut ark_std::test_rng();
+        let (a, b) = (BlsFr::from(3u8), BlsFr::from(11u8));
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            MultiplyDemoCircuit::<BlsFr> { a: None, b: None },
+            rng,
+        )
+        .unwrap();
+        let circuit = MultiplyDemoCircuit {
+            a: Some(a),
+            b: Some(b),
+        };
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+
+        for &mode in [
+            SerializationMode::Compressed,
+            SerializationMode::Uncompressed,
+        ]
+        .iter()
+        {
+            let (mut vk_bytes, mut proof_bytes) = (Vec::new(), Vec::new());
+            write_artifact(&vk, ArtifactKind::VerifyingKey, mode, &mut vk_bytes).unwrap();
+            write_artifact(&proof, ArtifactKind::Proof, mode, &mut proof_bytes).unwrap();
+
+            assert!(verify_bytes::<Bls12_381>(&vk_bytes, &proof_bytes, &[a * b]).unwrap());
+            assert!(!verify_bytes::<Bls12_381>(&vk_bytes, &proof_bytes, &[a]).unwrap());
+            assert!(matches!(
+                verify_bytes::<Bls12_381>(&vk_bytes, &proof_bytes, &[a, b]),
+                Err(VerifierError::Synthesis(_))
+            ));
+            // a proof is not a key
+            assert!(matches!(
+                verify_bytes::<Bls12_381>(&proof_bytes, &proof_bytes, &[a * b]),
+                Err(VerifierError::Persist(PersistError::WrongKind { .. }))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+            MultiplyDemoCircuit::<BlsFr> { a: None, b: None },
+            rng,
+        )
+        .unwrap();
+        let circuit = MultiplyDemoCircuit {
+            a: Some(BlsFr::from(3u8)),
+            b: Some(BlsFr::from(11u8)),
+        };
+        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, rng).unwrap();
+        assert_eq!(validate_proof(&proof), Ok(()));
+        assert_eq!(validate_vk(&vk), Ok(()));
+
+        // on the curve, but the cofactor of G1 is large and almost every x gives a point
+        // outside the subgroup
+        let small_order = (1u64..)
+            .filter_map(|x| G1Affine::get_point_from_x(x.into(), true))
+            .find(|p| !p.is_in_correct_subgroup_assuming_on_curve())
+            .unwrap();
+        // the x of a point with another y
+        let mut off_curve = vk.alpha_g1;
+        off_curve.y.double_in_place();
+
+        for &(point, error) in [
+            (small_order, PointError::NotInSubgroup),
+            (off_curve, PointError::NotOnCurve),
+        ]
+        .iter()
+        {
+            let mut bad = proof.clone();
+            bad.c = point;
+            let mut bytes = Vec::new();
+            bad.serialize_uncompressed(&mut bytes).unwrap();
+
+            // refused by the checked read, taken as is by the unchecked one
+            assert!(Proof::<Bls12_381>::deserialize_uncompressed(&bytes[..]).is_err());
+            let read = Proof::<Bls12_381>::deserialize_unchecked(&bytes[..]).unwrap();
+            assert_eq!(read, bad);
+            assert_eq!(
+                validate_proof(&read),
+                Err(InvalidPoint {
+                    element: "c",
+                    index: None,
+                    error
+                })
+            );
+
+            let mut bad = vk.clone();
+            bad.gamma_abc_g1[1] = point;
+            let mut bytes = Vec::new();
+            bad.serialize_uncompressed(&mut bytes).unwrap();
+            assert!(VerifyingKey::<Bls12_381>::deserialize_uncompressed(&bytes[..]).is_err());
+            let read = VerifyingKey::<Bls12_381>::deserialize_unchecked(&bytes[..]).unwrap();
+            let e = validate_vk(&read).unwrap_err();
+            assert_eq!(
+                (e.element, e.index, e.error),
+                ("gamma_abc_g1", Some(1), error)
+            );
+        }
+        assert_eq!(
+            InvalidPoint {
+                element: "gamma_abc_g1",
+                index: Some(1),
+                error: PointError::NotInSubgroup
+            }
+            .to_string(),
+            "gamma_abc_g1[1] is not in the prime order subgroup"
+        );
+
+        // a compressed point is recomputed from x, only the subgroup can be wrong
+        let mut bytes = Vec::new();
+        small_order.serialize(&mut bytes).unwrap();
+        assert!(G1Affine::deserialize(&bytes[..]).is_err());
+    }
+}
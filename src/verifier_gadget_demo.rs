@@ -0,0 +1,206 @@
+use ark_crypto_primitives::snark::{FromFieldElementsGadget, SNARKGadget};
+use ark_groth16::{Proof, VerifyingKey};
+use ark_mnt4_298::MNT4_298;
+use ark_r1cs_std::prelude::*;
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError},
+};
+use ark_std::Zero;
+use std::fmt;
+
+use crate::cycles::{self, Mnt4Mnt6};
+use crate::ivc_demo::placeholder_proof;
+
+// ark-groth16's verifier gadget (the `r1cs` feature) on its own: an outer circuit over
+// the scalar field of MNT6-298, the base field of MNT4-298, that checks one Groth16
+// proof on MNT4-298, through the `SNARKGadget` interface of ark-crypto-primitives:
+//   new_verification_key_unchecked  the inner verifying key, a constant here: the outer
+//                                   keys are for this one inner circuit
+//   new_proof_unchecked             the inner proof, a witness
+//   InputVar::new_input             the inner public inputs as outer public inputs. They
+//                                   are bits of MNT4 scalars repacked into MNT6 scalars,
+//                                   `InputVar::repack_input` gives the outer values
+//   verify                          a Boolean, enforced true
+// `aggregation_demo` and `ivc_demo` build on the same calls. `verifier_cost` counts the
+// constraints of each step. The types are those of the `cycles::Mnt4Mnt6` chain.
+
+pub type InnerFr = cycles::InnerFr<Mnt4Mnt6>;
+pub type OuterFr = cycles::OuterFr<Mnt4Mnt6>;
+pub type VerifierGadget = cycles::InnerVerifierGadget<Mnt4Mnt6>;
+type InputVar = cycles::InnerInputVar<Mnt4Mnt6>;
+
+#[derive(Clone)]
+pub struct VerifierCircuit {
+    pub vk: VerifyingKey<MNT4_298>,
+    pub inputs: Vec<InnerFr>,
+    pub proof: Proof<MNT4_298>,
+}
+
+impl VerifierCircuit {
+    pub fn new(vk: VerifyingKey<MNT4_298>, inputs: Vec<InnerFr>, proof: Proof<MNT4_298>) -> Self {
+        Self { vk, inputs, proof }
+    }
+
+    // the circuit of a key, for the setup
+    pub fn blank(vk: VerifyingKey<MNT4_298>) -> Self {
+        Self {
+            inputs: vec![InnerFr::zero(); vk.gamma_abc_g1.len() - 1],
+            vk,
+            proof: placeholder_proof(),
+        }
+    }
+
+    // the public inputs of the outer proof
+    pub fn public_inputs(&self) -> Vec<OuterFr> {
+        InputVar::repack_input(&self.inputs)
+    }
+
+    fn synthesize(self, cs: ConstraintSystemRef<OuterFr>) -> Result<VerifierCost, SynthesisError> {
+        let mut counted = 0;
+        let mut count = || {
+            let step = cs.num_constraints() - counted;
+            counted = cs.num_constraints();
+            step
+        };
+
+        let inputs = InputVar::new_input(ns!(cs, "inputs"), || Ok(&self.inputs))?;
+        let inputs_cost = count();
+        let vk = VerifierGadget::new_verification_key_unchecked(
+            ns!(cs, "vk"),
+            || Ok(&self.vk),
+            AllocationMode::Constant,
+        )?;
+        let vk_cost = count();
+        let proof = VerifierGadget::new_proof_unchecked(
+            ns!(cs, "proof"),
+            || Ok(&self.proof),
+            AllocationMode::Witness,
+        )?;
+        let proof_cost = count();
+        VerifierGadget::verify(&vk, &inputs, &proof)?.enforce_equal(&Boolean::TRUE)?;
+        Ok(VerifierCost {
+            inputs: inputs_cost,
+            vk: vk_cost,
+            proof: proof_cost,
+            verify: count(),
+            num_outer_inputs: cs.num_instance_variables() - 1,
+        })
+    }
+}
+
+impl ConstraintSynthesizer<OuterFr> for VerifierCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<OuterFr>) -> Result<(), SynthesisError> {
+        self.synthesize(cs).map(|_| ())
+    }
+}
+
+// constraints of each step of the outer circuit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifierCost {
+    pub inputs: usize,
+    pub vk: usize,
+    pub proof: usize,
+    pub verify: usize,
+    pub num_outer_inputs: usize,
+}
+
+impl VerifierCost {
+    pub fn total(&self) -> usize {
+        self.inputs + self.vk + self.proof + self.verify
+    }
+}
+
+impl fmt::Display for VerifierCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "inputs {:>8} ({} outer public inputs)",
+            self.inputs, self.num_outer_inputs
+        )?;
+        writeln!(f, "vk     {:>8}", self.vk)?;
+        writeln!(f, "proof  {:>8}", self.proof)?;
+        writeln!(f, "verify {:>8}", self.verify)?;
+        writeln!(f, "total  {:>8}", self.total())
+    }
+}
+
+// synthesized in setup mode, no proof needed
+pub fn verifier_cost(vk: &VerifyingKey<MNT4_298>) -> Result<VerifierCost, SynthesisError> {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_mode(ark_relations::r1cs::SynthesisMode::Setup);
+    VerifierCircuit::blank(vk.clone()).synthesize(cs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::{CubicDemoCircuit, CubicWitness};
+    use ark_groth16::{Groth16, ProvingKey};
+    use ark_mnt6_298::MNT6_298;
+    use ark_snark::SNARK;
+
+    fn is_satisfied(circuit: VerifierCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    fn inner(x: u8) -> (ProvingKey<MNT4_298>, VerifierCircuit) {
+        let rng = &mut ark_std::test_rng();
+        let (pk, vk) =
+            Groth16::<MNT4_298>::circuit_specific_setup(CubicDemoCircuit::blank(), rng).unwrap();
+        let witness = CubicWitness {
+            x: InnerFr::from(x),
+        };
+        let proof = Groth16::<MNT4_298>::prove(&pk, CubicDemoCircuit::from(witness), rng).unwrap();
+        let circuit = VerifierCircuit::new(vk, witness.instance().public_inputs(), proof);
+        (pk, circuit)
+    }
+
+    #[test]
+    fn test_verifier_gadget() {
+        let (pk, circuit) = inner(3);
+        // 35 in a single outer scalar, its bits reversed by the repacking
+        assert_eq!(circuit.public_inputs().len(), 1);
+        assert!(is_satisfied(circuit.clone()));
+
+        let mut wrong = circuit.clone();
+        wrong.inputs[0] = InnerFr::from(36u8);
+        assert!(!is_satisfied(wrong));
+        let mut wrong = circuit.clone();
+        let other = CubicWitness {
+            x: InnerFr::from(4u8),
+        };
+        let rng = &mut ark_std::test_rng();
+        wrong.proof = Groth16::<MNT4_298>::prove(&pk, CubicDemoCircuit::from(other), rng).unwrap();
+        assert!(!is_satisfied(wrong));
+
+        let cost = verifier_cost(&circuit.vk).unwrap();
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert_eq!(cost.total(), cs.num_constraints());
+        // a constant key is free, the pairings are most of it
+        assert_eq!(cost.vk, 0);
+        assert!(cost.verify > 10 * (cost.inputs + cost.proof));
+        assert_eq!(cost.num_outer_inputs, 1);
+        assert!(cost.to_string().contains("verify"));
+    }
+
+    // cargo test --release verifier_gadget -- --ignored, the outer proof on MNT6
+    #[test]
+    #[ignore]
+    fn test_verifier_gadget_proof() {
+        let rng = &mut ark_std::test_rng();
+        let (_, circuit) = inner(3);
+        let (pk, vk) = Groth16::<MNT6_298>::circuit_specific_setup(
+            VerifierCircuit::blank(circuit.vk.clone()),
+            rng,
+        )
+        .unwrap();
+        let inputs = circuit.public_inputs();
+        let proof = Groth16::<MNT6_298>::prove(&pk, circuit, rng).unwrap();
+        assert!(Groth16::<MNT6_298>::verify(&vk, &inputs, &proof).unwrap());
+        assert!(!Groth16::<MNT6_298>::verify(&vk, &[OuterFr::from(35u8)], &proof).unwrap());
+    }
+}
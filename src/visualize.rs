@@ -0,0 +1,214 @@
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{
+    ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, SynthesisError, SynthesisMode,
+};
+use num_bigint::BigUint;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+// Graphviz (DOT) export of a constraint system. Every variable is a node and every
+// constraint <A, w> * <B, w> = <C, w> is a box acting as a hyperedge: the variables of A
+// and B point into the box, the box points to the variables of C.
+//
+//   dot -Tsvg cubic.dot -o cubic.svg
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DotOptions {
+    // larger circuits are sampled: this many constraints, evenly spread over the circuit,
+    // together with the variables they touch
+    pub max_constraints: usize,
+    // label the edges with the matrix coefficients
+    pub show_coefficients: bool,
+}
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        Self {
+            max_constraints: 64,
+            show_coefficients: true,
+        }
+    }
+}
+
+// one = the constant 1, x_i = public inputs, w_i = witnesses, in allocation order
+pub fn variable_name(num_instance_variables: usize, index: usize) -> String {
+    if index == 0 {
+        "one".to_string()
+    } else if index < num_instance_variables {
+        format!("x{}", index)
+    } else {
+        format!("w{}", index - num_instance_variables)
+    }
+}
+
+// coefficients close to the modulus are shown as small negative numbers
+pub fn format_coefficient<F: PrimeField>(coeff: F) -> String {
+    let value: BigUint = coeff.into();
+    let neg: BigUint = (-coeff).into();
+    if neg < value {
+        format!("-{}", neg)
+    } else {
+        value.to_string()
+    }
+}
+
+// indices of the constraints that are drawn, all of them for small circuits
+pub fn sample_constraints(num_constraints: usize, max_constraints: usize) -> Vec<usize> {
+    if num_constraints <= max_constraints {
+        return (0..num_constraints).collect();
+    }
+    (0..max_constraints)
+        .map(|i| i * num_constraints / max_constraints)
+        .collect()
+}
+
+pub fn matrices_to_dot<F: PrimeField>(
+    matrices: &ConstraintMatrices<F>,
+    options: &DotOptions,
+) -> String {
+    let num_instance = matrices.num_instance_variables;
+    let sampled = sample_constraints(matrices.num_constraints, options.max_constraints);
+
+    let mut variables = BTreeSet::new();
+    for &i in sampled.iter() {
+        for row in [&matrices.a[i], &matrices.b[i], &matrices.c[i]].iter() {
+            variables.extend(row.iter().map(|(_, var)| *var));
+        }
+    }
+
+    let mut dot = String::new();
+    writeln!(dot, "digraph circuit {{").unwrap();
+    writeln!(dot, "  rankdir=LR;").unwrap();
+    writeln!(dot, "  node [fontname=\"monospace\"];").unwrap();
+    if sampled.len() < matrices.num_constraints {
+        writeln!(
+            dot,
+            "  label=\"sampled {} of {} constraints\";",
+            sampled.len(),
+            matrices.num_constraints
+        )
+        .unwrap();
+    }
+
+    for &var in variables.iter() {
+        let style = if var < num_instance {
+            ", style=filled, fillcolor=lightblue"
+        } else {
+            ""
+        };
+        writeln!(
+            dot,
+            "  v{} [label=\"{}\", shape=ellipse{}];",
+            var,
+            variable_name(num_instance, var),
+            style
+        )
+        .unwrap();
+    }
+
+    let edge_label = |matrix: &str, coeff: F| {
+        if options.show_coefficients && !coeff.is_one() {
+            format!("{} {}", matrix, format_coefficient(coeff))
+        } else {
+            matrix.to_string()
+        }
+    };
+    for &i in sampled.iter() {
+        writeln!(dot, "  c{} [label=\"c{}\", shape=box];", i, i).unwrap();
+        for (matrix, row) in [("A", &matrices.a[i]), ("B", &matrices.b[i])].iter() {
+            for &(coeff, var) in row.iter() {
+                writeln!(
+                    dot,
+                    "  v{} -> c{} [label=\"{}\"];",
+                    var,
+                    i,
+                    edge_label(matrix, coeff)
+                )
+                .unwrap();
+            }
+        }
+        for &(coeff, var) in matrices.c[i].iter() {
+            writeln!(
+                dot,
+                "  c{} -> v{} [label=\"{}\"];",
+                i,
+                var,
+                edge_label("C", coeff)
+            )
+            .unwrap();
+        }
+    }
+
+    writeln!(dot, "}}").unwrap();
+    dot
+}
+
+// synthesizes the circuit in setup mode, so no witness is needed
+pub fn constraint_matrices<F, C>(circuit: C) -> Result<ConstraintMatrices<F>, SynthesisError>
+where
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+{
+    let cs = ConstraintSystem::new_ref();
+    cs.set_mode(SynthesisMode::Setup);
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+    cs.to_matrices().ok_or(SynthesisError::MissingCS)
+}
+
+pub fn circuit_to_dot<F, C>(circuit: C, options: &DotOptions) -> Result<String, SynthesisError>
+where
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+{
+    Ok(matrices_to_dot(&constraint_matrices(circuit)?, options))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::CubicDemoCircuit;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use crate::padding::Padded;
+    use ark_bls12_381::Fr as BlsFr;
+
+    #[test]
+    fn test_cubic_dot() {
+        let dot = circuit_to_dot(
+            CubicDemoCircuit::<BlsFr> { x: None },
+            &DotOptions::default(),
+        )
+        .unwrap();
+
+        // x * x = tmp_1, tmp_1 * x = y, (y + x + 5) * 1 = out
+        assert_eq!(dot.matches("shape=box").count(), 3);
+        // one, out and the witnesses x, tmp_1, y
+        assert_eq!(dot.matches("shape=ellipse").count(), 5);
+        assert!(dot.contains("v0 [label=\"one\""));
+        assert!(dot.contains("v1 [label=\"x1\""));
+        assert!(dot.contains("label=\"A 5\""));
+        assert!(dot.starts_with("digraph circuit {"));
+        assert!(!dot.contains("sampled"));
+    }
+
+    #[test]
+    fn test_sampling() {
+        assert_eq!(sample_constraints(3, 10), vec![0, 1, 2]);
+        assert_eq!(sample_constraints(100, 4), vec![0, 25, 50, 75]);
+
+        let circuit = Padded::new(MultiplyDemoCircuit::<BlsFr> { a: None, b: None }, 100, 10);
+        let options = DotOptions {
+            max_constraints: 10,
+            show_coefficients: false,
+        };
+        let dot = circuit_to_dot(circuit, &options).unwrap();
+        assert_eq!(dot.matches("shape=box").count(), 10);
+        assert!(dot.contains("sampled 10 of 100 constraints"));
+    }
+
+    #[test]
+    fn test_format_coefficient() {
+        assert_eq!(format_coefficient(BlsFr::from(5u8)), "5");
+        assert_eq!(format_coefficient(-BlsFr::from(5u8)), "-5");
+    }
+}
@@ -0,0 +1,232 @@
+use ark_bls12_381::Fr;
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ed_on_bls12_381::{constraints::EdwardsVar, EdwardsAffine, Fr as JubjubScalar};
+use ark_ff::Zero;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_sponge::poseidon::PoseidonParameters;
+
+use crate::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+use crate::schnorr_gadget::{generator, generator_mul_var, secret_key_to_field, secret_key_var};
+
+// Verifiable random functions: the holder of a secret key evaluates a pseudorandom
+// function of an input, e.g. the round of a randomness beacon, and proves the output is
+// the one of the key behind the public key pk = sk * G, so the output can be neither
+// chosen nor predicted by anybody else. Here the proof is a SNARK that reveals nothing
+// about the key beyond that. Two functions:
+//   - `Vrf::Hash`, the output is H(sk, input). Only sound with a proof, H(sk, .) alone
+//     can't be checked against pk.
+//   - `Vrf::Ec`, the construction of the EC-VRF: the input is hashed to a point
+//     B = hash_to_curve(input), gamma = sk * B and the output is H(gamma). The verifier
+//     computes B itself and the circuit checks gamma against pk with two scalar
+//     multiplications by the same bits, the relation the EC-VRF proves with a DLEQ proof.
+// The key is range checked (`secret_key_var`), so there is one output per key and input.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Vrf {
+    Hash,
+    Ec,
+}
+
+// try and increment, a point of the prime order subgroup from H(input, counter) as the
+// x-coordinate
+pub fn hash_to_curve(params: &PoseidonParameters<Fr>, input: Fr) -> EdwardsAffine {
+    (0u64..)
+        .filter_map(|counter| {
+            let x = hash_inputs(params, &[input, Fr::from(counter)]);
+            EdwardsAffine::get_point_from_x(x, false)
+        })
+        .map(|point| point.mul_by_cofactor())
+        .find(|point| !point.is_zero())
+        .expect("half of the x-coordinates are on the curve")
+}
+
+pub fn evaluate(vrf: Vrf, params: &PoseidonParameters<Fr>, sk: JubjubScalar, input: Fr) -> Fr {
+    match vrf {
+        Vrf::Hash => hash_inputs(params, &[secret_key_to_field(sk), input]),
+        Vrf::Ec => {
+            let gamma = hash_to_curve(params, input).mul(sk).into_affine();
+            hash_inputs(params, &[gamma.x, gamma.y])
+        }
+    }
+}
+
+// what the verifier checks a proof against, the point B only for `Vrf::Ec`
+pub fn public_inputs(
+    vrf: Vrf,
+    params: &PoseidonParameters<Fr>,
+    pk: &EdwardsAffine,
+    input: Fr,
+    output: Fr,
+) -> Vec<Fr> {
+    let mut inputs = vec![pk.x, pk.y, input];
+    if vrf == Vrf::Ec {
+        let base = hash_to_curve(params, input);
+        inputs.extend_from_slice(&[base.x, base.y]);
+    }
+    inputs.push(output);
+    inputs
+}
+
+#[derive(Clone)]
+pub struct VrfCircuit {
+    pub vrf: Vrf,
+    pub params: PoseidonParameters<Fr>,
+    pub pk: EdwardsAffine,
+    pub input: Fr,
+    pub output: Fr,
+    pub secret_key: Option<JubjubScalar>,
+}
+
+impl VrfCircuit {
+    pub fn new(vrf: Vrf, params: PoseidonParameters<Fr>, sk: JubjubScalar, input: Fr) -> Self {
+        Self {
+            vrf,
+            pk: generator().mul(sk).into_affine(),
+            input,
+            output: evaluate(vrf, &params, sk, input),
+            params,
+            secret_key: Some(sk),
+        }
+    }
+
+    // the public key, the input and the output are public inputs
+    pub fn blank(vrf: Vrf, params: PoseidonParameters<Fr>) -> Self {
+        Self {
+            vrf,
+            params,
+            pk: generator(),
+            input: Fr::zero(),
+            output: Fr::zero(),
+            secret_key: None,
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        public_inputs(self.vrf, &self.params, &self.pk, self.input, self.output)
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for VrfCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let pk = EdwardsVar::new_input(ns!(cs, "pk"), || Ok(self.pk))?;
+        let input = FpVar::new_input(ns!(cs, "input"), || Ok(self.input))?;
+        // B is public, the verifier binds it to the input
+        let base = match self.vrf {
+            Vrf::Hash => None,
+            Vrf::Ec => {
+                let base = hash_to_curve(&self.params, self.input);
+                Some(EdwardsVar::new_input(ns!(cs, "base"), || Ok(base))?)
+            }
+        };
+        let output = FpVar::new_input(ns!(cs, "output"), || Ok(self.output))?;
+
+        let secret_key = secret_key_var(cs.clone(), self.secret_key)?;
+        generator_mul_var(&secret_key)?.enforce_equal(&pk)?;
+        let expected = match base {
+            None => {
+                let secret_key = Boolean::le_bits_to_fp_var(&secret_key)?;
+                hash_inputs_var(cs, &self.params, &[secret_key, input])?
+            }
+            Some(base) => {
+                let gamma = base.scalar_mul_le(secret_key.iter())?;
+                hash_inputs_var(cs, &self.params, &[gamma.x, gamma.y])?
+            }
+        };
+        expected.enforce_equal(&output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::poseidon_params::poseidon_parameters;
+    use crate::schnorr_gadget::keygen;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_snark::SNARK;
+
+    fn is_satisfied(circuit: VrfCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_hash_to_curve() {
+        let params = poseidon_parameters();
+        let points: Vec<_> = (0u8..8)
+            .map(|i| hash_to_curve(&params, Fr::from(i)))
+            .collect();
+        for (i, point) in points.iter().enumerate() {
+            assert!(point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve());
+            assert!(!points[..i].contains(point));
+        }
+    }
+
+    #[test]
+    fn test_beacon() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (sk, pk) = keygen(rng);
+        let (_, other_pk) = keygen(rng);
+
+        for &vrf in [Vrf::Hash, Vrf::Ec].iter() {
+            let (proving_key, vk) = Groth16::<Bls12_381>::circuit_specific_setup(
+                VrfCircuit::blank(vrf, params.clone()),
+                rng,
+            )
+            .unwrap();
+            // the beacon of a few rounds
+            for round in 1u8..4 {
+                let circuit = VrfCircuit::new(vrf, params.clone(), sk, Fr::from(round));
+                assert_eq!(circuit.pk, pk);
+                let output = circuit.output;
+                let proof = Groth16::<Bls12_381>::prove(&proving_key, circuit, rng).unwrap();
+                let inputs = public_inputs(vrf, &params, &pk, Fr::from(round), output);
+                assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+                // another output, the output of another round or another key
+                let other = [
+                    public_inputs(vrf, &params, &pk, Fr::from(round), output + Fr::from(1u8)),
+                    public_inputs(vrf, &params, &pk, Fr::from(round + 1), output),
+                    public_inputs(vrf, &params, &other_pk, Fr::from(round), output),
+                ];
+                for inputs in other.iter() {
+                    assert!(!Groth16::<Bls12_381>::verify(&vk, inputs, &proof).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrong_outputs() {
+        let rng = &mut ark_std::test_rng();
+        let params = poseidon_parameters();
+        let (sk, _) = keygen(rng);
+        let (other_sk, _) = keygen(rng);
+        let input = Fr::from(7u8);
+
+        for &vrf in [Vrf::Hash, Vrf::Ec].iter() {
+            let circuit = VrfCircuit::new(vrf, params.clone(), sk, input);
+            assert!(is_satisfied(circuit.clone()));
+            // the output of another key under this public key
+            let mut wrong = circuit.clone();
+            wrong.output = evaluate(vrf, &params, other_sk, input);
+            assert!(!is_satisfied(wrong));
+            // the key of another public key
+            let mut wrong = circuit;
+            wrong.secret_key = Some(other_sk);
+            assert!(!is_satisfied(wrong));
+        }
+        // the two functions differ
+        assert_ne!(
+            evaluate(Vrf::Hash, &params, sk, input),
+            evaluate(Vrf::Ec, &params, sk, input)
+        );
+    }
+}
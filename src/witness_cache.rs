@@ -0,0 +1,142 @@
+use ark_ff::Field;
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError, Variable};
+use std::collections::BTreeMap;
+
+// `new_witness_variable` behind a label: the first allocation under a label makes the
+// variable, every later one returns it. Synthesis code that asks for the same logical
+// value in several places (a helper called in a loop, the same input wired into two
+// sub-circuits) then pays for one variable instead of one per call, and the copies can't
+// drift apart in the assignment. The padding loops of the first Marlin multiply demo
+// allocated `a` again for every padding variable, the pattern this replaces when
+// the duplicates are not wanted as padding.
+//
+// When proving, a hit still computes the value and fails with `Unsatisfiable` if it
+// differs from the cached one: the label was reused for a different value.
+
+pub struct WitnessCache<F: Field> {
+    cs: ConstraintSystemRef<F>,
+    variables: BTreeMap<String, (Variable, Option<F>)>,
+    hits: usize,
+}
+
+impl<F: Field> WitnessCache<F> {
+    pub fn new(cs: ConstraintSystemRef<F>) -> Self {
+        Self {
+            cs,
+            variables: BTreeMap::new(),
+            hits: 0,
+        }
+    }
+
+    pub fn witness<V>(&mut self, label: &str, value: V) -> Result<Variable, SynthesisError>
+    where
+        V: FnOnce() -> Result<F, SynthesisError>,
+    {
+        let setup = self.cs.is_in_setup_mode();
+        if let Some(&(variable, cached)) = self.variables.get(label) {
+            if !setup && cached != Some(value()?) {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+            self.hits += 1;
+            return Ok(variable);
+        }
+        // the value is computed once, for the variable and for later checks
+        let value = if setup { None } else { Some(value()?) };
+        let variable = self
+            .cs
+            .new_witness_variable(|| value.ok_or(SynthesisError::AssignmentMissing))?;
+        self.variables.insert(label.to_string(), (variable, value));
+        Ok(variable)
+    }
+
+    pub fn get(&self, label: &str) -> Option<Variable> {
+        self.variables.get(label).map(|&(variable, _)| variable)
+    }
+
+    // distinct variables allocated
+    pub fn len(&self) -> usize {
+        self.variables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+
+    // allocations that reused a variable
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::Fr as BlsFr;
+    use ark_relations::lc;
+    use ark_relations::r1cs::{ConstraintSystem, SynthesisMode};
+
+    // a * b = c, with `a` asked for again in each of `k` further constraints like the
+    // padding loop did
+    fn synthesize(cs: ConstraintSystemRef<BlsFr>, k: usize, dedup: bool) {
+        let (a_value, b_value) = (BlsFr::from(3u8), BlsFr::from(5u8));
+        let mut cache = WitnessCache::new(cs.clone());
+        let mut a = Vec::new();
+        for _ in 0..=k {
+            a.push(if dedup {
+                cache.witness("a", || Ok(a_value)).unwrap()
+            } else {
+                cs.new_witness_variable(|| Ok(a_value)).unwrap()
+            });
+        }
+        let b = cs.new_witness_variable(|| Ok(b_value)).unwrap();
+        let c = cs.new_input_variable(|| Ok(a_value * b_value)).unwrap();
+        for a in a {
+            cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + c)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_fewer_variables() {
+        let k = 10;
+        for &dedup in [false, true].iter() {
+            let cs = ConstraintSystem::<BlsFr>::new_ref();
+            synthesize(cs.clone(), k, dedup);
+            assert!(cs.is_satisfied().unwrap());
+            assert_eq!(cs.num_constraints(), k + 1);
+            // a (k + 1 times without the cache) and b
+            let expected = if dedup { 2 } else { k + 2 };
+            assert_eq!(cs.num_witness_variables(), expected);
+        }
+    }
+
+    #[test]
+    fn test_labels() {
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        let mut cache = WitnessCache::new(cs.clone());
+        let x = cache.witness("x", || Ok(BlsFr::from(7u8))).unwrap();
+        let y = cache.witness("y", || Ok(BlsFr::from(7u8))).unwrap();
+        assert_ne!(x, y);
+        assert_eq!(cache.witness("x", || Ok(BlsFr::from(7u8))).unwrap(), x);
+        assert_eq!(cache.get("x"), Some(x));
+        assert_eq!(cache.get("z"), None);
+        assert_eq!((cache.len(), cache.hits()), (2, 1));
+        assert_eq!(cs.num_witness_variables(), 2);
+
+        // the same label for another value
+        assert_eq!(
+            cache.witness("x", || Ok(BlsFr::from(8u8))),
+            Err(SynthesisError::Unsatisfiable)
+        );
+
+        // in setup mode there is nothing to compare, or to compute
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        let mut cache = WitnessCache::new(cs.clone());
+        let x = cache
+            .witness("x", || Err(SynthesisError::AssignmentMissing))
+            .unwrap();
+        assert_eq!(cache.witness("x", || unreachable!()).unwrap(), x);
+        assert_eq!(cs.num_witness_variables(), 1);
+    }
+}
@@ -0,0 +1,375 @@
+use ark_ff::PrimeField;
+use num_bigint::BigUint;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::cubic_demo::CubicWitness;
+#[cfg(feature = "gadgets")]
+use crate::merkle_gadget::MerklePath;
+use crate::multiply_demo::MultiplyWitness;
+
+// Witnesses as JSON, the form the CLI (`prove --witness-file`) and HTTP front ends take
+// them in, one schema per circuit:
+//   multiply     {"a": "3", "b": "5"}
+//   cubic        {"x": "3"}
+//   formula      {"x": "3", "out": "35"}, any variable names
+//   merkle path  {"leaf": "7", "index": 5, "siblings": ["0x1f", "12", ...]}
+// Field elements are strings in decimal or 0x-prefixed (big-endian) hex, or JSON integers
+// where they fit in a u64. A value at or above the modulus is an error, not reduced the
+// way ark-ff's `FromStr` reduces it. Reading happens in two steps, so that every error
+// names what is wrong: serde reads the shape (a missing or unknown field fails there,
+// serde names it) with the values kept as JSON, then `parse` turns each value into a
+// field element and names the field, `siblings[2]`, that isn't one.
+
+#[derive(Debug)]
+pub enum WitnessError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    // neither a decimal nor a hex string nor an integer
+    InvalidValue { field: String, value: String },
+    // not below the modulus
+    OutOfRange { field: String },
+    // a position the path can't have, at or past 2^(number of siblings)
+    InvalidIndex { index: u64, height: usize },
+}
+
+impl fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessError::Io(e) => write!(f, "cannot read witness: {}", e),
+            WitnessError::Json(e) => write!(f, "invalid witness: {}", e),
+            WitnessError::InvalidValue { field, value } => write!(
+                f,
+                "`{}` is not a decimal or 0x hex field element: {}",
+                field, value
+            ),
+            WitnessError::OutOfRange { field } => {
+                write!(f, "`{}` is not smaller than the field modulus", field)
+            }
+            WitnessError::InvalidIndex { index, height } => write!(
+                f,
+                "`index` {} is not a leaf of a tree of height {}",
+                index, height
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WitnessError {}
+
+impl From<io::Error> for WitnessError {
+    fn from(e: io::Error) -> Self {
+        WitnessError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for WitnessError {
+    fn from(e: serde_json::Error) -> Self {
+        WitnessError::Json(e)
+    }
+}
+
+// a field element as it stands in the JSON, parsed by `parse`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct FieldValue(pub Value);
+
+impl FieldValue {
+    // decimal, as written out
+    pub fn from_field<F: PrimeField>(value: F) -> Self {
+        let value: BigUint = value.into();
+        FieldValue(Value::String(value.to_string()))
+    }
+
+    pub fn parse<F: PrimeField>(&self, field: &str) -> Result<F, WitnessError> {
+        match &self.0 {
+            Value::String(text) => parse_field_element(field, text),
+            Value::Number(n) => match n.as_u64() {
+                Some(n) => Ok(F::from(n)),
+                None => Err(WitnessError::InvalidValue {
+                    field: field.to_string(),
+                    value: n.to_string(),
+                }),
+            },
+            other => Err(WitnessError::InvalidValue {
+                field: field.to_string(),
+                value: other.to_string(),
+            }),
+        }
+    }
+}
+
+// "1234" or "0x4d2", at most the modulus minus one
+pub fn parse_field_element<F: PrimeField>(field: &str, text: &str) -> Result<F, WitnessError> {
+    let trimmed = text.trim();
+    let value = match trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+    {
+        Some(hex) => BigUint::parse_bytes(hex.as_bytes(), 16),
+        None => BigUint::parse_bytes(trimmed.as_bytes(), 10),
+    }
+    .ok_or_else(|| WitnessError::InvalidValue {
+        field: field.to_string(),
+        value: format!("{:?}", text),
+    })?;
+    let modulus = BigUint::from_bytes_le(
+        &F::characteristic()
+            .iter()
+            .flat_map(|limb| limb.to_le_bytes())
+            .collect::<Vec<u8>>(),
+    );
+    if value >= modulus {
+        return Err(WitnessError::OutOfRange {
+            field: field.to_string(),
+        });
+    }
+    Ok(F::from_le_bytes_mod_order(&value.to_bytes_le()))
+}
+
+// the JSON schema of a circuit's witness and how to parse it
+pub trait WitnessJson<F: PrimeField>: DeserializeOwned + Serialize {
+    type Witness;
+
+    fn parse(&self) -> Result<Self::Witness, WitnessError>;
+}
+
+pub fn from_json<F, J>(json: &str) -> Result<J::Witness, WitnessError>
+where
+    F: PrimeField,
+    J: WitnessJson<F>,
+{
+    serde_json::from_str::<J>(json)?.parse()
+}
+
+pub fn load<F, J, P>(path: P) -> Result<J::Witness, WitnessError>
+where
+    F: PrimeField,
+    J: WitnessJson<F>,
+    P: AsRef<Path>,
+{
+    from_json::<F, J>(&fs::read_to_string(path)?)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MultiplyWitnessJson {
+    pub a: FieldValue,
+    pub b: FieldValue,
+}
+
+impl<F: PrimeField> WitnessJson<F> for MultiplyWitnessJson {
+    type Witness = MultiplyWitness<F>;
+
+    fn parse(&self) -> Result<Self::Witness, WitnessError> {
+        Ok(MultiplyWitness {
+            a: self.a.parse("a")?,
+            b: self.b.parse("b")?,
+        })
+    }
+}
+
+impl<F: PrimeField> From<MultiplyWitness<F>> for MultiplyWitnessJson {
+    fn from(witness: MultiplyWitness<F>) -> Self {
+        Self {
+            a: FieldValue::from_field(witness.a),
+            b: FieldValue::from_field(witness.b),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CubicWitnessJson {
+    pub x: FieldValue,
+}
+
+impl<F: PrimeField> WitnessJson<F> for CubicWitnessJson {
+    type Witness = CubicWitness<F>;
+
+    fn parse(&self) -> Result<Self::Witness, WitnessError> {
+        Ok(CubicWitness {
+            x: self.x.parse("x")?,
+        })
+    }
+}
+
+impl<F: PrimeField> From<CubicWitness<F>> for CubicWitnessJson {
+    fn from(witness: CubicWitness<F>) -> Self {
+        Self {
+            x: FieldValue::from_field(witness.x),
+        }
+    }
+}
+
+// the values of a `formula` circuit's variables by name
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct FormulaWitnessJson(pub BTreeMap<String, FieldValue>);
+
+impl<F: PrimeField> WitnessJson<F> for FormulaWitnessJson {
+    type Witness = BTreeMap<String, F>;
+
+    fn parse(&self) -> Result<Self::Witness, WitnessError> {
+        self.0
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), value.parse(name)?)))
+            .collect()
+    }
+}
+
+// a leaf and its path, the membership witness of the Merkle demos
+#[cfg(feature = "gadgets")]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MerklePathWitnessJson {
+    pub leaf: FieldValue,
+    pub index: u64,
+    // from the leaf up
+    pub siblings: Vec<FieldValue>,
+}
+
+#[cfg(feature = "gadgets")]
+impl<F: PrimeField> WitnessJson<F> for MerklePathWitnessJson {
+    type Witness = (F, MerklePath<F>);
+
+    fn parse(&self) -> Result<Self::Witness, WitnessError> {
+        let height = self.siblings.len();
+        if height < 64 && self.index >> height != 0 {
+            return Err(WitnessError::InvalidIndex {
+                index: self.index,
+                height,
+            });
+        }
+        let siblings = self
+            .siblings
+            .iter()
+            .enumerate()
+            .map(|(i, sibling)| sibling.parse(&format!("siblings[{}]", i)))
+            .collect::<Result<_, _>>()?;
+        let path = MerklePath {
+            index: self.index as usize,
+            siblings,
+        };
+        Ok((self.leaf.parse("leaf")?, path))
+    }
+}
+
+#[cfg(feature = "gadgets")]
+impl<F: PrimeField> From<(F, &MerklePath<F>)> for MerklePathWitnessJson {
+    fn from((leaf, path): (F, &MerklePath<F>)) -> Self {
+        Self {
+            leaf: FieldValue::from_field(leaf),
+            index: path.index as u64,
+            siblings: path
+                .siblings
+                .iter()
+                .map(|&s| FieldValue::from_field(s))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_bls12_381::Fr;
+
+    #[test]
+    fn test_field_elements() {
+        let parse = |text| parse_field_element::<Fr>("x", text);
+        assert_eq!(parse(" 1234 ").unwrap(), Fr::from(1234u32));
+        assert_eq!(parse("0x4d2").unwrap(), Fr::from(1234u32));
+        assert_eq!(parse("0X04D2").unwrap(), Fr::from(1234u32));
+        let modulus =
+            "52435875175126190479447740508185965837690552500527637822603658699938581184513";
+        assert!(matches!(
+            parse(modulus),
+            Err(WitnessError::OutOfRange { .. })
+        ));
+        let minus_one =
+            "52435875175126190479447740508185965837690552500527637822603658699938581184512";
+        assert_eq!(parse(minus_one).unwrap(), -Fr::from(1u8));
+        for bad in ["", "-1", "12a", "0x", "0xg"].iter() {
+            assert!(matches!(parse(bad), Err(WitnessError::InvalidValue { .. })));
+        }
+    }
+
+    #[test]
+    fn test_witnesses() {
+        let witness: MultiplyWitness<Fr> =
+            from_json::<_, MultiplyWitnessJson>(r#"{"a": "3", "b": 5}"#).unwrap();
+        assert_eq!(witness.instance().c, Fr::from(15u8));
+        let json = serde_json::to_string(&MultiplyWitnessJson::from(witness)).unwrap();
+        assert_eq!(json, r#"{"a":"3","b":"5"}"#);
+        let witness: CubicWitness<Fr> =
+            from_json::<_, CubicWitnessJson>(r#"{"x": "0x3"}"#).unwrap();
+        assert_eq!(witness.x, Fr::from(3u8));
+        let values: BTreeMap<String, Fr> =
+            from_json::<_, FormulaWitnessJson>(r#"{"x": "3", "out": "35"}"#).unwrap();
+        assert_eq!(values["out"], Fr::from(35u8));
+
+        // every error names the field
+        let error = |json| {
+            from_json::<Fr, MultiplyWitnessJson>(json)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(error(r#"{"a": "3"}"#).contains("missing field `b`"));
+        assert!(error(r#"{"a": "3", "b": "5", "c": "15"}"#).contains("unknown field `c`"));
+        assert_eq!(
+            error(r#"{"a": "3", "b": "five"}"#),
+            "`b` is not a decimal or 0x hex field element: \"five\""
+        );
+        assert_eq!(
+            error(r#"{"a": -3, "b": "5"}"#),
+            "`a` is not a decimal or 0x hex field element: -3"
+        );
+        assert_eq!(
+            error(r#"{"a": true, "b": "5"}"#),
+            "`a` is not a decimal or 0x hex field element: true"
+        );
+        assert!(
+            from_json::<Fr, FormulaWitnessJson>(r#"{"x": "3", "out": null}"#)
+                .unwrap_err()
+                .to_string()
+                .starts_with("`out`")
+        );
+    }
+
+    #[cfg(feature = "gadgets")]
+    #[test]
+    fn test_merkle_path() {
+        use crate::merkle_gadget::MerkleTree;
+        use crate::poseidon_params::poseidon_parameters;
+
+        let params = poseidon_parameters();
+        let leaves: Vec<Fr> = (0..8u8).map(Fr::from).collect();
+        let tree = MerkleTree::new(params.clone(), 3, &leaves).unwrap();
+        let path = tree.path(5).unwrap();
+        let json = serde_json::to_string(&MerklePathWitnessJson::from((leaves[5], &path))).unwrap();
+        let (leaf, parsed): (Fr, _) = from_json::<_, MerklePathWitnessJson>(&json).unwrap();
+        assert_eq!(parsed, path);
+        assert_eq!(parsed.root(&params, leaf), tree.root());
+
+        let error = |json: &str| {
+            from_json::<Fr, MerklePathWitnessJson>(json)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error(r#"{"leaf": "7", "index": 1, "siblings": ["1", "x"]}"#),
+            "`siblings[1]` is not a decimal or 0x hex field element: \"x\""
+        );
+        assert_eq!(
+            error(r#"{"leaf": "7", "index": 4, "siblings": ["1", "2"]}"#),
+            "`index` 4 is not a leaf of a tree of height 2"
+        );
+    }
+}
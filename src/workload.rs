@@ -0,0 +1,138 @@
+use ark_ff::PrimeField;
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_relations::r1cs::{ConstraintMatrices, ConstraintSynthesizer, SynthesisError};
+use std::fmt;
+
+use crate::visualize::constraint_matrices;
+
+// the primitive operations of one Groth16 proof, from the dimensions of the circuit's
+// matrices alone:
+//   FFTs  7 over the QAP domain (constraints plus public inputs, rounded up to a power
+//         of two): A, B and C go through an inverse and a coset FFT each, h comes back
+//         through a coset inverse FFT
+//   MSMs  over the queries of the proving key: A and B in G1 and B in G2 with a scalar
+//         per variable but the constant one, H with one per domain point but the last,
+//         L with one per witness variable
+// `benches/workload.rs` times each of them at the sizes of the demo circuits; their sum
+// is the proving time less synthesis, which tells a faster MSM from a faster FFT apart
+// before buying hardware for either.
+
+pub const FFTS_PER_PROOF: usize = 7;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Groth16Workload {
+    pub num_constraints: usize,
+    // with the constant one
+    pub num_instance_variables: usize,
+    pub num_witness_variables: usize,
+    pub domain_size: usize,
+}
+
+impl Groth16Workload {
+    // None if the field has no domain that large
+    pub fn of_matrices<F: PrimeField>(matrices: &ConstraintMatrices<F>) -> Option<Self> {
+        let domain = GeneralEvaluationDomain::<F>::new(
+            matrices.num_constraints + matrices.num_instance_variables,
+        )?;
+        Some(Self {
+            num_constraints: matrices.num_constraints,
+            num_instance_variables: matrices.num_instance_variables,
+            num_witness_variables: matrices.num_witness_variables,
+            domain_size: domain.size(),
+        })
+    }
+
+    pub fn of_circuit<F, C>(circuit: C) -> Result<Self, SynthesisError>
+    where
+        F: PrimeField,
+        C: ConstraintSynthesizer<F>,
+    {
+        Self::of_matrices(&constraint_matrices::<F, C>(circuit)?)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)
+    }
+
+    // the A, B in G1 and B in G2 MSMs
+    pub fn variable_msm(&self) -> usize {
+        self.num_instance_variables + self.num_witness_variables - 1
+    }
+
+    pub fn h_msm(&self) -> usize {
+        self.domain_size - 1
+    }
+
+    pub fn l_msm(&self) -> usize {
+        self.num_witness_variables
+    }
+
+    // every G1 MSM of a proof, a and b first
+    pub fn g1_msms(&self) -> [usize; 4] {
+        let variables = self.variable_msm();
+        [variables, variables, self.h_msm(), self.l_msm()]
+    }
+}
+
+impl fmt::Display for Groth16Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, h, l] = self.g1_msms();
+        write!(
+            f,
+            "{} constraints: {} FFTs of {}, G1 MSMs of {}, {}, {}, {}, a G2 MSM of {}",
+            self.num_constraints,
+            FFTS_PER_PROOF,
+            self.domain_size,
+            a,
+            b,
+            h,
+            l,
+            self.variable_msm()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cubic_demo::CubicDemoCircuit;
+    use crate::matmul_demo::MatMulCircuit;
+    use crate::multiply_demo::MultiplyDemoCircuit;
+    use crate::synthetic::SyntheticCircuit;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_groth16::{Groth16, ProvingKey};
+    use ark_snark::SNARK;
+
+    fn check_against_key<C: ConstraintSynthesizer<Fr>>(circuit: impl Fn() -> C) -> Groth16Workload {
+        let rng = &mut ark_std::test_rng();
+        let workload = Groth16Workload::of_circuit(circuit()).unwrap();
+        let (pk, _): (ProvingKey<Bls12_381>, _) =
+            Groth16::<Bls12_381>::circuit_specific_setup(circuit(), rng).unwrap();
+        // the first point of the A and B queries goes in without an MSM
+        assert_eq!(pk.a_query.len() - 1, workload.variable_msm());
+        assert_eq!(pk.b_g1_query.len() - 1, workload.variable_msm());
+        assert_eq!(pk.b_g2_query.len() - 1, workload.variable_msm());
+        assert_eq!(pk.h_query.len(), workload.h_msm());
+        assert_eq!(pk.l_query.len(), workload.l_msm());
+        workload
+    }
+
+    #[test]
+    fn test_matches_the_proving_key() {
+        let multiply = check_against_key(MultiplyDemoCircuit::<Fr>::blank);
+        assert_eq!(multiply.domain_size, 4);
+        assert_eq!(
+            multiply.to_string(),
+            format!(
+                "{} constraints: 7 FFTs of 4, G1 MSMs of {v}, {v}, 3, {}, a G2 MSM of {v}",
+                multiply.num_constraints,
+                multiply.num_witness_variables,
+                v = multiply.variable_msm()
+            )
+        );
+
+        check_against_key(CubicDemoCircuit::<Fr>::blank);
+        let matmul = check_against_key(|| MatMulCircuit::<Fr>::blank(4));
+        // 64 products and 16 sums, 16 inputs and the constant one
+        assert_eq!(matmul.domain_size, 128);
+        let synthetic = check_against_key(|| SyntheticCircuit::of_log_size(8));
+        assert_eq!(synthetic.domain_size, 512);
+    }
+}
@@ -0,0 +1,123 @@
+// Exact shape of every demo circuit. Proving and verifying keys are only valid for the
+// shape they were generated for, so a refactor that changes any of these numbers
+// invalidates existing keys and must update them on purpose.
+#![cfg(feature = "std")]
+
+use ark_bls12_381::Fr as BlsFr;
+#[cfg(feature = "gadgets")]
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+#[cfg(feature = "gadgets")]
+use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisMode,
+};
+
+#[cfg(feature = "gadgets")]
+use gadgets::cubic_gadget::constraints::{
+    CubicGadgetCircuit, CubicRootGadgetTrait, ParamTypeVar, SolutionDemoGadget,
+};
+#[cfg(feature = "gadgets")]
+use gadgets::lagrange_demo::LagrangeEvalCircuit;
+#[cfg(feature = "marlin")]
+use gadgets::marlin_demo;
+#[cfg(feature = "marlin")]
+use gadgets::padding::Padded;
+use gadgets::{cubic_demo, multiply_demo};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Shape {
+    constraints: usize,
+    // including the constant one
+    instance_variables: usize,
+    witness_variables: usize,
+}
+
+fn shape_of(cs: &ConstraintSystemRef<BlsFr>) -> Shape {
+    Shape {
+        constraints: cs.num_constraints(),
+        instance_variables: cs.num_instance_variables(),
+        witness_variables: cs.num_witness_variables(),
+    }
+}
+
+// synthesized in setup mode, the shape must not depend on the witness
+fn circuit_shape<C: ConstraintSynthesizer<BlsFr>>(circuit: C) -> Shape {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_mode(SynthesisMode::Setup);
+    circuit.generate_constraints(cs.clone()).unwrap();
+    shape_of(&cs)
+}
+
+fn shape(constraints: usize, instance_variables: usize, witness_variables: usize) -> Shape {
+    Shape {
+        constraints,
+        instance_variables,
+        witness_variables,
+    }
+}
+
+#[test]
+fn multiply_demo_shape() {
+    let circuit = multiply_demo::MultiplyDemoCircuit::<BlsFr> { a: None, b: None };
+    assert_eq!(circuit_shape(circuit), shape(1, 2, 2));
+}
+
+#[test]
+fn cubic_demo_shape() {
+    let circuit = cubic_demo::CubicDemoCircuit::<BlsFr> { x: None };
+    assert_eq!(circuit_shape(circuit), shape(3, 2, 3));
+}
+
+#[test]
+#[cfg(feature = "marlin")]
+fn marlin_cubic_demo_shape() {
+    let circuit = marlin_demo::CubicDemoCircuit::<BlsFr> { x: None };
+    assert_eq!(circuit_shape(circuit), shape(3, 2, 3));
+}
+
+#[test]
+#[cfg(feature = "marlin")]
+fn marlin_multiply_demo_shape() {
+    let circuit = marlin_demo::MultiplyDemoCircuit::<BlsFr> { a: None, b: None };
+    assert_eq!(circuit_shape(circuit), shape(1, 2, 2));
+    // padded to 10 constraints and 8 variables, the constant one included
+    assert_eq!(circuit_shape(Padded::new(circuit, 10, 8)), shape(10, 2, 6));
+}
+
+#[test]
+#[cfg(feature = "gadgets")]
+fn cubic_gadget_shape() {
+    let cs = ConstraintSystem::<BlsFr>::new_ref();
+    let x = FpVar::new_witness(cs.clone(), || Ok(BlsFr::from(3u8))).unwrap();
+    let y = FpVar::new_input(cs.clone(), || Ok(BlsFr::from(35u8))).unwrap();
+    let gadget = SolutionDemoGadget {
+        x: ParamTypeVar::new(x),
+    };
+    // x * x, x^2 * x and 3 constraints for the boolean `is_eq`
+    let _is_root = gadget.verify(&ParamTypeVar::new(y)).unwrap();
+    assert_eq!(shape_of(&cs), shape(5, 2, 5));
+}
+
+#[test]
+#[cfg(feature = "gadgets")]
+fn cubic_gadget_circuit_shape() {
+    let circuit = CubicGadgetCircuit::<BlsFr> { x: None, y: None };
+    // the gadget plus one constraint forcing `is_eq` to true
+    assert_eq!(circuit_shape(circuit), shape(6, 2, 5));
+}
+
+#[test]
+#[cfg(feature = "gadgets")]
+fn lagrange_demo_shape() {
+    // log(n) squarings, an inverse and a product per evaluation, the final product
+    // with Z_H(z) and the equality check: log(n) + 2n + 2
+    for &(n, expected) in [(4, shape(12, 7, 11)), (8, shape(21, 11, 20))].iter() {
+        let domain = Radix2EvaluationDomain::<BlsFr>::new(n).unwrap();
+        assert_eq!(
+            circuit_shape(LagrangeEvalCircuit::blank(domain)),
+            expected,
+            "domain size {}",
+            n
+        );
+    }
+}
@@ -0,0 +1,419 @@
+// Whole journeys through the crate as an application would take them, one per backend:
+// setup, keys to disk and back, a proof from the reloaded proving key, the proof to disk
+// and back, verification with the reloaded verifying key. Every artifact goes through a
+// file in both serialization modes, so a mismatch between the modules (a kind written
+// as another, a mode the loader can't read, a key that does not survive its envelope)
+// fails here even when each module passes its own tests.
+#![cfg(all(feature = "groth16", feature = "marlin"))]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ark_bls12_381::{Bls12_381, Fr};
+use ark_bn254::Bn254;
+use ark_ec::PairingEngine;
+use ark_groth16::{Groth16, Proof, ProvingKey, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use gadgets::cubic_demo::{CubicDemoCircuit, CubicWitness};
+use gadgets::explain::explain;
+use gadgets::marlin_srs::{
+    MarlinInst, MarlinProof, MarlinProvingKey, MarlinSrsSize, MarlinVerifyingKey, UniversalSetup,
+};
+use gadgets::persist::{self, ArtifactKind, SerializationMode, Validation};
+use gadgets::prover;
+use gadgets::rng::RngConfig;
+use gadgets::text::{from_base64, to_base64};
+use gadgets::verifier::{validate_proof, validate_vk, verify_bytes};
+
+const MODES: [SerializationMode; 2] = [
+    SerializationMode::Compressed,
+    SerializationMode::Uncompressed,
+];
+
+fn journey_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "gadgets_end_to_end_{}_{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// to the file and back
+fn reload<T: CanonicalSerialize + CanonicalDeserialize>(
+    path: &Path,
+    artifact: &T,
+    kind: ArtifactKind,
+    mode: SerializationMode,
+) -> T {
+    persist::save(path, artifact, kind, mode).unwrap();
+    persist::load(path, kind, Validation::Checked).unwrap()
+}
+
+fn witness<F: ark_ff::Field>() -> CubicWitness<F> {
+    CubicWitness { x: F::from(3u8) }
+}
+
+fn groth16_journey<E: PairingEngine>(name: &str) {
+    let dir = journey_dir(name);
+    for (i, &mode) in MODES.iter().enumerate() {
+        let rng_config = RngConfig::seeded(i as u64);
+        let (pk, vk) =
+            prover::setup::<_, Groth16<E>, _>(CubicDemoCircuit::blank(), &rng_config).unwrap();
+        let pk: ProvingKey<E> = reload(&dir.join("pk.bin"), &pk, ArtifactKind::ProvingKey, mode);
+        let vk: VerifyingKey<E> =
+            reload(&dir.join("vk.bin"), &vk, ArtifactKind::VerifyingKey, mode);
+
+        let witness = witness::<E::Fr>();
+        let proof =
+            prover::prove::<_, Groth16<E>, _>(&pk, CubicDemoCircuit::from(witness), &rng_config)
+                .unwrap();
+        let proof: Proof<E> = reload(&dir.join("proof.bin"), &proof, ArtifactKind::Proof, mode);
+
+        let inputs = witness.instance().public_inputs();
+        assert!(prover::verify::<_, Groth16<E>>(&vk, &inputs, &proof).unwrap());
+        assert!(!prover::verify::<_, Groth16<E>>(&vk, &[E::Fr::from(36u8)], &proof).unwrap());
+        assert!(explain(&vk, &proof, &inputs).is_valid());
+
+        // the verifier side alone, from the bytes of the files
+        let vk_bytes = fs::read(dir.join("vk.bin")).unwrap();
+        let proof_bytes = fs::read(dir.join("proof.bin")).unwrap();
+        assert!(verify_bytes::<E>(&vk_bytes, &proof_bytes, &inputs).unwrap());
+
+        // and as text, e.g. in a JSON body
+        let decoded: Proof<E> = from_base64(&to_base64(&proof)).unwrap();
+        assert_eq!(decoded, proof);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn groth16_bls12_381() {
+    groth16_journey::<Bls12_381>("groth16_bls12_381");
+}
+
+#[test]
+fn groth16_bn254() {
+    groth16_journey::<Bn254>("groth16_bn254");
+}
+
+#[test]
+fn groth16_unchecked_artifacts_validated() {
+    // a trusted, uncompressed key read without the point checks, the proof from the
+    // network validated explicitly before verifying
+    let dir = journey_dir("groth16_unchecked");
+    let rng_config = RngConfig::seeded(7);
+    let (pk, vk) =
+        prover::setup::<_, Groth16<Bls12_381>, _>(CubicDemoCircuit::blank(), &rng_config).unwrap();
+    let path = dir.join("vk.bin");
+    persist::save(
+        &path,
+        &vk,
+        ArtifactKind::VerifyingKey,
+        SerializationMode::Uncompressed,
+    )
+    .unwrap();
+    let vk: VerifyingKey<Bls12_381> =
+        persist::load(&path, ArtifactKind::VerifyingKey, Validation::Unchecked).unwrap();
+    validate_vk(&vk).unwrap();
+
+    let witness = witness::<Fr>();
+    let proof = prover::prove::<_, Groth16<Bls12_381>, _>(
+        &pk,
+        CubicDemoCircuit::from(witness),
+        &rng_config,
+    )
+    .unwrap();
+    let mut bytes = Vec::new();
+    proof.serialize_unchecked(&mut bytes).unwrap();
+    let proof = Proof::<Bls12_381>::deserialize_unchecked(&bytes[..]).unwrap();
+    validate_proof(&proof).unwrap();
+    let inputs = witness.instance().public_inputs();
+    assert!(prover::verify::<_, Groth16<Bls12_381>>(&vk, &inputs, &proof).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn marlin_bls12_381() {
+    let dir = journey_dir("marlin_bls12_381");
+    for (i, &mode) in MODES.iter().enumerate() {
+        let mut rng = RngConfig::seeded(i as u64).build();
+        let size = MarlinSrsSize::of(CubicDemoCircuit::<Fr>::blank()).unwrap();
+        let setup = UniversalSetup::<Bls12_381>::new(size, &mut rng).unwrap();
+        let srs_path = dir.join("srs.bin");
+        setup.save(&srs_path, mode).unwrap();
+        let setup = UniversalSetup::<Bls12_381>::load(&srs_path, Validation::Checked).unwrap();
+
+        let (pk, vk) = setup.index_circuit(CubicDemoCircuit::blank()).unwrap();
+        let pk: MarlinProvingKey<Bls12_381> =
+            reload(&dir.join("pk.bin"), &pk, ArtifactKind::ProvingKey, mode);
+        let vk: MarlinVerifyingKey<Bls12_381> =
+            reload(&dir.join("vk.bin"), &vk, ArtifactKind::VerifyingKey, mode);
+
+        let witness = witness::<Fr>();
+        let proof =
+            MarlinInst::<Bls12_381>::prove(&pk, CubicDemoCircuit::from(witness), &mut rng).unwrap();
+        let proof: MarlinProof<Bls12_381> =
+            reload(&dir.join("proof.bin"), &proof, ArtifactKind::Proof, mode);
+
+        let inputs = witness.instance().public_inputs();
+        assert!(MarlinInst::<Bls12_381>::verify(&vk, &inputs, &proof, &mut rng).unwrap());
+        assert!(
+            !MarlinInst::<Bls12_381>::verify(&vk, &[Fr::from(36u8)], &proof, &mut rng).unwrap()
+        );
+
+        // a Marlin proof file is not a Groth16 proof
+        let groth16: Result<Proof<Bls12_381>, _> = persist::load(
+            dir.join("proof.bin"),
+            ArtifactKind::Proof,
+            Validation::Checked,
+        );
+        assert!(groth16.is_err());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// the same journey through the command line tool, which wires config, backends, persist
+// and text together. It reads what the tool prints, which `print-trace` mixes its timers
+// into
+#[cfg(all(feature = "cli", not(feature = "print-trace")))]
+#[test]
+fn cli_groth16() {
+    use std::process::Command;
+
+    let dir = journey_dir("cli_groth16");
+    let cli = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_gadgets-cli"))
+            .current_dir(&dir)
+            .args(args)
+            .output()
+            .unwrap();
+        (
+            output.status.success(),
+            String::from_utf8(output.stdout).unwrap(),
+        )
+    };
+
+    let (ok, _) = cli(&[
+        "setup",
+        "--circuit",
+        "cubic",
+        "--out-dir",
+        "keys",
+        "--seed",
+        "1",
+    ]);
+    assert!(ok);
+    let (ok, public) = cli(&[
+        "prove",
+        "--circuit",
+        "cubic",
+        "--pk",
+        "keys/pk.bin",
+        "--witness",
+        "x=3",
+        "--out",
+        "proof.bin",
+        "--seed",
+        "2",
+    ]);
+    assert!(ok);
+    assert_eq!(public.trim(), "35");
+
+    // the same witness from a JSON file, in hex; a value that is not one fails
+    let prove_json = |json: &str| {
+        fs::write(dir.join("witness.json"), json).unwrap();
+        cli(&[
+            "prove",
+            "--circuit",
+            "cubic",
+            "--pk",
+            "keys/pk.bin",
+            "--witness-file",
+            "witness.json",
+            "--out",
+            "json_proof.bin",
+        ])
+    };
+    let (ok, public) = prove_json(r#"{"x": "0x3"}"#);
+    assert!(ok);
+    assert_eq!(public.trim(), "35");
+    assert!(!prove_json(r#"{"x": "three"}"#).0);
+
+    // through text and back into a file
+    let (ok, text) = cli(&[
+        "encode",
+        "--kind",
+        "proof",
+        "--input",
+        "proof.bin",
+        "--format",
+        "hex",
+    ]);
+    assert!(ok);
+    fs::write(dir.join("proof.hex"), text).unwrap();
+    let (ok, _) = cli(&[
+        "decode",
+        "--kind",
+        "proof",
+        "--input",
+        "proof.hex",
+        "--format",
+        "hex",
+        "--out",
+        "decoded.bin",
+    ]);
+    assert!(ok);
+
+    let verify = |proof: &str, public: &str| {
+        cli(&[
+            "verify",
+            "--vk",
+            "keys/vk.bin",
+            "--proof",
+            proof,
+            "--public",
+            public,
+            "--explain",
+        ])
+    };
+    let (ok, out) = verify("decoded.bin", "35");
+    assert!(ok);
+    assert!(out.contains("holds") && out.ends_with("proof is valid\n"));
+    let (ok, out) = verify("decoded.bin", "36");
+    assert!(!ok);
+    assert!(out.contains("fails"));
+
+    // the key records the cubic circuit, verifying as another circuit is refused before
+    // the proof is looked at
+    let (ok, _) = cli(&[
+        "verify",
+        "--circuit",
+        "cubic",
+        "--vk",
+        "keys/vk.bin",
+        "--proof",
+        "decoded.bin",
+        "--public",
+        "35",
+    ]);
+    assert!(ok);
+    let (ok, out) = cli(&[
+        "verify",
+        "--circuit",
+        "multiply",
+        "--vk",
+        "keys/vk.bin",
+        "--proof",
+        "decoded.bin",
+        "--public",
+        "35",
+    ]);
+    assert!(!ok);
+    assert!(out.is_empty());
+    let (ok, _) = cli(&[
+        "verify",
+        "--formula",
+        "x^3 + x + 6 == out",
+        "--vk",
+        "keys/vk.bin",
+        "--proof",
+        "decoded.bin",
+        "--public",
+        "35",
+    ]);
+    assert!(!ok);
+
+    // `decode` writes the key without its circuit, like keys saved before circuits were
+    // recorded: checking one against a circuit takes --allow-unrecorded-circuit
+    let (ok, text) = cli(&[
+        "encode",
+        "--kind",
+        "vk",
+        "--input",
+        "keys/vk.bin",
+        "--format",
+        "hex",
+    ]);
+    assert!(ok);
+    fs::write(dir.join("vk.hex"), text).unwrap();
+    let (ok, _) = cli(&[
+        "decode",
+        "--kind",
+        "vk",
+        "--input",
+        "vk.hex",
+        "--format",
+        "hex",
+        "--out",
+        "unrecorded_vk.bin",
+    ]);
+    assert!(ok);
+    let verify_unrecorded = |flags: &[&str]| {
+        let mut args = vec![
+            "verify",
+            "--vk",
+            "unrecorded_vk.bin",
+            "--proof",
+            "decoded.bin",
+        ];
+        args.extend_from_slice(flags);
+        args.extend_from_slice(&["--public", "35"]);
+        cli(&args).0
+    };
+    assert!(verify_unrecorded(&[]));
+    assert!(!verify_unrecorded(&["--circuit", "cubic"]));
+    assert!(verify_unrecorded(&[
+        "--circuit",
+        "cubic",
+        "--allow-unrecorded-circuit"
+    ]));
+
+    // keys from the key cache: the second setup is a hit, the seed of a miss only
+    for (seed, out_dir) in [("3", "cached"), ("4", "cached_again")].iter() {
+        let (ok, _) = cli(&[
+            "setup",
+            "--circuit",
+            "cubic",
+            "--cache-dir",
+            "cache",
+            "--out-dir",
+            out_dir,
+            "--seed",
+            seed,
+        ]);
+        assert!(ok);
+    }
+    assert_eq!(
+        fs::read(dir.join("cached/vk.bin")).unwrap(),
+        fs::read(dir.join("cached_again/vk.bin")).unwrap()
+    );
+    let (ok, _) = cli(&[
+        "prove",
+        "--circuit",
+        "cubic",
+        "--cache-dir",
+        "cache",
+        "--witness",
+        "x=3",
+        "--out",
+        "cached_proof.bin",
+        "--seed",
+        "5",
+    ]);
+    assert!(ok);
+    let (ok, _) = cli(&[
+        "verify",
+        "--vk",
+        "cached/vk.bin",
+        "--proof",
+        "cached_proof.bin",
+        "--public",
+        "35",
+    ]);
+    assert!(ok);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,450 @@
+// every gadget against its native counterpart, see `gadgets::equivalence`. New gadgets
+// with a native implementation get a line here.
+#![cfg(feature = "gadgets")]
+
+use ark_bls12_381::Fr;
+use ark_ed_on_bls12_381::{constraints::EdwardsVar, EdwardsAffine};
+use ark_ff::{One, PrimeField, Zero};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use num_bigint::BigUint;
+
+use gadgets::boolean_demo::{less_than, ripple_carry_add, to_bits};
+use gadgets::conditional_demo::enforce_if;
+use gadgets::cubic_gadget::constraints::SolutionDemoGadget;
+use gadgets::date_gadget::{
+    days_between, days_between_var, year_of_day, year_of_day_var, DAY_BITS,
+};
+use gadgets::div_rem_demo::div_rem;
+use gadgets::equivalence::{check_equivalence, Equivalence};
+use gadgets::fiat_shamir_demo::{Transcript, TranscriptVar, LABEL};
+use gadgets::hashed_inputs_demo::{hash_inputs, hash_inputs_var};
+use gadgets::merkle_gadget::{MerklePath, MerklePathVar};
+use gadgets::poseidon_params::poseidon_parameters;
+use gadgets::private_index_demo::select_index;
+use gadgets::schnorr_gadget::{self, Signature, SignatureVar};
+
+const SAMPLES: usize = 100;
+
+#[test]
+fn cubic_gadget_matches_native() {
+    let rng = &mut ark_std::test_rng();
+    // 49 edge case pairs and the samples, each also with the y its x solves
+    let checked = check_equivalence::<Fr, SolutionDemoGadget<Fr>, _>(rng, SAMPLES).unwrap();
+    assert_eq!(checked, 2 * (49 + SAMPLES));
+}
+
+// the digest of three values
+struct HashInputs;
+
+impl Equivalence<Fr> for HashInputs {
+    const NUM_INPUTS: usize = 3;
+    type Native = Fr;
+    type Var = FpVar<Fr>;
+
+    fn native(inputs: &[Fr]) -> Result<Fr, gadgets::Error> {
+        Ok(hash_inputs(&poseidon_parameters(), inputs))
+    }
+
+    fn gadget(
+        cs: ConstraintSystemRef<Fr>,
+        inputs: &[FpVar<Fr>],
+    ) -> Result<FpVar<Fr>, SynthesisError> {
+        hash_inputs_var(cs, &poseidon_parameters(), inputs)
+    }
+}
+
+#[test]
+fn hashed_inputs_match_native() {
+    let rng = &mut ark_std::test_rng();
+    let checked = check_equivalence::<Fr, HashInputs, _>(rng, SAMPLES).unwrap();
+    assert_eq!(checked, 7 * 7 * 7 + SAMPLES);
+}
+
+// flag, lhs, rhs. The flag is checked to be a bit, the gadget gives it back.
+struct EnforceIf;
+
+impl Equivalence<Fr> for EnforceIf {
+    const NUM_INPUTS: usize = 3;
+    type Native = bool;
+    type Var = Boolean<Fr>;
+
+    fn native(inputs: &[Fr]) -> Result<bool, gadgets::Error> {
+        let flag = match inputs[0] {
+            f if f.is_zero() => false,
+            f if f.is_one() => true,
+            _ => return Err("the flag is not a bit".into()),
+        };
+        if flag && inputs[1] != inputs[2] {
+            return Err("lhs != rhs".into());
+        }
+        Ok(flag)
+    }
+
+    fn gadget(
+        cs: ConstraintSystemRef<Fr>,
+        inputs: &[FpVar<Fr>],
+    ) -> Result<Boolean<Fr>, SynthesisError> {
+        let flag = Boolean::new_witness(cs, || Ok(inputs[0].value()?.is_one()))?;
+        FpVar::from(flag.clone()).enforce_equal(&inputs[0])?;
+        enforce_if(&flag, &inputs[1], &inputs[2])?;
+        Ok(flag)
+    }
+
+    // an active flag with equal sides
+    fn related(inputs: &[Fr]) -> Option<Vec<Fr>> {
+        Some(vec![Fr::one(), inputs[1], inputs[1]])
+    }
+}
+
+#[test]
+fn enforce_if_matches_native() {
+    let rng = &mut ark_std::test_rng();
+    let checked = check_equivalence::<Fr, EnforceIf, _>(rng, SAMPLES).unwrap();
+    assert_eq!(checked, 2 * (7 * 7 * 7 + SAMPLES));
+}
+
+// array[i] for an array of three, inputs the array then i
+struct SelectIndex;
+
+impl Equivalence<Fr> for SelectIndex {
+    const NUM_INPUTS: usize = 4;
+    type Native = Fr;
+    type Var = FpVar<Fr>;
+
+    fn native(inputs: &[Fr]) -> Result<Fr, gadgets::Error> {
+        (0..3)
+            .find(|&j| inputs[3] == Fr::from(j as u64))
+            .map(|j| inputs[j])
+            .ok_or_else(|| "index out of range".into())
+    }
+
+    fn gadget(
+        cs: ConstraintSystemRef<Fr>,
+        inputs: &[FpVar<Fr>],
+    ) -> Result<FpVar<Fr>, SynthesisError> {
+        select_index(cs, &inputs[..3], &inputs[3])
+    }
+
+    // the same array with an index in range
+    fn related(inputs: &[Fr]) -> Option<Vec<Fr>> {
+        let index = inputs[3].into_repr().as_ref()[0] % 3;
+        Some(vec![inputs[0], inputs[1], inputs[2], Fr::from(index)])
+    }
+}
+
+#[test]
+fn select_index_matches_native() {
+    let rng = &mut ark_std::test_rng();
+    // each of the 7 edge cases in each of the 4 positions, and the samples
+    let checked = check_equivalence::<Fr, SelectIndex, _>(rng, SAMPLES).unwrap();
+    assert_eq!(checked, 2 * (4 * 7 + SAMPLES));
+}
+
+// two 8 bit numbers, their 9 bit sum from full adders
+struct RippleCarryAdd;
+
+impl Equivalence<Fr> for RippleCarryAdd {
+    const NUM_INPUTS: usize = 2;
+    type Native = Fr;
+    type Var = FpVar<Fr>;
+
+    fn native(inputs: &[Fr]) -> Result<Fr, gadgets::Error> {
+        let a = byte(inputs[0])?;
+        let b = byte(inputs[1])?;
+        Ok(Fr::from(a as u16 + b as u16))
+    }
+
+    fn gadget(
+        _: ConstraintSystemRef<Fr>,
+        inputs: &[FpVar<Fr>],
+    ) -> Result<FpVar<Fr>, SynthesisError> {
+        let sum = ripple_carry_add(&to_bits(&inputs[0], 8)?, &to_bits(&inputs[1], 8)?)?;
+        Boolean::le_bits_to_fp_var(&sum)
+    }
+
+    fn related(inputs: &[Fr]) -> Option<Vec<Fr>> {
+        Some(inputs.iter().map(|&v| Fr::from(low_byte(v))).collect())
+    }
+}
+
+// a < b for two 8 bit numbers
+struct LessThan;
+
+impl Equivalence<Fr> for LessThan {
+    const NUM_INPUTS: usize = 2;
+    type Native = bool;
+    type Var = Boolean<Fr>;
+
+    fn native(inputs: &[Fr]) -> Result<bool, gadgets::Error> {
+        Ok(byte(inputs[0])? < byte(inputs[1])?)
+    }
+
+    fn gadget(
+        _: ConstraintSystemRef<Fr>,
+        inputs: &[FpVar<Fr>],
+    ) -> Result<Boolean<Fr>, SynthesisError> {
+        less_than(&to_bits(&inputs[0], 8)?, &to_bits(&inputs[1], 8)?)
+    }
+
+    fn related(inputs: &[Fr]) -> Option<Vec<Fr>> {
+        Some(inputs.iter().map(|&v| Fr::from(low_byte(v))).collect())
+    }
+}
+
+fn low_byte(value: Fr) -> u8 {
+    value.into_repr().as_ref()[0] as u8
+}
+
+fn byte(value: Fr) -> Result<u8, gadgets::Error> {
+    match Fr::from(low_byte(value)) == value {
+        true => Ok(low_byte(value)),
+        false => Err("not a byte".into()),
+    }
+}
+
+#[test]
+fn boolean_gadgets_match_native() {
+    let rng = &mut ark_std::test_rng();
+    let checked = check_equivalence::<Fr, RippleCarryAdd, _>(rng, SAMPLES).unwrap();
+    assert_eq!(checked, 2 * (49 + SAMPLES));
+    let checked = check_equivalence::<Fr, LessThan, _>(rng, SAMPLES).unwrap();
+    assert_eq!(checked, 2 * (49 + SAMPLES));
+}
+
+// q and r of a / b for b of 8 bits, packed as q * 2^8 + r
+struct DivRem;
+
+impl Equivalence<Fr> for DivRem {
+    const NUM_INPUTS: usize = 2;
+    type Native = Fr;
+    type Var = FpVar<Fr>;
+
+    fn native(inputs: &[Fr]) -> Result<Fr, gadgets::Error> {
+        let b = byte(inputs[1])?;
+        if b == 0 {
+            return Err("division by zero".into());
+        }
+        let a: BigUint = inputs[0].into();
+        let q = &a / b;
+        if q >= BigUint::from(256u16) {
+            return Err("the quotient does not fit in 8 bits".into());
+        }
+        Ok(Fr::from(q * 256u16 + a % b))
+    }
+
+    fn gadget(
+        cs: ConstraintSystemRef<Fr>,
+        inputs: &[FpVar<Fr>],
+    ) -> Result<FpVar<Fr>, SynthesisError> {
+        let (q, r) = div_rem(cs, &inputs[0], &inputs[1], 8)?;
+        Ok(q * Fr::from(256u16) + r)
+    }
+
+    // a nonzero b and an a with a quotient of 8 bits
+    fn related(inputs: &[Fr]) -> Option<Vec<Fr>> {
+        let b = low_byte(inputs[1]).max(1) as u64;
+        let a = inputs[0].into_repr().as_ref()[0] % (256 * b);
+        Some(vec![Fr::from(a), Fr::from(b)])
+    }
+}
+
+#[test]
+fn div_rem_matches_native() {
+    let rng = &mut ark_std::test_rng();
+    let checked = check_equivalence::<Fr, DivRem, _>(rng, SAMPLES).unwrap();
+    assert_eq!(checked, 2 * (49 + SAMPLES));
+}
+
+// the year of a day since 1970-01-01
+struct YearOfDay;
+
+impl Equivalence<Fr> for YearOfDay {
+    const NUM_INPUTS: usize = 1;
+    type Native = Fr;
+    type Var = FpVar<Fr>;
+
+    fn native(inputs: &[Fr]) -> Result<Fr, gadgets::Error> {
+        Ok(Fr::from(year_of_day(day(inputs[0])?)))
+    }
+
+    fn gadget(
+        cs: ConstraintSystemRef<Fr>,
+        inputs: &[FpVar<Fr>],
+    ) -> Result<FpVar<Fr>, SynthesisError> {
+        year_of_day_var(cs, &inputs[0])
+    }
+
+    fn related(inputs: &[Fr]) -> Option<Vec<Fr>> {
+        Some(vec![Fr::from(low_day(inputs[0]))])
+    }
+}
+
+// the days from the first input to the second
+struct DaysBetween;
+
+impl Equivalence<Fr> for DaysBetween {
+    const NUM_INPUTS: usize = 2;
+    type Native = Fr;
+    type Var = FpVar<Fr>;
+
+    fn native(inputs: &[Fr]) -> Result<Fr, gadgets::Error> {
+        days_between(day(inputs[0])?, day(inputs[1])?)
+            .map(Fr::from)
+            .ok_or_else(|| "the second day is before the first".into())
+    }
+
+    fn gadget(
+        _: ConstraintSystemRef<Fr>,
+        inputs: &[FpVar<Fr>],
+    ) -> Result<FpVar<Fr>, SynthesisError> {
+        days_between_var(&inputs[0], &inputs[1])
+    }
+
+    // two days in order
+    fn related(inputs: &[Fr]) -> Option<Vec<Fr>> {
+        let (a, b) = (low_day(inputs[0]), low_day(inputs[1]));
+        Some(vec![Fr::from(a.min(b)), Fr::from(a.max(b))])
+    }
+}
+
+fn low_day(value: Fr) -> u32 {
+    (value.into_repr().as_ref()[0] % (1 << DAY_BITS)) as u32
+}
+
+fn day(value: Fr) -> Result<u32, gadgets::Error> {
+    match Fr::from(low_day(value)) == value {
+        true => Ok(low_day(value)),
+        false => Err("not a day".into()),
+    }
+}
+
+#[test]
+fn date_gadgets_match_native() {
+    let rng = &mut ark_std::test_rng();
+    let checked = check_equivalence::<Fr, YearOfDay, _>(rng, SAMPLES).unwrap();
+    assert_eq!(checked, 2 * (7 + SAMPLES));
+    let checked = check_equivalence::<Fr, DaysBetween, _>(rng, SAMPLES).unwrap();
+    assert_eq!(checked, 2 * (49 + SAMPLES));
+}
+
+// a fixed key and signature of the message 42, checked against the input as message
+struct SchnorrVerify;
+
+impl SchnorrVerify {
+    fn signed() -> (EdwardsAffine, Signature) {
+        let rng = &mut ark_std::test_rng();
+        let (sk, pk) = schnorr_gadget::keygen(rng);
+        let signature = schnorr_gadget::sign(&poseidon_parameters(), sk, &[Fr::from(42u8)], rng);
+        (pk, signature)
+    }
+}
+
+impl Equivalence<Fr> for SchnorrVerify {
+    const NUM_INPUTS: usize = 1;
+    type Native = bool;
+    type Var = Boolean<Fr>;
+
+    fn native(inputs: &[Fr]) -> Result<bool, gadgets::Error> {
+        let (pk, signature) = Self::signed();
+        Ok(schnorr_gadget::verify(
+            &poseidon_parameters(),
+            &pk,
+            inputs,
+            &signature,
+        ))
+    }
+
+    fn gadget(
+        cs: ConstraintSystemRef<Fr>,
+        inputs: &[FpVar<Fr>],
+    ) -> Result<Boolean<Fr>, SynthesisError> {
+        let (pk, signature) = Self::signed();
+        let pk = EdwardsVar::new_witness(cs.clone(), || Ok(pk))?;
+        let signature = SignatureVar::new_witness(cs.clone(), || Ok(signature))?;
+        schnorr_gadget::verify_var(cs, &poseidon_parameters(), &pk, inputs, &signature)
+    }
+
+    // the signed message
+    fn related(_: &[Fr]) -> Option<Vec<Fr>> {
+        Some(vec![Fr::from(42u8)])
+    }
+}
+
+#[test]
+fn schnorr_verify_matches_native() {
+    let rng = &mut ark_std::test_rng();
+    let checked = check_equivalence::<Fr, SchnorrVerify, _>(rng, SAMPLES).unwrap();
+    assert_eq!(checked, 2 * (7 + SAMPLES));
+}
+
+// the root of a tree of height 2 from a leaf and its siblings, the leaf at index 2
+struct MerkleRoot;
+
+impl Equivalence<Fr> for MerkleRoot {
+    const NUM_INPUTS: usize = 3;
+    type Native = Fr;
+    type Var = FpVar<Fr>;
+
+    fn native(inputs: &[Fr]) -> Result<Fr, gadgets::Error> {
+        let path = MerklePath {
+            index: 2,
+            siblings: inputs[1..].to_vec(),
+        };
+        Ok(path.root(&poseidon_parameters(), inputs[0]))
+    }
+
+    fn gadget(
+        cs: ConstraintSystemRef<Fr>,
+        inputs: &[FpVar<Fr>],
+    ) -> Result<FpVar<Fr>, SynthesisError> {
+        let path = MerklePathVar {
+            index_bits: vec![Boolean::FALSE, Boolean::TRUE],
+            siblings: inputs[1..].to_vec(),
+        };
+        path.root(cs, &poseidon_parameters(), &inputs[0])
+    }
+}
+
+#[test]
+fn merkle_root_matches_native() {
+    let rng = &mut ark_std::test_rng();
+    let checked = check_equivalence::<Fr, MerkleRoot, _>(rng, SAMPLES).unwrap();
+    assert_eq!(checked, 7 * 7 * 7 + SAMPLES);
+}
+
+// a message of one value, a challenge, a message of two and the second challenge, which
+// depends on the first
+struct TranscriptChallenges;
+
+impl Equivalence<Fr> for TranscriptChallenges {
+    const NUM_INPUTS: usize = 3;
+    type Native = Fr;
+    type Var = FpVar<Fr>;
+
+    fn native(inputs: &[Fr]) -> Result<Fr, gadgets::Error> {
+        let mut transcript = Transcript::new(&poseidon_parameters(), LABEL);
+        transcript.append(&inputs[..1]);
+        let _first = transcript.challenge();
+        transcript.append(&inputs[1..]);
+        Ok(transcript.challenge())
+    }
+
+    fn gadget(
+        cs: ConstraintSystemRef<Fr>,
+        inputs: &[FpVar<Fr>],
+    ) -> Result<FpVar<Fr>, SynthesisError> {
+        let mut transcript = TranscriptVar::new(cs, &poseidon_parameters(), LABEL)?;
+        transcript.append(&inputs[..1])?;
+        let _first = transcript.challenge()?;
+        transcript.append(&inputs[1..])?;
+        transcript.challenge()
+    }
+}
+
+#[test]
+fn transcript_matches_native() {
+    let rng = &mut ark_std::test_rng();
+    let checked = check_equivalence::<Fr, TranscriptChallenges, _>(rng, SAMPLES).unwrap();
+    assert_eq!(checked, 7 * 7 * 7 + SAMPLES);
+}
@@ -0,0 +1,110 @@
+// BN254 calldata against fixtures in snarkjs' formats: tests/data/snarkjs holds
+// proof.json, verification_key.json and public.json as written by `snarkjs groth16
+// prove` / `snarkjs zkey export verificationkey` (projective coordinates, Fq2 as
+// [c0, c1]), and calldata.txt as printed by `snarkjs zkey export soliditycalldata`.
+// The proof is of the multiply circuit with a = 3, b = 11; the files were written from an
+// arkworks proof in those layouts, snarkjs itself is not needed to run the check.
+#![cfg(feature = "groth16")]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_groth16::{Groth16, Proof, VerifyingKey};
+use ark_snark::SNARK;
+use serde_json::Value;
+
+use gadgets::eth_calldata::{inputs_from_words, inputs_to_words, solidity_calldata, ProofCalldata};
+
+fn fixture(name: &str) -> Value {
+    let path = data_dir().join(name);
+    serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap()
+}
+
+fn data_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/snarkjs")
+}
+
+fn fq(value: &Value) -> Fq {
+    Fq::from_str(value.as_str().unwrap()).unwrap()
+}
+
+// snarkjs writes points in projective coordinates, always normalized to z = 1
+fn g1(value: &Value) -> G1Affine {
+    assert_eq!(value[2], "1");
+    let p = G1Affine::new(fq(&value[0]), fq(&value[1]), false);
+    assert!(p.is_on_curve());
+    p
+}
+
+fn g2(value: &Value) -> G2Affine {
+    assert_eq!(value[2], serde_json::json!(["1", "0"]));
+    let fq2 = |v: &Value| Fq2::new(fq(&v[0]), fq(&v[1]));
+    let p = G2Affine::new(fq2(&value[0]), fq2(&value[1]), false);
+    assert!(p.is_on_curve());
+    p
+}
+
+fn snarkjs_proof() -> Proof<Bn254> {
+    let proof = fixture("proof.json");
+    assert_eq!(proof["curve"], "bn128");
+    Proof {
+        a: g1(&proof["pi_a"]),
+        b: g2(&proof["pi_b"]),
+        c: g1(&proof["pi_c"]),
+    }
+}
+
+fn snarkjs_verifying_key() -> VerifyingKey<Bn254> {
+    let vk = fixture("verification_key.json");
+    VerifyingKey {
+        alpha_g1: g1(&vk["vk_alpha_1"]),
+        beta_g2: g2(&vk["vk_beta_2"]),
+        gamma_g2: g2(&vk["vk_gamma_2"]),
+        delta_g2: g2(&vk["vk_delta_2"]),
+        gamma_abc_g1: vk["IC"].as_array().unwrap().iter().map(g1).collect(),
+    }
+}
+
+fn snarkjs_public() -> Vec<Fr> {
+    fixture("public.json")
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| Fr::from_str(v.as_str().unwrap()).unwrap())
+        .collect()
+}
+
+#[test]
+fn snarkjs_fixture_verifies() {
+    let public = snarkjs_public();
+    assert_eq!(public, vec![Fr::from(33u8)]);
+    assert!(Groth16::<Bn254>::verify(&snarkjs_verifying_key(), &public, &snarkjs_proof()).unwrap());
+}
+
+#[test]
+fn solidity_calldata_matches_snarkjs() {
+    let expected = fs::read_to_string(data_dir().join("calldata.txt")).unwrap();
+    assert_eq!(
+        solidity_calldata(&snarkjs_proof(), &snarkjs_public()),
+        expected.trim()
+    );
+}
+
+#[test]
+fn calldata_round_trip() {
+    let proof = snarkjs_proof();
+    let bytes = ProofCalldata::from_proof(&proof).to_bytes();
+    let decoded = ProofCalldata::from_bytes(&bytes)
+        .unwrap()
+        .to_proof()
+        .unwrap();
+    assert_eq!(decoded, proof);
+
+    let public = snarkjs_public();
+    let words = inputs_to_words(&public);
+    assert_eq!(words[0][31], 33);
+    let public = inputs_from_words(&words).unwrap();
+    assert!(Groth16::<Bn254>::verify(&snarkjs_verifying_key(), &public, &decoded).unwrap());
+}
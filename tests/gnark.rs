@@ -0,0 +1,79 @@
+// gnark artifacts of the multiply circuit (a = 3, b = 11, public c = 33) over BN254 in
+// tests/data/gnark: proof.bin and vk.bin as written by gnark's `WriteTo`, proof_raw.bin
+// and vk_raw.bin as written by `WriteRawTo`. It is the same proof as the snarkjs fixture
+// in tests/data/snarkjs, so the formats are checked against each other. The files were
+// written by `interop::gnark` following gnark's layout, Go is not needed to run the check.
+#![cfg(feature = "groth16")]
+
+use std::fs;
+use std::path::Path;
+
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::Groth16;
+use ark_snark::SNARK;
+
+use gadgets::eth_calldata::ProofCalldata;
+use gadgets::interop::gnark::{read_proof, read_verifying_key, write_proof, write_verifying_key};
+use gadgets::persist::SerializationMode;
+
+fn fixture(name: &str) -> Vec<u8> {
+    fs::read(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/data/gnark")
+            .join(name),
+    )
+    .unwrap()
+}
+
+#[test]
+fn gnark_fixtures_verify() {
+    let public = [Fr::from(33u8)];
+    for &(proof_file, vk_file, mode) in [
+        ("proof.bin", "vk.bin", SerializationMode::Compressed),
+        (
+            "proof_raw.bin",
+            "vk_raw.bin",
+            SerializationMode::Uncompressed,
+        ),
+    ]
+    .iter()
+    {
+        let (proof_bytes, vk_bytes) = (fixture(proof_file), fixture(vk_file));
+        let proof = read_proof(&proof_bytes[..]).unwrap();
+        let vk = read_verifying_key(&vk_bytes[..]).unwrap();
+        assert!(Groth16::<Bn254>::verify(&vk.vk, &public, &proof).unwrap());
+        assert!(!Groth16::<Bn254>::verify(&vk.vk, &[Fr::from(34u8)], &proof).unwrap());
+
+        // written back byte for byte
+        let mut bytes = Vec::new();
+        write_proof(&proof, mode, &mut bytes).unwrap();
+        assert_eq!(bytes, proof_bytes, "{}", proof_file);
+        let mut bytes = Vec::new();
+        write_verifying_key(&vk, mode, &mut bytes).unwrap();
+        assert_eq!(bytes, vk_bytes, "{}", vk_file);
+    }
+}
+
+#[test]
+fn gnark_and_snarkjs_fixtures_agree() {
+    // the raw proof starts with the 8 words of the Solidity calldata
+    let raw = fixture("proof_raw.bin");
+    let calldata = fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/snarkjs/calldata.txt"),
+    )
+    .unwrap();
+    let words: String = calldata
+        .split('"')
+        .filter(|s| s.starts_with("0x"))
+        .take(8)
+        .map(|s| &s[2..])
+        .collect();
+    let hex: String = raw[..ProofCalldata::LEN]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    assert_eq!(hex, words);
+
+    let compressed = read_proof(&fixture("proof.bin")[..]).unwrap();
+    assert_eq!(compressed, read_proof(&raw[..]).unwrap());
+}
@@ -0,0 +1,170 @@
+// What a Groth16 verifier binds and what it doesn't. Changing any one group element of
+// a proof, mixing the elements of two proofs or replaying a proof under another key is
+// rejected. But a valid proof can be turned into other valid proofs of the same
+// statement without the witness: (k A, B / k, C), (-A, -B, C) and a full rerandomization
+// all verify. A proof is not an identifier, a protocol that must not accept a statement
+// twice keys on the statement (a nullifier, a public input), never on the proof bytes.
+#![cfg(feature = "std")]
+
+use ark_bls12_381::{Bls12_381, Fr as BlsFr, G1Affine, G1Projective, G2Projective};
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{Field, One, PrimeField, Zero};
+use ark_groth16::{rerandomize_proof, Groth16, Proof, VerifyingKey};
+use ark_snark::SNARK;
+use ark_std::UniformRand;
+
+use gadgets::cubic_demo::CubicDemoCircuit;
+use gadgets::multiply_demo::MultiplyDemoCircuit;
+use gadgets::padding::Padded;
+
+type G16 = Groth16<Bls12_381>;
+
+fn multiply(a: u64, b: u64) -> MultiplyDemoCircuit<BlsFr> {
+    MultiplyDemoCircuit {
+        a: Some(BlsFr::from(a)),
+        b: Some(BlsFr::from(b)),
+    }
+}
+
+fn accepts(vk: &VerifyingKey<Bls12_381>, inputs: &[BlsFr], proof: &Proof<Bls12_381>) -> bool {
+    G16::verify(vk, inputs, proof).unwrap()
+}
+
+#[test]
+fn single_element_changes_are_rejected() {
+    let rng = &mut ark_std::test_rng();
+    let (pk, vk) = G16::circuit_specific_setup(multiply(0, 0), rng).unwrap();
+    let proof = G16::prove(&pk, multiply(3, 5), rng).unwrap();
+    let inputs = [BlsFr::from(15u8)];
+    assert!(accepts(&vk, &inputs, &proof));
+
+    let g1 = G1Projective::prime_subgroup_generator();
+    let g2 = G2Projective::prime_subgroup_generator();
+    let k = BlsFr::rand(rng);
+    let g1_changes = |p: G1Affine| {
+        vec![
+            (p.into_projective() + g1).into_affine(),
+            p.mul(k.into_repr()).into_affine(),
+            -p,
+            (p.into_projective().double()).into_affine(),
+            Zero::zero(),
+            g1.into_affine(),
+        ]
+    };
+
+    for a in g1_changes(proof.a) {
+        assert!(!accepts(&vk, &inputs, &Proof { a, ..proof.clone() }));
+    }
+    for c in g1_changes(proof.c) {
+        assert!(!accepts(&vk, &inputs, &Proof { c, ..proof.clone() }));
+    }
+    let p = proof.b;
+    for &b in [
+        (p.into_projective() + g2).into_affine(),
+        p.mul(k.into_repr()).into_affine(),
+        -p,
+        Zero::zero(),
+        g2.into_affine(),
+    ]
+    .iter()
+    {
+        assert!(!accepts(&vk, &inputs, &Proof { b, ..proof.clone() }));
+    }
+
+    // A and C are both in G1, exchanging them is one more change
+    let swapped = Proof {
+        a: proof.c,
+        c: proof.a,
+        ..proof.clone()
+    };
+    assert!(!accepts(&vk, &inputs, &swapped));
+}
+
+#[test]
+fn elements_of_two_proofs_do_not_mix() {
+    let rng = &mut ark_std::test_rng();
+    let (pk, vk) = G16::circuit_specific_setup(multiply(0, 0), rng).unwrap();
+    let inputs = [BlsFr::from(15u8)];
+
+    // two proofs of the same statement, from two witnesses and from one
+    let first = G16::prove(&pk, multiply(3, 5), rng).unwrap();
+    for second in [
+        G16::prove(&pk, multiply(5, 3), rng).unwrap(),
+        G16::prove(&pk, multiply(3, 5), rng).unwrap(),
+    ]
+    .iter()
+    {
+        assert!(accepts(&vk, &inputs, second));
+        // every proof taking some elements from each, the blinding of one doesn't
+        // cancel with the other's
+        for mask in 1..7u8 {
+            let pick = |bit: u8| mask >> bit & 1 == 1;
+            let mixed = Proof {
+                a: if pick(0) { second.a } else { first.a },
+                b: if pick(1) { second.b } else { first.b },
+                c: if pick(2) { second.c } else { first.c },
+            };
+            assert!(!accepts(&vk, &inputs, &mixed), "mask {}", mask);
+        }
+    }
+}
+
+#[test]
+fn proofs_do_not_replay_across_keys() {
+    let rng = &mut ark_std::test_rng();
+    let (pk, vk) = G16::circuit_specific_setup(multiply(0, 0), rng).unwrap();
+    let proof = G16::prove(&pk, multiply(5, 7), rng).unwrap();
+    let inputs = [BlsFr::from(35u8)];
+
+    // the cubic circuit with x = 3 has the same public output 35
+    let (_, cubic_vk) =
+        G16::circuit_specific_setup(CubicDemoCircuit::<BlsFr> { x: None }, rng).unwrap();
+    assert!(!accepts(&cubic_vk, &inputs, &proof));
+
+    // the same relation padded with a constraint is another circuit
+    let padded = Padded::new(MultiplyDemoCircuit::<BlsFr> { a: None, b: None }, 2, 0);
+    let (_, padded_vk) = G16::circuit_specific_setup(padded, rng).unwrap();
+    assert!(!accepts(&padded_vk, &inputs, &proof));
+
+    // a key stitched together from two setups of the very same circuit
+    let (_, other_vk) = G16::circuit_specific_setup(multiply(0, 0), rng).unwrap();
+    let stitched = VerifyingKey {
+        delta_g2: other_vk.delta_g2,
+        ..vk.clone()
+    };
+    assert!(!accepts(&stitched, &inputs, &proof));
+    let stitched = VerifyingKey {
+        gamma_abc_g1: other_vk.gamma_abc_g1.clone(),
+        ..vk.clone()
+    };
+    assert!(!accepts(&stitched, &inputs, &proof));
+    assert!(!accepts(&other_vk, &inputs, &proof));
+}
+
+#[test]
+fn valid_proofs_are_malleable() {
+    let rng = &mut ark_std::test_rng();
+    let (pk, vk) = G16::circuit_specific_setup(multiply(0, 0), rng).unwrap();
+    let proof = G16::prove(&pk, multiply(3, 5), rng).unwrap();
+    let inputs = [BlsFr::from(15u8)];
+
+    // e(A, B) only sees the product of the discrete logs of A and B
+    let k = BlsFr::rand(rng);
+    let scaled = Proof {
+        a: proof.a.mul(k.into_repr()).into_affine(),
+        b: proof.b.mul(k.inverse().unwrap().into_repr()).into_affine(),
+        c: proof.c,
+    };
+    let negated = Proof {
+        a: -proof.a,
+        b: -proof.b,
+        c: proof.c,
+    };
+    let rerandomized = rerandomize_proof(rng, &vk, &proof);
+    for other in [scaled, negated, rerandomized].iter() {
+        assert_ne!(other, &proof);
+        assert!(accepts(&vk, &inputs, other));
+        // still bound to the statement
+        assert!(!accepts(&vk, &[inputs[0] + BlsFr::one()], other));
+    }
+}
@@ -0,0 +1,209 @@
+// A malicious or buggy prover must never get a proof accepted. Every case below has to
+// end in `Ok(false)` or an error, never in `Ok(true)` and never in a panic.
+//
+// uses the lagrange and cubic gadget circuits
+#![cfg(feature = "gadgets")]
+
+use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+use ark_bn254::{Bn254, Fr as BnFr};
+use ark_ff::One;
+use ark_groth16::{Groth16, Proof, VerifyingKey};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_snark::SNARK;
+use ark_std::UniformRand;
+use std::panic::AssertUnwindSafe;
+
+use gadgets::cubic_demo::CubicDemoCircuit;
+use gadgets::lagrange_demo::{self, LagrangeEvalCircuit};
+use gadgets::multiply_demo::MultiplyDemoCircuit;
+use gadgets::persist::{self, ArtifactKind, SerializationMode, Validation};
+
+type G16 = Groth16<Bls12_381>;
+
+fn assert_rejected<E: std::fmt::Debug>(result: Result<bool, E>) {
+    assert!(
+        !matches!(result, Ok(true)),
+        "verification accepted: {:?}",
+        result
+    );
+}
+
+fn multiply(a: u64, b: u64) -> MultiplyDemoCircuit<BlsFr> {
+    MultiplyDemoCircuit {
+        a: Some(BlsFr::from(a)),
+        b: Some(BlsFr::from(b)),
+    }
+}
+
+fn to_bytes<T: CanonicalSerialize>(artifact: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    artifact.serialize(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn wrong_witness() {
+    let rng = &mut ark_std::test_rng();
+
+    // a * b = c is always satisfiable, claiming a different product must fail
+    let (pk, vk) = G16::circuit_specific_setup(multiply(0, 0), rng).unwrap();
+    let proof = G16::prove(&pk, multiply(3, 5), rng).unwrap();
+    assert_rejected(G16::verify(&vk, &[BlsFr::from(16u8)], &proof));
+
+    // Groth16 does not check satisfaction while proving, a witness that violates the
+    // constraints still yields a proof, but one that does not verify
+    let domain = Radix2EvaluationDomain::<BlsFr>::new(4).unwrap();
+    let evals: Vec<BlsFr> = (0..4).map(|_| BlsFr::rand(rng)).collect();
+    let z = BlsFr::rand(rng);
+    let wrong_value = lagrange_demo::evaluate(&domain, &evals, z) + BlsFr::one();
+    let (pk, vk) = G16::circuit_specific_setup(LagrangeEvalCircuit::blank(domain), rng).unwrap();
+    let circuit = LagrangeEvalCircuit::new(domain, evals.clone(), z, wrong_value);
+    // debug builds of ark-groth16 assert satisfaction inside the prover and panic, so
+    // the prover side is allowed to fail loudly, the verifier side is not
+    let proof = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        G16::prove(&pk, circuit, &mut ark_std::test_rng())
+    }));
+    if let Ok(Ok(proof)) = proof {
+        let inputs = LagrangeEvalCircuit::public_inputs(&evals, z, wrong_value);
+        assert_rejected(G16::verify(&vk, &inputs, &proof));
+    }
+}
+
+#[test]
+fn mismatched_public_inputs() {
+    let rng = &mut ark_std::test_rng();
+    let (pk, vk) = G16::circuit_specific_setup(multiply(0, 0), rng).unwrap();
+    let proof = G16::prove(&pk, multiply(3, 5), rng).unwrap();
+    let c = BlsFr::from(15u8);
+
+    assert_rejected(G16::verify(&vk, &[], &proof));
+    assert_rejected(G16::verify(&vk, &[c, c], &proof));
+    assert_rejected(G16::verify(&vk, &[c + BlsFr::one()], &proof));
+    assert_rejected(G16::verify(&vk, &[-c], &proof));
+    assert!(G16::verify(&vk, &[c], &proof).unwrap());
+}
+
+#[test]
+fn truncated_and_corrupted_proofs() {
+    let rng = &mut ark_std::test_rng();
+    let (pk, vk) = G16::circuit_specific_setup(multiply(0, 0), rng).unwrap();
+    let proof = G16::prove(&pk, multiply(3, 5), rng).unwrap();
+    let c = BlsFr::from(15u8);
+
+    for &mode in [
+        SerializationMode::Compressed,
+        SerializationMode::Uncompressed,
+    ]
+    .iter()
+    {
+        let mut bytes = Vec::new();
+        persist::write_artifact(&proof, ArtifactKind::Proof, mode, &mut bytes).unwrap();
+
+        for len in 0..bytes.len() {
+            let result: Result<Proof<Bls12_381>, _> =
+                persist::read_artifact(&bytes[..len], ArtifactKind::Proof, Validation::Checked);
+            assert!(
+                result.is_err(),
+                "{:?} proof truncated to {} bytes",
+                mode,
+                len
+            );
+        }
+
+        // flipping any payload bit gives an invalid encoding or a proof that is rejected.
+        // The exception is the sign flag of uncompressed points: y is stored in full, so
+        // the flag is ignored and the bytes decode to the very same proof
+        for bit in 8 * 7..8 * bytes.len() {
+            let mut corrupted = bytes.clone();
+            corrupted[bit / 8] ^= 1 << (bit % 8);
+            let result: Result<Proof<Bls12_381>, _> =
+                persist::read_artifact(&corrupted[..], ArtifactKind::Proof, Validation::Checked);
+            match result {
+                Ok(decoded) if decoded == proof => {
+                    assert_eq!(mode, SerializationMode::Uncompressed, "bit {}", bit)
+                }
+                Ok(decoded) => assert_rejected(G16::verify(&vk, &[c], &decoded)),
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+#[test]
+fn proof_against_another_circuits_vk() {
+    let rng = &mut ark_std::test_rng();
+    let (multiply_pk, multiply_vk) = G16::circuit_specific_setup(multiply(0, 0), rng).unwrap();
+    let (cubic_pk, cubic_vk) =
+        G16::circuit_specific_setup(CubicDemoCircuit::<BlsFr> { x: None }, rng).unwrap();
+
+    // both circuits have a single public input, so only the keys tell them apart
+    let multiply_proof = G16::prove(&multiply_pk, multiply(5, 7), rng).unwrap();
+    let cubic_proof = G16::prove(
+        &cubic_pk,
+        CubicDemoCircuit {
+            x: Some(BlsFr::from(3u8)),
+        },
+        rng,
+    )
+    .unwrap();
+    assert_rejected(G16::verify(
+        &cubic_vk,
+        &[BlsFr::from(35u8)],
+        &multiply_proof,
+    ));
+    assert_rejected(G16::verify(
+        &multiply_vk,
+        &[BlsFr::from(35u8)],
+        &cubic_proof,
+    ));
+
+    // a second setup of the same circuit is a different key as well
+    let (_, other_vk) = G16::circuit_specific_setup(multiply(0, 0), rng).unwrap();
+    assert_rejected(G16::verify(
+        &other_vk,
+        &[BlsFr::from(35u8)],
+        &multiply_proof,
+    ));
+}
+
+#[test]
+fn mixed_curves() {
+    let rng = &mut ark_std::test_rng();
+    let (bls_pk, bls_vk) = G16::circuit_specific_setup(multiply(0, 0), rng).unwrap();
+    let bls_proof = G16::prove(&bls_pk, multiply(3, 5), rng).unwrap();
+
+    let (bn_pk, bn_vk) = Groth16::<Bn254>::circuit_specific_setup(
+        MultiplyDemoCircuit::<BnFr> { a: None, b: None },
+        rng,
+    )
+    .unwrap();
+    let bn_proof = Groth16::<Bn254>::prove(
+        &bn_pk,
+        MultiplyDemoCircuit {
+            a: Some(BnFr::from(3u8)),
+            b: Some(BnFr::from(5u8)),
+        },
+        rng,
+    )
+    .unwrap();
+
+    // BN254 points are smaller, and their encodings are not valid BLS12-381 points
+    assert!(Proof::<Bls12_381>::deserialize(&to_bytes(&bn_proof)[..]).is_err());
+    assert!(VerifyingKey::<Bls12_381>::deserialize(&to_bytes(&bn_vk)[..]).is_err());
+    assert!(Proof::<Bn254>::deserialize(&to_bytes(&bls_proof)[..]).is_err());
+    assert!(VerifyingKey::<Bn254>::deserialize(&to_bytes(&bls_vk)[..]).is_err());
+
+    // the enveloped artifacts are rejected the same way
+    let mut bytes = Vec::new();
+    persist::write_artifact(
+        &bn_proof,
+        ArtifactKind::Proof,
+        SerializationMode::Uncompressed,
+        &mut bytes,
+    )
+    .unwrap();
+    let result: Result<Proof<Bls12_381>, _> =
+        persist::read_artifact(&bytes[..], ArtifactKind::Proof, Validation::Checked);
+    assert!(result.is_err());
+}
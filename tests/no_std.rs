@@ -0,0 +1,49 @@
+// `verifier-only` and `substrate` must keep building without std. The check cross compiles the crate
+// for a bare metal target, where any std dependency fails to link. It needs that target
+// (`rustup target add thumbv7em-none-eabi`), so it only runs when asked for:
+//   cargo test --test no_std -- --ignored
+
+use std::path::Path;
+use std::process::Command;
+
+const NO_STD_TARGET: &str = "thumbv7em-none-eabi";
+
+fn target_installed(target: &str) -> bool {
+    let output = Command::new("rustc")
+        .args(["--print", "target-libdir", "--target", target])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            Path::new(String::from_utf8_lossy(&output.stdout).trim()).exists()
+        }
+        _ => false,
+    }
+}
+
+#[test]
+#[ignore = "needs the thumbv7em-none-eabi target, run with --ignored"]
+fn verifier_builds_without_std() {
+    assert!(
+        target_installed(NO_STD_TARGET),
+        "{} is not installed, add it with `rustup target add {}`",
+        NO_STD_TARGET,
+        NO_STD_TARGET
+    );
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--lib", "--no-default-features", "--features"])
+        .args(["verifier-only,substrate", "--target", NO_STD_TARGET])
+        .current_dir(manifest_dir)
+        // a separate target directory, the outer `cargo test` holds the lock on the default one
+        .env(
+            "CARGO_TARGET_DIR",
+            Path::new(manifest_dir).join("target/no_std"),
+        )
+        .status()
+        .unwrap();
+    assert!(
+        status.success(),
+        "verifier-only,substrate does not build for {}",
+        NO_STD_TARGET
+    );
+}
@@ -0,0 +1,105 @@
+// The whole life of an airdrop claim with the nullifiers kept on disk: addresses are
+// registered in a file-backed tree (`state`), a holder claims with a proof, and every
+// later claim of the same address is refused, with a fresh proof to another recipient,
+// with the first proof rerandomized, and after the verifier restarts from its files.
+#![cfg(all(feature = "gadgets", feature = "groth16"))]
+
+use std::fs;
+
+use ark_bls12_381::{Bls12_381, Fr};
+use ark_groth16::{rerandomize_proof, Groth16};
+use ark_snark::SNARK;
+
+use gadgets::airdrop_demo::{
+    eligibility_leaf, recipient_input, Airdrop, AirdropCircuit, Claim, ClaimError,
+};
+use gadgets::nullifier_set::NullifierSet;
+use gadgets::poseidon_params::poseidon_parameters;
+use gadgets::schnorr_gadget::keygen;
+use gadgets::state::TreeState;
+
+type S = Groth16<Bls12_381>;
+
+const HEIGHT: usize = 3;
+
+#[test]
+fn deposit_claim_double_claim() {
+    let rng = &mut ark_std::test_rng();
+    let params = poseidon_parameters();
+    let dir =
+        std::env::temp_dir().join(format!("gadgets_nullifier_set_flow_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let (tree_path, nullifier_path) = (dir.join("tree.log"), dir.join("nullifiers.bin"));
+
+    // registration, one leaf per address
+    let keys: Vec<_> = (0..3).map(|_| keygen(rng)).collect();
+    let mut tree = TreeState::open(&tree_path, params.clone(), HEIGHT).unwrap();
+    for (_, address) in &keys {
+        tree.insert(eligibility_leaf(&params, address)).unwrap();
+    }
+    drop(tree);
+    let tree = TreeState::open(&tree_path, params.clone(), HEIGHT).unwrap();
+
+    let airdrop_id = Fr::from(7u8);
+    let (pk, vk) =
+        S::circuit_specific_setup(AirdropCircuit::blank(params.clone(), HEIGHT), rng).unwrap();
+    let claim = |index: usize, recipient: Fr, rng: &mut _| {
+        let circuit = AirdropCircuit::new(
+            params.clone(),
+            tree.tree(),
+            index,
+            airdrop_id,
+            recipient,
+            keys[index].0,
+        )
+        .unwrap();
+        Claim::<S> {
+            recipient,
+            nullifier: circuit.nullifier().unwrap(),
+            proof: S::prove(&pk, circuit, rng).unwrap(),
+        }
+    };
+    let verifier = || {
+        Airdrop::<S>::with_nullifiers(
+            airdrop_id,
+            tree.root(),
+            &vk,
+            NullifierSet::open(&nullifier_path).unwrap(),
+        )
+        .unwrap()
+    };
+
+    let mut airdrop = verifier();
+    let first = claim(0, recipient_input(&[0x11; 20]), rng);
+    airdrop.claim(&first).unwrap();
+
+    let again = claim(0, recipient_input(&[0x22; 20]), rng);
+    assert!(matches!(
+        airdrop.claim(&again),
+        Err(ClaimError::AlreadyClaimed)
+    ));
+    let mut rerandomized = first.clone();
+    rerandomized.proof = rerandomize_proof(rng, &vk, &first.proof);
+    assert_ne!(rerandomized.proof, first.proof);
+    assert!(matches!(
+        airdrop.claim(&rerandomized),
+        Err(ClaimError::AlreadyClaimed)
+    ));
+    drop(airdrop);
+
+    // the verifier restarts from its files
+    let mut airdrop = verifier();
+    assert!(airdrop.is_claimed(&first.nullifier));
+    assert!(matches!(
+        airdrop.claim(&again),
+        Err(ClaimError::AlreadyClaimed)
+    ));
+    airdrop
+        .claim(&claim(2, recipient_input(&[0x11; 20]), rng))
+        .unwrap();
+    drop(airdrop);
+    assert_eq!(NullifierSet::<Fr>::open(&nullifier_path).unwrap().len(), 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
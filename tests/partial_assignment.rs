@@ -0,0 +1,98 @@
+// Synthesizing a circuit for proving with only part of its assignment must fail with
+// `AssignmentMissing`, never panic, while setup ignores the values altogether. Every
+// circuit gets its partial assignments listed here.
+#![cfg(feature = "std")]
+
+use ark_bls12_381::Fr as BlsFr;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem, SynthesisError, SynthesisMode};
+
+use gadgets::cubic_demo::CubicDemoCircuit;
+use gadgets::multiply_demo::MultiplyDemoCircuit;
+use gadgets::padding::Padded;
+
+fn assert_missing<C: ConstraintSynthesizer<BlsFr>>(circuit: C, setup: C, name: &str) {
+    let cs = ConstraintSystem::new_ref();
+    assert_eq!(
+        circuit.generate_constraints(cs),
+        Err(SynthesisError::AssignmentMissing),
+        "{}",
+        name
+    );
+    let cs = ConstraintSystem::new_ref();
+    cs.set_mode(SynthesisMode::Setup);
+    assert_eq!(setup.generate_constraints(cs), Ok(()), "{} in setup", name);
+}
+
+fn value(v: u8) -> Option<BlsFr> {
+    Some(BlsFr::from(v))
+}
+
+#[test]
+fn multiply_partial_assignment() {
+    for &(a, b) in [(None, None), (value(3), None), (None, value(11))].iter() {
+        let circuit = || MultiplyDemoCircuit { a, b };
+        assert_missing(circuit(), circuit(), "multiply");
+        assert_missing(
+            Padded::new(circuit(), 4, 8),
+            Padded::new(circuit(), 4, 8),
+            "padded multiply",
+        );
+    }
+}
+
+#[test]
+fn cubic_partial_assignment() {
+    assert_missing(
+        CubicDemoCircuit::<BlsFr>::blank(),
+        CubicDemoCircuit::blank(),
+        "cubic",
+    );
+}
+
+#[test]
+#[cfg(feature = "marlin")]
+fn marlin_demo_partial_assignment() {
+    use gadgets::marlin_demo;
+
+    assert_missing(
+        marlin_demo::CubicDemoCircuit::<BlsFr>::blank(),
+        marlin_demo::CubicDemoCircuit::blank(),
+        "marlin cubic",
+    );
+    for &(a, b) in [(None, None), (value(3), None), (None, value(11))].iter() {
+        let circuit = || marlin_demo::MultiplyDemoCircuit { a, b };
+        assert_missing(circuit(), circuit(), "marlin multiply");
+    }
+}
+
+#[test]
+#[cfg(feature = "gadgets")]
+fn gadget_circuits_partial_assignment() {
+    use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+    use gadgets::cubic_gadget::constraints::CubicGadgetCircuit;
+    use gadgets::lagrange_demo::LagrangeEvalCircuit;
+
+    for &(x, y) in [(None, None), (value(3), None), (None, value(35))].iter() {
+        let circuit = || CubicGadgetCircuit { x, y };
+        assert_missing(circuit(), circuit(), "cubic gadget");
+    }
+
+    let domain = Radix2EvaluationDomain::<BlsFr>::new(4).unwrap();
+    let evals = || Some(vec![BlsFr::from(1u8); 4]);
+    let partial = [
+        // fewer evaluations than the domain has elements
+        (Some(vec![BlsFr::from(1u8); 2]), value(7), value(1)),
+        (evals(), None, value(1)),
+        (evals(), value(7), None),
+        (None, value(7), value(1)),
+    ];
+    for (evals, point, value) in partial.iter() {
+        let circuit = || LagrangeEvalCircuit {
+            domain,
+            evals: evals.clone(),
+            point: *point,
+            value: *value,
+        };
+        assert_missing(circuit(), circuit(), "lagrange");
+    }
+}
@@ -0,0 +1,154 @@
+// Property based tests: random witnesses for every demo circuit. The native relation
+// and the constraint system must agree on every input, proofs over satisfying witnesses
+// always verify and proofs never verify against a public input that does not match.
+//
+// uses the lagrange and cubic gadget circuits
+#![cfg(feature = "gadgets")]
+
+use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+use ark_ff::{One, PrimeField};
+use ark_groth16::{Groth16, ProvingKey, VerifyingKey};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_snark::SNARK;
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+use proptest::prelude::*;
+use std::sync::OnceLock;
+
+use gadgets::cubic_demo::CubicDemoCircuit;
+use gadgets::cubic_gadget::constraints::{CubicRootGadgetTrait, ParamTypeVar, SolutionDemoGadget};
+use gadgets::lagrange_demo::{self, LagrangeEvalCircuit};
+use gadgets::multiply_demo::MultiplyDemoCircuit;
+
+type Keys = (ProvingKey<Bls12_381>, VerifyingKey<Bls12_381>);
+
+// uniformly random field elements plus the small values where edge cases live
+fn field() -> impl Strategy<Value = BlsFr> {
+    prop_oneof![
+        any::<[u8; 32]>().prop_map(|bytes| BlsFr::from_le_bytes_mod_order(&bytes)),
+        (0u64..4).prop_map(BlsFr::from),
+        (1u64..4).prop_map(|v| -BlsFr::from(v)),
+    ]
+}
+
+fn nonzero_field() -> impl Strategy<Value = BlsFr> {
+    field().prop_filter("non-zero", |v| *v != BlsFr::from(0u8))
+}
+
+fn cubic(x: BlsFr) -> BlsFr {
+    x * x * x + x + BlsFr::from(5u8)
+}
+
+fn is_satisfied<C: ConstraintSynthesizer<BlsFr>>(circuit: C) -> bool {
+    let cs = ConstraintSystem::new_ref();
+    circuit.generate_constraints(cs.clone()).unwrap();
+    cs.is_satisfied().unwrap()
+}
+
+fn multiply_keys() -> &'static Keys {
+    static KEYS: OnceLock<Keys> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        Groth16::<Bls12_381>::circuit_specific_setup(
+            MultiplyDemoCircuit::<BlsFr> { a: None, b: None },
+            &mut ark_std::test_rng(),
+        )
+        .unwrap()
+    })
+}
+
+fn cubic_keys() -> &'static Keys {
+    static KEYS: OnceLock<Keys> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        Groth16::<Bls12_381>::circuit_specific_setup(
+            CubicDemoCircuit::<BlsFr> { x: None },
+            &mut ark_std::test_rng(),
+        )
+        .unwrap()
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn multiply_circuit_is_satisfied(a in field(), b in field()) {
+        let circuit = MultiplyDemoCircuit { a: Some(a), b: Some(b) };
+        prop_assert!(is_satisfied(circuit));
+    }
+
+    #[test]
+    fn cubic_circuit_is_satisfied(x in field()) {
+        let circuit = CubicDemoCircuit { x: Some(x) };
+        prop_assert!(is_satisfied(circuit));
+    }
+
+    #[test]
+    fn cubic_gadget_agrees_with_native(x in field(), y in field(), honest in any::<bool>()) {
+        let y = if honest { cubic(x) } else { y };
+        let cs = ConstraintSystem::<BlsFr>::new_ref();
+        let x_var = FpVar::new_witness(cs.clone(), || Ok(x)).unwrap();
+        let y_var = FpVar::new_input(cs.clone(), || Ok(y)).unwrap();
+        let gadget = SolutionDemoGadget { x: ParamTypeVar::new(x_var) };
+        let is_root = gadget.verify(&ParamTypeVar::new(y_var)).unwrap();
+
+        prop_assert_eq!(is_root.value().unwrap(), cubic(x) == y);
+        prop_assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn lagrange_circuit_agrees_with_native(
+        evals in proptest::collection::vec(field(), 4),
+        point in field(),
+        offset in field(),
+    ) {
+        let domain = Radix2EvaluationDomain::<BlsFr>::new(4).unwrap();
+        // points inside the domain have no barycentric form and are always rejected
+        prop_assume!(domain.evaluate_vanishing_polynomial(point) != BlsFr::from(0u8));
+
+        let expected = lagrange_demo::evaluate(&domain, &evals, point);
+        let value = expected + offset;
+        let satisfied = is_satisfied(LagrangeEvalCircuit::new(domain, evals, point, value));
+        prop_assert_eq!(satisfied, value == expected);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(8))]
+
+    #[test]
+    fn multiply_proofs_verify_only_for_matching_inputs(
+        a in field(),
+        b in field(),
+        delta in nonzero_field(),
+        seed in any::<u64>(),
+    ) {
+        let (pk, vk) = multiply_keys();
+        let rng = &mut StdRng::seed_from_u64(seed);
+        let proof =
+            Groth16::<Bls12_381>::prove(pk, MultiplyDemoCircuit { a: Some(a), b: Some(b) }, rng)
+                .unwrap();
+
+        prop_assert!(Groth16::<Bls12_381>::verify(vk, &[a * b], &proof).unwrap());
+        prop_assert!(!Groth16::<Bls12_381>::verify(vk, &[a * b + delta], &proof).unwrap());
+    }
+
+    #[test]
+    fn cubic_proofs_verify_only_for_matching_inputs(
+        x in field(),
+        delta in nonzero_field(),
+        seed in any::<u64>(),
+    ) {
+        let (pk, vk) = cubic_keys();
+        let rng = &mut StdRng::seed_from_u64(seed);
+        let proof = Groth16::<Bls12_381>::prove(pk, CubicDemoCircuit { x: Some(x) }, rng).unwrap();
+
+        prop_assert!(Groth16::<Bls12_381>::verify(vk, &[cubic(x)], &proof).unwrap());
+        prop_assert!(!Groth16::<Bls12_381>::verify(vk, &[cubic(x) + delta], &proof).unwrap());
+        // a second root candidate only verifies if it really maps to the same output
+        prop_assert_eq!(
+            Groth16::<Bls12_381>::verify(vk, &[cubic(x + BlsFr::one())], &proof).unwrap(),
+            cubic(x + BlsFr::one()) == cubic(x)
+        );
+    }
+}
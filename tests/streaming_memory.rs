@@ -0,0 +1,101 @@
+// Peak heap use of proving with the whole proving key loaded against proving with the key
+// streamed from disk (`streaming_prover`). A counting allocator records the high-water
+// mark of each run. A synthetic circuit of 2^12 constraints stands in for the large demos.
+// Both provers synthesize the circuit, which with its matrices costs about as much as the
+// key here (~1.2 KiB per constraint against ~1.1 KiB per variable), so the streaming
+// prover peaks at a bit more than half; the saving is the key, whatever the circuit.
+// One test in this file, the counter is global to the process.
+#![cfg(all(feature = "std", feature = "groth16"))]
+
+use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+use ark_groth16::{Groth16, ProvingKey};
+use ark_snark::SNARK;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use gadgets::persist::{self, ArtifactKind, SerializationMode, Validation};
+use gadgets::streaming_prover::{self, StreamingOptions};
+use gadgets::synthetic::SyntheticCircuit;
+
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let now = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(now, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// the peak of `f` above what was allocated before it
+fn peak_of<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let base = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(base, Ordering::SeqCst);
+    let result = f();
+    (result, PEAK.load(Ordering::SeqCst) - base)
+}
+
+#[test]
+fn streaming_does_not_hold_the_key() {
+    let rng = &mut ark_std::test_rng();
+    let circuit = SyntheticCircuit::of_log_size(12);
+    let inputs = circuit.public_inputs::<BlsFr>();
+    let path = std::env::temp_dir().join(format!(
+        "gadgets_streaming_memory_pk_{}.bin",
+        std::process::id()
+    ));
+    let vk = {
+        let (pk, vk) = Groth16::<Bls12_381>::circuit_specific_setup(circuit, rng).unwrap();
+        persist::save(
+            &path,
+            &pk,
+            ArtifactKind::ProvingKey,
+            SerializationMode::Uncompressed,
+        )
+        .unwrap();
+        vk
+    };
+
+    let load = || -> ProvingKey<Bls12_381> {
+        persist::load(&path, ArtifactKind::ProvingKey, Validation::Unchecked).unwrap()
+    };
+    let (_, key) = peak_of(load);
+    let (proof, in_memory) =
+        peak_of(|| Groth16::<Bls12_381>::prove(&load(), circuit, rng).unwrap());
+    assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+
+    let options = StreamingOptions {
+        chunk_size: 256,
+        validation: Validation::Unchecked,
+    };
+    let (proof, streaming) = peak_of(|| {
+        streaming_prover::prove_from_file::<Bls12_381, _, _, _>(&path, circuit, &options, rng)
+            .unwrap()
+    });
+    assert!(Groth16::<Bls12_381>::verify(&vk, &inputs, &proof).unwrap());
+    std::fs::remove_file(&path).unwrap();
+
+    // both provers hold the constraint system and the witness, the streaming one saves
+    // (almost) the whole key on top of that
+    assert!(
+        streaming + key * 3 / 4 < in_memory,
+        "peak heap: key {} KiB, in memory {} KiB, streaming {} KiB",
+        key / 1024,
+        in_memory / 1024,
+        streaming / 1024
+    );
+}
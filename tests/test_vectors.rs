@@ -0,0 +1,20 @@
+// the stored known-answer vectors in tests/data must keep verifying and must match a fresh
+// generation byte for byte, regenerate them with `gadgets-cli gen-vectors` after an
+// intended change
+#![cfg(all(feature = "cli", feature = "insecure"))]
+
+use std::path::Path;
+
+use gadgets::test_vectors::{check_vector, CIRCUITS};
+
+#[test]
+fn stored_vectors_verify() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("data");
+    for &circuit in CIRCUITS.iter() {
+        if let Err(e) = check_vector(&dir, circuit) {
+            panic!("{} test vector: {}", circuit, e);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+// the hex and base64 forms of the multiply known-answer vector (tests/data/multiply),
+// written by `gadgets-cli encode`: tests/data/text holds the proof and the verifying key
+// in both. A change in the encoding or in the serialization fails here, regenerate the
+// files with `gadgets-cli encode --kind proof --input tests/data/multiply/proof.bin
+// --format hex` and so on after an intended change.
+#![cfg(all(feature = "std", feature = "groth16"))]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ark_bls12_381::{Bls12_381, Fr};
+use ark_groth16::{Groth16, Proof, VerifyingKey};
+use ark_serialize::CanonicalDeserialize;
+use ark_snark::SNARK;
+
+use gadgets::persist::{self, ArtifactKind, Validation};
+use gadgets::text::{from_base64, from_hex, to_base64, to_hex, TextError};
+
+fn data_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data")
+}
+
+fn fixture(name: &str) -> String {
+    fs::read_to_string(data_dir().join("text").join(name))
+        .unwrap()
+        .trim()
+        .to_string()
+}
+
+fn artifact<T: CanonicalDeserialize>(name: &str, kind: ArtifactKind) -> T {
+    persist::load(
+        data_dir().join("multiply").join(name),
+        kind,
+        Validation::Checked,
+    )
+    .unwrap()
+}
+
+#[test]
+fn fixtures_match() {
+    let proof: Proof<Bls12_381> = artifact("proof.bin", ArtifactKind::Proof);
+    let vk: VerifyingKey<Bls12_381> = artifact("vk.bin", ArtifactKind::VerifyingKey);
+
+    assert_eq!(to_hex(&proof), fixture("multiply_proof.hex"));
+    assert_eq!(to_base64(&proof), fixture("multiply_proof.b64"));
+    assert_eq!(to_hex(&vk), fixture("multiply_vk.hex"));
+    assert_eq!(to_base64(&vk), fixture("multiply_vk.b64"));
+
+    // and back, to a proof that still verifies for 3 * 11
+    let decoded: Proof<Bls12_381> = from_base64(&fixture("multiply_proof.b64")).unwrap();
+    let vk: VerifyingKey<Bls12_381> = from_hex(&fixture("multiply_vk.hex")).unwrap();
+    assert_eq!(decoded, proof);
+    assert!(Groth16::<Bls12_381>::verify(&vk, &[Fr::from(33u8)], &decoded).unwrap());
+}
+
+#[test]
+fn fixtures_are_strict() {
+    // the proof text as a verifying key, a truncated proof
+    assert!(from_hex::<VerifyingKey<Bls12_381>>(&fixture("multiply_proof.hex")).is_err());
+    let hex = fixture("multiply_proof.hex");
+    assert!(matches!(
+        from_hex::<Proof<Bls12_381>>(&hex[..hex.len() - 2]),
+        Err(TextError::Serialization(_))
+    ));
+    let base64 = fixture("multiply_proof.b64");
+    assert!(matches!(
+        from_base64::<Proof<Bls12_381>>(&base64[1..]),
+        Err(TextError::InvalidBase64)
+    ));
+}
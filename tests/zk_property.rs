@@ -0,0 +1,147 @@
+// Zero knowledge, as far as a test can show it: proofs of one statement made from
+// different witnesses look the same. The statement is "I know a, b with a * b == 60",
+// two groups of proofs use the witnesses (3, 20) and (4, 15). The prover randomness r, s
+// blinds A, B and C, so no bit of the proof bytes may depend on the witness, and no two
+// proofs may be linked to each other. Without the blinding (`create_proof_no_zk`) the
+// proof is a function of the witness and the same checks tell the groups apart.
+#![cfg(feature = "std")]
+
+use ark_bls12_381::{Bls12_381, Fr as BlsFr};
+use ark_groth16::{
+    create_proof_no_zk, rerandomize_proof, Groth16, Proof, ProvingKey, VerifyingKey,
+};
+use ark_serialize::CanonicalSerialize;
+use ark_snark::SNARK;
+use std::collections::HashSet;
+
+use gadgets::multiply_demo::MultiplyDemoCircuit;
+
+// proofs per witness
+const SAMPLES: usize = 128;
+
+type Keys = (ProvingKey<Bls12_381>, VerifyingKey<Bls12_381>);
+
+fn keys() -> Keys {
+    Groth16::<Bls12_381>::circuit_specific_setup(
+        MultiplyDemoCircuit::<BlsFr> { a: None, b: None },
+        &mut ark_std::test_rng(),
+    )
+    .unwrap()
+}
+
+fn circuit(a: u8, b: u8) -> MultiplyDemoCircuit<BlsFr> {
+    MultiplyDemoCircuit {
+        a: Some(BlsFr::from(a)),
+        b: Some(BlsFr::from(b)),
+    }
+}
+
+fn to_bytes(proof: &Proof<Bls12_381>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    proof.serialize(&mut bytes).unwrap();
+    bytes
+}
+
+// the share of proofs with each bit of the compressed encoding set
+fn bit_frequencies(proofs: &[Vec<u8>]) -> Vec<f64> {
+    (0..proofs[0].len() * 8)
+        .map(|bit| {
+            let ones = proofs
+                .iter()
+                .filter(|p| p[bit / 8] >> (bit % 8) & 1 == 1)
+                .count();
+            ones as f64 / proofs.len() as f64
+        })
+        .collect()
+}
+
+// the largest difference between the groups in the share of a bit. For independent
+// fair bits it has a standard deviation of sqrt(1 / (2 * SAMPLES)) = 0.0625 and stays
+// around 0.2 over the 1536 bits of a proof, a bit that follows the witness gives 1
+fn max_bias(first: &[Vec<u8>], second: &[Vec<u8>]) -> f64 {
+    bit_frequencies(first)
+        .iter()
+        .zip(bit_frequencies(second))
+        .map(|(x, y)| (x - y).abs())
+        .fold(0.0, f64::max)
+}
+
+#[test]
+fn proofs_do_not_depend_on_the_witness() {
+    let rng = &mut ark_std::test_rng();
+    let (pk, vk) = keys();
+    let statement = [BlsFr::from(60u8)];
+
+    let mut prove = |a, b| -> Vec<Vec<u8>> {
+        (0..SAMPLES)
+            .map(|_| {
+                let proof = Groth16::<Bls12_381>::prove(&pk, circuit(a, b), rng).unwrap();
+                assert!(Groth16::<Bls12_381>::verify(&vk, &statement, &proof).unwrap());
+                to_bytes(&proof)
+            })
+            .collect()
+    };
+    let first = prove(3, 20);
+    let second = prove(4, 15);
+
+    // no proof repeats, not even a single point of one
+    let points: HashSet<&[u8]> = first
+        .iter()
+        .chain(second.iter())
+        .flat_map(|p| vec![&p[..48], &p[48..144], &p[144..]])
+        .collect();
+    assert_eq!(points.len(), 3 * 2 * SAMPLES);
+
+    let bias = max_bias(&first, &second);
+    assert!(bias < 0.4, "a proof bit follows the witness, bias {}", bias);
+    // the two halves of one group as a control
+    let control = max_bias(&first[..SAMPLES / 2], &first[SAMPLES / 2..]);
+    assert!(control < 0.5, "control bias {}", control);
+}
+
+#[test]
+fn proofs_without_blinding_reveal_the_witness() {
+    let (pk, vk) = keys();
+    let statement = [BlsFr::from(60u8)];
+
+    // still valid proofs of the same statement, but one per witness
+    let first = create_proof_no_zk(circuit(3, 20), &pk).unwrap();
+    let second = create_proof_no_zk(circuit(4, 15), &pk).unwrap();
+    assert!(Groth16::<Bls12_381>::verify(&vk, &statement, &first).unwrap());
+    assert!(Groth16::<Bls12_381>::verify(&vk, &statement, &second).unwrap());
+    assert_eq!(first, create_proof_no_zk(circuit(3, 20), &pk).unwrap());
+
+    // the bias test sees it at once
+    let first = vec![to_bytes(&first); SAMPLES];
+    let second = vec![to_bytes(&second); SAMPLES];
+    assert!(max_bias(&first, &second) == 1.0);
+
+    // and anyone who guesses the witness can check the guess against a proof
+    let guess = to_bytes(&create_proof_no_zk(circuit(4, 15), &pk).unwrap());
+    assert_ne!(guess, first[0]);
+    assert_eq!(guess, second[0]);
+}
+
+#[test]
+fn rerandomized_proofs_are_unlinkable() {
+    let rng = &mut ark_std::test_rng();
+    let (pk, vk) = keys();
+    let statement = [BlsFr::from(60u8)];
+
+    // whoever relays a proof can blind it again without the witness, the result shares
+    // no point with the original and passes the same bias test against fresh proofs
+    let proof = Groth16::<Bls12_381>::prove(&pk, circuit(3, 20), rng).unwrap();
+    let relayed: Vec<Vec<u8>> = (0..SAMPLES)
+        .map(|_| {
+            let other = rerandomize_proof(rng, &vk, &proof);
+            assert!(Groth16::<Bls12_381>::verify(&vk, &statement, &other).unwrap());
+            assert!(other.a != proof.a && other.b != proof.b && other.c != proof.c);
+            to_bytes(&other)
+        })
+        .collect();
+    let fresh: Vec<Vec<u8>> = (0..SAMPLES)
+        .map(|_| to_bytes(&Groth16::<Bls12_381>::prove(&pk, circuit(4, 15), rng).unwrap()))
+        .collect();
+    let bias = max_bias(&relayed, &fresh);
+    assert!(bias < 0.4, "relayed proofs stand out, bias {}", bias);
+}